@@ -0,0 +1,527 @@
+use crate::{
+    instructions::{
+        Expression, GcInstruction, Instruction, NumericInstruction, ReferenceInstruction,
+        VariableInstruction, VectorInstruction,
+    },
+    module::{
+        indices::TypeIdx,
+        types::{DefType, GlobalType, HeapType, Mutability, NumType, RefType, ValType, VecType},
+    },
+    runtime::{pack, reserve, AnyRef, FuncHandle, Heap, ModuleInst, Val},
+    subtyping,
+};
+
+/// Check that an expression is constant and produces a single value of the expected type
+///
+/// Constant expressions may only use `*.const` including `v128.const`, `ref.null`, `ref.func`, the extended-const `i32/i64.add/sub/mul`,
+/// the allocations `struct.new(_default)`, `array.new(_default/_fixed)` and `ref.i31`, the conversions between
+/// `any` and `extern`, and `global.get` of immutable globals, whose types are given by `globals`. `funcs` holds the
+/// type index of every function in the module including imports, and `types` the types they index.
+pub fn validate(
+    expr: &Expression,
+    expected: &ValType,
+    globals: &[&GlobalType],
+    funcs: &[TypeIdx],
+    types: &[DefType],
+) -> Result<(), &'static str> {
+    let mut stack: Vec<ValType> = Vec::new();
+    for instruction in &expr.0 {
+        match instruction {
+            Instruction::Numeric(NumericInstruction::I32Const(_)) => {
+                stack.push(ValType::NumType(NumType::I32))
+            }
+            Instruction::Numeric(NumericInstruction::I64Const(_)) => {
+                stack.push(ValType::NumType(NumType::I64))
+            }
+            Instruction::Numeric(NumericInstruction::F32Const(_)) => {
+                stack.push(ValType::NumType(NumType::F32))
+            }
+            Instruction::Numeric(NumericInstruction::F64Const(_)) => {
+                stack.push(ValType::NumType(NumType::F64))
+            }
+            Instruction::Vector(VectorInstruction::V128Const(_)) => {
+                stack.push(ValType::VecType(VecType::V128))
+            }
+            Instruction::Numeric(
+                NumericInstruction::I32Add
+                | NumericInstruction::I32Sub
+                | NumericInstruction::I32Mul,
+            ) => binary(&mut stack, NumType::I32)?,
+            Instruction::Numeric(
+                NumericInstruction::I64Add
+                | NumericInstruction::I64Sub
+                | NumericInstruction::I64Mul,
+            ) => binary(&mut stack, NumType::I64)?,
+            Instruction::Variable(VariableInstruction::GlobalGet(globalidx)) => {
+                let global = globals
+                    .get(*globalidx as usize)
+                    .ok_or("constant expression refers to an unknown global")?;
+                if global.m != Mutability::Const {
+                    return Err("constant expression refers to a mutable global");
+                }
+                stack.push(global.t.clone())
+            }
+            Instruction::Reference(ReferenceInstruction::Null(ht)) => {
+                if matches!(ht, HeapType::Type(typeidx) if *typeidx as usize >= types.len()) {
+                    return Err("unknown type");
+                }
+                stack.push(ValType::RefType(RefType::null(ht.clone())))
+            }
+            Instruction::Reference(ReferenceInstruction::Func(funcidx)) => {
+                let typeidx = funcs
+                    .get(*funcidx as usize)
+                    .ok_or("constant expression refers to an unknown function")?;
+                stack.push(ValType::RefType(RefType::non_null(HeapType::Type(
+                    *typeidx,
+                ))))
+            }
+            Instruction::Gc(instruction) => gc(&mut stack, types, instruction)?,
+            _ => return Err("constant expression required"),
+        }
+    }
+    match stack.as_slice() {
+        [t] if subtyping::matches(types, t, expected) => Ok(()),
+        _ => Err("type mismatch in constant expression"),
+    }
+}
+
+/// Check one of the GC instructions that are allowed in constant expressions
+fn gc(
+    stack: &mut Vec<ValType>,
+    types: &[DefType],
+    instruction: &GcInstruction,
+) -> Result<(), &'static str> {
+    let i32 = ValType::NumType(NumType::I32);
+    let def_type = |typeidx: TypeIdx| types.get(typeidx as usize).ok_or("unknown type");
+    let elem_type = |typeidx: TypeIdx| {
+        def_type(typeidx)?
+            .array_type()
+            .map(|at| at.ft.st.unpacked())
+            .ok_or("non-array type")
+    };
+    let mut pop = |expected: &ValType| match stack.pop() {
+        Some(t) if subtyping::matches(types, &t, expected) => Ok(t),
+        _ => Err("type mismatch in constant expression"),
+    };
+    let result = match instruction {
+        GcInstruction::StructNew(x) | GcInstruction::StructNewDefault(x) => {
+            let st = def_type(*x)?.struct_type().ok_or("non-structure type")?;
+            if let GcInstruction::StructNew(_) = instruction {
+                for ft in st.fields.iter().rev() {
+                    pop(&ft.st.unpacked())?;
+                }
+            } else if !st.fields.iter().all(|ft| ft.st.unpacked().is_defaultable()) {
+                return Err("field type is not defaultable");
+            }
+            HeapType::Type(*x)
+        }
+        GcInstruction::ArrayNew(x) => {
+            pop(&i32)?;
+            pop(&elem_type(*x)?)?;
+            HeapType::Type(*x)
+        }
+        GcInstruction::ArrayNewDefault(x) => {
+            if !elem_type(*x)?.is_defaultable() {
+                return Err("field type is not defaultable");
+            }
+            pop(&i32)?;
+            HeapType::Type(*x)
+        }
+        GcInstruction::ArrayNewFixed(x, n) => {
+            let t = elem_type(*x)?;
+            for _ in 0..*n {
+                pop(&t)?;
+            }
+            HeapType::Type(*x)
+        }
+        GcInstruction::RefI31 => {
+            pop(&i32)?;
+            HeapType::I31
+        }
+        GcInstruction::AnyConvertExtern | GcInstruction::ExternConvertAny => {
+            let (from, to) = match instruction {
+                GcInstruction::AnyConvertExtern => (HeapType::Extern, HeapType::Any),
+                _ => (HeapType::Any, HeapType::Extern),
+            };
+            let nullable = match pop(&ValType::RefType(RefType::null(from)))? {
+                ValType::RefType(rt) => rt.nullable,
+                _ => true,
+            };
+            stack.push(ValType::RefType(RefType { nullable, ht: to }));
+            return Ok(());
+        }
+        _ => return Err("constant expression required"),
+    };
+    stack.push(ValType::RefType(RefType::non_null(result)));
+    Ok(())
+}
+
+/// Pop two operands of the given type and push the result of a binary operation on them
+fn binary(stack: &mut Vec<ValType>, t: NumType) -> Result<(), &'static str> {
+    let t = ValType::NumType(t);
+    for _ in 0..2 {
+        if stack.pop().as_ref() != Some(&t) {
+            return Err("type mismatch in constant expression");
+        }
+    }
+    stack.push(t);
+    Ok(())
+}
+
+/// Evaluate a constant expression, returning `None` if it is not valid
+///
+/// `globals` holds the values of the globals the expression can refer to, and `instance` the function addresses and
+/// types of the module. Structs and arrays are allocated on `heap`.
+pub fn eval(
+    expr: &Expression,
+    globals: &[Val],
+    instance: &ModuleInst,
+    heap: &mut Heap,
+) -> Option<Val> {
+    let mut stack: Vec<Val> = Vec::new();
+    for instruction in &expr.0 {
+        let value = match instruction {
+            Instruction::Numeric(NumericInstruction::I32Const(n)) => Val::I32(*n),
+            Instruction::Numeric(NumericInstruction::I64Const(n)) => Val::I64(*n),
+            Instruction::Numeric(NumericInstruction::F32Const(z)) => Val::F32(*z),
+            Instruction::Numeric(NumericInstruction::F64Const(z)) => Val::F64(*z),
+            Instruction::Vector(VectorInstruction::V128Const(c)) => Val::V128(*c),
+            Instruction::Numeric(op) => {
+                let rhs = stack.pop()?;
+                let lhs = stack.pop()?;
+                match (op, lhs, rhs) {
+                    (NumericInstruction::I32Add, Val::I32(a), Val::I32(b)) => {
+                        Val::I32(a.wrapping_add(b))
+                    }
+                    (NumericInstruction::I32Sub, Val::I32(a), Val::I32(b)) => {
+                        Val::I32(a.wrapping_sub(b))
+                    }
+                    (NumericInstruction::I32Mul, Val::I32(a), Val::I32(b)) => {
+                        Val::I32(a.wrapping_mul(b))
+                    }
+                    (NumericInstruction::I64Add, Val::I64(a), Val::I64(b)) => {
+                        Val::I64(a.wrapping_add(b))
+                    }
+                    (NumericInstruction::I64Sub, Val::I64(a), Val::I64(b)) => {
+                        Val::I64(a.wrapping_sub(b))
+                    }
+                    (NumericInstruction::I64Mul, Val::I64(a), Val::I64(b)) => {
+                        Val::I64(a.wrapping_mul(b))
+                    }
+                    _ => return None,
+                }
+            }
+            Instruction::Variable(VariableInstruction::GlobalGet(globalidx)) => {
+                globals.get(*globalidx as usize)?.clone()
+            }
+            Instruction::Reference(ReferenceInstruction::Null(ht)) => instance.null(ht),
+            Instruction::Reference(ReferenceInstruction::Func(funcidx)) => Val::FuncRef(Some(
+                FuncHandle(*instance.funcaddrs.get(*funcidx as usize)?),
+            )),
+            Instruction::Gc(instruction) => eval_gc(&mut stack, instance, heap, instruction)?,
+            _ => return None,
+        };
+        stack.push(value);
+    }
+    match stack.as_slice() {
+        [_] => stack.pop(),
+        _ => None,
+    }
+}
+
+/// Evaluate one of the GC instructions that are allowed in constant expressions
+fn eval_gc(
+    stack: &mut Vec<Val>,
+    instance: &ModuleInst,
+    heap: &mut Heap,
+    instruction: &GcInstruction,
+) -> Option<Val> {
+    let types = &instance.types;
+    let (x, elements) = match instruction {
+        GcInstruction::StructNew(x) | GcInstruction::StructNewDefault(x) => {
+            let st = types.get(*x as usize)?.struct_type()?;
+            let fields = match instruction {
+                GcInstruction::StructNew(_) => stack
+                    .split_off(stack.len().checked_sub(st.fields.len())?)
+                    .into_iter()
+                    .zip(&st.fields)
+                    .map(|(val, ft)| pack(val, ft))
+                    .collect(),
+                _ => st
+                    .fields
+                    .iter()
+                    .map(|ft| instance.default_value(&ft.st.unpacked()))
+                    .collect(),
+            };
+            let object = heap.alloc(types.clone(), *x, fields);
+            return Some(Val::AnyRef(Some(AnyRef::Struct(object))));
+        }
+        GcInstruction::ArrayNew(x) | GcInstruction::ArrayNewDefault(x) => {
+            let ft = &types.get(*x as usize)?.array_type()?.ft;
+            let Val::I32(n) = stack.pop()? else {
+                return None;
+            };
+            let val = match instruction {
+                GcInstruction::ArrayNew(_) => pack(stack.pop()?, ft),
+                _ => instance.default_value(&ft.st.unpacked()),
+            };
+            let n = n as u32 as usize;
+            let mut elements = reserve(n).ok()?;
+            elements.resize(n, val);
+            (x, elements)
+        }
+        GcInstruction::ArrayNewFixed(x, n) => {
+            let ft = &types.get(*x as usize)?.array_type()?.ft;
+            let elements = stack
+                .split_off(stack.len().checked_sub(*n as usize)?)
+                .into_iter()
+                .map(|val| pack(val, ft))
+                .collect();
+            (x, elements)
+        }
+        GcInstruction::RefI31 => match stack.pop()? {
+            Val::I32(n) => return Some(Val::AnyRef(Some(AnyRef::I31(n as u32 & 0x7FFF_FFFF)))),
+            _ => return None,
+        },
+        GcInstruction::AnyConvertExtern => match stack.pop()? {
+            Val::ExternRef(reference) => {
+                return Some(Val::AnyRef(reference.map(AnyRef::internalize)))
+            }
+            _ => return None,
+        },
+        GcInstruction::ExternConvertAny => match stack.pop()? {
+            Val::AnyRef(reference) => {
+                return Some(Val::ExternRef(reference.map(AnyRef::externalize)))
+            }
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let object = heap.alloc(types.clone(), *x, elements);
+    Some(Val::AnyRef(Some(AnyRef::Array(object))))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::module::types::{
+        ArrayType, CompType, FieldType, FuncType, PackedType, RecType, StorageType, StructType,
+        SubType,
+    };
+
+    use super::*;
+
+    fn expr(instructions: Vec<Instruction>) -> Expression {
+        Expression(instructions)
+    }
+
+    fn i32_const(n: i32) -> Instruction {
+        Instruction::Numeric(NumericInstruction::I32Const(n))
+    }
+
+    const I32: ValType = ValType::NumType(NumType::I32);
+
+    #[test]
+    fn test_validate() {
+        let imported = GlobalType {
+            m: Mutability::Const,
+            t: I32,
+        };
+        let mutable = GlobalType {
+            m: Mutability::Var,
+            t: I32,
+        };
+        let globals = [&imported, &mutable];
+
+        let extended = expr(vec![
+            i32_const(1),
+            Instruction::Variable(VariableInstruction::GlobalGet(0)),
+            Instruction::Numeric(NumericInstruction::I32Mul),
+        ]);
+        assert_eq!(validate(&extended, &I32, &globals, &[], &[]), Ok(()));
+
+        let types = DefType::flatten(vec![RecType {
+            types: vec![FuncType {
+                rt1: vec![],
+                rt2: vec![],
+            }
+            .into()],
+        }]);
+        let func = expr(vec![Instruction::Reference(ReferenceInstruction::Func(0))]);
+        let funcref = ValType::RefType(RefType::FUNCREF);
+        assert_eq!(validate(&func, &funcref, &globals, &[0], &types), Ok(()));
+        assert!(validate(&func, &funcref, &globals, &[], &types).is_err());
+        // ref.func produces a non-null reference to the function's type, which is more precise than funcref
+        let typed = ValType::RefType(RefType::non_null(HeapType::Type(0)));
+        assert_eq!(validate(&func, &typed, &globals, &[0], &types), Ok(()));
+        let null = expr(vec![Instruction::Reference(ReferenceInstruction::Null(
+            HeapType::Type(0),
+        ))]);
+        assert_eq!(
+            validate(&null, &typed, &globals, &[0], &types),
+            Err("type mismatch in constant expression")
+        );
+        assert_eq!(
+            validate(&null, &funcref, &globals, &[0], &[]),
+            Err("unknown type")
+        );
+
+        let vector = expr(vec![Instruction::Vector(VectorInstruction::V128Const(0))]);
+        assert_eq!(
+            validate(
+                &vector,
+                &ValType::VecType(VecType::V128),
+                &globals,
+                &[],
+                &[]
+            ),
+            Ok(())
+        );
+
+        let mutable = expr(vec![Instruction::Variable(VariableInstruction::GlobalGet(
+            1,
+        ))]);
+        assert!(validate(&mutable, &I32, &globals, &[], &[]).is_err());
+
+        let unknown = expr(vec![Instruction::Variable(VariableInstruction::GlobalGet(
+            2,
+        ))]);
+        assert!(validate(&unknown, &I32, &globals, &[], &[]).is_err());
+
+        let not_constant = expr(vec![
+            i32_const(1),
+            Instruction::Numeric(NumericInstruction::I32Clz),
+        ]);
+        assert_eq!(
+            validate(&not_constant, &I32, &globals, &[], &[]),
+            Err("constant expression required")
+        );
+
+        let mismatch = expr(vec![
+            i32_const(1),
+            Instruction::Numeric(NumericInstruction::I64Const(1)),
+            Instruction::Numeric(NumericInstruction::I32Add),
+        ]);
+        assert!(validate(&mismatch, &I32, &globals, &[], &[]).is_err());
+        assert!(validate(&expr(vec![]), &I32, &globals, &[], &[]).is_err());
+        assert!(validate(
+            &expr(vec![i32_const(1), i32_const(2)]),
+            &I32,
+            &globals,
+            &[],
+            &[]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_validate_gc() {
+        let types = DefType::flatten(vec![RecType {
+            types: vec![
+                SubType::from(CompType::Struct(StructType {
+                    fields: vec![FieldType {
+                        st: StorageType::Val(I32),
+                        m: Mutability::Const,
+                    }],
+                })),
+                SubType::from(CompType::Array(ArrayType {
+                    ft: FieldType {
+                        st: StorageType::Packed(PackedType::I8),
+                        m: Mutability::Var,
+                    },
+                })),
+            ],
+        }]);
+        let gc = |instruction| Instruction::Gc(instruction);
+        let structref = ValType::RefType(RefType::STRUCTREF);
+
+        let new = expr(vec![i32_const(1), gc(GcInstruction::StructNew(0))]);
+        assert_eq!(validate(&new, &structref, &[], &[], &types), Ok(()));
+        let fixed = expr(vec![
+            i32_const(1),
+            i32_const(2),
+            gc(GcInstruction::ArrayNewFixed(1, 2)),
+        ]);
+        let array = ValType::RefType(RefType::non_null(HeapType::Type(1)));
+        assert_eq!(validate(&fixed, &array, &[], &[], &types), Ok(()));
+        assert_eq!(
+            validate(&fixed, &structref, &[], &[], &types),
+            Err("type mismatch in constant expression")
+        );
+        let i31 = expr(vec![i32_const(7), gc(GcInstruction::RefI31)]);
+        assert_eq!(
+            validate(&i31, &ValType::RefType(RefType::EQREF), &[], &[], &types),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                &expr(vec![gc(GcInstruction::StructNew(0))]),
+                &structref,
+                &[],
+                &[],
+                &types
+            ),
+            Err("type mismatch in constant expression")
+        );
+        assert_eq!(
+            validate(
+                &expr(vec![gc(GcInstruction::StructNew(1))]),
+                &structref,
+                &[],
+                &[],
+                &types
+            ),
+            Err("non-structure type")
+        );
+        assert_eq!(
+            validate(
+                &expr(vec![gc(GcInstruction::ArrayLen)]),
+                &I32,
+                &[],
+                &[],
+                &types
+            ),
+            Err("constant expression required")
+        );
+    }
+
+    #[test]
+    fn test_eval() {
+        let instance = ModuleInst {
+            funcaddrs: vec![4, 7],
+            ..ModuleInst::default()
+        };
+        let mut heap = Heap::default();
+        let mut eval =
+            |expr: &Expression, globals: &[Val]| super::eval(expr, globals, &instance, &mut heap);
+        let extended = expr(vec![
+            Instruction::Variable(VariableInstruction::GlobalGet(0)),
+            i32_const(2),
+            Instruction::Numeric(NumericInstruction::I32Sub),
+            i32_const(3),
+            Instruction::Numeric(NumericInstruction::I32Mul),
+        ]);
+        assert_eq!(eval(&extended, &[Val::I32(5)]), Some(Val::I32(9)));
+
+        let wrapping = expr(vec![
+            Instruction::Numeric(NumericInstruction::I64Const(i64::MAX)),
+            Instruction::Numeric(NumericInstruction::I64Const(1)),
+            Instruction::Numeric(NumericInstruction::I64Add),
+        ]);
+        assert_eq!(eval(&wrapping, &[]), Some(Val::I64(i64::MIN)));
+
+        let func = expr(vec![Instruction::Reference(ReferenceInstruction::Func(1))]);
+        assert_eq!(eval(&func, &[]), Some(Val::FuncRef(Some(FuncHandle(7)))));
+
+        let null = expr(vec![Instruction::Reference(ReferenceInstruction::Null(
+            HeapType::Extern,
+        ))]);
+        assert_eq!(eval(&null, &[]), Some(Val::ExternRef(None)));
+
+        let vector = expr(vec![Instruction::Vector(VectorInstruction::V128Const(7))]);
+        assert_eq!(eval(&vector, &[]), Some(Val::V128(7)));
+        let unknown = expr(vec![Instruction::Reference(ReferenceInstruction::Func(2))]);
+        assert_eq!(eval(&unknown, &[]), None);
+
+        assert_eq!(eval(&expr(vec![]), &[]), None);
+    }
+}
@@ -0,0 +1,438 @@
+use crate::{
+    instructions::{
+        BlockType, ControlInstruction, Instruction, MemoryInstruction, NumericInstruction,
+        ParametricInstruction, ReferenceInstruction, TableInstruction,
+    },
+    module::{
+        data::Data,
+        element::Element,
+        import::ImportDescriptor,
+        types::{CompType, HeapType, MemType, RefType, TableType, ValType},
+    },
+    Module,
+};
+
+/// The proposals that a module may use beyond the original Wasm specification
+///
+/// Every proposal is enabled by default. A module that uses a type, instruction or section introduced by a disabled
+/// proposal fails `Module::new_with` and validation with "feature not enabled".
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Features {
+    pub simd: bool,
+    pub threads: bool,
+    pub tail_call: bool,
+    pub multi_memory: bool,
+    pub memory64: bool,
+    pub exceptions: bool,
+    pub gc: bool,
+    pub function_references: bool,
+    pub extended_const: bool,
+    pub bulk_memory: bool,
+    pub reference_types: bool,
+    pub sign_extension: bool,
+    pub saturating_float_to_int: bool,
+    pub multi_value: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Self::all(true)
+    }
+}
+
+impl Features {
+    /// Every proposal enabled, or none of them for a module that must stick to the original specification
+    pub fn all(enabled: bool) -> Self {
+        Self {
+            simd: enabled,
+            threads: enabled,
+            tail_call: enabled,
+            multi_memory: enabled,
+            memory64: enabled,
+            exceptions: enabled,
+            gc: enabled,
+            function_references: enabled,
+            extended_const: enabled,
+            bulk_memory: enabled,
+            reference_types: enabled,
+            sign_extension: enabled,
+            saturating_float_to_int: enabled,
+            multi_value: enabled,
+        }
+    }
+
+    /// Enable or disable a proposal by name, such as `tail_call` or `tail-call`, or every proposal with `all`
+    pub fn set(&mut self, name: &str, enabled: bool) -> Result<(), &'static str> {
+        let flag = match name.replace('-', "_").as_str() {
+            "all" => {
+                *self = Self::all(enabled);
+                return Ok(());
+            }
+            "simd" => &mut self.simd,
+            "threads" => &mut self.threads,
+            "tail_call" => &mut self.tail_call,
+            "multi_memory" => &mut self.multi_memory,
+            "memory64" => &mut self.memory64,
+            "exceptions" => &mut self.exceptions,
+            "gc" => &mut self.gc,
+            "function_references" => &mut self.function_references,
+            "extended_const" => &mut self.extended_const,
+            "bulk_memory" => &mut self.bulk_memory,
+            "reference_types" => &mut self.reference_types,
+            "sign_extension" => &mut self.sign_extension,
+            "saturating_float_to_int" => &mut self.saturating_float_to_int,
+            "multi_value" => &mut self.multi_value,
+            _ => return Err("unknown feature"),
+        };
+        *flag = enabled;
+        Ok(())
+    }
+
+    /// Check that the module only uses the enabled proposals
+    pub(crate) fn check(&self, module: &Module) -> Result<(), &'static str> {
+        for dt in &module.types {
+            // Recursion groups, subtyping, structs and arrays all come from the GC proposal
+            require(
+                self.gc || (dt.group.len() == 1 && dt.st.is_final && dt.st.supertypes.is_empty()),
+            )?;
+            match &dt.st.ct {
+                CompType::Func(ft) => {
+                    require(self.multi_value || ft.rt2.len() <= 1)?;
+                    for t in ft.rt1.iter().chain(&ft.rt2) {
+                        self.val_type(t)?;
+                    }
+                }
+                CompType::Struct(_) | CompType::Array(_) => require(self.gc)?,
+            }
+        }
+
+        let mut tables = module.tables.len();
+        let mut mems = module.memories.len();
+        for import in &module.imports {
+            match &import.descriptor {
+                ImportDescriptor::Func(_) => (),
+                ImportDescriptor::Table(tt) => {
+                    tables += 1;
+                    self.table_type(tt)?;
+                }
+                ImportDescriptor::Mem(mt) => {
+                    mems += 1;
+                    self.mem_type(mt)?;
+                }
+                ImportDescriptor::Global(gt) => self.val_type(&gt.t)?,
+                ImportDescriptor::Tag(_) => require(self.exceptions)?,
+            }
+        }
+        require(self.reference_types || tables <= 1)?;
+        require(self.multi_memory || mems <= 1)?;
+        require(self.exceptions || module.tags.is_empty())?;
+
+        for table in &module.tables {
+            self.table_type(&table.tt)?;
+            if let Some(init) = &table.init {
+                require(self.function_references)?;
+                self.const_expr(&init.0)?;
+            }
+        }
+        for memory in &module.memories {
+            self.mem_type(&memory.mt)?;
+        }
+        for global in &module.globals {
+            self.val_type(&global.gt.t)?;
+            self.const_expr(&global.init.0)?;
+        }
+        for element in &module.elements {
+            self.element(element)?;
+        }
+        require(self.bulk_memory || module.data_count.is_none())?;
+        for data in &module.data {
+            match data {
+                Data::Active(offset, _) | Data::ActiveExplicit(_, offset, _) => {
+                    self.const_expr(&offset.0)?
+                }
+                Data::Passive(_) => require(self.bulk_memory)?,
+            }
+        }
+        for code in &module.code {
+            for local in &code.code.locals {
+                self.val_type(&local.value_type)?;
+            }
+            self.instructions(&code.code.body.0)?;
+        }
+        Ok(())
+    }
+
+    fn val_type(&self, t: &ValType) -> Result<(), &'static str> {
+        match t {
+            ValType::NumType(_) => Ok(()),
+            ValType::VecType(_) => require(self.simd),
+            ValType::RefType(rt) => self.ref_type(rt),
+        }
+    }
+
+    fn ref_type(&self, rt: &RefType) -> Result<(), &'static str> {
+        require(self.reference_types)?;
+        require(self.function_references || rt.nullable)?;
+        match rt.ht {
+            HeapType::Func | HeapType::Extern => Ok(()),
+            HeapType::Type(_) => require(self.function_references),
+            HeapType::Exn | HeapType::NoExn => require(self.exceptions),
+            HeapType::NoFunc
+            | HeapType::NoExtern
+            | HeapType::Any
+            | HeapType::Eq
+            | HeapType::I31
+            | HeapType::Struct
+            | HeapType::Array
+            | HeapType::None => require(self.gc),
+        }
+    }
+
+    /// Tables of `funcref` predate every proposal
+    fn table_type(&self, tt: &TableType) -> Result<(), &'static str> {
+        if tt.et == RefType::FUNCREF {
+            return Ok(());
+        }
+        self.ref_type(&tt.et)
+    }
+
+    fn mem_type(&self, mt: &MemType) -> Result<(), &'static str> {
+        require(self.threads || !mt.shared)?;
+        require(self.memory64 || !mt.memory64)
+    }
+
+    /// Only active segments of function indices for table 0 predate the bulk memory proposal
+    fn element(&self, element: &Element) -> Result<(), &'static str> {
+        let (offset, t, exprs) = match element {
+            Element::ActiveIndex(offset, _) => return self.const_expr(&offset.0),
+            Element::PassiveIndex(_, _) => (None, None, &[][..]),
+            Element::ActiveExplicitIndex(_, offset, _, _) => (Some(offset), None, &[][..]),
+            Element::DeclarativeIndex(_, _) => {
+                require(self.reference_types)?;
+                (None, None, &[][..])
+            }
+            Element::ActiveExpression(offset, exprs) => (Some(offset), None, &exprs[..]),
+            Element::PassiveExpression(t, exprs) => (None, Some(t), &exprs[..]),
+            Element::ActiveExplicitExpression(_, offset, t, exprs) => {
+                (Some(offset), Some(t), &exprs[..])
+            }
+            Element::DeclarativeExpression(t, exprs) => {
+                require(self.reference_types)?;
+                (None, Some(t), &exprs[..])
+            }
+        };
+        require(self.bulk_memory)?;
+        if let Some(offset) = offset {
+            self.const_expr(&offset.0)?;
+        }
+        if let Some(t) = t.filter(|t| **t != RefType::FUNCREF) {
+            self.ref_type(t)?;
+        }
+        for expr in exprs {
+            self.const_expr(&expr.0)?;
+        }
+        Ok(())
+    }
+
+    /// Constant expressions can only do arithmetic with the extended constant expressions proposal
+    fn const_expr(&self, instructions: &[Instruction]) -> Result<(), &'static str> {
+        for instruction in instructions {
+            if let Instruction::Numeric(
+                NumericInstruction::I32Add
+                | NumericInstruction::I32Sub
+                | NumericInstruction::I32Mul
+                | NumericInstruction::I64Add
+                | NumericInstruction::I64Sub
+                | NumericInstruction::I64Mul,
+            ) = instruction
+            {
+                require(self.extended_const)?;
+            }
+        }
+        self.instructions(instructions)
+    }
+
+    fn block_type(&self, bt: &BlockType) -> Result<(), &'static str> {
+        match bt {
+            BlockType::Empty => Ok(()),
+            BlockType::ValType(t) => self.val_type(t),
+            BlockType::TypeIdx(_) => require(self.multi_value),
+        }
+    }
+
+    fn instructions(&self, instructions: &[Instruction]) -> Result<(), &'static str> {
+        for instruction in instructions {
+            match instruction {
+                Instruction::Control(instruction) => self.control(instruction)?,
+                Instruction::Reference(instruction) => match instruction {
+                    ReferenceInstruction::Null(ht) => self.ref_type(&RefType::null(ht.clone()))?,
+                    ReferenceInstruction::IsNull | ReferenceInstruction::Func(_) => {
+                        require(self.reference_types)?
+                    }
+                    ReferenceInstruction::Eq => require(self.gc)?,
+                    ReferenceInstruction::AsNonNull => require(self.function_references)?,
+                },
+                Instruction::Parametric(ParametricInstruction::SelectTyped(ts)) => {
+                    require(self.reference_types)?;
+                    for t in ts {
+                        self.val_type(t)?;
+                    }
+                }
+                Instruction::Parametric(_) | Instruction::Variable(_) => (),
+                Instruction::Table(
+                    TableInstruction::TableInit(_, _)
+                    | TableInstruction::ElemDrop(_)
+                    | TableInstruction::TableCopy(_, _),
+                ) => require(self.bulk_memory)?,
+                Instruction::Table(_) => require(self.reference_types)?,
+                Instruction::Memory(
+                    MemoryInstruction::MemoryInit(_, _)
+                    | MemoryInstruction::DataDrop(_)
+                    | MemoryInstruction::MemoryCopy(_, _)
+                    | MemoryInstruction::MemoryFill(_),
+                ) => require(self.bulk_memory)?,
+                Instruction::Memory(_) => (),
+                Instruction::Numeric(
+                    NumericInstruction::I32Extend8S
+                    | NumericInstruction::I32Extend16S
+                    | NumericInstruction::I64Extend8S
+                    | NumericInstruction::I64Extend16S
+                    | NumericInstruction::I64Extend32S,
+                ) => require(self.sign_extension)?,
+                Instruction::Numeric(_) => (),
+                Instruction::Saturating(_) => require(self.saturating_float_to_int)?,
+                Instruction::Vector(_) => require(self.simd)?,
+                Instruction::Atomic(_) => require(self.threads)?,
+                Instruction::Gc(_) => require(self.gc)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn control(&self, instruction: &ControlInstruction) -> Result<(), &'static str> {
+        match instruction {
+            ControlInstruction::Block(bt, instructions)
+            | ControlInstruction::Loop(bt, instructions) => {
+                self.block_type(bt)?;
+                self.instructions(instructions)
+            }
+            ControlInstruction::If(bt, then, otherwise) => {
+                self.block_type(bt)?;
+                self.instructions(then)?;
+                self.instructions(otherwise)
+            }
+            ControlInstruction::TryTable(bt, _, instructions) => {
+                require(self.exceptions)?;
+                self.block_type(bt)?;
+                self.instructions(instructions)
+            }
+            ControlInstruction::Throw(_) | ControlInstruction::ThrowRef => require(self.exceptions),
+            ControlInstruction::BrOnNull(_)
+            | ControlInstruction::BrOnNonNull(_)
+            | ControlInstruction::CallRef(_) => require(self.function_references),
+            ControlInstruction::ReturnCall(_) | ControlInstruction::ReturnCallIndirect(_, _) => {
+                require(self.tail_call)
+            }
+            ControlInstruction::ReturnCallRef(_) => {
+                require(self.tail_call)?;
+                require(self.function_references)
+            }
+            ControlInstruction::Unreachable
+            | ControlInstruction::Nop
+            | ControlInstruction::Br(_)
+            | ControlInstruction::BrIf(_)
+            | ControlInstruction::BrTable(_, _)
+            | ControlInstruction::Return
+            | ControlInstruction::Call(_)
+            | ControlInstruction::CallIndirect(_, _) => Ok(()),
+        }
+    }
+}
+
+fn require(enabled: bool) -> Result<(), &'static str> {
+    if enabled {
+        Ok(())
+    } else {
+        Err("feature not enabled")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Decode;
+
+    fn check(features: &Features, bytes: &[u8]) -> Result<(), &'static str> {
+        let (_, module) = Module::decode(bytes).unwrap();
+        features.check(&module)
+    }
+
+    #[test]
+    fn test_set() {
+        let mut features = Features::default();
+        assert_eq!(features.set("tail-call", false), Ok(()));
+        assert!(!features.tail_call);
+        assert_eq!(features.set("all", false), Ok(()));
+        assert_eq!(features.set("simd", true), Ok(()));
+        assert_eq!(
+            features,
+            Features {
+                simd: true,
+                ..Features::all(false)
+            }
+        );
+        assert_eq!(features.set("tail_calls", true), Err("unknown feature"));
+    }
+
+    #[test]
+    fn test_check() {
+        let disabled = |name| {
+            let mut features = Features::default();
+            features.set(name, false).unwrap();
+            features
+        };
+
+        // A module with a function that returns i32.extend8_s of a constant
+        let sign_extension: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x05, 0x01, 0x60, 0x00, 0x01,
+            0x7F, 0x03, 0x02, 0x01, 0x00, 0x0A, 0x07, 0x01, 0x05, 0x00, 0x41, 0x01, 0xC0, 0x0B,
+        ];
+        assert_eq!(check(&Features::default(), sign_extension), Ok(()));
+        assert_eq!(check(&disabled("simd"), sign_extension), Ok(()));
+        assert_eq!(
+            check(&disabled("sign_extension"), sign_extension),
+            Err("feature not enabled")
+        );
+
+        // A module with a global initialized to the sum of two constants
+        let extended_const: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x06, 0x09, 0x01, 0x7F, 0x00, 0x41,
+            0x01, 0x41, 0x02, 0x6A, 0x0B,
+        ];
+        assert_eq!(check(&Features::default(), extended_const), Ok(()));
+        assert_eq!(
+            check(&disabled("extended_const"), extended_const),
+            Err("feature not enabled")
+        );
+
+        // A module with a function type that has two results
+        let multi_value: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x06, 0x01, 0x60, 0x00, 0x02,
+            0x7F, 0x7F,
+        ];
+        assert_eq!(
+            check(&Features::all(false), multi_value),
+            Err("feature not enabled")
+        );
+        assert_eq!(check(&disabled("gc"), multi_value), Ok(()));
+
+        // A module with a shared memory
+        let shared: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x05, 0x04, 0x01, 0x03, 0x01, 0x02,
+        ];
+        assert_eq!(
+            check(&disabled("threads"), shared),
+            Err("feature not enabled")
+        );
+    }
+}
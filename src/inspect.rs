@@ -0,0 +1,660 @@
+use std::fmt::{self, Write};
+
+use crate::{
+    module::{
+        export::ExportDescriptor,
+        import::ImportDescriptor,
+        indices::TypeIdx,
+        producers::{Producer, Producers},
+        target_features::TargetFeature,
+        types::{GlobalType, Limits, MemType, TableType},
+        values::Name,
+    },
+    objdump::section_name,
+    text::sub_type,
+    Module,
+};
+
+/// A summary of the interface and metadata of a module for the `inspect` subcommand, as text for people to read or
+/// as JSON for scripts
+pub struct Inspect<'a> {
+    module: &'a Module,
+    json: bool,
+}
+
+impl Module {
+    /// Summarize the imports, exports, limits, sections and tool metadata of the module
+    pub fn inspect(&self) -> Inspect<'_> {
+        Inspect {
+            module: self,
+            json: false,
+        }
+    }
+}
+
+impl Inspect<'_> {
+    /// Write the summary as JSON, whose keys are always present and in the same order
+    pub fn json(mut self, json: bool) -> Self {
+        self.json = json;
+        self
+    }
+
+    /// The imports with their resolved types
+    fn imports(&self) -> Vec<(&Name, &Name, Extern<'_>)> {
+        self.module
+            .imports
+            .iter()
+            .map(|import| {
+                let ext = match &import.descriptor {
+                    ImportDescriptor::Func(typeidx) => Extern::Func(*typeidx),
+                    ImportDescriptor::Table(tt) => Extern::Table(tt),
+                    ImportDescriptor::Mem(mt) => Extern::Memory(mt),
+                    ImportDescriptor::Global(gt) => Extern::Global(gt),
+                    ImportDescriptor::Tag(tt) => Extern::Tag(tt.typeidx),
+                };
+                (&import.module, &import.name, ext)
+            })
+            .collect()
+    }
+
+    /// The exports with the index they export and its resolved type, which is missing if the index is out of bounds
+    fn exports(&self) -> Vec<(&Name, u32, Option<Extern<'_>>)> {
+        let module = self.module;
+        module
+            .exports
+            .iter()
+            .map(|export| {
+                let (idx, ext) = match export.descriptor {
+                    ExportDescriptor::Func(idx) => {
+                        (idx, module.func_types().nth(idx as usize).map(Extern::Func))
+                    }
+                    ExportDescriptor::Table(idx) => {
+                        (idx, module.tables().nth(idx as usize).map(Extern::Table))
+                    }
+                    ExportDescriptor::Mem(idx) => {
+                        (idx, module.memories().nth(idx as usize).map(Extern::Memory))
+                    }
+                    ExportDescriptor::Global(idx) => {
+                        (idx, module.globals().nth(idx as usize).map(Extern::Global))
+                    }
+                    ExportDescriptor::Tag(idx) => {
+                        (idx, tag_types(module).nth(idx as usize).map(Extern::Tag))
+                    }
+                };
+                (&export.name, idx, ext)
+            })
+            .collect()
+    }
+
+    /// The name, if it's a custom section, and size of the contents of each section in the decoded bytes
+    fn sections(&self) -> Vec<(u8, Option<String>, usize)> {
+        // The custom sections are kept in the same order as their headers
+        let mut customs = self.module.custom_sections.iter();
+        self.module
+            .sections
+            .iter()
+            .map(|header| {
+                let custom = match header.id {
+                    0 => customs.next().map(|section| name(&section.name)),
+                    _ => None,
+                };
+                (header.id, custom, header.contents.len())
+            })
+            .collect()
+    }
+
+    /// The type of an import or export in the text format
+    fn extern_type(&self, ext: &Extern) -> String {
+        match ext {
+            Extern::Func(typeidx) => self.func_type(*typeidx),
+            Extern::Table(tt) => format!("(table {})", tt),
+            Extern::Memory(mt) => format!("(memory {})", mt),
+            Extern::Global(gt) => format!("(global {})", gt),
+            Extern::Tag(typeidx) => format!("(tag {})", self.func_type(*typeidx)),
+        }
+    }
+
+    /// The function type at an index of the type section, or just the index if there is no type there
+    fn func_type(&self, typeidx: TypeIdx) -> String {
+        match self.module.types.get(typeidx as usize) {
+            Some(dt) => match dt.func_type() {
+                Some(ft) => ft.to_string(),
+                None => sub_type(&dt.st),
+            },
+            None => format!("(type {})", typeidx),
+        }
+    }
+
+    fn text(&self) -> String {
+        let mut out = String::new();
+        let imports = self.imports();
+        if !imports.is_empty() {
+            out.push_str("imports:\n");
+            for (module, field, ext) in &imports {
+                writeln!(
+                    out,
+                    "  {} {}.{}: {}",
+                    ext.kind(),
+                    name(module),
+                    name(field),
+                    self.extern_type(ext)
+                )
+                .unwrap();
+            }
+        }
+        let exports = self.exports();
+        if !exports.is_empty() {
+            out.push_str("exports:\n");
+            for (field, idx, ext) in &exports {
+                let (kind, ty) = match ext {
+                    Some(ext) => (ext.kind(), self.extern_type(ext)),
+                    None => ("unknown", "out of bounds".to_string()),
+                };
+                writeln!(out, "  {} {} ({}): {}", kind, name(field), idx, ty).unwrap();
+            }
+        }
+        let tables: Vec<_> = self.module.tables().collect();
+        if !tables.is_empty() {
+            out.push_str("tables:\n");
+            for (i, tt) in tables.iter().enumerate() {
+                writeln!(out, "  {}: {} {}", i, limits(&tt.lim), tt.et).unwrap();
+            }
+        }
+        let memories: Vec<_> = self.module.memories().collect();
+        if !memories.is_empty() {
+            out.push_str("memories:\n");
+            for (i, mt) in memories.iter().enumerate() {
+                write!(out, "  {}: {}", i, limits(&mt.lim)).unwrap();
+                if mt.memory64 {
+                    out.push_str(" i64");
+                }
+                if mt.shared {
+                    out.push_str(" shared");
+                }
+                out.push('\n');
+            }
+        }
+        if let Some(start) = self.module.start {
+            writeln!(out, "start: {}", start).unwrap();
+        }
+        let sections = self.sections();
+        if !sections.is_empty() {
+            out.push_str("sections:\n");
+            for (id, custom, size) in &sections {
+                match custom {
+                    Some(custom) => {
+                        writeln!(out, "  {} \"{}\": size={}", section_name(*id), custom, size)
+                    }
+                    None => writeln!(out, "  {}: size={}", section_name(*id), size),
+                }
+                .unwrap();
+            }
+        }
+        if let Some(producers) = self.module.producers() {
+            out.push_str("producers:\n");
+            for (field, values) in producer_fields(&producers) {
+                for Producer {
+                    name: tool,
+                    version,
+                } in values
+                {
+                    writeln!(out, "  {}: {} {}", field, name(tool), name(version)).unwrap();
+                }
+            }
+        }
+        if let Some(features) = self.module.target_features() {
+            out.push_str("target features:\n");
+            for feature in &features {
+                let (prefix, feature) = target_feature(feature);
+                writeln!(out, "  {}{}", prefix, name(feature)).unwrap();
+            }
+        }
+        out
+    }
+
+    fn json_value(&self) -> Json {
+        let imports = self
+            .imports()
+            .iter()
+            .map(|(module, field, ext)| {
+                let mut fields = vec![
+                    ("module", Json::String(name(module))),
+                    ("name", Json::String(name(field))),
+                ];
+                fields.extend(self.extern_json(ext));
+                Json::Object(fields)
+            })
+            .collect();
+        let exports = self
+            .exports()
+            .iter()
+            .map(|(field, idx, ext)| {
+                let mut fields = vec![
+                    ("name", Json::String(name(field))),
+                    ("index", Json::Number(*idx as u64)),
+                ];
+                match ext {
+                    Some(ext) => fields.extend(self.extern_json(ext)),
+                    None => fields.extend([
+                        ("kind", Json::String("unknown".to_string())),
+                        ("type", Json::Null),
+                    ]),
+                }
+                Json::Object(fields)
+            })
+            .collect();
+        let tables = self
+            .module
+            .tables()
+            .map(|tt| Json::Object(table_json(tt)))
+            .collect();
+        let memories = self
+            .module
+            .memories()
+            .map(|mt| Json::Object(memory_json(mt)))
+            .collect();
+        let sections = self
+            .sections()
+            .into_iter()
+            .map(|(id, custom, size)| {
+                Json::Object(vec![
+                    ("id", Json::Number(id as u64)),
+                    ("name", Json::String(section_name(id).to_lowercase())),
+                    ("custom", custom.map_or(Json::Null, Json::String)),
+                    ("size", Json::Number(size as u64)),
+                ])
+            })
+            .collect();
+        let producers = self.module.producers().map_or(Json::Null, |producers| {
+            Json::Object(
+                producer_fields(&producers)
+                    .into_iter()
+                    .map(|(field, values)| {
+                        let values = values
+                            .iter()
+                            .map(|producer| {
+                                Json::Object(vec![
+                                    ("name", Json::String(name(&producer.name))),
+                                    ("version", Json::String(name(&producer.version))),
+                                ])
+                            })
+                            .collect();
+                        (field, Json::Array(values))
+                    })
+                    .collect(),
+            )
+        });
+        let target_features = self
+            .module
+            .target_features()
+            .map_or(Json::Null, |features| {
+                Json::Array(
+                    features
+                        .iter()
+                        .map(|feature| {
+                            let (prefix, feature) = target_feature(feature);
+                            Json::Object(vec![
+                                ("prefix", Json::String(prefix.to_string())),
+                                ("name", Json::String(name(feature))),
+                            ])
+                        })
+                        .collect(),
+                )
+            });
+        Json::Object(vec![
+            ("imports", Json::Array(imports)),
+            ("exports", Json::Array(exports)),
+            ("tables", Json::Array(tables)),
+            ("memories", Json::Array(memories)),
+            (
+                "start",
+                self.module
+                    .start
+                    .map_or(Json::Null, |start| Json::Number(start as u64)),
+            ),
+            ("sections", Json::Array(sections)),
+            ("producers", producers),
+            ("target_features", target_features),
+        ])
+    }
+
+    /// The kind and type of an import or export, with the limits of tables and memories spelled out
+    fn extern_json(&self, ext: &Extern) -> Vec<(&'static str, Json)> {
+        let mut fields = vec![
+            ("kind", Json::String(ext.kind().to_string())),
+            ("type", Json::String(self.extern_type(ext))),
+        ];
+        match ext {
+            Extern::Table(tt) => fields.extend(table_json(tt)),
+            Extern::Memory(mt) => fields.extend(memory_json(mt)),
+            _ => (),
+        }
+        fields
+    }
+}
+
+impl fmt::Display for Inspect<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.json {
+            writeln!(f, "{}", self.json_value())
+        } else {
+            f.write_str(&self.text())
+        }
+    }
+}
+
+/// The type index of every tag, with the imported tags first as in the tag index space
+fn tag_types(module: &Module) -> impl Iterator<Item = TypeIdx> + '_ {
+    module
+        .imports
+        .iter()
+        .filter_map(|import| match &import.descriptor {
+            ImportDescriptor::Tag(tt) => Some(tt.typeidx),
+            _ => None,
+        })
+        .chain(module.tags.iter().map(|tag| tag.tt.typeidx))
+}
+
+/// What an import or export refers to, with the type it has
+enum Extern<'a> {
+    Func(TypeIdx),
+    Table(&'a TableType),
+    Memory(&'a MemType),
+    Global(&'a GlobalType),
+    Tag(TypeIdx),
+}
+
+impl Extern<'_> {
+    /// The keyword for the kind of the extern in the text format
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Func(_) => "func",
+            Self::Table(_) => "table",
+            Self::Memory(_) => "memory",
+            Self::Global(_) => "global",
+            Self::Tag(_) => "tag",
+        }
+    }
+}
+
+/// A JSON value, written with two spaces of indentation and the fields of objects in the order they were given
+enum Json {
+    Null,
+    Bool(bool),
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            Self::Null => out.push_str("null"),
+            Self::Bool(b) => write!(out, "{}", b).unwrap(),
+            Self::Number(n) => write!(out, "{}", n).unwrap(),
+            Self::String(s) => json_string(out, s),
+            Self::Array(values) if values.is_empty() => out.push_str("[]"),
+            Self::Array(values) => {
+                out.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    out.push_str(&"  ".repeat(indent + 1));
+                    value.write(out, indent + 1);
+                }
+                write!(out, "\n{}]", "  ".repeat(indent)).unwrap();
+            }
+            Self::Object(fields) if fields.is_empty() => out.push_str("{}"),
+            Self::Object(fields) => {
+                out.push('{');
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    out.push_str(&"  ".repeat(indent + 1));
+                    json_string(out, key);
+                    out.push_str(": ");
+                    value.write(out, indent + 1);
+                }
+                write!(out, "\n{}}}", "  ".repeat(indent)).unwrap();
+            }
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        self.write(&mut out, 0);
+        f.write_str(&out)
+    }
+}
+
+/// A JSON string literal, escaping quotes, backslashes and control characters
+fn json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn table_json(tt: &TableType) -> Vec<(&'static str, Json)> {
+    vec![
+        ("element", Json::String(tt.et.to_string())),
+        ("min", Json::Number(tt.lim.min)),
+        ("max", tt.lim.max.map_or(Json::Null, Json::Number)),
+    ]
+}
+
+fn memory_json(mt: &MemType) -> Vec<(&'static str, Json)> {
+    vec![
+        ("min", Json::Number(mt.lim.min)),
+        ("max", mt.lim.max.map_or(Json::Null, Json::Number)),
+        ("memory64", Json::Bool(mt.memory64)),
+        ("shared", Json::Bool(mt.shared)),
+    ]
+}
+
+fn limits(lim: &Limits) -> String {
+    match lim.max {
+        Some(max) => format!("min={} max={}", lim.min, max),
+        None => format!("min={}", lim.min),
+    }
+}
+
+/// The fields of the `producers` section with their names in the section
+fn producer_fields(producers: &Producers) -> [(&'static str, &Vec<Producer>); 3] {
+    [
+        ("language", &producers.language),
+        ("processed-by", &producers.processed_by),
+        ("sdk", &producers.sdk),
+    ]
+}
+
+/// The prefix and name of a feature in the `target_features` section
+fn target_feature(feature: &TargetFeature) -> (char, &Name) {
+    match feature {
+        TargetFeature::Used(name) => ('+', name),
+        TargetFeature::Disallowed(name) => ('-', name),
+    }
+}
+
+/// A name as UTF-8 where it's valid, with the invalid bytes escaped as `\xx` and backslashes as `\\`
+fn name(name: &Name) -> String {
+    let mut out = String::new();
+    for chunk in name.0.utf8_chunks() {
+        out.push_str(&chunk.valid().replace('\\', "\\\\"));
+        for byte in chunk.invalid() {
+            write!(out, "\\{:02x}", byte).unwrap();
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{module::custom::CustomSection, New};
+
+    // A module that imports a memory and a function, defines a table and a function that it exports as "main" and
+    // starts, and has a custom section "x"
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x06, 0x01, 0x60, 0x01, 0x7F, 0x01, 0x7F, // type
+        0x02, 0x14, 0x02, 0x03, 0x65, 0x6E, 0x76, 0x03, 0x6D, 0x65, 0x6D, 0x02, 0x00, 0x01, 0x03,
+        0x65, 0x6E, 0x76, 0x01, 0x66, 0x00, 0x00, // import
+        0x03, 0x02, 0x01, 0x00, // function
+        0x04, 0x04, 0x01, 0x70, 0x00, 0x01, // table
+        0x07, 0x08, 0x01, 0x04, 0x6D, 0x61, 0x69, 0x6E, 0x00, 0x01, // export
+        0x08, 0x01, 0x01, // start
+        0x0A, 0x06, 0x01, 0x04, 0x00, 0x20, 0x00, 0x0B, // code
+        0x00, 0x02, 0x01, 0x78, // custom "x"
+    ];
+
+    #[test]
+    fn test_text() {
+        let module = Module::new(MODULE).unwrap();
+        assert_eq!(
+            module.inspect().to_string(),
+            "imports:
+  memory env.mem: (memory 1)
+  func env.f: (func (param i32) (result i32))
+exports:
+  func main (1): (func (param i32) (result i32))
+tables:
+  0: min=1 funcref
+memories:
+  0: min=1
+start: 1
+sections:
+  Type: size=6
+  Import: size=20
+  Function: size=2
+  Table: size=4
+  Export: size=8
+  Start: size=1
+  Code: size=6
+  Custom \"x\": size=2
+"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let module = Module::new(MODULE).unwrap();
+        let json = module.inspect().json(true).to_string();
+        assert!(json.starts_with(
+            "{
+  \"imports\": [
+    {
+      \"module\": \"env\",
+      \"name\": \"mem\",
+      \"kind\": \"memory\",
+      \"type\": \"(memory 1)\",
+      \"min\": 1,
+      \"max\": null,
+      \"memory64\": false,
+      \"shared\": false
+    },
+"
+        ));
+        assert!(json.contains(
+            "  \"start\": 1,
+  \"sections\": [
+    {
+      \"id\": 1,
+      \"name\": \"type\",
+      \"custom\": null,
+      \"size\": 6
+    },
+"
+        ));
+        assert!(json.ends_with(
+            "  \"producers\": null,
+  \"target_features\": null
+}
+"
+        ));
+
+        // Every key is present even when the module is empty
+        let module = Module::new(&MODULE[..8]).unwrap();
+        assert_eq!(
+            module.inspect().json(true).to_string(),
+            "{
+  \"imports\": [],
+  \"exports\": [],
+  \"tables\": [],
+  \"memories\": [],
+  \"start\": null,
+  \"sections\": [],
+  \"producers\": null,
+  \"target_features\": null
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_metadata() {
+        let mut module = Module::new(&MODULE[..8]).unwrap();
+        assert_eq!(module.inspect().to_string(), "");
+        module.add_custom_section(CustomSection::new(
+            "producers",
+            vec![
+                0x01, 0x0C, 0x70, 0x72, 0x6F, 0x63, 0x65, 0x73, 0x73, 0x65, 0x64, 0x2D, 0x62,
+                0x79, // "processed-by"
+                0x01, 0x05, 0x72, 0x75, 0x73, 0x74, 0x63, 0x03, 0x31, 0x2E,
+                0x30, // "rustc" "1.0"
+            ],
+        ));
+        module.add_custom_section(CustomSection::new(
+            "target_features",
+            vec![0x02, 0x2B, 0x01, 0x61, 0x2D, 0x01, 0x62],
+        ));
+        assert_eq!(
+            module.inspect().to_string(),
+            "producers:\n  processed-by: rustc 1.0\ntarget features:\n  +a\n  -b\n"
+        );
+        assert!(module.inspect().json(true).to_string().ends_with(
+            "  \"producers\": {
+    \"language\": [],
+    \"processed-by\": [
+      {
+        \"name\": \"rustc\",
+        \"version\": \"1.0\"
+      }
+    ],
+    \"sdk\": []
+  },
+  \"target_features\": [
+    {
+      \"prefix\": \"+\",
+      \"name\": \"a\"
+    },
+    {
+      \"prefix\": \"-\",
+      \"name\": \"b\"
+    }
+  ]
+}
+"
+        ));
+    }
+
+    #[test]
+    fn test_name() {
+        assert_eq!(name(&Name("é".as_bytes().to_vec())), "é");
+        assert_eq!(name(&Name(vec![0x61, 0xFF, 0x62])), "a\\ffb");
+        assert_eq!(name(&Name(b"a\\ffb".to_vec())), "a\\\\ffb");
+        let mut out = String::new();
+        json_string(&mut out, "a\\ffb\"\n\u{1}");
+        assert_eq!(out, "\"a\\\\ffb\\\"\\n\\u0001\"");
+    }
+}
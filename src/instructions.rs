@@ -1,22 +1,35 @@
 use nom::{branch::alt, bytes::complete::tag, combinator::map, multi::many_till, IResult};
 
-use crate::Decode;
+use crate::{module::indices::FuncIdx, Decode, Encode};
 
-use self::{
+pub use self::{
+    atomic::{AtomicInstruction, AtomicOp, RmwOp},
+    control::{BlockType, CatchClause, ControlInstruction},
+    gc::GcInstruction,
+    memory::{MemArg, MemoryInstruction},
     numeric::{NumericInstruction, SaturatingTruncationInstruction},
     parametric::ParametricInstruction,
     reference::ReferenceInstruction,
+    table::TableInstruction,
     variable::VariableInstruction,
+    vector::VectorInstruction,
 };
 
+mod atomic;
+mod control;
+mod gc;
+mod memory;
 mod numeric;
 mod parametric;
 mod reference;
+mod table;
 mod variable;
+mod vector;
 
 /// TODO: Document
-#[derive(Debug, PartialEq)]
-pub struct Expression(Vec<Instruction>);
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Expression(pub Vec<Instruction>);
 
 impl Decode for Expression {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
@@ -26,25 +39,87 @@ impl Decode for Expression {
     }
 }
 
+impl Encode for Expression {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0
+            .iter()
+            .for_each(|instruction| instruction.encode(out));
+        out.push(0x0B);
+    }
+}
+
+impl Expression {
+    /// The indices of every function referenced with `ref.func` in the expression, including in nested blocks
+    pub fn func_refs(&self) -> impl Iterator<Item = FuncIdx> + '_ {
+        func_refs(&self.0)
+    }
+}
+
+fn func_refs(instructions: &[Instruction]) -> Box<dyn Iterator<Item = FuncIdx> + '_> {
+    Box::new(
+        instructions
+            .iter()
+            .flat_map(|instruction| -> Box<dyn Iterator<Item = FuncIdx>> {
+                match instruction {
+                    Instruction::Reference(ReferenceInstruction::Func(funcidx)) => {
+                        Box::new(std::iter::once(*funcidx))
+                    }
+                    Instruction::Control(
+                        ControlInstruction::Block(_, instructions)
+                        | ControlInstruction::Loop(_, instructions)
+                        | ControlInstruction::TryTable(_, _, instructions),
+                    ) => func_refs(instructions),
+                    Instruction::Control(ControlInstruction::If(_, then, otherwise)) => {
+                        Box::new(func_refs(then).chain(func_refs(otherwise)))
+                    }
+                    _ => Box::new(std::iter::empty()),
+                }
+            }),
+    )
+}
+
 /// TODO: Document
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Instruction {
-    //     Control(ControlInstruction),
+    Control(ControlInstruction),
     Reference(ReferenceInstruction),
     Parametric(ParametricInstruction),
     Variable(VariableInstruction),
-    //     Table(TableInstruction),
-    //     Memory(MemoryInstruction),
+    Table(TableInstruction),
+    Memory(MemoryInstruction),
     Numeric(NumericInstruction),
     Saturating(SaturatingTruncationInstruction),
+    Vector(VectorInstruction),
+    Atomic(AtomicInstruction),
+    Gc(GcInstruction),
+}
+
+impl Instruction {
+    /// The name of the instruction in the text format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Control(instruction) => instruction.name(),
+            Self::Reference(instruction) => instruction.name(),
+            Self::Parametric(instruction) => instruction.name(),
+            Self::Variable(instruction) => instruction.name(),
+            Self::Table(instruction) => instruction.name(),
+            Self::Memory(instruction) => instruction.name(),
+            Self::Numeric(instruction) => instruction.name(),
+            Self::Saturating(instruction) => instruction.name(),
+            Self::Vector(instruction) => instruction.name(),
+            Self::Atomic(instruction) => instruction.name(),
+            Self::Gc(instruction) => instruction.name(),
+        }
+    }
 }
 
 impl Decode for Instruction {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
         alt((
-            // map(ControlInstruction::decode, |instruction| {
-            //     Self::Control(instruction)
-            // }),
+            map(ControlInstruction::decode, |instruction| {
+                Self::Control(instruction)
+            }),
             map(ReferenceInstruction::decode, |instruction| {
                 Self::Reference(instruction)
             }),
@@ -54,22 +129,47 @@ impl Decode for Instruction {
             map(VariableInstruction::decode, |instruction| {
                 Self::Variable(instruction)
             }),
-            // map(TableInstruction::decode, |instruction| {
-            //     Self::Table(instruction)
-            // }),
-            // map(MemoryInstruction::decode, |instruction| {
-            //     Self::Memory(instruction)
-            // }),
+            map(TableInstruction::decode, |instruction| {
+                Self::Table(instruction)
+            }),
+            map(MemoryInstruction::decode, |instruction| {
+                Self::Memory(instruction)
+            }),
             map(NumericInstruction::decode, |instruction| {
                 Self::Numeric(instruction)
             }),
             map(SaturatingTruncationInstruction::decode, |instruction| {
                 Self::Saturating(instruction)
             }),
+            map(VectorInstruction::decode, |instruction| {
+                Self::Vector(instruction)
+            }),
+            map(AtomicInstruction::decode, |instruction| {
+                Self::Atomic(instruction)
+            }),
+            map(GcInstruction::decode, Self::Gc),
         ))(input)
     }
 }
 
+impl Encode for Instruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Control(instruction) => instruction.encode(out),
+            Self::Reference(instruction) => instruction.encode(out),
+            Self::Parametric(instruction) => instruction.encode(out),
+            Self::Variable(instruction) => instruction.encode(out),
+            Self::Table(instruction) => instruction.encode(out),
+            Self::Memory(instruction) => instruction.encode(out),
+            Self::Numeric(instruction) => instruction.encode(out),
+            Self::Saturating(instruction) => instruction.encode(out),
+            Self::Vector(instruction) => instruction.encode(out),
+            Self::Atomic(instruction) => instruction.encode(out),
+            Self::Gc(instruction) => instruction.encode(out),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -97,6 +197,61 @@ mod tests {
             Instruction::decode(&[0xC0]),
             Ok((EMPTY, Instruction::Numeric(NumericInstruction::I32Extend8S)))
         );
+        assert_eq!(
+            Instruction::decode(&[0xFC, 0x11, 0x00]),
+            Ok((EMPTY, Instruction::Table(TableInstruction::TableFill(0))))
+        );
+        assert_eq!(
+            Instruction::decode(&[0xFD, 0x0F]),
+            Ok((EMPTY, Instruction::Vector(VectorInstruction::I8x16Splat)))
+        );
+        assert_eq!(
+            Instruction::decode(&[0xFE, 0x03, 0x00]),
+            Ok((EMPTY, Instruction::Atomic(AtomicInstruction::AtomicFence)))
+        );
+        assert_eq!(
+            Instruction::decode(&[0xFC, 0x0B, 0x00]),
+            Ok((EMPTY, Instruction::Memory(MemoryInstruction::MemoryFill(0))))
+        );
+        assert_eq!(
+            Instruction::decode(&[0xFB, 0x0F]),
+            Ok((EMPTY, Instruction::Gc(GcInstruction::ArrayLen)))
+        );
         assert!(Instruction::decode(&[0xFF]).is_err());
     }
+
+    #[test]
+    fn test_func_refs() {
+        // A block containing ref.func 1 followed by ref.func 2
+        let (_, expr) =
+            Expression::decode(&[0x02, 0x40, 0xD2, 0x01, 0x1A, 0x0B, 0xD2, 0x02, 0x1A, 0x0B])
+                .unwrap();
+        assert_eq!(expr.func_refs().collect::<Vec<_>>(), vec!(1, 2));
+    }
+
+    #[test]
+    fn test_encode() {
+        let input: &[u8] = &[
+            0x02, 0x40, 0x41, 0x7F, 0x0D, 0x00, 0x1A, 0x0B, // block (br_if 0 (i32.const -1))
+            0x04, 0x7F, 0x41, 0x01, 0x05, 0x41, 0x02, 0x0B, // if (result i32) else
+            0x28, 0x42, 0x01, 0x08, // i32.load 1 offset=8 align=4
+            0xFC, 0x0A, 0x00, 0x01, // memory.copy 0 1
+            0xFC, 0x0E, 0x01, 0x00, // table.copy 1 0
+            0x26, 0x02, // table.set 2
+            0x44, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF8, 0x3F, // f64.const 1.5
+            0xFD, 0x54, 0x00, 0x00, 0x03, // v128.load8_lane 3
+            0xFE, 0x03, 0x00, // atomic.fence
+            0xFB, 0x18, 0x03, 0x00, 0x6E, 0x6B, // br_on_cast 0 anyref structref
+            0xFB, 0x14, 0x6C, // ref.test (ref i31)
+            0x1F, 0x40, 0x01, 0x02, 0x00, 0x0B, // try_table (catch_all 0)
+            0x0E, 0x02, 0x00, 0x01, 0x00, // br_table 0 1 0
+            0xFC, 0x00, // i32.trunc_sat_f32_s
+            0x0B,
+        ];
+        let (rest, expr) = Expression::decode(input).unwrap();
+        assert_eq!(rest, EMPTY);
+        let mut out = Vec::new();
+        expr.encode(&mut out);
+        assert_eq!(out, input);
+    }
 }
@@ -0,0 +1,515 @@
+use nom::{
+    bytes::complete::tag,
+    combinator::{fail, map},
+    IResult,
+};
+
+use crate::{module::types::NumType, Decode, Encode};
+
+use super::memory::MemArg;
+
+// NOTE: The variants and match arms below are generated by util/gen_atomic.py
+
+/// An atomic memory instruction from the threads proposal, encoded behind the 0xFE prefix
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AtomicInstruction {
+    MemoryAtomicNotify(MemArg),
+    MemoryAtomicWait32(MemArg),
+    MemoryAtomicWait64(MemArg),
+    AtomicFence,
+    I32AtomicLoad(MemArg),
+    I64AtomicLoad(MemArg),
+    I32AtomicLoad8U(MemArg),
+    I32AtomicLoad16U(MemArg),
+    I64AtomicLoad8U(MemArg),
+    I64AtomicLoad16U(MemArg),
+    I64AtomicLoad32U(MemArg),
+    I32AtomicStore(MemArg),
+    I64AtomicStore(MemArg),
+    I32AtomicStore8(MemArg),
+    I32AtomicStore16(MemArg),
+    I64AtomicStore8(MemArg),
+    I64AtomicStore16(MemArg),
+    I64AtomicStore32(MemArg),
+    I32AtomicRmwAdd(MemArg),
+    I64AtomicRmwAdd(MemArg),
+    I32AtomicRmw8AddU(MemArg),
+    I32AtomicRmw16AddU(MemArg),
+    I64AtomicRmw8AddU(MemArg),
+    I64AtomicRmw16AddU(MemArg),
+    I64AtomicRmw32AddU(MemArg),
+    I32AtomicRmwSub(MemArg),
+    I64AtomicRmwSub(MemArg),
+    I32AtomicRmw8SubU(MemArg),
+    I32AtomicRmw16SubU(MemArg),
+    I64AtomicRmw8SubU(MemArg),
+    I64AtomicRmw16SubU(MemArg),
+    I64AtomicRmw32SubU(MemArg),
+    I32AtomicRmwAnd(MemArg),
+    I64AtomicRmwAnd(MemArg),
+    I32AtomicRmw8AndU(MemArg),
+    I32AtomicRmw16AndU(MemArg),
+    I64AtomicRmw8AndU(MemArg),
+    I64AtomicRmw16AndU(MemArg),
+    I64AtomicRmw32AndU(MemArg),
+    I32AtomicRmwOr(MemArg),
+    I64AtomicRmwOr(MemArg),
+    I32AtomicRmw8OrU(MemArg),
+    I32AtomicRmw16OrU(MemArg),
+    I64AtomicRmw8OrU(MemArg),
+    I64AtomicRmw16OrU(MemArg),
+    I64AtomicRmw32OrU(MemArg),
+    I32AtomicRmwXor(MemArg),
+    I64AtomicRmwXor(MemArg),
+    I32AtomicRmw8XorU(MemArg),
+    I32AtomicRmw16XorU(MemArg),
+    I64AtomicRmw8XorU(MemArg),
+    I64AtomicRmw16XorU(MemArg),
+    I64AtomicRmw32XorU(MemArg),
+    I32AtomicRmwXchg(MemArg),
+    I64AtomicRmwXchg(MemArg),
+    I32AtomicRmw8XchgU(MemArg),
+    I32AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw8XchgU(MemArg),
+    I64AtomicRmw16XchgU(MemArg),
+    I64AtomicRmw32XchgU(MemArg),
+    I32AtomicRmwCmpxchg(MemArg),
+    I64AtomicRmwCmpxchg(MemArg),
+    I32AtomicRmw8CmpxchgU(MemArg),
+    I32AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw8CmpxchgU(MemArg),
+    I64AtomicRmw16CmpxchgU(MemArg),
+    I64AtomicRmw32CmpxchgU(MemArg),
+}
+
+/// The operation an atomic memory access performs
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AtomicOp {
+    Notify,
+    Wait,
+    Load,
+    Store,
+    Rmw(RmwOp),
+    Cmpxchg,
+}
+
+/// The arithmetic or bitwise operation of an atomic read-modify-write
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RmwOp {
+    Add,
+    Sub,
+    And,
+    Or,
+    Xor,
+    Xchg,
+}
+
+impl AtomicInstruction {
+    /// The operation, operand type, access width in bytes and memory argument of the instruction
+    ///
+    /// Returns `None` for `atomic.fence`, which does not access memory.
+    pub fn access(&self) -> Option<(AtomicOp, NumType, u32, &MemArg)> {
+        let access = match self {
+            Self::MemoryAtomicNotify(memarg) => (AtomicOp::Notify, NumType::I32, 4, memarg),
+            Self::MemoryAtomicWait32(memarg) => (AtomicOp::Wait, NumType::I32, 4, memarg),
+            Self::MemoryAtomicWait64(memarg) => (AtomicOp::Wait, NumType::I64, 8, memarg),
+            Self::AtomicFence => return None,
+            Self::I32AtomicLoad(memarg) => (AtomicOp::Load, NumType::I32, 4, memarg),
+            Self::I64AtomicLoad(memarg) => (AtomicOp::Load, NumType::I64, 8, memarg),
+            Self::I32AtomicLoad8U(memarg) => (AtomicOp::Load, NumType::I32, 1, memarg),
+            Self::I32AtomicLoad16U(memarg) => (AtomicOp::Load, NumType::I32, 2, memarg),
+            Self::I64AtomicLoad8U(memarg) => (AtomicOp::Load, NumType::I64, 1, memarg),
+            Self::I64AtomicLoad16U(memarg) => (AtomicOp::Load, NumType::I64, 2, memarg),
+            Self::I64AtomicLoad32U(memarg) => (AtomicOp::Load, NumType::I64, 4, memarg),
+            Self::I32AtomicStore(memarg) => (AtomicOp::Store, NumType::I32, 4, memarg),
+            Self::I64AtomicStore(memarg) => (AtomicOp::Store, NumType::I64, 8, memarg),
+            Self::I32AtomicStore8(memarg) => (AtomicOp::Store, NumType::I32, 1, memarg),
+            Self::I32AtomicStore16(memarg) => (AtomicOp::Store, NumType::I32, 2, memarg),
+            Self::I64AtomicStore8(memarg) => (AtomicOp::Store, NumType::I64, 1, memarg),
+            Self::I64AtomicStore16(memarg) => (AtomicOp::Store, NumType::I64, 2, memarg),
+            Self::I64AtomicStore32(memarg) => (AtomicOp::Store, NumType::I64, 4, memarg),
+            Self::I32AtomicRmwAdd(memarg) => (AtomicOp::Rmw(RmwOp::Add), NumType::I32, 4, memarg),
+            Self::I64AtomicRmwAdd(memarg) => (AtomicOp::Rmw(RmwOp::Add), NumType::I64, 8, memarg),
+            Self::I32AtomicRmw8AddU(memarg) => (AtomicOp::Rmw(RmwOp::Add), NumType::I32, 1, memarg),
+            Self::I32AtomicRmw16AddU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::Add), NumType::I32, 2, memarg)
+            }
+            Self::I64AtomicRmw8AddU(memarg) => (AtomicOp::Rmw(RmwOp::Add), NumType::I64, 1, memarg),
+            Self::I64AtomicRmw16AddU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::Add), NumType::I64, 2, memarg)
+            }
+            Self::I64AtomicRmw32AddU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::Add), NumType::I64, 4, memarg)
+            }
+            Self::I32AtomicRmwSub(memarg) => (AtomicOp::Rmw(RmwOp::Sub), NumType::I32, 4, memarg),
+            Self::I64AtomicRmwSub(memarg) => (AtomicOp::Rmw(RmwOp::Sub), NumType::I64, 8, memarg),
+            Self::I32AtomicRmw8SubU(memarg) => (AtomicOp::Rmw(RmwOp::Sub), NumType::I32, 1, memarg),
+            Self::I32AtomicRmw16SubU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::Sub), NumType::I32, 2, memarg)
+            }
+            Self::I64AtomicRmw8SubU(memarg) => (AtomicOp::Rmw(RmwOp::Sub), NumType::I64, 1, memarg),
+            Self::I64AtomicRmw16SubU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::Sub), NumType::I64, 2, memarg)
+            }
+            Self::I64AtomicRmw32SubU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::Sub), NumType::I64, 4, memarg)
+            }
+            Self::I32AtomicRmwAnd(memarg) => (AtomicOp::Rmw(RmwOp::And), NumType::I32, 4, memarg),
+            Self::I64AtomicRmwAnd(memarg) => (AtomicOp::Rmw(RmwOp::And), NumType::I64, 8, memarg),
+            Self::I32AtomicRmw8AndU(memarg) => (AtomicOp::Rmw(RmwOp::And), NumType::I32, 1, memarg),
+            Self::I32AtomicRmw16AndU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::And), NumType::I32, 2, memarg)
+            }
+            Self::I64AtomicRmw8AndU(memarg) => (AtomicOp::Rmw(RmwOp::And), NumType::I64, 1, memarg),
+            Self::I64AtomicRmw16AndU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::And), NumType::I64, 2, memarg)
+            }
+            Self::I64AtomicRmw32AndU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::And), NumType::I64, 4, memarg)
+            }
+            Self::I32AtomicRmwOr(memarg) => (AtomicOp::Rmw(RmwOp::Or), NumType::I32, 4, memarg),
+            Self::I64AtomicRmwOr(memarg) => (AtomicOp::Rmw(RmwOp::Or), NumType::I64, 8, memarg),
+            Self::I32AtomicRmw8OrU(memarg) => (AtomicOp::Rmw(RmwOp::Or), NumType::I32, 1, memarg),
+            Self::I32AtomicRmw16OrU(memarg) => (AtomicOp::Rmw(RmwOp::Or), NumType::I32, 2, memarg),
+            Self::I64AtomicRmw8OrU(memarg) => (AtomicOp::Rmw(RmwOp::Or), NumType::I64, 1, memarg),
+            Self::I64AtomicRmw16OrU(memarg) => (AtomicOp::Rmw(RmwOp::Or), NumType::I64, 2, memarg),
+            Self::I64AtomicRmw32OrU(memarg) => (AtomicOp::Rmw(RmwOp::Or), NumType::I64, 4, memarg),
+            Self::I32AtomicRmwXor(memarg) => (AtomicOp::Rmw(RmwOp::Xor), NumType::I32, 4, memarg),
+            Self::I64AtomicRmwXor(memarg) => (AtomicOp::Rmw(RmwOp::Xor), NumType::I64, 8, memarg),
+            Self::I32AtomicRmw8XorU(memarg) => (AtomicOp::Rmw(RmwOp::Xor), NumType::I32, 1, memarg),
+            Self::I32AtomicRmw16XorU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::Xor), NumType::I32, 2, memarg)
+            }
+            Self::I64AtomicRmw8XorU(memarg) => (AtomicOp::Rmw(RmwOp::Xor), NumType::I64, 1, memarg),
+            Self::I64AtomicRmw16XorU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::Xor), NumType::I64, 2, memarg)
+            }
+            Self::I64AtomicRmw32XorU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::Xor), NumType::I64, 4, memarg)
+            }
+            Self::I32AtomicRmwXchg(memarg) => (AtomicOp::Rmw(RmwOp::Xchg), NumType::I32, 4, memarg),
+            Self::I64AtomicRmwXchg(memarg) => (AtomicOp::Rmw(RmwOp::Xchg), NumType::I64, 8, memarg),
+            Self::I32AtomicRmw8XchgU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::Xchg), NumType::I32, 1, memarg)
+            }
+            Self::I32AtomicRmw16XchgU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::Xchg), NumType::I32, 2, memarg)
+            }
+            Self::I64AtomicRmw8XchgU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::Xchg), NumType::I64, 1, memarg)
+            }
+            Self::I64AtomicRmw16XchgU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::Xchg), NumType::I64, 2, memarg)
+            }
+            Self::I64AtomicRmw32XchgU(memarg) => {
+                (AtomicOp::Rmw(RmwOp::Xchg), NumType::I64, 4, memarg)
+            }
+            Self::I32AtomicRmwCmpxchg(memarg) => (AtomicOp::Cmpxchg, NumType::I32, 4, memarg),
+            Self::I64AtomicRmwCmpxchg(memarg) => (AtomicOp::Cmpxchg, NumType::I64, 8, memarg),
+            Self::I32AtomicRmw8CmpxchgU(memarg) => (AtomicOp::Cmpxchg, NumType::I32, 1, memarg),
+            Self::I32AtomicRmw16CmpxchgU(memarg) => (AtomicOp::Cmpxchg, NumType::I32, 2, memarg),
+            Self::I64AtomicRmw8CmpxchgU(memarg) => (AtomicOp::Cmpxchg, NumType::I64, 1, memarg),
+            Self::I64AtomicRmw16CmpxchgU(memarg) => (AtomicOp::Cmpxchg, NumType::I64, 2, memarg),
+            Self::I64AtomicRmw32CmpxchgU(memarg) => (AtomicOp::Cmpxchg, NumType::I64, 4, memarg),
+        };
+        Some(access)
+    }
+
+    /// The name of the instruction in the text format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::MemoryAtomicNotify(..) => "memory.atomic.notify",
+            Self::MemoryAtomicWait32(..) => "memory.atomic.wait32",
+            Self::MemoryAtomicWait64(..) => "memory.atomic.wait64",
+            Self::AtomicFence => "atomic.fence",
+            Self::I32AtomicLoad(..) => "i32.atomic.load",
+            Self::I64AtomicLoad(..) => "i64.atomic.load",
+            Self::I32AtomicLoad8U(..) => "i32.atomic.load8_u",
+            Self::I32AtomicLoad16U(..) => "i32.atomic.load16_u",
+            Self::I64AtomicLoad8U(..) => "i64.atomic.load8_u",
+            Self::I64AtomicLoad16U(..) => "i64.atomic.load16_u",
+            Self::I64AtomicLoad32U(..) => "i64.atomic.load32_u",
+            Self::I32AtomicStore(..) => "i32.atomic.store",
+            Self::I64AtomicStore(..) => "i64.atomic.store",
+            Self::I32AtomicStore8(..) => "i32.atomic.store8",
+            Self::I32AtomicStore16(..) => "i32.atomic.store16",
+            Self::I64AtomicStore8(..) => "i64.atomic.store8",
+            Self::I64AtomicStore16(..) => "i64.atomic.store16",
+            Self::I64AtomicStore32(..) => "i64.atomic.store32",
+            Self::I32AtomicRmwAdd(..) => "i32.atomic.rmw.add",
+            Self::I64AtomicRmwAdd(..) => "i64.atomic.rmw.add",
+            Self::I32AtomicRmw8AddU(..) => "i32.atomic.rmw8.add_u",
+            Self::I32AtomicRmw16AddU(..) => "i32.atomic.rmw16.add_u",
+            Self::I64AtomicRmw8AddU(..) => "i64.atomic.rmw8.add_u",
+            Self::I64AtomicRmw16AddU(..) => "i64.atomic.rmw16.add_u",
+            Self::I64AtomicRmw32AddU(..) => "i64.atomic.rmw32.add_u",
+            Self::I32AtomicRmwSub(..) => "i32.atomic.rmw.sub",
+            Self::I64AtomicRmwSub(..) => "i64.atomic.rmw.sub",
+            Self::I32AtomicRmw8SubU(..) => "i32.atomic.rmw8.sub_u",
+            Self::I32AtomicRmw16SubU(..) => "i32.atomic.rmw16.sub_u",
+            Self::I64AtomicRmw8SubU(..) => "i64.atomic.rmw8.sub_u",
+            Self::I64AtomicRmw16SubU(..) => "i64.atomic.rmw16.sub_u",
+            Self::I64AtomicRmw32SubU(..) => "i64.atomic.rmw32.sub_u",
+            Self::I32AtomicRmwAnd(..) => "i32.atomic.rmw.and",
+            Self::I64AtomicRmwAnd(..) => "i64.atomic.rmw.and",
+            Self::I32AtomicRmw8AndU(..) => "i32.atomic.rmw8.and_u",
+            Self::I32AtomicRmw16AndU(..) => "i32.atomic.rmw16.and_u",
+            Self::I64AtomicRmw8AndU(..) => "i64.atomic.rmw8.and_u",
+            Self::I64AtomicRmw16AndU(..) => "i64.atomic.rmw16.and_u",
+            Self::I64AtomicRmw32AndU(..) => "i64.atomic.rmw32.and_u",
+            Self::I32AtomicRmwOr(..) => "i32.atomic.rmw.or",
+            Self::I64AtomicRmwOr(..) => "i64.atomic.rmw.or",
+            Self::I32AtomicRmw8OrU(..) => "i32.atomic.rmw8.or_u",
+            Self::I32AtomicRmw16OrU(..) => "i32.atomic.rmw16.or_u",
+            Self::I64AtomicRmw8OrU(..) => "i64.atomic.rmw8.or_u",
+            Self::I64AtomicRmw16OrU(..) => "i64.atomic.rmw16.or_u",
+            Self::I64AtomicRmw32OrU(..) => "i64.atomic.rmw32.or_u",
+            Self::I32AtomicRmwXor(..) => "i32.atomic.rmw.xor",
+            Self::I64AtomicRmwXor(..) => "i64.atomic.rmw.xor",
+            Self::I32AtomicRmw8XorU(..) => "i32.atomic.rmw8.xor_u",
+            Self::I32AtomicRmw16XorU(..) => "i32.atomic.rmw16.xor_u",
+            Self::I64AtomicRmw8XorU(..) => "i64.atomic.rmw8.xor_u",
+            Self::I64AtomicRmw16XorU(..) => "i64.atomic.rmw16.xor_u",
+            Self::I64AtomicRmw32XorU(..) => "i64.atomic.rmw32.xor_u",
+            Self::I32AtomicRmwXchg(..) => "i32.atomic.rmw.xchg",
+            Self::I64AtomicRmwXchg(..) => "i64.atomic.rmw.xchg",
+            Self::I32AtomicRmw8XchgU(..) => "i32.atomic.rmw8.xchg_u",
+            Self::I32AtomicRmw16XchgU(..) => "i32.atomic.rmw16.xchg_u",
+            Self::I64AtomicRmw8XchgU(..) => "i64.atomic.rmw8.xchg_u",
+            Self::I64AtomicRmw16XchgU(..) => "i64.atomic.rmw16.xchg_u",
+            Self::I64AtomicRmw32XchgU(..) => "i64.atomic.rmw32.xchg_u",
+            Self::I32AtomicRmwCmpxchg(..) => "i32.atomic.rmw.cmpxchg",
+            Self::I64AtomicRmwCmpxchg(..) => "i64.atomic.rmw.cmpxchg",
+            Self::I32AtomicRmw8CmpxchgU(..) => "i32.atomic.rmw8.cmpxchg_u",
+            Self::I32AtomicRmw16CmpxchgU(..) => "i32.atomic.rmw16.cmpxchg_u",
+            Self::I64AtomicRmw8CmpxchgU(..) => "i64.atomic.rmw8.cmpxchg_u",
+            Self::I64AtomicRmw16CmpxchgU(..) => "i64.atomic.rmw16.cmpxchg_u",
+            Self::I64AtomicRmw32CmpxchgU(..) => "i64.atomic.rmw32.cmpxchg_u",
+        }
+    }
+}
+
+impl Decode for AtomicInstruction {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, _) = tag([0xFE])(input)?;
+        let (input, opcode) = u32::decode(input)?;
+        match opcode {
+            0x00 => map(MemArg::decode, Self::MemoryAtomicNotify)(input),
+            0x01 => map(MemArg::decode, Self::MemoryAtomicWait32)(input),
+            0x02 => map(MemArg::decode, Self::MemoryAtomicWait64)(input),
+            0x03 => map(tag([0x00]), |_| Self::AtomicFence)(input),
+            0x10 => map(MemArg::decode, Self::I32AtomicLoad)(input),
+            0x11 => map(MemArg::decode, Self::I64AtomicLoad)(input),
+            0x12 => map(MemArg::decode, Self::I32AtomicLoad8U)(input),
+            0x13 => map(MemArg::decode, Self::I32AtomicLoad16U)(input),
+            0x14 => map(MemArg::decode, Self::I64AtomicLoad8U)(input),
+            0x15 => map(MemArg::decode, Self::I64AtomicLoad16U)(input),
+            0x16 => map(MemArg::decode, Self::I64AtomicLoad32U)(input),
+            0x17 => map(MemArg::decode, Self::I32AtomicStore)(input),
+            0x18 => map(MemArg::decode, Self::I64AtomicStore)(input),
+            0x19 => map(MemArg::decode, Self::I32AtomicStore8)(input),
+            0x1A => map(MemArg::decode, Self::I32AtomicStore16)(input),
+            0x1B => map(MemArg::decode, Self::I64AtomicStore8)(input),
+            0x1C => map(MemArg::decode, Self::I64AtomicStore16)(input),
+            0x1D => map(MemArg::decode, Self::I64AtomicStore32)(input),
+            0x1E => map(MemArg::decode, Self::I32AtomicRmwAdd)(input),
+            0x1F => map(MemArg::decode, Self::I64AtomicRmwAdd)(input),
+            0x20 => map(MemArg::decode, Self::I32AtomicRmw8AddU)(input),
+            0x21 => map(MemArg::decode, Self::I32AtomicRmw16AddU)(input),
+            0x22 => map(MemArg::decode, Self::I64AtomicRmw8AddU)(input),
+            0x23 => map(MemArg::decode, Self::I64AtomicRmw16AddU)(input),
+            0x24 => map(MemArg::decode, Self::I64AtomicRmw32AddU)(input),
+            0x25 => map(MemArg::decode, Self::I32AtomicRmwSub)(input),
+            0x26 => map(MemArg::decode, Self::I64AtomicRmwSub)(input),
+            0x27 => map(MemArg::decode, Self::I32AtomicRmw8SubU)(input),
+            0x28 => map(MemArg::decode, Self::I32AtomicRmw16SubU)(input),
+            0x29 => map(MemArg::decode, Self::I64AtomicRmw8SubU)(input),
+            0x2A => map(MemArg::decode, Self::I64AtomicRmw16SubU)(input),
+            0x2B => map(MemArg::decode, Self::I64AtomicRmw32SubU)(input),
+            0x2C => map(MemArg::decode, Self::I32AtomicRmwAnd)(input),
+            0x2D => map(MemArg::decode, Self::I64AtomicRmwAnd)(input),
+            0x2E => map(MemArg::decode, Self::I32AtomicRmw8AndU)(input),
+            0x2F => map(MemArg::decode, Self::I32AtomicRmw16AndU)(input),
+            0x30 => map(MemArg::decode, Self::I64AtomicRmw8AndU)(input),
+            0x31 => map(MemArg::decode, Self::I64AtomicRmw16AndU)(input),
+            0x32 => map(MemArg::decode, Self::I64AtomicRmw32AndU)(input),
+            0x33 => map(MemArg::decode, Self::I32AtomicRmwOr)(input),
+            0x34 => map(MemArg::decode, Self::I64AtomicRmwOr)(input),
+            0x35 => map(MemArg::decode, Self::I32AtomicRmw8OrU)(input),
+            0x36 => map(MemArg::decode, Self::I32AtomicRmw16OrU)(input),
+            0x37 => map(MemArg::decode, Self::I64AtomicRmw8OrU)(input),
+            0x38 => map(MemArg::decode, Self::I64AtomicRmw16OrU)(input),
+            0x39 => map(MemArg::decode, Self::I64AtomicRmw32OrU)(input),
+            0x3A => map(MemArg::decode, Self::I32AtomicRmwXor)(input),
+            0x3B => map(MemArg::decode, Self::I64AtomicRmwXor)(input),
+            0x3C => map(MemArg::decode, Self::I32AtomicRmw8XorU)(input),
+            0x3D => map(MemArg::decode, Self::I32AtomicRmw16XorU)(input),
+            0x3E => map(MemArg::decode, Self::I64AtomicRmw8XorU)(input),
+            0x3F => map(MemArg::decode, Self::I64AtomicRmw16XorU)(input),
+            0x40 => map(MemArg::decode, Self::I64AtomicRmw32XorU)(input),
+            0x41 => map(MemArg::decode, Self::I32AtomicRmwXchg)(input),
+            0x42 => map(MemArg::decode, Self::I64AtomicRmwXchg)(input),
+            0x43 => map(MemArg::decode, Self::I32AtomicRmw8XchgU)(input),
+            0x44 => map(MemArg::decode, Self::I32AtomicRmw16XchgU)(input),
+            0x45 => map(MemArg::decode, Self::I64AtomicRmw8XchgU)(input),
+            0x46 => map(MemArg::decode, Self::I64AtomicRmw16XchgU)(input),
+            0x47 => map(MemArg::decode, Self::I64AtomicRmw32XchgU)(input),
+            0x48 => map(MemArg::decode, Self::I32AtomicRmwCmpxchg)(input),
+            0x49 => map(MemArg::decode, Self::I64AtomicRmwCmpxchg)(input),
+            0x4A => map(MemArg::decode, Self::I32AtomicRmw8CmpxchgU)(input),
+            0x4B => map(MemArg::decode, Self::I32AtomicRmw16CmpxchgU)(input),
+            0x4C => map(MemArg::decode, Self::I64AtomicRmw8CmpxchgU)(input),
+            0x4D => map(MemArg::decode, Self::I64AtomicRmw16CmpxchgU)(input),
+            0x4E => map(MemArg::decode, Self::I64AtomicRmw32CmpxchgU)(input),
+            _ => fail(input),
+        }
+    }
+}
+
+impl AtomicInstruction {
+    /// The opcode that follows the 0xFE prefix
+    fn opcode(&self) -> u32 {
+        match self {
+            Self::MemoryAtomicNotify(..) => 0x00,
+            Self::MemoryAtomicWait32(..) => 0x01,
+            Self::MemoryAtomicWait64(..) => 0x02,
+            Self::AtomicFence => 0x03,
+            Self::I32AtomicLoad(..) => 0x10,
+            Self::I64AtomicLoad(..) => 0x11,
+            Self::I32AtomicLoad8U(..) => 0x12,
+            Self::I32AtomicLoad16U(..) => 0x13,
+            Self::I64AtomicLoad8U(..) => 0x14,
+            Self::I64AtomicLoad16U(..) => 0x15,
+            Self::I64AtomicLoad32U(..) => 0x16,
+            Self::I32AtomicStore(..) => 0x17,
+            Self::I64AtomicStore(..) => 0x18,
+            Self::I32AtomicStore8(..) => 0x19,
+            Self::I32AtomicStore16(..) => 0x1A,
+            Self::I64AtomicStore8(..) => 0x1B,
+            Self::I64AtomicStore16(..) => 0x1C,
+            Self::I64AtomicStore32(..) => 0x1D,
+            Self::I32AtomicRmwAdd(..) => 0x1E,
+            Self::I64AtomicRmwAdd(..) => 0x1F,
+            Self::I32AtomicRmw8AddU(..) => 0x20,
+            Self::I32AtomicRmw16AddU(..) => 0x21,
+            Self::I64AtomicRmw8AddU(..) => 0x22,
+            Self::I64AtomicRmw16AddU(..) => 0x23,
+            Self::I64AtomicRmw32AddU(..) => 0x24,
+            Self::I32AtomicRmwSub(..) => 0x25,
+            Self::I64AtomicRmwSub(..) => 0x26,
+            Self::I32AtomicRmw8SubU(..) => 0x27,
+            Self::I32AtomicRmw16SubU(..) => 0x28,
+            Self::I64AtomicRmw8SubU(..) => 0x29,
+            Self::I64AtomicRmw16SubU(..) => 0x2A,
+            Self::I64AtomicRmw32SubU(..) => 0x2B,
+            Self::I32AtomicRmwAnd(..) => 0x2C,
+            Self::I64AtomicRmwAnd(..) => 0x2D,
+            Self::I32AtomicRmw8AndU(..) => 0x2E,
+            Self::I32AtomicRmw16AndU(..) => 0x2F,
+            Self::I64AtomicRmw8AndU(..) => 0x30,
+            Self::I64AtomicRmw16AndU(..) => 0x31,
+            Self::I64AtomicRmw32AndU(..) => 0x32,
+            Self::I32AtomicRmwOr(..) => 0x33,
+            Self::I64AtomicRmwOr(..) => 0x34,
+            Self::I32AtomicRmw8OrU(..) => 0x35,
+            Self::I32AtomicRmw16OrU(..) => 0x36,
+            Self::I64AtomicRmw8OrU(..) => 0x37,
+            Self::I64AtomicRmw16OrU(..) => 0x38,
+            Self::I64AtomicRmw32OrU(..) => 0x39,
+            Self::I32AtomicRmwXor(..) => 0x3A,
+            Self::I64AtomicRmwXor(..) => 0x3B,
+            Self::I32AtomicRmw8XorU(..) => 0x3C,
+            Self::I32AtomicRmw16XorU(..) => 0x3D,
+            Self::I64AtomicRmw8XorU(..) => 0x3E,
+            Self::I64AtomicRmw16XorU(..) => 0x3F,
+            Self::I64AtomicRmw32XorU(..) => 0x40,
+            Self::I32AtomicRmwXchg(..) => 0x41,
+            Self::I64AtomicRmwXchg(..) => 0x42,
+            Self::I32AtomicRmw8XchgU(..) => 0x43,
+            Self::I32AtomicRmw16XchgU(..) => 0x44,
+            Self::I64AtomicRmw8XchgU(..) => 0x45,
+            Self::I64AtomicRmw16XchgU(..) => 0x46,
+            Self::I64AtomicRmw32XchgU(..) => 0x47,
+            Self::I32AtomicRmwCmpxchg(..) => 0x48,
+            Self::I64AtomicRmwCmpxchg(..) => 0x49,
+            Self::I32AtomicRmw8CmpxchgU(..) => 0x4A,
+            Self::I32AtomicRmw16CmpxchgU(..) => 0x4B,
+            Self::I64AtomicRmw8CmpxchgU(..) => 0x4C,
+            Self::I64AtomicRmw16CmpxchgU(..) => 0x4D,
+            Self::I64AtomicRmw32CmpxchgU(..) => 0x4E,
+        }
+    }
+}
+
+impl Encode for AtomicInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(0xFE);
+        self.opcode().encode(out);
+        match self.access() {
+            Some((.., memarg)) => memarg.encode(out),
+            // The only instruction without a memarg is atomic.fence, whose immediate is a reserved zero byte
+            None => out.push(0x00),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY: &[u8] = &[];
+
+    #[test]
+    fn test_atomic_instruction() {
+        assert_eq!(
+            AtomicInstruction::decode(&[0xFE, 0x1E, 0x02, 0x08]),
+            Ok((
+                EMPTY,
+                AtomicInstruction::I32AtomicRmwAdd(MemArg {
+                    align: 2,
+                    offset: 8,
+                    memidx: 0
+                })
+            ))
+        );
+        assert_eq!(
+            AtomicInstruction::decode(&[0xFE, 0x4E, 0x03, 0x00]),
+            Ok((
+                EMPTY,
+                AtomicInstruction::I64AtomicRmw32CmpxchgU(MemArg {
+                    align: 3,
+                    offset: 0,
+                    memidx: 0
+                })
+            ))
+        );
+        assert_eq!(
+            AtomicInstruction::decode(&[0xFE, 0x03, 0x00]),
+            Ok((EMPTY, AtomicInstruction::AtomicFence))
+        );
+        assert!(AtomicInstruction::decode(&[0xFE, 0x03, 0x01]).is_err());
+        assert!(AtomicInstruction::decode(&[0xFE, 0x04]).is_err());
+        assert!(AtomicInstruction::decode(&[0xFD, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_access() {
+        let memarg = MemArg {
+            align: 0,
+            offset: 0,
+            memidx: 0,
+        };
+        assert_eq!(
+            AtomicInstruction::I64AtomicRmw16XorU(memarg.clone()).access(),
+            Some((AtomicOp::Rmw(RmwOp::Xor), NumType::I64, 2, &memarg))
+        );
+        assert_eq!(
+            AtomicInstruction::MemoryAtomicWait64(memarg.clone()).access(),
+            Some((AtomicOp::Wait, NumType::I64, 8, &memarg))
+        );
+        assert_eq!(AtomicInstruction::AtomicFence.access(), None);
+    }
+}
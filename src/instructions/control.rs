@@ -0,0 +1,484 @@
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    combinator::{map, verify},
+    multi::many_till,
+    sequence::{pair, tuple},
+    IResult,
+};
+use nom_leb128::leb128_i64;
+
+use crate::{
+    module::{
+        indices::{FuncIdx, LabelIdx, TableIdx, TagIdx, TypeIdx},
+        types::ValType,
+    },
+    Decode, Encode,
+};
+
+use super::Instruction;
+
+/// The type of a block, loop or if, which is either a shorthand for no parameters and at most one result, or a
+/// reference to a function type in the type section for any number of parameters and results
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BlockType {
+    Empty,
+    ValType(ValType),
+    TypeIdx(TypeIdx),
+}
+
+impl Decode for BlockType {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        alt((
+            map(tag([0x40]), |_| Self::Empty),
+            map(ValType::decode, Self::ValType),
+            // The type index is encoded as a positive signed 33-bit integer so that it can't be confused with the
+            // single byte encodings above, which are negative when read as signed integers
+            map(
+                verify(leb128_i64, |typeidx| {
+                    (0..=u32::MAX as i64).contains(typeidx)
+                }),
+                |typeidx| Self::TypeIdx(typeidx as TypeIdx),
+            ),
+        ))(input)
+    }
+}
+
+impl Encode for BlockType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Empty => out.push(0x40),
+            Self::ValType(t) => t.encode(out),
+            Self::TypeIdx(typeidx) => (*typeidx as i64).encode(out),
+        }
+    }
+}
+
+/// A handler of a `try_table`, which branches to a label when an exception with a matching tag is thrown inside it
+///
+/// The `Ref` variants also pass the caught exception to the label as an `exnref` that can be rethrown.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CatchClause {
+    Catch(TagIdx, LabelIdx),
+    CatchRef(TagIdx, LabelIdx),
+    CatchAll(LabelIdx),
+    CatchAllRef(LabelIdx),
+}
+
+impl Decode for CatchClause {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        alt((
+            map(
+                tuple((tag([0x00]), TagIdx::decode, LabelIdx::decode)),
+                |tuple| Self::Catch(tuple.1, tuple.2),
+            ),
+            map(
+                tuple((tag([0x01]), TagIdx::decode, LabelIdx::decode)),
+                |tuple| Self::CatchRef(tuple.1, tuple.2),
+            ),
+            map(pair(tag([0x02]), LabelIdx::decode), |pair| {
+                Self::CatchAll(pair.1)
+            }),
+            map(pair(tag([0x03]), LabelIdx::decode), |pair| {
+                Self::CatchAllRef(pair.1)
+            }),
+        ))(input)
+    }
+}
+
+impl Encode for CatchClause {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Catch(tagidx, labelidx) => {
+                out.push(0x00);
+                tagidx.encode(out);
+                labelidx.encode(out);
+            }
+            Self::CatchRef(tagidx, labelidx) => {
+                out.push(0x01);
+                tagidx.encode(out);
+                labelidx.encode(out);
+            }
+            Self::CatchAll(labelidx) => {
+                out.push(0x02);
+                labelidx.encode(out);
+            }
+            Self::CatchAllRef(labelidx) => {
+                out.push(0x03);
+                labelidx.encode(out);
+            }
+        }
+    }
+}
+
+/// TODO: Document
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ControlInstruction {
+    Unreachable,
+    Nop,
+    Block(BlockType, Vec<Instruction>),
+    Loop(BlockType, Vec<Instruction>),
+    If(BlockType, Vec<Instruction>, Vec<Instruction>),
+    TryTable(BlockType, Vec<CatchClause>, Vec<Instruction>),
+    Throw(TagIdx),
+    ThrowRef,
+    Br(LabelIdx),
+    BrIf(LabelIdx),
+    BrTable(Vec<LabelIdx>, LabelIdx),
+    BrOnNull(LabelIdx),
+    BrOnNonNull(LabelIdx),
+    Return,
+    Call(FuncIdx),
+    CallIndirect(TypeIdx, TableIdx),
+    CallRef(TypeIdx),
+    ReturnCall(FuncIdx),
+    ReturnCallIndirect(TypeIdx, TableIdx),
+    ReturnCallRef(TypeIdx),
+}
+
+impl ControlInstruction {
+    /// The name of the instruction in the text format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Unreachable => "unreachable",
+            Self::Nop => "nop",
+            Self::Block(..) => "block",
+            Self::Loop(..) => "loop",
+            Self::If(..) => "if",
+            Self::TryTable(..) => "try_table",
+            Self::Throw(_) => "throw",
+            Self::ThrowRef => "throw_ref",
+            Self::Br(_) => "br",
+            Self::BrIf(_) => "br_if",
+            Self::BrTable(..) => "br_table",
+            Self::BrOnNull(_) => "br_on_null",
+            Self::BrOnNonNull(_) => "br_on_non_null",
+            Self::Return => "return",
+            Self::Call(_) => "call",
+            Self::CallIndirect(..) => "call_indirect",
+            Self::CallRef(_) => "call_ref",
+            Self::ReturnCall(_) => "return_call",
+            Self::ReturnCallIndirect(..) => "return_call_indirect",
+            Self::ReturnCallRef(_) => "return_call_ref",
+        }
+    }
+}
+
+impl Decode for ControlInstruction {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        alt((
+            map(tag([0x00]), |_| Self::Unreachable),
+            map(tag([0x01]), |_| Self::Nop),
+            map(
+                tuple((tag([0x02]), BlockType::decode, instructions)),
+                |tuple| Self::Block(tuple.1, tuple.2),
+            ),
+            map(
+                tuple((tag([0x03]), BlockType::decode, instructions)),
+                |tuple| Self::Loop(tuple.1, tuple.2),
+            ),
+            if_else,
+            map(
+                tuple((
+                    tag([0x1F]),
+                    BlockType::decode,
+                    Vec::<CatchClause>::decode,
+                    instructions,
+                )),
+                |tuple| Self::TryTable(tuple.1, tuple.2, tuple.3),
+            ),
+            map(pair(tag([0x08]), TagIdx::decode), |pair| {
+                Self::Throw(pair.1)
+            }),
+            map(tag([0x0A]), |_| Self::ThrowRef),
+            map(pair(tag([0x0C]), LabelIdx::decode), |pair| Self::Br(pair.1)),
+            map(pair(tag([0x0D]), LabelIdx::decode), |pair| {
+                Self::BrIf(pair.1)
+            }),
+            map(
+                tuple((tag([0x0E]), Vec::<LabelIdx>::decode, LabelIdx::decode)),
+                |tuple| Self::BrTable(tuple.1, tuple.2),
+            ),
+            map(pair(tag([0xD5]), LabelIdx::decode), |pair| {
+                Self::BrOnNull(pair.1)
+            }),
+            map(pair(tag([0xD6]), LabelIdx::decode), |pair| {
+                Self::BrOnNonNull(pair.1)
+            }),
+            map(tag([0x0F]), |_| Self::Return),
+            map(pair(tag([0x10]), FuncIdx::decode), |pair| {
+                Self::Call(pair.1)
+            }),
+            map(
+                tuple((tag([0x11]), TypeIdx::decode, TableIdx::decode)),
+                |tuple| Self::CallIndirect(tuple.1, tuple.2),
+            ),
+            map(pair(tag([0x14]), TypeIdx::decode), |pair| {
+                Self::CallRef(pair.1)
+            }),
+            map(pair(tag([0x12]), FuncIdx::decode), |pair| {
+                Self::ReturnCall(pair.1)
+            }),
+            map(
+                tuple((tag([0x13]), TypeIdx::decode, TableIdx::decode)),
+                |tuple| Self::ReturnCallIndirect(tuple.1, tuple.2),
+            ),
+            map(pair(tag([0x15]), TypeIdx::decode), |pair| {
+                Self::ReturnCallRef(pair.1)
+            }),
+        ))(input)
+    }
+}
+
+impl Encode for ControlInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Unreachable => out.push(0x00),
+            Self::Nop => out.push(0x01),
+            Self::Block(bt, body) => {
+                out.push(0x02);
+                bt.encode(out);
+                encode_instructions(body, out);
+            }
+            Self::Loop(bt, body) => {
+                out.push(0x03);
+                bt.encode(out);
+                encode_instructions(body, out);
+            }
+            Self::If(bt, then, otherwise) => {
+                out.push(0x04);
+                bt.encode(out);
+                then.iter().for_each(|instruction| instruction.encode(out));
+                if !otherwise.is_empty() {
+                    out.push(0x05);
+                    otherwise
+                        .iter()
+                        .for_each(|instruction| instruction.encode(out));
+                }
+                out.push(0x0B);
+            }
+            Self::TryTable(bt, catches, body) => {
+                out.push(0x1F);
+                bt.encode(out);
+                catches.encode(out);
+                encode_instructions(body, out);
+            }
+            Self::Throw(tagidx) => {
+                out.push(0x08);
+                tagidx.encode(out);
+            }
+            Self::ThrowRef => out.push(0x0A),
+            Self::Br(labelidx) => {
+                out.push(0x0C);
+                labelidx.encode(out);
+            }
+            Self::BrIf(labelidx) => {
+                out.push(0x0D);
+                labelidx.encode(out);
+            }
+            Self::BrTable(labels, default) => {
+                out.push(0x0E);
+                labels.encode(out);
+                default.encode(out);
+            }
+            Self::BrOnNull(labelidx) => {
+                out.push(0xD5);
+                labelidx.encode(out);
+            }
+            Self::BrOnNonNull(labelidx) => {
+                out.push(0xD6);
+                labelidx.encode(out);
+            }
+            Self::Return => out.push(0x0F),
+            Self::Call(funcidx) => {
+                out.push(0x10);
+                funcidx.encode(out);
+            }
+            Self::CallIndirect(typeidx, tableidx) => {
+                out.push(0x11);
+                typeidx.encode(out);
+                tableidx.encode(out);
+            }
+            Self::CallRef(typeidx) => {
+                out.push(0x14);
+                typeidx.encode(out);
+            }
+            Self::ReturnCall(funcidx) => {
+                out.push(0x12);
+                funcidx.encode(out);
+            }
+            Self::ReturnCallIndirect(typeidx, tableidx) => {
+                out.push(0x13);
+                typeidx.encode(out);
+                tableidx.encode(out);
+            }
+            Self::ReturnCallRef(typeidx) => {
+                out.push(0x15);
+                typeidx.encode(out);
+            }
+        }
+    }
+}
+
+/// Decode the instructions of a block up to and including the `end` that closes it
+fn instructions(input: &[u8]) -> IResult<&[u8], Vec<Instruction>> {
+    map(many_till(Instruction::decode, tag([0x0B])), |pair| pair.0)(input)
+}
+
+/// Encode the instructions of a block followed by the `end` that closes it
+fn encode_instructions(instructions: &[Instruction], out: &mut Vec<u8>) {
+    instructions
+        .iter()
+        .for_each(|instruction| instruction.encode(out));
+    out.push(0x0B);
+}
+
+/// Decode an `if` with an optional `else` branch
+fn if_else(input: &[u8]) -> IResult<&[u8], ControlInstruction> {
+    let (input, _) = tag([0x04])(input)?;
+    let (input, bt) = BlockType::decode(input)?;
+    let (input, (then, end)) =
+        many_till(Instruction::decode, alt((tag([0x05]), tag([0x0B]))))(input)?;
+    let (input, otherwise) = match end {
+        [0x05] => instructions(input)?,
+        _ => (input, Vec::new()),
+    };
+    Ok((input, ControlInstruction::If(bt, then, otherwise)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{instructions::NumericInstruction, module::types::NumType};
+
+    use super::*;
+
+    const EMPTY: &[u8] = &[];
+
+    #[test]
+    fn test_block_type() {
+        assert_eq!(BlockType::decode(&[0x40]), Ok((EMPTY, BlockType::Empty)));
+        assert_eq!(
+            BlockType::decode(&[0x7F]),
+            Ok((EMPTY, BlockType::ValType(ValType::NumType(NumType::I32))))
+        );
+        assert_eq!(
+            BlockType::decode(&[0x02]),
+            Ok((EMPTY, BlockType::TypeIdx(2)))
+        );
+        assert_eq!(
+            BlockType::decode(&[0x80, 0x01]),
+            Ok((EMPTY, BlockType::TypeIdx(128)))
+        );
+        assert!(BlockType::decode(&[0x41]).is_err());
+    }
+
+    #[test]
+    fn test_catch_clause() {
+        assert_eq!(
+            CatchClause::decode(&[0x00, 0x01, 0x02]),
+            Ok((EMPTY, CatchClause::Catch(1, 2)))
+        );
+        assert_eq!(
+            CatchClause::decode(&[0x01, 0x01, 0x02]),
+            Ok((EMPTY, CatchClause::CatchRef(1, 2)))
+        );
+        assert_eq!(
+            CatchClause::decode(&[0x02, 0x03]),
+            Ok((EMPTY, CatchClause::CatchAll(3)))
+        );
+        assert_eq!(
+            CatchClause::decode(&[0x03, 0x03]),
+            Ok((EMPTY, CatchClause::CatchAllRef(3)))
+        );
+        assert!(CatchClause::decode(&[0x04, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_control_instruction() {
+        assert_eq!(
+            ControlInstruction::decode(&[0x02, 0x01, 0x41, 0x01, 0x0C, 0x00, 0x0B]),
+            Ok((
+                EMPTY,
+                ControlInstruction::Block(
+                    BlockType::TypeIdx(1),
+                    vec!(
+                        Instruction::Numeric(NumericInstruction::I32Const(1)),
+                        Instruction::Control(ControlInstruction::Br(0))
+                    )
+                )
+            ))
+        );
+        assert_eq!(
+            ControlInstruction::decode(&[0x04, 0x40, 0x01, 0x05, 0x00, 0x0B]),
+            Ok((
+                EMPTY,
+                ControlInstruction::If(
+                    BlockType::Empty,
+                    vec!(Instruction::Control(ControlInstruction::Nop)),
+                    vec!(Instruction::Control(ControlInstruction::Unreachable))
+                )
+            ))
+        );
+        assert_eq!(
+            ControlInstruction::decode(&[0x04, 0x40, 0x0B]),
+            Ok((
+                EMPTY,
+                ControlInstruction::If(BlockType::Empty, vec!(), vec!())
+            ))
+        );
+        assert_eq!(
+            ControlInstruction::decode(&[0x0E, 0x02, 0x00, 0x01, 0x02]),
+            Ok((EMPTY, ControlInstruction::BrTable(vec!(0, 1), 2)))
+        );
+        assert_eq!(
+            ControlInstruction::decode(&[0x11, 0x01, 0x00]),
+            Ok((EMPTY, ControlInstruction::CallIndirect(1, 0)))
+        );
+        assert_eq!(
+            ControlInstruction::decode(&[0x12, 0x03]),
+            Ok((EMPTY, ControlInstruction::ReturnCall(3)))
+        );
+        assert_eq!(
+            ControlInstruction::decode(&[0x13, 0x01, 0x00]),
+            Ok((EMPTY, ControlInstruction::ReturnCallIndirect(1, 0)))
+        );
+        assert_eq!(
+            ControlInstruction::decode(&[
+                0x1F, 0x40, 0x02, 0x00, 0x01, 0x00, 0x03, 0x01, 0x08, 0x01, 0x0B
+            ]),
+            Ok((
+                EMPTY,
+                ControlInstruction::TryTable(
+                    BlockType::Empty,
+                    vec!(CatchClause::Catch(1, 0), CatchClause::CatchAllRef(1)),
+                    vec!(Instruction::Control(ControlInstruction::Throw(1)))
+                )
+            ))
+        );
+        assert_eq!(
+            ControlInstruction::decode(&[0x0A]),
+            Ok((EMPTY, ControlInstruction::ThrowRef))
+        );
+        assert_eq!(
+            ControlInstruction::decode(&[0x14, 0x02]),
+            Ok((EMPTY, ControlInstruction::CallRef(2)))
+        );
+        assert_eq!(
+            ControlInstruction::decode(&[0x15, 0x02]),
+            Ok((EMPTY, ControlInstruction::ReturnCallRef(2)))
+        );
+        assert_eq!(
+            ControlInstruction::decode(&[0xD5, 0x01]),
+            Ok((EMPTY, ControlInstruction::BrOnNull(1)))
+        );
+        assert_eq!(
+            ControlInstruction::decode(&[0xD6, 0x00]),
+            Ok((EMPTY, ControlInstruction::BrOnNonNull(0)))
+        );
+        assert!(ControlInstruction::decode(&[0x02, 0x40, 0x01]).is_err());
+        assert!(ControlInstruction::decode(&[0xFF]).is_err());
+    }
+}
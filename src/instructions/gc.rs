@@ -0,0 +1,323 @@
+use nom::{
+    bytes::complete::tag,
+    combinator::{fail, map, verify},
+    sequence::{pair, tuple},
+    IResult,
+};
+
+use crate::{
+    module::{
+        indices::{DataIdx, ElemIdx, FieldIdx, LabelIdx, TypeIdx},
+        types::{HeapType, RefType},
+    },
+    Decode, Encode,
+};
+
+/// An instruction from the garbage collection proposal, encoded behind the 0xFB prefix
+///
+/// These allocate and access structs and arrays on the heap, cast references and convert between the `any` and
+/// `extern` hierarchies and unboxed 31-bit integers.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GcInstruction {
+    StructNew(TypeIdx),
+    StructNewDefault(TypeIdx),
+    StructGet(TypeIdx, FieldIdx),
+    StructGetS(TypeIdx, FieldIdx),
+    StructGetU(TypeIdx, FieldIdx),
+    StructSet(TypeIdx, FieldIdx),
+    ArrayNew(TypeIdx),
+    ArrayNewDefault(TypeIdx),
+    ArrayNewFixed(TypeIdx, u32),
+    ArrayNewData(TypeIdx, DataIdx),
+    ArrayNewElem(TypeIdx, ElemIdx),
+    ArrayGet(TypeIdx),
+    ArrayGetS(TypeIdx),
+    ArrayGetU(TypeIdx),
+    ArraySet(TypeIdx),
+    ArrayLen,
+    ArrayFill(TypeIdx),
+    ArrayCopy(TypeIdx, TypeIdx),
+    ArrayInitData(TypeIdx, DataIdx),
+    ArrayInitElem(TypeIdx, ElemIdx),
+    RefTest(RefType),
+    RefCast(RefType),
+    BrOnCast(LabelIdx, RefType, RefType),
+    BrOnCastFail(LabelIdx, RefType, RefType),
+    AnyConvertExtern,
+    ExternConvertAny,
+    RefI31,
+    I31GetS,
+    I31GetU,
+}
+
+/// Decode the operands of `br_on_cast` and `br_on_cast_fail`, whose flags say which of the two types are nullable
+fn cast_operands(input: &[u8]) -> IResult<&[u8], (LabelIdx, RefType, RefType)> {
+    map(
+        tuple((
+            verify(u8::decode, |flags| *flags <= 0x03),
+            LabelIdx::decode,
+            HeapType::decode,
+            HeapType::decode,
+        )),
+        |(flags, labelidx, ht1, ht2)| {
+            (
+                labelidx,
+                RefType {
+                    nullable: flags & 0x01 != 0,
+                    ht: ht1,
+                },
+                RefType {
+                    nullable: flags & 0x02 != 0,
+                    ht: ht2,
+                },
+            )
+        },
+    )(input)
+}
+
+impl GcInstruction {
+    /// The name of the instruction in the text format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::StructNew(_) => "struct.new",
+            Self::StructNewDefault(_) => "struct.new_default",
+            Self::StructGet(..) => "struct.get",
+            Self::StructGetS(..) => "struct.get_s",
+            Self::StructGetU(..) => "struct.get_u",
+            Self::StructSet(..) => "struct.set",
+            Self::ArrayNew(_) => "array.new",
+            Self::ArrayNewDefault(_) => "array.new_default",
+            Self::ArrayNewFixed(..) => "array.new_fixed",
+            Self::ArrayNewData(..) => "array.new_data",
+            Self::ArrayNewElem(..) => "array.new_elem",
+            Self::ArrayGet(_) => "array.get",
+            Self::ArrayGetS(_) => "array.get_s",
+            Self::ArrayGetU(_) => "array.get_u",
+            Self::ArraySet(_) => "array.set",
+            Self::ArrayLen => "array.len",
+            Self::ArrayFill(_) => "array.fill",
+            Self::ArrayCopy(..) => "array.copy",
+            Self::ArrayInitData(..) => "array.init_data",
+            Self::ArrayInitElem(..) => "array.init_elem",
+            Self::RefTest(_) => "ref.test",
+            Self::RefCast(_) => "ref.cast",
+            Self::BrOnCast(..) => "br_on_cast",
+            Self::BrOnCastFail(..) => "br_on_cast_fail",
+            Self::AnyConvertExtern => "any.convert_extern",
+            Self::ExternConvertAny => "extern.convert_any",
+            Self::RefI31 => "ref.i31",
+            Self::I31GetS => "i31.get_s",
+            Self::I31GetU => "i31.get_u",
+        }
+    }
+}
+
+impl Decode for GcInstruction {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, _) = tag([0xFB])(input)?;
+        let (input, opcode) = u32::decode(input)?;
+        match opcode {
+            0 => map(TypeIdx::decode, Self::StructNew)(input),
+            1 => map(TypeIdx::decode, Self::StructNewDefault)(input),
+            2 => map(pair(TypeIdx::decode, FieldIdx::decode), |(x, y)| {
+                Self::StructGet(x, y)
+            })(input),
+            3 => map(pair(TypeIdx::decode, FieldIdx::decode), |(x, y)| {
+                Self::StructGetS(x, y)
+            })(input),
+            4 => map(pair(TypeIdx::decode, FieldIdx::decode), |(x, y)| {
+                Self::StructGetU(x, y)
+            })(input),
+            5 => map(pair(TypeIdx::decode, FieldIdx::decode), |(x, y)| {
+                Self::StructSet(x, y)
+            })(input),
+            6 => map(TypeIdx::decode, Self::ArrayNew)(input),
+            7 => map(TypeIdx::decode, Self::ArrayNewDefault)(input),
+            8 => map(pair(TypeIdx::decode, u32::decode), |(x, n)| {
+                Self::ArrayNewFixed(x, n)
+            })(input),
+            9 => map(pair(TypeIdx::decode, DataIdx::decode), |(x, y)| {
+                Self::ArrayNewData(x, y)
+            })(input),
+            10 => map(pair(TypeIdx::decode, ElemIdx::decode), |(x, y)| {
+                Self::ArrayNewElem(x, y)
+            })(input),
+            11 => map(TypeIdx::decode, Self::ArrayGet)(input),
+            12 => map(TypeIdx::decode, Self::ArrayGetS)(input),
+            13 => map(TypeIdx::decode, Self::ArrayGetU)(input),
+            14 => map(TypeIdx::decode, Self::ArraySet)(input),
+            15 => Ok((input, Self::ArrayLen)),
+            16 => map(TypeIdx::decode, Self::ArrayFill)(input),
+            17 => map(pair(TypeIdx::decode, TypeIdx::decode), |(x, y)| {
+                Self::ArrayCopy(x, y)
+            })(input),
+            18 => map(pair(TypeIdx::decode, DataIdx::decode), |(x, y)| {
+                Self::ArrayInitData(x, y)
+            })(input),
+            19 => map(pair(TypeIdx::decode, ElemIdx::decode), |(x, y)| {
+                Self::ArrayInitElem(x, y)
+            })(input),
+            20 => map(HeapType::decode, |ht| Self::RefTest(RefType::non_null(ht)))(input),
+            21 => map(HeapType::decode, |ht| Self::RefTest(RefType::null(ht)))(input),
+            22 => map(HeapType::decode, |ht| Self::RefCast(RefType::non_null(ht)))(input),
+            23 => map(HeapType::decode, |ht| Self::RefCast(RefType::null(ht)))(input),
+            24 => map(cast_operands, |(l, rt1, rt2)| Self::BrOnCast(l, rt1, rt2))(input),
+            25 => map(cast_operands, |(l, rt1, rt2)| {
+                Self::BrOnCastFail(l, rt1, rt2)
+            })(input),
+            26 => Ok((input, Self::AnyConvertExtern)),
+            27 => Ok((input, Self::ExternConvertAny)),
+            28 => Ok((input, Self::RefI31)),
+            29 => Ok((input, Self::I31GetS)),
+            30 => Ok((input, Self::I31GetU)),
+            _ => fail(input),
+        }
+    }
+}
+
+impl GcInstruction {
+    /// The opcode that follows the 0xFB prefix
+    fn opcode(&self) -> u32 {
+        match self {
+            Self::StructNew(_) => 0,
+            Self::StructNewDefault(_) => 1,
+            Self::StructGet(..) => 2,
+            Self::StructGetS(..) => 3,
+            Self::StructGetU(..) => 4,
+            Self::StructSet(..) => 5,
+            Self::ArrayNew(_) => 6,
+            Self::ArrayNewDefault(_) => 7,
+            Self::ArrayNewFixed(..) => 8,
+            Self::ArrayNewData(..) => 9,
+            Self::ArrayNewElem(..) => 10,
+            Self::ArrayGet(_) => 11,
+            Self::ArrayGetS(_) => 12,
+            Self::ArrayGetU(_) => 13,
+            Self::ArraySet(_) => 14,
+            Self::ArrayLen => 15,
+            Self::ArrayFill(_) => 16,
+            Self::ArrayCopy(..) => 17,
+            Self::ArrayInitData(..) => 18,
+            Self::ArrayInitElem(..) => 19,
+            Self::RefTest(rt) => 20 + rt.nullable as u32,
+            Self::RefCast(rt) => 22 + rt.nullable as u32,
+            Self::BrOnCast(..) => 24,
+            Self::BrOnCastFail(..) => 25,
+            Self::AnyConvertExtern => 26,
+            Self::ExternConvertAny => 27,
+            Self::RefI31 => 28,
+            Self::I31GetS => 29,
+            Self::I31GetU => 30,
+        }
+    }
+}
+
+impl Encode for GcInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(0xFB);
+        self.opcode().encode(out);
+        match self {
+            Self::StructNew(x)
+            | Self::StructNewDefault(x)
+            | Self::ArrayNew(x)
+            | Self::ArrayNewDefault(x)
+            | Self::ArrayGet(x)
+            | Self::ArrayGetS(x)
+            | Self::ArrayGetU(x)
+            | Self::ArraySet(x)
+            | Self::ArrayFill(x) => x.encode(out),
+            Self::StructGet(x, y)
+            | Self::StructGetS(x, y)
+            | Self::StructGetU(x, y)
+            | Self::StructSet(x, y)
+            | Self::ArrayNewFixed(x, y)
+            | Self::ArrayNewData(x, y)
+            | Self::ArrayNewElem(x, y)
+            | Self::ArrayCopy(x, y)
+            | Self::ArrayInitData(x, y)
+            | Self::ArrayInitElem(x, y) => {
+                x.encode(out);
+                y.encode(out);
+            }
+            Self::RefTest(rt) | Self::RefCast(rt) => rt.ht.encode(out),
+            Self::BrOnCast(labelidx, rt1, rt2) | Self::BrOnCastFail(labelidx, rt1, rt2) => {
+                out.push(rt1.nullable as u8 | (rt2.nullable as u8) << 1);
+                labelidx.encode(out);
+                rt1.ht.encode(out);
+                rt2.ht.encode(out);
+            }
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY: &[u8] = &[];
+
+    #[test]
+    fn test_gc_instruction() {
+        assert_eq!(
+            GcInstruction::decode(&[0xFB, 0x00, 0x02]),
+            Ok((EMPTY, GcInstruction::StructNew(2)))
+        );
+        assert_eq!(
+            GcInstruction::decode(&[0xFB, 0x03, 0x01, 0x04]),
+            Ok((EMPTY, GcInstruction::StructGetS(1, 4)))
+        );
+        assert_eq!(
+            GcInstruction::decode(&[0xFB, 0x08, 0x00, 0x80, 0x01]),
+            Ok((EMPTY, GcInstruction::ArrayNewFixed(0, 128)))
+        );
+        assert_eq!(
+            GcInstruction::decode(&[0xFB, 0x0F]),
+            Ok((EMPTY, GcInstruction::ArrayLen))
+        );
+        assert_eq!(
+            GcInstruction::decode(&[0xFB, 0x11, 0x01, 0x02]),
+            Ok((EMPTY, GcInstruction::ArrayCopy(1, 2)))
+        );
+        assert_eq!(
+            GcInstruction::decode(&[0xFB, 0x14, 0x6C]),
+            Ok((
+                EMPTY,
+                GcInstruction::RefTest(RefType::non_null(HeapType::I31))
+            ))
+        );
+        assert_eq!(
+            GcInstruction::decode(&[0xFB, 0x17, 0x03]),
+            Ok((
+                EMPTY,
+                GcInstruction::RefCast(RefType::null(HeapType::Type(3)))
+            ))
+        );
+        assert_eq!(
+            GcInstruction::decode(&[0xFB, 0x18, 0x01, 0x00, 0x6E, 0x6B]),
+            Ok((
+                EMPTY,
+                GcInstruction::BrOnCast(0, RefType::ANYREF, RefType::non_null(HeapType::Struct))
+            ))
+        );
+        assert_eq!(
+            GcInstruction::decode(&[0xFB, 0x19, 0x02, 0x01, 0x6E, 0x00]),
+            Ok((
+                EMPTY,
+                GcInstruction::BrOnCastFail(
+                    1,
+                    RefType::non_null(HeapType::Any),
+                    RefType::null(HeapType::Type(0))
+                )
+            ))
+        );
+        assert_eq!(
+            GcInstruction::decode(&[0xFB, 0x1C]),
+            Ok((EMPTY, GcInstruction::RefI31))
+        );
+        assert!(GcInstruction::decode(&[0xFB, 0x18, 0x04, 0x00, 0x6E, 0x6B]).is_err());
+        assert!(GcInstruction::decode(&[0xFB, 0x1F]).is_err());
+        assert!(GcInstruction::decode(&[0xFC, 0x00]).is_err());
+    }
+}
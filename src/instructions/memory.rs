@@ -0,0 +1,311 @@
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take},
+    combinator::{fail, map},
+    sequence::pair,
+    IResult,
+};
+
+use crate::{
+    module::indices::{DataIdx, MemIdx},
+    Decode, Encode,
+};
+
+/// The alignment, offset and memory index immediates of an instruction that accesses memory
+///
+/// The alignment is given as an exponent of two, so `align: 2` means the access is expected to be 4-byte aligned.
+/// Bit 6 of the encoded alignment signals that a memory index follows, otherwise the access is to memory 0. Offsets
+/// are 64-bit so that they can reach anywhere in a 64-bit memory.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemArg {
+    pub align: u32,
+    pub offset: u64,
+    pub memidx: MemIdx,
+}
+
+impl Decode for MemArg {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, flags) = u32::decode(input)?;
+        let (input, memidx) = if flags & 0x40 != 0 {
+            MemIdx::decode(input)?
+        } else {
+            (input, 0)
+        };
+        let (input, offset) = u64::decode(input)?;
+        Ok((
+            input,
+            Self {
+                align: flags & !0x40,
+                offset,
+                memidx,
+            },
+        ))
+    }
+}
+
+impl Encode for MemArg {
+    fn encode(&self, out: &mut Vec<u8>) {
+        if self.memidx == 0 {
+            self.align.encode(out);
+        } else {
+            (self.align | 0x40).encode(out);
+            self.memidx.encode(out);
+        }
+        self.offset.encode(out);
+    }
+}
+
+/// An instruction that loads from, stores to or manages a linear memory
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MemoryInstruction {
+    I32Load(MemArg),
+    I64Load(MemArg),
+    F32Load(MemArg),
+    F64Load(MemArg),
+    I32Load8S(MemArg),
+    I32Load8U(MemArg),
+    I32Load16S(MemArg),
+    I32Load16U(MemArg),
+    I64Load8S(MemArg),
+    I64Load8U(MemArg),
+    I64Load16S(MemArg),
+    I64Load16U(MemArg),
+    I64Load32S(MemArg),
+    I64Load32U(MemArg),
+    I32Store(MemArg),
+    I64Store(MemArg),
+    F32Store(MemArg),
+    F64Store(MemArg),
+    I32Store8(MemArg),
+    I32Store16(MemArg),
+    I64Store8(MemArg),
+    I64Store16(MemArg),
+    I64Store32(MemArg),
+    MemorySize(MemIdx),
+    MemoryGrow(MemIdx),
+    MemoryInit(DataIdx, MemIdx),
+    DataDrop(DataIdx),
+    MemoryCopy(MemIdx, MemIdx),
+    MemoryFill(MemIdx),
+}
+
+impl Decode for MemoryInstruction {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        alt((Self::decode_single, Self::decode_prefixed))(input)
+    }
+}
+
+impl Encode for MemoryInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let (opcode, memarg) = match self {
+            Self::I32Load(memarg) => (0x28, memarg),
+            Self::I64Load(memarg) => (0x29, memarg),
+            Self::F32Load(memarg) => (0x2A, memarg),
+            Self::F64Load(memarg) => (0x2B, memarg),
+            Self::I32Load8S(memarg) => (0x2C, memarg),
+            Self::I32Load8U(memarg) => (0x2D, memarg),
+            Self::I32Load16S(memarg) => (0x2E, memarg),
+            Self::I32Load16U(memarg) => (0x2F, memarg),
+            Self::I64Load8S(memarg) => (0x30, memarg),
+            Self::I64Load8U(memarg) => (0x31, memarg),
+            Self::I64Load16S(memarg) => (0x32, memarg),
+            Self::I64Load16U(memarg) => (0x33, memarg),
+            Self::I64Load32S(memarg) => (0x34, memarg),
+            Self::I64Load32U(memarg) => (0x35, memarg),
+            Self::I32Store(memarg) => (0x36, memarg),
+            Self::I64Store(memarg) => (0x37, memarg),
+            Self::F32Store(memarg) => (0x38, memarg),
+            Self::F64Store(memarg) => (0x39, memarg),
+            Self::I32Store8(memarg) => (0x3A, memarg),
+            Self::I32Store16(memarg) => (0x3B, memarg),
+            Self::I64Store8(memarg) => (0x3C, memarg),
+            Self::I64Store16(memarg) => (0x3D, memarg),
+            Self::I64Store32(memarg) => (0x3E, memarg),
+            Self::MemorySize(memidx) => {
+                out.push(0x3F);
+                return memidx.encode(out);
+            }
+            Self::MemoryGrow(memidx) => {
+                out.push(0x40);
+                return memidx.encode(out);
+            }
+            Self::MemoryInit(dataidx, memidx) => {
+                out.push(0xFC);
+                8u32.encode(out);
+                dataidx.encode(out);
+                return memidx.encode(out);
+            }
+            Self::DataDrop(dataidx) => {
+                out.push(0xFC);
+                9u32.encode(out);
+                return dataidx.encode(out);
+            }
+            Self::MemoryCopy(dst, src) => {
+                out.push(0xFC);
+                10u32.encode(out);
+                dst.encode(out);
+                return src.encode(out);
+            }
+            Self::MemoryFill(memidx) => {
+                out.push(0xFC);
+                11u32.encode(out);
+                return memidx.encode(out);
+            }
+        };
+        out.push(opcode);
+        memarg.encode(out);
+    }
+}
+
+impl MemoryInstruction {
+    /// The name of the instruction in the text format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::I32Load(..) => "i32.load",
+            Self::I64Load(..) => "i64.load",
+            Self::F32Load(..) => "f32.load",
+            Self::F64Load(..) => "f64.load",
+            Self::I32Load8S(..) => "i32.load8_s",
+            Self::I32Load8U(..) => "i32.load8_u",
+            Self::I32Load16S(..) => "i32.load16_s",
+            Self::I32Load16U(..) => "i32.load16_u",
+            Self::I64Load8S(..) => "i64.load8_s",
+            Self::I64Load8U(..) => "i64.load8_u",
+            Self::I64Load16S(..) => "i64.load16_s",
+            Self::I64Load16U(..) => "i64.load16_u",
+            Self::I64Load32S(..) => "i64.load32_s",
+            Self::I64Load32U(..) => "i64.load32_u",
+            Self::I32Store(..) => "i32.store",
+            Self::I64Store(..) => "i64.store",
+            Self::F32Store(..) => "f32.store",
+            Self::F64Store(..) => "f64.store",
+            Self::I32Store8(..) => "i32.store8",
+            Self::I32Store16(..) => "i32.store16",
+            Self::I64Store8(..) => "i64.store8",
+            Self::I64Store16(..) => "i64.store16",
+            Self::I64Store32(..) => "i64.store32",
+            Self::MemorySize(..) => "memory.size",
+            Self::MemoryGrow(..) => "memory.grow",
+            Self::MemoryInit(..) => "memory.init",
+            Self::DataDrop(..) => "data.drop",
+            Self::MemoryCopy(..) => "memory.copy",
+            Self::MemoryFill(..) => "memory.fill",
+        }
+    }
+
+    /// Decode an instruction with a single byte opcode
+    fn decode_single(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, opcode) = take(1usize)(input)?;
+        match opcode[0] {
+            0x28 => map(MemArg::decode, Self::I32Load)(input),
+            0x29 => map(MemArg::decode, Self::I64Load)(input),
+            0x2A => map(MemArg::decode, Self::F32Load)(input),
+            0x2B => map(MemArg::decode, Self::F64Load)(input),
+            0x2C => map(MemArg::decode, Self::I32Load8S)(input),
+            0x2D => map(MemArg::decode, Self::I32Load8U)(input),
+            0x2E => map(MemArg::decode, Self::I32Load16S)(input),
+            0x2F => map(MemArg::decode, Self::I32Load16U)(input),
+            0x30 => map(MemArg::decode, Self::I64Load8S)(input),
+            0x31 => map(MemArg::decode, Self::I64Load8U)(input),
+            0x32 => map(MemArg::decode, Self::I64Load16S)(input),
+            0x33 => map(MemArg::decode, Self::I64Load16U)(input),
+            0x34 => map(MemArg::decode, Self::I64Load32S)(input),
+            0x35 => map(MemArg::decode, Self::I64Load32U)(input),
+            0x36 => map(MemArg::decode, Self::I32Store)(input),
+            0x37 => map(MemArg::decode, Self::I64Store)(input),
+            0x38 => map(MemArg::decode, Self::F32Store)(input),
+            0x39 => map(MemArg::decode, Self::F64Store)(input),
+            0x3A => map(MemArg::decode, Self::I32Store8)(input),
+            0x3B => map(MemArg::decode, Self::I32Store16)(input),
+            0x3C => map(MemArg::decode, Self::I64Store8)(input),
+            0x3D => map(MemArg::decode, Self::I64Store16)(input),
+            0x3E => map(MemArg::decode, Self::I64Store32)(input),
+            0x3F => map(MemIdx::decode, Self::MemorySize)(input),
+            0x40 => map(MemIdx::decode, Self::MemoryGrow)(input),
+            _ => fail(input),
+        }
+    }
+
+    /// Decode an instruction behind the 0xFC prefix
+    fn decode_prefixed(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, _) = tag([0xFC])(input)?;
+        let (input, opcode) = u32::decode(input)?;
+        match opcode {
+            8 => map(pair(DataIdx::decode, MemIdx::decode), |pair| {
+                Self::MemoryInit(pair.0, pair.1)
+            })(input),
+            9 => map(DataIdx::decode, Self::DataDrop)(input),
+            10 => map(pair(MemIdx::decode, MemIdx::decode), |pair| {
+                Self::MemoryCopy(pair.0, pair.1)
+            })(input),
+            11 => map(MemIdx::decode, Self::MemoryFill)(input),
+            _ => fail(input),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY: &[u8] = &[];
+
+    #[test]
+    fn test_memarg() {
+        assert_eq!(
+            MemArg::decode(&[0x02, 0x80, 0x01]),
+            Ok((
+                EMPTY,
+                MemArg {
+                    align: 2,
+                    offset: 128,
+                    memidx: 0
+                }
+            ))
+        );
+        assert_eq!(
+            MemArg::decode(&[0x42, 0x01, 0x00]),
+            Ok((
+                EMPTY,
+                MemArg {
+                    align: 2,
+                    offset: 0,
+                    memidx: 1
+                }
+            ))
+        );
+        assert!(MemArg::decode(&[0x02]).is_err());
+        assert!(MemArg::decode(&[0x42, 0x01]).is_err());
+    }
+
+    #[test]
+    fn test_memory_instruction() {
+        assert_eq!(
+            MemoryInstruction::decode(&[0x2D, 0x00, 0x04]),
+            Ok((
+                EMPTY,
+                MemoryInstruction::I32Load8U(MemArg {
+                    align: 0,
+                    offset: 4,
+                    memidx: 0
+                })
+            ))
+        );
+        assert_eq!(
+            MemoryInstruction::decode(&[0x40, 0x02]),
+            Ok((EMPTY, MemoryInstruction::MemoryGrow(2)))
+        );
+        assert_eq!(
+            MemoryInstruction::decode(&[0xFC, 0x0A, 0x01, 0x00]),
+            Ok((EMPTY, MemoryInstruction::MemoryCopy(1, 0)))
+        );
+        assert_eq!(
+            MemoryInstruction::decode(&[0xFC, 0x08, 0x03, 0x00]),
+            Ok((EMPTY, MemoryInstruction::MemoryInit(3, 0)))
+        );
+        assert!(MemoryInstruction::decode(&[0x41, 0x00]).is_err());
+        assert!(MemoryInstruction::decode(&[0xFC, 0x00]).is_err());
+    }
+}
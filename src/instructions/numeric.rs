@@ -6,16 +6,17 @@ use nom::{
     IResult,
 };
 
-use crate::Decode;
+use crate::{Decode, Encode};
 
 /// TODO: Document
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NumericInstruction {
     // const
     I32Const(i32),
     I64Const(i64),
-    F32Const(f32),
-    F64Const(f64),
+    F32Const(#[cfg_attr(feature = "serde", serde(with = "crate::module::values::f32_bits"))] f32),
+    F64Const(#[cfg_attr(feature = "serde", serde(with = "crate::module::values::f64_bits"))] f64),
     // i32
     I32Eqz,
     I32Eq,
@@ -161,6 +162,146 @@ pub enum NumericInstruction {
     I64Extend32S,
 }
 
+impl NumericInstruction {
+    /// The name of the instruction in the text format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::I32Const(_) => "i32.const",
+            Self::I64Const(_) => "i64.const",
+            Self::F32Const(_) => "f32.const",
+            Self::F64Const(_) => "f64.const",
+            Self::I32Eqz => "i32.eqz",
+            Self::I32Eq => "i32.eq",
+            Self::I32Ne => "i32.ne",
+            Self::I32LtS => "i32.lt_s",
+            Self::I32NeU => "i32.lt_u",
+            Self::I32GtS => "i32.gt_s",
+            Self::I32GtU => "i32.gt_u",
+            Self::I32LeS => "i32.le_s",
+            Self::I32LeU => "i32.le_u",
+            Self::I32GeS => "i32.ge_s",
+            Self::I32GeU => "i32.ge_u",
+            Self::I64Eqz => "i64.eqz",
+            Self::I64Eq => "i64.eq",
+            Self::I64Ne => "i64.ne",
+            Self::I64LtS => "i64.lt_s",
+            Self::I64NeU => "i64.lt_u",
+            Self::I64GtS => "i64.gt_s",
+            Self::I64GtU => "i64.gt_u",
+            Self::I64LeS => "i64.le_s",
+            Self::I64LeU => "i64.le_u",
+            Self::I64GeS => "i64.ge_s",
+            Self::I64GeU => "i64.ge_u",
+            Self::F32Eq => "f32.eq",
+            Self::F32Ne => "f32.ne",
+            Self::F32Lt => "f32.lt",
+            Self::F32Gt => "f32.gt",
+            Self::F32Le => "f32.le",
+            Self::F32Ge => "f32.ge",
+            Self::F64Eq => "f64.eq",
+            Self::F64Ne => "f64.ne",
+            Self::F64Lt => "f64.lt",
+            Self::F64Gt => "f64.gt",
+            Self::F64Le => "f64.le",
+            Self::F64Ge => "f64.ge",
+            Self::I32Clz => "i32.clz",
+            Self::I32Ctz => "i32.ctz",
+            Self::I32Popcnt => "i32.popcnt",
+            Self::I32Add => "i32.add",
+            Self::I32Sub => "i32.sub",
+            Self::I32Mul => "i32.mul",
+            Self::I32DivS => "i32.div_s",
+            Self::I32DivU => "i32.div_u",
+            Self::I32RemS => "i32.rem_s",
+            Self::I32RemU => "i32.rem_u",
+            Self::I32And => "i32.and",
+            Self::I32Or => "i32.or",
+            Self::I32Xor => "i32.xor",
+            Self::I32Shl => "i32.shl",
+            Self::I32ShrS => "i32.shr_s",
+            Self::I32ShrU => "i32.shr_u",
+            Self::I32Rotl => "i32.rotl",
+            Self::I32Rotr => "i32.rotr",
+            Self::I64Clz => "i64.clz",
+            Self::I64Ctz => "i64.ctz",
+            Self::I64Popcnt => "i64.popcnt",
+            Self::I64Add => "i64.add",
+            Self::I64Sub => "i64.sub",
+            Self::I64Mul => "i64.mul",
+            Self::I64DivS => "i64.div_s",
+            Self::I64DivU => "i64.div_u",
+            Self::I64RemS => "i64.rem_s",
+            Self::I64RemU => "i64.rem_u",
+            Self::I64And => "i64.and",
+            Self::I64Or => "i64.or",
+            Self::I64Xor => "i64.xor",
+            Self::I64Shl => "i64.shl",
+            Self::I64ShrS => "i64.shr_s",
+            Self::I64ShrU => "i64.shr_u",
+            Self::I64Rotl => "i64.rotl",
+            Self::I64Rotr => "i64.rotr",
+            Self::F32Abs => "f32.abs",
+            Self::F32Neg => "f32.neg",
+            Self::F32Ceil => "f32.ceil",
+            Self::F32Floor => "f32.floor",
+            Self::F32Trunc => "f32.trunc",
+            Self::F32Nearest => "f32.nearest",
+            Self::F32Sqrt => "f32.sqrt",
+            Self::F32Add => "f32.add",
+            Self::F32Sub => "f32.sub",
+            Self::F32Mul => "f32.mul",
+            Self::F32Div => "f32.div",
+            Self::F32Min => "f32.min",
+            Self::F32Max => "f32.max",
+            Self::F32CopySign => "f32.copysign",
+            Self::F64Abs => "f64.abs",
+            Self::F64Neg => "f64.neg",
+            Self::F64Ceil => "f64.ceil",
+            Self::F64Floor => "f64.floor",
+            Self::F64Trunc => "f64.trunc",
+            Self::F64Nearest => "f64.nearest",
+            Self::F64Sqrt => "f64.sqrt",
+            Self::F64Add => "f64.add",
+            Self::F64Sub => "f64.sub",
+            Self::F64Mul => "f64.mul",
+            Self::F64Div => "f64.div",
+            Self::F64Min => "f64.min",
+            Self::F64Max => "f64.max",
+            Self::F64CopySign => "f64.copysign",
+            Self::I32WrapI64 => "i32.wrap_i64",
+            Self::I32TruncF32S => "i32.trunc_f32_s",
+            Self::I32TruncF32U => "i32.trunc_f32_u",
+            Self::I32TruncF64S => "i32.trunc_f64_s",
+            Self::I32TruncF64U => "i32.trunc_f64_u",
+            Self::I64ExtendI32S => "i64.extend_i32_s",
+            Self::I64ExtendI32U => "i64.extend_i32_u",
+            Self::I64TruncF32S => "i64.trunc_f32_s",
+            Self::I64TruncF32U => "i64.trunc_f32_u",
+            Self::I64TruncF64S => "i64.trunc_f64_s",
+            Self::I64TruncF64U => "i64.trunc_f64_u",
+            Self::F32ConvertI32S => "f32.convert_i32_s",
+            Self::F32ConvertI32U => "f32.convert_i32_u",
+            Self::F32ConvertI64S => "f32.convert_i64_s",
+            Self::F32ConvertI64u => "f32.convert_i64_u",
+            Self::F32DemoteF64 => "f32.demote_f64",
+            Self::F64ConvertI32S => "f64.convert_i32_s",
+            Self::F64ConvertI32U => "f64.convert_i32_u",
+            Self::F64ConvertI64S => "f64.convert_i64_s",
+            Self::F64ConvertI64u => "f64.convert_i64_u",
+            Self::F64PromoteF32 => "f64.promote_f32",
+            Self::I32ReinterpretF32 => "i32.reinterpret_f32",
+            Self::I64ReinterpretF64 => "i64.reinterpret_f64",
+            Self::F32ReinterpretI32 => "f32.reinterpret_i32",
+            Self::F64ReinterpretI64 => "f64.reinterpret_i64",
+            Self::I32Extend8S => "i32.extend8_s",
+            Self::I32Extend16S => "i32.extend16_s",
+            Self::I64Extend8S => "i64.extend8_s",
+            Self::I64Extend16S => "i64.extend16_s",
+            Self::I64Extend32S => "i64.extend32_s",
+        }
+    }
+}
+
 impl Decode for NumericInstruction {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
         // Nested alts used to avoid limitation of only 21 clauses allowed in alt
@@ -356,8 +497,162 @@ impl Decode for NumericInstruction {
     }
 }
 
+impl Encode for NumericInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        // NOTE: The opcodes of the instructions without immediates are generated by util/gen_numeric.py
+        let opcode = match self {
+            Self::I32Const(n) => {
+                out.push(0x41);
+                return n.encode(out);
+            }
+            Self::I64Const(n) => {
+                out.push(0x42);
+                return n.encode(out);
+            }
+            Self::F32Const(z) => {
+                out.push(0x43);
+                return z.encode(out);
+            }
+            Self::F64Const(z) => {
+                out.push(0x44);
+                return z.encode(out);
+            }
+            Self::I32Eqz => 0x45,
+            Self::I32Eq => 0x46,
+            Self::I32Ne => 0x47,
+            Self::I32LtS => 0x48,
+            Self::I32NeU => 0x49,
+            Self::I32GtS => 0x4A,
+            Self::I32GtU => 0x4B,
+            Self::I32LeS => 0x4C,
+            Self::I32LeU => 0x4D,
+            Self::I32GeS => 0x4E,
+            Self::I32GeU => 0x4F,
+            Self::I64Eqz => 0x50,
+            Self::I64Eq => 0x51,
+            Self::I64Ne => 0x52,
+            Self::I64LtS => 0x53,
+            Self::I64NeU => 0x54,
+            Self::I64GtS => 0x55,
+            Self::I64GtU => 0x56,
+            Self::I64LeS => 0x57,
+            Self::I64LeU => 0x58,
+            Self::I64GeS => 0x59,
+            Self::I64GeU => 0x5A,
+            Self::F32Eq => 0x5B,
+            Self::F32Ne => 0x5C,
+            Self::F32Lt => 0x5D,
+            Self::F32Gt => 0x5E,
+            Self::F32Le => 0x5F,
+            Self::F32Ge => 0x60,
+            Self::F64Eq => 0x61,
+            Self::F64Ne => 0x62,
+            Self::F64Lt => 0x63,
+            Self::F64Gt => 0x64,
+            Self::F64Le => 0x65,
+            Self::F64Ge => 0x66,
+            Self::I32Clz => 0x67,
+            Self::I32Ctz => 0x68,
+            Self::I32Popcnt => 0x69,
+            Self::I32Add => 0x6A,
+            Self::I32Sub => 0x6B,
+            Self::I32Mul => 0x6C,
+            Self::I32DivS => 0x6D,
+            Self::I32DivU => 0x6E,
+            Self::I32RemS => 0x6F,
+            Self::I32RemU => 0x70,
+            Self::I32And => 0x71,
+            Self::I32Or => 0x72,
+            Self::I32Xor => 0x73,
+            Self::I32Shl => 0x74,
+            Self::I32ShrS => 0x75,
+            Self::I32ShrU => 0x76,
+            Self::I32Rotl => 0x77,
+            Self::I32Rotr => 0x78,
+            Self::I64Clz => 0x79,
+            Self::I64Ctz => 0x7A,
+            Self::I64Popcnt => 0x7B,
+            Self::I64Add => 0x7C,
+            Self::I64Sub => 0x7D,
+            Self::I64Mul => 0x7E,
+            Self::I64DivS => 0x7F,
+            Self::I64DivU => 0x80,
+            Self::I64RemS => 0x81,
+            Self::I64RemU => 0x82,
+            Self::I64And => 0x83,
+            Self::I64Or => 0x84,
+            Self::I64Xor => 0x85,
+            Self::I64Shl => 0x86,
+            Self::I64ShrS => 0x87,
+            Self::I64ShrU => 0x88,
+            Self::I64Rotl => 0x89,
+            Self::I64Rotr => 0x8A,
+            Self::F32Abs => 0x8B,
+            Self::F32Neg => 0x8C,
+            Self::F32Ceil => 0x8D,
+            Self::F32Floor => 0x8E,
+            Self::F32Trunc => 0x8F,
+            Self::F32Nearest => 0x90,
+            Self::F32Sqrt => 0x91,
+            Self::F32Add => 0x92,
+            Self::F32Sub => 0x93,
+            Self::F32Mul => 0x94,
+            Self::F32Div => 0x95,
+            Self::F32Min => 0x96,
+            Self::F32Max => 0x97,
+            Self::F32CopySign => 0x98,
+            Self::F64Abs => 0x99,
+            Self::F64Neg => 0x9A,
+            Self::F64Ceil => 0x9B,
+            Self::F64Floor => 0x9C,
+            Self::F64Trunc => 0x9D,
+            Self::F64Nearest => 0x9E,
+            Self::F64Sqrt => 0x9F,
+            Self::F64Add => 0xA0,
+            Self::F64Sub => 0xA1,
+            Self::F64Mul => 0xA2,
+            Self::F64Div => 0xA3,
+            Self::F64Min => 0xA4,
+            Self::F64Max => 0xA5,
+            Self::F64CopySign => 0xA6,
+            Self::I32WrapI64 => 0xA7,
+            Self::I32TruncF32S => 0xA8,
+            Self::I32TruncF32U => 0xA9,
+            Self::I32TruncF64S => 0xAA,
+            Self::I32TruncF64U => 0xAB,
+            Self::I64ExtendI32S => 0xAC,
+            Self::I64ExtendI32U => 0xAD,
+            Self::I64TruncF32S => 0xAE,
+            Self::I64TruncF32U => 0xAF,
+            Self::I64TruncF64S => 0xB0,
+            Self::I64TruncF64U => 0xB1,
+            Self::F32ConvertI32S => 0xB2,
+            Self::F32ConvertI32U => 0xB3,
+            Self::F32ConvertI64S => 0xB4,
+            Self::F32ConvertI64u => 0xB5,
+            Self::F32DemoteF64 => 0xB6,
+            Self::F64ConvertI32S => 0xB7,
+            Self::F64ConvertI32U => 0xB8,
+            Self::F64ConvertI64S => 0xB9,
+            Self::F64ConvertI64u => 0xBA,
+            Self::F64PromoteF32 => 0xBB,
+            Self::I32ReinterpretF32 => 0xBC,
+            Self::I64ReinterpretF64 => 0xBD,
+            Self::F32ReinterpretI32 => 0xBE,
+            Self::F64ReinterpretI64 => 0xBF,
+            Self::I32Extend8S => 0xC0,
+            Self::I32Extend16S => 0xC1,
+            Self::I64Extend8S => 0xC2,
+            Self::I64Extend16S => 0xC3,
+            Self::I64Extend32S => 0xC4,
+        };
+        out.push(opcode);
+    }
+}
+
 /// TODO: Document
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SaturatingTruncationInstruction {
     I32TruncSatF32S,
     I32TruncSatF32U,
@@ -369,6 +664,22 @@ pub enum SaturatingTruncationInstruction {
     I64TruncSatF64U,
 }
 
+impl SaturatingTruncationInstruction {
+    /// The name of the instruction in the text format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::I32TruncSatF32S => "i32.trunc_sat_f32_s",
+            Self::I32TruncSatF32U => "i32.trunc_sat_f32_u",
+            Self::I32TruncSatF64S => "i32.trunc_sat_f64_s",
+            Self::I32TruncSatF64U => "i32.trunc_sat_f64_u",
+            Self::I64TruncSatF32S => "i64.trunc_sat_f32_s",
+            Self::I64TruncSatF32U => "i64.trunc_sat_f32_u",
+            Self::I64TruncSatF64S => "i64.trunc_sat_f64_s",
+            Self::I64TruncSatF64U => "i64.trunc_sat_f64_u",
+        }
+    }
+}
+
 impl Decode for SaturatingTruncationInstruction {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
         let (input, _) = tag([0xFC])(input)?;
@@ -388,6 +699,23 @@ impl Decode for SaturatingTruncationInstruction {
     }
 }
 
+impl Encode for SaturatingTruncationInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let opcode: u32 = match self {
+            Self::I32TruncSatF32S => 0,
+            Self::I32TruncSatF32U => 1,
+            Self::I32TruncSatF64S => 2,
+            Self::I32TruncSatF64U => 3,
+            Self::I64TruncSatF32S => 4,
+            Self::I64TruncSatF32U => 5,
+            Self::I64TruncSatF64S => 6,
+            Self::I64TruncSatF64U => 7,
+        };
+        out.push(0xFC);
+        opcode.encode(out);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -415,4 +743,24 @@ mod tests {
         );
         assert!(SaturatingTruncationInstruction::decode(&[0xFF]).is_err());
     }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_floats() {
+        // A NaN with a payload, which JSON can't represent as a number
+        let instruction = NumericInstruction::F32Const(f32::from_bits(0x7FA0_0001));
+        let json = serde_json::to_string(&instruction).unwrap();
+        assert_eq!(json, r#"{"F32Const":2141192193}"#);
+        match serde_json::from_str(&json).unwrap() {
+            NumericInstruction::F32Const(z) => assert_eq!(z.to_bits(), 0x7FA0_0001),
+            instruction => panic!("unexpected instruction {:?}", instruction),
+        }
+
+        let instruction = NumericInstruction::F64Const(-0.0);
+        let json = serde_json::to_string(&instruction).unwrap();
+        match serde_json::from_str(&json).unwrap() {
+            NumericInstruction::F64Const(z) => assert_eq!(z.to_bits(), (-0.0f64).to_bits()),
+            instruction => panic!("unexpected instruction {:?}", instruction),
+        }
+    }
 }
@@ -2,17 +2,29 @@ use nom::{branch::alt, bytes::complete::tag, combinator::map, sequence::pair, IR
 
 use crate::{
     module::types::{RefType, ValType},
-    Decode,
+    Decode, Encode,
 };
 
 /// TODO: Document
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ParametricInstruction {
     Drop,
     Select,
     SelectTyped(Vec<ValType>),
 }
 
+impl ParametricInstruction {
+    /// The name of the instruction in the text format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Drop => "drop",
+            Self::Select => "select",
+            Self::SelectTyped(_) => "select",
+        }
+    }
+}
+
 impl Decode for ParametricInstruction {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
         alt((
@@ -25,6 +37,19 @@ impl Decode for ParametricInstruction {
     }
 }
 
+impl Encode for ParametricInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Drop => out.push(0x1A),
+            Self::Select => out.push(0x1B),
+            Self::SelectTyped(types) => {
+                out.push(0x1C);
+                types.encode(out);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -41,7 +66,7 @@ mod tests {
             ParametricInstruction::decode(&[0x1C, 0x01, 0x70]),
             Ok((
                 EMPTY,
-                ParametricInstruction::SelectTyped(vec!(ValType::RefType(RefType::FuncRef)))
+                ParametricInstruction::SelectTyped(vec!(ValType::RefType(RefType::FUNCREF)))
             ))
         );
         assert!(ParametricInstruction::decode(&[0xFF]).is_err());
@@ -1,32 +1,68 @@
 use nom::{branch::alt, bytes::complete::tag, combinator::map, sequence::pair, IResult};
 
 use crate::{
-    module::{indices::FuncIdx, types::RefType},
-    Decode,
+    module::{indices::FuncIdx, types::HeapType},
+    Decode, Encode,
 };
 
 /// TODO: Document
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ReferenceInstruction {
-    Null(RefType),
+    Null(HeapType),
     IsNull,
     Func(FuncIdx),
+    Eq,
+    AsNonNull,
+}
+
+impl ReferenceInstruction {
+    /// The name of the instruction in the text format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Null(_) => "ref.null",
+            Self::IsNull => "ref.is_null",
+            Self::Func(_) => "ref.func",
+            Self::Eq => "ref.eq",
+            Self::AsNonNull => "ref.as_non_null",
+        }
+    }
 }
 
 impl Decode for ReferenceInstruction {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
         alt((
-            map(pair(tag([0xD0]), RefType::decode), |pair| {
+            map(pair(tag([0xD0]), HeapType::decode), |pair| {
                 Self::Null(pair.1)
             }),
             map(tag([0xD1]), |_| Self::IsNull),
-            map(pair(tag([0xD0]), FuncIdx::decode), |pair| {
+            map(pair(tag([0xD2]), FuncIdx::decode), |pair| {
                 Self::Func(pair.1)
             }),
+            map(tag([0xD3]), |_| Self::Eq),
+            map(tag([0xD4]), |_| Self::AsNonNull),
         ))(input)
     }
 }
 
+impl Encode for ReferenceInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Null(ht) => {
+                out.push(0xD0);
+                ht.encode(out);
+            }
+            Self::IsNull => out.push(0xD1),
+            Self::Func(funcidx) => {
+                out.push(0xD2);
+                funcidx.encode(out);
+            }
+            Self::Eq => out.push(0xD3),
+            Self::AsNonNull => out.push(0xD4),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -41,7 +77,23 @@ mod tests {
         );
         assert_eq!(
             ReferenceInstruction::decode(&[0xD0, 0x70]),
-            Ok((EMPTY, ReferenceInstruction::Null(RefType::FuncRef)))
+            Ok((EMPTY, ReferenceInstruction::Null(HeapType::Func)))
+        );
+        assert_eq!(
+            ReferenceInstruction::decode(&[0xD0, 0x02]),
+            Ok((EMPTY, ReferenceInstruction::Null(HeapType::Type(2))))
+        );
+        assert_eq!(
+            ReferenceInstruction::decode(&[0xD2, 0x01]),
+            Ok((EMPTY, ReferenceInstruction::Func(1)))
+        );
+        assert_eq!(
+            ReferenceInstruction::decode(&[0xD3]),
+            Ok((EMPTY, ReferenceInstruction::Eq))
+        );
+        assert_eq!(
+            ReferenceInstruction::decode(&[0xD4]),
+            Ok((EMPTY, ReferenceInstruction::AsNonNull))
         );
         assert!(ReferenceInstruction::decode(&[0xFF]).is_err());
     }
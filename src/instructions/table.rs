@@ -0,0 +1,144 @@
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    combinator::{fail, map},
+    sequence::{pair, preceded},
+    IResult,
+};
+
+use crate::{
+    module::indices::{ElemIdx, TableIdx},
+    Decode, Encode,
+};
+
+/// An instruction that reads, writes or resizes a table, or manages an element segment
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TableInstruction {
+    TableGet(TableIdx),
+    TableSet(TableIdx),
+    TableInit(ElemIdx, TableIdx),
+    ElemDrop(ElemIdx),
+    TableCopy(TableIdx, TableIdx),
+    TableGrow(TableIdx),
+    TableSize(TableIdx),
+    TableFill(TableIdx),
+}
+
+impl TableInstruction {
+    /// The name of the instruction in the text format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::TableGet(_) => "table.get",
+            Self::TableSet(_) => "table.set",
+            Self::TableInit(..) => "table.init",
+            Self::ElemDrop(_) => "elem.drop",
+            Self::TableCopy(..) => "table.copy",
+            Self::TableGrow(_) => "table.grow",
+            Self::TableSize(_) => "table.size",
+            Self::TableFill(_) => "table.fill",
+        }
+    }
+
+    /// Decode an instruction behind the 0xFC prefix
+    fn decode_prefixed(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, _) = tag([0xFC])(input)?;
+        let (input, opcode) = u32::decode(input)?;
+        match opcode {
+            12 => map(pair(ElemIdx::decode, TableIdx::decode), |pair| {
+                Self::TableInit(pair.0, pair.1)
+            })(input),
+            13 => map(ElemIdx::decode, Self::ElemDrop)(input),
+            14 => map(pair(TableIdx::decode, TableIdx::decode), |pair| {
+                Self::TableCopy(pair.0, pair.1)
+            })(input),
+            15 => map(TableIdx::decode, Self::TableGrow)(input),
+            16 => map(TableIdx::decode, Self::TableSize)(input),
+            17 => map(TableIdx::decode, Self::TableFill)(input),
+            _ => fail(input),
+        }
+    }
+}
+
+impl Decode for TableInstruction {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        alt((
+            map(preceded(tag([0x25]), TableIdx::decode), Self::TableGet),
+            map(preceded(tag([0x26]), TableIdx::decode), Self::TableSet),
+            Self::decode_prefixed,
+        ))(input)
+    }
+}
+
+impl Encode for TableInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::TableGet(tableidx) => {
+                out.push(0x25);
+                tableidx.encode(out);
+            }
+            Self::TableSet(tableidx) => {
+                out.push(0x26);
+                tableidx.encode(out);
+            }
+            Self::TableInit(elemidx, tableidx) => {
+                out.push(0xFC);
+                12u32.encode(out);
+                elemidx.encode(out);
+                tableidx.encode(out);
+            }
+            Self::ElemDrop(elemidx) => {
+                out.push(0xFC);
+                13u32.encode(out);
+                elemidx.encode(out);
+            }
+            Self::TableCopy(dst, src) => {
+                out.push(0xFC);
+                14u32.encode(out);
+                dst.encode(out);
+                src.encode(out);
+            }
+            Self::TableGrow(tableidx) => {
+                out.push(0xFC);
+                15u32.encode(out);
+                tableidx.encode(out);
+            }
+            Self::TableSize(tableidx) => {
+                out.push(0xFC);
+                16u32.encode(out);
+                tableidx.encode(out);
+            }
+            Self::TableFill(tableidx) => {
+                out.push(0xFC);
+                17u32.encode(out);
+                tableidx.encode(out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY: &[u8] = &[];
+
+    #[test]
+    fn test_table_instruction() {
+        assert_eq!(
+            TableInstruction::decode(&[0x25, 0x01]),
+            Ok((EMPTY, TableInstruction::TableGet(1)))
+        );
+        assert_eq!(
+            TableInstruction::decode(&[0xFC, 0x0C, 0x02, 0x00]),
+            Ok((EMPTY, TableInstruction::TableInit(2, 0)))
+        );
+        assert_eq!(
+            TableInstruction::decode(&[0xFC, 0x0F, 0x00]),
+            Ok((EMPTY, TableInstruction::TableGrow(0)))
+        );
+        assert!(TableInstruction::decode(&[0x27, 0x00]).is_err());
+        assert!(TableInstruction::decode(&[0xFC, 0x08, 0x00, 0x00]).is_err());
+        assert!(TableInstruction::decode(&[0xFC, 0x11]).is_err());
+    }
+}
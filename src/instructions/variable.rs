@@ -4,11 +4,12 @@ use nom::{branch::alt, bytes::complete::tag, combinator::map, sequence::pair, IR
 
 use crate::{
     module::types::{RefType, ValType},
-    Decode,
+    Decode, Encode,
 };
 
 /// TODO: Document
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VariableInstruction {
     LocalGet(LocalIdx),
     LocalSet(LocalIdx),
@@ -17,6 +18,19 @@ pub enum VariableInstruction {
     GlobalSet(GlobalIdx),
 }
 
+impl VariableInstruction {
+    /// The name of the instruction in the text format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::LocalGet(_) => "local.get",
+            Self::LocalSet(_) => "local.set",
+            Self::LocalTee(_) => "local.tee",
+            Self::GlobalGet(_) => "global.get",
+            Self::GlobalSet(_) => "global.set",
+        }
+    }
+}
+
 impl Decode for VariableInstruction {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
         alt((
@@ -39,6 +53,20 @@ impl Decode for VariableInstruction {
     }
 }
 
+impl Encode for VariableInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let (opcode, idx) = match self {
+            Self::LocalGet(localidx) => (0x20, localidx),
+            Self::LocalSet(localidx) => (0x21, localidx),
+            Self::LocalTee(localidx) => (0x22, localidx),
+            Self::GlobalGet(globalidx) => (0x23, globalidx),
+            Self::GlobalSet(globalidx) => (0x24, globalidx),
+        };
+        out.push(opcode);
+        idx.encode(out);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
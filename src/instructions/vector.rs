@@ -0,0 +1,1159 @@
+use nom::{
+    bytes::complete::{tag, take},
+    combinator::{fail, map},
+    sequence::pair,
+    IResult,
+};
+
+use crate::{module::indices::LaneIdx, Decode, Encode};
+
+use super::memory::MemArg;
+
+// NOTE: The variants and match arms below are generated by util/gen_vector.py
+
+/// An instruction that operates on 128-bit vectors, encoded behind the 0xFD prefix
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VectorInstruction {
+    V128Load(MemArg),
+    V128Load8x8S(MemArg),
+    V128Load8x8U(MemArg),
+    V128Load16x4S(MemArg),
+    V128Load16x4U(MemArg),
+    V128Load32x2S(MemArg),
+    V128Load32x2U(MemArg),
+    V128Load8Splat(MemArg),
+    V128Load16Splat(MemArg),
+    V128Load32Splat(MemArg),
+    V128Load64Splat(MemArg),
+    V128Store(MemArg),
+    V128Const(u128),
+    I8x16Shuffle([LaneIdx; 16]),
+    I8x16Swizzle,
+    I8x16Splat,
+    I16x8Splat,
+    I32x4Splat,
+    I64x2Splat,
+    F32x4Splat,
+    F64x2Splat,
+    I8x16ExtractLaneS(LaneIdx),
+    I8x16ExtractLaneU(LaneIdx),
+    I8x16ReplaceLane(LaneIdx),
+    I16x8ExtractLaneS(LaneIdx),
+    I16x8ExtractLaneU(LaneIdx),
+    I16x8ReplaceLane(LaneIdx),
+    I32x4ExtractLane(LaneIdx),
+    I32x4ReplaceLane(LaneIdx),
+    I64x2ExtractLane(LaneIdx),
+    I64x2ReplaceLane(LaneIdx),
+    F32x4ExtractLane(LaneIdx),
+    F32x4ReplaceLane(LaneIdx),
+    F64x2ExtractLane(LaneIdx),
+    F64x2ReplaceLane(LaneIdx),
+    I8x16Eq,
+    I8x16Ne,
+    I8x16LtS,
+    I8x16LtU,
+    I8x16GtS,
+    I8x16GtU,
+    I8x16LeS,
+    I8x16LeU,
+    I8x16GeS,
+    I8x16GeU,
+    I16x8Eq,
+    I16x8Ne,
+    I16x8LtS,
+    I16x8LtU,
+    I16x8GtS,
+    I16x8GtU,
+    I16x8LeS,
+    I16x8LeU,
+    I16x8GeS,
+    I16x8GeU,
+    I32x4Eq,
+    I32x4Ne,
+    I32x4LtS,
+    I32x4LtU,
+    I32x4GtS,
+    I32x4GtU,
+    I32x4LeS,
+    I32x4LeU,
+    I32x4GeS,
+    I32x4GeU,
+    F32x4Eq,
+    F32x4Ne,
+    F32x4Lt,
+    F32x4Gt,
+    F32x4Le,
+    F32x4Ge,
+    F64x2Eq,
+    F64x2Ne,
+    F64x2Lt,
+    F64x2Gt,
+    F64x2Le,
+    F64x2Ge,
+    V128Not,
+    V128And,
+    V128Andnot,
+    V128Or,
+    V128Xor,
+    V128Bitselect,
+    V128AnyTrue,
+    V128Load8Lane(MemArg, LaneIdx),
+    V128Load16Lane(MemArg, LaneIdx),
+    V128Load32Lane(MemArg, LaneIdx),
+    V128Load64Lane(MemArg, LaneIdx),
+    V128Store8Lane(MemArg, LaneIdx),
+    V128Store16Lane(MemArg, LaneIdx),
+    V128Store32Lane(MemArg, LaneIdx),
+    V128Store64Lane(MemArg, LaneIdx),
+    V128Load32Zero(MemArg),
+    V128Load64Zero(MemArg),
+    F32x4DemoteF64x2Zero,
+    F64x2PromoteLowF32x4,
+    I8x16Abs,
+    I8x16Neg,
+    I8x16Popcnt,
+    I8x16AllTrue,
+    I8x16Bitmask,
+    I8x16NarrowI16x8S,
+    I8x16NarrowI16x8U,
+    F32x4Ceil,
+    F32x4Floor,
+    F32x4Trunc,
+    F32x4Nearest,
+    I8x16Shl,
+    I8x16ShrS,
+    I8x16ShrU,
+    I8x16Add,
+    I8x16AddSatS,
+    I8x16AddSatU,
+    I8x16Sub,
+    I8x16SubSatS,
+    I8x16SubSatU,
+    F64x2Ceil,
+    F64x2Floor,
+    I8x16MinS,
+    I8x16MinU,
+    I8x16MaxS,
+    I8x16MaxU,
+    F64x2Trunc,
+    I8x16AvgrU,
+    I16x8ExtaddPairwiseI8x16S,
+    I16x8ExtaddPairwiseI8x16U,
+    I32x4ExtaddPairwiseI16x8S,
+    I32x4ExtaddPairwiseI16x8U,
+    I16x8Abs,
+    I16x8Neg,
+    I16x8Q15mulrSatS,
+    I16x8AllTrue,
+    I16x8Bitmask,
+    I16x8NarrowI32x4S,
+    I16x8NarrowI32x4U,
+    I16x8ExtendLowI8x16S,
+    I16x8ExtendHighI8x16S,
+    I16x8ExtendLowI8x16U,
+    I16x8ExtendHighI8x16U,
+    I16x8Shl,
+    I16x8ShrS,
+    I16x8ShrU,
+    I16x8Add,
+    I16x8AddSatS,
+    I16x8AddSatU,
+    I16x8Sub,
+    I16x8SubSatS,
+    I16x8SubSatU,
+    F64x2Nearest,
+    I16x8Mul,
+    I16x8MinS,
+    I16x8MinU,
+    I16x8MaxS,
+    I16x8MaxU,
+    I16x8AvgrU,
+    I16x8ExtmulLowI8x16S,
+    I16x8ExtmulHighI8x16S,
+    I16x8ExtmulLowI8x16U,
+    I16x8ExtmulHighI8x16U,
+    I32x4Abs,
+    I32x4Neg,
+    I32x4AllTrue,
+    I32x4Bitmask,
+    I32x4ExtendLowI16x8S,
+    I32x4ExtendHighI16x8S,
+    I32x4ExtendLowI16x8U,
+    I32x4ExtendHighI16x8U,
+    I32x4Shl,
+    I32x4ShrS,
+    I32x4ShrU,
+    I32x4Add,
+    I32x4Sub,
+    I32x4Mul,
+    I32x4MinS,
+    I32x4MinU,
+    I32x4MaxS,
+    I32x4MaxU,
+    I32x4DotI16x8S,
+    I32x4ExtmulLowI16x8S,
+    I32x4ExtmulHighI16x8S,
+    I32x4ExtmulLowI16x8U,
+    I32x4ExtmulHighI16x8U,
+    I64x2Abs,
+    I64x2Neg,
+    I64x2AllTrue,
+    I64x2Bitmask,
+    I64x2ExtendLowI32x4S,
+    I64x2ExtendHighI32x4S,
+    I64x2ExtendLowI32x4U,
+    I64x2ExtendHighI32x4U,
+    I64x2Shl,
+    I64x2ShrS,
+    I64x2ShrU,
+    I64x2Add,
+    I64x2Sub,
+    I64x2Mul,
+    I64x2Eq,
+    I64x2Ne,
+    I64x2LtS,
+    I64x2GtS,
+    I64x2LeS,
+    I64x2GeS,
+    I64x2ExtmulLowI32x4S,
+    I64x2ExtmulHighI32x4S,
+    I64x2ExtmulLowI32x4U,
+    I64x2ExtmulHighI32x4U,
+    F32x4Abs,
+    F32x4Neg,
+    F32x4Sqrt,
+    F32x4Add,
+    F32x4Sub,
+    F32x4Mul,
+    F32x4Div,
+    F32x4Min,
+    F32x4Max,
+    F32x4Pmin,
+    F32x4Pmax,
+    F64x2Abs,
+    F64x2Neg,
+    F64x2Sqrt,
+    F64x2Add,
+    F64x2Sub,
+    F64x2Mul,
+    F64x2Div,
+    F64x2Min,
+    F64x2Max,
+    F64x2Pmin,
+    F64x2Pmax,
+    I32x4TruncSatF32x4S,
+    I32x4TruncSatF32x4U,
+    F32x4ConvertI32x4S,
+    F32x4ConvertI32x4U,
+    I32x4TruncSatF64x2SZero,
+    I32x4TruncSatF64x2UZero,
+    F64x2ConvertLowI32x4S,
+    F64x2ConvertLowI32x4U,
+}
+
+impl VectorInstruction {
+    /// The memory argument of an instruction that loads or stores, which names the memory it accesses
+    pub fn memarg(&self) -> Option<&MemArg> {
+        use VectorInstruction::*;
+
+        match self {
+            V128Load(m) | V128Load8x8S(m) | V128Load8x8U(m) | V128Load16x4S(m)
+            | V128Load16x4U(m) | V128Load32x2S(m) | V128Load32x2U(m) | V128Load8Splat(m)
+            | V128Load16Splat(m) | V128Load32Splat(m) | V128Load64Splat(m) | V128Store(m)
+            | V128Load32Zero(m) | V128Load64Zero(m) => Some(m),
+            V128Load8Lane(m, _)
+            | V128Load16Lane(m, _)
+            | V128Load32Lane(m, _)
+            | V128Load64Lane(m, _)
+            | V128Store8Lane(m, _)
+            | V128Store16Lane(m, _)
+            | V128Store32Lane(m, _)
+            | V128Store64Lane(m, _) => Some(m),
+            _ => None,
+        }
+    }
+
+    /// The name of the instruction in the text format
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::V128Load(..) => "v128.load",
+            Self::V128Load8x8S(..) => "v128.load8x8_s",
+            Self::V128Load8x8U(..) => "v128.load8x8_u",
+            Self::V128Load16x4S(..) => "v128.load16x4_s",
+            Self::V128Load16x4U(..) => "v128.load16x4_u",
+            Self::V128Load32x2S(..) => "v128.load32x2_s",
+            Self::V128Load32x2U(..) => "v128.load32x2_u",
+            Self::V128Load8Splat(..) => "v128.load8_splat",
+            Self::V128Load16Splat(..) => "v128.load16_splat",
+            Self::V128Load32Splat(..) => "v128.load32_splat",
+            Self::V128Load64Splat(..) => "v128.load64_splat",
+            Self::V128Store(..) => "v128.store",
+            Self::V128Const(..) => "v128.const",
+            Self::I8x16Shuffle(..) => "i8x16.shuffle",
+            Self::I8x16Swizzle => "i8x16.swizzle",
+            Self::I8x16Splat => "i8x16.splat",
+            Self::I16x8Splat => "i16x8.splat",
+            Self::I32x4Splat => "i32x4.splat",
+            Self::I64x2Splat => "i64x2.splat",
+            Self::F32x4Splat => "f32x4.splat",
+            Self::F64x2Splat => "f64x2.splat",
+            Self::I8x16ExtractLaneS(..) => "i8x16.extract_lane_s",
+            Self::I8x16ExtractLaneU(..) => "i8x16.extract_lane_u",
+            Self::I8x16ReplaceLane(..) => "i8x16.replace_lane",
+            Self::I16x8ExtractLaneS(..) => "i16x8.extract_lane_s",
+            Self::I16x8ExtractLaneU(..) => "i16x8.extract_lane_u",
+            Self::I16x8ReplaceLane(..) => "i16x8.replace_lane",
+            Self::I32x4ExtractLane(..) => "i32x4.extract_lane",
+            Self::I32x4ReplaceLane(..) => "i32x4.replace_lane",
+            Self::I64x2ExtractLane(..) => "i64x2.extract_lane",
+            Self::I64x2ReplaceLane(..) => "i64x2.replace_lane",
+            Self::F32x4ExtractLane(..) => "f32x4.extract_lane",
+            Self::F32x4ReplaceLane(..) => "f32x4.replace_lane",
+            Self::F64x2ExtractLane(..) => "f64x2.extract_lane",
+            Self::F64x2ReplaceLane(..) => "f64x2.replace_lane",
+            Self::I8x16Eq => "i8x16.eq",
+            Self::I8x16Ne => "i8x16.ne",
+            Self::I8x16LtS => "i8x16.lt_s",
+            Self::I8x16LtU => "i8x16.lt_u",
+            Self::I8x16GtS => "i8x16.gt_s",
+            Self::I8x16GtU => "i8x16.gt_u",
+            Self::I8x16LeS => "i8x16.le_s",
+            Self::I8x16LeU => "i8x16.le_u",
+            Self::I8x16GeS => "i8x16.ge_s",
+            Self::I8x16GeU => "i8x16.ge_u",
+            Self::I16x8Eq => "i16x8.eq",
+            Self::I16x8Ne => "i16x8.ne",
+            Self::I16x8LtS => "i16x8.lt_s",
+            Self::I16x8LtU => "i16x8.lt_u",
+            Self::I16x8GtS => "i16x8.gt_s",
+            Self::I16x8GtU => "i16x8.gt_u",
+            Self::I16x8LeS => "i16x8.le_s",
+            Self::I16x8LeU => "i16x8.le_u",
+            Self::I16x8GeS => "i16x8.ge_s",
+            Self::I16x8GeU => "i16x8.ge_u",
+            Self::I32x4Eq => "i32x4.eq",
+            Self::I32x4Ne => "i32x4.ne",
+            Self::I32x4LtS => "i32x4.lt_s",
+            Self::I32x4LtU => "i32x4.lt_u",
+            Self::I32x4GtS => "i32x4.gt_s",
+            Self::I32x4GtU => "i32x4.gt_u",
+            Self::I32x4LeS => "i32x4.le_s",
+            Self::I32x4LeU => "i32x4.le_u",
+            Self::I32x4GeS => "i32x4.ge_s",
+            Self::I32x4GeU => "i32x4.ge_u",
+            Self::F32x4Eq => "f32x4.eq",
+            Self::F32x4Ne => "f32x4.ne",
+            Self::F32x4Lt => "f32x4.lt",
+            Self::F32x4Gt => "f32x4.gt",
+            Self::F32x4Le => "f32x4.le",
+            Self::F32x4Ge => "f32x4.ge",
+            Self::F64x2Eq => "f64x2.eq",
+            Self::F64x2Ne => "f64x2.ne",
+            Self::F64x2Lt => "f64x2.lt",
+            Self::F64x2Gt => "f64x2.gt",
+            Self::F64x2Le => "f64x2.le",
+            Self::F64x2Ge => "f64x2.ge",
+            Self::V128Not => "v128.not",
+            Self::V128And => "v128.and",
+            Self::V128Andnot => "v128.andnot",
+            Self::V128Or => "v128.or",
+            Self::V128Xor => "v128.xor",
+            Self::V128Bitselect => "v128.bitselect",
+            Self::V128AnyTrue => "v128.any_true",
+            Self::V128Load8Lane(..) => "v128.load8_lane",
+            Self::V128Load16Lane(..) => "v128.load16_lane",
+            Self::V128Load32Lane(..) => "v128.load32_lane",
+            Self::V128Load64Lane(..) => "v128.load64_lane",
+            Self::V128Store8Lane(..) => "v128.store8_lane",
+            Self::V128Store16Lane(..) => "v128.store16_lane",
+            Self::V128Store32Lane(..) => "v128.store32_lane",
+            Self::V128Store64Lane(..) => "v128.store64_lane",
+            Self::V128Load32Zero(..) => "v128.load32_zero",
+            Self::V128Load64Zero(..) => "v128.load64_zero",
+            Self::F32x4DemoteF64x2Zero => "f32x4.demote_f64x2_zero",
+            Self::F64x2PromoteLowF32x4 => "f64x2.promote_low_f32x4",
+            Self::I8x16Abs => "i8x16.abs",
+            Self::I8x16Neg => "i8x16.neg",
+            Self::I8x16Popcnt => "i8x16.popcnt",
+            Self::I8x16AllTrue => "i8x16.all_true",
+            Self::I8x16Bitmask => "i8x16.bitmask",
+            Self::I8x16NarrowI16x8S => "i8x16.narrow_i16x8_s",
+            Self::I8x16NarrowI16x8U => "i8x16.narrow_i16x8_u",
+            Self::F32x4Ceil => "f32x4.ceil",
+            Self::F32x4Floor => "f32x4.floor",
+            Self::F32x4Trunc => "f32x4.trunc",
+            Self::F32x4Nearest => "f32x4.nearest",
+            Self::I8x16Shl => "i8x16.shl",
+            Self::I8x16ShrS => "i8x16.shr_s",
+            Self::I8x16ShrU => "i8x16.shr_u",
+            Self::I8x16Add => "i8x16.add",
+            Self::I8x16AddSatS => "i8x16.add_sat_s",
+            Self::I8x16AddSatU => "i8x16.add_sat_u",
+            Self::I8x16Sub => "i8x16.sub",
+            Self::I8x16SubSatS => "i8x16.sub_sat_s",
+            Self::I8x16SubSatU => "i8x16.sub_sat_u",
+            Self::F64x2Ceil => "f64x2.ceil",
+            Self::F64x2Floor => "f64x2.floor",
+            Self::I8x16MinS => "i8x16.min_s",
+            Self::I8x16MinU => "i8x16.min_u",
+            Self::I8x16MaxS => "i8x16.max_s",
+            Self::I8x16MaxU => "i8x16.max_u",
+            Self::F64x2Trunc => "f64x2.trunc",
+            Self::I8x16AvgrU => "i8x16.avgr_u",
+            Self::I16x8ExtaddPairwiseI8x16S => "i16x8.extadd_pairwise_i8x16_s",
+            Self::I16x8ExtaddPairwiseI8x16U => "i16x8.extadd_pairwise_i8x16_u",
+            Self::I32x4ExtaddPairwiseI16x8S => "i32x4.extadd_pairwise_i16x8_s",
+            Self::I32x4ExtaddPairwiseI16x8U => "i32x4.extadd_pairwise_i16x8_u",
+            Self::I16x8Abs => "i16x8.abs",
+            Self::I16x8Neg => "i16x8.neg",
+            Self::I16x8Q15mulrSatS => "i16x8.q15mulr_sat_s",
+            Self::I16x8AllTrue => "i16x8.all_true",
+            Self::I16x8Bitmask => "i16x8.bitmask",
+            Self::I16x8NarrowI32x4S => "i16x8.narrow_i32x4_s",
+            Self::I16x8NarrowI32x4U => "i16x8.narrow_i32x4_u",
+            Self::I16x8ExtendLowI8x16S => "i16x8.extend_low_i8x16_s",
+            Self::I16x8ExtendHighI8x16S => "i16x8.extend_high_i8x16_s",
+            Self::I16x8ExtendLowI8x16U => "i16x8.extend_low_i8x16_u",
+            Self::I16x8ExtendHighI8x16U => "i16x8.extend_high_i8x16_u",
+            Self::I16x8Shl => "i16x8.shl",
+            Self::I16x8ShrS => "i16x8.shr_s",
+            Self::I16x8ShrU => "i16x8.shr_u",
+            Self::I16x8Add => "i16x8.add",
+            Self::I16x8AddSatS => "i16x8.add_sat_s",
+            Self::I16x8AddSatU => "i16x8.add_sat_u",
+            Self::I16x8Sub => "i16x8.sub",
+            Self::I16x8SubSatS => "i16x8.sub_sat_s",
+            Self::I16x8SubSatU => "i16x8.sub_sat_u",
+            Self::F64x2Nearest => "f64x2.nearest",
+            Self::I16x8Mul => "i16x8.mul",
+            Self::I16x8MinS => "i16x8.min_s",
+            Self::I16x8MinU => "i16x8.min_u",
+            Self::I16x8MaxS => "i16x8.max_s",
+            Self::I16x8MaxU => "i16x8.max_u",
+            Self::I16x8AvgrU => "i16x8.avgr_u",
+            Self::I16x8ExtmulLowI8x16S => "i16x8.extmul_low_i8x16_s",
+            Self::I16x8ExtmulHighI8x16S => "i16x8.extmul_high_i8x16_s",
+            Self::I16x8ExtmulLowI8x16U => "i16x8.extmul_low_i8x16_u",
+            Self::I16x8ExtmulHighI8x16U => "i16x8.extmul_high_i8x16_u",
+            Self::I32x4Abs => "i32x4.abs",
+            Self::I32x4Neg => "i32x4.neg",
+            Self::I32x4AllTrue => "i32x4.all_true",
+            Self::I32x4Bitmask => "i32x4.bitmask",
+            Self::I32x4ExtendLowI16x8S => "i32x4.extend_low_i16x8_s",
+            Self::I32x4ExtendHighI16x8S => "i32x4.extend_high_i16x8_s",
+            Self::I32x4ExtendLowI16x8U => "i32x4.extend_low_i16x8_u",
+            Self::I32x4ExtendHighI16x8U => "i32x4.extend_high_i16x8_u",
+            Self::I32x4Shl => "i32x4.shl",
+            Self::I32x4ShrS => "i32x4.shr_s",
+            Self::I32x4ShrU => "i32x4.shr_u",
+            Self::I32x4Add => "i32x4.add",
+            Self::I32x4Sub => "i32x4.sub",
+            Self::I32x4Mul => "i32x4.mul",
+            Self::I32x4MinS => "i32x4.min_s",
+            Self::I32x4MinU => "i32x4.min_u",
+            Self::I32x4MaxS => "i32x4.max_s",
+            Self::I32x4MaxU => "i32x4.max_u",
+            Self::I32x4DotI16x8S => "i32x4.dot_i16x8_s",
+            Self::I32x4ExtmulLowI16x8S => "i32x4.extmul_low_i16x8_s",
+            Self::I32x4ExtmulHighI16x8S => "i32x4.extmul_high_i16x8_s",
+            Self::I32x4ExtmulLowI16x8U => "i32x4.extmul_low_i16x8_u",
+            Self::I32x4ExtmulHighI16x8U => "i32x4.extmul_high_i16x8_u",
+            Self::I64x2Abs => "i64x2.abs",
+            Self::I64x2Neg => "i64x2.neg",
+            Self::I64x2AllTrue => "i64x2.all_true",
+            Self::I64x2Bitmask => "i64x2.bitmask",
+            Self::I64x2ExtendLowI32x4S => "i64x2.extend_low_i32x4_s",
+            Self::I64x2ExtendHighI32x4S => "i64x2.extend_high_i32x4_s",
+            Self::I64x2ExtendLowI32x4U => "i64x2.extend_low_i32x4_u",
+            Self::I64x2ExtendHighI32x4U => "i64x2.extend_high_i32x4_u",
+            Self::I64x2Shl => "i64x2.shl",
+            Self::I64x2ShrS => "i64x2.shr_s",
+            Self::I64x2ShrU => "i64x2.shr_u",
+            Self::I64x2Add => "i64x2.add",
+            Self::I64x2Sub => "i64x2.sub",
+            Self::I64x2Mul => "i64x2.mul",
+            Self::I64x2Eq => "i64x2.eq",
+            Self::I64x2Ne => "i64x2.ne",
+            Self::I64x2LtS => "i64x2.lt_s",
+            Self::I64x2GtS => "i64x2.gt_s",
+            Self::I64x2LeS => "i64x2.le_s",
+            Self::I64x2GeS => "i64x2.ge_s",
+            Self::I64x2ExtmulLowI32x4S => "i64x2.extmul_low_i32x4_s",
+            Self::I64x2ExtmulHighI32x4S => "i64x2.extmul_high_i32x4_s",
+            Self::I64x2ExtmulLowI32x4U => "i64x2.extmul_low_i32x4_u",
+            Self::I64x2ExtmulHighI32x4U => "i64x2.extmul_high_i32x4_u",
+            Self::F32x4Abs => "f32x4.abs",
+            Self::F32x4Neg => "f32x4.neg",
+            Self::F32x4Sqrt => "f32x4.sqrt",
+            Self::F32x4Add => "f32x4.add",
+            Self::F32x4Sub => "f32x4.sub",
+            Self::F32x4Mul => "f32x4.mul",
+            Self::F32x4Div => "f32x4.div",
+            Self::F32x4Min => "f32x4.min",
+            Self::F32x4Max => "f32x4.max",
+            Self::F32x4Pmin => "f32x4.pmin",
+            Self::F32x4Pmax => "f32x4.pmax",
+            Self::F64x2Abs => "f64x2.abs",
+            Self::F64x2Neg => "f64x2.neg",
+            Self::F64x2Sqrt => "f64x2.sqrt",
+            Self::F64x2Add => "f64x2.add",
+            Self::F64x2Sub => "f64x2.sub",
+            Self::F64x2Mul => "f64x2.mul",
+            Self::F64x2Div => "f64x2.div",
+            Self::F64x2Min => "f64x2.min",
+            Self::F64x2Max => "f64x2.max",
+            Self::F64x2Pmin => "f64x2.pmin",
+            Self::F64x2Pmax => "f64x2.pmax",
+            Self::I32x4TruncSatF32x4S => "i32x4.trunc_sat_f32x4_s",
+            Self::I32x4TruncSatF32x4U => "i32x4.trunc_sat_f32x4_u",
+            Self::F32x4ConvertI32x4S => "f32x4.convert_i32x4_s",
+            Self::F32x4ConvertI32x4U => "f32x4.convert_i32x4_u",
+            Self::I32x4TruncSatF64x2SZero => "i32x4.trunc_sat_f64x2_s_zero",
+            Self::I32x4TruncSatF64x2UZero => "i32x4.trunc_sat_f64x2_u_zero",
+            Self::F64x2ConvertLowI32x4S => "f64x2.convert_low_i32x4_s",
+            Self::F64x2ConvertLowI32x4U => "f64x2.convert_low_i32x4_u",
+        }
+    }
+}
+
+impl Decode for VectorInstruction {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, _) = tag([0xFD])(input)?;
+        let (input, opcode) = u32::decode(input)?;
+        match opcode {
+            0 => map(MemArg::decode, Self::V128Load)(input),
+            1 => map(MemArg::decode, Self::V128Load8x8S)(input),
+            2 => map(MemArg::decode, Self::V128Load8x8U)(input),
+            3 => map(MemArg::decode, Self::V128Load16x4S)(input),
+            4 => map(MemArg::decode, Self::V128Load16x4U)(input),
+            5 => map(MemArg::decode, Self::V128Load32x2S)(input),
+            6 => map(MemArg::decode, Self::V128Load32x2U)(input),
+            7 => map(MemArg::decode, Self::V128Load8Splat)(input),
+            8 => map(MemArg::decode, Self::V128Load16Splat)(input),
+            9 => map(MemArg::decode, Self::V128Load32Splat)(input),
+            10 => map(MemArg::decode, Self::V128Load64Splat)(input),
+            11 => map(MemArg::decode, Self::V128Store)(input),
+            12 => map(u128::decode, Self::V128Const)(input),
+            13 => map(lanes, Self::I8x16Shuffle)(input),
+            14 => Ok((input, Self::I8x16Swizzle)),
+            15 => Ok((input, Self::I8x16Splat)),
+            16 => Ok((input, Self::I16x8Splat)),
+            17 => Ok((input, Self::I32x4Splat)),
+            18 => Ok((input, Self::I64x2Splat)),
+            19 => Ok((input, Self::F32x4Splat)),
+            20 => Ok((input, Self::F64x2Splat)),
+            21 => map(LaneIdx::decode, Self::I8x16ExtractLaneS)(input),
+            22 => map(LaneIdx::decode, Self::I8x16ExtractLaneU)(input),
+            23 => map(LaneIdx::decode, Self::I8x16ReplaceLane)(input),
+            24 => map(LaneIdx::decode, Self::I16x8ExtractLaneS)(input),
+            25 => map(LaneIdx::decode, Self::I16x8ExtractLaneU)(input),
+            26 => map(LaneIdx::decode, Self::I16x8ReplaceLane)(input),
+            27 => map(LaneIdx::decode, Self::I32x4ExtractLane)(input),
+            28 => map(LaneIdx::decode, Self::I32x4ReplaceLane)(input),
+            29 => map(LaneIdx::decode, Self::I64x2ExtractLane)(input),
+            30 => map(LaneIdx::decode, Self::I64x2ReplaceLane)(input),
+            31 => map(LaneIdx::decode, Self::F32x4ExtractLane)(input),
+            32 => map(LaneIdx::decode, Self::F32x4ReplaceLane)(input),
+            33 => map(LaneIdx::decode, Self::F64x2ExtractLane)(input),
+            34 => map(LaneIdx::decode, Self::F64x2ReplaceLane)(input),
+            35 => Ok((input, Self::I8x16Eq)),
+            36 => Ok((input, Self::I8x16Ne)),
+            37 => Ok((input, Self::I8x16LtS)),
+            38 => Ok((input, Self::I8x16LtU)),
+            39 => Ok((input, Self::I8x16GtS)),
+            40 => Ok((input, Self::I8x16GtU)),
+            41 => Ok((input, Self::I8x16LeS)),
+            42 => Ok((input, Self::I8x16LeU)),
+            43 => Ok((input, Self::I8x16GeS)),
+            44 => Ok((input, Self::I8x16GeU)),
+            45 => Ok((input, Self::I16x8Eq)),
+            46 => Ok((input, Self::I16x8Ne)),
+            47 => Ok((input, Self::I16x8LtS)),
+            48 => Ok((input, Self::I16x8LtU)),
+            49 => Ok((input, Self::I16x8GtS)),
+            50 => Ok((input, Self::I16x8GtU)),
+            51 => Ok((input, Self::I16x8LeS)),
+            52 => Ok((input, Self::I16x8LeU)),
+            53 => Ok((input, Self::I16x8GeS)),
+            54 => Ok((input, Self::I16x8GeU)),
+            55 => Ok((input, Self::I32x4Eq)),
+            56 => Ok((input, Self::I32x4Ne)),
+            57 => Ok((input, Self::I32x4LtS)),
+            58 => Ok((input, Self::I32x4LtU)),
+            59 => Ok((input, Self::I32x4GtS)),
+            60 => Ok((input, Self::I32x4GtU)),
+            61 => Ok((input, Self::I32x4LeS)),
+            62 => Ok((input, Self::I32x4LeU)),
+            63 => Ok((input, Self::I32x4GeS)),
+            64 => Ok((input, Self::I32x4GeU)),
+            65 => Ok((input, Self::F32x4Eq)),
+            66 => Ok((input, Self::F32x4Ne)),
+            67 => Ok((input, Self::F32x4Lt)),
+            68 => Ok((input, Self::F32x4Gt)),
+            69 => Ok((input, Self::F32x4Le)),
+            70 => Ok((input, Self::F32x4Ge)),
+            71 => Ok((input, Self::F64x2Eq)),
+            72 => Ok((input, Self::F64x2Ne)),
+            73 => Ok((input, Self::F64x2Lt)),
+            74 => Ok((input, Self::F64x2Gt)),
+            75 => Ok((input, Self::F64x2Le)),
+            76 => Ok((input, Self::F64x2Ge)),
+            77 => Ok((input, Self::V128Not)),
+            78 => Ok((input, Self::V128And)),
+            79 => Ok((input, Self::V128Andnot)),
+            80 => Ok((input, Self::V128Or)),
+            81 => Ok((input, Self::V128Xor)),
+            82 => Ok((input, Self::V128Bitselect)),
+            83 => Ok((input, Self::V128AnyTrue)),
+            84 => map(pair(MemArg::decode, LaneIdx::decode), |pair| {
+                Self::V128Load8Lane(pair.0, pair.1)
+            })(input),
+            85 => map(pair(MemArg::decode, LaneIdx::decode), |pair| {
+                Self::V128Load16Lane(pair.0, pair.1)
+            })(input),
+            86 => map(pair(MemArg::decode, LaneIdx::decode), |pair| {
+                Self::V128Load32Lane(pair.0, pair.1)
+            })(input),
+            87 => map(pair(MemArg::decode, LaneIdx::decode), |pair| {
+                Self::V128Load64Lane(pair.0, pair.1)
+            })(input),
+            88 => map(pair(MemArg::decode, LaneIdx::decode), |pair| {
+                Self::V128Store8Lane(pair.0, pair.1)
+            })(input),
+            89 => map(pair(MemArg::decode, LaneIdx::decode), |pair| {
+                Self::V128Store16Lane(pair.0, pair.1)
+            })(input),
+            90 => map(pair(MemArg::decode, LaneIdx::decode), |pair| {
+                Self::V128Store32Lane(pair.0, pair.1)
+            })(input),
+            91 => map(pair(MemArg::decode, LaneIdx::decode), |pair| {
+                Self::V128Store64Lane(pair.0, pair.1)
+            })(input),
+            92 => map(MemArg::decode, Self::V128Load32Zero)(input),
+            93 => map(MemArg::decode, Self::V128Load64Zero)(input),
+            94 => Ok((input, Self::F32x4DemoteF64x2Zero)),
+            95 => Ok((input, Self::F64x2PromoteLowF32x4)),
+            96 => Ok((input, Self::I8x16Abs)),
+            97 => Ok((input, Self::I8x16Neg)),
+            98 => Ok((input, Self::I8x16Popcnt)),
+            99 => Ok((input, Self::I8x16AllTrue)),
+            100 => Ok((input, Self::I8x16Bitmask)),
+            101 => Ok((input, Self::I8x16NarrowI16x8S)),
+            102 => Ok((input, Self::I8x16NarrowI16x8U)),
+            103 => Ok((input, Self::F32x4Ceil)),
+            104 => Ok((input, Self::F32x4Floor)),
+            105 => Ok((input, Self::F32x4Trunc)),
+            106 => Ok((input, Self::F32x4Nearest)),
+            107 => Ok((input, Self::I8x16Shl)),
+            108 => Ok((input, Self::I8x16ShrS)),
+            109 => Ok((input, Self::I8x16ShrU)),
+            110 => Ok((input, Self::I8x16Add)),
+            111 => Ok((input, Self::I8x16AddSatS)),
+            112 => Ok((input, Self::I8x16AddSatU)),
+            113 => Ok((input, Self::I8x16Sub)),
+            114 => Ok((input, Self::I8x16SubSatS)),
+            115 => Ok((input, Self::I8x16SubSatU)),
+            116 => Ok((input, Self::F64x2Ceil)),
+            117 => Ok((input, Self::F64x2Floor)),
+            118 => Ok((input, Self::I8x16MinS)),
+            119 => Ok((input, Self::I8x16MinU)),
+            120 => Ok((input, Self::I8x16MaxS)),
+            121 => Ok((input, Self::I8x16MaxU)),
+            122 => Ok((input, Self::F64x2Trunc)),
+            123 => Ok((input, Self::I8x16AvgrU)),
+            124 => Ok((input, Self::I16x8ExtaddPairwiseI8x16S)),
+            125 => Ok((input, Self::I16x8ExtaddPairwiseI8x16U)),
+            126 => Ok((input, Self::I32x4ExtaddPairwiseI16x8S)),
+            127 => Ok((input, Self::I32x4ExtaddPairwiseI16x8U)),
+            128 => Ok((input, Self::I16x8Abs)),
+            129 => Ok((input, Self::I16x8Neg)),
+            130 => Ok((input, Self::I16x8Q15mulrSatS)),
+            131 => Ok((input, Self::I16x8AllTrue)),
+            132 => Ok((input, Self::I16x8Bitmask)),
+            133 => Ok((input, Self::I16x8NarrowI32x4S)),
+            134 => Ok((input, Self::I16x8NarrowI32x4U)),
+            135 => Ok((input, Self::I16x8ExtendLowI8x16S)),
+            136 => Ok((input, Self::I16x8ExtendHighI8x16S)),
+            137 => Ok((input, Self::I16x8ExtendLowI8x16U)),
+            138 => Ok((input, Self::I16x8ExtendHighI8x16U)),
+            139 => Ok((input, Self::I16x8Shl)),
+            140 => Ok((input, Self::I16x8ShrS)),
+            141 => Ok((input, Self::I16x8ShrU)),
+            142 => Ok((input, Self::I16x8Add)),
+            143 => Ok((input, Self::I16x8AddSatS)),
+            144 => Ok((input, Self::I16x8AddSatU)),
+            145 => Ok((input, Self::I16x8Sub)),
+            146 => Ok((input, Self::I16x8SubSatS)),
+            147 => Ok((input, Self::I16x8SubSatU)),
+            148 => Ok((input, Self::F64x2Nearest)),
+            149 => Ok((input, Self::I16x8Mul)),
+            150 => Ok((input, Self::I16x8MinS)),
+            151 => Ok((input, Self::I16x8MinU)),
+            152 => Ok((input, Self::I16x8MaxS)),
+            153 => Ok((input, Self::I16x8MaxU)),
+            155 => Ok((input, Self::I16x8AvgrU)),
+            156 => Ok((input, Self::I16x8ExtmulLowI8x16S)),
+            157 => Ok((input, Self::I16x8ExtmulHighI8x16S)),
+            158 => Ok((input, Self::I16x8ExtmulLowI8x16U)),
+            159 => Ok((input, Self::I16x8ExtmulHighI8x16U)),
+            160 => Ok((input, Self::I32x4Abs)),
+            161 => Ok((input, Self::I32x4Neg)),
+            163 => Ok((input, Self::I32x4AllTrue)),
+            164 => Ok((input, Self::I32x4Bitmask)),
+            167 => Ok((input, Self::I32x4ExtendLowI16x8S)),
+            168 => Ok((input, Self::I32x4ExtendHighI16x8S)),
+            169 => Ok((input, Self::I32x4ExtendLowI16x8U)),
+            170 => Ok((input, Self::I32x4ExtendHighI16x8U)),
+            171 => Ok((input, Self::I32x4Shl)),
+            172 => Ok((input, Self::I32x4ShrS)),
+            173 => Ok((input, Self::I32x4ShrU)),
+            174 => Ok((input, Self::I32x4Add)),
+            177 => Ok((input, Self::I32x4Sub)),
+            181 => Ok((input, Self::I32x4Mul)),
+            182 => Ok((input, Self::I32x4MinS)),
+            183 => Ok((input, Self::I32x4MinU)),
+            184 => Ok((input, Self::I32x4MaxS)),
+            185 => Ok((input, Self::I32x4MaxU)),
+            186 => Ok((input, Self::I32x4DotI16x8S)),
+            188 => Ok((input, Self::I32x4ExtmulLowI16x8S)),
+            189 => Ok((input, Self::I32x4ExtmulHighI16x8S)),
+            190 => Ok((input, Self::I32x4ExtmulLowI16x8U)),
+            191 => Ok((input, Self::I32x4ExtmulHighI16x8U)),
+            192 => Ok((input, Self::I64x2Abs)),
+            193 => Ok((input, Self::I64x2Neg)),
+            195 => Ok((input, Self::I64x2AllTrue)),
+            196 => Ok((input, Self::I64x2Bitmask)),
+            199 => Ok((input, Self::I64x2ExtendLowI32x4S)),
+            200 => Ok((input, Self::I64x2ExtendHighI32x4S)),
+            201 => Ok((input, Self::I64x2ExtendLowI32x4U)),
+            202 => Ok((input, Self::I64x2ExtendHighI32x4U)),
+            203 => Ok((input, Self::I64x2Shl)),
+            204 => Ok((input, Self::I64x2ShrS)),
+            205 => Ok((input, Self::I64x2ShrU)),
+            206 => Ok((input, Self::I64x2Add)),
+            209 => Ok((input, Self::I64x2Sub)),
+            213 => Ok((input, Self::I64x2Mul)),
+            214 => Ok((input, Self::I64x2Eq)),
+            215 => Ok((input, Self::I64x2Ne)),
+            216 => Ok((input, Self::I64x2LtS)),
+            217 => Ok((input, Self::I64x2GtS)),
+            218 => Ok((input, Self::I64x2LeS)),
+            219 => Ok((input, Self::I64x2GeS)),
+            220 => Ok((input, Self::I64x2ExtmulLowI32x4S)),
+            221 => Ok((input, Self::I64x2ExtmulHighI32x4S)),
+            222 => Ok((input, Self::I64x2ExtmulLowI32x4U)),
+            223 => Ok((input, Self::I64x2ExtmulHighI32x4U)),
+            224 => Ok((input, Self::F32x4Abs)),
+            225 => Ok((input, Self::F32x4Neg)),
+            227 => Ok((input, Self::F32x4Sqrt)),
+            228 => Ok((input, Self::F32x4Add)),
+            229 => Ok((input, Self::F32x4Sub)),
+            230 => Ok((input, Self::F32x4Mul)),
+            231 => Ok((input, Self::F32x4Div)),
+            232 => Ok((input, Self::F32x4Min)),
+            233 => Ok((input, Self::F32x4Max)),
+            234 => Ok((input, Self::F32x4Pmin)),
+            235 => Ok((input, Self::F32x4Pmax)),
+            236 => Ok((input, Self::F64x2Abs)),
+            237 => Ok((input, Self::F64x2Neg)),
+            239 => Ok((input, Self::F64x2Sqrt)),
+            240 => Ok((input, Self::F64x2Add)),
+            241 => Ok((input, Self::F64x2Sub)),
+            242 => Ok((input, Self::F64x2Mul)),
+            243 => Ok((input, Self::F64x2Div)),
+            244 => Ok((input, Self::F64x2Min)),
+            245 => Ok((input, Self::F64x2Max)),
+            246 => Ok((input, Self::F64x2Pmin)),
+            247 => Ok((input, Self::F64x2Pmax)),
+            248 => Ok((input, Self::I32x4TruncSatF32x4S)),
+            249 => Ok((input, Self::I32x4TruncSatF32x4U)),
+            250 => Ok((input, Self::F32x4ConvertI32x4S)),
+            251 => Ok((input, Self::F32x4ConvertI32x4U)),
+            252 => Ok((input, Self::I32x4TruncSatF64x2SZero)),
+            253 => Ok((input, Self::I32x4TruncSatF64x2UZero)),
+            254 => Ok((input, Self::F64x2ConvertLowI32x4S)),
+            255 => Ok((input, Self::F64x2ConvertLowI32x4U)),
+            _ => fail(input),
+        }
+    }
+}
+
+impl VectorInstruction {
+    /// The opcode that follows the 0xFD prefix
+    fn opcode(&self) -> u32 {
+        match self {
+            Self::V128Load(..) => 0,
+            Self::V128Load8x8S(..) => 1,
+            Self::V128Load8x8U(..) => 2,
+            Self::V128Load16x4S(..) => 3,
+            Self::V128Load16x4U(..) => 4,
+            Self::V128Load32x2S(..) => 5,
+            Self::V128Load32x2U(..) => 6,
+            Self::V128Load8Splat(..) => 7,
+            Self::V128Load16Splat(..) => 8,
+            Self::V128Load32Splat(..) => 9,
+            Self::V128Load64Splat(..) => 10,
+            Self::V128Store(..) => 11,
+            Self::V128Const(..) => 12,
+            Self::I8x16Shuffle(..) => 13,
+            Self::I8x16Swizzle => 14,
+            Self::I8x16Splat => 15,
+            Self::I16x8Splat => 16,
+            Self::I32x4Splat => 17,
+            Self::I64x2Splat => 18,
+            Self::F32x4Splat => 19,
+            Self::F64x2Splat => 20,
+            Self::I8x16ExtractLaneS(..) => 21,
+            Self::I8x16ExtractLaneU(..) => 22,
+            Self::I8x16ReplaceLane(..) => 23,
+            Self::I16x8ExtractLaneS(..) => 24,
+            Self::I16x8ExtractLaneU(..) => 25,
+            Self::I16x8ReplaceLane(..) => 26,
+            Self::I32x4ExtractLane(..) => 27,
+            Self::I32x4ReplaceLane(..) => 28,
+            Self::I64x2ExtractLane(..) => 29,
+            Self::I64x2ReplaceLane(..) => 30,
+            Self::F32x4ExtractLane(..) => 31,
+            Self::F32x4ReplaceLane(..) => 32,
+            Self::F64x2ExtractLane(..) => 33,
+            Self::F64x2ReplaceLane(..) => 34,
+            Self::I8x16Eq => 35,
+            Self::I8x16Ne => 36,
+            Self::I8x16LtS => 37,
+            Self::I8x16LtU => 38,
+            Self::I8x16GtS => 39,
+            Self::I8x16GtU => 40,
+            Self::I8x16LeS => 41,
+            Self::I8x16LeU => 42,
+            Self::I8x16GeS => 43,
+            Self::I8x16GeU => 44,
+            Self::I16x8Eq => 45,
+            Self::I16x8Ne => 46,
+            Self::I16x8LtS => 47,
+            Self::I16x8LtU => 48,
+            Self::I16x8GtS => 49,
+            Self::I16x8GtU => 50,
+            Self::I16x8LeS => 51,
+            Self::I16x8LeU => 52,
+            Self::I16x8GeS => 53,
+            Self::I16x8GeU => 54,
+            Self::I32x4Eq => 55,
+            Self::I32x4Ne => 56,
+            Self::I32x4LtS => 57,
+            Self::I32x4LtU => 58,
+            Self::I32x4GtS => 59,
+            Self::I32x4GtU => 60,
+            Self::I32x4LeS => 61,
+            Self::I32x4LeU => 62,
+            Self::I32x4GeS => 63,
+            Self::I32x4GeU => 64,
+            Self::F32x4Eq => 65,
+            Self::F32x4Ne => 66,
+            Self::F32x4Lt => 67,
+            Self::F32x4Gt => 68,
+            Self::F32x4Le => 69,
+            Self::F32x4Ge => 70,
+            Self::F64x2Eq => 71,
+            Self::F64x2Ne => 72,
+            Self::F64x2Lt => 73,
+            Self::F64x2Gt => 74,
+            Self::F64x2Le => 75,
+            Self::F64x2Ge => 76,
+            Self::V128Not => 77,
+            Self::V128And => 78,
+            Self::V128Andnot => 79,
+            Self::V128Or => 80,
+            Self::V128Xor => 81,
+            Self::V128Bitselect => 82,
+            Self::V128AnyTrue => 83,
+            Self::V128Load8Lane(..) => 84,
+            Self::V128Load16Lane(..) => 85,
+            Self::V128Load32Lane(..) => 86,
+            Self::V128Load64Lane(..) => 87,
+            Self::V128Store8Lane(..) => 88,
+            Self::V128Store16Lane(..) => 89,
+            Self::V128Store32Lane(..) => 90,
+            Self::V128Store64Lane(..) => 91,
+            Self::V128Load32Zero(..) => 92,
+            Self::V128Load64Zero(..) => 93,
+            Self::F32x4DemoteF64x2Zero => 94,
+            Self::F64x2PromoteLowF32x4 => 95,
+            Self::I8x16Abs => 96,
+            Self::I8x16Neg => 97,
+            Self::I8x16Popcnt => 98,
+            Self::I8x16AllTrue => 99,
+            Self::I8x16Bitmask => 100,
+            Self::I8x16NarrowI16x8S => 101,
+            Self::I8x16NarrowI16x8U => 102,
+            Self::F32x4Ceil => 103,
+            Self::F32x4Floor => 104,
+            Self::F32x4Trunc => 105,
+            Self::F32x4Nearest => 106,
+            Self::I8x16Shl => 107,
+            Self::I8x16ShrS => 108,
+            Self::I8x16ShrU => 109,
+            Self::I8x16Add => 110,
+            Self::I8x16AddSatS => 111,
+            Self::I8x16AddSatU => 112,
+            Self::I8x16Sub => 113,
+            Self::I8x16SubSatS => 114,
+            Self::I8x16SubSatU => 115,
+            Self::F64x2Ceil => 116,
+            Self::F64x2Floor => 117,
+            Self::I8x16MinS => 118,
+            Self::I8x16MinU => 119,
+            Self::I8x16MaxS => 120,
+            Self::I8x16MaxU => 121,
+            Self::F64x2Trunc => 122,
+            Self::I8x16AvgrU => 123,
+            Self::I16x8ExtaddPairwiseI8x16S => 124,
+            Self::I16x8ExtaddPairwiseI8x16U => 125,
+            Self::I32x4ExtaddPairwiseI16x8S => 126,
+            Self::I32x4ExtaddPairwiseI16x8U => 127,
+            Self::I16x8Abs => 128,
+            Self::I16x8Neg => 129,
+            Self::I16x8Q15mulrSatS => 130,
+            Self::I16x8AllTrue => 131,
+            Self::I16x8Bitmask => 132,
+            Self::I16x8NarrowI32x4S => 133,
+            Self::I16x8NarrowI32x4U => 134,
+            Self::I16x8ExtendLowI8x16S => 135,
+            Self::I16x8ExtendHighI8x16S => 136,
+            Self::I16x8ExtendLowI8x16U => 137,
+            Self::I16x8ExtendHighI8x16U => 138,
+            Self::I16x8Shl => 139,
+            Self::I16x8ShrS => 140,
+            Self::I16x8ShrU => 141,
+            Self::I16x8Add => 142,
+            Self::I16x8AddSatS => 143,
+            Self::I16x8AddSatU => 144,
+            Self::I16x8Sub => 145,
+            Self::I16x8SubSatS => 146,
+            Self::I16x8SubSatU => 147,
+            Self::F64x2Nearest => 148,
+            Self::I16x8Mul => 149,
+            Self::I16x8MinS => 150,
+            Self::I16x8MinU => 151,
+            Self::I16x8MaxS => 152,
+            Self::I16x8MaxU => 153,
+            Self::I16x8AvgrU => 155,
+            Self::I16x8ExtmulLowI8x16S => 156,
+            Self::I16x8ExtmulHighI8x16S => 157,
+            Self::I16x8ExtmulLowI8x16U => 158,
+            Self::I16x8ExtmulHighI8x16U => 159,
+            Self::I32x4Abs => 160,
+            Self::I32x4Neg => 161,
+            Self::I32x4AllTrue => 163,
+            Self::I32x4Bitmask => 164,
+            Self::I32x4ExtendLowI16x8S => 167,
+            Self::I32x4ExtendHighI16x8S => 168,
+            Self::I32x4ExtendLowI16x8U => 169,
+            Self::I32x4ExtendHighI16x8U => 170,
+            Self::I32x4Shl => 171,
+            Self::I32x4ShrS => 172,
+            Self::I32x4ShrU => 173,
+            Self::I32x4Add => 174,
+            Self::I32x4Sub => 177,
+            Self::I32x4Mul => 181,
+            Self::I32x4MinS => 182,
+            Self::I32x4MinU => 183,
+            Self::I32x4MaxS => 184,
+            Self::I32x4MaxU => 185,
+            Self::I32x4DotI16x8S => 186,
+            Self::I32x4ExtmulLowI16x8S => 188,
+            Self::I32x4ExtmulHighI16x8S => 189,
+            Self::I32x4ExtmulLowI16x8U => 190,
+            Self::I32x4ExtmulHighI16x8U => 191,
+            Self::I64x2Abs => 192,
+            Self::I64x2Neg => 193,
+            Self::I64x2AllTrue => 195,
+            Self::I64x2Bitmask => 196,
+            Self::I64x2ExtendLowI32x4S => 199,
+            Self::I64x2ExtendHighI32x4S => 200,
+            Self::I64x2ExtendLowI32x4U => 201,
+            Self::I64x2ExtendHighI32x4U => 202,
+            Self::I64x2Shl => 203,
+            Self::I64x2ShrS => 204,
+            Self::I64x2ShrU => 205,
+            Self::I64x2Add => 206,
+            Self::I64x2Sub => 209,
+            Self::I64x2Mul => 213,
+            Self::I64x2Eq => 214,
+            Self::I64x2Ne => 215,
+            Self::I64x2LtS => 216,
+            Self::I64x2GtS => 217,
+            Self::I64x2LeS => 218,
+            Self::I64x2GeS => 219,
+            Self::I64x2ExtmulLowI32x4S => 220,
+            Self::I64x2ExtmulHighI32x4S => 221,
+            Self::I64x2ExtmulLowI32x4U => 222,
+            Self::I64x2ExtmulHighI32x4U => 223,
+            Self::F32x4Abs => 224,
+            Self::F32x4Neg => 225,
+            Self::F32x4Sqrt => 227,
+            Self::F32x4Add => 228,
+            Self::F32x4Sub => 229,
+            Self::F32x4Mul => 230,
+            Self::F32x4Div => 231,
+            Self::F32x4Min => 232,
+            Self::F32x4Max => 233,
+            Self::F32x4Pmin => 234,
+            Self::F32x4Pmax => 235,
+            Self::F64x2Abs => 236,
+            Self::F64x2Neg => 237,
+            Self::F64x2Sqrt => 239,
+            Self::F64x2Add => 240,
+            Self::F64x2Sub => 241,
+            Self::F64x2Mul => 242,
+            Self::F64x2Div => 243,
+            Self::F64x2Min => 244,
+            Self::F64x2Max => 245,
+            Self::F64x2Pmin => 246,
+            Self::F64x2Pmax => 247,
+            Self::I32x4TruncSatF32x4S => 248,
+            Self::I32x4TruncSatF32x4U => 249,
+            Self::F32x4ConvertI32x4S => 250,
+            Self::F32x4ConvertI32x4U => 251,
+            Self::I32x4TruncSatF64x2SZero => 252,
+            Self::I32x4TruncSatF64x2UZero => 253,
+            Self::F64x2ConvertLowI32x4S => 254,
+            Self::F64x2ConvertLowI32x4U => 255,
+        }
+    }
+}
+
+impl Encode for VectorInstruction {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(0xFD);
+        self.opcode().encode(out);
+        match self {
+            Self::V128Load(memarg)
+            | Self::V128Load8x8S(memarg)
+            | Self::V128Load8x8U(memarg)
+            | Self::V128Load16x4S(memarg)
+            | Self::V128Load16x4U(memarg)
+            | Self::V128Load32x2S(memarg)
+            | Self::V128Load32x2U(memarg)
+            | Self::V128Load8Splat(memarg)
+            | Self::V128Load16Splat(memarg)
+            | Self::V128Load32Splat(memarg)
+            | Self::V128Load64Splat(memarg)
+            | Self::V128Store(memarg)
+            | Self::V128Load32Zero(memarg)
+            | Self::V128Load64Zero(memarg) => memarg.encode(out),
+            Self::I8x16ExtractLaneS(lane)
+            | Self::I8x16ExtractLaneU(lane)
+            | Self::I8x16ReplaceLane(lane)
+            | Self::I16x8ExtractLaneS(lane)
+            | Self::I16x8ExtractLaneU(lane)
+            | Self::I16x8ReplaceLane(lane)
+            | Self::I32x4ExtractLane(lane)
+            | Self::I32x4ReplaceLane(lane)
+            | Self::I64x2ExtractLane(lane)
+            | Self::I64x2ReplaceLane(lane)
+            | Self::F32x4ExtractLane(lane)
+            | Self::F32x4ReplaceLane(lane)
+            | Self::F64x2ExtractLane(lane)
+            | Self::F64x2ReplaceLane(lane) => lane.encode(out),
+            Self::V128Load8Lane(memarg, lane)
+            | Self::V128Load16Lane(memarg, lane)
+            | Self::V128Load32Lane(memarg, lane)
+            | Self::V128Load64Lane(memarg, lane)
+            | Self::V128Store8Lane(memarg, lane)
+            | Self::V128Store16Lane(memarg, lane)
+            | Self::V128Store32Lane(memarg, lane)
+            | Self::V128Store64Lane(memarg, lane) => {
+                memarg.encode(out);
+                lane.encode(out);
+            }
+            Self::V128Const(n) => n.encode(out),
+            Self::I8x16Shuffle(lanes) => out.extend_from_slice(lanes),
+            _ => (),
+        }
+    }
+}
+
+/// Decode the 16 lane indices of `i8x16.shuffle`
+fn lanes(input: &[u8]) -> IResult<&[u8], [LaneIdx; 16]> {
+    map(take(16usize), |bytes: &[u8]| {
+        let mut lanes = [0; 16];
+        lanes.copy_from_slice(bytes);
+        lanes
+    })(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY: &[u8] = &[];
+
+    #[test]
+    fn test_vector_instruction() {
+        assert_eq!(
+            VectorInstruction::decode(&[0xFD, 0x00, 0x04, 0x10]),
+            Ok((
+                EMPTY,
+                VectorInstruction::V128Load(MemArg {
+                    align: 4,
+                    offset: 16,
+                    memidx: 0
+                })
+            ))
+        );
+        assert_eq!(
+            VectorInstruction::decode(&[0xFD, 0x54, 0x00, 0x00, 0x0F]),
+            Ok((
+                EMPTY,
+                VectorInstruction::V128Load8Lane(
+                    MemArg {
+                        align: 0,
+                        offset: 0,
+                        memidx: 0
+                    },
+                    15
+                )
+            ))
+        );
+        assert_eq!(
+            VectorInstruction::decode(&[0xFD, 0x15, 0x03]),
+            Ok((EMPTY, VectorInstruction::I8x16ExtractLaneS(3)))
+        );
+        // Opcodes above 127 take more than one byte
+        assert_eq!(
+            VectorInstruction::decode(&[0xFD, 0xFF, 0x01]),
+            Ok((EMPTY, VectorInstruction::F64x2ConvertLowI32x4U))
+        );
+        assert_eq!(
+            VectorInstruction::decode(&[0xFD, 0x6E]),
+            Ok((EMPTY, VectorInstruction::I8x16Add))
+        );
+        assert!(VectorInstruction::decode(&[0xFD, 0x9A, 0x01]).is_err());
+        assert!(VectorInstruction::decode(&[0xFC, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_vector_immediates() {
+        let mut bytes = vec![0xFD, 0x0C];
+        bytes.extend(1u128.to_le_bytes());
+        assert_eq!(
+            VectorInstruction::decode(&bytes),
+            Ok((EMPTY, VectorInstruction::V128Const(1)))
+        );
+
+        let mut bytes = vec![0xFD, 0x0D];
+        bytes.extend(0..16);
+        assert_eq!(
+            VectorInstruction::decode(&bytes),
+            Ok((
+                EMPTY,
+                VectorInstruction::I8x16Shuffle([
+                    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15
+                ])
+            ))
+        );
+        assert!(VectorInstruction::decode(&bytes[..17]).is_err());
+    }
+}
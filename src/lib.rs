@@ -1,10 +1,56 @@
-pub use module::Module;
+pub use features::Features;
+pub use inspect::Inspect;
+pub use instructions::{
+    AtomicInstruction, AtomicOp, BlockType, CatchClause, ControlInstruction, Expression,
+    GcInstruction, Instruction, MemArg, MemoryInstruction, NumericInstruction,
+    ParametricInstruction, ReferenceInstruction, RmwOp, SaturatingTruncationInstruction,
+    TableInstruction, VariableInstruction, VectorInstruction,
+};
+pub use module::{
+    builder::{FunctionBuilder, ModuleBuilder},
+    code::{Code, Func, Local},
+    custom::CustomSection,
+    data::Data,
+    element::{Element, ElementKind},
+    export::{Export, ExportDescriptor},
+    global::Global,
+    import::{Import, ImportDescriptor},
+    indices::{
+        DataIdx, ElemIdx, FieldIdx, FuncIdx, GlobalIdx, LabelIdx, LaneIdx, LocalIdx, MemIdx,
+        TableIdx, TagIdx, TypeIdx,
+    },
+    names::{IndirectNameMap, NameMap, NameSection},
+    producers::{Producer, Producers},
+    target_features::TargetFeature,
+    types::{
+        ArrayType, CompType, DefType, FieldType, FuncType, GlobalType, HeapType, Limits, MemType,
+        Mutability, NumType, PackedType, RecType, RefType, StorageType, StructType, SubType,
+        TableType, TagType, ValType, VecType,
+    },
+    values::Name,
+    Module,
+};
 use nom::IResult;
+pub use objdump::Objdump;
+pub use runtime::{
+    AnyRef, DataAddr, ElemAddr, Exception, Extern, ExternRef, FuncAddr, FuncHandle, GcRef,
+    GlobalAddr, InstantiationError, InterruptHandle, MemAddr, ModuleInst, ResourceLimiter,
+    SharedMemory, Store, StoreLimits, TableAddr, TagAddr, Trap, Val, WasmTuple, WasmTy,
+};
+pub use text::Wat;
 
 // TODO: Using command cargo modules generate tree --lib --with-types develop a better module tree structure
 // For example should I actually have a parent module called decode/parse that does all that work? Where does validation live?
+mod const_eval;
+mod features;
+mod inspect;
 mod instructions;
 mod module;
+mod objdump;
+mod runtime;
+mod subtyping;
+mod text;
+mod validation;
 
 /// Trait that allows a type to decode itself from a sequence of bytes using nom
 trait Decode
@@ -15,6 +61,12 @@ where
     fn decode<'a>(input: &'a [u8]) -> IResult<&[u8], Self, nom::error::Error<&'a [u8]>>;
 }
 
+/// Trait that allows a type to encode itself as a sequence of bytes, which `Decode` turns back into the same value
+trait Encode {
+    /// Append the encoding of self to the given bytes
+    fn encode(&self, out: &mut Vec<u8>);
+}
+
 /// Trait that allows you to create a new module
 pub trait New<T>
 where
@@ -29,24 +81,188 @@ impl New<&[u8]> for Module {
     /// Create a new module from a sequence of bytes
     fn new(bytes: &[u8]) -> Option<Module> {
         match Module::decode(bytes) {
-            Ok((_, module)) => Some(module),
-            Err(_) => None,
+            // Bytes left over are a section that failed to decode
+            Ok(([], module)) => Some(module),
+            _ => None,
         }
     }
 }
 
 impl New<&str> for Module {
-    /// Create a new module from a sequence of characters
-    fn new(_chars: &str) -> Option<Self> {
-        todo!()
+    /// Create a new module from its text format, by way of the binary format it encodes to
+    fn new(chars: &str) -> Option<Self> {
+        let bytes = wat::parse_str(chars).ok()?;
+        Module::new(bytes.as_slice())
     }
 }
 
 impl Module {
-    /// TODO: Document
+    /// Decode or parse a module that may only use the enabled proposals
+    ///
+    /// The module is checked against the features once it is decoded, before it is returned. An opcode, type or
+    /// section from a disabled proposal fails with "feature not enabled", and input that isn't a module fails with
+    /// "malformed module".
+    pub fn new_with<T>(input: T, features: &Features) -> Result<Module, &'static str>
+    where
+        Module: New<T>,
+    {
+        let module = Module::new(input).ok_or("malformed module")?;
+        features.check(&module)?;
+        Ok(module)
+    }
+
+    /// Check that the module is valid according to the spec, with every proposal enabled
     pub fn validate(&self) -> Result<(), &'static str> {
+        self.validate_with(&Features::default())
+    }
+
+    /// Check that the module is valid according to the spec, and only uses the enabled proposals
+    pub fn validate_with(&self, features: &Features) -> Result<(), &'static str> {
+        features.check(self)?;
         // TODO: Improve error return type
-        // TODO: Implement
+        // TODO: Validate the remaining module-level rules, such as limits and export name uniqueness
+        let mut globals: Vec<&GlobalType> = self
+            .imports
+            .iter()
+            .filter_map(|import| match &import.descriptor {
+                ImportDescriptor::Global(gt) => Some(gt),
+                _ => None,
+            })
+            .collect();
+        let funcs: Vec<TypeIdx> = self.func_types().collect();
+        // Each global can refer to the globals before it, and everything else to all of them
+        for global in &self.globals {
+            const_eval::validate(&global.init, &global.gt.t, &globals, &funcs, &self.types)?;
+            globals.push(&global.gt);
+        }
+        let validate = |expr, expected: &ValType| {
+            const_eval::validate(expr, expected, &globals, &funcs, &self.types)
+        };
+        let i32 = ValType::NumType(NumType::I32);
+        let funcref = ValType::RefType(RefType::FUNCREF);
+
+        for table in &self.tables {
+            let et = ValType::RefType(table.tt.et.clone());
+            match &table.init {
+                Some(init) => validate(init, &et)?,
+                None if !et.is_defaultable() => return Err("type mismatch"),
+                None => (),
+            }
+        }
+        for element in &self.elements {
+            match element {
+                Element::ActiveIndex(offset, _) | Element::ActiveExplicitIndex(_, offset, _, _) => {
+                    validate(offset, &i32)?
+                }
+                Element::ActiveExpression(offset, exprs) => {
+                    validate(offset, &i32)?;
+                    for expr in exprs {
+                        validate(expr, &funcref)?;
+                    }
+                }
+                Element::ActiveExplicitExpression(_, offset, t, exprs) => {
+                    validate(offset, &i32)?;
+                    for expr in exprs {
+                        validate(expr, &ValType::RefType(t.clone()))?;
+                    }
+                }
+                Element::PassiveExpression(t, exprs) | Element::DeclarativeExpression(t, exprs) => {
+                    for expr in exprs {
+                        validate(expr, &ValType::RefType(t.clone()))?;
+                    }
+                }
+                Element::PassiveIndex(_, _) | Element::DeclarativeIndex(_, _) => (),
+            }
+        }
+
+        // Type-check every function body
+        if self.functions.len() != self.code.len() {
+            return Err("function and code section have inconsistent lengths");
+        }
+        let context = validation::Context::new(self)?;
+
+        // The start function must exist and take and return nothing
+        if let Some(start) = self.start {
+            let typeidx = *context
+                .funcs
+                .get(start as usize)
+                .ok_or("unknown function")?;
+            let ty = context.func_type(typeidx)?;
+            if !ty.rt1.is_empty() || !ty.rt2.is_empty() {
+                return Err("start function");
+            }
+        }
+
+        // Element segments must refer to functions that exist, and active ones to a table that can hold their
+        // elements
+        for (element, rt) in self.elements.iter().zip(&context.elems) {
+            if let Element::ActiveIndex(_, funcs)
+            | Element::PassiveIndex(_, funcs)
+            | Element::ActiveExplicitIndex(_, _, _, funcs)
+            | Element::DeclarativeIndex(_, funcs) = element
+            {
+                if funcs
+                    .iter()
+                    .any(|funcidx| *funcidx as usize >= context.funcs.len())
+                {
+                    return Err("unknown function");
+                }
+            }
+            let tableidx = match element {
+                Element::ActiveIndex(_, _) | Element::ActiveExpression(_, _) => 0,
+                Element::ActiveExplicitIndex(tableidx, _, _, _)
+                | Element::ActiveExplicitExpression(tableidx, _, _, _) => *tableidx,
+                _ => continue,
+            };
+            let table = context
+                .tables
+                .get(tableidx as usize)
+                .ok_or("unknown table")?;
+            if !subtyping::matches_ref_type(&self.types, rt, &table.et) {
+                return Err("type mismatch");
+            }
+        }
+
+        // Exports must refer to something that exists
+        for export in &self.exports {
+            let (idx, len, error) = match export.descriptor {
+                ExportDescriptor::Func(idx) => (idx, context.funcs.len(), "unknown function"),
+                ExportDescriptor::Table(idx) => (idx, context.tables.len(), "unknown table"),
+                ExportDescriptor::Mem(idx) => (idx, context.mems.len(), "unknown memory"),
+                ExportDescriptor::Global(idx) => (idx, context.globals.len(), "unknown global"),
+                ExportDescriptor::Tag(idx) => (idx, context.tags.len(), "unknown tag"),
+            };
+            if idx as usize >= len {
+                return Err(error);
+            }
+        }
+
+        // Active data segments must refer to memories that exist, and their offset has the address type of the
+        // memory
+        for data in &self.data {
+            let (memidx, offset) = match data {
+                Data::Active(offset, _) => (0, offset),
+                Data::ActiveExplicit(memidx, offset, _) => (*memidx, offset),
+                Data::Passive(_) => continue,
+            };
+            let mt = context.mems.get(memidx as usize).ok_or("unknown memory")?;
+            validate(offset, &ValType::NumType(mt.addr_type()))?;
+        }
+        for (typeidx, code) in self.functions.iter().zip(&self.code) {
+            let locals = code
+                .code
+                .locals
+                .iter()
+                .flat_map(|local| (0..local.count).map(|_| local.value_type.clone()))
+                .collect();
+            validation::validate_func(
+                &context,
+                context.func_type(*typeidx)?,
+                locals,
+                &code.code.body.0,
+            )?;
+        }
+
         Ok(())
     }
 }
@@ -58,12 +274,215 @@ mod tests {
     fn test_module_new_bytes() {
         let bytes: &[u8] = include_bytes!("../examples/module.wasm");
         assert!(Module::new(bytes).is_some());
+
+        // A section that fails to decode makes the whole module malformed rather than being dropped
+        let mut bytes = bytes.to_vec();
+        bytes.extend([0x0A, 0x02, 0x01]);
+        assert!(Module::new(bytes.as_slice()).is_none());
+    }
+
+    #[test]
+    fn test_module_new_with() {
+        // A module with a function that returns i32.extend8_s of a constant
+        let bytes: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x05, 0x01, 0x60, 0x00, 0x01,
+            0x7F, 0x03, 0x02, 0x01, 0x00, 0x0A, 0x07, 0x01, 0x05, 0x00, 0x41, 0x01, 0xC0, 0x0B,
+        ];
+        assert!(Module::new_with(bytes, &Features::default()).is_ok());
+        let mut features = Features::default();
+        features.sign_extension = false;
+        assert_eq!(
+            Module::new_with(bytes, &features).err(),
+            Some("feature not enabled")
+        );
+        assert_eq!(
+            Module::new_with(&bytes[..4], &Features::default()).err(),
+            Some("malformed module")
+        );
+
+        // The same module in the text format
+        let text = "(module (func (result i32) (i32.extend8_s (i32.const 1))))";
+        assert!(Module::new_with(text, &Features::default()).is_ok());
+        assert_eq!(
+            Module::new_with(text, &features).err(),
+            Some("feature not enabled")
+        );
+        assert_eq!(
+            Module::new_with("(module (func (i32.extend8_s)", &features).err(),
+            Some("malformed module")
+        );
+    }
+
+    #[test]
+    fn test_module_validate() {
+        let bytes: &[u8] = include_bytes!("../examples/module.wasm");
+        assert_eq!(Module::new(bytes).unwrap().validate(), Ok(()));
+
+        // A module with a global initialized by a non-constant expression
+        let bytes: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x06, 0x07, 0x01, 0x7F, 0x00, 0x41,
+            0x01, 0x67, 0x0B,
+        ];
+        assert_eq!(
+            Module::new(bytes).unwrap().validate(),
+            Err("constant expression required")
+        );
+    }
+
+    #[test]
+    fn test_module_validate_func_refs() {
+        // A module with a function that evaluates ref.func 0 and drops it, with or without a declarative element
+        // segment that declares the reference
+        let undeclared: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00, 0x0A, 0x07, 0x01, 0x05, 0x00, 0xD2, 0x00, 0x1A, 0x0B,
+        ];
+        assert_eq!(
+            Module::new(undeclared).unwrap().validate(),
+            Err("undeclared function reference")
+        );
+
+        let declared: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00, 0x09, 0x05, 0x01, 0x03, 0x00, 0x01, 0x00, 0x0A, 0x07, 0x01,
+            0x05, 0x00, 0xD2, 0x00, 0x1A, 0x0B,
+        ];
+        assert_eq!(Module::new(declared).unwrap().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_module_validate_tags() {
+        // A module with a tag of type [i32] -> [], or [i32] -> [i32] which can't be a tag type
+        let valid: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x05, 0x01, 0x60, 0x01, 0x7F,
+            0x00, 0x0D, 0x03, 0x01, 0x00, 0x00,
+        ];
+        assert_eq!(Module::new(valid).unwrap().validate(), Ok(()));
+
+        let results: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x06, 0x01, 0x60, 0x01, 0x7F,
+            0x01, 0x7F, 0x0D, 0x03, 0x01, 0x00, 0x00,
+        ];
+        assert_eq!(
+            Module::new(results).unwrap().validate(),
+            Err("non-empty tag result type")
+        );
+    }
+
+    #[test]
+    fn test_module_validate_shared_memory() {
+        // A module with a shared memory of 1 page, with a maximum of 2 pages or no maximum
+        let valid: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x05, 0x04, 0x01, 0x03, 0x01, 0x02,
+        ];
+        assert_eq!(Module::new(valid).unwrap().validate(), Ok(()));
+
+        let unbounded: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x05, 0x03, 0x01, 0x02, 0x01,
+        ];
+        assert_eq!(
+            Module::new(unbounded).unwrap().validate(),
+            Err("shared memory must have maximum")
+        );
+    }
+
+    #[test]
+    fn test_module_validate_memories() {
+        // A module with two memories, exporting memory 1 or the nonexistent memory 2
+        let valid: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x05, 0x05, 0x02, 0x00, 0x01, 0x00,
+            0x00, 0x07, 0x05, 0x01, 0x01, 0x6D, 0x02, 0x01,
+        ];
+        assert_eq!(Module::new(valid).unwrap().validate(), Ok(()));
+
+        let unknown: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x05, 0x05, 0x02, 0x00, 0x01, 0x00,
+            0x00, 0x07, 0x05, 0x01, 0x01, 0x6D, 0x02, 0x02,
+        ];
+        assert_eq!(
+            Module::new(unknown).unwrap().validate(),
+            Err("unknown memory")
+        );
+    }
+
+    #[test]
+    fn test_module_validate_table_init() {
+        // A module with a table of two non-nullable function references, initialized to ref.func 0 or not at all
+        let init: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00, 0x04, 0x0A, 0x01, 0x40, 0x00, 0x64, 0x70, 0x00, 0x02, 0xD2,
+            0x00, 0x0B, 0x0A, 0x04, 0x01, 0x02, 0x00, 0x0B,
+        ];
+        assert_eq!(Module::new(init).unwrap().validate(), Ok(()));
+
+        let uninitialized: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00, 0x04, 0x05, 0x01, 0x64, 0x70, 0x00, 0x02, 0x0A, 0x04, 0x01,
+            0x02, 0x00, 0x0B,
+        ];
+        assert_eq!(
+            Module::new(uninitialized).unwrap().validate(),
+            Err("type mismatch")
+        );
+    }
+
+    #[test]
+    fn test_module_validate_indices() {
+        // A module with a function of type () -> () followed by the given sections
+        let module = |sections: &[u8]| {
+            let mut bytes = vec![
+                0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+                0x03, 0x02, 0x01, 0x00,
+            ];
+            bytes.extend(sections);
+            bytes.extend([0x0A, 0x04, 0x01, 0x02, 0x00, 0x0B]);
+            Module::new(bytes.as_slice()).unwrap().validate()
+        };
+        assert_eq!(module(&[0x08, 0x01, 0x00]), Ok(()));
+        assert_eq!(module(&[0x08, 0x01, 0x01]), Err("unknown function"));
+        assert_eq!(
+            module(&[0x07, 0x05, 0x01, 0x01, 0x66, 0x00, 0x01]),
+            Err("unknown function")
+        );
+        assert_eq!(
+            module(&[0x07, 0x05, 0x01, 0x01, 0x74, 0x01, 0x00]),
+            Err("unknown table")
+        );
+        // An active element segment of function 0 or 1, into a table of funcref, of externref or into no table
+        let funcref = [0x04, 0x04, 0x01, 0x70, 0x00, 0x01];
+        let externref = [0x04, 0x04, 0x01, 0x6F, 0x00, 0x01];
+        let element = |funcidx| [0x09, 0x07, 0x01, 0x00, 0x41, 0x00, 0x0B, 0x01, funcidx];
+        assert_eq!(module(&[&funcref[..], &element(0)].concat()), Ok(()));
+        assert_eq!(
+            module(&[&funcref[..], &element(1)].concat()),
+            Err("unknown function")
+        );
+        assert_eq!(
+            module(&[&externref[..], &element(0)].concat()),
+            Err("type mismatch")
+        );
+        assert_eq!(module(&element(0)), Err("unknown table"));
+
+        // The start function of a module must take and return nothing
+        let start: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x05, 0x01, 0x60, 0x01, 0x7F,
+            0x00, 0x03, 0x02, 0x01, 0x00, 0x08, 0x01, 0x00, 0x0A, 0x04, 0x01, 0x02, 0x00, 0x0B,
+        ];
+        assert_eq!(
+            Module::new(start).unwrap().validate(),
+            Err("start function")
+        );
     }
 
     #[test]
-    #[should_panic]
     fn test_module_new_str() {
         let str = "(module)";
-        Module::new(str);
+        assert!(Module::new(str).is_some());
+
+        let module =
+            Module::new("(module (func (export \"f\") (result i32) (i32.const 1)))").unwrap();
+        assert_eq!(module.exports.len(), 1);
+        assert_eq!(module.validate(), Ok(()));
+        assert!(Module::new("(module (func (result i32) (i32.const)))").is_none());
     }
 }
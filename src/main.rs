@@ -1,6 +1,6 @@
-use easy::{Module, New};
-use std::{fs::File, io::Read, path::PathBuf};
-use structopt::StructOpt;
+use easy::{Features, Module, New, Objdump, Store, StoreLimits};
+use std::{fs::File, io::Read, path::PathBuf, thread, time::Duration};
+use structopt::{clap::ArgMatches, StructOpt};
 
 #[derive(Debug, StructOpt)]
 #[structopt(
@@ -17,24 +17,137 @@ struct CLI {
 enum SubCommand {
     #[structopt(name = "run", about = "Run a web assembly module")]
     Run(RunOptions),
+    #[structopt(
+        name = "print",
+        about = "Print a web assembly module in the text format"
+    )]
+    Print(PrintOptions),
+    #[structopt(
+        name = "objdump",
+        about = "Show where the sections and instructions of a web assembly module are in its bytes"
+    )]
+    Objdump(ObjdumpOptions),
+    #[structopt(
+        name = "inspect",
+        about = "Summarize the imports, exports, sections and producers of a web assembly module"
+    )]
+    Inspect(InspectOptions),
 }
 
 #[derive(Debug, StructOpt)]
-struct RunOptions {
+struct PrintOptions {
+    #[structopt(parse(from_os_str), help = "Path to a Wasm module")]
+    module: PathBuf,
+    #[structopt(long, help = "Print instructions as nested S-expressions")]
+    folded: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct InspectOptions {
     #[structopt(parse(from_os_str), help = "Path to a Wasm module")]
     module: PathBuf,
+    #[structopt(long, help = "Print the summary as JSON")]
+    json: bool,
+}
+
+/// The flags follow wasm-objdump, so help is only available as --help since -h prints the section headers
+#[derive(Debug, StructOpt)]
+struct ObjdumpOptions {
+    #[structopt(parse(from_os_str), help = "Path to a Wasm module")]
+    module: PathBuf,
+    #[structopt(
+        short = "h",
+        long,
+        help = "Print the offset, size and item count of each section"
+    )]
+    headers: bool,
+    #[structopt(short = "x", long, help = "Print the contents of the sections")]
+    details: bool,
+    #[structopt(short = "d", long, help = "Disassemble the function bodies")]
+    disassemble: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct RunOptions {
+    #[structopt(
+        parse(from_os_str),
+        help = "Path to a Wasm module, in the binary or text format"
+    )]
+    module: PathBuf,
+    #[structopt(
+        long,
+        parse(try_from_str = parse_size),
+        help = "Maximum size of each linear memory, e.g. 64MiB"
+    )]
+    max_memory: Option<u64>,
+    #[structopt(long, help = "Maximum number of elements in each table")]
+    max_table_elements: Option<u32>,
+    #[structopt(
+        long,
+        parse(try_from_str = parse_duration),
+        help = "Interrupt execution after this long, e.g. 5s"
+    )]
+    timeout: Option<Duration>,
+    #[structopt(flatten)]
+    features: FeatureOptions,
+}
+
+/// The proposals a module may use, starting from all of them
+#[derive(Debug, StructOpt)]
+struct FeatureOptions {
+    #[structopt(
+        long,
+        number_of_values = 1,
+        help = "Enable a proposal, e.g. tail-call, or all of them with all"
+    )]
+    enable: Vec<String>,
+    #[structopt(
+        long,
+        number_of_values = 1,
+        help = "Disable a proposal, e.g. simd, or all of them with all"
+    )]
+    disable: Vec<String>,
+}
+
+impl FeatureOptions {
+    /// Apply the flags in the order they were given, so that `--disable all --enable simd` only allows SIMD
+    ///
+    /// `matches` are the arguments the options were parsed from, which know where each flag was on the command line.
+    fn features(&self, matches: &ArgMatches) -> Result<Features, String> {
+        let indices = |name| matches.indices_of(name).into_iter().flatten();
+        let mut changes: Vec<_> = indices("enable")
+            .zip(self.enable.iter().map(|name| (name, true)))
+            .chain(indices("disable").zip(self.disable.iter().map(|name| (name, false))))
+            .collect();
+        changes.sort_by_key(|(index, _)| *index);
+
+        let mut features = Features::default();
+        for (_, (name, enabled)) in changes {
+            features
+                .set(name, enabled)
+                .map_err(|error| format!("{}: {}", error, name))?;
+        }
+        Ok(features)
+    }
 }
 
 fn main() {
-    let cli = CLI::from_args();
+    let matches = CLI::clap().get_matches();
+    let cli = CLI::from_clap(&matches);
     match cli.cmd {
-        SubCommand::Run(options) => run(options),
+        SubCommand::Run(options) => run(options, matches.subcommand_matches("run").unwrap()),
+        SubCommand::Print(options) => print(options),
+        SubCommand::Objdump(options) => objdump(options),
+        SubCommand::Inspect(options) => inspect(options),
     }
 }
 
-fn run(options: RunOptions) {
-    let extension = options.module.extension();
-    if extension.is_none() || extension.unwrap() != "wasm" {
+fn run(options: RunOptions, matches: &ArgMatches) {
+    let extension = options
+        .module
+        .extension()
+        .and_then(|extension| extension.to_str());
+    if !matches!(extension, Some("wasm" | "wat")) {
         println!("Incorrect file extension");
         // TODO: Return result error of some kind
         return;
@@ -45,11 +158,143 @@ fn run(options: RunOptions) {
         return;
     }
 
+    let features = match options.features.features(matches) {
+        Ok(features) => features,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+
+    let text = extension == Some("wat");
+    let bytes = read_file(options.module);
+
+    // The text format is parsed and checked against the features the same way the binary format is decoded
+    let module = if text {
+        std::str::from_utf8(&bytes)
+            .map_err(|_| "malformed module")
+            .and_then(|text| Module::new_with(text, &features))
+    } else {
+        Module::new_with(bytes.as_slice(), &features)
+    };
+    let module = match module {
+        Ok(module) => module,
+        Err(error) => {
+            println!("Failed to decode module: {}", error);
+            return;
+        }
+    };
+    warn(&module);
+    if let Err(error) = module.validate_with(&features) {
+        println!("Invalid module: {}", error);
+        return;
+    }
+
+    let mut store = Store::new();
+    store.limiter(StoreLimits {
+        memory_size: options.max_memory,
+        table_elements: options.max_table_elements,
+    });
+    if let Some(timeout) = options.timeout {
+        let handle = store.interrupt_handle();
+        thread::spawn(move || {
+            thread::sleep(timeout);
+            handle.interrupt();
+        });
+    }
+    match store.instantiate(&module, &[]) {
+        Ok(instance) => println!("{:?}", instance),
+        Err(error) => println!("Failed to instantiate module: {}", error),
+    }
+}
+
+fn print(options: PrintOptions) {
+    if !options.module.exists() {
+        println!("Wasm module not found at path");
+        return;
+    }
+    let bytes = read_file(options.module);
+    match Module::new(&bytes as &[u8]) {
+        Some(module) => {
+            warn(&module);
+            print!("{}", module.wat().folded(options.folded));
+        }
+        None => println!("Failed to decode module"),
+    }
+}
+
+/// Report the problems that didn't stop a module from decoding on stderr, so that they stay out of printed output
+fn warn(module: &Module) {
+    for warning in module.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+}
+
+fn objdump(options: ObjdumpOptions) {
+    if !options.module.exists() {
+        println!("Wasm module not found at path");
+        return;
+    }
+    if !(options.headers || options.details || options.disassemble) {
+        println!("At least one of -h, -x or -d is required");
+        return;
+    }
+    let path = options.module.display().to_string();
+    let bytes = read_file(options.module);
+    let objdump = match Objdump::new(&bytes) {
+        Some(objdump) => objdump,
+        None => {
+            println!("Failed to decode module");
+            return;
+        }
+    };
+    println!("{}:\tfile format wasm 0x1", path);
+    if options.headers {
+        print!("\n{}", objdump.headers());
+    }
+    if options.details {
+        print!("\n{}", objdump.details());
+    }
+    if options.disassemble {
+        print!("\n{}", objdump.disassemble());
+    }
+}
+
+fn inspect(options: InspectOptions) {
+    if !options.module.exists() {
+        println!("Wasm module not found at path");
+        return;
+    }
     let bytes = read_file(options.module);
+    match Module::new(&bytes as &[u8]) {
+        Some(module) => {
+            warn(&module);
+            print!("{}", module.inspect().json(options.json));
+        }
+        None => println!("Failed to decode module"),
+    }
+}
 
-    let module = Module::new(&bytes as &[u8]).unwrap(); // TODO: Don't unwrap
-    println!("{:?}", module.validate());
-    println!("{:?}", module);
+/// Parse a size in bytes with an optional binary unit suffix such as KiB, MiB or GiB
+fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid size: {}", size))?;
+    let multiplier: u64 = match unit.trim() {
+        "" | "B" => 1,
+        "KiB" => 1 << 10,
+        "MiB" => 1 << 20,
+        "GiB" => 1 << 30,
+        unit => return Err(format!("unknown size unit: {}", unit)),
+    };
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("size too large: {}", size))
 }
 
 fn read_file(path: PathBuf) -> Vec<u8> {
@@ -60,3 +305,76 @@ fn read_file(path: PathBuf) -> Vec<u8> {
 }
 
 // TODO: Write some integration tests that run the entire CLI
+
+/// Parse a duration with a unit suffix of ms, s or m
+fn parse_duration(duration: &str) -> Result<Duration, String> {
+    let duration = duration.trim();
+    let split = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(duration.len());
+    let (number, unit) = duration.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration: {}", duration))?;
+    match unit.trim() {
+        "ms" => Ok(Duration::from_millis(number)),
+        "s" => Ok(Duration::from_secs(number)),
+        "m" => number
+            .checked_mul(60)
+            .map(Duration::from_secs)
+            .ok_or_else(|| format!("duration too large: {}", duration)),
+        unit => Err(format!("unknown duration unit: {}", unit)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("2KiB"), Ok(2048));
+        assert_eq!(parse_size("64MiB"), Ok(64 * 1024 * 1024));
+        assert_eq!(parse_size("1 GiB"), Ok(1024 * 1024 * 1024));
+        assert!(parse_size("64MB").is_err());
+        assert!(parse_size("MiB").is_err());
+    }
+
+    #[test]
+    fn test_features() {
+        let features = |args: &[&str]| {
+            let matches = FeatureOptions::clap().get_matches_from(args);
+            FeatureOptions::from_clap(&matches).features(&matches)
+        };
+
+        let mut expected = Features::all(false);
+        expected.gc = true;
+        assert_eq!(
+            features(&["features", "--disable", "all", "--enable", "gc"]),
+            Ok(expected)
+        );
+
+        // Later flags override earlier ones
+        let enabled = features(&["features", "--enable", "gc", "--disable", "gc"]);
+        assert_eq!(enabled.map(|features| features.gc), Ok(false));
+        let enabled = features(&["features", "--disable", "gc", "--enable", "gc"]);
+        assert_eq!(enabled.map(|features| features.gc), Ok(true));
+        let enabled = features(&["features", "--enable", "all", "--disable", "all"]);
+        assert_eq!(enabled, Ok(Features::all(false)));
+
+        assert_eq!(
+            features(&["features", "--disable", "simd128"]),
+            Err("unknown feature: simd128".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("5s"), Ok(Duration::from_secs(5)));
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert!(parse_duration("5").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+}
@@ -1,49 +1,73 @@
-use nom::{bytes::complete::tag, multi::many0, IResult};
+use nom::{
+    bytes::complete::tag,
+    combinator::{all_consuming, consumed},
+    multi::many0,
+    IResult,
+};
 
-use crate::Decode;
+use crate::{Decode, Encode};
 
 use self::{
     code::Code,
+    custom::CustomSection,
     data::Data,
     element::Element,
     export::Export,
     global::Global,
-    import::Import,
+    import::{Import, ImportDescriptor},
     indices::{FuncIdx, TypeIdx},
     memory::Memory,
-    section::Section,
+    names::NameSection,
+    producers::Producers,
+    section::{Section, SectionHeader},
     table::Table,
-    types::FuncType,
+    tag::Tag,
+    target_features::TargetFeature,
+    types::{DefType, FuncType, GlobalType, MemType, TableType},
 };
 
-mod code;
-mod data;
-mod element;
-mod export;
-mod global;
-mod import;
+pub mod builder;
+pub mod code;
+pub mod custom;
+pub mod data;
+pub mod element;
+pub mod export;
+pub mod global;
+pub mod import;
 pub mod indices;
 mod memory;
-mod section;
+pub mod names;
+pub mod producers;
+pub mod section;
 mod table;
+mod tag;
+pub mod target_features;
 pub mod types;
-mod values;
+pub mod values;
 
 /// A Wasm module
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Module {
-    types: Vec<FuncType>,
-    imports: Vec<Import>,
-    functions: Vec<TypeIdx>, // TODO: Does this need to be a more robust type for execution?
-    tables: Vec<Table>,
-    memories: Vec<Memory>,
-    globals: Vec<Global>,
-    exports: Vec<Export>,
-    start: Option<FuncIdx>,
-    elements: Vec<Element>,
-    code: Vec<Code>,
-    data: Vec<Data>,
-    data_count: Option<u32>,
+    pub(crate) types: Vec<DefType>,
+    pub(crate) imports: Vec<Import>,
+    pub(crate) functions: Vec<TypeIdx>, // TODO: Does this need to be a more robust type for execution?
+    pub(crate) tables: Vec<Table>,
+    pub(crate) memories: Vec<Memory>,
+    pub(crate) tags: Vec<Tag>,
+    pub(crate) globals: Vec<Global>,
+    pub(crate) exports: Vec<Export>,
+    pub(crate) start: Option<FuncIdx>,
+    pub(crate) elements: Vec<Element>,
+    pub(crate) code: Vec<Code>,
+    pub(crate) data: Vec<Data>,
+    pub(crate) data_count: Option<u32>,
+    pub(crate) names: NameSection,
+    pub(crate) custom_sections: Vec<CustomSection>,
+    pub(crate) sections: Vec<SectionHeader>,
+    // The warnings are only about the bytes the module was decoded from
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) warnings: Vec<&'static str>,
 }
 
 impl Decode for Module {
@@ -55,46 +79,283 @@ impl Decode for Module {
         let (input, _) = wasm_version(input)?;
 
         // Decode an arbitrary number of sections
-        let (input, sections) = many0(Section::decode)(input)?;
+        let (input, sections) = many0(consumed(Section::decode))(input)?;
 
         // Create an empty module that we can populate
-        let mut module = Self {
-            types: Vec::new(),
-            imports: Vec::new(),
-            functions: Vec::new(),
-            tables: Vec::new(),
-            memories: Vec::new(),
-            globals: Vec::new(),
-            exports: Vec::new(),
-            start: None,
-            elements: Vec::new(),
-            code: Vec::new(),
-            data: Vec::new(),
-            data_count: None,
-        };
+        let mut module = Self::default();
+
+        // Record where each section is, since the sections themselves don't keep their offsets
+        let mut offset = 8; // The length of the magic header and version
+        for (bytes, _) in &sections {
+            module.sections.push(SectionHeader::new(offset, bytes));
+            offset += bytes.len();
+        }
 
         // Build up a module based on the sections we've decoded
-        sections.into_iter().for_each(|section| match section {
-            Section::CustomSection(_) => (),
-            Section::TypeSection(types) => module.types = types,
-            Section::ImportSection(imports) => module.imports = imports,
-            Section::FunctionSection(functions) => module.functions = functions,
-            Section::TableSection(tables) => module.tables = tables,
-            Section::MemorySection(memories) => module.memories = memories,
-            Section::GlobalSection(globals) => module.globals = globals,
-            Section::ExportSection(exports) => module.exports = exports,
-            Section::StartSection(start) => module.start = start,
-            Section::ElementSection(elements) => module.elements = elements,
-            Section::CodeSection(code) => module.code = code,
-            Section::DataSection(data) => module.data = data,
-            Section::DataCountSection(data_count) => module.data_count = data_count,
-        });
+        let mut named = false;
+        let mut after = None;
+        for (bytes, section) in sections {
+            match section {
+                Section::CustomSection((name, data)) => {
+                    // A malformed name section only produces warnings rather than failing to decode the module
+                    if name.0 == b"name" {
+                        if named {
+                            module.warnings.push("duplicate name section");
+                        }
+                        named = true;
+                        let (names, warnings) = NameSection::decode_lenient(&data);
+                        module.names = names;
+                        module.warnings.extend(warnings);
+                    }
+                    // Custom sections are kept with the id of the known section before them to encode them in place
+                    module
+                        .custom_sections
+                        .push(CustomSection { name, data, after });
+                    continue;
+                }
+                Section::TypeSection(types) => module.types = DefType::flatten(types),
+                Section::ImportSection(imports) => module.imports = imports,
+                Section::FunctionSection(functions) => module.functions = functions,
+                Section::TableSection(tables) => module.tables = tables,
+                Section::MemorySection(memories) => module.memories = memories,
+                Section::TagSection(tags) => module.tags = tags,
+                Section::GlobalSection(globals) => module.globals = globals,
+                Section::ExportSection(exports) => module.exports = exports,
+                Section::StartSection(start) => module.start = start,
+                Section::ElementSection(elements) => module.elements = elements,
+                Section::CodeSection(code) => module.code = code,
+                Section::DataSection(data) => module.data = data,
+                Section::DataCountSection(data_count) => module.data_count = data_count,
+            }
+            after = Some(bytes[0]);
+        }
 
         // Return the decoded module
         Ok((input, module))
     }
 }
 
+impl Module {
+    /// The types defined by the type section, with the types of each recursion group flattened in order
+    pub fn types(&self) -> &[DefType] {
+        &self.types
+    }
+
+    pub fn imports(&self) -> &[Import] {
+        &self.imports
+    }
+
+    /// Every function of the module with its type, starting with the imported ones as in the function index space
+    ///
+    /// The type is `None` if the function's type index doesn't refer to a function type, which validation rules out.
+    pub fn functions(&self) -> impl Iterator<Item = (FuncIdx, Option<&FuncType>)> + '_ {
+        self.func_types()
+            .enumerate()
+            .map(|(funcidx, typeidx)| (funcidx as FuncIdx, self.resolve(typeidx)))
+    }
+
+    /// The type of a function, counting the imported functions before the ones the module defines
+    pub fn func_type(&self, funcidx: FuncIdx) -> Option<&FuncType> {
+        self.func_types()
+            .nth(funcidx as usize)
+            .and_then(|typeidx| self.resolve(typeidx))
+    }
+
+    /// The types of the imported and then the defined tables, in the order of the table index space
+    pub fn tables(&self) -> impl Iterator<Item = &TableType> {
+        self.imports
+            .iter()
+            .filter_map(|import| match &import.descriptor {
+                ImportDescriptor::Table(tt) => Some(tt),
+                _ => None,
+            })
+            .chain(self.tables.iter().map(|table| &table.tt))
+    }
+
+    /// The types of the imported and then the defined memories, in the order of the memory index space
+    pub fn memories(&self) -> impl Iterator<Item = &MemType> {
+        self.imports
+            .iter()
+            .filter_map(|import| match &import.descriptor {
+                ImportDescriptor::Mem(mt) => Some(mt),
+                _ => None,
+            })
+            .chain(self.memories.iter().map(|memory| &memory.mt))
+    }
+
+    /// The types of the imported and then the defined globals, in the order of the global index space
+    pub fn globals(&self) -> impl Iterator<Item = &GlobalType> {
+        self.imports
+            .iter()
+            .filter_map(|import| match &import.descriptor {
+                ImportDescriptor::Global(gt) => Some(gt),
+                _ => None,
+            })
+            .chain(self.globals.iter().map(|global| &global.gt))
+    }
+
+    pub fn exports(&self) -> &[Export] {
+        &self.exports
+    }
+
+    /// The export with the given name, which is unique in a valid module
+    pub fn export(&self, name: &str) -> Option<&Export> {
+        self.exports
+            .iter()
+            .find(|export| export.name.0 == name.as_bytes())
+    }
+
+    /// The function that runs when the module is instantiated, if it has one
+    pub fn start(&self) -> Option<FuncIdx> {
+        self.start
+    }
+
+    pub fn elements(&self) -> &[Element] {
+        &self.elements
+    }
+
+    pub fn data(&self) -> &[Data] {
+        &self.data
+    }
+
+    /// The type index of every function, with the imported functions first
+    pub(crate) fn func_types(&self) -> impl Iterator<Item = TypeIdx> + '_ {
+        self.imports
+            .iter()
+            .filter_map(|import| match import.descriptor {
+                ImportDescriptor::Func(typeidx) => Some(typeidx),
+                _ => None,
+            })
+            .chain(self.functions.iter().copied())
+    }
+
+    /// The function type at an index of the type section
+    fn resolve(&self, typeidx: TypeIdx) -> Option<&FuncType> {
+        self.types.get(typeidx as usize)?.func_type()
+    }
+
+    /// The names given to the module and its items by the name section, which are empty if it has none
+    pub fn names(&self) -> &NameSection {
+        &self.names
+    }
+
+    /// Problems that didn't stop the module from decoding, such as a malformed name section
+    pub fn warnings(&self) -> &[&'static str] {
+        &self.warnings
+    }
+
+    /// The custom sections of the module, in the order they appear in
+    pub fn custom_sections(&self) -> &[CustomSection] {
+        &self.custom_sections
+    }
+
+    /// The first custom section with the given name
+    pub fn custom_section(&self, name: &str) -> Option<&CustomSection> {
+        self.custom_sections
+            .iter()
+            .find(|section| section.name.0 == name.as_bytes())
+    }
+
+    /// Add a custom section after the existing custom sections at the same position
+    pub fn add_custom_section(&mut self, section: CustomSection) {
+        let named = section.name.0 == b"name";
+        self.custom_sections.push(section);
+        if named {
+            self.refresh_names();
+        }
+    }
+
+    /// Remove the first custom section with the given name and return it
+    pub fn remove_custom_section(&mut self, name: &str) -> Option<CustomSection> {
+        let index = self
+            .custom_sections
+            .iter()
+            .position(|section| section.name.0 == name.as_bytes())?;
+        let section = self.custom_sections.remove(index);
+        if name == "name" {
+            self.refresh_names();
+        }
+        Some(section)
+    }
+
+    /// The languages and tools that produced the module, if it has a well-formed `producers` section
+    pub fn producers(&self) -> Option<Producers> {
+        let section = self.custom_section("producers")?;
+        let (_, producers) = all_consuming(Producers::decode)(&section.data).ok()?;
+        Some(producers)
+    }
+
+    /// The features the module was compiled to use or avoid, if it has a well-formed `target_features` section
+    pub fn target_features(&self) -> Option<Vec<TargetFeature>> {
+        let section = self.custom_section("target_features")?;
+        let (_, features) = all_consuming(Vec::<TargetFeature>::decode)(&section.data).ok()?;
+        Some(features)
+    }
+
+    /// Decode the names again after the name section changed, where the last one wins as it does when decoding
+    fn refresh_names(&mut self) {
+        self.names = self
+            .custom_sections
+            .iter()
+            .rev()
+            .find(|section| section.name.0 == b"name")
+            .map(|section| NameSection::decode_lenient(&section.data).0)
+            .unwrap_or_default();
+    }
+
+    /// Encode the module in the binary format
+    ///
+    /// The known sections are written in the order the specification requires and left out when they are empty,
+    /// while the custom sections are written back after the known section they followed.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&[0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00]);
+        self.encode_custom_sections(None, &mut out);
+        // The tag section goes between the memory and global sections, and the data count before the code
+        for id in [1, 2, 3, 4, 5, 13, 6, 7, 8, 9, 12, 10, 11] {
+            match id {
+                1 if !self.types.is_empty() => {
+                    encode_section(id, &DefType::unflatten(&self.types), &mut out)
+                }
+                2 if !self.imports.is_empty() => encode_section(id, &self.imports, &mut out),
+                3 if !self.functions.is_empty() => encode_section(id, &self.functions, &mut out),
+                4 if !self.tables.is_empty() => encode_section(id, &self.tables, &mut out),
+                5 if !self.memories.is_empty() => encode_section(id, &self.memories, &mut out),
+                13 if !self.tags.is_empty() => encode_section(id, &self.tags, &mut out),
+                6 if !self.globals.is_empty() => encode_section(id, &self.globals, &mut out),
+                7 if !self.exports.is_empty() => encode_section(id, &self.exports, &mut out),
+                8 if self.start.is_some() => encode_section(id, &self.start.unwrap(), &mut out),
+                9 if !self.elements.is_empty() => encode_section(id, &self.elements, &mut out),
+                12 if self.data_count.is_some() => {
+                    encode_section(id, &self.data_count.unwrap(), &mut out)
+                }
+                10 if !self.code.is_empty() => encode_section(id, &self.code, &mut out),
+                11 if !self.data.is_empty() => encode_section(id, &self.data, &mut out),
+                _ => (),
+            }
+            self.encode_custom_sections(Some(id), &mut out);
+        }
+        out
+    }
+
+    /// Encode the custom sections that follow the known section with the given id
+    fn encode_custom_sections(&self, after: Option<u8>, out: &mut Vec<u8>) {
+        self.custom_sections
+            .iter()
+            .filter(|section| section.after == after)
+            .for_each(|section| encode_section(0, section, out));
+    }
+}
+
+/// Encode a section with the given id, whose contents are prefixed by their size
+fn encode_section(id: u8, contents: &impl Encode, out: &mut Vec<u8>) {
+    let mut bytes = Vec::new();
+    contents.encode(&mut bytes);
+    out.push(id);
+    (bytes.len() as u32).encode(out);
+    out.extend(bytes);
+}
+
 fn magic_header<'a>(input: &'a [u8]) -> IResult<&[u8], &[u8]> {
     // The magic header that every Wasm module begins with
     let magic_header = [0x00, 0x61, 0x73, 0x6D];
@@ -119,6 +380,165 @@ mod tests {
         assert!(Module::decode(bytes).is_ok());
     }
 
+    #[test]
+    fn test_names() {
+        let bytes: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+            0x00, 0x09, 0x04, 0x6E, 0x61, 0x6D, 0x65, 0x00, 0x02, 0x01, 0x6D, // custom "name"
+            0x00, 0x08, 0x04, 0x6E, 0x61, 0x6D, 0x65, 0x01, 0x01,
+            0x01, // custom "name", malformed
+        ];
+        let (_, module) = Module::decode(bytes).unwrap();
+        assert_eq!(module.names().module, None);
+        assert_eq!(
+            module.warnings(),
+            &["duplicate name section", "malformed function names"]
+        );
+
+        let (_, module) = Module::decode(&bytes[..19]).unwrap();
+        assert_eq!(module.names().module, Some(values::Name(vec!(0x6D))));
+        assert!(module.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_encode() {
+        for bytes in [
+            &include_bytes!("../examples/module.wasm")[..],
+            &include_bytes!("../examples/func.wasm")[..],
+        ] {
+            let (_, module) = Module::decode(bytes).unwrap();
+            assert_eq!(module.encode(), bytes);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        // A module survives a trip through JSON and encodes back to the same bytes
+        let bytes = include_bytes!("../examples/module.wasm");
+        let (_, module) = Module::decode(bytes).unwrap();
+        let json = serde_json::to_string(&module).unwrap();
+        let module: Module = serde_json::from_str(&json).unwrap();
+        assert_eq!(module.encode(), bytes);
+    }
+
+    #[test]
+    fn test_custom_sections() {
+        let bytes: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+            0x00, 0x03, 0x01, 0x61, 0x01, // custom "a"
+            0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type section
+            0x00, 0x02, 0x01, 0x62, // custom "b"
+            0x03, 0x02, 0x01, 0x00, // function section
+            0x0A, 0x04, 0x01, 0x02, 0x00, 0x0B, // code section
+            0x00, 0x03, 0x01, 0x63, 0x02, // custom "c"
+        ];
+        let (_, mut module) = Module::decode(bytes).unwrap();
+        assert_eq!(
+            module.custom_sections(),
+            &[
+                CustomSection {
+                    name: values::Name(vec!(0x61)),
+                    data: vec!(0x01),
+                    after: None
+                },
+                CustomSection {
+                    name: values::Name(vec!(0x62)),
+                    data: vec!(),
+                    after: Some(1)
+                },
+                CustomSection {
+                    name: values::Name(vec!(0x63)),
+                    data: vec!(0x02),
+                    after: Some(10)
+                },
+            ]
+        );
+        assert_eq!(module.custom_section("b").unwrap().after, Some(1));
+        assert_eq!(module.custom_section("d"), None);
+        assert_eq!(module.encode(), bytes);
+
+        // Removing a section leaves the others in place, and added sections go at the end
+        assert_eq!(module.remove_custom_section("b").unwrap().after, Some(1));
+        assert_eq!(module.remove_custom_section("b"), None);
+        module.add_custom_section(CustomSection::new("d", vec![0x03]));
+        let mut expected = bytes[..19].to_vec();
+        expected.extend_from_slice(&bytes[23..]);
+        expected.extend_from_slice(&[0x00, 0x03, 0x01, 0x64, 0x03]);
+        assert_eq!(module.encode(), expected);
+
+        // The names follow the name section as it is added and removed
+        module.add_custom_section(CustomSection::new("name", vec![0x00, 0x02, 0x01, 0x6D]));
+        assert_eq!(module.names().module, Some(values::Name(vec!(0x6D))));
+        module.remove_custom_section("name");
+        assert_eq!(module.names().module, None);
+    }
+
+    #[test]
+    fn test_accessors() {
+        let bytes: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+            0x01, 0x09, 0x02, 0x60, 0x01, 0x7F, 0x01, 0x7F, 0x60, 0x00, 0x00, // type
+            0x02, 0x12, 0x02, 0x03, 0x65, 0x6E, 0x76, 0x01, 0x66, 0x00, 0x01, 0x03, 0x65, 0x6E,
+            0x76, 0x01, 0x67, 0x03, 0x7F, 0x01, // import
+            0x03, 0x02, 0x01, 0x00, // function
+            0x05, 0x03, 0x01, 0x00, 0x01, // memory
+            0x06, 0x06, 0x01, 0x7F, 0x00, 0x41, 0x00, 0x0B, // global
+            0x07, 0x08, 0x01, 0x04, 0x6D, 0x61, 0x69, 0x6E, 0x00, 0x01, // export
+            0x08, 0x01, 0x00, // start
+            0x0A, 0x06, 0x01, 0x04, 0x00, 0x20, 0x00, 0x0B, // code
+        ];
+        let (_, module) = Module::decode(bytes).unwrap();
+        let i32 = types::ValType::NumType(types::NumType::I32);
+        let unary = FuncType {
+            rt1: vec![i32.clone()],
+            rt2: vec![i32.clone()],
+        };
+        let nullary = FuncType {
+            rt1: vec![],
+            rt2: vec![],
+        };
+        assert_eq!(module.types().len(), 2);
+        assert_eq!(module.imports().len(), 2);
+
+        // The imported function comes first in the function index space
+        assert_eq!(
+            module.functions().collect::<Vec<_>>(),
+            vec!((0, Some(&nullary)), (1, Some(&unary)))
+        );
+        assert_eq!(module.func_type(1), Some(&unary));
+        assert_eq!(module.func_type(2), None);
+
+        assert_eq!(module.tables().count(), 0);
+        assert_eq!(
+            module.memories().map(|mt| mt.lim.min).collect::<Vec<_>>(),
+            vec!(1)
+        );
+        assert_eq!(
+            module.globals().collect::<Vec<_>>(),
+            vec!(
+                &GlobalType {
+                    m: types::Mutability::Var,
+                    t: i32.clone()
+                },
+                &GlobalType {
+                    m: types::Mutability::Const,
+                    t: i32
+                }
+            )
+        );
+
+        assert_eq!(module.exports().len(), 1);
+        assert_eq!(
+            module.export("main").map(|export| &export.descriptor),
+            Some(&export::ExportDescriptor::Func(1))
+        );
+        assert_eq!(module.export("mai"), None);
+        assert_eq!(module.start(), Some(0));
+        assert!(module.elements().is_empty());
+        assert!(module.data().is_empty());
+    }
+
     #[test]
     fn test_magic_header() {
         let magic: &[u8] = &[0x00, 0x61, 0x73, 0x6D];
@@ -0,0 +1,509 @@
+use std::mem::discriminant;
+
+use crate::{
+    instructions::{BlockType, CatchClause, ControlInstruction, Expression, Instruction},
+    Encode,
+};
+
+use super::{
+    code::{Code, Func, Local},
+    data::Data,
+    element::Element,
+    export::{Export, ExportDescriptor},
+    global::Global,
+    import::{Import, ImportDescriptor},
+    indices::{DataIdx, ElemIdx, FuncIdx, GlobalIdx, LocalIdx, MemIdx, TableIdx, TypeIdx},
+    memory::Memory,
+    table::Table,
+    types::{DefType, FuncType, GlobalType, MemType, SubType, TableType, ValType},
+    values::Name,
+    Module,
+};
+
+/// Builds a module item by item, for code generators that want to emit Wasm without going through the binary format
+///
+/// Every item gets its index as it is added. Imports come first in each index space, so an import can't be added
+/// once the module defines an item of the same kind, since that would change the indices already handed out.
+#[derive(Debug, Default)]
+pub struct ModuleBuilder {
+    module: Module,
+}
+
+impl ModuleBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The index of a function type, which is only added to the type section if an identical type isn't there yet
+    pub fn func_type(&mut self, ft: FuncType) -> TypeIdx {
+        let st = SubType::from(ft);
+        // A type in a larger recursion group is a different type, even if its definition is the same
+        let existing = self
+            .module
+            .types
+            .iter()
+            .position(|dt| dt.group.len() == 1 && dt.st == st);
+        match existing {
+            Some(typeidx) => typeidx as TypeIdx,
+            None => {
+                let typeidx = self.module.types.len() as TypeIdx;
+                self.module.types.push(DefType {
+                    st,
+                    group: typeidx..typeidx + 1,
+                });
+                typeidx
+            }
+        }
+    }
+
+    pub fn import_func(
+        &mut self,
+        module: &str,
+        name: &str,
+        ft: FuncType,
+    ) -> Result<FuncIdx, &'static str> {
+        if !self.module.functions.is_empty() {
+            return Err("functions must be imported before any are defined");
+        }
+        let typeidx = self.func_type(ft);
+        Ok(self.import(module, name, ImportDescriptor::Func(typeidx)))
+    }
+
+    pub fn import_table(
+        &mut self,
+        module: &str,
+        name: &str,
+        tt: TableType,
+    ) -> Result<TableIdx, &'static str> {
+        if !self.module.tables.is_empty() {
+            return Err("tables must be imported before any are defined");
+        }
+        Ok(self.import(module, name, ImportDescriptor::Table(tt)))
+    }
+
+    pub fn import_memory(
+        &mut self,
+        module: &str,
+        name: &str,
+        mt: MemType,
+    ) -> Result<MemIdx, &'static str> {
+        if !self.module.memories.is_empty() {
+            return Err("memories must be imported before any are defined");
+        }
+        Ok(self.import(module, name, ImportDescriptor::Mem(mt)))
+    }
+
+    pub fn import_global(
+        &mut self,
+        module: &str,
+        name: &str,
+        gt: GlobalType,
+    ) -> Result<GlobalIdx, &'static str> {
+        if !self.module.globals.is_empty() {
+            return Err("globals must be imported before any are defined");
+        }
+        Ok(self.import(module, name, ImportDescriptor::Global(gt)))
+    }
+
+    /// Add an import, returning its index among the imports of the same kind
+    fn import(&mut self, module: &str, name: &str, descriptor: ImportDescriptor) -> u32 {
+        let idx = self
+            .module
+            .imports
+            .iter()
+            .filter(|import| discriminant(&import.descriptor) == discriminant(&descriptor))
+            .count();
+        self.module.imports.push(Import {
+            module: name_of(module),
+            name: name_of(name),
+            descriptor,
+        });
+        idx as u32
+    }
+
+    /// Add a function, or fail if its body leaves a block open
+    pub fn function(&mut self, function: FunctionBuilder) -> Result<FuncIdx, &'static str> {
+        let funcidx = self.module.func_types().count() as FuncIdx;
+        let FunctionBuilder { ft, locals, frames } = function;
+        let body = match <[_; 1]>::try_from(frames) {
+            Ok([(Frame::Body, instructions)]) => Expression(instructions),
+            _ => return Err("function body ends with a block still open"),
+        };
+        let typeidx = self.func_type(ft);
+        let code = Func { locals, body };
+        let mut bytes = Vec::new();
+        code.encode(&mut bytes);
+        self.module.functions.push(typeidx);
+        self.module.code.push(Code {
+            size: bytes.len() as u32,
+            code,
+        });
+        Ok(funcidx)
+    }
+
+    pub fn table(&mut self, tt: TableType) -> TableIdx {
+        let tableidx = self.module.tables().count() as TableIdx;
+        self.module.tables.push(Table { tt, init: None });
+        tableidx
+    }
+
+    pub fn memory(&mut self, mt: MemType) -> MemIdx {
+        let memidx = self.module.memories().count() as MemIdx;
+        self.module.memories.push(Memory { mt });
+        memidx
+    }
+
+    /// Add a global whose initial value is computed by a constant expression
+    pub fn global(&mut self, gt: GlobalType, init: Expression) -> GlobalIdx {
+        let globalidx = self.module.globals().count() as GlobalIdx;
+        self.module.globals.push(Global { gt, init });
+        globalidx
+    }
+
+    /// Export an item, or fail if another export already has the name
+    pub fn export(&mut self, name: &str, descriptor: ExportDescriptor) -> Result<(), &'static str> {
+        if self.module.export(name).is_some() {
+            return Err("duplicate export name");
+        }
+        self.module.exports.push(Export {
+            name: name_of(name),
+            descriptor,
+        });
+        Ok(())
+    }
+
+    pub fn start(&mut self, funcidx: FuncIdx) {
+        self.module.start = Some(funcidx);
+    }
+
+    pub fn element(&mut self, element: Element) -> ElemIdx {
+        self.module.elements.push(element);
+        self.module.elements.len() as ElemIdx - 1
+    }
+
+    pub fn data(&mut self, data: Data) -> DataIdx {
+        self.module.data.push(data);
+        self.module.data.len() as DataIdx - 1
+    }
+
+    /// The finished module, which still needs to be validated
+    pub fn build(mut self) -> Module {
+        // Instructions that refer to data segments need the count up front, and it does no harm otherwise
+        if !self.module.data.is_empty() {
+            self.module.data_count = Some(self.module.data.len() as u32);
+        }
+        self.module
+    }
+}
+
+/// Builds the locals and body of a function, with the blocks opened and closed as they are in the binary format
+///
+/// Blocks are checked to be nested properly as they are closed, so that `else` only follows the instructions of an
+/// `if` and every `end` closes a block.
+#[derive(Debug)]
+pub struct FunctionBuilder {
+    ft: FuncType,
+    locals: Vec<Local>,
+    /// The instructions of the body and of each block that is still open, innermost last
+    frames: Vec<(Frame, Vec<Instruction>)>,
+}
+
+/// What a list of instructions being built belongs to
+#[derive(Debug)]
+enum Frame {
+    Body,
+    Block(BlockType),
+    Loop(BlockType),
+    If(BlockType),
+    /// The else branch of an if, along with the instructions of the then branch
+    Else(BlockType, Vec<Instruction>),
+    TryTable(BlockType, Vec<CatchClause>),
+}
+
+impl FunctionBuilder {
+    pub fn new(ft: FuncType) -> Self {
+        Self {
+            ft,
+            locals: Vec::new(),
+            frames: vec![(Frame::Body, Vec::new())],
+        }
+    }
+
+    /// Declare a local and return its index, which comes after the indices of the parameters
+    pub fn local(&mut self, t: ValType) -> LocalIdx {
+        let declared: u32 = self.locals.iter().map(|local| local.count).sum();
+        match self.locals.last_mut() {
+            // Consecutive locals of the same type share an entry, as they do in the binary format
+            Some(local) if local.value_type == t => local.count += 1,
+            _ => self.locals.push(Local {
+                count: 1,
+                value_type: t,
+            }),
+        }
+        self.ft.rt1.len() as LocalIdx + declared
+    }
+
+    /// Append an instruction to the innermost open block
+    pub fn instruction(&mut self, instruction: Instruction) -> &mut Self {
+        self.frames
+            .last_mut()
+            .expect("the body is always open")
+            .1
+            .push(instruction);
+        self
+    }
+
+    pub fn block(&mut self, bt: BlockType) -> &mut Self {
+        self.open(Frame::Block(bt))
+    }
+
+    pub fn loop_(&mut self, bt: BlockType) -> &mut Self {
+        self.open(Frame::Loop(bt))
+    }
+
+    pub fn if_(&mut self, bt: BlockType) -> &mut Self {
+        self.open(Frame::If(bt))
+    }
+
+    pub fn try_table(&mut self, bt: BlockType, catches: Vec<CatchClause>) -> &mut Self {
+        self.open(Frame::TryTable(bt, catches))
+    }
+
+    fn open(&mut self, frame: Frame) -> &mut Self {
+        self.frames.push((frame, Vec::new()));
+        self
+    }
+
+    /// Start the else branch of the innermost block, which must be an if without one
+    pub fn else_(&mut self) -> Result<&mut Self, &'static str> {
+        match self.frames.pop() {
+            Some((Frame::If(bt), then)) => {
+                self.frames.push((Frame::Else(bt, then), Vec::new()));
+                Ok(self)
+            }
+            Some(frame) => {
+                self.frames.push(frame);
+                Err("else without a matching if")
+            }
+            None => unreachable!("the body is always open"),
+        }
+    }
+
+    /// Close the innermost block, which can't be the body of the function itself
+    pub fn end(&mut self) -> Result<&mut Self, &'static str> {
+        let instruction = match self.frames.pop() {
+            Some((Frame::Body, instructions)) => {
+                self.frames.push((Frame::Body, instructions));
+                return Err("end without an open block");
+            }
+            Some((Frame::Block(bt), instructions)) => ControlInstruction::Block(bt, instructions),
+            Some((Frame::Loop(bt), instructions)) => ControlInstruction::Loop(bt, instructions),
+            Some((Frame::If(bt), then)) => ControlInstruction::If(bt, then, Vec::new()),
+            Some((Frame::Else(bt, then), otherwise)) => ControlInstruction::If(bt, then, otherwise),
+            Some((Frame::TryTable(bt, catches), instructions)) => {
+                ControlInstruction::TryTable(bt, catches, instructions)
+            }
+            None => unreachable!("the body is always open"),
+        };
+        Ok(self.instruction(Instruction::Control(instruction)))
+    }
+}
+
+fn name_of(name: &str) -> Name {
+    Name(name.as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        instructions::{NumericInstruction, VariableInstruction},
+        module::types::{HeapType, Limits, Mutability, NumType, RefType},
+        Decode, Extern, Store, Val,
+    };
+
+    use super::*;
+
+    const I32: ValType = ValType::NumType(NumType::I32);
+
+    fn i32_const(n: i32) -> Instruction {
+        Instruction::Numeric(NumericInstruction::I32Const(n))
+    }
+
+    fn local_get(idx: LocalIdx) -> Instruction {
+        Instruction::Variable(VariableInstruction::LocalGet(idx))
+    }
+
+    fn local_set(idx: LocalIdx) -> Instruction {
+        Instruction::Variable(VariableInstruction::LocalSet(idx))
+    }
+
+    fn unary() -> FuncType {
+        FuncType {
+            rt1: vec![I32],
+            rt2: vec![I32],
+        }
+    }
+
+    fn nullary() -> FuncType {
+        FuncType {
+            rt1: vec![],
+            rt2: vec![],
+        }
+    }
+
+    #[test]
+    fn test_build() {
+        let mut builder = ModuleBuilder::new();
+        let memidx = builder.memory(MemType {
+            lim: Limits { min: 1, max: None },
+            shared: false,
+            memory64: false,
+        });
+        builder.global(
+            GlobalType {
+                m: Mutability::Var,
+                t: I32,
+            },
+            Expression(vec![i32_const(0)]),
+        );
+        builder.data(Data::Active(
+            Expression(vec![i32_const(0)]),
+            b"hello".to_vec(),
+        ));
+
+        // Returns 1 if the argument is non-zero and 2 otherwise, by way of a local
+        let mut choose = FunctionBuilder::new(unary());
+        let result = choose.local(I32);
+        assert_eq!(result, 1);
+        choose
+            .instruction(local_get(0))
+            .if_(BlockType::Empty)
+            .instruction(i32_const(1))
+            .instruction(local_set(result));
+        choose
+            .else_()
+            .unwrap()
+            .instruction(i32_const(2))
+            .instruction(local_set(result));
+        choose.end().unwrap().instruction(local_get(result));
+        let funcidx = builder.function(choose).unwrap();
+        assert_eq!(funcidx, 0);
+
+        let tableidx = builder.table(TableType {
+            lim: Limits { min: 1, max: None },
+            et: RefType {
+                nullable: true,
+                ht: HeapType::Func,
+            },
+        });
+        builder.element(Element::ActiveIndex(
+            Expression(vec![i32_const(0)]),
+            vec![funcidx],
+        ));
+        builder
+            .export("choose", ExportDescriptor::Func(funcidx))
+            .unwrap();
+        builder
+            .export("memory", ExportDescriptor::Mem(memidx))
+            .unwrap();
+        builder
+            .export("table", ExportDescriptor::Table(tableidx))
+            .unwrap();
+        assert_eq!(
+            builder.export("choose", ExportDescriptor::Func(funcidx)),
+            Err("duplicate export name")
+        );
+
+        let module = builder.build();
+        assert_eq!(module.data_count, Some(1));
+        assert_eq!(module.validate(), Ok(()));
+
+        // The encoded module decodes to the same module
+        let bytes = module.encode();
+        let (_, decoded) = Module::decode(&bytes).unwrap();
+        assert_eq!(decoded.encode(), bytes);
+        assert_eq!(decoded.code, module.code);
+
+        let mut store = Store::new();
+        let instance = store.instantiate(&module, &[]).unwrap();
+        let Some(Extern::Func(addr)) = instance.export("choose") else {
+            panic!("choose isn't exported as a function");
+        };
+        assert_eq!(store.invoke(addr, &[Val::I32(0)]), Ok(vec!(Val::I32(2))));
+        assert_eq!(store.invoke(addr, &[Val::I32(5)]), Ok(vec!(Val::I32(1))));
+    }
+
+    #[test]
+    fn test_func_types() {
+        let mut builder = ModuleBuilder::new();
+        assert_eq!(builder.func_type(unary()), 0);
+        assert_eq!(builder.func_type(nullary()), 1);
+        assert_eq!(builder.func_type(unary()), 0);
+        assert_eq!(builder.build().types.len(), 2);
+    }
+
+    #[test]
+    fn test_imports() {
+        let mut builder = ModuleBuilder::new();
+        let gt = GlobalType {
+            m: Mutability::Const,
+            t: I32,
+        };
+        assert_eq!(builder.import_func("env", "f", nullary()), Ok(0));
+        assert_eq!(builder.import_global("env", "g", gt.clone()), Ok(0));
+        assert_eq!(builder.import_func("env", "h", unary()), Ok(1));
+
+        // Defined items come after the imports in each index space
+        assert_eq!(builder.function(FunctionBuilder::new(nullary())), Ok(2));
+        assert_eq!(
+            builder.global(gt.clone(), Expression(vec!(i32_const(1)))),
+            1
+        );
+        assert_eq!(
+            builder.import_func("env", "i", nullary()),
+            Err("functions must be imported before any are defined")
+        );
+        assert_eq!(
+            builder.import_global("env", "j", gt),
+            Err("globals must be imported before any are defined")
+        );
+        assert_eq!(builder.build().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_nesting() {
+        let mut function = FunctionBuilder::new(nullary());
+        assert_eq!(function.end().err(), Some("end without an open block"));
+        assert_eq!(function.else_().err(), Some("else without a matching if"));
+        function.block(BlockType::Empty);
+        assert_eq!(function.else_().err(), Some("else without a matching if"));
+        function.end().unwrap().if_(BlockType::Empty);
+        function.else_().unwrap();
+        assert_eq!(function.else_().err(), Some("else without a matching if"));
+        function.loop_(BlockType::Empty);
+        function.end().unwrap();
+
+        // The if is still open
+        assert_eq!(
+            ModuleBuilder::new().function(function).err(),
+            Some("function body ends with a block still open")
+        );
+
+        let mut function = FunctionBuilder::new(nullary());
+        function.block(BlockType::Empty).if_(BlockType::Empty);
+        function.end().unwrap().end().unwrap();
+        let mut builder = ModuleBuilder::new();
+        builder.function(function).unwrap();
+        assert_eq!(
+            builder.build().code[0].code.body,
+            Expression(vec!(Instruction::Control(ControlInstruction::Block(
+                BlockType::Empty,
+                vec!(Instruction::Control(ControlInstruction::If(
+                    BlockType::Empty,
+                    vec!(),
+                    vec!()
+                )))
+            ))))
+        );
+    }
+}
@@ -2,12 +2,13 @@ use nom::combinator::map;
 use nom::sequence::pair;
 use nom::IResult;
 
-use crate::{instructions::Expression, Decode};
+use crate::{instructions::Expression, Decode, Encode};
 
 use super::types::{NumType, ValType};
 
 /// TODO: Document
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Code {
     pub size: u32,
     pub code: Func,
@@ -22,8 +23,19 @@ impl Decode for Code {
     }
 }
 
+impl Encode for Code {
+    fn encode(&self, out: &mut Vec<u8>) {
+        // The size is worked out again from the encoded function, so that changing the function keeps it right
+        let mut func = Vec::new();
+        self.code.encode(&mut func);
+        (func.len() as u32).encode(out);
+        out.extend(func);
+    }
+}
+
 /// TODO: Document
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Func {
     pub locals: Vec<Local>,
     pub body: Expression,
@@ -40,8 +52,16 @@ impl Decode for Func {
     }
 }
 
+impl Encode for Func {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.locals.encode(out);
+        self.body.encode(out);
+    }
+}
+
 /// TODO: Document
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Local {
     pub count: u32,
     pub value_type: ValType,
@@ -56,6 +76,13 @@ impl Decode for Local {
     }
 }
 
+impl Encode for Local {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.count.encode(out);
+        self.value_type.encode(out);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,36 @@
+use crate::Encode;
+
+use super::values::Name;
+
+/// A custom section, which holds data such as debugging information or build metadata that the module's semantics
+/// don't depend on
+///
+/// Custom sections can appear before, between or after the known sections, so each one remembers the id of the known
+/// section it follows in order to be written back in the same place.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CustomSection {
+    pub name: Name,
+    pub data: Vec<u8>,
+    /// The id of the known section this section follows, or `None` if it comes before all of them
+    pub after: Option<u8>,
+}
+
+impl CustomSection {
+    /// Create a custom section that is placed at the end of the module
+    pub fn new(name: &str, data: Vec<u8>) -> Self {
+        Self {
+            name: Name(name.as_bytes().to_vec()),
+            data,
+            // The data section is the last of the known sections
+            after: Some(11),
+        }
+    }
+}
+
+impl Encode for CustomSection {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.name.encode(out);
+        out.extend_from_slice(&self.data);
+    }
+}
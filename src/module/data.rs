@@ -1,11 +1,16 @@
-use nom::IResult;
+use nom::{
+    combinator::{map, verify},
+    sequence::pair,
+    IResult,
+};
 
-use crate::{instructions::Expression, Decode};
+use crate::{instructions::Expression, Decode, Encode};
 
 use super::indices::MemIdx;
 
-/// TODO: Document
+/// A data segment that can be used to initialize a range of memory
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Data {
     Active(Expression, Vec<u8>),
     Passive(Vec<u8>),
@@ -13,14 +18,69 @@ pub enum Data {
 }
 
 impl Decode for Data {
-    fn decode(_input: &[u8]) -> IResult<&[u8], Self> {
-        todo!()
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, flag) = verify(u32::decode, |flag| *flag <= 2)(input)?;
+        match flag {
+            0 => map(pair(Expression::decode, Vec::<u8>::decode), |pair| {
+                Self::Active(pair.0, pair.1)
+            })(input),
+            1 => map(Vec::<u8>::decode, Self::Passive)(input),
+            2 => {
+                let (input, x) = MemIdx::decode(input)?;
+                let (input, e) = Expression::decode(input)?;
+                let (input, b) = Vec::<u8>::decode(input)?;
+                Ok((input, Self::ActiveExplicit(x, e, b)))
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Encode for Data {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Active(e, b) => {
+                0u32.encode(out);
+                e.encode(out);
+                b.encode(out);
+            }
+            Self::Passive(b) => {
+                1u32.encode(out);
+                b.encode(out);
+            }
+            Self::ActiveExplicit(x, e, b) => {
+                2u32.encode(out);
+                x.encode(out);
+                e.encode(out);
+                b.encode(out);
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use crate::instructions::{Instruction, NumericInstruction};
 
-    // TODO: Write tests
+    use super::*;
+
+    const EMPTY: &[u8] = &[];
+
+    #[test]
+    fn test_data() {
+        let offset = || Expression(vec![Instruction::Numeric(NumericInstruction::I32Const(1))]);
+        assert_eq!(
+            Data::decode(&[0x00, 0x41, 0x01, 0x0B, 0x02, 0xAA, 0xBB]),
+            Ok((EMPTY, Data::Active(offset(), vec!(0xAA, 0xBB))))
+        );
+        assert_eq!(
+            Data::decode(&[0x01, 0x01, 0xAA]),
+            Ok((EMPTY, Data::Passive(vec!(0xAA))))
+        );
+        assert_eq!(
+            Data::decode(&[0x02, 0x01, 0x41, 0x01, 0x0B, 0x00]),
+            Ok((EMPTY, Data::ActiveExplicit(1, offset(), vec!())))
+        );
+        assert!(Data::decode(&[0x03]).is_err());
+    }
 }
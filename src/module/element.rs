@@ -1,14 +1,20 @@
-use nom::IResult;
+use nom::{
+    bytes::complete::tag,
+    combinator::{map, verify},
+    sequence::pair,
+    IResult,
+};
 
-use crate::{instructions::Expression, Decode};
+use crate::{instructions::Expression, Decode, Encode};
 
 use super::{
     indices::{FuncIdx, TableIdx},
     types::RefType,
 };
 
-/// TODO: Document
+/// An element segment that can be used to initialize a range of a table
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Element {
     ActiveIndex(Expression, Vec<FuncIdx>),
     PassiveIndex(ElementKind, Vec<FuncIdx>),
@@ -21,26 +27,171 @@ pub enum Element {
 }
 
 impl Decode for Element {
-    fn decode(_input: &[u8]) -> IResult<&[u8], Self> {
-        todo!()
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, flag) = verify(u32::decode, |flag| *flag <= 7)(input)?;
+        match flag {
+            0 => map(pair(Expression::decode, Vec::<FuncIdx>::decode), |pair| {
+                Self::ActiveIndex(pair.0, pair.1)
+            })(input),
+            1 => map(pair(ElementKind::decode, Vec::<FuncIdx>::decode), |pair| {
+                Self::PassiveIndex(pair.0, pair.1)
+            })(input),
+            2 => {
+                let (input, x) = TableIdx::decode(input)?;
+                let (input, e) = Expression::decode(input)?;
+                let (input, et) = ElementKind::decode(input)?;
+                let (input, y) = Vec::<FuncIdx>::decode(input)?;
+                Ok((input, Self::ActiveExplicitIndex(x, e, et, y)))
+            }
+            3 => map(pair(ElementKind::decode, Vec::<FuncIdx>::decode), |pair| {
+                Self::DeclarativeIndex(pair.0, pair.1)
+            })(input),
+            4 => map(
+                pair(Expression::decode, Vec::<Expression>::decode),
+                |pair| Self::ActiveExpression(pair.0, pair.1),
+            )(input),
+            5 => map(pair(RefType::decode, Vec::<Expression>::decode), |pair| {
+                Self::PassiveExpression(pair.0, pair.1)
+            })(input),
+            6 => {
+                let (input, x) = TableIdx::decode(input)?;
+                let (input, e) = Expression::decode(input)?;
+                let (input, et) = RefType::decode(input)?;
+                let (input, el) = Vec::<Expression>::decode(input)?;
+                Ok((input, Self::ActiveExplicitExpression(x, e, et, el)))
+            }
+            7 => map(pair(RefType::decode, Vec::<Expression>::decode), |pair| {
+                Self::DeclarativeExpression(pair.0, pair.1)
+            })(input),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Encode for Element {
+    fn encode(&self, out: &mut Vec<u8>) {
+        // Each variant has its own flag, in the order they are declared
+        match self {
+            Self::ActiveIndex(e, y) => {
+                0u32.encode(out);
+                e.encode(out);
+                y.encode(out);
+            }
+            Self::PassiveIndex(et, y) => {
+                1u32.encode(out);
+                et.encode(out);
+                y.encode(out);
+            }
+            Self::ActiveExplicitIndex(x, e, et, y) => {
+                2u32.encode(out);
+                x.encode(out);
+                e.encode(out);
+                et.encode(out);
+                y.encode(out);
+            }
+            Self::DeclarativeIndex(et, y) => {
+                3u32.encode(out);
+                et.encode(out);
+                y.encode(out);
+            }
+            Self::ActiveExpression(e, el) => {
+                4u32.encode(out);
+                e.encode(out);
+                el.encode(out);
+            }
+            Self::PassiveExpression(et, el) => {
+                5u32.encode(out);
+                et.encode(out);
+                el.encode(out);
+            }
+            Self::ActiveExplicitExpression(x, e, et, el) => {
+                6u32.encode(out);
+                x.encode(out);
+                e.encode(out);
+                et.encode(out);
+                el.encode(out);
+            }
+            Self::DeclarativeExpression(et, el) => {
+                7u32.encode(out);
+                et.encode(out);
+                el.encode(out);
+            }
+        }
     }
 }
 
-/// TODO: Document
+/// The kind of references held by an element segment given as function indices
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ElementKind {
     FuncRef,
 }
 
 impl Decode for ElementKind {
-    fn decode(_input: &[u8]) -> IResult<&[u8], Self> {
-        todo!()
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        map(tag([0x00]), |_| Self::FuncRef)(input)
+    }
+}
+
+impl Encode for ElementKind {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(0x00);
     }
 }
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use crate::{
+        instructions::{Instruction, NumericInstruction, ReferenceInstruction},
+        module::types::HeapType,
+    };
+
+    use super::*;
+
+    const EMPTY: &[u8] = &[];
 
-    // TODO: Write tests
+    #[test]
+    fn test_element() {
+        let offset = || Expression(vec![Instruction::Numeric(NumericInstruction::I32Const(1))]);
+        assert_eq!(
+            Element::decode(&[0x00, 0x41, 0x01, 0x0B, 0x02, 0x00, 0x01]),
+            Ok((EMPTY, Element::ActiveIndex(offset(), vec!(0, 1))))
+        );
+        assert_eq!(
+            Element::decode(&[0x02, 0x01, 0x41, 0x01, 0x0B, 0x00, 0x01, 0x03]),
+            Ok((
+                EMPTY,
+                Element::ActiveExplicitIndex(1, offset(), ElementKind::FuncRef, vec!(3))
+            ))
+        );
+        assert_eq!(
+            Element::decode(&[0x03, 0x00, 0x01, 0x02]),
+            Ok((
+                EMPTY,
+                Element::DeclarativeIndex(ElementKind::FuncRef, vec!(2))
+            ))
+        );
+        assert_eq!(
+            Element::decode(&[0x05, 0x70, 0x01, 0xD0, 0x70, 0x0B]),
+            Ok((
+                EMPTY,
+                Element::PassiveExpression(
+                    RefType::FUNCREF,
+                    vec!(Expression(vec!(Instruction::Reference(
+                        ReferenceInstruction::Null(HeapType::Func)
+                    ))))
+                )
+            ))
+        );
+        assert!(Element::decode(&[0x08]).is_err());
+    }
+
+    #[test]
+    fn test_element_kind() {
+        assert_eq!(
+            ElementKind::decode(&[0x00]),
+            Ok((EMPTY, ElementKind::FuncRef))
+        );
+        assert!(ElementKind::decode(&[0x01]).is_err());
+    }
 }
@@ -1,14 +1,15 @@
 use nom::{branch::alt, bytes::complete::tag, combinator::map, sequence::pair, IResult};
 
-use crate::Decode;
+use crate::{Decode, Encode};
 
 use super::{
-    indices::{FuncIdx, GlobalIdx, MemIdx, TableIdx},
+    indices::{FuncIdx, GlobalIdx, MemIdx, TableIdx, TagIdx},
     values::Name,
 };
 
 /// TODO: Document
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Export {
     pub name: Name,
     pub descriptor: ExportDescriptor,
@@ -23,13 +24,22 @@ impl Decode for Export {
     }
 }
 
+impl Encode for Export {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.name.encode(out);
+        self.descriptor.encode(out);
+    }
+}
+
 /// TODO: Document
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExportDescriptor {
     Func(FuncIdx),
     Table(TableIdx),
     Mem(MemIdx),
     Global(GlobalIdx),
+    Tag(TagIdx),
 }
 
 impl Decode for ExportDescriptor {
@@ -45,10 +55,25 @@ impl Decode for ExportDescriptor {
             map(pair(tag([0x03]), GlobalIdx::decode), |pair| {
                 Self::Global(pair.1)
             }),
+            map(pair(tag([0x04]), TagIdx::decode), |pair| Self::Tag(pair.1)),
         ))(input)
     }
 }
 
+impl Encode for ExportDescriptor {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let (kind, idx) = match self {
+            Self::Func(funcidx) => (0x00, funcidx),
+            Self::Table(tableidx) => (0x01, tableidx),
+            Self::Mem(memidx) => (0x02, memidx),
+            Self::Global(globalidx) => (0x03, globalidx),
+            Self::Tag(tagidx) => (0x04, tagidx),
+        };
+        out.push(kind);
+        idx.encode(out);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -76,6 +101,10 @@ mod tests {
             ExportDescriptor::decode(&[0x00, 0x00]),
             Ok((EMPTY, ExportDescriptor::Func(0)))
         );
+        assert_eq!(
+            ExportDescriptor::decode(&[0x04, 0x01]),
+            Ok((EMPTY, ExportDescriptor::Tag(1)))
+        );
         assert!(ExportDescriptor::decode(&[0x7A]).is_err());
     }
 }
@@ -1,25 +1,59 @@
-use nom::IResult;
+use nom::{combinator::map, sequence::pair, IResult};
 
-use crate::{instructions::Expression, Decode};
+use crate::{instructions::Expression, Decode, Encode};
 
 use super::types::GlobalType;
 
-/// TODO: Document
+/// A global variable with its type and the constant expression that initializes it
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Global {
-    gt: GlobalType,
-    init: Expression,
+    pub gt: GlobalType,
+    pub init: Expression,
 }
 
 impl Decode for Global {
-    fn decode(_input: &[u8]) -> IResult<&[u8], Self> {
-        todo!()
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        map(pair(GlobalType::decode, Expression::decode), |pair| Self {
+            gt: pair.0,
+            init: pair.1,
+        })(input)
+    }
+}
+
+impl Encode for Global {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.gt.encode(out);
+        self.init.encode(out);
     }
 }
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use crate::{
+        instructions::{Instruction, NumericInstruction},
+        module::types::{Mutability, NumType, ValType},
+    };
 
-    // TODO: Write tests
+    use super::*;
+
+    const EMPTY: &[u8] = &[];
+
+    #[test]
+    fn test_global() {
+        assert_eq!(
+            Global::decode(&[0x7F, 0x01, 0x41, 0x08, 0x0B]),
+            Ok((
+                EMPTY,
+                Global {
+                    gt: GlobalType {
+                        m: Mutability::Var,
+                        t: ValType::NumType(NumType::I32),
+                    },
+                    init: Expression(vec!(Instruction::Numeric(NumericInstruction::I32Const(8))))
+                }
+            ))
+        );
+        assert!(Global::decode(&[0x7F, 0x01, 0x41, 0x08]).is_err());
+    }
 }
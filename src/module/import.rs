@@ -6,16 +6,17 @@ use nom::{
     IResult,
 };
 
-use crate::Decode;
+use crate::{Decode, Encode};
 
 use super::{
     indices::TypeIdx,
-    types::{GlobalType, MemType, TableType},
+    types::{GlobalType, MemType, TableType, TagType},
     values::Name,
 };
 
 /// TODO: Document
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Import {
     pub module: Name,
     pub name: Name,
@@ -35,13 +36,23 @@ impl Decode for Import {
     }
 }
 
+impl Encode for Import {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.module.encode(out);
+        self.name.encode(out);
+        self.descriptor.encode(out);
+    }
+}
+
 /// TODO: Document
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ImportDescriptor {
     Func(TypeIdx),
     Table(TableType),
     Mem(MemType),
     Global(GlobalType),
+    Tag(TagType),
 }
 
 impl Decode for ImportDescriptor {
@@ -57,10 +68,38 @@ impl Decode for ImportDescriptor {
             map(pair(tag([0x03]), GlobalType::decode), |pair| {
                 Self::Global(pair.1)
             }),
+            map(pair(tag([0x04]), TagType::decode), |pair| Self::Tag(pair.1)),
         ))(input)
     }
 }
 
+impl Encode for ImportDescriptor {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Func(typeidx) => {
+                out.push(0x00);
+                typeidx.encode(out);
+            }
+            Self::Table(tt) => {
+                out.push(0x01);
+                tt.encode(out);
+            }
+            Self::Mem(mt) => {
+                out.push(0x02);
+                mt.encode(out);
+            }
+            Self::Global(gt) => {
+                out.push(0x03);
+                gt.encode(out);
+            }
+            Self::Tag(tt) => {
+                out.push(0x04);
+                tt.encode(out);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -89,6 +128,10 @@ mod tests {
             ImportDescriptor::decode(&[0x00, 0x00]),
             Ok((EMPTY, ImportDescriptor::Func(0)))
         );
+        assert_eq!(
+            ImportDescriptor::decode(&[0x04, 0x00, 0x01]),
+            Ok((EMPTY, ImportDescriptor::Tag(TagType { typeidx: 1 })))
+        );
         assert!(ImportDescriptor::decode(&[0x7A]).is_err());
     }
 }
@@ -7,3 +7,6 @@ pub type ElemIdx = u32;
 pub type DataIdx = u32;
 pub type LocalIdx = u32;
 pub type LabelIdx = u32;
+pub type TagIdx = u32;
+pub type FieldIdx = u32;
+pub type LaneIdx = u8;
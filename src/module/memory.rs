@@ -1,11 +1,12 @@
 use nom::{combinator::map, IResult};
 
-use crate::Decode;
+use crate::{Decode, Encode};
 
 use super::types::MemType;
 
 /// TODO: Document
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Memory {
     pub mt: MemType,
 }
@@ -16,6 +17,12 @@ impl Decode for Memory {
     }
 }
 
+impl Encode for Memory {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.mt.encode(out);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::module::types::Limits;
@@ -32,7 +39,9 @@ mod tests {
                 EMPTY,
                 Memory {
                     mt: MemType {
-                        lim: Limits { min: 0, max: None }
+                        lim: Limits { min: 0, max: None },
+                        shared: false,
+                        memory64: false
                     }
                 }
             ))
@@ -0,0 +1,272 @@
+use nom::{
+    bytes::complete::take,
+    combinator::{all_consuming, map, verify},
+    multi::count,
+    sequence::pair,
+    IResult,
+};
+
+use crate::Decode;
+
+use super::values::Name;
+
+/// The names of a module and the items in it, from the `name` custom section
+///
+/// Names are only a debugging aid, so they don't affect validation or execution. Besides the module, function and local
+/// names of the core specification, this includes the subsections of the extended name section proposal.
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NameSection {
+    pub module: Option<Name>,
+    pub functions: NameMap,
+    pub locals: IndirectNameMap,
+    pub labels: IndirectNameMap,
+    pub types: NameMap,
+    pub tables: NameMap,
+    pub memories: NameMap,
+    pub globals: NameMap,
+    pub elems: NameMap,
+    pub data: NameMap,
+    pub fields: IndirectNameMap,
+}
+
+impl NameSection {
+    /// Decode the contents of a name section, leaving out the subsections that are malformed
+    ///
+    /// The specification requires a malformed name section not to fail decoding the module, so each subsection that
+    /// can't be used adds a warning instead.
+    pub(crate) fn decode_lenient(mut input: &[u8]) -> (Self, Vec<&'static str>) {
+        let mut names = Self::default();
+        let mut warnings = Vec::new();
+        let mut last = None;
+        while !input.is_empty() {
+            let (rest, (id, contents)) = match subsection(input) {
+                Ok(subsection) => subsection,
+                Err(_) => {
+                    warnings.push("malformed name subsection");
+                    break;
+                }
+            };
+            input = rest;
+
+            // Each subsection may appear at most once, in order of id
+            if last.is_some_and(|last| id <= last) {
+                warnings.push("name subsection out of order");
+                continue;
+            }
+            last = Some(id);
+
+            let decoded = match id {
+                0 => {
+                    all_consuming(Name::decode)(contents).map(|(_, name)| names.module = Some(name))
+                }
+                1 => decode_into(contents, &mut names.functions),
+                2 => decode_into(contents, &mut names.locals),
+                3 => decode_into(contents, &mut names.labels),
+                4 => decode_into(contents, &mut names.types),
+                5 => decode_into(contents, &mut names.tables),
+                6 => decode_into(contents, &mut names.memories),
+                7 => decode_into(contents, &mut names.globals),
+                8 => decode_into(contents, &mut names.elems),
+                9 => decode_into(contents, &mut names.data),
+                10 => decode_into(contents, &mut names.fields),
+                // Subsections this decoder doesn't know are skipped, since the contents are prefixed by their size
+                _ => Ok(()),
+            };
+            if decoded.is_err() {
+                warnings.push(match id {
+                    0 => "malformed module name",
+                    1 => "malformed function names",
+                    2 => "malformed local names",
+                    3 => "malformed label names",
+                    4 => "malformed type names",
+                    5 => "malformed table names",
+                    6 => "malformed memory names",
+                    7 => "malformed global names",
+                    8 => "malformed elem segment names",
+                    9 => "malformed data segment names",
+                    _ => "malformed field names",
+                });
+            }
+        }
+        (names, warnings)
+    }
+}
+
+/// Decode the whole contents of a subsection into one of the maps of the name section
+fn decode_into<'a, T: Decode>(
+    contents: &'a [u8],
+    map: &mut T,
+) -> Result<(), nom::Err<nom::error::Error<&'a [u8]>>> {
+    let (_, decoded) = all_consuming(T::decode)(contents)?;
+    *map = decoded;
+    Ok(())
+}
+
+/// Decode the id and contents of a subsection
+fn subsection(input: &[u8]) -> IResult<&[u8], (u8, &[u8])> {
+    let (input, id) = u8::decode(input)?;
+    let (input, size) = u32::decode(input)?;
+    let (input, contents) = take(size as usize)(input)?;
+    Ok((input, (id, contents)))
+}
+
+/// Names for the indices of an index space, in increasing order of index
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NameMap(pub Vec<(u32, Name)>);
+
+impl NameMap {
+    pub fn get(&self, idx: u32) -> Option<&Name> {
+        self.0
+            .binary_search_by_key(&idx, |(i, _)| *i)
+            .ok()
+            .map(|i| &self.0[i].1)
+    }
+}
+
+impl Decode for NameMap {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, length) = u32::decode(input)?;
+        map(
+            verify(
+                count(pair(u32::decode, Name::decode), length as usize),
+                increasing,
+            ),
+            Self,
+        )(input)
+    }
+}
+
+/// Names for the indices of an index space inside each function, such as its locals
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndirectNameMap(pub Vec<(u32, NameMap)>);
+
+impl IndirectNameMap {
+    pub fn get(&self, idx: u32) -> Option<&NameMap> {
+        self.0
+            .binary_search_by_key(&idx, |(i, _)| *i)
+            .ok()
+            .map(|i| &self.0[i].1)
+    }
+}
+
+impl Decode for IndirectNameMap {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, length) = u32::decode(input)?;
+        map(
+            verify(
+                count(pair(u32::decode, NameMap::decode), length as usize),
+                increasing,
+            ),
+            Self,
+        )(input)
+    }
+}
+
+/// Whether the indices of a name map are in increasing order without duplicates, as the lookups rely on
+fn increasing<T>(entries: &[(u32, T)]) -> bool {
+    entries.windows(2).all(|pair| pair[0].0 < pair[1].0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY: &[u8] = &[];
+
+    fn name(s: &str) -> Name {
+        Name(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_name_map() {
+        assert_eq!(
+            NameMap::decode(&[0x02, 0x00, 0x01, 0x61, 0x03, 0x01, 0x62]),
+            Ok((EMPTY, NameMap(vec!((0, name("a")), (3, name("b"))))))
+        );
+        assert_eq!(
+            NameMap(vec!((0, name("a")), (3, name("b")))).get(3),
+            Some(&name("b"))
+        );
+        assert_eq!(NameMap(vec!((0, name("a")))).get(1), None);
+        // Indices must be increasing and unique
+        assert!(NameMap::decode(&[0x02, 0x03, 0x01, 0x61, 0x00, 0x01, 0x62]).is_err());
+        assert!(NameMap::decode(&[0x02, 0x00, 0x01, 0x61, 0x00, 0x01, 0x62]).is_err());
+    }
+
+    #[test]
+    fn test_indirect_name_map() {
+        assert_eq!(
+            IndirectNameMap::decode(&[0x01, 0x02, 0x01, 0x00, 0x01, 0x61]),
+            Ok((
+                EMPTY,
+                IndirectNameMap(vec!((2, NameMap(vec!((0, name("a")))))))
+            ))
+        );
+        assert!(IndirectNameMap::decode(&[0x02, 0x01, 0x00, 0x01, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_name_section() {
+        let input = &[
+            0x00, 0x02, 0x01, 0x6D, // module "m"
+            0x01, 0x04, 0x01, 0x00, 0x01, 0x66, // function 0 "f"
+            0x03, 0x06, 0x01, 0x00, 0x01, 0x01, 0x01, 0x6C, // label 1 of function 0 "l"
+            0x07, 0x04, 0x01, 0x02, 0x01, 0x67, // global 2 "g"
+            0x0A, 0x06, 0x01, 0x01, 0x01, 0x00, 0x01, 0x78, // field 0 of type 1 "x"
+            0x0F, 0x01, 0xFF, // an unknown subsection
+        ];
+        assert_eq!(
+            NameSection::decode_lenient(input),
+            (
+                NameSection {
+                    module: Some(name("m")),
+                    functions: NameMap(vec!((0, name("f")))),
+                    labels: IndirectNameMap(vec!((0, NameMap(vec!((1, name("l"))))))),
+                    globals: NameMap(vec!((2, name("g")))),
+                    fields: IndirectNameMap(vec!((1, NameMap(vec!((0, name("x"))))))),
+                    ..NameSection::default()
+                },
+                vec!()
+            )
+        );
+    }
+
+    #[test]
+    fn test_malformed_name_section() {
+        // The function names have an index out of order, but the module name is still used
+        let input = &[
+            0x00, 0x02, 0x01, 0x6D, // module "m"
+            0x01, 0x07, 0x02, 0x01, 0x01, 0x66, 0x00, 0x01, 0x67, // functions 1 "f" and 0 "g"
+        ];
+        assert_eq!(
+            NameSection::decode_lenient(input),
+            (
+                NameSection {
+                    module: Some(name("m")),
+                    ..NameSection::default()
+                },
+                vec!("malformed function names")
+            )
+        );
+
+        // The second module name is out of order and the last subsection is cut off
+        let input = &[
+            0x00, 0x02, 0x01, 0x6D, // module "m"
+            0x00, 0x02, 0x01, 0x6E, // module "n"
+            0x01, 0x07, 0x01, // truncated
+        ];
+        assert_eq!(
+            NameSection::decode_lenient(input),
+            (
+                NameSection {
+                    module: Some(name("m")),
+                    ..NameSection::default()
+                },
+                vec!("name subsection out of order", "malformed name subsection")
+            )
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use nom::{combinator::fail, sequence::pair, IResult};
+
+use crate::Decode;
+
+use super::values::Name;
+
+/// The languages, tools and SDKs that produced a module, from the `producers` custom section of the tool conventions
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Producers {
+    pub language: Vec<Producer>,
+    pub processed_by: Vec<Producer>,
+    pub sdk: Vec<Producer>,
+}
+
+impl Decode for Producers {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        let (mut input, length) = u32::decode(input)?;
+        let mut producers = Self::default();
+        let mut fields: Vec<Name> = Vec::new();
+        for _ in 0..length {
+            let (rest, (field, values)) = pair(Name::decode, Vec::<Producer>::decode)(input)?;
+            // Each field may appear at most once, and only the fields of the tool conventions are allowed
+            if fields.contains(&field) {
+                return fail(input);
+            }
+            match field.0.as_slice() {
+                b"language" => producers.language = values,
+                b"processed-by" => producers.processed_by = values,
+                b"sdk" => producers.sdk = values,
+                _ => return fail(input),
+            }
+            fields.push(field);
+            input = rest;
+        }
+        Ok((input, producers))
+    }
+}
+
+/// A name and version in the `producers` section, such as `rustc` and `1.80.0`
+///
+/// The version is free-form text and may be empty.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Producer {
+    pub name: Name,
+    pub version: Name,
+}
+
+impl Decode for Producer {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        let (input, (name, version)) = pair(Name::decode, Name::decode)(input)?;
+        Ok((input, Self { name, version }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY: &[u8] = &[];
+
+    fn name(s: &str) -> Name {
+        Name(s.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_producers() {
+        let input = &[
+            0x02, // two fields
+            0x08, 0x6C, 0x61, 0x6E, 0x67, 0x75, 0x61, 0x67, 0x65, // "language"
+            0x01, 0x04, 0x52, 0x75, 0x73, 0x74, 0x00, // "Rust" ""
+            0x03, 0x73, 0x64, 0x6B, // "sdk"
+            0x01, 0x01, 0x78, 0x03, 0x31, 0x2E, 0x30, // "x" "1.0"
+        ];
+        assert_eq!(
+            Producers::decode(input),
+            Ok((
+                EMPTY,
+                Producers {
+                    language: vec!(Producer {
+                        name: name("Rust"),
+                        version: name("")
+                    }),
+                    processed_by: vec!(),
+                    sdk: vec!(Producer {
+                        name: name("x"),
+                        version: name("1.0")
+                    }),
+                }
+            ))
+        );
+        // Unknown and duplicate fields are malformed
+        assert!(Producers::decode(&[0x01, 0x01, 0x61, 0x00]).is_err());
+        assert!(Producers::decode(&[
+            0x02, 0x03, 0x73, 0x64, 0x6B, 0x00, 0x03, 0x73, 0x64, 0x6B, 0x00
+        ])
+        .is_err());
+    }
+}
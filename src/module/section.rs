@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use nom::{
     bytes::complete::take,
     combinator::{consumed, map},
@@ -16,17 +18,19 @@ use super::{
     indices::{FuncIdx, TypeIdx},
     memory::Memory,
     table::Table,
-    types::FuncType,
+    tag::Tag,
+    types::RecType,
     values::Name,
 };
 
 /// A component of a module record
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Section {
     /// Intended for use in debugging or third-party extensions
     CustomSection((Name, Vec<u8>)),
     /// Types found in the module
-    TypeSection(Vec<FuncType>),
+    TypeSection(Vec<RecType>),
     /// Imports that are required for instantiation
     ImportSection(Vec<Import>),
     /// Correlation between functions and their respective types
@@ -49,6 +53,34 @@ pub enum Section {
     DataSection(Vec<Data>),
     /// The number of data segments in the data section
     DataCountSection(Option<u32>),
+    /// Tags that identify the exceptions the module can throw and catch
+    TagSection(Vec<Tag>),
+}
+
+/// Where a section was found in the binary, which decoding the section itself doesn't keep
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SectionHeader {
+    /// The section id, such as 1 for the type section
+    pub id: u8,
+    /// The offset of the section id from the start of the module
+    pub offset: usize,
+    /// The offsets of the section contents, after the id and size
+    pub contents: Range<usize>,
+}
+
+impl SectionHeader {
+    /// Locate a section that starts at the given offset from the bytes decoded for it
+    pub(crate) fn new(offset: usize, section: &[u8]) -> Self {
+        // The bytes were already decoded as a section, so they start with an id and a valid size
+        let (contents, _) = u32::decode(&section[1..]).expect("section size");
+        let start = offset + section.len() - contents.len();
+        Self {
+            id: section[0],
+            offset,
+            contents: start..offset + section.len(),
+        }
+    }
 }
 
 impl Decode for Section {
@@ -65,7 +97,7 @@ impl Decode for Section {
             }
             1 => {
                 // Type section
-                map(Vec::<FuncType>::decode, |types| Section::TypeSection(types))(input)
+                map(Vec::<RecType>::decode, |types| Section::TypeSection(types))(input)
             }
             2 => {
                 // Import section
@@ -127,6 +159,10 @@ impl Decode for Section {
                     Section::DataCountSection(data_count)
                 })(input)
             }
+            13 => {
+                // Tag section
+                map(Vec::<Tag>::decode, Section::TagSection)(input)
+            }
             _ => unreachable!(), // TODO: This should probably actually throw a parse error rather than panic. Write a test for it
         }
     }
@@ -134,16 +170,35 @@ impl Decode for Section {
 
 #[cfg(test)]
 mod tests {
-    use crate::module::{
-        export::ExportDescriptor,
-        import::ImportDescriptor,
-        types::{Limits, MemType, NumType, RefType, TableType, ValType},
+    use crate::{
+        instructions::{Expression, Instruction, NumericInstruction},
+        module::{
+            export::ExportDescriptor,
+            import::ImportDescriptor,
+            types::{
+                FuncType, GlobalType, Limits, MemType, Mutability, NumType, RefType, SubType,
+                TableType, TagType, ValType,
+            },
+        },
     };
 
     use super::*;
 
     const EMPTY: &[u8] = &[];
 
+    #[test]
+    fn test_section_header() {
+        let section = &[0x01, 0x84, 0x80, 0x80, 0x80, 0x00, 0x01, 0x60, 0x00, 0x00];
+        assert_eq!(
+            SectionHeader::new(8, section),
+            SectionHeader {
+                id: 1,
+                offset: 8,
+                contents: 14..18,
+            }
+        );
+    }
+
     #[test]
     fn test_custom_section() {
         let input = &[0x00, 0x05, 0x01, 0xAA, 0x01, 0x02, 0x03];
@@ -165,9 +220,11 @@ mod tests {
             section,
             Ok((
                 EMPTY,
-                Section::TypeSection(vec!(FuncType {
-                    rt1: vec!(ValType::NumType(NumType::I32)),
-                    rt2: vec!(ValType::NumType(NumType::I32)),
+                Section::TypeSection(vec!(RecType {
+                    types: vec!(SubType::from(FuncType {
+                        rt1: vec!(ValType::NumType(NumType::I32)),
+                        rt2: vec!(ValType::NumType(NumType::I32)),
+                    }))
                 }))
             ))
         )
@@ -211,8 +268,9 @@ mod tests {
                             min: 0,
                             max: Some(1),
                         },
-                        et: RefType::FuncRef
-                    }
+                        et: RefType::FUNCREF
+                    },
+                    init: None
                 }))
             ))
         )
@@ -228,26 +286,33 @@ mod tests {
                 EMPTY,
                 Section::MemorySection(vec!(Memory {
                     mt: MemType {
-                        lim: Limits { min: 0, max: None }
+                        lim: Limits { min: 0, max: None },
+                        shared: false,
+                        memory64: false
                     }
                 }))
             ))
         )
     }
 
-    // TODO: Tests for global section
-    // #[test]
-    // fn test_global_section() {
-    //     let input = &[0x06, 0x??];
-    //     let section = Section::decode(input);
-    //     assert_eq!(
-    //         section,
-    //         Ok((
-    //             EMPTY,
-    //             Section::GlobalSection(vec!(Global { gt: todo!(), init: todo!() }))
-    //         ))
-    //     )
-    // }
+    #[test]
+    fn test_global_section() {
+        let input = &[0x06, 0x06, 0x01, 0x7F, 0x00, 0x41, 0x08, 0x0B];
+        let section = Section::decode(input);
+        assert_eq!(
+            section,
+            Ok((
+                EMPTY,
+                Section::GlobalSection(vec!(Global {
+                    gt: GlobalType {
+                        m: Mutability::Const,
+                        t: ValType::NumType(NumType::I32),
+                    },
+                    init: Expression(vec!(Instruction::Numeric(NumericInstruction::I32Const(8))))
+                }))
+            ))
+        )
+    }
 
     #[test]
     fn test_export_section() {
@@ -272,19 +337,21 @@ mod tests {
         assert_eq!(section, Ok((EMPTY, Section::StartSection(Some(0)))))
     }
 
-    // TODO: Tests for element section
-    // #[test]
-    // fn test_element_section() {
-    //     let input = &[0x09, 0x??];
-    //     let section = Section::decode(input);
-    //     assert_eq!(
-    //         section,
-    //         Ok((
-    //             EMPTY,
-    //             Section::ElementSection(vec!(Element { gt: todo!(), init: todo!() }))
-    //         ))
-    //     )
-    // }
+    #[test]
+    fn test_element_section() {
+        let input = &[0x09, 0x07, 0x01, 0x00, 0x41, 0x00, 0x0B, 0x01, 0x00];
+        let section = Section::decode(input);
+        assert_eq!(
+            section,
+            Ok((
+                EMPTY,
+                Section::ElementSection(vec!(Element::ActiveIndex(
+                    Expression(vec!(Instruction::Numeric(NumericInstruction::I32Const(0)))),
+                    vec!(0)
+                )))
+            ))
+        )
+    }
 
     // TODO: Tests for code section
     // #[test]
@@ -300,19 +367,21 @@ mod tests {
     //     )
     // }
 
-    // TODO: Tests for data section
-    // #[test]
-    // fn test_data_section() {
-    //     let input = &[0x09, 0x??];
-    //     let section = Section::decode(input);
-    //     assert_eq!(
-    //         section,
-    //         Ok((
-    //             EMPTY,
-    //             Section::DataSection(vec!(Data { gt: todo!(), init: todo!() }))
-    //         ))
-    //     )
-    // }
+    #[test]
+    fn test_data_section() {
+        let input = &[0x0B, 0x07, 0x01, 0x00, 0x41, 0x00, 0x0B, 0x01, 0xAA];
+        let section = Section::decode(input);
+        assert_eq!(
+            section,
+            Ok((
+                EMPTY,
+                Section::DataSection(vec!(Data::Active(
+                    Expression(vec!(Instruction::Numeric(NumericInstruction::I32Const(0)))),
+                    vec!(0xAA)
+                )))
+            ))
+        )
+    }
 
     #[test]
     fn test_data_count_section() {
@@ -320,4 +389,19 @@ mod tests {
         let section = Section::decode(input);
         assert_eq!(section, Ok((EMPTY, Section::DataCountSection(Some(8)))))
     }
+
+    #[test]
+    fn test_tag_section() {
+        let input = &[0x0D, 0x03, 0x01, 0x00, 0x00];
+        let section = Section::decode(input);
+        assert_eq!(
+            section,
+            Ok((
+                EMPTY,
+                Section::TagSection(vec!(Tag {
+                    tt: TagType { typeidx: 0 }
+                }))
+            ))
+        )
+    }
 }
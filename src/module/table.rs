@@ -1,24 +1,62 @@
-use nom::{combinator::map, IResult};
+use nom::{
+    branch::alt,
+    bytes::complete::tag,
+    combinator::map,
+    sequence::{pair, preceded},
+    IResult,
+};
 
-use crate::Decode;
+use crate::{instructions::Expression, Decode, Encode};
 
 use super::types::TableType;
 
-/// TODO: Document
+/// A table definition, whose elements start out null unless it has an initializer expression
+///
+/// Tables of non-nullable references have no null to start out with, so they need an initializer.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Table {
     pub tt: TableType,
+    pub init: Option<Expression>,
 }
 
 impl Decode for Table {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
-        map(TableType::decode, |tt| Table { tt })(input)
+        alt((
+            map(
+                preceded(
+                    tag([0x40, 0x00]),
+                    pair(TableType::decode, Expression::decode),
+                ),
+                |(tt, init)| Table {
+                    tt,
+                    init: Some(init),
+                },
+            ),
+            map(TableType::decode, |tt| Table { tt, init: None }),
+        ))(input)
+    }
+}
+
+impl Encode for Table {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match &self.init {
+            Some(init) => {
+                out.extend_from_slice(&[0x40, 0x00]);
+                self.tt.encode(out);
+                init.encode(out);
+            }
+            None => self.tt.encode(out),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::module::types::{Limits, RefType};
+    use crate::{
+        instructions::{Instruction, ReferenceInstruction},
+        module::types::{HeapType, Limits, RefType},
+    };
 
     use super::*;
 
@@ -36,11 +74,28 @@ mod tests {
                             min: 0,
                             max: Some(1)
                         },
-                        et: RefType::FuncRef
-                    }
+                        et: RefType::FUNCREF
+                    },
+                    init: None
+                }
+            ))
+        );
+        assert_eq!(
+            Table::decode(&[0x40, 0x00, 0x64, 0x70, 0x00, 0x01, 0xD2, 0x00, 0x0B]),
+            Ok((
+                EMPTY,
+                Table {
+                    tt: TableType {
+                        lim: Limits { min: 1, max: None },
+                        et: RefType::non_null(HeapType::Func)
+                    },
+                    init: Some(Expression(vec!(Instruction::Reference(
+                        ReferenceInstruction::Func(0)
+                    ))))
                 }
             ))
         );
+        assert!(Table::decode(&[0x40, 0x01, 0x70, 0x00, 0x01, 0x0B]).is_err());
         assert!(Table::decode(&[0x7A]).is_err());
     }
 }
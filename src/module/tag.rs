@@ -0,0 +1,45 @@
+use nom::{combinator::map, IResult};
+
+use crate::{Decode, Encode};
+
+use super::types::TagType;
+
+/// An exception tag, which identifies a kind of exception and the types of the values it carries
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tag {
+    pub tt: TagType,
+}
+
+impl Decode for Tag {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        map(TagType::decode, |tt| Tag { tt })(input)
+    }
+}
+
+impl Encode for Tag {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.tt.encode(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY: &[u8] = &[];
+
+    #[test]
+    fn test_tag() {
+        assert_eq!(
+            Tag::decode(&[0x00, 0x01]),
+            Ok((
+                EMPTY,
+                Tag {
+                    tt: TagType { typeidx: 1 }
+                }
+            ))
+        );
+        assert!(Tag::decode(&[0x7A]).is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use nom::{branch::alt, bytes::complete::tag, combinator::map, sequence::preceded, IResult};
+
+use crate::Decode;
+
+use super::values::Name;
+
+/// A feature from the `target_features` custom section of the tool conventions, which records whether the module was
+/// compiled to use the feature or to avoid it
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TargetFeature {
+    /// Prefixed by `+`, the module uses the feature
+    Used(Name),
+    /// Prefixed by `-`, the module doesn't use the feature and mustn't be linked with modules that do
+    Disallowed(Name),
+}
+
+impl Decode for TargetFeature {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        alt((
+            map(preceded(tag([b'+']), Name::decode), Self::Used),
+            map(preceded(tag([b'-']), Name::decode), Self::Disallowed),
+        ))(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY: &[u8] = &[];
+
+    #[test]
+    fn test_target_feature() {
+        assert_eq!(
+            TargetFeature::decode(&[0x2B, 0x04, 0x73, 0x69, 0x6D, 0x64]),
+            Ok((EMPTY, TargetFeature::Used(Name(b"simd".to_vec()))))
+        );
+        assert_eq!(
+            TargetFeature::decode(&[0x2D, 0x01, 0x78]),
+            Ok((EMPTY, TargetFeature::Disallowed(Name(b"x".to_vec()))))
+        );
+        // The required prefix was removed from the tool conventions
+        assert!(TargetFeature::decode(&[0x3D, 0x01, 0x78]).is_err());
+    }
+}
@@ -1,15 +1,21 @@
+use std::ops::Range;
+
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    combinator::map,
-    sequence::{pair, preceded},
+    combinator::{map, verify},
+    sequence::{pair, preceded, tuple},
     IResult,
 };
+use nom_leb128::leb128_i64;
+
+use crate::{Decode, Encode};
 
-use crate::Decode;
+use super::indices::TypeIdx;
 
 /// Classify numeric values
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NumType {
     I32,
     I64,
@@ -28,8 +34,20 @@ impl Decode for NumType {
     }
 }
 
+impl Encode for NumType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(match self {
+            NumType::I32 => 0x7F,
+            NumType::I64 => 0x7E,
+            NumType::F32 => 0x7D,
+            NumType::F64 => 0x7C,
+        });
+    }
+}
+
 /// Classify vectors of numeric values processed by vector instructions (also known as SIMD instructions, single instruction multiple data)
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VecType {
     V128,
 }
@@ -40,30 +58,174 @@ impl Decode for VecType {
     }
 }
 
-/// Classify first-class references to objects in the runtime store
-#[derive(Debug, PartialEq)]
-pub enum RefType {
-    FuncRef,
-    ExternRef,
+impl Encode for VecType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(0x7B);
+    }
+}
+
+/// Classify the objects that references point to, either abstractly or as a concrete type from the type section
+///
+/// The abstract heap types form separate hierarchies for functions, external references, exceptions and everything
+/// else (`any`), each with a bottom type (`nofunc`, `noextern`, `noexn` and `none`) that only null references have.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HeapType {
+    Func,
+    NoFunc,
+    Extern,
+    NoExtern,
+    Exn,
+    NoExn,
+    Any,
+    Eq,
+    I31,
+    Struct,
+    Array,
+    None,
+    Type(TypeIdx),
+}
+
+/// Decode the single byte encodings of the abstract heap types, which double as shorthands for nullable references
+fn abstract_heap_type(input: &[u8]) -> IResult<&[u8], HeapType> {
+    alt((
+        map(tag([0x70]), |_| HeapType::Func),
+        map(tag([0x73]), |_| HeapType::NoFunc),
+        map(tag([0x6F]), |_| HeapType::Extern),
+        map(tag([0x72]), |_| HeapType::NoExtern),
+        map(tag([0x69]), |_| HeapType::Exn),
+        map(tag([0x74]), |_| HeapType::NoExn),
+        map(tag([0x6E]), |_| HeapType::Any),
+        map(tag([0x6D]), |_| HeapType::Eq),
+        map(tag([0x6C]), |_| HeapType::I31),
+        map(tag([0x6B]), |_| HeapType::Struct),
+        map(tag([0x6A]), |_| HeapType::Array),
+        map(tag([0x71]), |_| HeapType::None),
+    ))(input)
+}
+
+impl Decode for HeapType {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        alt((
+            abstract_heap_type,
+            // Like in block types, a type index is a positive signed 33-bit integer so that it can't be confused
+            // with the abstract heap types, which are negative when read as signed integers
+            map(
+                verify(leb128_i64, |typeidx| {
+                    (0..=u32::MAX as i64).contains(typeidx)
+                }),
+                |typeidx| Self::Type(typeidx as TypeIdx),
+            ),
+        ))(input)
+    }
+}
+
+impl Encode for HeapType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(match self {
+            HeapType::Func => 0x70,
+            HeapType::NoFunc => 0x73,
+            HeapType::Extern => 0x6F,
+            HeapType::NoExtern => 0x72,
+            HeapType::Exn => 0x69,
+            HeapType::NoExn => 0x74,
+            HeapType::Any => 0x6E,
+            HeapType::Eq => 0x6D,
+            HeapType::I31 => 0x6C,
+            HeapType::Struct => 0x6B,
+            HeapType::Array => 0x6A,
+            HeapType::None => 0x71,
+            HeapType::Type(typeidx) => return i64::from(*typeidx).encode(out),
+        });
+    }
+}
+
+/// Classify first-class references to objects in the runtime store, which can only be null if the type is nullable
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RefType {
+    pub nullable: bool,
+    pub ht: HeapType,
+}
+
+impl RefType {
+    /// `funcref`, the shorthand for `(ref null func)`
+    pub const FUNCREF: Self = Self::null(HeapType::Func);
+    /// `externref`, the shorthand for `(ref null extern)`
+    pub const EXTERNREF: Self = Self::null(HeapType::Extern);
+    /// `exnref`, the shorthand for `(ref null exn)`
+    pub const EXNREF: Self = Self::null(HeapType::Exn);
+    /// `anyref`, the shorthand for `(ref null any)`
+    pub const ANYREF: Self = Self::null(HeapType::Any);
+    /// `eqref`, the shorthand for `(ref null eq)`
+    pub const EQREF: Self = Self::null(HeapType::Eq);
+    /// `i31ref`, the shorthand for `(ref null i31)`
+    pub const I31REF: Self = Self::null(HeapType::I31);
+    /// `structref`, the shorthand for `(ref null struct)`
+    pub const STRUCTREF: Self = Self::null(HeapType::Struct);
+    /// `arrayref`, the shorthand for `(ref null array)`
+    pub const ARRAYREF: Self = Self::null(HeapType::Array);
+    /// `nullref`, the shorthand for `(ref null none)`
+    pub const NULLREF: Self = Self::null(HeapType::None);
+
+    /// The nullable reference type `(ref null ht)`
+    pub const fn null(ht: HeapType) -> Self {
+        Self { nullable: true, ht }
+    }
+
+    /// The non-nullable reference type `(ref ht)`
+    pub const fn non_null(ht: HeapType) -> Self {
+        Self {
+            nullable: false,
+            ht,
+        }
+    }
 }
 
 impl Decode for RefType {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
         alt((
-            map(tag([0x70]), |_| RefType::FuncRef),
-            map(tag([0x6F]), |_| RefType::ExternRef),
+            map(preceded(tag([0x64]), HeapType::decode), Self::non_null),
+            map(preceded(tag([0x63]), HeapType::decode), Self::null),
+            map(abstract_heap_type, Self::null),
         ))(input)
     }
 }
 
+impl Encode for RefType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        // Nullable references to abstract heap types use the single byte shorthand
+        match (self.nullable, &self.ht) {
+            (true, HeapType::Type(_)) => out.push(0x63),
+            (true, _) => (),
+            (false, _) => out.push(0x64),
+        }
+        self.ht.encode(out);
+    }
+}
+
 /// Classify the individual values that WebAssembly code can compute with and the values that a variable accepts. They are either number types, vector types, or reference types
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ValType {
     NumType(NumType),
     VecType(VecType),
     RefType(RefType),
 }
 
+impl ValType {
+    /// Whether the type has a default value, which every type except non-nullable references does
+    pub fn is_defaultable(&self) -> bool {
+        !matches!(
+            self,
+            Self::RefType(RefType {
+                nullable: false,
+                ..
+            })
+        )
+    }
+}
+
 impl Decode for ValType {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
         alt((
@@ -74,11 +236,22 @@ impl Decode for ValType {
     }
 }
 
+impl Encode for ValType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ValType::NumType(num_type) => num_type.encode(out),
+            ValType::VecType(vec_type) => vec_type.encode(out),
+            ValType::RefType(ref_type) => ref_type.encode(out),
+        }
+    }
+}
+
 /// Classify the result of executing instructions or functions, which is a sequence of values, written with brackets
 type ResultType = Vec<ValType>;
 
 /// Classify the signature of functions, mapping a vector of parameters to a vector of results. They are also used to classify the inputs and outputs of instructions
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FuncType {
     pub rt1: ResultType,
     pub rt2: ResultType,
@@ -93,45 +266,441 @@ impl Decode for FuncType {
     }
 }
 
+impl Encode for FuncType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(0x60);
+        self.rt1.encode(out);
+        self.rt2.encode(out);
+    }
+}
+
+/// Integer types that only exist as the storage of struct and array fields, which are read and written as i32
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PackedType {
+    I8,
+    I16,
+}
+
+impl PackedType {
+    /// The number of bits the integer is stored in
+    pub fn bits(&self) -> u32 {
+        match self {
+            Self::I8 => 8,
+            Self::I16 => 16,
+        }
+    }
+}
+
+impl Decode for PackedType {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        alt((
+            map(tag([0x78]), |_| Self::I8),
+            map(tag([0x77]), |_| Self::I16),
+        ))(input)
+    }
+}
+
+impl Encode for PackedType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(match self {
+            Self::I8 => 0x78,
+            Self::I16 => 0x77,
+        });
+    }
+}
+
+/// Classify what the fields of structs and arrays hold, which is either a value or a packed integer
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StorageType {
+    Val(ValType),
+    Packed(PackedType),
+}
+
+impl StorageType {
+    /// The type of the operands that read and write a field of this type, which is i32 for packed integers
+    pub fn unpacked(&self) -> ValType {
+        match self {
+            Self::Val(t) => t.clone(),
+            Self::Packed(_) => ValType::NumType(NumType::I32),
+        }
+    }
+}
+
+impl Decode for StorageType {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        alt((
+            map(ValType::decode, Self::Val),
+            map(PackedType::decode, Self::Packed),
+        ))(input)
+    }
+}
+
+impl Encode for StorageType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Val(t) => t.encode(out),
+            Self::Packed(t) => t.encode(out),
+        }
+    }
+}
+
+/// Classify the fields of structs and the elements of arrays, which can either be mutable or immutable
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldType {
+    pub st: StorageType,
+    pub m: Mutability,
+}
+
+impl Decode for FieldType {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        map(pair(StorageType::decode, Mutability::decode), |pair| Self {
+            st: pair.0,
+            m: pair.1,
+        })(input)
+    }
+}
+
+impl Encode for FieldType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.st.encode(out);
+        self.m.encode(out);
+    }
+}
+
+/// Classify structs, which are heap objects with a fixed sequence of fields
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructType {
+    pub fields: Vec<FieldType>,
+}
+
+/// Classify arrays, which are heap objects with a dynamic number of elements of the same field type
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArrayType {
+    pub ft: FieldType,
+}
+
+/// Classify the objects that a type section defines, which are functions, structs or arrays
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CompType {
+    Func(FuncType),
+    Struct(StructType),
+    Array(ArrayType),
+}
+
+impl Decode for CompType {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        alt((
+            map(FuncType::decode, Self::Func),
+            map(preceded(tag([0x5F]), Vec::<FieldType>::decode), |fields| {
+                Self::Struct(StructType { fields })
+            }),
+            map(preceded(tag([0x5E]), FieldType::decode), |ft| {
+                Self::Array(ArrayType { ft })
+            }),
+        ))(input)
+    }
+}
+
+impl Encode for CompType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Func(ft) => ft.encode(out),
+            Self::Struct(st) => {
+                out.push(0x5F);
+                st.fields.encode(out);
+            }
+            Self::Array(at) => {
+                out.push(0x5E);
+                at.ft.encode(out);
+            }
+        }
+    }
+}
+
+/// A composite type along with the supertypes it declares, which must not be final
+///
+/// A composite type on its own is shorthand for a final subtype without supertypes.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubType {
+    pub is_final: bool,
+    pub supertypes: Vec<TypeIdx>,
+    pub ct: CompType,
+}
+
+impl Decode for SubType {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        alt((
+            map(
+                tuple((tag([0x50]), Vec::<TypeIdx>::decode, CompType::decode)),
+                |(_, supertypes, ct)| Self {
+                    is_final: false,
+                    supertypes,
+                    ct,
+                },
+            ),
+            map(
+                tuple((tag([0x4F]), Vec::<TypeIdx>::decode, CompType::decode)),
+                |(_, supertypes, ct)| Self {
+                    is_final: true,
+                    supertypes,
+                    ct,
+                },
+            ),
+            map(CompType::decode, Self::from),
+        ))(input)
+    }
+}
+
+impl Encode for SubType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        // A final subtype without supertypes uses the shorthand of the composite type on its own
+        if !self.is_final || !self.supertypes.is_empty() {
+            out.push(if self.is_final { 0x4F } else { 0x50 });
+            self.supertypes.encode(out);
+        }
+        self.ct.encode(out);
+    }
+}
+
+impl From<CompType> for SubType {
+    /// A composite type on its own, which is the same final subtype without supertypes that its encoding stands for
+    fn from(ct: CompType) -> Self {
+        Self {
+            is_final: true,
+            supertypes: vec![],
+            ct,
+        }
+    }
+}
+
+impl From<FuncType> for SubType {
+    fn from(ft: FuncType) -> Self {
+        CompType::Func(ft).into()
+    }
+}
+
+/// A group of subtypes that can refer to each other recursively, which is what an entry of the type section is
+///
+/// A subtype on its own is shorthand for a group of one.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecType {
+    pub types: Vec<SubType>,
+}
+
+impl Decode for RecType {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        alt((
+            map(preceded(tag([0x4E]), Vec::<SubType>::decode), |types| {
+                Self { types }
+            }),
+            map(SubType::decode, |st| Self { types: vec![st] }),
+        ))(input)
+    }
+}
+
+impl Encode for RecType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self.types.as_slice() {
+            [st] => st.encode(out),
+            types => {
+                out.push(0x4E);
+                (types.len() as u32).encode(out);
+                types.iter().for_each(|st| st.encode(out));
+            }
+        }
+    }
+}
+
+/// A type defined by a module, which is one of the subtypes of a recursion group
+///
+/// Every subtype of a group gets its own type index, and `group` holds the indices of the whole group. Types are
+/// equivalent when they are at the same position of equivalent groups, so the group is needed to tell references
+/// within the group apart from references to the types before it.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DefType {
+    pub st: SubType,
+    pub group: Range<TypeIdx>,
+}
+
+impl DefType {
+    /// Give every subtype of a sequence of recursion groups its own type index
+    pub fn flatten(recs: Vec<RecType>) -> Vec<Self> {
+        let mut types = Vec::new();
+        for rec in recs {
+            let start = types.len() as TypeIdx;
+            let group = start..start + rec.types.len() as TypeIdx;
+            types.extend(rec.types.into_iter().map(|st| Self {
+                st,
+                group: group.clone(),
+            }));
+        }
+        types
+    }
+
+    /// Split flattened types back into the recursion groups they were defined in, which is what `flatten` undoes
+    pub(crate) fn unflatten(types: &[Self]) -> Vec<RecType> {
+        // Neighbouring groups never have the same range of indices, even when they are equivalent
+        types
+            .chunk_by(|a, b| a.group == b.group)
+            .map(|group| RecType {
+                types: group.iter().map(|t| t.st.clone()).collect(),
+            })
+            .collect()
+    }
+
+    /// The function type this type defines, if it is one
+    pub fn func_type(&self) -> Option<&FuncType> {
+        match &self.st.ct {
+            CompType::Func(ft) => Some(ft),
+            _ => None,
+        }
+    }
+
+    /// The struct type this type defines, if it is one
+    pub fn struct_type(&self) -> Option<&StructType> {
+        match &self.st.ct {
+            CompType::Struct(st) => Some(st),
+            _ => None,
+        }
+    }
+
+    /// The array type this type defines, if it is one
+    pub fn array_type(&self) -> Option<&ArrayType> {
+        match &self.st.ct {
+            CompType::Array(at) => Some(at),
+            _ => None,
+        }
+    }
+}
+
 /// Classify the size range of resizable storage associated with memory types and table types
-#[derive(Debug, PartialEq)]
+///
+/// Bounds are 64-bit so that they can describe 64-bit memories, but tables and 32-bit memories only encode 32-bit
+/// bounds.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Limits {
-    pub min: u32,
-    pub max: Option<u32>,
+    pub min: u64,
+    pub max: Option<u64>,
 }
 
 impl Decode for Limits {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
         alt((
             map(preceded(tag([0x00]), u32::decode), |min| Limits {
-                min,
+                min: min as u64,
                 max: None,
             }),
             map(
                 preceded(tag([0x01]), pair(u32::decode, u32::decode)),
                 |pair| Limits {
-                    min: pair.0,
-                    max: Some(pair.1),
+                    min: pair.0 as u64,
+                    max: Some(pair.1 as u64),
                 },
             ),
         ))(input)
     }
 }
 
-/// Classify linear memories and their size range
-#[derive(Debug, PartialEq)]
+impl Encode for Limits {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self.max {
+            None => {
+                out.push(0x00);
+                (self.min as u32).encode(out);
+            }
+            Some(max) => {
+                out.push(0x01);
+                (self.min as u32).encode(out);
+                (max as u32).encode(out);
+            }
+        }
+    }
+}
+
+/// Classify linear memories and their size range, whether they can be shared between threads and whether they are
+/// addressed with 64-bit indices
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemType {
     pub lim: Limits,
+    pub shared: bool,
+    pub memory64: bool,
+}
+
+impl MemType {
+    /// The type of the addresses, sizes and lengths that instructions use to access the memory
+    pub fn addr_type(&self) -> NumType {
+        if self.memory64 {
+            NumType::I64
+        } else {
+            NumType::I32
+        }
+    }
 }
 
 impl Decode for MemType {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
-        map(Limits::decode, |limits| Self { lim: limits })(input)
+        // Bit 0 of the flags says whether the limits have a maximum, bit 1 whether the memory is shared and bit 2
+        // whether it is a 64-bit memory, whose bounds are encoded as u64 rather than u32
+        let (input, flags) = verify(u8::decode, |flags| *flags <= 0x07)(input)?;
+        let memory64 = flags & 0x04 != 0;
+        let bound = |input| {
+            if memory64 {
+                u64::decode(input)
+            } else {
+                map(u32::decode, |n| n as u64)(input)
+            }
+        };
+        let (input, min) = bound(input)?;
+        let (input, max) = if flags & 0x01 != 0 {
+            map(bound, Some)(input)?
+        } else {
+            (input, None)
+        };
+        Ok((
+            input,
+            Self {
+                lim: Limits { min, max },
+                shared: flags & 0x02 != 0,
+                memory64,
+            },
+        ))
+    }
+}
+
+impl Encode for MemType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let flags = u8::from(self.lim.max.is_some())
+            | u8::from(self.shared) << 1
+            | u8::from(self.memory64) << 2;
+        out.push(flags);
+        let bound = |n: u64, out: &mut Vec<u8>| {
+            if self.memory64 {
+                n.encode(out)
+            } else {
+                (n as u32).encode(out)
+            }
+        };
+        bound(self.lim.min, out);
+        if let Some(max) = self.lim.max {
+            bound(max, out);
+        }
     }
 }
 
 /// Classify tables over elements of reference type within a size range
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TableType {
     pub lim: Limits,
     pub et: RefType,
@@ -146,8 +715,16 @@ impl Decode for TableType {
     }
 }
 
+impl Encode for TableType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.et.encode(out);
+        self.lim.encode(out);
+    }
+}
+
 /// Classify global variables, which hold a value and can either be mutable or immutable
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GlobalType {
     pub m: Mutability,
     pub t: ValType,
@@ -162,8 +739,40 @@ impl Decode for GlobalType {
     }
 }
 
+impl Encode for GlobalType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.t.encode(out);
+        self.m.encode(out);
+    }
+}
+
+/// Classify exception tags by the function type of their payload, whose parameters are the values an exception
+/// carries and whose results must be empty
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TagType {
+    pub typeidx: TypeIdx,
+}
+
+impl Decode for TagType {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        // The leading attribute byte is reserved for future kinds of tags, with 0 being the only one defined
+        map(preceded(tag([0x00]), TypeIdx::decode), |typeidx| Self {
+            typeidx,
+        })(input)
+    }
+}
+
+impl Encode for TagType {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(0x00);
+        self.typeidx.encode(out);
+    }
+}
+
 /// Classify whether something is mutable
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Mutability {
     Const,
     Var,
@@ -178,6 +787,15 @@ impl Decode for Mutability {
     }
 }
 
+impl Encode for Mutability {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(match self {
+            Self::Const => 0x00,
+            Self::Var => 0x01,
+        });
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -199,10 +817,52 @@ mod tests {
         assert!(VecType::decode(&[0x7A]).is_err());
     }
 
+    #[test]
+    fn test_heap_type() {
+        assert_eq!(HeapType::decode(&[0x70]), Ok((EMPTY, HeapType::Func)));
+        assert_eq!(HeapType::decode(&[0x6F]), Ok((EMPTY, HeapType::Extern)));
+        assert_eq!(HeapType::decode(&[0x69]), Ok((EMPTY, HeapType::Exn)));
+        assert_eq!(HeapType::decode(&[0x6E]), Ok((EMPTY, HeapType::Any)));
+        assert_eq!(HeapType::decode(&[0x6D]), Ok((EMPTY, HeapType::Eq)));
+        assert_eq!(HeapType::decode(&[0x6C]), Ok((EMPTY, HeapType::I31)));
+        assert_eq!(HeapType::decode(&[0x6B]), Ok((EMPTY, HeapType::Struct)));
+        assert_eq!(HeapType::decode(&[0x6A]), Ok((EMPTY, HeapType::Array)));
+        assert_eq!(HeapType::decode(&[0x71]), Ok((EMPTY, HeapType::None)));
+        assert_eq!(HeapType::decode(&[0x73]), Ok((EMPTY, HeapType::NoFunc)));
+        assert_eq!(HeapType::decode(&[0x72]), Ok((EMPTY, HeapType::NoExtern)));
+        assert_eq!(HeapType::decode(&[0x74]), Ok((EMPTY, HeapType::NoExn)));
+        assert_eq!(HeapType::decode(&[0x03]), Ok((EMPTY, HeapType::Type(3))));
+        assert_eq!(
+            HeapType::decode(&[0x80, 0x01]),
+            Ok((EMPTY, HeapType::Type(128)))
+        );
+        assert!(HeapType::decode(&[0x7A]).is_err());
+    }
+
     #[test]
     fn test_ref_type() {
-        assert_eq!(RefType::decode(&[0x70]), Ok((EMPTY, RefType::FuncRef)));
-        assert_eq!(RefType::decode(&[0x6F]), Ok((EMPTY, RefType::ExternRef)));
+        assert_eq!(RefType::decode(&[0x70]), Ok((EMPTY, RefType::FUNCREF)));
+        assert_eq!(RefType::decode(&[0x6F]), Ok((EMPTY, RefType::EXTERNREF)));
+        assert_eq!(RefType::decode(&[0x69]), Ok((EMPTY, RefType::EXNREF)));
+        assert_eq!(
+            RefType::decode(&[0x63, 0x70]),
+            Ok((EMPTY, RefType::FUNCREF))
+        );
+        assert_eq!(
+            RefType::decode(&[0x64, 0x6F]),
+            Ok((EMPTY, RefType::non_null(HeapType::Extern)))
+        );
+        assert_eq!(
+            RefType::decode(&[0x64, 0x02]),
+            Ok((EMPTY, RefType::non_null(HeapType::Type(2))))
+        );
+        assert_eq!(
+            RefType::decode(&[0x63, 0x02]),
+            Ok((EMPTY, RefType::null(HeapType::Type(2))))
+        );
+        // A bare type index is a heap type but not a reference type
+        assert!(RefType::decode(&[0x02]).is_err());
+        assert!(RefType::decode(&[0x64, 0x7F]).is_err());
         assert!(RefType::decode(&[0x7A]).is_err());
     }
 
@@ -218,11 +878,25 @@ mod tests {
         );
         assert_eq!(
             ValType::decode(&[0x6F]),
-            Ok((EMPTY, ValType::RefType(RefType::ExternRef)))
+            Ok((EMPTY, ValType::RefType(RefType::EXTERNREF)))
+        );
+        assert_eq!(
+            ValType::decode(&[0x64, 0x00]),
+            Ok((
+                EMPTY,
+                ValType::RefType(RefType::non_null(HeapType::Type(0)))
+            ))
         );
         assert!(ValType::decode(&[0x1A]).is_err());
     }
 
+    #[test]
+    fn test_val_type_defaultable() {
+        assert!(ValType::NumType(NumType::I32).is_defaultable());
+        assert!(ValType::RefType(RefType::FUNCREF).is_defaultable());
+        assert!(!ValType::RefType(RefType::non_null(HeapType::Func)).is_defaultable());
+    }
+
     #[test]
     fn test_result_type() {
         assert_eq!(
@@ -259,6 +933,110 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_field_type() {
+        assert_eq!(
+            FieldType::decode(&[0x78, 0x01]),
+            Ok((
+                EMPTY,
+                FieldType {
+                    st: StorageType::Packed(PackedType::I8),
+                    m: Mutability::Var
+                }
+            ))
+        );
+        assert_eq!(
+            FieldType::decode(&[0x63, 0x6E, 0x00]),
+            Ok((
+                EMPTY,
+                FieldType {
+                    st: StorageType::Val(ValType::RefType(RefType::ANYREF)),
+                    m: Mutability::Const
+                }
+            ))
+        );
+        assert_eq!(
+            StorageType::Packed(PackedType::I16).unpacked(),
+            ValType::NumType(NumType::I32)
+        );
+        assert!(FieldType::decode(&[0x76, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_sub_type() {
+        let array = CompType::Array(ArrayType {
+            ft: FieldType {
+                st: StorageType::Packed(PackedType::I16),
+                m: Mutability::Const,
+            },
+        });
+        assert_eq!(
+            SubType::decode(&[0x5E, 0x77, 0x00]),
+            Ok((EMPTY, SubType::from(array.clone())))
+        );
+        assert_eq!(
+            SubType::decode(&[0x50, 0x01, 0x02, 0x5F, 0x01, 0x7F, 0x01]),
+            Ok((
+                EMPTY,
+                SubType {
+                    is_final: false,
+                    supertypes: vec!(2),
+                    ct: CompType::Struct(StructType {
+                        fields: vec!(FieldType {
+                            st: StorageType::Val(ValType::NumType(NumType::I32)),
+                            m: Mutability::Var
+                        })
+                    })
+                }
+            ))
+        );
+        assert_eq!(
+            SubType::decode(&[0x4F, 0x00, 0x60, 0x00, 0x00]),
+            Ok((
+                EMPTY,
+                SubType::from(FuncType {
+                    rt1: vec!(),
+                    rt2: vec!()
+                })
+            ))
+        );
+        assert!(SubType::decode(&[0x50, 0x00, 0x7F]).is_err());
+    }
+
+    #[test]
+    fn test_rec_type() {
+        let ft = || FuncType {
+            rt1: vec![],
+            rt2: vec![],
+        };
+        assert_eq!(
+            RecType::decode(&[0x60, 0x00, 0x00]),
+            Ok((
+                EMPTY,
+                RecType {
+                    types: vec!(ft().into())
+                }
+            ))
+        );
+        let (_, group) =
+            RecType::decode(&[0x4E, 0x02, 0x60, 0x00, 0x00, 0x60, 0x00, 0x00]).unwrap();
+        assert_eq!(group.types.len(), 2);
+
+        // Flattening numbers the types of each group consecutively and remembers the group's range
+        let types = DefType::flatten(vec![
+            RecType {
+                types: vec![ft().into()],
+            },
+            group,
+        ]);
+        assert_eq!(
+            types.iter().map(|t| t.group.clone()).collect::<Vec<_>>(),
+            vec!(0..1, 1..3, 1..3)
+        );
+        assert_eq!(types[2].func_type(), Some(&ft()));
+        assert_eq!(types[2].struct_type(), None);
+    }
+
     #[test]
     fn test_limits_type() {
         assert_eq!(
@@ -285,7 +1063,9 @@ mod tests {
             Ok((
                 EMPTY,
                 MemType {
-                    lim: Limits { min: 1, max: None }
+                    lim: Limits { min: 1, max: None },
+                    shared: false,
+                    memory64: false
                 }
             ))
         );
@@ -297,10 +1077,66 @@ mod tests {
                     lim: Limits {
                         min: 1,
                         max: Some(2)
-                    }
+                    },
+                    shared: false,
+                    memory64: false
                 }
             ))
         );
+        assert_eq!(
+            MemType::decode(&[0x03, 0x01, 0x02]),
+            Ok((
+                EMPTY,
+                MemType {
+                    lim: Limits {
+                        min: 1,
+                        max: Some(2)
+                    },
+                    shared: true,
+                    memory64: false
+                }
+            ))
+        );
+        assert_eq!(
+            MemType::decode(&[0x02, 0x01]),
+            Ok((
+                EMPTY,
+                MemType {
+                    lim: Limits { min: 1, max: None },
+                    shared: true,
+                    memory64: false
+                }
+            ))
+        );
+        assert_eq!(
+            MemType::decode(&[0x05, 0x01, 0x80, 0x80, 0x80, 0x80, 0x20]),
+            Ok((
+                EMPTY,
+                MemType {
+                    lim: Limits {
+                        min: 1,
+                        max: Some(1 << 33)
+                    },
+                    shared: false,
+                    memory64: true,
+                }
+            ))
+        );
+        assert_eq!(
+            MemType::decode(&[0x07, 0x01, 0x02]),
+            Ok((
+                EMPTY,
+                MemType {
+                    lim: Limits {
+                        min: 1,
+                        max: Some(2)
+                    },
+                    shared: true,
+                    memory64: true,
+                }
+            ))
+        );
+        assert!(MemType::decode(&[0x08, 0x01]).is_err());
         assert!(MemType::decode(&[0x7A]).is_err());
     }
 
@@ -312,7 +1148,7 @@ mod tests {
                 EMPTY,
                 TableType {
                     lim: Limits { min: 1, max: None },
-                    et: RefType::FuncRef
+                    et: RefType::FUNCREF
                 }
             ))
         );
@@ -325,7 +1161,7 @@ mod tests {
                         min: 1,
                         max: Some(2)
                     },
-                    et: RefType::FuncRef
+                    et: RefType::FUNCREF
                 }
             ))
         );
@@ -347,6 +1183,15 @@ mod tests {
         assert!(GlobalType::decode(&[0x7A]).is_err());
     }
 
+    #[test]
+    fn test_tag_type() {
+        assert_eq!(
+            TagType::decode(&[0x00, 0x02]),
+            Ok((EMPTY, TagType { typeidx: 2 }))
+        );
+        assert!(TagType::decode(&[0x01, 0x02]).is_err());
+    }
+
     #[test]
     fn test_mutability_type() {
         assert_eq!(Mutability::decode(&[0x00]), Ok((EMPTY, Mutability::Const)));
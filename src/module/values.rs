@@ -1,7 +1,7 @@
 use nom::{combinator::opt, multi::count, IResult};
-use nom_leb128::{leb128_i32, leb128_i64, leb128_u32};
+use nom_leb128::{leb128_i32, leb128_i64, leb128_u32, leb128_u64};
 
-use crate::Decode;
+use crate::{Decode, Encode};
 
 impl Decode for u32 {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
@@ -9,12 +9,24 @@ impl Decode for u32 {
     }
 }
 
+impl Decode for u64 {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        leb128_u64(input)
+    }
+}
+
 impl Decode for u8 {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
         nom::number::complete::u8(input)
     }
 }
 
+impl Decode for u128 {
+    fn decode(input: &[u8]) -> IResult<&[u8], Self> {
+        nom::number::complete::le_u128(input)
+    }
+}
+
 impl Decode for f32 {
     fn decode(input: &[u8]) -> IResult<&[u8], Self> {
         nom::number::complete::le_f32(input)
@@ -59,7 +71,8 @@ where
 }
 
 /// A UTF-8 character sequence
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Name(pub Vec<u8>);
 
 impl Decode for Name {
@@ -69,9 +82,173 @@ impl Decode for Name {
     }
 }
 
+impl Encode for Name {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+    }
+}
+
+// Integers are encoded in the shortest LEB128 form, seven bits per byte with the high bit set on all but the last
+
+impl Encode for u32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        u64::from(*self).encode(out);
+    }
+}
+
+impl Encode for u64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut n = *self;
+        loop {
+            let byte = (n & 0x7F) as u8;
+            n >>= 7;
+            if n == 0 {
+                out.push(byte);
+                return;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+}
+
+impl Encode for i32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        i64::from(*self).encode(out);
+    }
+}
+
+impl Encode for i64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let mut n = *self;
+        loop {
+            let byte = (n & 0x7F) as u8;
+            // The shift is arithmetic, so the remaining bits are all copies of the sign bit once the value fits
+            n >>= 7;
+            let done = (n == 0 && byte & 0x40 == 0) || (n == -1 && byte & 0x40 != 0);
+            if done {
+                out.push(byte);
+                return;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+}
+
+impl Encode for u8 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self);
+    }
+}
+
+impl Encode for u128 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Encode for f32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        // The bits are written as they are, so NaN payloads survive
+        out.extend_from_slice(&self.to_bits().to_le_bytes());
+    }
+}
+
+impl Encode for f64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_bits().to_le_bytes());
+    }
+}
+
+impl<T> Encode for Vec<T>
+where
+    T: Encode,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).encode(out);
+        for item in self {
+            item.encode(out);
+        }
+    }
+}
+
+/// Serialize an `f32` as its bits, since formats like JSON can't represent NaN, let alone its payload
+#[cfg(feature = "serde")]
+pub(crate) mod f32_bits {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(z: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(z.to_bits())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+        u32::deserialize(deserializer).map(f32::from_bits)
+    }
+}
+
+/// Serialize an `f64` as its bits, like `f32_bits`
+#[cfg(feature = "serde")]
+pub(crate) mod f64_bits {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(z: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(z.to_bits())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        u64::deserialize(deserializer).map(f64::from_bits)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use super::*;
+
+    const EMPTY: &[u8] = &[];
 
-    // TODO: Write tests
+    fn encode(value: impl Encode) -> Vec<u8> {
+        let mut out = Vec::new();
+        value.encode(&mut out);
+        out
+    }
+
+    #[test]
+    fn test_leb128() {
+        assert_eq!(encode(0u32), vec!(0x00));
+        assert_eq!(encode(127u32), vec!(0x7F));
+        assert_eq!(encode(128u32), vec!(0x80, 0x01));
+        assert_eq!(encode(u32::MAX), vec!(0xFF, 0xFF, 0xFF, 0xFF, 0x0F));
+        assert_eq!(encode(63i32), vec!(0x3F));
+        assert_eq!(encode(64i32), vec!(0xC0, 0x00));
+        assert_eq!(encode(-1i32), vec!(0x7F));
+        assert_eq!(encode(-65i32), vec!(0xBF, 0x7F));
+        assert_eq!(encode(i64::MIN), {
+            let mut bytes = vec![0x80; 9];
+            bytes.push(0x7F);
+            bytes
+        });
+        for n in [0, 1, -1, 63, -64, 64, -65, i64::MAX, i64::MIN] {
+            assert_eq!(i64::decode(&encode(n)), Ok((EMPTY, n)));
+        }
+        for n in [0, 1, 127, 128, u64::MAX] {
+            assert_eq!(u64::decode(&encode(n)), Ok((EMPTY, n)));
+        }
+    }
+
+    #[test]
+    fn test_floats() {
+        let nan = f32::from_bits(0x7FA0_0001);
+        assert_eq!(encode(nan), vec!(0x01, 0x00, 0xA0, 0x7F));
+        assert_eq!(
+            f32::decode(&encode(nan)).map(|(_, z)| z.to_bits()),
+            Ok(0x7FA0_0001)
+        );
+        assert_eq!(encode(-0.0f64), vec!(0, 0, 0, 0, 0, 0, 0, 0x80));
+    }
+
+    #[test]
+    fn test_name() {
+        let name = Name(vec![0x61, 0x62]);
+        assert_eq!(encode(name.clone()), vec!(0x02, 0x61, 0x62));
+        assert_eq!(Name::decode(&[0x02, 0x61, 0x62]), Ok((EMPTY, name)));
+    }
 }
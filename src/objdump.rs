@@ -0,0 +1,488 @@
+use std::fmt::Write;
+
+use nom::{combinator::map, IResult};
+
+use crate::{
+    instructions::{BlockType, CatchClause, ControlInstruction, Instruction},
+    module::{
+        code::Local,
+        export::ExportDescriptor,
+        import::ImportDescriptor,
+        indices::FuncIdx,
+        section::SectionHeader,
+        types::{DefType, Limits, MemType, Mutability, ValType},
+        values::Name,
+    },
+    text::{string, sub_type, Printer},
+    Decode, Module, New,
+};
+
+/// A `wasm-objdump` style view of a binary module, which shows where each part of the module is in its bytes
+pub struct Objdump<'a> {
+    bytes: &'a [u8],
+    module: Module,
+}
+
+impl<'a> Objdump<'a> {
+    /// Decode a module, keeping its bytes so that they can be shown next to what they decode to
+    pub fn new(bytes: &'a [u8]) -> Option<Self> {
+        let module = Module::new(bytes)?;
+        Some(Self { bytes, module })
+    }
+
+    /// The id, offsets, size and number of items of each section
+    pub fn headers(&self) -> String {
+        let mut out = String::from("Sections:\n\n");
+        for header in &self.module.sections {
+            let contents = &self.bytes[header.contents.clone()];
+            let summary = match header.id {
+                0 => match Name::decode(contents) {
+                    Ok((_, name)) => string(&name.0),
+                    Err(_) => String::new(),
+                },
+                8 => match u32::decode(contents) {
+                    Ok((_, start)) => format!("start: {}", start),
+                    Err(_) => String::new(),
+                },
+                _ => match u32::decode(contents) {
+                    Ok((_, count)) => format!("count: {}", count),
+                    Err(_) => String::new(),
+                },
+            };
+            writeln!(
+                out,
+                "{:>9} ({:>2}) start={:#010x} end={:#010x} (size={:#010x}) {}",
+                section_name(header.id),
+                header.id,
+                header.contents.start,
+                header.contents.end,
+                header.contents.len(),
+                summary
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    /// The contents of the type, import, function, export, global and start sections
+    pub fn details(&self) -> String {
+        let module = &self.module;
+        let printer = Printer::new(module, false);
+        let imported = self.imported_funcs();
+        let mut out = String::from("Section Details:\n\n");
+        for header in &module.sections {
+            let name = section_name(header.id);
+            match header.id {
+                1 => {
+                    writeln!(out, "{}[{}]:", name, module.types.len()).unwrap();
+                    for (i, dt) in module.types.iter().enumerate() {
+                        let text = match dt.func_type() {
+                            Some(ft) => format!("{} -> {}", tuple(&ft.rt1), results(&ft.rt2)),
+                            None => sub_type(&dt.st),
+                        };
+                        writeln!(out, " - type[{}] {}", i, text).unwrap();
+                    }
+                }
+                2 => {
+                    writeln!(out, "{}[{}]:", name, module.imports.len()).unwrap();
+                    let (mut funcs, mut tables, mut mems, mut globals, mut tags) = (0, 0, 0, 0, 0);
+                    for import in &module.imports {
+                        let text = match &import.descriptor {
+                            ImportDescriptor::Func(typeidx) => {
+                                funcs += 1;
+                                format!(
+                                    "func[{}] sig={}{}",
+                                    funcs - 1,
+                                    typeidx,
+                                    self.func_name(funcs - 1)
+                                )
+                            }
+                            ImportDescriptor::Table(tt) => {
+                                tables += 1;
+                                format!("table[{}] type={} {}", tables - 1, tt.et, limits(&tt.lim))
+                            }
+                            ImportDescriptor::Mem(mt) => {
+                                mems += 1;
+                                format!("memory[{}] {}", mems - 1, memory(mt))
+                            }
+                            ImportDescriptor::Global(gt) => {
+                                globals += 1;
+                                format!("global[{}] {}", globals - 1, global(&gt.t, &gt.m))
+                            }
+                            ImportDescriptor::Tag(tt) => {
+                                tags += 1;
+                                format!("tag[{}] sig={}", tags - 1, tt.typeidx)
+                            }
+                        };
+                        writeln!(
+                            out,
+                            " - {} <- {}.{}",
+                            text,
+                            String::from_utf8_lossy(&import.module.0),
+                            String::from_utf8_lossy(&import.name.0)
+                        )
+                        .unwrap();
+                    }
+                }
+                3 => {
+                    writeln!(out, "{}[{}]:", name, module.functions.len()).unwrap();
+                    for (i, typeidx) in module.functions.iter().enumerate() {
+                        let funcidx = imported + i as FuncIdx;
+                        let name = self.func_name(funcidx);
+                        writeln!(out, " - func[{}] sig={}{}", funcidx, typeidx, name).unwrap();
+                    }
+                }
+                6 => {
+                    writeln!(out, "{}[{}]:", name, module.globals.len()).unwrap();
+                    let imported = module
+                        .imports
+                        .iter()
+                        .filter(|import| matches!(import.descriptor, ImportDescriptor::Global(_)))
+                        .count();
+                    for (i, g) in module.globals.iter().enumerate() {
+                        writeln!(
+                            out,
+                            " - global[{}] {} - init {}",
+                            imported + i,
+                            global(&g.gt.t, &g.gt.m),
+                            printer.const_expr(&g.init)
+                        )
+                        .unwrap();
+                    }
+                }
+                7 => {
+                    writeln!(out, "{}[{}]:", name, module.exports.len()).unwrap();
+                    for export in &module.exports {
+                        let text = match export.descriptor {
+                            ExportDescriptor::Func(funcidx) => {
+                                format!("func[{}]{}", funcidx, self.func_name(funcidx))
+                            }
+                            ExportDescriptor::Table(tableidx) => format!("table[{}]", tableidx),
+                            ExportDescriptor::Mem(memidx) => format!("memory[{}]", memidx),
+                            ExportDescriptor::Global(globalidx) => {
+                                format!("global[{}]", globalidx)
+                            }
+                            ExportDescriptor::Tag(tagidx) => format!("tag[{}]", tagidx),
+                        };
+                        writeln!(out, " - {} -> {}", text, string(&export.name.0)).unwrap();
+                    }
+                }
+                8 => {
+                    if let Some(start) = module.start {
+                        writeln!(out, "{}:", name).unwrap();
+                        writeln!(out, " - start function: {}{}", start, self.func_name(start))
+                            .unwrap();
+                    }
+                }
+                _ => (),
+            }
+        }
+        out
+    }
+
+    /// Every code body, with the offset and raw bytes of each local declaration and instruction
+    pub fn disassemble(&self) -> String {
+        let mut out = String::from("Code Disassembly:\n\n");
+        let header = self.module.sections.iter().find(|header| header.id == 10);
+        if let Some(header) = header {
+            if self.code(&mut out, header).is_err() {
+                out.push_str("failed to decode the code section\n");
+            }
+        }
+        out
+    }
+
+    fn code<'b>(&'b self, out: &mut String, header: &SectionHeader) -> IResult<&'b [u8], ()> {
+        let printer = Printer::new(&self.module, false);
+        let imported = self.imported_funcs();
+        let (mut input, count) = u32::decode(&self.bytes[header.contents.clone()])?;
+        for i in 0..count {
+            let funcidx = imported + i;
+            let offset = self.offset(input);
+            let (rest, size) = u32::decode(input)?;
+            let end = self.offset(rest) + size as usize;
+            writeln!(
+                out,
+                "{:06x} func[{}]{}:",
+                offset,
+                funcidx,
+                self.func_name(funcidx)
+            )
+            .unwrap();
+
+            // The locals are numbered after the parameters of the function
+            let mut localidx = self
+                .module
+                .functions
+                .get(i as usize)
+                .and_then(|typeidx| self.module.types.get(*typeidx as usize))
+                .and_then(DefType::func_type)
+                .map_or(0, |ft| ft.rt1.len() as u32);
+            let (mut body, locals) = u32::decode(&self.bytes[self.offset(rest)..end])?;
+            for _ in 0..locals {
+                let (rest, local) = Local::decode(body)?;
+                let range = match local.count {
+                    1 => localidx.to_string(),
+                    n => format!("{}..{}", localidx, localidx + n),
+                };
+                let text = format!("local[{}] type={}", range, local.value_type);
+                self.line(out, body, rest, &text);
+                localidx += local.count;
+                body = rest;
+            }
+
+            let mut depth = 0;
+            while !body.is_empty() {
+                let (rest, op) = op(body)?;
+                let text = match &op {
+                    Op::Instruction(instruction) => printer.plain(instruction),
+                    Op::Else => "else".to_string(),
+                    Op::End => "end".to_string(),
+                };
+                if matches!(op, Op::Else | Op::End) {
+                    depth -= 1;
+                }
+                let indent = "  ".repeat(depth.max(0) as usize);
+                self.line(out, body, rest, &format!("{}{}", indent, text));
+                match op {
+                    Op::Instruction(Instruction::Control(
+                        ControlInstruction::Block(..)
+                        | ControlInstruction::Loop(..)
+                        | ControlInstruction::If(..)
+                        | ControlInstruction::TryTable(..),
+                    ))
+                    | Op::Else => depth += 1,
+                    _ => (),
+                }
+                body = rest;
+            }
+            input = &self.bytes[end..header.contents.end];
+        }
+        Ok((input, ()))
+    }
+
+    /// Write the bytes between two positions in the module, starting a new line every eight bytes
+    fn line(&self, out: &mut String, from: &[u8], to: &[u8], text: &str) {
+        let offset = self.offset(from);
+        let bytes = &from[..from.len() - to.len()];
+        for (i, chunk) in bytes.chunks(8).enumerate() {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let text = if i == 0 { text } else { "" };
+            let line = format!(" {:06x}: {:<23} | {}", offset + i * 8, hex.join(" "), text);
+            writeln!(out, "{}", line.trim_end()).unwrap();
+        }
+    }
+
+    /// The offset of a position in the module, given the bytes from there to the end of the module or of a code body
+    fn offset(&self, input: &[u8]) -> usize {
+        input.as_ptr() as usize - self.bytes.as_ptr() as usize
+    }
+
+    fn imported_funcs(&self) -> FuncIdx {
+        self.module
+            .imports
+            .iter()
+            .filter(|import| matches!(import.descriptor, ImportDescriptor::Func(_)))
+            .count() as FuncIdx
+    }
+
+    /// The name of a function from the name section, as ` <name>`, or nothing if it isn't named
+    fn func_name(&self, funcidx: FuncIdx) -> String {
+        match self.module.names.functions.get(funcidx) {
+            Some(name) => format!(" <{}>", String::from_utf8_lossy(&name.0)),
+            None => String::new(),
+        }
+    }
+}
+
+/// One instruction of a code body, without the instructions nested inside it if it is a block
+enum Op {
+    Instruction(Instruction),
+    Else,
+    End,
+}
+
+fn op(input: &[u8]) -> IResult<&[u8], Op> {
+    let block = |instruction| Op::Instruction(Instruction::Control(instruction));
+    match input.first() {
+        Some(0x02) => map(BlockType::decode, |bt| {
+            block(ControlInstruction::Block(bt, Vec::new()))
+        })(&input[1..]),
+        Some(0x03) => map(BlockType::decode, |bt| {
+            block(ControlInstruction::Loop(bt, Vec::new()))
+        })(&input[1..]),
+        Some(0x04) => map(BlockType::decode, |bt| {
+            block(ControlInstruction::If(bt, Vec::new(), Vec::new()))
+        })(&input[1..]),
+        Some(0x1F) => {
+            let (input, bt) = BlockType::decode(&input[1..])?;
+            let (input, catches) = Vec::<CatchClause>::decode(input)?;
+            let instruction = ControlInstruction::TryTable(bt, catches, Vec::new());
+            Ok((input, block(instruction)))
+        }
+        Some(0x05) => Ok((&input[1..], Op::Else)),
+        Some(0x0B) => Ok((&input[1..], Op::End)),
+        _ => map(Instruction::decode, Op::Instruction)(input),
+    }
+}
+
+pub(crate) fn section_name(id: u8) -> &'static str {
+    match id {
+        0 => "Custom",
+        1 => "Type",
+        2 => "Import",
+        3 => "Function",
+        4 => "Table",
+        5 => "Memory",
+        6 => "Global",
+        7 => "Export",
+        8 => "Start",
+        9 => "Elem",
+        10 => "Code",
+        11 => "Data",
+        12 => "DataCount",
+        13 => "Tag",
+        _ => "Unknown",
+    }
+}
+
+fn tuple(types: &[ValType]) -> String {
+    let types: Vec<String> = types.iter().map(ToString::to_string).collect();
+    format!("({})", types.join(", "))
+}
+
+fn results(types: &[ValType]) -> String {
+    match types {
+        [] => "nil".to_string(),
+        [t] => t.to_string(),
+        types => tuple(types),
+    }
+}
+
+fn limits(lim: &Limits) -> String {
+    match lim.max {
+        Some(max) => format!("initial={} max={}", lim.min, max),
+        None => format!("initial={}", lim.min),
+    }
+}
+
+fn memory(mt: &MemType) -> String {
+    let mut text = format!("pages: {}", limits(&mt.lim));
+    if mt.shared {
+        text += " shared";
+    }
+    if mt.memory64 {
+        text += " i64";
+    }
+    text
+}
+
+fn global(t: &ValType, m: &Mutability) -> String {
+    let mutable = match m {
+        Mutability::Const => 0,
+        Mutability::Var => 1,
+    };
+    format!("{} mutable={}", t, mutable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A module that imports a function, defines a mutable global and a function named "main" that calls the import
+    // and extracts a lane of a v128 constant, and exports and starts it
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x08, 0x02, 0x60, 0x00, 0x00, 0x60, 0x00, 0x01, 0x7F, // type
+        0x02, 0x0C, 0x01, 0x03, 0x65, 0x6E, 0x76, 0x04, 0x74, 0x69, 0x63, 0x6B, 0x00,
+        0x00, // import
+        0x03, 0x02, 0x01, 0x01, // function
+        0x06, 0x06, 0x01, 0x7F, 0x01, 0x41, 0x2A, 0x0B, // global
+        0x07, 0x08, 0x01, 0x04, 0x6D, 0x61, 0x69, 0x6E, 0x00, 0x01, // export
+        0x08, 0x01, 0x01, // start
+        0x0A, 0x1B, 0x01, 0x19, 0x00, 0x10, 0x00, 0xFD, 0x0C, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05,
+        0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0xFD, 0x1B, 0x03,
+        0x0B, // code
+        0x00, 0x0E, 0x04, 0x6E, 0x61, 0x6D, 0x65, 0x01, 0x07, 0x01, 0x01, 0x04, 0x6D, 0x61, 0x69,
+        0x6E, // custom "name"
+    ];
+
+    #[test]
+    fn test_headers() {
+        let objdump = Objdump::new(MODULE).unwrap();
+        assert_eq!(
+            objdump.headers(),
+            "Sections:
+
+     Type ( 1) start=0x0000000a end=0x00000012 (size=0x00000008) count: 2
+   Import ( 2) start=0x00000014 end=0x00000020 (size=0x0000000c) count: 1
+ Function ( 3) start=0x00000022 end=0x00000024 (size=0x00000002) count: 1
+   Global ( 6) start=0x00000026 end=0x0000002c (size=0x00000006) count: 1
+   Export ( 7) start=0x0000002e end=0x00000036 (size=0x00000008) count: 1
+    Start ( 8) start=0x00000038 end=0x00000039 (size=0x00000001) start: 1
+     Code (10) start=0x0000003b end=0x00000056 (size=0x0000001b) count: 1
+   Custom ( 0) start=0x00000058 end=0x00000066 (size=0x0000000e) \"name\"
+"
+        );
+    }
+
+    #[test]
+    fn test_details() {
+        let objdump = Objdump::new(MODULE).unwrap();
+        assert_eq!(
+            objdump.details(),
+            "Section Details:
+
+Type[2]:
+ - type[0] () -> nil
+ - type[1] () -> i32
+Import[1]:
+ - func[0] sig=0 <- env.tick
+Function[1]:
+ - func[1] sig=1 <main>
+Global[1]:
+ - global[0] i32 mutable=1 - init (i32.const 42)
+Export[1]:
+ - func[1] <main> -> \"main\"
+Start:
+ - start function: 1 <main>
+"
+        );
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let objdump = Objdump::new(MODULE).unwrap();
+        assert_eq!(
+            objdump.disassemble(),
+            "Code Disassembly:
+
+00003c func[1] <main>:
+ 00003e: 10 00                   | call 0
+ 000040: fd 0c 00 01 02 03 04 05 | v128.const i32x4 0x03020100 0x07060504 0x0b0a0908 0x0f0e0d0c
+ 000048: 06 07 08 09 0a 0b 0c 0d |
+ 000050: 0e 0f                   |
+ 000052: fd 1b 03                | i32x4.extract_lane 3
+ 000055: 0b                      | end
+"
+        );
+    }
+
+    #[test]
+    fn test_op() {
+        const EMPTY: &[u8] = &[];
+        assert!(matches!(
+            op(&[0x04, 0x40, 0x0B]),
+            Ok((
+                [0x0B],
+                Op::Instruction(Instruction::Control(ControlInstruction::If(
+                    BlockType::Empty,
+                    ..
+                )))
+            ))
+        ));
+        assert!(matches!(op(&[0x05]), Ok((EMPTY, Op::Else))));
+        assert!(matches!(op(&[0x0B]), Ok((EMPTY, Op::End))));
+        assert!(op(&[0x02]).is_err());
+    }
+}
@@ -0,0 +1,38 @@
+pub use self::{
+    heap::GcRef,
+    instance::{Extern, InstantiationError, ModuleInst},
+    interrupt::InterruptHandle,
+    limiter::{ResourceLimiter, StoreLimits},
+    memory::SharedMemory,
+    store::{DataAddr, ElemAddr, FuncAddr, GlobalAddr, MemAddr, Store, TableAddr, TagAddr},
+    trap::Trap,
+    typed::{WasmTuple, WasmTy},
+    values::{AnyRef, Exception, ExternRef, FuncHandle, Val},
+};
+
+pub(crate) use self::{
+    gc::{pack, reserve},
+    heap::Heap,
+};
+
+mod atomic;
+mod code;
+mod function;
+mod gc;
+mod global;
+mod heap;
+mod instance;
+mod interpreter;
+mod interrupt;
+mod limiter;
+mod memory;
+mod numeric;
+mod operands;
+mod segment;
+mod store;
+mod table;
+mod tag;
+mod trap;
+mod typed;
+mod values;
+mod vector;
@@ -0,0 +1,282 @@
+use std::time::Duration;
+
+use crate::{
+    instructions::{AtomicInstruction, AtomicOp, MemArg, RmwOp},
+    module::types::NumType,
+};
+
+use super::{
+    interrupt::InterruptHandle,
+    memory::MemInst,
+    operands::{self, pop_i32, pop_i64},
+    trap::Trap,
+    values::Val,
+};
+
+/// Pop an integer operand of type `t`, zero-extended to 64 bits
+fn pop_int(stack: &mut Vec<Val>, t: &NumType) -> u64 {
+    match t {
+        NumType::I64 => pop_i64(stack) as u64,
+        _ => pop_i32(stack) as u32 as u64,
+    }
+}
+
+/// Wrap an integer result as a value of type `t`
+fn int(t: &NumType, n: u64) -> Val {
+    match t {
+        NumType::I64 => Val::I64(n as i64),
+        _ => Val::I32(n as u32 as i32),
+    }
+}
+
+/// Read the little-endian integer stored in `bytes`, zero-extended to 64 bits
+fn read(bytes: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    u64::from_le_bytes(buf)
+}
+
+/// Store the low bytes of `n` into `bytes`, wrapping it to the access width
+fn write(bytes: &mut [u8], n: u64) {
+    let len = bytes.len();
+    bytes.copy_from_slice(&n.to_le_bytes()[..len]);
+}
+
+/// Pop the address operand and compute an effective address, which must be a multiple of the access width
+fn effective_address(
+    stack: &mut Vec<Val>,
+    memarg: &MemArg,
+    memory: &MemInst,
+    width: usize,
+) -> Result<u64, Trap> {
+    let ea = operands::effective_address(stack, memarg, memory.memory64)?;
+    if !ea.is_multiple_of(width as u64) {
+        return Err(Trap::UnalignedAtomic);
+    }
+    Ok(ea)
+}
+
+/// Execute an atomic instruction against the operand stack
+///
+/// `memory` is the memory of the current module, which validation guarantees is present for instructions that
+/// access memory. Memories take a lock for every access, so `atomic.fence` has nothing left to order. `interrupt` can
+/// cancel a `memory.atomic.wait`.
+pub fn execute(
+    instruction: &AtomicInstruction,
+    stack: &mut Vec<Val>,
+    memory: Option<&mut MemInst>,
+    interrupt: &InterruptHandle,
+) -> Result<(), Trap> {
+    let Some((op, t, width, memarg)) = instruction.access() else {
+        return Ok(());
+    };
+    let memory = memory.expect("validation guarantees the module has a memory");
+    let width = width as usize;
+
+    let result = match op {
+        AtomicOp::Notify => {
+            let count = pop_i32(stack) as u32;
+            let ea = effective_address(stack, memarg, memory, width)?;
+            Val::I32(memory.notify(ea, count)? as i32)
+        }
+        AtomicOp::Wait => {
+            // A negative timeout waits forever
+            let timeout = pop_i64(stack);
+            let timeout = (timeout >= 0).then(|| Duration::from_nanos(timeout as u64));
+            let expected = pop_int(stack, &t);
+            let ea = effective_address(stack, memarg, memory, width)?;
+            Val::I32(memory.wait(ea, &expected.to_le_bytes()[..width], timeout, interrupt)? as i32)
+        }
+        AtomicOp::Load => {
+            let ea = effective_address(stack, memarg, memory, width)?;
+            let mut bytes = [0; 8];
+            memory.read(ea, &mut bytes[..width])?;
+            int(&t, u64::from_le_bytes(bytes))
+        }
+        AtomicOp::Store => {
+            let n = pop_int(stack, &t);
+            let ea = effective_address(stack, memarg, memory, width)?;
+            return memory.write(ea, &n.to_le_bytes()[..width]);
+        }
+        AtomicOp::Rmw(rmw) => {
+            let n = pop_int(stack, &t);
+            let ea = effective_address(stack, memarg, memory, width)?;
+            let old = memory.atomic(ea, width, |bytes| {
+                let old = read(bytes);
+                let new = match rmw {
+                    RmwOp::Add => old.wrapping_add(n),
+                    RmwOp::Sub => old.wrapping_sub(n),
+                    RmwOp::And => old & n,
+                    RmwOp::Or => old | n,
+                    RmwOp::Xor => old ^ n,
+                    RmwOp::Xchg => n,
+                };
+                write(bytes, new);
+                old
+            })?;
+            int(&t, old)
+        }
+        AtomicOp::Cmpxchg => {
+            let replacement = pop_int(stack, &t);
+            let expected = pop_int(stack, &t);
+            let ea = effective_address(stack, memarg, memory, width)?;
+            let old = memory.atomic(ea, width, |bytes| {
+                let old = read(bytes);
+                // The expected value is wrapped to the access width before comparing
+                if old == read(&expected.to_le_bytes()[..width]) {
+                    write(bytes, replacement);
+                }
+                old
+            })?;
+            int(&t, old)
+        }
+    };
+    stack.push(result);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::module::types::{Limits, MemType};
+
+    use super::*;
+
+    fn memarg(align: u32) -> MemArg {
+        MemArg {
+            align,
+            offset: 0,
+            memidx: 0,
+        }
+    }
+
+    #[test]
+    fn test_atomic() {
+        use AtomicInstruction::*;
+
+        let mt = MemType {
+            lim: Limits {
+                min: 1,
+                max: Some(1),
+            },
+            shared: true,
+            memory64: false,
+        };
+        let mut memory = MemInst::new(&mt, None).unwrap().unwrap();
+        let interrupt = InterruptHandle::default();
+
+        let mut stack = vec![Val::I32(8), Val::I64(-1)];
+        assert_eq!(
+            execute(
+                &I64AtomicStore(memarg(3)),
+                &mut stack,
+                Some(&mut memory),
+                &interrupt
+            ),
+            Ok(())
+        );
+        let mut stack = vec![Val::I32(8), Val::I32(0x1FF)];
+        assert_eq!(
+            execute(
+                &I32AtomicRmw8AddU(memarg(0)),
+                &mut stack,
+                Some(&mut memory),
+                &interrupt
+            ),
+            Ok(())
+        );
+        assert_eq!(stack, vec!(Val::I32(0xFF)));
+        let mut stack = vec![Val::I32(8)];
+        assert_eq!(
+            execute(
+                &I64AtomicLoad16U(memarg(1)),
+                &mut stack,
+                Some(&mut memory),
+                &interrupt
+            ),
+            Ok(())
+        );
+        assert_eq!(stack, vec!(Val::I64(0xFFFE)));
+
+        let mut stack = vec![Val::I32(8), Val::I32(0x1FE), Val::I32(7)];
+        assert_eq!(
+            execute(
+                &I32AtomicRmw8CmpxchgU(memarg(0)),
+                &mut stack,
+                Some(&mut memory),
+                &interrupt
+            ),
+            Ok(())
+        );
+        assert_eq!(stack, vec!(Val::I32(0xFE)));
+        let mut stack = vec![Val::I32(8), Val::I32(0), Val::I32(1)];
+        assert_eq!(
+            execute(
+                &I32AtomicRmwCmpxchg(memarg(2)),
+                &mut stack,
+                Some(&mut memory),
+                &interrupt
+            ),
+            Ok(())
+        );
+        assert_eq!(stack, vec!(Val::I32(-249)));
+
+        let mut stack = vec![Val::I32(2), Val::I32(1)];
+        assert_eq!(
+            execute(
+                &I32AtomicRmwSub(memarg(2)),
+                &mut stack,
+                Some(&mut memory),
+                &interrupt
+            ),
+            Err(Trap::UnalignedAtomic)
+        );
+        let mut stack = vec![Val::I32(65536)];
+        assert_eq!(
+            execute(
+                &I32AtomicLoad(memarg(2)),
+                &mut stack,
+                Some(&mut memory),
+                &interrupt
+            ),
+            Err(Trap::OutOfBoundsMemoryAccess)
+        );
+
+        let mut stack = vec![Val::I32(0), Val::I32(1), Val::I64(0)];
+        assert_eq!(
+            execute(
+                &MemoryAtomicWait32(memarg(2)),
+                &mut stack,
+                Some(&mut memory),
+                &interrupt
+            ),
+            Ok(())
+        );
+        assert_eq!(stack, vec!(Val::I32(1)));
+        let mut stack = vec![Val::I32(0), Val::I64(0), Val::I64(1000)];
+        assert_eq!(
+            execute(
+                &MemoryAtomicWait64(memarg(3)),
+                &mut stack,
+                Some(&mut memory),
+                &interrupt
+            ),
+            Ok(())
+        );
+        assert_eq!(stack, vec!(Val::I32(2)));
+        let mut stack = vec![Val::I32(0), Val::I32(1)];
+        assert_eq!(
+            execute(
+                &MemoryAtomicNotify(memarg(2)),
+                &mut stack,
+                Some(&mut memory),
+                &interrupt
+            ),
+            Ok(())
+        );
+        assert_eq!(stack, vec!(Val::I32(0)));
+
+        let mut stack = vec![];
+        assert_eq!(execute(&AtomicFence, &mut stack, None, &interrupt), Ok(()));
+        assert!(stack.is_empty());
+    }
+}
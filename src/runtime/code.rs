@@ -0,0 +1,150 @@
+use crate::{
+    instructions::{BlockType, CatchClause, ControlInstruction, Instruction},
+    module::code::{Func, Local},
+};
+
+/// A function body flattened for the interpreter
+///
+/// Blocks are ranges of positions rather than nested vectors, so the interpreter can enter and leave them by jumping
+/// instead of recursing, and keep its frames and labels on the heap.
+pub struct Code {
+    pub locals: Vec<Local>,
+    pub ops: Vec<Op>,
+}
+
+/// A single step of a flattened function body
+#[derive(Debug)]
+pub enum Op {
+    /// An instruction that doesn't enter or leave a block
+    Instruction(Instruction),
+    /// Enter a block that ends at the given position
+    Block(BlockType, usize),
+    /// Enter a loop whose body starts at the next position
+    Loop(BlockType),
+    /// Enter an if that continues at the first position if the condition is false, and ends at the second
+    If(BlockType, usize, usize),
+    /// Leave the then branch of an if for the position after its end
+    Else(usize),
+    /// Enter a try_table that ends at the given position
+    TryTable(BlockType, Vec<CatchClause>, usize),
+    /// Leave the innermost block, loop, if or try_table
+    End,
+    /// Leave the function, which is where its body ends
+    Return,
+}
+
+impl Code {
+    pub fn new(func: &Func) -> Self {
+        let mut ops = Vec::new();
+        flatten(&func.body.0, &mut ops);
+        ops.push(Op::Return);
+        Self {
+            locals: func.locals.clone(),
+            ops,
+        }
+    }
+}
+
+/// Append a sequence of instructions, patching in the end of each block once its body is flattened
+fn flatten(instructions: &[Instruction], ops: &mut Vec<Op>) {
+    for instruction in instructions {
+        let start = ops.len();
+        match instruction {
+            Instruction::Control(ControlInstruction::Block(bt, body)) => {
+                ops.push(Op::Block(bt.clone(), 0));
+                flatten(body, ops);
+                ops.push(Op::End);
+                ops[start] = Op::Block(bt.clone(), ops.len());
+            }
+            Instruction::Control(ControlInstruction::Loop(bt, body)) => {
+                ops.push(Op::Loop(bt.clone()));
+                flatten(body, ops);
+                ops.push(Op::End);
+            }
+            Instruction::Control(ControlInstruction::If(bt, then, otherwise)) => {
+                ops.push(Op::If(bt.clone(), 0, 0));
+                flatten(then, ops);
+                let mut else_position = None;
+                if !otherwise.is_empty() {
+                    else_position = Some(ops.len());
+                    ops.push(Op::Else(0));
+                    flatten(otherwise, ops);
+                }
+                ops.push(Op::End);
+                let end = ops.len();
+                // Without an else branch, a false condition goes straight to the end of the if
+                let otherwise_start = match else_position {
+                    Some(position) => {
+                        ops[position] = Op::Else(end);
+                        position + 1
+                    }
+                    None => end - 1,
+                };
+                ops[start] = Op::If(bt.clone(), otherwise_start, end);
+            }
+            Instruction::Control(ControlInstruction::TryTable(bt, catches, body)) => {
+                ops.push(Op::TryTable(bt.clone(), catches.clone(), 0));
+                flatten(body, ops);
+                ops.push(Op::End);
+                ops[start] = Op::TryTable(bt.clone(), catches.clone(), ops.len());
+            }
+            instruction => ops.push(Op::Instruction(instruction.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::instructions::{Expression, NumericInstruction};
+
+    use super::*;
+
+    #[test]
+    fn test_flatten() {
+        let i32_const = |n| Instruction::Numeric(NumericInstruction::I32Const(n));
+        // (block (if (then (i32.const 1)) (else (i32.const 2))) (loop (if (then))))
+        let func = Func {
+            locals: vec![],
+            body: Expression(vec![Instruction::Control(ControlInstruction::Block(
+                BlockType::Empty,
+                vec![
+                    Instruction::Control(ControlInstruction::If(
+                        BlockType::Empty,
+                        vec![i32_const(1)],
+                        vec![i32_const(2)],
+                    )),
+                    Instruction::Control(ControlInstruction::Loop(
+                        BlockType::Empty,
+                        vec![Instruction::Control(ControlInstruction::If(
+                            BlockType::Empty,
+                            vec![],
+                            vec![],
+                        ))],
+                    )),
+                ],
+            ))]),
+        };
+        let code = Code::new(&func);
+        let positions: Vec<String> = code
+            .ops
+            .iter()
+            .map(|op| match op {
+                Op::Instruction(_) => "i".to_string(),
+                Op::Block(_, end) => format!("block {}", end),
+                Op::Loop(_) => "loop".to_string(),
+                Op::If(_, otherwise, end) => format!("if {} {}", otherwise, end),
+                Op::Else(end) => format!("else {}", end),
+                Op::TryTable(_, _, end) => format!("try_table {}", end),
+                Op::End => "end".to_string(),
+                Op::Return => "return".to_string(),
+            })
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                "block 11", "if 4 6", "i", "else 6", "i", "end", "loop", "if 8 9", "end", "end",
+                "end", "return"
+            ]
+        );
+    }
+}
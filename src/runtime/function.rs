@@ -0,0 +1,45 @@
+use std::{fmt, rc::Rc};
+
+use crate::module::{indices::TypeIdx, types::FuncType};
+
+use super::{code::Code, store::InstanceAddr, trap::Trap, values::Val};
+
+/// A function implemented by the host that can be imported by Wasm modules
+pub type HostFunc = Box<dyn Fn(&[Val]) -> Result<Vec<Val>, Trap>>;
+
+/// The runtime representation of a function
+pub enum FuncInst {
+    /// A function defined by a Wasm module, along with the address of the instance it was defined in and the index
+    /// of its type there
+    Wasm {
+        ty: FuncType,
+        typeidx: TypeIdx,
+        instance: InstanceAddr,
+        code: Rc<Code>,
+    },
+    /// A function defined by the host
+    Host { ty: FuncType, func: HostFunc },
+}
+
+impl FuncInst {
+    /// The type of the function
+    pub fn ty(&self) -> &FuncType {
+        match self {
+            Self::Wasm { ty, .. } => ty,
+            Self::Host { ty, .. } => ty,
+        }
+    }
+}
+
+impl fmt::Debug for FuncInst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Wasm { ty, instance, .. } => f
+                .debug_struct("Wasm")
+                .field("ty", ty)
+                .field("instance", instance)
+                .finish(),
+            Self::Host { ty, .. } => f.debug_struct("Host").field("ty", ty).finish(),
+        }
+    }
+}
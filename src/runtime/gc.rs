@@ -0,0 +1,404 @@
+use std::rc::Rc;
+
+use crate::{
+    instructions::GcInstruction,
+    module::{
+        indices::{LabelIdx, TypeIdx},
+        types::{DefType, FieldType, HeapType, NumType, PackedType, RefType, StorageType, ValType},
+    },
+    subtyping::matches_heap_type,
+};
+
+use super::{
+    function::FuncInst,
+    heap::GcRef,
+    operands::{pop, pop_i32, pop_ref},
+    store::{FuncAddr, InstanceAddr, Store},
+    trap::Trap,
+    values::{AnyRef, Val},
+};
+
+/// The most elements an array can have, which keeps a single allocation from exhausting the host's memory
+const MAX_ARRAY_LEN: usize = 1 << 27;
+
+impl Store {
+    /// Execute a GC instruction, returning the label to branch to if a `br_on_cast` or `br_on_cast_fail` is taken
+    pub(super) fn gc(
+        &mut self,
+        instance: InstanceAddr,
+        stack: &mut Vec<Val>,
+        instruction: &GcInstruction,
+    ) -> Result<Option<LabelIdx>, Trap> {
+        use GcInstruction::*;
+
+        let types = self.instances[instance].types.clone();
+        let result = match instruction {
+            StructNew(x) | StructNewDefault(x) => {
+                let st = types[*x as usize]
+                    .struct_type()
+                    .expect("validated struct type");
+                let fields = match instruction {
+                    StructNew(_) => {
+                        let vals = stack.split_off(stack.len() - st.fields.len());
+                        vals.into_iter()
+                            .zip(&st.fields)
+                            .map(|(val, ft)| pack(val, ft))
+                            .collect()
+                    }
+                    _ => st
+                        .fields
+                        .iter()
+                        .map(|ft| self.instances[instance].default_value(&ft.st.unpacked()))
+                        .collect(),
+                };
+                let object = self.alloc_object(stack, types.clone(), *x, fields);
+                Val::AnyRef(Some(AnyRef::Struct(object)))
+            }
+            StructGet(x, y) | StructGetS(x, y) | StructGetU(x, y) => {
+                let object = pop_struct(stack)?;
+                let ft = &types[*x as usize]
+                    .struct_type()
+                    .expect("validated struct type")
+                    .fields[*y as usize];
+                let val = object.get(*y as usize).expect("validated field");
+                unpack(val, ft, matches!(instruction, StructGetS(_, _)))
+            }
+            StructSet(x, y) => {
+                let val = pop(stack);
+                let object = pop_struct(stack)?;
+                let ft = &types[*x as usize]
+                    .struct_type()
+                    .expect("validated struct type")
+                    .fields[*y as usize];
+                object.set(*y as usize, pack(val, ft));
+                return Ok(None);
+            }
+            ArrayNew(x) | ArrayNewDefault(x) => {
+                let n = pop_i32(stack) as u32 as usize;
+                let ft = array_field(&types, *x);
+                let val = match instruction {
+                    ArrayNew(_) => pack(pop(stack), ft),
+                    _ => self.instances[instance].default_value(&ft.st.unpacked()),
+                };
+                let mut elements = reserve(n)?;
+                elements.resize(n, val);
+                self.alloc_array(stack, &types, *x, elements)
+            }
+            ArrayNewFixed(x, n) => {
+                let ft = array_field(&types, *x);
+                let elements = stack
+                    .split_off(stack.len() - *n as usize)
+                    .into_iter()
+                    .map(|val| pack(val, ft))
+                    .collect();
+                self.alloc_array(stack, &types, *x, elements)
+            }
+            ArrayNewData(x, y) => {
+                let n = pop_i32(stack) as u32 as usize;
+                let s = pop_i32(stack) as u32 as usize;
+                let ft = array_field(&types, *x);
+                let dataaddr = self.instances[instance].dataaddrs[*y as usize];
+                let elements = read_data(&self.datas[dataaddr].data, s, n, &ft.st)?;
+                self.alloc_array(stack, &types, *x, elements)
+            }
+            ArrayNewElem(x, y) => {
+                let n = pop_i32(stack) as u32 as usize;
+                let s = pop_i32(stack) as u32 as usize;
+                let elemaddr = self.instances[instance].elemaddrs[*y as usize];
+                let elements = self.elems[elemaddr]
+                    .elements
+                    .get(s..s + n)
+                    .ok_or(Trap::OutOfBoundsTableAccess)?
+                    .to_vec();
+                self.alloc_array(stack, &types, *x, elements)
+            }
+            ArrayGet(x) | ArrayGetS(x) | ArrayGetU(x) => {
+                let i = pop_i32(stack) as u32 as usize;
+                let array = pop_array(stack)?;
+                let val = array.get(i).ok_or(Trap::OutOfBoundsArrayAccess)?;
+                unpack(
+                    val,
+                    array_field(&types, *x),
+                    matches!(instruction, ArrayGetS(_)),
+                )
+            }
+            ArraySet(x) => {
+                let val = pop(stack);
+                let i = pop_i32(stack) as u32 as usize;
+                let array = pop_array(stack)?;
+                if i >= array.len() {
+                    return Err(Trap::OutOfBoundsArrayAccess);
+                }
+                array.set(i, pack(val, array_field(&types, *x)));
+                return Ok(None);
+            }
+            ArrayLen => Val::I32(pop_array(stack)?.len() as i32),
+            ArrayFill(x) => {
+                let n = pop_i32(stack) as u32 as usize;
+                let val = pack(pop(stack), array_field(&types, *x));
+                let d = pop_i32(stack) as u32 as usize;
+                let array = pop_array(stack)?;
+                array.update(|elements| {
+                    elements
+                        .get_mut(d..d + n)
+                        .ok_or(Trap::OutOfBoundsArrayAccess)
+                        .map(|range| range.fill(val))
+                })?;
+                return Ok(None);
+            }
+            ArrayCopy(_, _) => {
+                let n = pop_i32(stack) as u32 as usize;
+                let s = pop_i32(stack) as u32 as usize;
+                let src = pop_ref(stack);
+                let d = pop_i32(stack) as u32 as usize;
+                let dst = array(pop_ref(stack))?;
+                let src = array(src)?;
+                if s + n > src.len() || d + n > dst.len() {
+                    return Err(Trap::OutOfBoundsArrayAccess);
+                }
+                // Copying out first handles overlapping ranges of the same array
+                let elements = src.fields()[s..s + n].to_vec();
+                dst.update(|dst| dst[d..d + n].clone_from_slice(&elements));
+                return Ok(None);
+            }
+            ArrayInitData(x, y) => {
+                let n = pop_i32(stack) as u32 as usize;
+                let s = pop_i32(stack) as u32 as usize;
+                let d = pop_i32(stack) as u32 as usize;
+                let array = pop_array(stack)?;
+                if d + n > array.len() {
+                    return Err(Trap::OutOfBoundsArrayAccess);
+                }
+                let dataaddr = self.instances[instance].dataaddrs[*y as usize];
+                let elements = read_data(
+                    &self.datas[dataaddr].data,
+                    s,
+                    n,
+                    &array_field(&types, *x).st,
+                )?;
+                array.update(|dst| dst[d..d + n].clone_from_slice(&elements));
+                return Ok(None);
+            }
+            ArrayInitElem(_, y) => {
+                let n = pop_i32(stack) as u32 as usize;
+                let s = pop_i32(stack) as u32 as usize;
+                let d = pop_i32(stack) as u32 as usize;
+                let array = pop_array(stack)?;
+                if d + n > array.len() {
+                    return Err(Trap::OutOfBoundsArrayAccess);
+                }
+                let elemaddr = self.instances[instance].elemaddrs[*y as usize];
+                let elements = self.elems[elemaddr]
+                    .elements
+                    .get(s..s + n)
+                    .ok_or(Trap::OutOfBoundsTableAccess)?;
+                array.update(|dst| dst[d..d + n].clone_from_slice(elements));
+                return Ok(None);
+            }
+            RefTest(rt) => {
+                let val = pop_ref(stack);
+                Val::I32(self.ref_matches(&val, &types, rt) as i32)
+            }
+            RefCast(rt) => {
+                let val = pop_ref(stack);
+                if !self.ref_matches(&val, &types, rt) {
+                    return Err(Trap::CastFailure);
+                }
+                val
+            }
+            BrOnCast(labelidx, _, rt) | BrOnCastFail(labelidx, _, rt) => {
+                let val = pop_ref(stack);
+                let cast = self.ref_matches(&val, &types, rt);
+                stack.push(val);
+                let taken = cast == matches!(instruction, BrOnCast(_, _, _));
+                return Ok(taken.then_some(*labelidx));
+            }
+            AnyConvertExtern => match pop_ref(stack) {
+                Val::ExternRef(reference) => Val::AnyRef(reference.map(AnyRef::internalize)),
+                val => unreachable!("expected an externref operand, found {:?}", val),
+            },
+            ExternConvertAny => match pop_ref(stack) {
+                Val::AnyRef(reference) => Val::ExternRef(reference.map(AnyRef::externalize)),
+                val => unreachable!("expected an anyref operand, found {:?}", val),
+            },
+            RefI31 => Val::AnyRef(Some(AnyRef::I31(pop_i32(stack) as u32 & 0x7FFF_FFFF))),
+            I31GetS | I31GetU => match pop_ref(stack) {
+                Val::AnyRef(Some(AnyRef::I31(n))) if *instruction == I31GetS => {
+                    Val::I32(((n << 1) as i32) >> 1)
+                }
+                Val::AnyRef(Some(AnyRef::I31(n))) => Val::I32(n as i32),
+                Val::AnyRef(None) => return Err(Trap::NullI31Reference),
+                val => unreachable!("expected an i31ref operand, found {:?}", val),
+            },
+        };
+        stack.push(result);
+        Ok(None)
+    }
+
+    fn alloc_array(
+        &mut self,
+        stack: &[Val],
+        types: &Rc<[DefType]>,
+        typeidx: TypeIdx,
+        elements: Vec<Val>,
+    ) -> Val {
+        let object = self.alloc_object(stack, types.clone(), typeidx, elements);
+        Val::AnyRef(Some(AnyRef::Array(object)))
+    }
+
+    /// Allocate an object, collecting garbage first if the heap has grown enough since the last collection
+    ///
+    /// The operand stack of the running call holds the locals and operands of every frame, so it is a root, along
+    /// with the fields of the new object.
+    fn alloc_object(
+        &mut self,
+        stack: &[Val],
+        types: Rc<[DefType]>,
+        typeidx: TypeIdx,
+        fields: Vec<Val>,
+    ) -> GcRef {
+        if self.heap.is_full() {
+            self.collect(&[stack, &fields]);
+        }
+        self.heap.alloc(types, typeidx, fields)
+    }
+
+    /// Whether a reference has the given type, whose type indices refer to `types`, with the semantics of `ref.test`
+    pub(super) fn ref_matches(&self, val: &Val, types: &[DefType], rt: &RefType) -> bool {
+        match val {
+            val if val.is_null() => rt.nullable,
+            Val::FuncRef(Some(handle)) => match &rt.ht {
+                HeapType::Type(typeidx) => self.func_matches(handle.0, types, *typeidx),
+                ht => *ht == HeapType::Func,
+            },
+            Val::AnyRef(Some(AnyRef::Struct(object) | AnyRef::Array(object))) => {
+                let object = object.object();
+                let ht = HeapType::Type(object.typeidx);
+                matches_heap_type(&object.types, &ht, types, &rt.ht)
+            }
+            Val::AnyRef(Some(AnyRef::I31(_))) => {
+                matches_heap_type(&[], &HeapType::I31, types, &rt.ht)
+            }
+            Val::AnyRef(Some(AnyRef::Extern(_))) => rt.ht == HeapType::Any,
+            Val::ExternRef(_) => rt.ht == HeapType::Extern,
+            Val::ExnRef(_) => rt.ht == HeapType::Exn,
+            _ => false,
+        }
+    }
+
+    /// Whether a function has a subtype of the given function type, which is how indirect calls and imports are checked
+    ///
+    /// Host functions don't belong to a module, so their types are compared structurally.
+    pub(super) fn func_matches(
+        &self,
+        funcaddr: FuncAddr,
+        types: &[DefType],
+        typeidx: TypeIdx,
+    ) -> bool {
+        match &self.funcs[funcaddr] {
+            FuncInst::Wasm {
+                typeidx: actual,
+                instance,
+                ..
+            } => matches_heap_type(
+                &self.instances[*instance].types,
+                &HeapType::Type(*actual),
+                types,
+                &HeapType::Type(typeidx),
+            ),
+            FuncInst::Host { ty, .. } => {
+                types.get(typeidx as usize).and_then(DefType::func_type) == Some(ty)
+            }
+        }
+    }
+}
+
+fn array_field(types: &[DefType], typeidx: TypeIdx) -> &FieldType {
+    &types[typeidx as usize]
+        .array_type()
+        .expect("validated array type")
+        .ft
+}
+
+fn pop_struct(stack: &mut Vec<Val>) -> Result<GcRef, Trap> {
+    match pop_ref(stack) {
+        Val::AnyRef(Some(AnyRef::Struct(object))) => Ok(object),
+        Val::AnyRef(None) => Err(Trap::NullStructureReference),
+        val => unreachable!("expected a struct operand, found {:?}", val),
+    }
+}
+
+fn pop_array(stack: &mut Vec<Val>) -> Result<GcRef, Trap> {
+    array(pop_ref(stack))
+}
+
+fn array(val: Val) -> Result<GcRef, Trap> {
+    match val {
+        Val::AnyRef(Some(AnyRef::Array(object))) => Ok(object),
+        Val::AnyRef(None) => Err(Trap::NullArrayReference),
+        val => unreachable!("expected an array operand, found {:?}", val),
+    }
+}
+
+/// Allocate room for the elements of a new array, trapping if there are too many
+pub(crate) fn reserve(n: usize) -> Result<Vec<Val>, Trap> {
+    let mut elements = Vec::new();
+    if n > MAX_ARRAY_LEN || elements.try_reserve_exact(n).is_err() {
+        return Err(Trap::ResourceLimitExceeded);
+    }
+    Ok(elements)
+}
+
+/// Wrap a value to the width of a packed field
+pub(crate) fn pack(val: Val, ft: &FieldType) -> Val {
+    match (&ft.st, val) {
+        (StorageType::Packed(pt), Val::I32(n)) => Val::I32(n & ((1 << pt.bits()) - 1)),
+        (_, val) => val,
+    }
+}
+
+/// Read a field, extending a packed field to an i32 with or without its sign
+fn unpack(val: Val, ft: &FieldType, signed: bool) -> Val {
+    match (&ft.st, val) {
+        (StorageType::Packed(pt), Val::I32(n)) if signed => {
+            let shift = 32 - pt.bits();
+            Val::I32((n << shift) >> shift)
+        }
+        (_, val) => val,
+    }
+}
+
+/// Read `n` little-endian elements of the given storage type from a data segment, starting at byte `s`
+fn read_data(data: &[u8], s: usize, n: usize, st: &StorageType) -> Result<Vec<Val>, Trap> {
+    let size = match st {
+        StorageType::Packed(PackedType::I8) => 1,
+        StorageType::Packed(PackedType::I16) => 2,
+        StorageType::Val(ValType::NumType(NumType::I32 | NumType::F32)) => 4,
+        StorageType::Val(ValType::NumType(NumType::I64 | NumType::F64)) => 8,
+        StorageType::Val(_) => 16,
+    };
+    let bytes = n
+        .checked_mul(size)
+        .and_then(|len| data.get(s..s.checked_add(len)?))
+        .ok_or(Trap::OutOfBoundsMemoryAccess)?;
+    let mut elements = reserve(n)?;
+    elements.extend(bytes.chunks_exact(size).map(|chunk| {
+        let mut buf = [0; 16];
+        buf[..size].copy_from_slice(chunk);
+        let bits = u128::from_le_bytes(buf);
+        match st {
+            StorageType::Packed(_) | StorageType::Val(ValType::NumType(NumType::I32)) => {
+                Val::I32(bits as u32 as i32)
+            }
+            StorageType::Val(ValType::NumType(NumType::I64)) => Val::I64(bits as u64 as i64),
+            StorageType::Val(ValType::NumType(NumType::F32)) => {
+                Val::F32(f32::from_bits(bits as u32))
+            }
+            StorageType::Val(ValType::NumType(NumType::F64)) => {
+                Val::F64(f64::from_bits(bits as u64))
+            }
+            StorageType::Val(_) => Val::V128(bits),
+        }
+    }));
+    Ok(elements)
+}
@@ -0,0 +1,10 @@
+use crate::module::types::GlobalType;
+
+use super::values::Val;
+
+/// The runtime representation of a global variable
+#[derive(Debug, PartialEq)]
+pub struct GlobalInst {
+    pub ty: GlobalType,
+    pub value: Val,
+}
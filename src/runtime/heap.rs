@@ -0,0 +1,384 @@
+//! The heap that GC objects are allocated on
+//!
+//! Objects are reference counted, so they are freed as soon as nothing refers to them, and the collector only has to
+//! free the cycles among them. It marks everything reachable from the roots: the operand stack of the running call,
+//! which holds the locals and operands of every frame, and the globals, tables and element segments of the store.
+//! Marking follows the fields of structs and arrays, the payloads of exceptions, and the objects that
+//! `extern.convert_any` wraps in host references, so cycles through any of those are found too.
+//!
+//! The host can also hold references outside the store, such as the results of a call. Those can't be traced, so
+//! after marking, the collector counts the references among everything it didn't reach. An unreached object,
+//! exception or host reference with more references than that is held by the host, and what it reaches is kept as
+//! well. The fields of every other unreached object are cleared, which breaks its cycles and frees it.
+//!
+//! A collection runs when an allocation by the interpreter finds that the heap has doubled since the last one. It
+//! pauses execution for time proportional to the number of live objects and their fields, which keeps the cost per
+//! allocation constant on average but doesn't bound a single pause.
+
+use std::{
+    any::Any,
+    cell::{Ref, RefCell},
+    collections::{HashMap, HashSet},
+    fmt,
+    rc::{Rc, Weak},
+};
+
+use crate::module::{indices::TypeIdx, types::DefType};
+
+use super::values::{AnyRef, Exception, Val};
+
+/// The fewest objects the heap holds before it first collects garbage
+const MIN_THRESHOLD: usize = 1024;
+
+/// A struct or array allocated by `struct.new` or `array.new`, along with the types of the module that allocated it
+///
+/// Packed fields hold an `i32` that is already wrapped to the width of the field.
+pub(super) struct Object {
+    pub types: Rc<[DefType]>,
+    pub typeidx: TypeIdx,
+    fields: RefCell<Vec<Val>>,
+}
+
+impl Drop for Object {
+    /// Drop the objects only this one refers to without recursing, so that long linked lists can't overflow the stack
+    fn drop(&mut self) {
+        let mut pending = std::mem::take(self.fields.get_mut());
+        while let Some(val) = pending.pop() {
+            if let Val::AnyRef(Some(AnyRef::Struct(GcRef(object)) | AnyRef::Array(GcRef(object)))) =
+                val
+            {
+                if let Some(mut object) = Rc::into_inner(object) {
+                    pending.append(object.fields.get_mut());
+                }
+            }
+        }
+    }
+}
+
+/// A reference to an object on the heap, which compares equal to another reference to the same object
+#[derive(Clone)]
+pub struct GcRef(Rc<Object>);
+
+impl GcRef {
+    pub(super) fn object(&self) -> &Object {
+        &self.0
+    }
+
+    /// The index of the object's type in the module that allocated it
+    pub fn typeidx(&self) -> TypeIdx {
+        self.0.typeidx
+    }
+
+    /// Read a field of a struct or an element of an array
+    pub fn get(&self, i: usize) -> Option<Val> {
+        self.0.fields.borrow().get(i).cloned()
+    }
+
+    /// The number of fields of a struct or elements of an array
+    pub fn len(&self) -> usize {
+        self.0.fields.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The fields of a struct or the elements of an array
+    pub(super) fn fields(&self) -> Ref<'_, Vec<Val>> {
+        self.0.fields.borrow()
+    }
+
+    pub(super) fn set(&self, i: usize, val: Val) {
+        self.0.fields.borrow_mut()[i] = val;
+    }
+
+    /// Run a function on the fields, such as to fill or copy a range of an array
+    pub(super) fn update<T>(&self, f: impl FnOnce(&mut Vec<Val>) -> T) -> T {
+        f(&mut self.0.fields.borrow_mut())
+    }
+}
+
+impl PartialEq for GcRef {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for GcRef {
+    // Objects can refer to each other in cycles, so only the address and type are printed
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "GcRef({:p}, type {})",
+            Rc::as_ptr(&self.0),
+            self.0.typeidx
+        )
+    }
+}
+
+/// Every object allocated in a store, whose cycles are collected by marking from the store's roots
+pub struct Heap {
+    objects: Vec<Weak<Object>>,
+    threshold: usize,
+}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self {
+            objects: Vec::new(),
+            threshold: MIN_THRESHOLD,
+        }
+    }
+}
+
+impl Heap {
+    /// Allocate an object
+    pub fn alloc(&mut self, types: Rc<[DefType]>, typeidx: TypeIdx, fields: Vec<Val>) -> GcRef {
+        let object = Rc::new(Object {
+            types,
+            typeidx,
+            fields: RefCell::new(fields),
+        });
+        self.objects.push(Rc::downgrade(&object));
+        GcRef(object)
+    }
+
+    /// Whether the heap has grown enough since the last collection that the next allocation should collect first
+    pub fn is_full(&self) -> bool {
+        self.objects.len() >= self.threshold
+    }
+
+    /// Free the objects that are unreachable from `roots` and from the host
+    pub fn collect<'a>(&mut self, roots: impl IntoIterator<Item = &'a Val>) {
+        let mut marked = HashSet::new();
+        mark(
+            &mut marked,
+            roots.into_iter().filter_map(Node::of).collect(),
+        );
+
+        // Find everything the unreached objects refer to that wasn't reached either, and count the references to it
+        let mut nodes: Vec<Node> = self
+            .objects
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(Node::Object)
+            .filter(|node| !marked.contains(&node.ptr()))
+            .collect();
+        let mut index: HashMap<*const (), usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.ptr(), i))
+            .collect();
+        let mut references = vec![0; nodes.len()];
+        let mut i = 0;
+        while i < nodes.len() {
+            for child in nodes[i].children() {
+                if marked.contains(&child.ptr()) {
+                    continue;
+                }
+                let j = *index.entry(child.ptr()).or_insert_with(|| {
+                    nodes.push(child.clone());
+                    references.push(0);
+                    nodes.len() - 1
+                });
+                references[j] += 1;
+            }
+            i += 1;
+        }
+
+        // Each node holds one reference of its own, and any beyond that and the counted ones are the host's
+        let held = nodes
+            .iter()
+            .zip(&references)
+            .filter(|(node, references)| node.strong_count() - 1 > **references)
+            .map(|(node, _)| node.clone())
+            .collect();
+        mark(&mut marked, held);
+
+        for node in &nodes {
+            match node {
+                Node::Object(object) if !marked.contains(&node.ptr()) => {
+                    object.fields.borrow_mut().clear()
+                }
+                _ => (),
+            }
+        }
+        drop(nodes);
+
+        self.objects.retain(|object| object.strong_count() > 0);
+        self.threshold = MIN_THRESHOLD.max(2 * self.objects.len());
+    }
+}
+
+/// Something that can refer to objects on the heap: an object itself, an exception, or a host reference that
+/// `extern.convert_any` made from an object
+#[derive(Clone)]
+enum Node {
+    Object(Rc<Object>),
+    Exception(Rc<Exception>),
+    Extern(Rc<dyn Any>),
+}
+
+impl Node {
+    /// The node a value refers to, if it can lead to any objects
+    fn of(val: &Val) -> Option<Self> {
+        match val {
+            Val::AnyRef(Some(reference)) => Self::of_any(reference),
+            Val::ExternRef(Some(reference)) => Self::of_any(&AnyRef::Extern(reference.clone())),
+            Val::ExnRef(Some(exception)) => Some(Self::Exception(exception.clone())),
+            _ => None,
+        }
+    }
+
+    fn of_any(reference: &AnyRef) -> Option<Self> {
+        match reference {
+            AnyRef::Struct(GcRef(object)) | AnyRef::Array(GcRef(object)) => {
+                Some(Self::Object(object.clone()))
+            }
+            AnyRef::Extern(reference) => match reference.data().downcast_ref::<AnyRef>() {
+                Some(AnyRef::Struct(_) | AnyRef::Array(_)) => Some(Self::Extern(reference.rc())),
+                _ => None,
+            },
+            AnyRef::I31(_) => None,
+        }
+    }
+
+    /// The nodes this one refers to
+    fn children(&self) -> Vec<Self> {
+        match self {
+            Self::Object(object) => object.fields.borrow().iter().filter_map(Self::of).collect(),
+            Self::Exception(exception) => exception.payload.iter().filter_map(Self::of).collect(),
+            Self::Extern(data) => data
+                .downcast_ref::<AnyRef>()
+                .and_then(Self::of_any)
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// The address of the node, which identifies it
+    fn ptr(&self) -> *const () {
+        match self {
+            Self::Object(object) => Rc::as_ptr(object) as *const (),
+            Self::Exception(exception) => Rc::as_ptr(exception) as *const (),
+            Self::Extern(data) => Rc::as_ptr(data) as *const (),
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Self::Object(object) => Rc::strong_count(object),
+            Self::Exception(exception) => Rc::strong_count(exception),
+            Self::Extern(data) => Rc::strong_count(data),
+        }
+    }
+}
+
+/// Mark every node reachable from `pending`
+fn mark(marked: &mut HashSet<*const ()>, mut pending: Vec<Node>) {
+    while let Some(node) = pending.pop() {
+        if marked.insert(node.ptr()) {
+            pending.extend(node.children());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alloc(heap: &mut Heap, fields: Vec<Val>) -> GcRef {
+        heap.alloc(Rc::from([]), 0, fields)
+    }
+
+    /// The number of objects that have been allocated and not yet freed
+    fn live(heap: &Heap) -> usize {
+        heap.objects
+            .iter()
+            .filter(|object| object.strong_count() > 0)
+            .count()
+    }
+
+    fn reference(object: &GcRef) -> Val {
+        Val::AnyRef(Some(AnyRef::Struct(object.clone())))
+    }
+
+    #[test]
+    fn test_collect_cycles() {
+        let mut heap = Heap::default();
+        let a = alloc(&mut heap, vec![Val::AnyRef(None)]);
+        let b = alloc(&mut heap, vec![reference(&a)]);
+        a.set(0, reference(&b));
+        let root = alloc(&mut heap, vec![Val::AnyRef(None)]);
+        let kept = alloc(&mut heap, vec![reference(&root)]);
+        root.set(0, reference(&kept));
+        drop((a, b, kept));
+        assert_eq!(live(&heap), 4);
+
+        // Only the cycle that is still referenced from outside the heap survives
+        heap.collect(&[]);
+        assert_eq!(live(&heap), 2);
+        assert!(matches!(root.get(0), Some(Val::AnyRef(Some(_)))));
+        drop(root);
+        heap.collect(&[]);
+        assert_eq!(live(&heap), 0);
+    }
+
+    #[test]
+    fn test_collect_roots() {
+        // A cycle that a root refers to survives until the root is gone
+        let mut heap = Heap::default();
+        let a = alloc(&mut heap, vec![Val::AnyRef(None)]);
+        let b = alloc(&mut heap, vec![reference(&a)]);
+        a.set(0, reference(&b));
+        let roots = vec![reference(&a)];
+        drop((a, b));
+        heap.collect(&roots);
+        assert_eq!(live(&heap), 2);
+        drop(roots);
+        heap.collect(&[]);
+        assert_eq!(live(&heap), 0);
+    }
+
+    #[test]
+    fn test_collect_wrapped_cycles() {
+        let mut heap = Heap::default();
+        // A struct that refers to itself through the payload of an exception, and one that refers to itself through
+        // a host reference made by extern.convert_any
+        let a = alloc(&mut heap, vec![Val::AnyRef(None)]);
+        let exception = Rc::new(Exception {
+            tag: 0,
+            payload: vec![reference(&a)],
+        });
+        a.set(0, Val::ExnRef(Some(exception.clone())));
+        let b = alloc(&mut heap, vec![Val::AnyRef(None)]);
+        b.set(
+            0,
+            Val::ExternRef(Some(AnyRef::Struct(b.clone()).externalize())),
+        );
+        drop((a, b));
+        assert_eq!(live(&heap), 2);
+
+        // The host still holds the exception, which keeps its payload alive
+        heap.collect(&[]);
+        assert_eq!(live(&heap), 1);
+        assert!(matches!(
+            &exception.payload[0],
+            Val::AnyRef(Some(AnyRef::Struct(a))) if a.get(0).is_some()
+        ));
+        drop(exception);
+        heap.collect(&[]);
+        assert_eq!(live(&heap), 0);
+    }
+
+    #[test]
+    fn test_drop_long_list() {
+        let mut heap = Heap::default();
+        let mut list = Val::AnyRef(None);
+        for _ in 0..1_000_000 {
+            list = reference(&alloc(&mut heap, vec![list]));
+        }
+        drop(list);
+        assert_eq!(live(&heap), 0);
+    }
+}
@@ -0,0 +1,651 @@
+use std::{fmt, rc::Rc};
+
+use crate::{
+    const_eval,
+    instructions::Expression,
+    module::{
+        data::Data,
+        element::Element,
+        export::ExportDescriptor,
+        import::{Import, ImportDescriptor},
+        types::{DefType, FuncType, HeapType, Limits, ValType},
+    },
+    subtyping::top,
+    Module,
+};
+
+use super::{
+    code::Code,
+    function::FuncInst,
+    segment::{DataInst, ElemInst},
+    store::{DataAddr, ElemAddr, FuncAddr, GlobalAddr, MemAddr, Store, TableAddr, TagAddr},
+    trap::Trap,
+    values::{FuncHandle, Val},
+};
+
+/// Something in the store that can be imported or exported by a module instance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Extern {
+    Func(FuncAddr),
+    Table(TableAddr),
+    Memory(MemAddr),
+    Global(GlobalAddr),
+    Tag(TagAddr),
+}
+
+/// The runtime representation of a module, mapping its index spaces to addresses in the store
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModuleInst {
+    pub types: Rc<[DefType]>,
+    pub funcaddrs: Vec<FuncAddr>,
+    pub tableaddrs: Vec<TableAddr>,
+    pub memaddrs: Vec<MemAddr>,
+    pub globaladdrs: Vec<GlobalAddr>,
+    pub tagaddrs: Vec<TagAddr>,
+    pub elemaddrs: Vec<ElemAddr>,
+    pub dataaddrs: Vec<DataAddr>,
+    pub exports: Vec<(String, Extern)>,
+}
+
+impl ModuleInst {
+    /// Look up an export by name
+    pub fn export(&self, name: &str) -> Option<Extern> {
+        self.exports
+            .iter()
+            .find(|export| export.0 == name)
+            .map(|export| export.1)
+    }
+
+    /// The null reference to a heap type, which for a concrete type depends on whether it is a function type
+    pub fn null(&self, ht: &HeapType) -> Val {
+        Val::null(&top(&self.types, ht))
+    }
+
+    /// The default value of a type, such as for locals and for the fields of `struct.new_default`
+    pub fn default_value(&self, t: &ValType) -> Val {
+        match t {
+            ValType::RefType(rt) => self.null(&rt.ht),
+            t => Val::default(t),
+        }
+    }
+}
+
+/// The reasons instantiating a module can fail
+#[derive(Debug, PartialEq)]
+pub enum InstantiationError {
+    /// The number of imports provided does not match the number the module declares
+    ImportCount { expected: usize, actual: usize },
+    /// An import was provided with the wrong kind or an incompatible type
+    IncompatibleImport { module: String, name: String },
+    /// An initializer is not a constant expression that can be evaluated
+    InvalidConstExpr,
+    /// The module refers to something it doesn't define, which validation would have rejected
+    Invalid(&'static str),
+    /// One of the steps of instantiation trapped
+    Trap(Trap),
+}
+
+impl fmt::Display for InstantiationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ImportCount { expected, actual } => {
+                write!(
+                    f,
+                    "expected {} imports but {} were provided",
+                    expected, actual
+                )
+            }
+            Self::IncompatibleImport { module, name } => {
+                write!(f, "incompatible import \"{}\" \"{}\"", module, name)
+            }
+            Self::InvalidConstExpr => write!(f, "invalid constant expression"),
+            Self::Invalid(message) => write!(f, "invalid module: {}", message),
+            Self::Trap(trap) => write!(f, "trapped during instantiation: {}", trap),
+        }
+    }
+}
+
+impl std::error::Error for InstantiationError {}
+
+impl From<Trap> for InstantiationError {
+    fn from(trap: Trap) -> Self {
+        Self::Trap(trap)
+    }
+}
+
+impl Store {
+    /// Instantiate a module with the given imports, following the order of steps laid out by the spec
+    pub fn instantiate(
+        &mut self,
+        module: &Module,
+        imports: &[Extern],
+    ) -> Result<ModuleInst, InstantiationError> {
+        let mut instance = ModuleInst {
+            types: module.types.clone().into(),
+            ..ModuleInst::default()
+        };
+        let instanceaddr = self.instances.len();
+
+        // Resolve imports
+        if module.imports.len() != imports.len() {
+            return Err(InstantiationError::ImportCount {
+                expected: module.imports.len(),
+                actual: imports.len(),
+            });
+        }
+        for (import, external) in module.imports.iter().zip(imports) {
+            if !self.matches(import, external, &instance.types) {
+                return Err(InstantiationError::IncompatibleImport {
+                    module: String::from_utf8_lossy(&import.module.0).into_owned(),
+                    name: String::from_utf8_lossy(&import.name.0).into_owned(),
+                });
+            }
+            match *external {
+                Extern::Func(addr) => instance.funcaddrs.push(addr),
+                Extern::Table(addr) => instance.tableaddrs.push(addr),
+                Extern::Memory(addr) => instance.memaddrs.push(addr),
+                Extern::Global(addr) => instance.globaladdrs.push(addr),
+                Extern::Tag(addr) => instance.tagaddrs.push(addr),
+            }
+        }
+
+        // Allocate functions, tables, memories, tags and globals
+        for (typeidx, code) in module.functions.iter().zip(&module.code) {
+            let ty = func_type(&instance, *typeidx)?.clone();
+            self.funcs.push(FuncInst::Wasm {
+                ty,
+                typeidx: *typeidx,
+                instance: instanceaddr,
+                code: Rc::new(Code::new(&code.code)),
+            });
+            instance.funcaddrs.push(self.funcs.len() - 1);
+        }
+        let imported_tables = instance.tableaddrs.len();
+        for table in &module.tables {
+            let tableaddr = self.alloc_table(&table.tt)?;
+            self.tables[tableaddr]
+                .elements
+                .fill(instance.null(&table.tt.et.ht));
+            instance.tableaddrs.push(tableaddr);
+        }
+        for memory in &module.memories {
+            instance.memaddrs.push(self.alloc_memory(&memory.mt)?);
+        }
+        for tag in &module.tags {
+            let ty = func_type(&instance, tag.tt.typeidx)?.clone();
+            instance.tagaddrs.push(self.alloc_tag(ty));
+        }
+
+        // Evaluate global initializers in order, since each can refer to the globals before it
+        let mut globals: Vec<Val> = instance
+            .globaladdrs
+            .iter()
+            .map(|globaladdr| self.globals[*globaladdr].value.clone())
+            .collect();
+        for global in &module.globals {
+            let value = self.eval_const(&global.init, &globals, &instance)?;
+            globals.push(value.clone());
+            instance
+                .globaladdrs
+                .push(self.alloc_global(global.gt.clone(), value));
+        }
+
+        // Fill tables that have initializers, which can refer to globals like any other constant expression
+        for (table, tableaddr) in module
+            .tables
+            .iter()
+            .zip(&instance.tableaddrs[imported_tables..])
+        {
+            if let Some(init) = &table.init {
+                let value = self.eval_const(init, &globals, &instance)?;
+                self.tables[*tableaddr].elements.fill(value);
+            }
+        }
+
+        // Allocate element and data segments
+        for element in &module.elements {
+            let elements = match element {
+                Element::ActiveIndex(_, funcs)
+                | Element::PassiveIndex(_, funcs)
+                | Element::ActiveExplicitIndex(_, _, _, funcs)
+                | Element::DeclarativeIndex(_, funcs) => funcs
+                    .iter()
+                    .map(|funcidx| {
+                        let funcaddr = addr(&instance.funcaddrs, *funcidx, "unknown function")?;
+                        Ok(Val::FuncRef(Some(FuncHandle(funcaddr))))
+                    })
+                    .collect::<Result<_, InstantiationError>>()?,
+                Element::ActiveExpression(_, exprs)
+                | Element::PassiveExpression(_, exprs)
+                | Element::ActiveExplicitExpression(_, _, _, exprs)
+                | Element::DeclarativeExpression(_, exprs) => exprs
+                    .iter()
+                    .map(|expr| self.eval_const(expr, &globals, &instance))
+                    .collect::<Result<_, _>>()?,
+            };
+            self.elems.push(ElemInst { elements });
+            instance.elemaddrs.push(self.elems.len() - 1);
+        }
+        for data in &module.data {
+            let data = match data {
+                Data::Active(_, data) | Data::Passive(data) | Data::ActiveExplicit(_, _, data) => {
+                    data.clone()
+                }
+            };
+            self.datas.push(DataInst { data });
+            instance.dataaddrs.push(self.datas.len() - 1);
+        }
+
+        // Collect the exports and make the instance available to its functions, which may run from here on
+        for export in &module.exports {
+            let external = match export.descriptor {
+                ExportDescriptor::Func(idx) => {
+                    Extern::Func(addr(&instance.funcaddrs, idx, "unknown function")?)
+                }
+                ExportDescriptor::Table(idx) => {
+                    Extern::Table(addr(&instance.tableaddrs, idx, "unknown table")?)
+                }
+                ExportDescriptor::Mem(idx) => {
+                    Extern::Memory(addr(&instance.memaddrs, idx, "unknown memory")?)
+                }
+                ExportDescriptor::Global(idx) => {
+                    Extern::Global(addr(&instance.globaladdrs, idx, "unknown global")?)
+                }
+                ExportDescriptor::Tag(idx) => {
+                    Extern::Tag(addr(&instance.tagaddrs, idx, "unknown tag")?)
+                }
+            };
+            let name = String::from_utf8_lossy(&export.name.0).into_owned();
+            instance.exports.push((name, external));
+        }
+        self.instances.push(instance.clone());
+
+        // Apply active element segments and drop them along with declarative ones
+        for (element, elemaddr) in module.elements.iter().zip(&instance.elemaddrs) {
+            let (tableidx, offset) = match element {
+                Element::ActiveIndex(offset, _) | Element::ActiveExpression(offset, _) => {
+                    (0, offset)
+                }
+                Element::ActiveExplicitIndex(tableidx, offset, _, _)
+                | Element::ActiveExplicitExpression(tableidx, offset, _, _) => (*tableidx, offset),
+                Element::DeclarativeIndex(_, _) | Element::DeclarativeExpression(_, _) => {
+                    self.elems[*elemaddr] = ElemInst::default();
+                    continue;
+                }
+                Element::PassiveIndex(_, _) | Element::PassiveExpression(_, _) => continue,
+            };
+            let offset = self.eval_offset(offset, &globals, &instance)?;
+            let elements = std::mem::take(&mut self.elems[*elemaddr]).elements;
+            let table = &mut self.tables[addr(&instance.tableaddrs, tableidx, "unknown table")?];
+            let range = offset..offset + elements.len();
+            if range.end > table.elements.len() {
+                return Err(Trap::OutOfBoundsTableAccess.into());
+            }
+            table.elements.splice(range, elements);
+        }
+
+        // Apply active data segments and drop them
+        for (data, dataaddr) in module.data.iter().zip(&instance.dataaddrs) {
+            let (memidx, offset) = match data {
+                Data::Active(offset, _) => (0, offset),
+                Data::ActiveExplicit(memidx, offset, _) => (*memidx, offset),
+                Data::Passive(_) => continue,
+            };
+            let offset = self.eval_offset(offset, &globals, &instance)?;
+            let data = std::mem::take(&mut self.datas[*dataaddr]).data;
+            let memory = &mut self.memories[addr(&instance.memaddrs, memidx, "unknown memory")?];
+            memory.write(offset as u64, &data)?;
+        }
+
+        // Invoke the start function last
+        if let Some(start) = module.start {
+            self.invoke_validated(addr(&instance.funcaddrs, start, "unknown function")?, &[])?;
+        }
+
+        Ok(instance)
+    }
+
+    /// Check whether an external value can be used to satisfy an import
+    fn matches(&self, import: &Import, external: &Extern, types: &[DefType]) -> bool {
+        match (&import.descriptor, *external) {
+            (ImportDescriptor::Func(typeidx), Extern::Func(addr)) => {
+                self.func_matches(addr, types, *typeidx)
+            }
+            (ImportDescriptor::Table(tt), Extern::Table(addr)) => {
+                let table = &self.tables[addr];
+                table.et == tt.et
+                    && limits_match(
+                        table.size() as u64,
+                        table.max.map(|max| max as u64),
+                        &tt.lim,
+                    )
+            }
+            (ImportDescriptor::Mem(mt), Extern::Memory(addr)) => {
+                let memory = &self.memories[addr];
+                memory.shared().is_some() == mt.shared
+                    && memory.memory64 == mt.memory64
+                    && limits_match(memory.size(), memory.max, &mt.lim)
+            }
+            (ImportDescriptor::Global(gt), Extern::Global(addr)) => self.globals[addr].ty == *gt,
+            (ImportDescriptor::Tag(tt), Extern::Tag(addr)) => {
+                types.get(tt.typeidx as usize).and_then(DefType::func_type)
+                    == Some(&self.tags[addr].ty)
+            }
+            _ => false,
+        }
+    }
+
+    /// Evaluate a constant expression given the values of the globals it can refer to, allocating any objects it
+    /// creates on the store's heap
+    fn eval_const(
+        &mut self,
+        expr: &Expression,
+        globals: &[Val],
+        instance: &ModuleInst,
+    ) -> Result<Val, InstantiationError> {
+        const_eval::eval(expr, globals, instance, &mut self.heap)
+            .ok_or(InstantiationError::InvalidConstExpr)
+    }
+
+    /// Evaluate the offset of an active segment, which is an i64 for segments of 64-bit memories
+    fn eval_offset(
+        &mut self,
+        expr: &Expression,
+        globals: &[Val],
+        instance: &ModuleInst,
+    ) -> Result<usize, InstantiationError> {
+        match self.eval_const(expr, globals, instance)? {
+            Val::I32(offset) => Ok(offset as u32 as usize),
+            Val::I64(offset) => {
+                usize::try_from(offset as u64).map_err(|_| Trap::OutOfBoundsMemoryAccess.into())
+            }
+            _ => Err(InstantiationError::InvalidConstExpr),
+        }
+    }
+}
+
+/// Check whether the limits of an external table or memory are within the limits of an import
+fn limits_match(min: u64, max: Option<u64>, import: &Limits) -> bool {
+    min >= import.min
+        && match import.max {
+            None => true,
+            Some(import_max) => max.is_some_and(|max| max <= import_max),
+        }
+}
+
+/// Look up an address in one of an instance's index spaces, which an unvalidated module can index out of bounds
+fn addr(addrs: &[usize], idx: u32, error: &'static str) -> Result<usize, InstantiationError> {
+    addrs
+        .get(idx as usize)
+        .copied()
+        .ok_or(InstantiationError::Invalid(error))
+}
+
+/// Look up the function type at an index of an instance's type section
+fn func_type(instance: &ModuleInst, typeidx: u32) -> Result<&FuncType, InstantiationError> {
+    instance
+        .types
+        .get(typeidx as usize)
+        .and_then(DefType::func_type)
+        .ok_or(InstantiationError::Invalid("unknown type"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use crate::{
+        module::types::{FuncType, GlobalType, MemType, Mutability, NumType, RefType, TableType},
+        runtime::{limiter::StoreLimits, memory::PAGE_SIZE},
+        Decode,
+    };
+
+    use super::*;
+
+    // A module with a table of 1 funcref and a memory of 1 page
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x04, 0x04, 0x01, 0x70, 0x00, 0x01, 0x05,
+        0x03, 0x01, 0x00, 0x01,
+    ];
+
+    // A module that imports a start function and an i32 global, which it uses to initialize its own global and as
+    // the offset of a data segment. An element segment puts its own function into the second slot of its table
+    const SEGMENTS: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type
+        0x02, 0x16, 0x02, 0x03, 0x65, 0x6E, 0x76, 0x05, 0x73, 0x74, 0x61, 0x72, 0x74, 0x00, 0x00,
+        0x03, 0x65, 0x6E, 0x76, 0x01, 0x67, 0x03, 0x7F, 0x00, // import
+        0x03, 0x02, 0x01, 0x00, // function
+        0x04, 0x04, 0x01, 0x70, 0x00, 0x02, // table
+        0x05, 0x03, 0x01, 0x00, 0x01, // memory
+        0x06, 0x06, 0x01, 0x7F, 0x00, 0x23, 0x00, 0x0B, // global
+        0x08, 0x01, 0x00, // start
+        0x09, 0x07, 0x01, 0x00, 0x41, 0x01, 0x0B, 0x01, 0x01, // element
+        0x0A, 0x04, 0x01, 0x02, 0x00, 0x0B, // code
+        0x0B, 0x08, 0x01, 0x00, 0x23, 0x00, 0x0B, 0x02, 0xAA, 0xBB, // data
+    ];
+
+    fn imports(store: &mut Store, offset: i32, started: Rc<Cell<bool>>) -> Vec<Extern> {
+        let start = FuncType {
+            rt1: vec![],
+            rt2: vec![],
+        };
+        let global = GlobalType {
+            m: Mutability::Const,
+            t: ValType::NumType(NumType::I32),
+        };
+        vec![
+            Extern::Func(store.alloc_func(start, move |_| {
+                started.set(true);
+                Ok(vec![])
+            })),
+            Extern::Global(store.alloc_global(global, Val::I32(offset))),
+        ]
+    }
+
+    #[test]
+    fn test_instantiate() {
+        let (_, module) = Module::decode(MODULE).unwrap();
+        let mut store = Store::new();
+        let instance = store.instantiate(&module, &[]).unwrap();
+        assert_eq!(instance.tableaddrs, vec!(0));
+        assert_eq!(instance.memaddrs, vec!(0));
+        assert_eq!(store.memory_grow(0, 1), Ok(1));
+        assert_eq!(store.table_grow(0, 1, Val::FuncRef(None)), Ok(1));
+    }
+
+    #[test]
+    fn test_instantiate_segments() {
+        let (_, module) = Module::decode(SEGMENTS).unwrap();
+        let mut store = Store::new();
+        let started = Rc::new(Cell::new(false));
+        let imports = imports(&mut store, 3, started.clone());
+        let instance = store.instantiate(&module, &imports).unwrap();
+
+        assert!(started.get());
+        assert_eq!(store.globals[instance.globaladdrs[1]].value, Val::I32(3));
+        assert_eq!(
+            store.tables[instance.tableaddrs[0]].elements,
+            vec!(
+                Val::FuncRef(None),
+                Val::FuncRef(Some(FuncHandle(instance.funcaddrs[1])))
+            )
+        );
+        let memory = &store.memories[instance.memaddrs[0]];
+        let mut bytes = [0xFF; 4];
+        memory.read(2, &mut bytes).unwrap();
+        assert_eq!(bytes, [0x00, 0xAA, 0xBB, 0x00]);
+        assert!(store.elems[instance.elemaddrs[0]].elements.is_empty());
+        assert!(store.datas[instance.dataaddrs[0]].data.is_empty());
+    }
+
+    #[test]
+    fn test_instantiate_table_init() {
+        // A module with a table of two non-nullable function references initialized to ref.func 0
+        let bytes: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+            0x03, 0x02, 0x01, 0x00, 0x04, 0x0A, 0x01, 0x40, 0x00, 0x64, 0x70, 0x00, 0x02, 0xD2,
+            0x00, 0x0B, 0x0A, 0x04, 0x01, 0x02, 0x00, 0x0B,
+        ];
+        let (_, module) = Module::decode(bytes).unwrap();
+        let mut store = Store::new();
+        let instance = store.instantiate(&module, &[]).unwrap();
+        let func = Val::FuncRef(Some(FuncHandle(instance.funcaddrs[0])));
+        assert_eq!(
+            store.tables[instance.tableaddrs[0]].elements,
+            vec!(func.clone(), func)
+        );
+    }
+
+    #[test]
+    fn test_instantiate_out_of_bounds() {
+        let (_, module) = Module::decode(SEGMENTS).unwrap();
+        let mut store = Store::new();
+        let started = Rc::new(Cell::new(false));
+        let imports = imports(&mut store, PAGE_SIZE as i32 - 1, started.clone());
+        assert_eq!(
+            store.instantiate(&module, &imports),
+            Err(InstantiationError::Trap(Trap::OutOfBoundsMemoryAccess))
+        );
+        assert!(!started.get());
+    }
+
+    #[test]
+    fn test_instantiate_invalid() {
+        // Modules with a function of type () -> () and a start function, export or element segment that refers
+        // to a function, table or type that doesn't exist
+        let invalid: [&[u8]; 3] = [
+            &[0x08, 0x01, 0x01],
+            &[0x07, 0x05, 0x01, 0x01, 0x66, 0x00, 0x01],
+            &[0x09, 0x07, 0x01, 0x00, 0x41, 0x00, 0x0B, 0x01, 0x00],
+        ];
+        let errors = ["unknown function", "unknown function", "unknown table"];
+        for (sections, error) in invalid.iter().zip(errors) {
+            let mut bytes = vec![
+                0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x01, 0x04, 0x01, 0x60, 0x00, 0x00,
+                0x03, 0x02, 0x01, 0x00,
+            ];
+            bytes.extend(*sections);
+            bytes.extend([0x0A, 0x04, 0x01, 0x02, 0x00, 0x0B]);
+            let (_, module) = Module::decode(&bytes).unwrap();
+            assert_eq!(
+                Store::new().instantiate(&module, &[]),
+                Err(InstantiationError::Invalid(error))
+            );
+        }
+
+        // A function whose type index is out of range
+        let (_, module) = Module::decode(&[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x03, 0x02, 0x01, 0x05, 0x0A, 0x04,
+            0x01, 0x02, 0x00, 0x0B,
+        ])
+        .unwrap();
+        assert_eq!(
+            Store::new().instantiate(&module, &[]),
+            Err(InstantiationError::Invalid("unknown type"))
+        );
+    }
+
+    #[test]
+    fn test_instantiate_imports() {
+        let (_, module) = Module::decode(SEGMENTS).unwrap();
+        let mut store = Store::new();
+        let started = Rc::new(Cell::new(false));
+        let mut imports = imports(&mut store, 0, started);
+        assert_eq!(
+            store.instantiate(&module, &imports[..1]),
+            Err(InstantiationError::ImportCount {
+                expected: 2,
+                actual: 1
+            })
+        );
+
+        imports.swap(0, 1);
+        assert_eq!(
+            store.instantiate(&module, &imports),
+            Err(InstantiationError::IncompatibleImport {
+                module: "env".to_string(),
+                name: "start".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_limits_match() {
+        let lim = Limits {
+            min: 1,
+            max: Some(2),
+        };
+        assert!(limits_match(1, Some(2), &lim));
+        assert!(limits_match(2, Some(2), &lim));
+        assert!(!limits_match(0, Some(2), &lim));
+        assert!(!limits_match(1, Some(3), &lim));
+        assert!(!limits_match(1, None, &lim));
+        assert!(limits_match(1, None, &Limits { min: 1, max: None }));
+    }
+
+    #[test]
+    fn test_instantiate_limited() {
+        let (_, module) = Module::decode(MODULE).unwrap();
+
+        let mut store = Store::new();
+        store.limiter(StoreLimits {
+            memory_size: Some(0),
+            table_elements: None,
+        });
+        assert_eq!(
+            store.instantiate(&module, &[]),
+            Err(InstantiationError::Trap(Trap::ResourceLimitExceeded))
+        );
+
+        let mut store = Store::new();
+        store.limiter(StoreLimits {
+            memory_size: Some(PAGE_SIZE),
+            table_elements: Some(1),
+        });
+        assert!(store.instantiate(&module, &[]).is_ok());
+        assert_eq!(store.memory_grow(0, 1), Ok(-1));
+        assert_eq!(store.table_grow(0, 1, Val::FuncRef(None)), Ok(-1));
+    }
+
+    #[test]
+    fn test_instantiate_memory_import() {
+        // A module that imports a memory of at least 1 page
+        let bytes: &[u8] = &[
+            0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, 0x02, 0x0B, 0x01, 0x03, 0x65, 0x6E,
+            0x76, 0x03, 0x6D, 0x65, 0x6D, 0x02, 0x00, 0x01,
+        ];
+        let (_, module) = Module::decode(bytes).unwrap();
+        let mut store = Store::new();
+        let small = store
+            .alloc_memory(&MemType {
+                lim: Limits { min: 0, max: None },
+                shared: false,
+                memory64: false,
+            })
+            .unwrap();
+        let big = store
+            .alloc_memory(&MemType {
+                lim: Limits { min: 1, max: None },
+                shared: false,
+                memory64: false,
+            })
+            .unwrap();
+        let table = store
+            .alloc_table(&TableType {
+                lim: Limits { min: 1, max: None },
+                et: RefType::FUNCREF,
+            })
+            .unwrap();
+        assert!(store
+            .instantiate(&module, &[Extern::Memory(small)])
+            .is_err());
+        assert!(store.instantiate(&module, &[Extern::Table(table)]).is_err());
+        assert_eq!(
+            store
+                .instantiate(&module, &[Extern::Memory(big)])
+                .map(|instance| instance.memaddrs),
+            Ok(vec!(big))
+        );
+    }
+}
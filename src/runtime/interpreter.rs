@@ -0,0 +1,1620 @@
+use std::rc::Rc;
+
+use crate::{
+    instructions::{
+        BlockType, CatchClause, ControlInstruction, Instruction, MemArg, MemoryInstruction,
+        ParametricInstruction, ReferenceInstruction, TableInstruction, VariableInstruction,
+    },
+    module::{
+        indices::{MemIdx, TableIdx, TypeIdx},
+        types::FuncType,
+    },
+};
+
+use super::{
+    atomic,
+    code::{Code, Op},
+    function::FuncInst,
+    memory::MemInst,
+    numeric,
+    operands::{effective_address, pop, pop_addr, pop_f32, pop_f64, pop_i32, pop_i64, pop_ref},
+    segment::{DataInst, ElemInst},
+    store::{FuncAddr, InstanceAddr, Store},
+    trap::Trap,
+    values::{Exception, FuncHandle, Val},
+    vector,
+};
+
+/// The deepest that Wasm function calls can nest before trapping
+///
+/// Frames are kept on the heap rather than the host's stack, so this only bounds the memory that runaway recursion
+/// can use up.
+const MAX_DEPTH: usize = 1 << 16;
+
+/// Where control goes after executing an instruction
+enum Flow {
+    /// Fall through to the next instruction
+    Continue,
+    /// Branch to the label with the given index, relative to the innermost enclosing block
+    Branch(u32),
+    /// Return from the current function
+    Return,
+    /// Call a function with arguments from the operand stack
+    Call(FuncAddr),
+    /// Return from the current function by calling another with arguments from the operand stack
+    ReturnCall(FuncAddr),
+}
+
+/// The state of a single function call
+struct Frame {
+    code: Rc<Code>,
+    instance: InstanceAddr,
+    /// The position of the next op to execute
+    pc: usize,
+    /// Where the function's locals start on the value stack, which its operands follow
+    locals: usize,
+    /// Where the function's labels start on the label stack
+    labels: usize,
+    /// The number of results the function returns
+    arity: usize,
+}
+
+/// A block, loop, if or try_table that is being executed
+struct Label {
+    kind: LabelKind,
+    /// The height of the value stack below the parameters of the block
+    height: usize,
+    /// The number of values a branch to the label keeps
+    arity: usize,
+    /// The position a branch to the label continues at
+    target: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum LabelKind {
+    Block,
+    Loop,
+    /// A try_table, along with the position of its op, which holds its handlers
+    TryTable(usize),
+}
+
+/// Every frame and label of a call from the host into Wasm
+///
+/// The frames share one value stack, on which the locals of each function sit below its operands, so that it holds
+/// every value the running functions can reach.
+#[derive(Default)]
+struct Stack {
+    values: Vec<Val>,
+    frames: Vec<Frame>,
+    labels: Vec<Label>,
+}
+
+impl Stack {
+    /// Branch to a label of the current frame, returning its kind, or `None` if the label is the function body and
+    /// the branch is a return
+    fn branch(&mut self, labelidx: u32) -> Option<LabelKind> {
+        let frame = self.frames.last_mut().unwrap();
+        // The function body is the outermost label, below the ones on the label stack
+        if labelidx as usize >= self.labels.len() - frame.labels {
+            return None;
+        }
+        let i = self.labels.len() - 1 - labelidx as usize;
+        let label = &self.labels[i];
+        let kind = label.kind;
+        unwind(&mut self.values, label.height, label.arity);
+        frame.pc = label.target;
+        // A loop's label stays in place for the next iteration, while every other block is left
+        self.labels
+            .truncate(if kind == LabelKind::Loop { i + 1 } else { i });
+        Some(kind)
+    }
+
+    /// Pop the current frame, leaving its results on the value stack in place of its locals and operands
+    fn ret(&mut self) {
+        let frame = self.frames.pop().unwrap();
+        unwind(&mut self.values, frame.locals, frame.arity);
+        self.labels.truncate(frame.labels);
+    }
+}
+
+impl Store {
+    /// Call a function defined by a Wasm module, returning its results
+    pub(super) fn call_wasm(&mut self, addr: FuncAddr, args: Vec<Val>) -> Result<Vec<Val>, Trap> {
+        let mut stack = Stack {
+            values: args,
+            ..Stack::default()
+        };
+        self.enter(&mut stack, addr)?;
+        loop {
+            match self.execute(&mut stack) {
+                Err(Trap::Exception(exception)) => self.catch(&mut stack, exception)?,
+                result => return result.map(|()| stack.values),
+            }
+        }
+    }
+
+    /// Pop a function's arguments off the value stack and call it, returning whether it pushed a frame to execute
+    ///
+    /// Host functions run to completion straight away, leaving their results on the value stack.
+    fn enter(&mut self, stack: &mut Stack, addr: FuncAddr) -> Result<bool, Trap> {
+        self.check_interrupt()?;
+        let FuncInst::Wasm {
+            ty, instance, code, ..
+        } = &self.funcs[addr]
+        else {
+            let params = self.funcs[addr].ty().rt1.len();
+            let args = stack.values.split_off(stack.values.len() - params);
+            let results = self.invoke_validated(addr, &args)?;
+            stack.values.extend(results);
+            return Ok(false);
+        };
+        if stack.frames.len() == MAX_DEPTH {
+            return Err(Trap::CallStackExhausted);
+        }
+
+        let locals = stack.values.len() - ty.rt1.len();
+        for local in &code.locals {
+            let default = self.instances[*instance].default_value(&local.value_type);
+            stack
+                .values
+                .extend((0..local.count).map(|_| default.clone()));
+        }
+        stack.frames.push(Frame {
+            code: code.clone(),
+            instance: *instance,
+            pc: 0,
+            locals,
+            labels: stack.labels.len(),
+            arity: ty.rt2.len(),
+        });
+        Ok(true)
+    }
+
+    /// Execute the function on top of the stack, and every function it calls, until the bottom frame returns
+    fn execute(&mut self, stack: &mut Stack) -> Result<(), Trap> {
+        while let Some(frame) = stack.frames.last() {
+            // The frame on top only changes on a call or a return, so its code stays the same until then
+            let code = frame.code.clone();
+            loop {
+                let frame = stack.frames.last_mut().unwrap();
+                let op = &code.ops[frame.pc];
+                frame.pc += 1;
+                let flow = match op {
+                    Op::Instruction(instruction) => {
+                        self.instruction(frame, &mut stack.values, instruction)?
+                    }
+                    Op::Block(bt, end) => {
+                        let (params, results) = self.arity(frame.instance, bt);
+                        stack.labels.push(Label {
+                            kind: LabelKind::Block,
+                            height: stack.values.len() - params,
+                            arity: results,
+                            target: *end,
+                        });
+                        Flow::Continue
+                    }
+                    Op::Loop(bt) => {
+                        let (params, _) = self.arity(frame.instance, bt);
+                        stack.labels.push(Label {
+                            kind: LabelKind::Loop,
+                            height: stack.values.len() - params,
+                            arity: params,
+                            target: frame.pc,
+                        });
+                        Flow::Continue
+                    }
+                    Op::If(bt, otherwise, end) => {
+                        if pop_i32(&mut stack.values) == 0 {
+                            frame.pc = *otherwise;
+                        }
+                        let (params, results) = self.arity(frame.instance, bt);
+                        stack.labels.push(Label {
+                            kind: LabelKind::Block,
+                            height: stack.values.len() - params,
+                            arity: results,
+                            target: *end,
+                        });
+                        Flow::Continue
+                    }
+                    Op::Else(end) => {
+                        stack.labels.pop();
+                        frame.pc = *end;
+                        Flow::Continue
+                    }
+                    Op::TryTable(bt, _, end) => {
+                        let (params, results) = self.arity(frame.instance, bt);
+                        stack.labels.push(Label {
+                            kind: LabelKind::TryTable(frame.pc - 1),
+                            height: stack.values.len() - params,
+                            arity: results,
+                            target: *end,
+                        });
+                        Flow::Continue
+                    }
+                    Op::End => {
+                        stack.labels.pop();
+                        Flow::Continue
+                    }
+                    Op::Return => Flow::Return,
+                };
+
+                match flow {
+                    Flow::Continue => (),
+                    Flow::Branch(labelidx) => match stack.branch(labelidx) {
+                        Some(LabelKind::Loop) => self.check_interrupt()?,
+                        Some(_) => (),
+                        None => {
+                            stack.ret();
+                            break;
+                        }
+                    },
+                    Flow::Return => {
+                        stack.ret();
+                        break;
+                    }
+                    Flow::Call(addr) => {
+                        if self.enter(stack, addr)? {
+                            break;
+                        }
+                    }
+                    Flow::ReturnCall(addr) => {
+                        // The arguments take the place of the caller's locals, so tail calls don't grow the stack
+                        let params = self.funcs[addr].ty().rt1.len();
+                        let frame = stack.frames.pop().unwrap();
+                        unwind(&mut stack.values, frame.locals, params);
+                        stack.labels.truncate(frame.labels);
+                        // Host functions don't have a frame to replace, so a tail call to one is an ordinary call
+                        self.enter(stack, addr)?;
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Unwind to the innermost try_table with a handler for an exception and branch to the handler's label
+    ///
+    /// Exceptions unwind as `Trap::Exception` errors, so they cross Wasm frames and host functions alike on their way
+    /// to the nearest handler with a matching tag. If no frame catches the exception, it is returned as a trap.
+    fn catch(&self, stack: &mut Stack, exception: Rc<Exception>) -> Result<(), Trap> {
+        while let Some(frame) = stack.frames.last() {
+            let (code, instance, labels) = (frame.code.clone(), frame.instance, frame.labels);
+            let tagaddrs = &self.instances[instance].tagaddrs;
+            while stack.labels.len() > labels {
+                let label = stack.labels.pop().unwrap();
+                let LabelKind::TryTable(position) = label.kind else {
+                    continue;
+                };
+                let Op::TryTable(_, catches, _) = &code.ops[position] else {
+                    unreachable!("a try_table label refers to its op");
+                };
+                for catch in catches {
+                    let (labelidx, payload, reference) = match catch {
+                        CatchClause::Catch(tagidx, labelidx)
+                        | CatchClause::CatchRef(tagidx, labelidx)
+                            if tagaddrs[*tagidx as usize] != exception.tag =>
+                        {
+                            continue
+                        }
+                        CatchClause::Catch(_, labelidx) => (labelidx, true, false),
+                        CatchClause::CatchRef(_, labelidx) => (labelidx, true, true),
+                        CatchClause::CatchAll(labelidx) => (labelidx, false, false),
+                        CatchClause::CatchAllRef(labelidx) => (labelidx, false, true),
+                    };
+                    stack.values.truncate(label.height);
+                    if payload {
+                        stack.values.extend(exception.payload.iter().cloned());
+                    }
+                    if reference {
+                        stack.values.push(Val::ExnRef(Some(exception)));
+                    }
+                    // The try_table's own label is already popped, so handler labels are relative to its surroundings
+                    if stack.branch(*labelidx).is_none() {
+                        stack.ret();
+                    }
+                    return Ok(());
+                }
+            }
+            let frame = stack.frames.pop().unwrap();
+            stack.values.truncate(frame.locals);
+        }
+        Err(Trap::Exception(exception))
+    }
+
+    /// Execute an instruction that doesn't enter or leave a block
+    fn instruction(
+        &mut self,
+        frame: &Frame,
+        stack: &mut Vec<Val>,
+        instruction: &Instruction,
+    ) -> Result<Flow, Trap> {
+        match instruction {
+            Instruction::Control(instruction) => return self.control(frame, stack, instruction),
+            Instruction::Reference(instruction) => self.reference(frame, stack, instruction)?,
+            Instruction::Parametric(instruction) => parametric(stack, instruction),
+            Instruction::Variable(instruction) => self.variable(frame, stack, instruction),
+            Instruction::Table(instruction) => self.table(frame, stack, instruction)?,
+            Instruction::Numeric(instruction) => numeric::execute(instruction, stack)?,
+            Instruction::Saturating(instruction) => numeric::saturate(instruction, stack),
+            Instruction::Memory(instruction) => self.memory(frame, stack, instruction)?,
+            Instruction::Vector(instruction) => {
+                let memory = instruction.memarg().map(|m| self.mem(frame, m.memidx));
+                vector::execute(instruction, stack, memory)?
+            }
+            Instruction::Atomic(instruction) => {
+                let interrupt = self.interrupt_handle();
+                let access = instruction.access();
+                let memory = access.map(|(_, _, _, m)| self.mem(frame, m.memidx));
+                atomic::execute(instruction, stack, memory, &interrupt)?
+            }
+            Instruction::Gc(instruction) => {
+                if let Some(labelidx) = self.gc(frame.instance, stack, instruction)? {
+                    return Ok(Flow::Branch(labelidx));
+                }
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn control(
+        &mut self,
+        frame: &Frame,
+        stack: &mut Vec<Val>,
+        instruction: &ControlInstruction,
+    ) -> Result<Flow, Trap> {
+        match instruction {
+            ControlInstruction::Unreachable => return Err(Trap::Unreachable),
+            ControlInstruction::Nop => (),
+            ControlInstruction::Block(..)
+            | ControlInstruction::Loop(..)
+            | ControlInstruction::If(..)
+            | ControlInstruction::TryTable(..) => unreachable!("blocks are flattened into ops"),
+            ControlInstruction::Throw(tagidx) => {
+                let tag = self.instances[frame.instance].tagaddrs[*tagidx as usize];
+                let params = self.tags[tag].ty.rt1.len();
+                let payload = stack.split_off(stack.len() - params);
+                return Err(Trap::Exception(Rc::new(Exception { tag, payload })));
+            }
+            ControlInstruction::ThrowRef => match pop_ref(stack) {
+                Val::ExnRef(Some(exception)) => return Err(Trap::Exception(exception)),
+                _ => return Err(Trap::NullExceptionReference),
+            },
+            ControlInstruction::Br(labelidx) => return Ok(Flow::Branch(*labelidx)),
+            ControlInstruction::BrIf(labelidx) => {
+                if pop_i32(stack) != 0 {
+                    return Ok(Flow::Branch(*labelidx));
+                }
+            }
+            ControlInstruction::BrTable(labels, default) => {
+                let i = pop_i32(stack) as u32 as usize;
+                return Ok(Flow::Branch(*labels.get(i).unwrap_or(default)));
+            }
+            ControlInstruction::BrOnNull(labelidx) => {
+                let reference = pop_ref(stack);
+                if reference.is_null() {
+                    return Ok(Flow::Branch(*labelidx));
+                }
+                stack.push(reference);
+            }
+            ControlInstruction::BrOnNonNull(labelidx) => {
+                let reference = pop_ref(stack);
+                if !reference.is_null() {
+                    stack.push(reference);
+                    return Ok(Flow::Branch(*labelidx));
+                }
+            }
+            ControlInstruction::Return => return Ok(Flow::Return),
+            ControlInstruction::Call(funcidx) => {
+                let funcaddr = self.instances[frame.instance].funcaddrs[*funcidx as usize];
+                return Ok(Flow::Call(funcaddr));
+            }
+            ControlInstruction::CallIndirect(typeidx, tableidx) => {
+                let funcaddr = self.indirect(frame, stack, *typeidx, *tableidx)?;
+                return Ok(Flow::Call(funcaddr));
+            }
+            ControlInstruction::CallRef(_) => return Ok(Flow::Call(func_ref(stack)?)),
+            ControlInstruction::ReturnCall(funcidx) => {
+                let funcaddr = self.instances[frame.instance].funcaddrs[*funcidx as usize];
+                return Ok(Flow::ReturnCall(funcaddr));
+            }
+            ControlInstruction::ReturnCallIndirect(typeidx, tableidx) => {
+                let funcaddr = self.indirect(frame, stack, *typeidx, *tableidx)?;
+                return Ok(Flow::ReturnCall(funcaddr));
+            }
+            ControlInstruction::ReturnCallRef(_) => return Ok(Flow::ReturnCall(func_ref(stack)?)),
+        }
+        Ok(Flow::Continue)
+    }
+
+    /// Pop an index into a table and look up the function there, which must have the expected type
+    fn indirect(
+        &self,
+        frame: &Frame,
+        stack: &mut Vec<Val>,
+        typeidx: TypeIdx,
+        tableidx: TableIdx,
+    ) -> Result<FuncAddr, Trap> {
+        let instance = &self.instances[frame.instance];
+        let tableaddr = instance.tableaddrs[tableidx as usize];
+        let i = pop_i32(stack) as u32 as usize;
+        let funcaddr = match self.tables[tableaddr].elements.get(i) {
+            Some(Val::FuncRef(Some(FuncHandle(funcaddr)))) => *funcaddr,
+            Some(_) => return Err(Trap::UninitializedElement),
+            None => return Err(Trap::UndefinedElement),
+        };
+        if !self.func_matches(funcaddr, &instance.types, typeidx) {
+            return Err(Trap::IndirectCallTypeMismatch);
+        }
+        Ok(funcaddr)
+    }
+
+    /// The number of parameters and results of a block
+    fn arity(&self, instance: InstanceAddr, bt: &BlockType) -> (usize, usize) {
+        match bt {
+            BlockType::Empty => (0, 0),
+            BlockType::ValType(_) => (0, 1),
+            BlockType::TypeIdx(typeidx) => {
+                let FuncType { rt1, rt2 } = self.instances[instance].types[*typeidx as usize]
+                    .func_type()
+                    .expect("validated block type");
+                (rt1.len(), rt2.len())
+            }
+        }
+    }
+
+    fn reference(
+        &mut self,
+        frame: &Frame,
+        stack: &mut Vec<Val>,
+        instruction: &ReferenceInstruction,
+    ) -> Result<(), Trap> {
+        let result = match instruction {
+            ReferenceInstruction::Null(ht) => self.instances[frame.instance].null(ht),
+            ReferenceInstruction::IsNull => Val::I32(pop_ref(stack).is_null() as i32),
+            ReferenceInstruction::Func(funcidx) => {
+                let funcaddr = self.instances[frame.instance].funcaddrs[*funcidx as usize];
+                Val::FuncRef(Some(FuncHandle(funcaddr)))
+            }
+            ReferenceInstruction::Eq => {
+                let (b, a) = (pop_ref(stack), pop_ref(stack));
+                Val::I32((a == b) as i32)
+            }
+            ReferenceInstruction::AsNonNull => match pop_ref(stack) {
+                reference if reference.is_null() => return Err(Trap::NullReference),
+                reference => reference,
+            },
+        };
+        stack.push(result);
+        Ok(())
+    }
+
+    fn variable(&mut self, frame: &Frame, stack: &mut Vec<Val>, instruction: &VariableInstruction) {
+        match instruction {
+            VariableInstruction::LocalGet(localidx) => {
+                stack.push(stack[frame.locals + *localidx as usize].clone())
+            }
+            VariableInstruction::LocalSet(localidx) => {
+                let value = pop(stack);
+                stack[frame.locals + *localidx as usize] = value;
+            }
+            VariableInstruction::LocalTee(localidx) => {
+                let value = pop(stack);
+                stack[frame.locals + *localidx as usize] = value.clone();
+                stack.push(value);
+            }
+            VariableInstruction::GlobalGet(globalidx) => {
+                let globaladdr = self.instances[frame.instance].globaladdrs[*globalidx as usize];
+                stack.push(self.globals[globaladdr].value.clone());
+            }
+            VariableInstruction::GlobalSet(globalidx) => {
+                let globaladdr = self.instances[frame.instance].globaladdrs[*globalidx as usize];
+                self.globals[globaladdr].value = pop(stack);
+            }
+        }
+    }
+
+    fn table(
+        &mut self,
+        frame: &Frame,
+        stack: &mut Vec<Val>,
+        instruction: &TableInstruction,
+    ) -> Result<(), Trap> {
+        use TableInstruction::*;
+
+        let instance = &self.instances[frame.instance];
+        match instruction {
+            TableGet(x) => {
+                let i = pop_i32(stack) as u32;
+                stack.push(self.table_get(instance.tableaddrs[*x as usize], i)?);
+            }
+            TableSet(x) => {
+                let val = pop(stack);
+                let i = pop_i32(stack) as u32;
+                let table = &mut self.tables[instance.tableaddrs[*x as usize]];
+                let element = table
+                    .elements
+                    .get_mut(i as usize)
+                    .ok_or(Trap::OutOfBoundsTableAccess)?;
+                *element = val;
+            }
+            TableInit(y, x) => {
+                let elements = &self.elems[instance.elemaddrs[*y as usize]].elements;
+                let table = &mut self.tables[instance.tableaddrs[*x as usize]];
+                let n = pop_i32(stack) as u32 as usize;
+                let s = pop_i32(stack) as u32 as usize;
+                let d = pop_i32(stack) as u32 as usize;
+                let src = range(elements.len(), s, n)?;
+                let dst = range(table.elements.len(), d, n)?;
+                table.elements[dst].clone_from_slice(&elements[src]);
+            }
+            ElemDrop(y) => {
+                let elemaddr = instance.elemaddrs[*y as usize];
+                self.elems[elemaddr] = ElemInst::default();
+            }
+            TableCopy(x, y) => {
+                let (dst, src) = (
+                    instance.tableaddrs[*x as usize],
+                    instance.tableaddrs[*y as usize],
+                );
+                let n = pop_i32(stack) as u32 as usize;
+                let s = pop_i32(stack) as u32 as usize;
+                let d = pop_i32(stack) as u32 as usize;
+                // Check both ranges before copying anything, so that a trap leaves the tables untouched
+                let s = range(self.tables[src].elements.len(), s, n)?;
+                let d = range(self.tables[dst].elements.len(), d, n)?;
+                // The ranges can overlap within a single table, so copy through a buffer
+                let elements = self.tables[src].elements[s].to_vec();
+                self.tables[dst].elements[d].clone_from_slice(&elements);
+            }
+            TableGrow(x) => {
+                let tableaddr = instance.tableaddrs[*x as usize];
+                let n = pop_i32(stack) as u32;
+                let init = pop(stack);
+                let result = self.table_grow(tableaddr, n, init)?;
+                stack.push(Val::I32(result));
+            }
+            TableSize(x) => {
+                let table = &self.tables[instance.tableaddrs[*x as usize]];
+                stack.push(Val::I32(table.size() as i32));
+            }
+            TableFill(x) => {
+                let table = &mut self.tables[instance.tableaddrs[*x as usize]];
+                let n = pop_i32(stack) as u32 as usize;
+                let val = pop(stack);
+                let i = pop_i32(stack) as u32 as usize;
+                let range = range(table.elements.len(), i, n)?;
+                table.elements[range].fill(val);
+            }
+        }
+        Ok(())
+    }
+
+    fn memory(
+        &mut self,
+        frame: &Frame,
+        stack: &mut Vec<Val>,
+        instruction: &MemoryInstruction,
+    ) -> Result<(), Trap> {
+        use MemoryInstruction::*;
+
+        match instruction {
+            I32Load(m) => load(self.mem(frame, m.memidx), stack, m, 4, |n| {
+                Val::I32(n as i32)
+            })?,
+            I64Load(m) => load(self.mem(frame, m.memidx), stack, m, 8, |n| {
+                Val::I64(n as i64)
+            })?,
+            F32Load(m) => load(self.mem(frame, m.memidx), stack, m, 4, |n| {
+                Val::F32(f32::from_bits(n as u32))
+            })?,
+            F64Load(m) => load(self.mem(frame, m.memidx), stack, m, 8, |n| {
+                Val::F64(f64::from_bits(n))
+            })?,
+            I32Load8S(m) => load(self.mem(frame, m.memidx), stack, m, 1, |n| {
+                Val::I32(n as i8 as i32)
+            })?,
+            I32Load8U(m) => load(self.mem(frame, m.memidx), stack, m, 1, |n| {
+                Val::I32(n as i32)
+            })?,
+            I32Load16S(m) => load(self.mem(frame, m.memidx), stack, m, 2, |n| {
+                Val::I32(n as i16 as i32)
+            })?,
+            I32Load16U(m) => load(self.mem(frame, m.memidx), stack, m, 2, |n| {
+                Val::I32(n as i32)
+            })?,
+            I64Load8S(m) => load(self.mem(frame, m.memidx), stack, m, 1, |n| {
+                Val::I64(n as i8 as i64)
+            })?,
+            I64Load8U(m) => load(self.mem(frame, m.memidx), stack, m, 1, |n| {
+                Val::I64(n as i64)
+            })?,
+            I64Load16S(m) => load(self.mem(frame, m.memidx), stack, m, 2, |n| {
+                Val::I64(n as i16 as i64)
+            })?,
+            I64Load16U(m) => load(self.mem(frame, m.memidx), stack, m, 2, |n| {
+                Val::I64(n as i64)
+            })?,
+            I64Load32S(m) => load(self.mem(frame, m.memidx), stack, m, 4, |n| {
+                Val::I64(n as i32 as i64)
+            })?,
+            I64Load32U(m) => load(self.mem(frame, m.memidx), stack, m, 4, |n| {
+                Val::I64(n as i64)
+            })?,
+            I32Store(m) => {
+                let n = pop_i32(stack) as u64;
+                store(self.mem(frame, m.memidx), stack, m, &n.to_le_bytes()[..4])?
+            }
+            I64Store(m) => {
+                let n = pop_i64(stack) as u64;
+                store(self.mem(frame, m.memidx), stack, m, &n.to_le_bytes())?
+            }
+            F32Store(m) => {
+                let z = pop_f32(stack);
+                store(self.mem(frame, m.memidx), stack, m, &z.to_le_bytes())?
+            }
+            F64Store(m) => {
+                let z = pop_f64(stack);
+                store(self.mem(frame, m.memidx), stack, m, &z.to_le_bytes())?
+            }
+            I32Store8(m) | I64Store8(m) => {
+                let n = pop_wrapped(stack);
+                store(self.mem(frame, m.memidx), stack, m, &n.to_le_bytes()[..1])?
+            }
+            I32Store16(m) | I64Store16(m) => {
+                let n = pop_wrapped(stack);
+                store(self.mem(frame, m.memidx), stack, m, &n.to_le_bytes()[..2])?
+            }
+            I64Store32(m) => {
+                let n = pop_i64(stack);
+                store(self.mem(frame, m.memidx), stack, m, &n.to_le_bytes()[..4])?
+            }
+            MemorySize(memidx) => {
+                let memory = self.mem(frame, *memidx);
+                stack.push(addr(memory.memory64, memory.size()));
+            }
+            MemoryGrow(memidx) => {
+                let memaddr = self.instances[frame.instance].memaddrs[*memidx as usize];
+                let memory64 = self.memories[memaddr].memory64;
+                let delta = pop_addr(stack, memory64);
+                let result = self.memory_grow(memaddr, delta)?;
+                stack.push(addr(memory64, result as u64));
+            }
+            MemoryInit(dataidx, memidx) => {
+                let instance = &self.instances[frame.instance];
+                let data = &self.datas[instance.dataaddrs[*dataidx as usize]].data;
+                let memory = &mut self.memories[instance.memaddrs[*memidx as usize]];
+                let n = pop_i32(stack) as u32 as usize;
+                let s = pop_i32(stack) as u32 as usize;
+                let d = pop_addr(stack, memory.memory64);
+                let bytes = s
+                    .checked_add(n)
+                    .and_then(|end| data.get(s..end))
+                    .ok_or(Trap::OutOfBoundsMemoryAccess)?;
+                memory.write(d, bytes)?;
+            }
+            DataDrop(dataidx) => {
+                let dataaddr = self.instances[frame.instance].dataaddrs[*dataidx as usize];
+                self.datas[dataaddr] = DataInst::default();
+            }
+            MemoryCopy(dst, src) => {
+                let memaddrs = &self.instances[frame.instance].memaddrs;
+                let (dst, src) = (memaddrs[*dst as usize], memaddrs[*src as usize]);
+                let (dst64, src64) = (self.memories[dst].memory64, self.memories[src].memory64);
+                // The length can only be an i64 when both memories are 64-bit
+                let n = pop_addr(stack, dst64 && src64);
+                let s = pop_addr(stack, src64);
+                let d = pop_addr(stack, dst64);
+                if dst == src {
+                    self.memories[dst].copy_within(d, s, n)?;
+                } else {
+                    // Check both ranges before copying anything, so that a trap leaves the memories untouched
+                    self.memories[src].check(s, n)?;
+                    self.memories[dst].check(d, n)?;
+                    let mut bytes = vec![0; n as usize];
+                    self.memories[src].read(s, &mut bytes)?;
+                    self.memories[dst].write(d, &bytes)?;
+                }
+            }
+            MemoryFill(memidx) => {
+                let memory = self.mem(frame, *memidx);
+                let n = pop_addr(stack, memory.memory64);
+                let value = pop_i32(stack) as u8;
+                let d = pop_addr(stack, memory.memory64);
+                memory.fill(d, value, n)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The memory at an index of the current module
+    fn mem(&mut self, frame: &Frame, memidx: MemIdx) -> &mut MemInst {
+        &mut self.memories[self.instances[frame.instance].memaddrs[memidx as usize]]
+    }
+}
+
+/// The range of `n` elements of a table or element segment starting at `start`, trapping if it doesn't fit in `len`
+fn range(len: usize, start: usize, n: usize) -> Result<std::ops::Range<usize>, Trap> {
+    match start.checked_add(n) {
+        Some(end) if end <= len => Ok(start..end),
+        _ => Err(Trap::OutOfBoundsTableAccess),
+    }
+}
+
+/// Pop the address operand, add the static offset and load `width` bytes as a zero-extended integer
+fn load(
+    memory: &MemInst,
+    stack: &mut Vec<Val>,
+    memarg: &MemArg,
+    width: usize,
+    val: impl FnOnce(u64) -> Val,
+) -> Result<(), Trap> {
+    let ea = effective_address(stack, memarg, memory.memory64)?;
+    let mut bytes = [0; 8];
+    memory.read(ea, &mut bytes[..width])?;
+    stack.push(val(u64::from_le_bytes(bytes)));
+    Ok(())
+}
+
+/// Pop the address operand, add the static offset and store `bytes`
+fn store(
+    memory: &mut MemInst,
+    stack: &mut Vec<Val>,
+    memarg: &MemArg,
+    bytes: &[u8],
+) -> Result<(), Trap> {
+    let ea = effective_address(stack, memarg, memory.memory64)?;
+    memory.write(ea, bytes)
+}
+
+/// Pop an i32 or i64 operand of a narrow store, which only keeps its low bytes
+fn pop_wrapped(stack: &mut Vec<Val>) -> u64 {
+    match pop(stack) {
+        Val::I32(n) => n as u32 as u64,
+        Val::I64(n) => n as u64,
+        val => unreachable!("expected an integer operand, found {:?}", val),
+    }
+}
+
+/// An address, size or length of a memory as a value of the memory's address type
+fn addr(memory64: bool, n: u64) -> Val {
+    if memory64 {
+        Val::I64(n as i64)
+    } else {
+        Val::I32(n as i32)
+    }
+}
+
+fn parametric(stack: &mut Vec<Val>, instruction: &ParametricInstruction) {
+    match instruction {
+        ParametricInstruction::Drop => {
+            pop(stack);
+        }
+        ParametricInstruction::Select | ParametricInstruction::SelectTyped(_) => {
+            let c = pop_i32(stack);
+            let b = pop(stack);
+            let a = pop(stack);
+            stack.push(if c != 0 { a } else { b });
+        }
+    }
+}
+
+/// Pop the function reference of `call_ref` or `return_call_ref`, whose type validation has already checked
+fn func_ref(stack: &mut Vec<Val>) -> Result<FuncAddr, Trap> {
+    match pop_ref(stack) {
+        Val::FuncRef(Some(FuncHandle(funcaddr))) => Ok(funcaddr),
+        _ => Err(Trap::NullFunctionReference),
+    }
+}
+
+/// Keep the top `arity` values of the operand stack and discard everything else above `height`
+fn unwind(stack: &mut Vec<Val>, height: usize, arity: usize) {
+    let values = stack.split_off(stack.len() - arity);
+    stack.truncate(height);
+    stack.extend(values);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        module::types::{FuncType, Limits, MemType, NumType, ValType},
+        runtime::{
+            instance::{Extern, InstantiationError, ModuleInst},
+            limiter::StoreLimits,
+            memory::SharedMemory,
+            values::AnyRef,
+        },
+        Decode, Module,
+    };
+
+    use super::*;
+
+    // A module exporting:
+    //   pair: () -> (i32, i64), returning 1 and 2 from a block
+    //   split: (i32) -> (i32, i32), returning x >> 1 and x & 1 from a block that takes x as a parameter
+    //   fact: (i32) -> i32, computed recursively with an if
+    //   sum: (i32) -> i32, the sum of 1..=n computed by a loop that takes n as a parameter
+    //   deep: (i32) -> i32, which recurses forever
+    //   indirect: (i32) -> i32, calling the function at that index in a table of [fact, split, null] with 5
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x11, 0x03, 0x60, 0x00, 0x02, 0x7F, 0x7E, 0x60, 0x01, 0x7F, 0x02, 0x7F, 0x7F, 0x60,
+        0x01, 0x7F, 0x01, 0x7F, // type
+        0x03, 0x07, 0x06, 0x00, 0x01, 0x02, 0x02, 0x02, 0x02, // function
+        0x04, 0x04, 0x01, 0x70, 0x00, 0x03, // table
+        0x07, 0x2F, 0x06, 0x04, 0x70, 0x61, 0x69, 0x72, 0x00, 0x00, 0x05, 0x73, 0x70, 0x6C, 0x69,
+        0x74, 0x00, 0x01, 0x04, 0x66, 0x61, 0x63, 0x74, 0x00, 0x02, 0x03, 0x73, 0x75, 0x6D, 0x00,
+        0x03, 0x04, 0x64, 0x65, 0x65, 0x70, 0x00, 0x04, 0x08, 0x69, 0x6E, 0x64, 0x69, 0x72, 0x65,
+        0x63, 0x74, 0x00, 0x05, // export
+        0x09, 0x08, 0x01, 0x00, 0x41, 0x00, 0x0B, 0x02, 0x02, 0x01, // element
+        0x0A, 0x66, 0x06, // code
+        0x09, 0x00, 0x02, 0x00, 0x41, 0x01, 0x42, 0x02, 0x0B, 0x0B, // pair
+        0x13, 0x01, 0x01, 0x7F, 0x20, 0x00, 0x02, 0x01, 0x22, 0x01, 0x41, 0x01, 0x76, 0x20, 0x01,
+        0x41, 0x01, 0x71, 0x0B, 0x0B, // split
+        0x15, 0x00, 0x20, 0x00, 0x45, 0x04, 0x7F, 0x41, 0x01, 0x05, 0x20, 0x00, 0x20, 0x00, 0x41,
+        0x01, 0x6B, 0x10, 0x02, 0x6C, 0x0B, 0x0B, // fact
+        0x1F, 0x01, 0x01, 0x7F, 0x20, 0x00, 0x03, 0x02, 0x22, 0x00, 0x20, 0x01, 0x6A, 0x21, 0x01,
+        0x20, 0x00, 0x41, 0x01, 0x6B, 0x20, 0x00, 0x41, 0x01, 0x6B, 0x0D, 0x00, 0x0B, 0x1A, 0x20,
+        0x01, 0x0B, // sum
+        0x06, 0x00, 0x20, 0x00, 0x10, 0x04, 0x0B, // deep
+        0x09, 0x00, 0x41, 0x05, 0x20, 0x00, 0x11, 0x02, 0x00, 0x0B, // indirect
+    ];
+
+    // A module exporting nested: (i32) -> i32, which returns n by calling itself with n - 1 from inside six nested
+    // blocks and an if
+    const NESTED: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x06, 0x01, 0x60, 0x01, 0x7F, 0x01, 0x7F, // type
+        0x03, 0x02, 0x01, 0x00, // function
+        0x07, 0x0A, 0x01, 0x06, 0x6E, 0x65, 0x73, 0x74, 0x65, 0x64, 0x00, 0x00, // export
+        0x0A, 0x29, 0x01, // code
+        0x27, 0x00, 0x02, 0x7F, 0x02, 0x7F, 0x02, 0x7F, 0x02, 0x7F, 0x02, 0x7F, 0x02, 0x7F, 0x20,
+        0x00, 0x45, 0x04, 0x7F, 0x41, 0x00, 0x05, 0x20, 0x00, 0x41, 0x01, 0x6B, 0x10, 0x00, 0x41,
+        0x01, 0x6A, 0x0B, 0x0B, 0x0B, 0x0B, 0x0B, 0x0B, 0x0B, 0x0B, // nested
+    ];
+
+    // A module with a funcref table of 1 element and a passive element segment of 2 functions, exporting:
+    //   grow: (i32) -> i32, which grows the table by n null elements with table.grow
+    //   size: () -> i32, which returns table.size
+    //   init: (i32) -> (), which copies both elements of the segment to index d with table.init
+    //   is_null: (i32) -> i32, which checks whether the element at an index is null with table.get
+    //   fill: (i32) -> (), which sets the first n elements to null with table.fill
+    //   copy: () -> (), which copies element 0 to index 1 with table.copy
+    //   drop: () -> (), which drops the segment with elem.drop
+    const TABLES: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x11, 0x04, 0x60, 0x01, 0x7F, 0x01, 0x7F, 0x60, 0x00, 0x01, 0x7F, 0x60, 0x01, 0x7F,
+        0x00, 0x60, 0x00, 0x00, // type
+        0x03, 0x08, 0x07, 0x00, 0x01, 0x02, 0x00, 0x02, 0x03, 0x03, // function
+        0x04, 0x04, 0x01, 0x70, 0x00, 0x01, // table
+        0x07, 0x35, 0x07, 0x04, 0x67, 0x72, 0x6F, 0x77, 0x00, 0x00, 0x04, 0x73, 0x69, 0x7A, 0x65,
+        0x00, 0x01, 0x04, 0x69, 0x6E, 0x69, 0x74, 0x00, 0x02, 0x07, 0x69, 0x73, 0x5F, 0x6E, 0x75,
+        0x6C, 0x6C, 0x00, 0x03, 0x04, 0x66, 0x69, 0x6C, 0x6C, 0x00, 0x04, 0x04, 0x63, 0x6F, 0x70,
+        0x79, 0x00, 0x05, 0x04, 0x64, 0x72, 0x6F, 0x70, 0x00, 0x06, // export
+        0x09, 0x06, 0x01, 0x01, 0x00, 0x02, 0x01, 0x03, // element
+        0x0A, 0x45, 0x07, // code
+        0x09, 0x00, 0xD0, 0x70, 0x20, 0x00, 0xFC, 0x0F, 0x00, 0x0B, // grow
+        0x05, 0x00, 0xFC, 0x10, 0x00, 0x0B, // size
+        0x0C, 0x00, 0x20, 0x00, 0x41, 0x00, 0x41, 0x02, 0xFC, 0x0C, 0x00, 0x00, 0x0B, // init
+        0x07, 0x00, 0x20, 0x00, 0x25, 0x00, 0xD1, 0x0B, // is_null
+        0x0B, 0x00, 0x41, 0x00, 0xD0, 0x70, 0x20, 0x00, 0xFC, 0x11, 0x00, 0x0B, // fill
+        0x0C, 0x00, 0x41, 0x01, 0x41, 0x00, 0x41, 0x01, 0xFC, 0x0E, 0x00, 0x00, 0x0B, // copy
+        0x05, 0x00, 0xFC, 0x0D, 0x00, 0x0B, // drop
+    ];
+
+    // A module exporting sum: (i32, i64) -> i64, which adds 1..=n to an accumulator with a tail call, and
+    // sum_indirect, which does the same with a tail call through a table
+    const TAIL: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x07, 0x01, 0x60, 0x02, 0x7F, 0x7E, 0x01, 0x7E, // type
+        0x03, 0x03, 0x02, 0x00, 0x00, // function
+        0x04, 0x04, 0x01, 0x70, 0x00, 0x02, // table
+        0x07, 0x16, 0x02, 0x03, 0x73, 0x75, 0x6D, 0x00, 0x00, 0x0C, 0x73, 0x75, 0x6D, 0x5F, 0x69,
+        0x6E, 0x64, 0x69, 0x72, 0x65, 0x63, 0x74, 0x00, 0x01, // export
+        0x09, 0x08, 0x01, 0x00, 0x41, 0x00, 0x0B, 0x02, 0x00, 0x01, // element
+        0x0A, 0x36, 0x02, // code
+        0x18, 0x00, 0x20, 0x00, 0x45, 0x04, 0x40, 0x20, 0x01, 0x0F, 0x0B, 0x20, 0x00, 0x41, 0x01,
+        0x6B, 0x20, 0x01, 0x20, 0x00, 0xAD, 0x7C, 0x12, 0x00, 0x0B, // sum
+        0x1B, 0x00, 0x20, 0x00, 0x45, 0x04, 0x40, 0x20, 0x01, 0x0F, 0x0B, 0x20, 0x00, 0x41, 0x01,
+        0x6B, 0x20, 0x01, 0x20, 0x00, 0xAD, 0x7C, 0x41, 0x01, 0x13, 0x00, 0x00,
+        0x0B, // sum_indirect
+    ];
+
+    // A module that imports a tag carrying an i32 and a host function, and exports:
+    //   throw: (i32) -> (), which throws the tag
+    //   catch: (i32) -> i32, which calls throw and returns the payload it catches
+    //   rethrow: (i32) -> (), which calls throw, catches any exception as an exnref and rethrows it
+    //   catch_host: () -> i32, which returns 1 if the host function throws anything and 0 otherwise
+    const EXCEPTIONS: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x11, 0x04, 0x60, 0x01, 0x7F, 0x00, 0x60, 0x01, 0x7F, 0x01, 0x7F, 0x60, 0x00, 0x01,
+        0x7F, 0x60, 0x00, 0x00, // type
+        0x02, 0x17, 0x02, 0x03, 0x65, 0x6E, 0x76, 0x03, 0x74, 0x61, 0x67, 0x04, 0x00, 0x00, 0x03,
+        0x65, 0x6E, 0x76, 0x04, 0x68, 0x6F, 0x73, 0x74, 0x00, 0x03, // import
+        0x03, 0x05, 0x04, 0x00, 0x01, 0x00, 0x02, // function
+        0x07, 0x28, 0x04, 0x05, 0x74, 0x68, 0x72, 0x6F, 0x77, 0x00, 0x01, 0x05, 0x63, 0x61, 0x74,
+        0x63, 0x68, 0x00, 0x02, 0x07, 0x72, 0x65, 0x74, 0x68, 0x72, 0x6F, 0x77, 0x00, 0x03, 0x0A,
+        0x63, 0x61, 0x74, 0x63, 0x68, 0x5F, 0x68, 0x6F, 0x73, 0x74, 0x00, 0x04, // export
+        0x0A, 0x40, 0x04, // code
+        0x06, 0x00, 0x20, 0x00, 0x08, 0x00, 0x0B, // throw
+        0x12, 0x00, 0x02, 0x7F, 0x1F, 0x40, 0x01, 0x00, 0x00, 0x00, 0x20, 0x00, 0x10, 0x01, 0x0B,
+        0x41, 0x7F, 0x0B, 0x0B, // catch
+        0x11, 0x00, 0x02, 0x69, 0x1F, 0x40, 0x01, 0x03, 0x00, 0x20, 0x00, 0x10, 0x01, 0x0B, 0x00,
+        0x0B, 0x0A, 0x0B, // rethrow
+        0x12, 0x00, 0x02, 0x40, 0x1F, 0x40, 0x01, 0x02, 0x00, 0x10, 0x00, 0x0B, 0x41, 0x00, 0x0F,
+        0x0B, 0x41, 0x01, 0x0B, // catch_host
+    ];
+
+    // A module with a memory of 1 to 2 pages, initialized with [1, 2, 3, 4], exporting it as mem along with:
+    //   copy: () -> (), which copies 4 bytes from address 0 to address 8
+    //   load: (i32) -> i32, store: (i32, i32) -> (), which stores the low 2 bytes of its second argument at offset 4
+    //   grow: (i32) -> i32 and size: () -> i32
+    //   fill: () -> (), which sets 2 bytes at address 16 to 0xFF
+    //   init: () -> (), which copies the passive segment [0xAA, 0xBB] to address 24 and drops it
+    const MEMORY: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x12, 0x04, 0x60, 0x00, 0x00, 0x60, 0x01, 0x7F, 0x01, 0x7F, 0x60, 0x00, 0x01, 0x7F,
+        0x60, 0x02, 0x7F, 0x7F, 0x00, // type
+        0x03, 0x08, 0x07, 0x00, 0x01, 0x01, 0x02, 0x00, 0x00, 0x03, // function
+        0x05, 0x04, 0x01, 0x01, 0x01, 0x02, // memory
+        0x07, 0x39, 0x08, 0x03, 0x6D, 0x65, 0x6D, 0x02, 0x00, 0x04, 0x63, 0x6F, 0x70, 0x79, 0x00,
+        0x00, 0x04, 0x6C, 0x6F, 0x61, 0x64, 0x00, 0x01, 0x04, 0x67, 0x72, 0x6F, 0x77, 0x00, 0x02,
+        0x04, 0x73, 0x69, 0x7A, 0x65, 0x00, 0x03, 0x04, 0x66, 0x69, 0x6C, 0x6C, 0x00, 0x04, 0x04,
+        0x69, 0x6E, 0x69, 0x74, 0x00, 0x05, 0x05, 0x73, 0x74, 0x6F, 0x72, 0x65, 0x00,
+        0x06, // export
+        0x0C, 0x01, 0x02, // data count
+        0x0A, 0x4A, 0x07, // code
+        0x0C, 0x00, 0x41, 0x08, 0x41, 0x00, 0x41, 0x04, 0xFC, 0x0A, 0x00, 0x00, 0x0B, // copy
+        0x07, 0x00, 0x20, 0x00, 0x28, 0x02, 0x00, 0x0B, // load
+        0x06, 0x00, 0x20, 0x00, 0x40, 0x00, 0x0B, // grow
+        0x04, 0x00, 0x3F, 0x00, 0x0B, // size
+        0x0C, 0x00, 0x41, 0x10, 0x41, 0xFF, 0x01, 0x41, 0x02, 0xFC, 0x0B, 0x00, 0x0B, // fill
+        0x0F, 0x00, 0x41, 0x18, 0x41, 0x00, 0x41, 0x02, 0xFC, 0x08, 0x01, 0x00, 0xFC, 0x09, 0x01,
+        0x0B, // init
+        0x0A, 0x00, 0x20, 0x00, 0x20, 0x01, 0xAD, 0x3D, 0x01, 0x04, 0x0B, // store
+        0x0B, 0x0E, 0x02, 0x00, 0x41, 0x00, 0x0B, 0x04, 0x01, 0x02, 0x03, 0x04, 0x01, 0x02, 0xAA,
+        0xBB, // data
+    ];
+
+    // A module that imports interrupt: () -> () and exports spin: () -> (), which calls it in an endless loop
+    const SPIN: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type
+        0x02, 0x11, 0x01, 0x03, 0x65, 0x6E, 0x76, 0x09, 0x69, 0x6E, 0x74, 0x65, 0x72, 0x72, 0x75,
+        0x70, 0x74, 0x00, 0x00, // import
+        0x03, 0x02, 0x01, 0x00, // function
+        0x07, 0x08, 0x01, 0x04, 0x73, 0x70, 0x69, 0x6E, 0x00, 0x01, // export
+        0x0A, 0x0B, 0x01, // code
+        0x09, 0x00, 0x03, 0x40, 0x10, 0x00, 0x0C, 0x00, 0x0B, 0x0B, // spin
+    ];
+
+    // A module that imports memory 0 and defines memory 1 of 1 to 2 pages, initialized with [1, 2, 3, 4], exporting
+    // it as mem1 along with:
+    //   copy: () -> (), which copies 4 bytes from address 0 of memory 1 to address 0 of memory 0
+    //   load0: (i32) -> i32 and load1: (i32) -> i32, which load from memory 0 and memory 1
+    //   grow1: (i32) -> i32 and size1: () -> i32, which grow and measure memory 1
+    //   fill: () -> (), which sets 2 bytes at address 8 of memory 0 to 0xFF
+    //   init: () -> (), which copies the passive segment [0xAA, 0xBB] to address 16 of memory 0 and drops it
+    const MULTI: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x0D, 0x03, 0x60, 0x00, 0x00, 0x60, 0x01, 0x7F, 0x01, 0x7F, 0x60, 0x00, 0x01,
+        0x7F, // type
+        0x02, 0x0F, 0x01, 0x03, 0x65, 0x6E, 0x76, 0x06, 0x6D, 0x65, 0x6D, 0x6F, 0x72, 0x79, 0x02,
+        0x00, 0x01, // import
+        0x03, 0x08, 0x07, 0x00, 0x01, 0x01, 0x01, 0x02, 0x00, 0x00, // function
+        0x05, 0x04, 0x01, 0x01, 0x01, 0x02, // memory
+        0x07, 0x3D, 0x08, 0x04, 0x63, 0x6F, 0x70, 0x79, 0x00, 0x00, 0x05, 0x6C, 0x6F, 0x61, 0x64,
+        0x30, 0x00, 0x01, 0x05, 0x6C, 0x6F, 0x61, 0x64, 0x31, 0x00, 0x02, 0x05, 0x67, 0x72, 0x6F,
+        0x77, 0x31, 0x00, 0x03, 0x05, 0x73, 0x69, 0x7A, 0x65, 0x31, 0x00, 0x04, 0x04, 0x66, 0x69,
+        0x6C, 0x6C, 0x00, 0x05, 0x04, 0x69, 0x6E, 0x69, 0x74, 0x00, 0x06, 0x04, 0x6D, 0x65, 0x6D,
+        0x31, 0x02, 0x01, // export
+        0x0C, 0x01, 0x02, // data count
+        0x0A, 0x47, 0x07, // code
+        0x0C, 0x00, 0x41, 0x00, 0x41, 0x00, 0x41, 0x04, 0xFC, 0x0A, 0x00, 0x01, 0x0B, // copy
+        0x07, 0x00, 0x20, 0x00, 0x28, 0x02, 0x00, 0x0B, // load0
+        0x08, 0x00, 0x20, 0x00, 0x28, 0x42, 0x01, 0x00, 0x0B, // load1
+        0x06, 0x00, 0x20, 0x00, 0x40, 0x01, 0x0B, // grow1
+        0x04, 0x00, 0x3F, 0x01, 0x0B, // size1
+        0x0B, 0x00, 0x41, 0x08, 0x41, 0x7F, 0x41, 0x02, 0xFC, 0x0B, 0x00, 0x0B, // fill
+        0x0F, 0x00, 0x41, 0x10, 0x41, 0x00, 0x41, 0x02, 0xFC, 0x08, 0x01, 0x00, 0xFC, 0x09, 0x01,
+        0x0B, // init
+        0x0B, 0x0F, 0x02, 0x02, 0x01, 0x41, 0x00, 0x0B, 0x04, 0x01, 0x02, 0x03, 0x04, 0x01, 0x02,
+        0xAA, 0xBB, // data
+    ];
+
+    // A module with a 64-bit memory 0 of 1 to 2 pages holding 0x2A at address 16, and a 32-bit memory 1, exporting:
+    //   store: (i64, i64) -> () and load: (i64) -> i64, which access i64s in memory 0
+    //   load_far: (i64) -> i64, which loads with a static offset of 4GiB
+    //   size: () -> i64 and grow: (i64) -> i64, which measure and grow memory 0
+    //   copy: () -> i32, which copies 8 bytes from memory 0 to memory 1 and loads the first 4 from memory 1
+    //   fill: () -> (), which sets 4 bytes at address 8 of memory 0 to 0xAB
+    const MEMORY64: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x16, 0x05, 0x60, 0x02, 0x7E, 0x7E, 0x00, 0x60, 0x01, 0x7E, 0x01, 0x7E, 0x60, 0x00,
+        0x01, 0x7E, 0x60, 0x00, 0x01, 0x7F, 0x60, 0x00, 0x00, // type
+        0x03, 0x08, 0x07, 0x00, 0x01, 0x01, 0x02, 0x01, 0x03, 0x04, // function
+        0x05, 0x06, 0x02, 0x05, 0x01, 0x02, 0x00, 0x01, // memory
+        0x07, 0x37, 0x07, 0x05, 0x73, 0x74, 0x6F, 0x72, 0x65, 0x00, 0x00, 0x04, 0x6C, 0x6F, 0x61,
+        0x64, 0x00, 0x01, 0x08, 0x6C, 0x6F, 0x61, 0x64, 0x5F, 0x66, 0x61, 0x72, 0x00, 0x02, 0x04,
+        0x73, 0x69, 0x7A, 0x65, 0x00, 0x03, 0x04, 0x67, 0x72, 0x6F, 0x77, 0x00, 0x04, 0x04, 0x63,
+        0x6F, 0x70, 0x79, 0x00, 0x05, 0x04, 0x66, 0x69, 0x6C, 0x6C, 0x00, 0x06, // export
+        0x0A, 0x4B, 0x07, // code
+        0x09, 0x00, 0x20, 0x00, 0x20, 0x01, 0x37, 0x03, 0x00, 0x0B, // store
+        0x07, 0x00, 0x20, 0x00, 0x29, 0x03, 0x00, 0x0B, // load
+        0x0B, 0x00, 0x20, 0x00, 0x29, 0x03, 0x80, 0x80, 0x80, 0x80, 0x10, 0x0B, // load_far
+        0x04, 0x00, 0x3F, 0x00, 0x0B, // size
+        0x06, 0x00, 0x20, 0x00, 0x40, 0x00, 0x0B, // grow
+        0x12, 0x00, 0x41, 0x00, 0x42, 0x00, 0x41, 0x08, 0xFC, 0x0A, 0x01, 0x00, 0x41, 0x00, 0x28,
+        0x42, 0x01, 0x00, 0x0B, // copy
+        0x0C, 0x00, 0x42, 0x08, 0x41, 0xAB, 0x01, 0x42, 0x04, 0xFC, 0x0B, 0x00, 0x0B, // fill
+        0x0B, 0x07, 0x01, 0x00, 0x42, 0x10, 0x0B, 0x01, 0x2A, // data
+    ];
+
+    // A module that imports a shared memory and exports add: (i32) -> i32, which atomically adds its argument to
+    // the i32 at address 0 and returns the previous value
+    const ATOMIC: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x06, 0x01, 0x60, 0x01, 0x7F, 0x01, 0x7F, // type
+        0x02, 0x10, 0x01, 0x03, 0x65, 0x6E, 0x76, 0x06, 0x6D, 0x65, 0x6D, 0x6F, 0x72, 0x79, 0x02,
+        0x03, 0x01, 0x01, // import
+        0x03, 0x02, 0x01, 0x00, // function
+        0x07, 0x07, 0x01, 0x03, 0x61, 0x64, 0x64, 0x00, 0x00, // export
+        0x0A, 0x0C, 0x01, // code
+        0x0A, 0x00, 0x41, 0x00, 0x20, 0x00, 0xFE, 0x1E, 0x02, 0x00, 0x0B, // add
+    ];
+
+    // A module with a function type t = (i32) -> i32 and a declared reference to double, exporting:
+    //   double: (i32) -> i32, which returns twice its argument
+    //   apply: ((ref null t), i32) -> i32, which calls the reference with call_ref
+    //   tail: (i32) -> i32, which calls a reference to double with return_call_ref
+    //   or_default: ((ref null t), i32) -> i32, which returns the argument unchanged if the reference is null
+    //   apply_non_null: ((ref null t), i32) -> i32, which converts the reference with ref.as_non_null before calling it
+    //   local: (i32) -> i32, which calls double through a non-nullable local
+    //   is_func: ((ref null t)) -> i32, which uses br_on_non_null to return whether the reference is not null
+    const FUNCREFS: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x13, 0x03, 0x60, 0x01, 0x7F, 0x01, 0x7F, 0x60, 0x02, 0x63, 0x00, 0x7F, 0x01, 0x7F,
+        0x60, 0x01, 0x63, 0x00, 0x01, 0x7F, // type
+        0x03, 0x08, 0x07, 0x00, 0x01, 0x00, 0x01, 0x01, 0x00, 0x02, // function
+        0x07, 0x49, 0x07, 0x06, 0x64, 0x6F, 0x75, 0x62, 0x6C, 0x65, 0x00, 0x00, 0x05, 0x61, 0x70,
+        0x70, 0x6C, 0x79, 0x00, 0x01, 0x04, 0x74, 0x61, 0x69, 0x6C, 0x00, 0x02, 0x0A, 0x6F, 0x72,
+        0x5F, 0x64, 0x65, 0x66, 0x61, 0x75, 0x6C, 0x74, 0x00, 0x03, 0x0E, 0x61, 0x70, 0x70, 0x6C,
+        0x79, 0x5F, 0x6E, 0x6F, 0x6E, 0x5F, 0x6E, 0x75, 0x6C, 0x6C, 0x00, 0x04, 0x05, 0x6C, 0x6F,
+        0x63, 0x61, 0x6C, 0x00, 0x05, 0x07, 0x69, 0x73, 0x5F, 0x66, 0x75, 0x6E, 0x63, 0x00,
+        0x06, // export
+        0x09, 0x05, 0x01, 0x03, 0x00, 0x01, 0x00, // element
+        0x0A, 0x54, 0x07, // code
+        0x07, 0x00, 0x20, 0x00, 0x41, 0x02, 0x6C, 0x0B, // double
+        0x08, 0x00, 0x20, 0x01, 0x20, 0x00, 0x14, 0x00, 0x0B, // apply
+        0x08, 0x00, 0x20, 0x00, 0xD2, 0x00, 0x15, 0x00, 0x0B, // tail
+        0x0D, 0x00, 0x02, 0x7F, 0x20, 0x01, 0x20, 0x00, 0xD5, 0x00, 0x14, 0x00, 0x0B,
+        0x0B, // or_default
+        0x09, 0x00, 0x20, 0x01, 0x20, 0x00, 0xD4, 0x14, 0x00, 0x0B, // apply_non_null
+        0x0F, 0x01, 0x01, 0x64, 0x00, 0xD2, 0x00, 0x21, 0x01, 0x20, 0x00, 0x20, 0x01, 0x14, 0x00,
+        0x0B, // local
+        0x10, 0x00, 0x02, 0x64, 0x00, 0x20, 0x00, 0xD6, 0x00, 0x41, 0x00, 0x0F, 0x0B, 0x1A, 0x41,
+        0x01, 0x0B, // is_func
+    ];
+
+    // A module with types pair = (struct (mut i32) (mut i32)) and bytes = (array (mut i8)), exporting:
+    //   new_pair: (i32, i32) -> (ref pair), which allocates a pair with struct.new
+    //   pair_diff: (i32, i32) -> i32, which allocates a pair and subtracts its second field from its first
+    //   bytes: (i32) -> i32, which allocates an array of n bytes set to 511 and adds array.get_u of its first
+    //     element to array.len
+    //   classify: (anyref) -> i32, which uses br_on_cast to return the first field of a pair, or -1 otherwise
+    //   cast: (anyref) -> i32, which converts the reference with ref.cast to an i31ref and returns i31.get_s
+    const GC: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x21, 0x06, 0x5F, 0x02, 0x7F, 0x01, 0x7F, 0x01, 0x5E, 0x78, 0x01, 0x60, 0x02, 0x7F,
+        0x7F, 0x01, 0x7F, 0x60, 0x01, 0x7F, 0x01, 0x7F, 0x60, 0x01, 0x6E, 0x01, 0x7F, 0x60, 0x02,
+        0x7F, 0x7F, 0x01, 0x64, 0x00, // type
+        0x03, 0x06, 0x05, 0x05, 0x02, 0x03, 0x04, 0x04, // function
+        0x07, 0x32, 0x05, 0x08, 0x6E, 0x65, 0x77, 0x5F, 0x70, 0x61, 0x69, 0x72, 0x00, 0x00, 0x09,
+        0x70, 0x61, 0x69, 0x72, 0x5F, 0x64, 0x69, 0x66, 0x66, 0x00, 0x01, 0x05, 0x62, 0x79, 0x74,
+        0x65, 0x73, 0x00, 0x02, 0x08, 0x63, 0x6C, 0x61, 0x73, 0x73, 0x69, 0x66, 0x79, 0x00, 0x03,
+        0x04, 0x63, 0x61, 0x73, 0x74, 0x00, 0x04, // export
+        0x0A, 0x62, 0x05, // code
+        0x09, 0x00, 0x20, 0x00, 0x20, 0x01, 0xFB, 0x00, 0x00, 0x0B, // new_pair
+        0x19, 0x01, 0x01, 0x63, 0x00, 0x20, 0x00, 0x20, 0x01, 0xFB, 0x00, 0x00, 0x22, 0x02, 0xFB,
+        0x02, 0x00, 0x00, 0x20, 0x02, 0xFB, 0x02, 0x00, 0x01, 0x6B, 0x0B, // pair_diff
+        0x1B, 0x01, 0x01, 0x63, 0x01, 0x41, 0xFF, 0x03, 0x20, 0x00, 0xFB, 0x06, 0x01, 0x21, 0x01,
+        0x20, 0x01, 0x41, 0x00, 0xFB, 0x0D, 0x01, 0x20, 0x01, 0xFB, 0x0F, 0x6A, 0x0B, // bytes
+        0x16, 0x00, 0x02, 0x64, 0x00, 0x20, 0x00, 0xFB, 0x18, 0x01, 0x00, 0x6E, 0x00, 0x1A, 0x41,
+        0x7F, 0x0F, 0x0B, 0xFB, 0x02, 0x00, 0x00, 0x0B, // classify
+        0x09, 0x00, 0x20, 0x00, 0xFB, 0x16, 0x6C, 0xFB, 0x1D, 0x0B, // cast
+    ];
+
+    // A module with type s = (struct (mut anyref)), exporting churn: (i32) -> i32, which makes a struct that refers to
+    // itself and keeps it in a local, then allocates n more such structs as garbage, and returns 1 if the first
+    // struct's field is still set
+    const ROOTS: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x0A, 0x02, 0x5F, 0x01, 0x6E, 0x01, 0x60, 0x01, 0x7F, 0x01, 0x7F, // type
+        0x03, 0x02, 0x01, 0x01, // function
+        0x07, 0x09, 0x01, 0x05, 0x63, 0x68, 0x75, 0x72, 0x6E, 0x00, 0x00, // export
+        0x0A, 0x33, 0x01, // code
+        0x31, 0x01, 0x02, 0x63, 0x00, 0xFB, 0x01, 0x00, 0x21, 0x01, 0x20, 0x01, 0x20, 0x01, 0xFB,
+        0x05, 0x00, 0x00, 0x03, 0x40, 0xFB, 0x01, 0x00, 0x22, 0x02, 0x20, 0x02, 0xFB, 0x05, 0x00,
+        0x00, 0x20, 0x00, 0x41, 0x01, 0x6B, 0x22, 0x00, 0x0D, 0x00, 0x0B, 0x20, 0x01, 0xFB, 0x02,
+        0x00, 0x00, 0xD1, 0x45, 0x0B, // churn
+    ];
+
+    fn instantiate(bytes: &[u8]) -> (Store, ModuleInst) {
+        let mut store = Store::new();
+        let instance = instantiate_with(&mut store, bytes, &[]).unwrap();
+        (store, instance)
+    }
+
+    fn instantiate_with(
+        store: &mut Store,
+        bytes: &[u8],
+        imports: &[Extern],
+    ) -> Result<ModuleInst, InstantiationError> {
+        let (_, module) = Module::decode(bytes).unwrap();
+        assert_eq!(module.validate(), Ok(()));
+        store.instantiate(&module, imports)
+    }
+
+    fn func(instance: &ModuleInst, name: &str) -> FuncAddr {
+        match instance.export(name) {
+            Some(Extern::Func(addr)) => addr,
+            _ => panic!("expected a function export named {}", name),
+        }
+    }
+
+    #[test]
+    fn test_multi_value() {
+        let (mut store, instance) = instantiate(MODULE);
+        assert_eq!(
+            store.invoke(func(&instance, "pair"), &[]),
+            Ok(vec!(Val::I32(1), Val::I64(2)))
+        );
+        assert_eq!(
+            store.call::<_, (i32, i32)>(func(&instance, "split"), 7),
+            Ok((3, 1))
+        );
+        assert_eq!(store.call::<_, i32>(func(&instance, "sum"), 4), Ok(10));
+    }
+
+    #[test]
+    fn test_calls() {
+        let (mut store, instance) = instantiate(MODULE);
+        assert_eq!(store.call::<_, i32>(func(&instance, "fact"), 5), Ok(120));
+        assert_eq!(
+            store.call::<_, i32>(func(&instance, "deep"), 0),
+            Err(Trap::CallStackExhausted)
+        );
+        // Nothing is left over from a trap, so later calls still succeed
+        assert_eq!(store.call::<_, i32>(func(&instance, "fact"), 3), Ok(6));
+
+        let indirect = func(&instance, "indirect");
+        assert_eq!(store.call::<_, i32>(indirect, 0), Ok(120));
+        assert_eq!(
+            store.call::<_, i32>(indirect, 1),
+            Err(Trap::IndirectCallTypeMismatch)
+        );
+        assert_eq!(
+            store.call::<_, i32>(indirect, 2),
+            Err(Trap::UninitializedElement)
+        );
+        assert_eq!(
+            store.call::<_, i32>(indirect, 3),
+            Err(Trap::UndefinedElement)
+        );
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let (_, module) = Module::decode(SPIN).unwrap();
+        assert_eq!(module.validate(), Ok(()));
+        let mut store = Store::new();
+        let handle = store.interrupt_handle();
+        let ty = FuncType {
+            rt1: vec![],
+            rt2: vec![],
+        };
+        let interrupt = store.alloc_func(ty, move |_| {
+            handle.interrupt();
+            Ok(vec![])
+        });
+        let instance = store
+            .instantiate(&module, &[Extern::Func(interrupt)])
+            .unwrap();
+
+        // The loop traps at its first back-edge after the host function requests the interruption
+        assert_eq!(
+            store.invoke(func(&instance, "spin"), &[]),
+            Err(Trap::Interrupted)
+        );
+    }
+
+    #[test]
+    fn test_deep_recursion() {
+        // Calls and blocks don't recurse on the host's stack, so even a small thread runs out of frames first
+        std::thread::Builder::new()
+            .stack_size(2 << 20)
+            .spawn(|| {
+                let (mut store, instance) = instantiate(NESTED);
+                let nested = func(&instance, "nested");
+                assert_eq!(store.call::<_, i32>(nested, 50_000), Ok(50_000));
+                assert_eq!(
+                    store.call::<_, i32>(nested, 100_000),
+                    Err(Trap::CallStackExhausted)
+                );
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_tables() {
+        let (_, module) = Module::decode(TABLES).unwrap();
+        assert_eq!(module.validate(), Ok(()));
+        let mut store = Store::new();
+        store.limiter(StoreLimits {
+            memory_size: None,
+            table_elements: Some(3),
+        });
+        let instance = store.instantiate(&module, &[]).unwrap();
+        let mut call = |name, arg: Option<i32>| {
+            let args: Vec<Val> = arg.into_iter().map(Val::I32).collect();
+            store.invoke(func(&instance, name), &args)
+        };
+
+        assert_eq!(call("size", None), Ok(vec!(Val::I32(1))));
+        assert_eq!(call("grow", Some(1)), Ok(vec!(Val::I32(1))));
+        // The table has no maximum of its own, but the limiter caps it at 3 elements
+        assert_eq!(call("grow", Some(5)), Ok(vec!(Val::I32(-1))));
+        assert_eq!(call("size", None), Ok(vec!(Val::I32(2))));
+        assert_eq!(call("is_null", Some(1)), Ok(vec!(Val::I32(1))));
+
+        assert_eq!(call("init", Some(0)), Ok(vec!()));
+        assert_eq!(call("is_null", Some(0)), Ok(vec!(Val::I32(0))));
+        assert_eq!(call("is_null", Some(1)), Ok(vec!(Val::I32(0))));
+        assert_eq!(call("init", Some(1)), Err(Trap::OutOfBoundsTableAccess));
+        assert_eq!(call("fill", Some(1)), Ok(vec!()));
+        assert_eq!(call("is_null", Some(0)), Ok(vec!(Val::I32(1))));
+        assert_eq!(call("copy", None), Ok(vec!()));
+        assert_eq!(call("is_null", Some(1)), Ok(vec!(Val::I32(1))));
+        assert_eq!(call("is_null", Some(2)), Err(Trap::OutOfBoundsTableAccess));
+        assert_eq!(call("fill", Some(3)), Err(Trap::OutOfBoundsTableAccess));
+
+        // The segment is empty once dropped, so it no longer has 2 elements to copy
+        assert_eq!(call("drop", None), Ok(vec!()));
+        assert_eq!(call("init", Some(0)), Err(Trap::OutOfBoundsTableAccess));
+
+        assert_eq!(call("grow", Some(1)), Ok(vec!(Val::I32(2))));
+        assert_eq!(call("grow", Some(1)), Ok(vec!(Val::I32(-1))));
+    }
+
+    #[test]
+    fn test_tail_calls() {
+        let (mut store, instance) = instantiate(TAIL);
+        // Far deeper than the call stack allows, so this only succeeds if tail calls replace the frame
+        assert_eq!(
+            store.call::<_, i64>(func(&instance, "sum"), (100_000, 0i64)),
+            Ok(5_000_050_000)
+        );
+        assert_eq!(
+            store.call::<_, i64>(func(&instance, "sum_indirect"), (100_000, 0i64)),
+            Ok(5_000_050_000)
+        );
+
+        store.interrupt_handle().interrupt();
+        assert_eq!(
+            store.call::<_, i64>(func(&instance, "sum"), (1, 0i64)),
+            Err(Trap::Interrupted)
+        );
+    }
+
+    #[test]
+    fn test_exceptions() {
+        let mut store = Store::new();
+        let i32_tag = FuncType {
+            rt1: vec![ValType::NumType(NumType::I32)],
+            rt2: vec![],
+        };
+        let tag = store.alloc_tag(i32_tag.clone());
+        let other = store.alloc_tag(i32_tag);
+        let host = store.alloc_typed_func(move |()| -> Result<(), Trap> {
+            Err(Trap::Exception(Rc::new(Exception {
+                tag: other,
+                payload: vec![Val::I32(42)],
+            })))
+        });
+        let instance = instantiate_with(
+            &mut store,
+            EXCEPTIONS,
+            &[Extern::Tag(tag), Extern::Func(host)],
+        )
+        .unwrap();
+
+        let exception = |payload| {
+            Err(Trap::Exception(Rc::new(Exception {
+                tag,
+                payload: vec![Val::I32(payload)],
+            })))
+        };
+        assert_eq!(
+            store.call::<_, ()>(func(&instance, "throw"), 3),
+            exception(3)
+        );
+        assert_eq!(store.call::<_, i32>(func(&instance, "catch"), 7), Ok(7));
+        assert_eq!(
+            store.call::<_, ()>(func(&instance, "rethrow"), 5),
+            exception(5)
+        );
+        assert_eq!(
+            store.call::<_, i32>(func(&instance, "catch_host"), ()),
+            Ok(1)
+        );
+
+        // Tags only match imports of the same type
+        let unit_tag = store.alloc_tag(FuncType {
+            rt1: vec![],
+            rt2: vec![],
+        });
+        assert!(matches!(
+            instantiate_with(
+                &mut store,
+                EXCEPTIONS,
+                &[Extern::Tag(unit_tag), Extern::Func(host)]
+            ),
+            Err(InstantiationError::IncompatibleImport { .. })
+        ));
+    }
+
+    #[test]
+    fn test_shared_memory_threads() {
+        let shared = SharedMemory::new(1, 1);
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    let mut store = Store::new();
+                    let memory = store.alloc_shared_memory(shared);
+                    let instance =
+                        instantiate_with(&mut store, ATOMIC, &[Extern::Memory(memory)]).unwrap();
+                    let add = func(&instance, "add");
+                    for _ in 0..1000 {
+                        store.call::<_, i32>(add, 1).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let mut store = Store::new();
+        let memory = store.alloc_shared_memory(shared);
+        assert!(store.shared_memory(memory).is_some());
+        let instance = instantiate_with(&mut store, ATOMIC, &[Extern::Memory(memory)]).unwrap();
+        assert_eq!(store.call::<_, i32>(func(&instance, "add"), 0), Ok(4000));
+
+        // Shared memory imports are not satisfied by unshared memories
+        let unshared = store
+            .alloc_memory(&MemType {
+                lim: Limits {
+                    min: 1,
+                    max: Some(1),
+                },
+                shared: false,
+                memory64: false,
+            })
+            .unwrap();
+        assert_eq!(store.shared_memory(unshared), None);
+        assert!(matches!(
+            instantiate_with(&mut store, ATOMIC, &[Extern::Memory(unshared)]),
+            Err(InstantiationError::IncompatibleImport { .. })
+        ));
+    }
+
+    #[test]
+    fn test_memory() {
+        let (mut store, instance) = instantiate(MEMORY);
+        let mut call = |name, args: &[i32]| {
+            let args: Vec<Val> = args.iter().copied().map(Val::I32).collect();
+            store.invoke(func(&instance, name), &args)
+        };
+
+        assert_eq!(call("load", &[0]), Ok(vec!(Val::I32(0x04030201))));
+        assert_eq!(call("load", &[1]), Ok(vec!(Val::I32(0x00040302))));
+        assert_eq!(call("copy", &[]), Ok(vec!()));
+        assert_eq!(call("load", &[8]), Ok(vec!(Val::I32(0x04030201))));
+        assert_eq!(call("store", &[8, 0x12345678]), Ok(vec!()));
+        assert_eq!(call("load", &[12]), Ok(vec!(Val::I32(0x5678))));
+        assert_eq!(call("fill", &[]), Ok(vec!()));
+        assert_eq!(call("load", &[16]), Ok(vec!(Val::I32(0xFFFF))));
+        assert_eq!(call("init", &[]), Ok(vec!()));
+        assert_eq!(call("load", &[24]), Ok(vec!(Val::I32(0xBBAA))));
+        // The segment was dropped, so it no longer has the 2 bytes to copy
+        assert_eq!(call("init", &[]), Err(Trap::OutOfBoundsMemoryAccess));
+
+        assert_eq!(call("size", &[]), Ok(vec!(Val::I32(1))));
+        assert_eq!(
+            call("load", &[65536 - 2]),
+            Err(Trap::OutOfBoundsMemoryAccess)
+        );
+        assert_eq!(call("grow", &[1]), Ok(vec!(Val::I32(1))));
+        assert_eq!(call("grow", &[1]), Ok(vec!(Val::I32(-1))));
+        assert_eq!(call("size", &[]), Ok(vec!(Val::I32(2))));
+        assert_eq!(call("load", &[65536 - 2]), Ok(vec!(Val::I32(0))));
+        // The address and offset are added without wrapping around
+        assert_eq!(call("store", &[-1, 0]), Err(Trap::OutOfBoundsMemoryAccess));
+    }
+
+    #[test]
+    fn test_multiple_memories() {
+        let mut store = Store::new();
+        let memory = store
+            .alloc_memory(&MemType {
+                lim: Limits { min: 1, max: None },
+                shared: false,
+                memory64: false,
+            })
+            .unwrap();
+        let instance = instantiate_with(&mut store, MULTI, &[Extern::Memory(memory)]).unwrap();
+        let mut call = |name, arg: Option<i32>| {
+            let args: Vec<Val> = arg.into_iter().map(Val::I32).collect();
+            store.invoke(func(&instance, name), &args)
+        };
+
+        assert_eq!(call("load0", Some(0)), Ok(vec!(Val::I32(0))));
+        assert_eq!(call("load1", Some(0)), Ok(vec!(Val::I32(0x04030201))));
+        assert_eq!(call("copy", None), Ok(vec!()));
+        assert_eq!(call("load0", Some(0)), Ok(vec!(Val::I32(0x04030201))));
+        assert_eq!(call("fill", None), Ok(vec!()));
+        assert_eq!(call("load0", Some(8)), Ok(vec!(Val::I32(0xFFFF))));
+        assert_eq!(call("init", None), Ok(vec!()));
+        assert_eq!(call("load0", Some(16)), Ok(vec!(Val::I32(0xBBAA))));
+        // The segment was dropped, so it no longer has the 2 bytes to copy
+        assert_eq!(call("init", None), Err(Trap::OutOfBoundsMemoryAccess));
+
+        assert_eq!(call("size1", None), Ok(vec!(Val::I32(1))));
+        assert_eq!(call("grow1", Some(1)), Ok(vec!(Val::I32(1))));
+        assert_eq!(call("grow1", Some(1)), Ok(vec!(Val::I32(-1))));
+        assert_eq!(call("size1", None), Ok(vec!(Val::I32(2))));
+        assert_eq!(call("load1", Some(2 * 65536 - 4)), Ok(vec!(Val::I32(0))));
+        assert_eq!(
+            call("load0", Some(65536 - 2)),
+            Err(Trap::OutOfBoundsMemoryAccess)
+        );
+
+        // Memory 1 can be exported and imported as memory 0 of another instance
+        let mem1 = match instance.export("mem1") {
+            Some(Extern::Memory(addr)) => addr,
+            _ => panic!("expected a memory export named mem1"),
+        };
+        assert_ne!(mem1, memory);
+        let other = instantiate_with(&mut store, MULTI, &[Extern::Memory(mem1)]).unwrap();
+        assert_eq!(
+            store.invoke(func(&other, "load0"), &[Val::I32(0)]),
+            Ok(vec!(Val::I32(0x04030201)))
+        );
+    }
+
+    #[test]
+    fn test_memory64() {
+        let (mut store, instance) = instantiate(MEMORY64);
+        let mut call = |name, args: &[i64]| {
+            let args: Vec<Val> = args.iter().map(|n| Val::I64(*n)).collect();
+            store.invoke(func(&instance, name), &args)
+        };
+
+        assert_eq!(call("load", &[16]), Ok(vec!(Val::I64(0x2A))));
+        assert_eq!(call("store", &[0, 0x0102030405060708]), Ok(vec!()));
+        assert_eq!(call("store", &[8, -1]), Ok(vec!()));
+        assert_eq!(call("fill", &[]), Ok(vec!()));
+        assert_eq!(
+            call("load", &[8]),
+            Ok(vec!(Val::I64(0xFFFF_FFFF_ABAB_ABABu64 as i64)))
+        );
+        assert_eq!(call("copy", &[]), Ok(vec!(Val::I32(0x05060708))));
+
+        assert_eq!(call("size", &[]), Ok(vec!(Val::I64(1))));
+        assert_eq!(call("grow", &[1]), Ok(vec!(Val::I64(1))));
+        assert_eq!(call("grow", &[1]), Ok(vec!(Val::I64(-1))));
+        assert_eq!(call("size", &[]), Ok(vec!(Val::I64(2))));
+
+        // Addresses are 64-bit, so neither the offset nor the address wraps around
+        assert_eq!(call("load_far", &[0]), Err(Trap::OutOfBoundsMemoryAccess));
+        assert_eq!(call("load", &[-1]), Err(Trap::OutOfBoundsMemoryAccess));
+        assert_eq!(call("load", &[1 << 32]), Err(Trap::OutOfBoundsMemoryAccess));
+    }
+
+    #[test]
+    fn test_function_references() {
+        let (mut store, instance) = instantiate(FUNCREFS);
+        let double = Val::FuncRef(Some(FuncHandle(func(&instance, "double"))));
+        let null = Val::FuncRef(None);
+        let mut call = |name, args: &[Val]| store.invoke(func(&instance, name), args);
+
+        assert_eq!(
+            call("apply", &[double.clone(), Val::I32(3)]),
+            Ok(vec!(Val::I32(6)))
+        );
+        assert_eq!(
+            call("apply", &[null.clone(), Val::I32(3)]),
+            Err(Trap::NullFunctionReference)
+        );
+        // A reference to a function of another type is rejected before call_ref could use it
+        let apply = Val::FuncRef(Some(FuncHandle(func(&instance, "apply"))));
+        assert_eq!(
+            call("apply", &[apply, Val::I32(3)]),
+            Err(Trap::TypeMismatch)
+        );
+        assert_eq!(call("tail", &[Val::I32(4)]), Ok(vec!(Val::I32(8))));
+        assert_eq!(call("local", &[Val::I32(5)]), Ok(vec!(Val::I32(10))));
+
+        assert_eq!(
+            call("or_default", &[double.clone(), Val::I32(3)]),
+            Ok(vec!(Val::I32(6)))
+        );
+        assert_eq!(
+            call("or_default", &[null.clone(), Val::I32(3)]),
+            Ok(vec!(Val::I32(3)))
+        );
+        assert_eq!(
+            call("apply_non_null", &[double.clone(), Val::I32(1)]),
+            Ok(vec!(Val::I32(2)))
+        );
+        assert_eq!(
+            call("apply_non_null", &[null.clone(), Val::I32(1)]),
+            Err(Trap::NullReference)
+        );
+        assert_eq!(call("is_func", &[double]), Ok(vec!(Val::I32(1))));
+        assert_eq!(call("is_func", &[null]), Ok(vec!(Val::I32(0))));
+        assert_eq!(
+            call("is_func", &[Val::ExternRef(None)]),
+            Err(Trap::TypeMismatch)
+        );
+    }
+
+    #[test]
+    fn test_gc() {
+        let (mut store, instance) = instantiate(GC);
+        let mut call = |name, args: &[Val]| store.invoke(func(&instance, name), args);
+
+        assert_eq!(
+            call("pair_diff", &[Val::I32(7), Val::I32(2)]),
+            Ok(vec!(Val::I32(5)))
+        );
+        // Elements of an i8 array are wrapped when stored and extended when loaded
+        assert_eq!(call("bytes", &[Val::I32(3)]), Ok(vec!(Val::I32(258))));
+        assert_eq!(
+            call("bytes", &[Val::I32(0)]),
+            Err(Trap::OutOfBoundsArrayAccess)
+        );
+
+        let pair = call("new_pair", &[Val::I32(4), Val::I32(9)])
+            .unwrap()
+            .remove(0);
+        let Val::AnyRef(Some(AnyRef::Struct(object))) = &pair else {
+            panic!("expected a struct, found {:?}", pair);
+        };
+        assert_eq!(object.get(1), Some(Val::I32(9)));
+        assert_eq!(call("classify", &[pair.clone()]), Ok(vec!(Val::I32(4))));
+        let i31 = Val::AnyRef(Some(AnyRef::I31(0x7FFF_FFFF)));
+        assert_eq!(call("classify", &[i31.clone()]), Ok(vec!(Val::I32(-1))));
+        assert_eq!(
+            call("classify", &[Val::AnyRef(None)]),
+            Ok(vec!(Val::I32(-1)))
+        );
+        assert_eq!(call("cast", &[i31]), Ok(vec!(Val::I32(-1))));
+        assert_eq!(call("cast", &[pair.clone()]), Err(Trap::CastFailure));
+
+        // Objects the host still refers to survive a collection
+        store.collect_garbage();
+        assert_eq!(object.get(0), Some(Val::I32(4)));
+    }
+
+    #[test]
+    fn test_gc_roots() {
+        let (mut store, instance) = instantiate(ROOTS);
+        // Enough garbage to collect several times while the first struct is only held by a local
+        assert_eq!(store.call::<_, i32>(func(&instance, "churn"), 5000), Ok(1));
+        assert!(!store.heap.is_full());
+    }
+}
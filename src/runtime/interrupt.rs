@@ -0,0 +1,43 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// A handle that can cancel execution in a store from another thread
+///
+/// Once interrupted, the interpreter traps with `Trap::Interrupted` the next time it enters a function or takes a
+/// loop back-edge, or shortly after if it is suspended in `memory.atomic.wait`.
+#[derive(Clone, Debug, Default)]
+pub struct InterruptHandle {
+    interrupted: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    /// Request that execution in the store traps as soon as possible
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::SeqCst);
+    }
+
+    /// Check for and clear a pending interruption, so that the store can be used again after trapping
+    pub(crate) fn take(&self) -> bool {
+        self.interrupted.swap(false, Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_interrupt_handle() {
+        let handle = InterruptHandle::default();
+        assert!(!handle.take());
+
+        let other = handle.clone();
+        thread::spawn(move || other.interrupt()).join().unwrap();
+        assert!(handle.take());
+        assert!(!handle.take());
+    }
+}
@@ -0,0 +1,74 @@
+use super::trap::Trap;
+
+/// Lets the host cap the resources a guest can use, on top of the limits the module declares itself
+///
+/// The limiter is consulted whenever a memory or table is allocated during instantiation and whenever one grows.
+/// Returning `Ok(false)` denies the request, which makes `memory.grow` and `table.grow` return -1 and fails
+/// instantiation. Returning an `Err` traps instead.
+pub trait ResourceLimiter {
+    /// Decide whether a memory may grow from `current` to `desired` bytes. `maximum` is the module's own limit in bytes
+    fn memory_growing(
+        &mut self,
+        current: u64,
+        desired: u64,
+        maximum: Option<u64>,
+    ) -> Result<bool, Trap>;
+
+    /// Decide whether a table may grow from `current` to `desired` elements. `maximum` is the module's own limit
+    fn table_growing(
+        &mut self,
+        current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> Result<bool, Trap>;
+}
+
+/// A simple limiter that caps the size of every memory and table in a store
+#[derive(Debug, Default, PartialEq)]
+pub struct StoreLimits {
+    /// The maximum size of any single memory in bytes
+    pub memory_size: Option<u64>,
+    /// The maximum number of elements in any single table
+    pub table_elements: Option<u32>,
+}
+
+impl ResourceLimiter for StoreLimits {
+    fn memory_growing(
+        &mut self,
+        _current: u64,
+        desired: u64,
+        _maximum: Option<u64>,
+    ) -> Result<bool, Trap> {
+        Ok(self.memory_size.is_none_or(|limit| desired <= limit))
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: u32,
+        desired: u32,
+        _maximum: Option<u32>,
+    ) -> Result<bool, Trap> {
+        Ok(self.table_elements.is_none_or(|limit| desired <= limit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_limits() {
+        let mut limits = StoreLimits {
+            memory_size: Some(1024),
+            table_elements: Some(10),
+        };
+        assert_eq!(limits.memory_growing(0, 1024, None), Ok(true));
+        assert_eq!(limits.memory_growing(0, 1025, None), Ok(false));
+        assert_eq!(limits.table_growing(0, 10, None), Ok(true));
+        assert_eq!(limits.table_growing(0, 11, None), Ok(false));
+
+        let mut unlimited = StoreLimits::default();
+        assert_eq!(unlimited.memory_growing(0, u64::MAX, None), Ok(true));
+        assert_eq!(unlimited.table_growing(0, u32::MAX, None), Ok(true));
+    }
+}
@@ -0,0 +1,546 @@
+use std::{
+    fmt,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::module::types::MemType;
+
+use super::{interrupt::InterruptHandle, limiter::ResourceLimiter, trap::Trap};
+
+/// The size of a single page of linear memory in bytes
+pub const PAGE_SIZE: u64 = 65536;
+
+/// The largest number of pages a 32-bit linear memory can have
+const MAX_PAGES: u64 = 1 << 16;
+
+/// The largest number of pages a 64-bit linear memory can have
+const MAX_PAGES64: u64 = 1 << 48;
+
+/// How long a `memory.atomic.wait` sleeps at a time before checking whether it was interrupted
+const WAIT_SLICE: Duration = Duration::from_millis(10);
+
+/// The runtime representation of a linear memory
+#[derive(Debug, PartialEq)]
+pub struct MemInst {
+    pub max: Option<u64>,
+    pub memory64: bool,
+    bytes: Bytes,
+}
+
+/// The contents of a memory, which shared memories keep behind a lock
+#[derive(Debug, PartialEq)]
+enum Bytes {
+    Unshared(Vec<u8>),
+    Shared(SharedMemory),
+}
+
+/// Allocate `pages` zeroed pages, or `None` if the host can't provide that much memory
+fn zeroed(pages: u64) -> Option<Vec<u8>> {
+    let len = usize::try_from(pages.checked_mul(PAGE_SIZE)?).ok()?;
+    let mut bytes = Vec::new();
+    bytes.try_reserve_exact(len).ok()?;
+    bytes.resize(len, 0);
+    Some(bytes)
+}
+
+impl MemInst {
+    /// Allocate a memory of the given type, or `None` if the limiter denies it or the host is out of memory
+    pub fn new(
+        mt: &MemType,
+        limiter: Option<&mut dyn ResourceLimiter>,
+    ) -> Result<Option<Self>, Trap> {
+        let desired = mt.lim.min.saturating_mul(PAGE_SIZE);
+        if let Some(limiter) = limiter {
+            let maximum = mt.lim.max.map(|max| max.saturating_mul(PAGE_SIZE));
+            if !limiter.memory_growing(0, desired, maximum)? {
+                return Ok(None);
+            }
+        }
+        let Some(bytes) = zeroed(mt.lim.min) else {
+            return Ok(None);
+        };
+        let bytes = if mt.shared {
+            let max = mt.lim.max.unwrap_or(max_pages(mt.memory64));
+            Bytes::Shared(SharedMemory::from_bytes(bytes, max, mt.memory64))
+        } else {
+            Bytes::Unshared(bytes)
+        };
+        Ok(Some(Self {
+            max: mt.lim.max,
+            memory64: mt.memory64,
+            bytes,
+        }))
+    }
+
+    /// The memory of another store, which both stores can then access
+    pub fn from_shared(shared: SharedMemory) -> Self {
+        Self {
+            max: Some(shared.0.max),
+            memory64: shared.0.memory64,
+            bytes: Bytes::Shared(shared),
+        }
+    }
+
+    /// A handle to the memory if it is shared
+    pub fn shared(&self) -> Option<&SharedMemory> {
+        match &self.bytes {
+            Bytes::Unshared(_) => None,
+            Bytes::Shared(shared) => Some(shared),
+        }
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&Vec<u8>) -> R) -> R {
+        match &self.bytes {
+            Bytes::Unshared(bytes) => f(bytes),
+            Bytes::Shared(shared) => f(&shared.lock().bytes),
+        }
+    }
+
+    fn with_mut<R>(&mut self, f: impl FnOnce(&mut Vec<u8>) -> R) -> R {
+        match &mut self.bytes {
+            Bytes::Unshared(bytes) => f(bytes),
+            Bytes::Shared(shared) => f(&mut shared.lock().bytes),
+        }
+    }
+
+    /// The current size of the memory in pages
+    pub fn size(&self) -> u64 {
+        self.with(|bytes| bytes.len() as u64 / PAGE_SIZE)
+    }
+
+    /// Grow the memory by `delta` pages, returning the previous size or `None` if the growth was denied
+    pub fn grow(
+        &mut self,
+        delta: u64,
+        limiter: Option<&mut dyn ResourceLimiter>,
+    ) -> Result<Option<u64>, Trap> {
+        let max = self.max;
+        let limit = max_pages(self.memory64);
+        // Shared memories hold the lock throughout so that concurrent growth is not lost
+        self.with_mut(|bytes| {
+            let old = bytes.len() as u64 / PAGE_SIZE;
+            let new = match old.checked_add(delta) {
+                Some(new) if new <= max.unwrap_or(limit).min(limit) => new,
+                _ => return Ok(None),
+            };
+            if let Some(limiter) = limiter {
+                let maximum = max.map(|max| max.saturating_mul(PAGE_SIZE));
+                if !limiter.memory_growing(
+                    old * PAGE_SIZE,
+                    new.saturating_mul(PAGE_SIZE),
+                    maximum,
+                )? {
+                    return Ok(None);
+                }
+            }
+            // Fail like a denied growth when the host can't provide the memory, rather than aborting
+            let Some(len) = new
+                .checked_mul(PAGE_SIZE)
+                .and_then(|len| usize::try_from(len).ok())
+            else {
+                return Ok(None);
+            };
+            if bytes.try_reserve_exact(len - bytes.len()).is_err() {
+                return Ok(None);
+            }
+            bytes.resize(len, 0);
+            Ok(Some(old))
+        })
+    }
+
+    /// Fill `buf` with the bytes starting at the effective address `ea`
+    pub fn read(&self, ea: u64, buf: &mut [u8]) -> Result<(), Trap> {
+        self.with(|bytes| {
+            buf.copy_from_slice(&bytes[range(bytes, ea, buf.len() as u64)?]);
+            Ok(())
+        })
+    }
+
+    /// Copy `bytes` into the memory starting at the effective address `ea`
+    pub fn write(&mut self, ea: u64, src: &[u8]) -> Result<(), Trap> {
+        self.with_mut(|bytes| {
+            let range = range(bytes, ea, src.len() as u64)?;
+            bytes[range].copy_from_slice(src);
+            Ok(())
+        })
+    }
+
+    /// Set `len` bytes starting at the effective address `ea` to `value`
+    pub fn fill(&mut self, ea: u64, value: u8, len: u64) -> Result<(), Trap> {
+        self.with_mut(|bytes| {
+            let range = range(bytes, ea, len)?;
+            bytes[range].fill(value);
+            Ok(())
+        })
+    }
+
+    /// Copy `len` bytes from `src` to `dst` within the memory, which may overlap
+    pub fn copy_within(&mut self, dst: u64, src: u64, len: u64) -> Result<(), Trap> {
+        self.with_mut(|bytes| {
+            let src = range(bytes, src, len)?;
+            let dst = range(bytes, dst, len)?;
+            bytes.copy_within(src, dst.start);
+            Ok(())
+        })
+    }
+
+    /// Check that `len` bytes starting at the effective address `ea` are in bounds
+    pub fn check(&self, ea: u64, len: u64) -> Result<(), Trap> {
+        self.with(|bytes| range(bytes, ea, len).map(|_| ()))
+    }
+
+    /// Apply `f` to `len` bytes starting at the effective address `ea`, without any other access in between
+    pub fn atomic<R>(
+        &mut self,
+        ea: u64,
+        len: usize,
+        f: impl FnOnce(&mut [u8]) -> R,
+    ) -> Result<R, Trap> {
+        self.with_mut(|bytes| {
+            let range = range(bytes, ea, len as u64)?;
+            Ok(f(&mut bytes[range]))
+        })
+    }
+
+    /// Suspend the thread until notified if the bytes at `ea` equal `expected`, with the result of `memory.atomic.wait`
+    ///
+    /// Returns 0 when woken by a notification, 1 when the bytes differ and 2 when `timeout` elapses first. Traps with
+    /// `Trap::Interrupted` if `interrupt` is used while waiting.
+    pub fn wait(
+        &self,
+        ea: u64,
+        expected: &[u8],
+        timeout: Option<Duration>,
+        interrupt: &InterruptHandle,
+    ) -> Result<u32, Trap> {
+        match &self.bytes {
+            Bytes::Unshared(bytes) => {
+                range(bytes, ea, expected.len() as u64)?;
+                Err(Trap::ExpectedSharedMemory)
+            }
+            Bytes::Shared(shared) => shared.wait(ea, expected, timeout, interrupt),
+        }
+    }
+
+    /// Wake up to `count` threads waiting on `ea`, returning how many were woken
+    pub fn notify(&self, ea: u64, count: u32) -> Result<u32, Trap> {
+        match &self.bytes {
+            // Nothing can wait on an unshared memory
+            Bytes::Unshared(bytes) => range(bytes, ea, 4).map(|_| 0),
+            Bytes::Shared(shared) => shared.notify(ea, count),
+        }
+    }
+}
+
+/// The largest number of pages a memory can have, given whether it is a 64-bit memory
+fn max_pages(memory64: bool) -> u64 {
+    if memory64 {
+        MAX_PAGES64
+    } else {
+        MAX_PAGES
+    }
+}
+
+/// The range of `len` bytes starting at the effective address `ea`, if it is within bounds
+fn range(bytes: &[u8], ea: u64, len: u64) -> Result<std::ops::Range<usize>, Trap> {
+    ea.checked_add(len)
+        .filter(|end| *end <= bytes.len() as u64)
+        .map(|end| ea as usize..end as usize)
+        .ok_or(Trap::OutOfBoundsMemoryAccess)
+}
+
+/// A linear memory that stores on different threads can use at the same time
+///
+/// Cloning the handle does not copy the memory. Every access takes a lock, so plain loads and stores are never torn
+/// and the atomic instructions are sequentially consistent.
+#[derive(Clone)]
+pub struct SharedMemory(Arc<Shared>);
+
+struct Shared {
+    max: u64,
+    memory64: bool,
+    state: Mutex<State>,
+    notified: Condvar,
+}
+
+struct State {
+    bytes: Vec<u8>,
+    /// The address and id of every suspended `memory.atomic.wait`, in the order they started waiting
+    waiters: Vec<(u64, u64)>,
+    next_id: u64,
+}
+
+impl SharedMemory {
+    /// Allocate a shared memory of `min` pages that can grow to `max` pages
+    pub fn new(min: u32, max: u32) -> Self {
+        Self::from_bytes(
+            vec![0; (min as u64 * PAGE_SIZE) as usize],
+            max as u64,
+            false,
+        )
+    }
+
+    fn from_bytes(bytes: Vec<u8>, max: u64, memory64: bool) -> Self {
+        Self(Arc::new(Shared {
+            max,
+            memory64,
+            state: Mutex::new(State {
+                bytes,
+                waiters: Vec::new(),
+                next_id: 0,
+            }),
+            notified: Condvar::new(),
+        }))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // A host thread that panicked mid-access cannot leave the bytes in an invalid state
+        self.0.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait(
+        &self,
+        ea: u64,
+        expected: &[u8],
+        timeout: Option<Duration>,
+        interrupt: &InterruptHandle,
+    ) -> Result<u32, Trap> {
+        let mut state = self.lock();
+        if state.bytes[range(&state.bytes, ea, expected.len() as u64)?] != *expected {
+            return Ok(1);
+        }
+        let id = state.next_id;
+        state.next_id += 1;
+        state.waiters.push((ea, id));
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            // Sleep in slices so that an interruption is noticed even without a notification or a timeout
+            let slice = deadline.map_or(WAIT_SLICE, |deadline| {
+                deadline
+                    .saturating_duration_since(Instant::now())
+                    .min(WAIT_SLICE)
+            });
+            state = self
+                .0
+                .notified
+                .wait_timeout(state, slice)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+            // Notifying removes the waiter, so a waiter that is still listed was woken spuriously, timed out or has
+            // finished a slice
+            let Some(position) = state.waiters.iter().position(|waiter| waiter.1 == id) else {
+                return Ok(0);
+            };
+            if interrupt.take() {
+                state.waiters.remove(position);
+                return Err(Trap::Interrupted);
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                state.waiters.remove(position);
+                return Ok(2);
+            }
+        }
+    }
+
+    fn notify(&self, ea: u64, count: u32) -> Result<u32, Trap> {
+        let mut state = self.lock();
+        range(&state.bytes, ea, 4)?;
+        let mut woken = 0;
+        state.waiters.retain(|waiter| {
+            if waiter.0 == ea && woken < count {
+                woken += 1;
+                false
+            } else {
+                true
+            }
+        });
+        if woken > 0 {
+            self.0.notified.notify_all();
+        }
+        Ok(woken)
+    }
+}
+
+impl PartialEq for SharedMemory {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for SharedMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedMemory")
+            .field("max", &self.0.max)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{module::types::Limits, runtime::limiter::StoreLimits};
+
+    use super::*;
+
+    #[test]
+    fn test_mem_inst_grow() {
+        let mt = MemType {
+            lim: Limits {
+                min: 1,
+                max: Some(3),
+            },
+            shared: false,
+            memory64: false,
+        };
+        let mut memory = MemInst::new(&mt, None).unwrap().unwrap();
+        assert_eq!(memory.size(), 1);
+        assert_eq!(memory.grow(1, None), Ok(Some(1)));
+        assert_eq!(memory.size(), 2);
+        assert_eq!(memory.grow(2, None), Ok(None));
+        assert_eq!(memory.size(), 2);
+    }
+
+    #[test]
+    fn test_mem_inst_grow64() {
+        let mt = MemType {
+            lim: Limits { min: 1, max: None },
+            shared: false,
+            memory64: true,
+        };
+        let mut memory = MemInst::new(&mt, None).unwrap().unwrap();
+        assert_eq!(memory.grow(1, None), Ok(Some(1)));
+        // More memory than the host can provide fails like any other denied growth
+        assert_eq!(memory.grow(1 << 47, None), Ok(None));
+        assert_eq!(memory.grow(1 << 48, None), Ok(None));
+        assert_eq!(memory.grow(u64::MAX, None), Ok(None));
+        assert_eq!(memory.size(), 2);
+    }
+
+    #[test]
+    fn test_mem_inst_limiter() {
+        let mt = MemType {
+            lim: Limits { min: 1, max: None },
+            shared: false,
+            memory64: false,
+        };
+        let mut limits = StoreLimits {
+            memory_size: Some(2 * PAGE_SIZE),
+            table_elements: None,
+        };
+        let mut memory = MemInst::new(&mt, Some(&mut limits)).unwrap().unwrap();
+        assert_eq!(memory.grow(1, Some(&mut limits)), Ok(Some(1)));
+        assert_eq!(memory.grow(1, Some(&mut limits)), Ok(None));
+        assert_eq!(memory.size(), 2);
+
+        let too_big = MemType {
+            lim: Limits { min: 3, max: None },
+            shared: false,
+            memory64: false,
+        };
+        assert_eq!(MemInst::new(&too_big, Some(&mut limits)), Ok(None));
+    }
+
+    #[test]
+    fn test_mem_inst_access() {
+        let mt = MemType {
+            lim: Limits { min: 1, max: None },
+            shared: false,
+            memory64: false,
+        };
+        let mut memory = MemInst::new(&mt, None).unwrap().unwrap();
+        assert_eq!(memory.write(PAGE_SIZE - 2, &[1, 2]), Ok(()));
+        let mut buf = [0xFF; 3];
+        assert_eq!(memory.read(PAGE_SIZE - 3, &mut buf), Ok(()));
+        assert_eq!(buf, [0, 1, 2]);
+        assert_eq!(
+            memory.read(PAGE_SIZE - 1, &mut buf[..2]),
+            Err(Trap::OutOfBoundsMemoryAccess)
+        );
+        assert_eq!(
+            memory.write(u64::MAX, &[1]),
+            Err(Trap::OutOfBoundsMemoryAccess)
+        );
+    }
+
+    #[test]
+    fn test_shared_memory() {
+        let shared = SharedMemory::new(1, 2);
+        let mut a = MemInst::from_shared(shared.clone());
+        let mut b = MemInst::from_shared(shared);
+        assert_eq!(a, b);
+        assert_eq!(a.write(8, &[1, 2, 3, 4]), Ok(()));
+        assert_eq!(b.grow(1, None), Ok(Some(1)));
+        assert_eq!(a.size(), 2);
+        assert_eq!(a.grow(1, None), Ok(None));
+        assert_eq!(
+            b.atomic(8, 4, |bytes| {
+                bytes[0] += 1;
+                bytes[0]
+            }),
+            Ok(2)
+        );
+        let mut buf = [0; 4];
+        assert_eq!(a.read(8, &mut buf), Ok(()));
+        assert_eq!(buf, [2, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_wait_notify() {
+        let shared = SharedMemory::new(1, 1);
+        let memory = MemInst::from_shared(shared.clone());
+        let interrupt = InterruptHandle::default();
+        assert_eq!(memory.wait(0, &[1, 0, 0, 0], None, &interrupt), Ok(1));
+        assert_eq!(
+            memory.wait(0, &[0; 4], Some(Duration::from_millis(10)), &interrupt),
+            Ok(2)
+        );
+        assert_eq!(memory.notify(0, 1), Ok(0));
+
+        let waiter = std::thread::spawn(move || {
+            let memory = MemInst::from_shared(shared);
+            let interrupt = InterruptHandle::default();
+            // Traps hold exceptions, which cannot be sent back from the thread
+            memory
+                .wait(16, &[0; 8], Some(Duration::from_secs(60)), &interrupt)
+                .ok()
+        });
+        // Keep notifying until the waiter has started waiting
+        while memory.notify(16, 1) != Ok(1) {
+            std::thread::yield_now();
+        }
+        assert_eq!(waiter.join().unwrap(), Some(0));
+        assert_eq!(
+            memory.notify(PAGE_SIZE, 1),
+            Err(Trap::OutOfBoundsMemoryAccess)
+        );
+
+        let mt = MemType {
+            lim: Limits { min: 1, max: None },
+            shared: false,
+            memory64: false,
+        };
+        let unshared = MemInst::new(&mt, None).unwrap().unwrap();
+        assert_eq!(
+            unshared.wait(0, &[0; 4], None, &interrupt),
+            Err(Trap::ExpectedSharedMemory)
+        );
+        assert_eq!(unshared.notify(0, 1), Ok(0));
+    }
+
+    #[test]
+    fn test_wait_interrupt() {
+        let memory = MemInst::from_shared(SharedMemory::new(1, 1));
+        let interrupt = InterruptHandle::default();
+        let other = interrupt.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            other.interrupt();
+        });
+        // A negative timeout waits forever, so only the interruption can end the wait
+        assert_eq!(
+            memory.wait(0, &[0; 4], None, &interrupt),
+            Err(Trap::Interrupted)
+        );
+        assert_eq!(memory.notify(0, 1), Ok(0));
+    }
+}
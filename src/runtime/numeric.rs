@@ -0,0 +1,436 @@
+use crate::instructions::{NumericInstruction, SaturatingTruncationInstruction};
+
+use super::{
+    operands::{pop_f32, pop_f64, pop_i32, pop_i64},
+    trap::Trap,
+    values::Val,
+};
+
+/// The `fmin` of the spec, which propagates NaN and orders -0 below +0
+pub fn fmin(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == b {
+        if a.is_sign_negative() {
+            a
+        } else {
+            b
+        }
+    } else {
+        a.min(b)
+    }
+}
+
+/// The `fmax` of the spec, which propagates NaN and orders +0 above -0
+pub fn fmax(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == b {
+        if a.is_sign_positive() {
+            a
+        } else {
+            b
+        }
+    } else {
+        a.max(b)
+    }
+}
+
+/// Truncate a float towards zero, trapping unless the result lies in `min..max`
+///
+/// Every f32 and every bound used here converts to f64 exactly, so the check is precise for both float types.
+fn trunc(z: f64, min: f64, max: f64) -> Result<f64, Trap> {
+    if z.is_nan() {
+        return Err(Trap::InvalidConversionToInteger);
+    }
+    let z = z.trunc();
+    if z < min || z >= max {
+        return Err(Trap::IntegerOverflow);
+    }
+    Ok(z)
+}
+
+const I32_MIN: f64 = -2147483648.0;
+const I32_END: f64 = 2147483648.0;
+const U32_END: f64 = 4294967296.0;
+const I64_MIN: f64 = -9223372036854775808.0;
+const I64_END: f64 = 9223372036854775808.0;
+const U64_END: f64 = 18446744073709551616.0;
+
+/// Execute a numeric instruction against the operand stack
+pub fn execute(instruction: &NumericInstruction, stack: &mut Vec<Val>) -> Result<(), Trap> {
+    use NumericInstruction::*;
+
+    let result = match instruction {
+        I32Const(n) => Val::I32(*n),
+        I64Const(n) => Val::I64(*n),
+        F32Const(z) => Val::F32(*z),
+        F64Const(z) => Val::F64(*z),
+
+        I32Eqz => Val::I32((pop_i32(stack) == 0) as i32),
+        I64Eqz => Val::I32((pop_i64(stack) == 0) as i32),
+
+        I32Clz | I32Ctz | I32Popcnt | I32Extend8S | I32Extend16S => {
+            let a = pop_i32(stack);
+            Val::I32(match instruction {
+                I32Clz => a.leading_zeros() as i32,
+                I32Ctz => a.trailing_zeros() as i32,
+                I32Popcnt => a.count_ones() as i32,
+                I32Extend8S => a as i8 as i32,
+                _ => a as i16 as i32,
+            })
+        }
+        I64Clz | I64Ctz | I64Popcnt | I64Extend8S | I64Extend16S | I64Extend32S => {
+            let a = pop_i64(stack);
+            Val::I64(match instruction {
+                I64Clz => a.leading_zeros() as i64,
+                I64Ctz => a.trailing_zeros() as i64,
+                I64Popcnt => a.count_ones() as i64,
+                I64Extend8S => a as i8 as i64,
+                I64Extend16S => a as i16 as i64,
+                _ => a as i32 as i64,
+            })
+        }
+
+        I32Eq | I32Ne | I32LtS | I32NeU | I32GtS | I32GtU | I32LeS | I32LeU | I32GeS | I32GeU => {
+            let b = pop_i32(stack);
+            let a = pop_i32(stack);
+            let (ua, ub) = (a as u32, b as u32);
+            // NOTE: I32NeU is the spec's i32.lt_u
+            Val::I32(match instruction {
+                I32Eq => a == b,
+                I32Ne => a != b,
+                I32LtS => a < b,
+                I32NeU => ua < ub,
+                I32GtS => a > b,
+                I32GtU => ua > ub,
+                I32LeS => a <= b,
+                I32LeU => ua <= ub,
+                I32GeS => a >= b,
+                _ => ua >= ub,
+            } as i32)
+        }
+        I64Eq | I64Ne | I64LtS | I64NeU | I64GtS | I64GtU | I64LeS | I64LeU | I64GeS | I64GeU => {
+            let b = pop_i64(stack);
+            let a = pop_i64(stack);
+            let (ua, ub) = (a as u64, b as u64);
+            // NOTE: I64NeU is the spec's i64.lt_u
+            Val::I32(match instruction {
+                I64Eq => a == b,
+                I64Ne => a != b,
+                I64LtS => a < b,
+                I64NeU => ua < ub,
+                I64GtS => a > b,
+                I64GtU => ua > ub,
+                I64LeS => a <= b,
+                I64LeU => ua <= ub,
+                I64GeS => a >= b,
+                _ => ua >= ub,
+            } as i32)
+        }
+        F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge => {
+            let b = pop_f32(stack);
+            let a = pop_f32(stack);
+            Val::I32(match instruction {
+                F32Eq => a == b,
+                F32Ne => a != b,
+                F32Lt => a < b,
+                F32Gt => a > b,
+                F32Le => a <= b,
+                _ => a >= b,
+            } as i32)
+        }
+        F64Eq | F64Ne | F64Lt | F64Gt | F64Le | F64Ge => {
+            let b = pop_f64(stack);
+            let a = pop_f64(stack);
+            Val::I32(match instruction {
+                F64Eq => a == b,
+                F64Ne => a != b,
+                F64Lt => a < b,
+                F64Gt => a > b,
+                F64Le => a <= b,
+                _ => a >= b,
+            } as i32)
+        }
+
+        I32Add | I32Sub | I32Mul | I32DivS | I32DivU | I32RemS | I32RemU | I32And | I32Or
+        | I32Xor | I32Shl | I32ShrS | I32ShrU | I32Rotl | I32Rotr => {
+            let b = pop_i32(stack);
+            let a = pop_i32(stack);
+            Val::I32(match instruction {
+                I32Add => a.wrapping_add(b),
+                I32Sub => a.wrapping_sub(b),
+                I32Mul => a.wrapping_mul(b),
+                I32DivS | I32DivU | I32RemS | I32RemU if b == 0 => {
+                    return Err(Trap::IntegerDivideByZero)
+                }
+                I32DivS => a.checked_div(b).ok_or(Trap::IntegerOverflow)?,
+                I32DivU => ((a as u32) / (b as u32)) as i32,
+                I32RemS => a.wrapping_rem(b),
+                I32RemU => ((a as u32) % (b as u32)) as i32,
+                I32And => a & b,
+                I32Or => a | b,
+                I32Xor => a ^ b,
+                I32Shl => a.wrapping_shl(b as u32),
+                I32ShrS => a.wrapping_shr(b as u32),
+                I32ShrU => (a as u32).wrapping_shr(b as u32) as i32,
+                I32Rotl => a.rotate_left(b as u32),
+                _ => a.rotate_right(b as u32),
+            })
+        }
+        I64Add | I64Sub | I64Mul | I64DivS | I64DivU | I64RemS | I64RemU | I64And | I64Or
+        | I64Xor | I64Shl | I64ShrS | I64ShrU | I64Rotl | I64Rotr => {
+            let b = pop_i64(stack);
+            let a = pop_i64(stack);
+            Val::I64(match instruction {
+                I64Add => a.wrapping_add(b),
+                I64Sub => a.wrapping_sub(b),
+                I64Mul => a.wrapping_mul(b),
+                I64DivS | I64DivU | I64RemS | I64RemU if b == 0 => {
+                    return Err(Trap::IntegerDivideByZero)
+                }
+                I64DivS => a.checked_div(b).ok_or(Trap::IntegerOverflow)?,
+                I64DivU => ((a as u64) / (b as u64)) as i64,
+                I64RemS => a.wrapping_rem(b),
+                I64RemU => ((a as u64) % (b as u64)) as i64,
+                I64And => a & b,
+                I64Or => a | b,
+                I64Xor => a ^ b,
+                I64Shl => a.wrapping_shl(b as u32),
+                I64ShrS => a.wrapping_shr(b as u32),
+                I64ShrU => (a as u64).wrapping_shr(b as u32) as i64,
+                I64Rotl => a.rotate_left((b & 63) as u32),
+                _ => a.rotate_right((b & 63) as u32),
+            })
+        }
+
+        F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest | F32Sqrt => {
+            let a = pop_f32(stack);
+            Val::F32(match instruction {
+                F32Abs => a.abs(),
+                F32Neg => -a,
+                F32Ceil => a.ceil(),
+                F32Floor => a.floor(),
+                F32Trunc => a.trunc(),
+                F32Nearest => a.round_ties_even(),
+                _ => a.sqrt(),
+            })
+        }
+        F64Abs | F64Neg | F64Ceil | F64Floor | F64Trunc | F64Nearest | F64Sqrt => {
+            let a = pop_f64(stack);
+            Val::F64(match instruction {
+                F64Abs => a.abs(),
+                F64Neg => -a,
+                F64Ceil => a.ceil(),
+                F64Floor => a.floor(),
+                F64Trunc => a.trunc(),
+                F64Nearest => a.round_ties_even(),
+                _ => a.sqrt(),
+            })
+        }
+        F32Add | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32CopySign => {
+            let b = pop_f32(stack);
+            let a = pop_f32(stack);
+            Val::F32(match instruction {
+                F32Add => a + b,
+                F32Sub => a - b,
+                F32Mul => a * b,
+                F32Div => a / b,
+                // Both operands convert to f64 exactly and the result is one of them, so converting back is exact too
+                F32Min => fmin(a as f64, b as f64) as f32,
+                F32Max => fmax(a as f64, b as f64) as f32,
+                _ => a.copysign(b),
+            })
+        }
+        F64Add | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64CopySign => {
+            let b = pop_f64(stack);
+            let a = pop_f64(stack);
+            Val::F64(match instruction {
+                F64Add => a + b,
+                F64Sub => a - b,
+                F64Mul => a * b,
+                F64Div => a / b,
+                F64Min => fmin(a, b),
+                F64Max => fmax(a, b),
+                _ => a.copysign(b),
+            })
+        }
+
+        I32WrapI64 => Val::I32(pop_i64(stack) as i32),
+        I32TruncF32S => Val::I32(trunc(pop_f32(stack) as f64, I32_MIN, I32_END)? as i32),
+        I32TruncF32U => Val::I32(trunc(pop_f32(stack) as f64, 0.0, U32_END)? as u32 as i32),
+        I32TruncF64S => Val::I32(trunc(pop_f64(stack), I32_MIN, I32_END)? as i32),
+        I32TruncF64U => Val::I32(trunc(pop_f64(stack), 0.0, U32_END)? as u32 as i32),
+        I64ExtendI32S => Val::I64(pop_i32(stack) as i64),
+        I64ExtendI32U => Val::I64(pop_i32(stack) as u32 as i64),
+        I64TruncF32S => Val::I64(trunc(pop_f32(stack) as f64, I64_MIN, I64_END)? as i64),
+        I64TruncF32U => Val::I64(trunc(pop_f32(stack) as f64, 0.0, U64_END)? as u64 as i64),
+        I64TruncF64S => Val::I64(trunc(pop_f64(stack), I64_MIN, I64_END)? as i64),
+        I64TruncF64U => Val::I64(trunc(pop_f64(stack), 0.0, U64_END)? as u64 as i64),
+        F32ConvertI32S => Val::F32(pop_i32(stack) as f32),
+        F32ConvertI32U => Val::F32(pop_i32(stack) as u32 as f32),
+        F32ConvertI64S => Val::F32(pop_i64(stack) as f32),
+        F32ConvertI64u => Val::F32(pop_i64(stack) as u64 as f32),
+        F32DemoteF64 => Val::F32(pop_f64(stack) as f32),
+        F64ConvertI32S => Val::F64(pop_i32(stack) as f64),
+        F64ConvertI32U => Val::F64(pop_i32(stack) as u32 as f64),
+        F64ConvertI64S => Val::F64(pop_i64(stack) as f64),
+        F64ConvertI64u => Val::F64(pop_i64(stack) as u64 as f64),
+        F64PromoteF32 => Val::F64(pop_f32(stack) as f64),
+        I32ReinterpretF32 => Val::I32(pop_f32(stack).to_bits() as i32),
+        I64ReinterpretF64 => Val::I64(pop_f64(stack).to_bits() as i64),
+        F32ReinterpretI32 => Val::F32(f32::from_bits(pop_i32(stack) as u32)),
+        F64ReinterpretI64 => Val::F64(f64::from_bits(pop_i64(stack) as u64)),
+    };
+    stack.push(result);
+    Ok(())
+}
+
+/// Execute a saturating truncation against the operand stack
+///
+/// Casting a float to an integer in Rust saturates and maps NaN to 0, which is exactly what these instructions do.
+pub fn saturate(instruction: &SaturatingTruncationInstruction, stack: &mut Vec<Val>) {
+    use SaturatingTruncationInstruction::*;
+
+    let result = match instruction {
+        I32TruncSatF32S => Val::I32(pop_f32(stack) as i32),
+        I32TruncSatF32U => Val::I32(pop_f32(stack) as u32 as i32),
+        I32TruncSatF64S => Val::I32(pop_f64(stack) as i32),
+        I32TruncSatF64U => Val::I32(pop_f64(stack) as u32 as i32),
+        I64TruncSatF32S => Val::I64(pop_f32(stack) as i64),
+        I64TruncSatF32U => Val::I64(pop_f32(stack) as u64 as i64),
+        I64TruncSatF64S => Val::I64(pop_f64(stack) as i64),
+        I64TruncSatF64U => Val::I64(pop_f64(stack) as u64 as i64),
+    };
+    stack.push(result);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(instruction: NumericInstruction, operands: Vec<Val>) -> Result<Val, Trap> {
+        let mut stack = operands;
+        execute(&instruction, &mut stack)?;
+        Ok(stack.pop().unwrap())
+    }
+
+    #[test]
+    fn test_integer() {
+        use NumericInstruction::*;
+
+        assert_eq!(
+            run(I32Add, vec![Val::I32(i32::MAX), Val::I32(1)]),
+            Ok(Val::I32(i32::MIN))
+        );
+        assert_eq!(
+            run(I32NeU, vec![Val::I32(-1), Val::I32(1)]),
+            Ok(Val::I32(0))
+        );
+        assert_eq!(
+            run(I32LtS, vec![Val::I32(-1), Val::I32(1)]),
+            Ok(Val::I32(1))
+        );
+        assert_eq!(
+            run(I32DivS, vec![Val::I32(1), Val::I32(0)]),
+            Err(Trap::IntegerDivideByZero)
+        );
+        assert_eq!(
+            run(I32DivS, vec![Val::I32(i32::MIN), Val::I32(-1)]),
+            Err(Trap::IntegerOverflow)
+        );
+        assert_eq!(
+            run(I32RemS, vec![Val::I32(i32::MIN), Val::I32(-1)]),
+            Ok(Val::I32(0))
+        );
+        assert_eq!(
+            run(I32DivU, vec![Val::I32(-1), Val::I32(2)]),
+            Ok(Val::I32(i32::MAX))
+        );
+        assert_eq!(
+            run(I64Shl, vec![Val::I64(1), Val::I64(65)]),
+            Ok(Val::I64(2))
+        );
+        assert_eq!(
+            run(I64Rotr, vec![Val::I64(1), Val::I64(-1)]),
+            Ok(Val::I64(2))
+        );
+        assert_eq!(
+            run(I32ShrU, vec![Val::I32(-1), Val::I32(31)]),
+            Ok(Val::I32(1))
+        );
+        assert_eq!(run(I64Clz, vec![Val::I64(1)]), Ok(Val::I64(63)));
+        assert_eq!(run(I32Extend8S, vec![Val::I32(0x80)]), Ok(Val::I32(-128)));
+        assert_eq!(
+            run(I64ExtendI32U, vec![Val::I32(-1)]),
+            Ok(Val::I64(0xFFFFFFFF))
+        );
+    }
+
+    #[test]
+    fn test_float() {
+        use NumericInstruction::*;
+
+        assert_eq!(run(F32Nearest, vec![Val::F32(-2.5)]), Ok(Val::F32(-2.0)));
+        assert_eq!(
+            run(F64Min, vec![Val::F64(0.0), Val::F64(-0.0)]).map(|val| match val {
+                Val::F64(z) => z.is_sign_negative(),
+                _ => false,
+            }),
+            Ok(true)
+        );
+        assert!(matches!(
+            run(F32Max, vec![Val::F32(f32::NAN), Val::F32(1.0)]),
+            Ok(Val::F32(z)) if z.is_nan()
+        ));
+        assert_eq!(
+            run(F64CopySign, vec![Val::F64(1.0), Val::F64(-0.0)]),
+            Ok(Val::F64(-1.0))
+        );
+        assert_eq!(
+            run(F32ConvertI64u, vec![Val::I64(-1)]),
+            Ok(Val::F32(18446744073709551616.0))
+        );
+        assert_eq!(
+            run(I32ReinterpretF32, vec![Val::F32(-0.0)]),
+            Ok(Val::I32(i32::MIN))
+        );
+    }
+
+    #[test]
+    fn test_trunc() {
+        use NumericInstruction::*;
+
+        assert_eq!(run(I32TruncF32S, vec![Val::F32(-1.9)]), Ok(Val::I32(-1)));
+        assert_eq!(run(I32TruncF64U, vec![Val::F64(-0.9)]), Ok(Val::I32(0)));
+        assert_eq!(
+            run(I32TruncF64S, vec![Val::F64(2147483647.9)]),
+            Ok(Val::I32(i32::MAX))
+        );
+        assert_eq!(
+            run(I32TruncF64S, vec![Val::F64(2147483648.0)]),
+            Err(Trap::IntegerOverflow)
+        );
+        assert_eq!(
+            run(I64TruncF32U, vec![Val::F32(-1.0)]),
+            Err(Trap::IntegerOverflow)
+        );
+        assert_eq!(
+            run(I64TruncF64S, vec![Val::F64(f64::NAN)]),
+            Err(Trap::InvalidConversionToInteger)
+        );
+
+        let mut stack = vec![Val::F64(-1e10)];
+        saturate(
+            &SaturatingTruncationInstruction::I32TruncSatF64S,
+            &mut stack,
+        );
+        assert_eq!(stack, vec![Val::I32(i32::MIN)]);
+        let mut stack = vec![Val::F32(f32::NAN)];
+        saturate(
+            &SaturatingTruncationInstruction::I64TruncSatF32U,
+            &mut stack,
+        );
+        assert_eq!(stack, vec![Val::I64(0)]);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::instructions::MemArg;
+
+use super::{trap::Trap, values::Val};
+
+/// Pop an operand off the operand stack
+///
+/// Validation guarantees that every instruction finds operands of the right types, so a missing or mismatched operand
+/// is a bug in the runtime rather than in the program, and the typed variants below panic on one.
+pub fn pop(stack: &mut Vec<Val>) -> Val {
+    stack
+        .pop()
+        .expect("validation guarantees the operand stack holds the operands")
+}
+
+pub fn pop_v128(stack: &mut Vec<Val>) -> u128 {
+    match pop(stack) {
+        Val::V128(v) => v,
+        val => unreachable!("expected a v128 operand, found {:?}", val),
+    }
+}
+
+pub fn pop_i32(stack: &mut Vec<Val>) -> i32 {
+    match pop(stack) {
+        Val::I32(n) => n,
+        val => unreachable!("expected an i32 operand, found {:?}", val),
+    }
+}
+
+pub fn pop_i64(stack: &mut Vec<Val>) -> i64 {
+    match pop(stack) {
+        Val::I64(n) => n,
+        val => unreachable!("expected an i64 operand, found {:?}", val),
+    }
+}
+
+/// Pop an address, size or length operand of a memory, which is an i64 for 64-bit memories and an i32 otherwise
+pub fn pop_addr(stack: &mut Vec<Val>, memory64: bool) -> u64 {
+    if memory64 {
+        pop_i64(stack) as u64
+    } else {
+        pop_i32(stack) as u32 as u64
+    }
+}
+
+/// Pop the address operand and add the static offset, trapping if that overflows
+pub fn effective_address(
+    stack: &mut Vec<Val>,
+    memarg: &MemArg,
+    memory64: bool,
+) -> Result<u64, Trap> {
+    pop_addr(stack, memory64)
+        .checked_add(memarg.offset)
+        .ok_or(Trap::OutOfBoundsMemoryAccess)
+}
+
+pub fn pop_f32(stack: &mut Vec<Val>) -> f32 {
+    match pop(stack) {
+        Val::F32(z) => z,
+        val => unreachable!("expected an f32 operand, found {:?}", val),
+    }
+}
+
+pub fn pop_f64(stack: &mut Vec<Val>) -> f64 {
+    match pop(stack) {
+        Val::F64(z) => z,
+        val => unreachable!("expected an f64 operand, found {:?}", val),
+    }
+}
+
+pub fn pop_ref(stack: &mut Vec<Val>) -> Val {
+    match pop(stack) {
+        val @ (Val::FuncRef(_) | Val::ExternRef(_) | Val::ExnRef(_) | Val::AnyRef(_)) => val,
+        val => unreachable!("expected a reference operand, found {:?}", val),
+    }
+}
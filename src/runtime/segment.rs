@@ -0,0 +1,13 @@
+use super::values::Val;
+
+/// The runtime representation of an element segment, which is emptied once it is dropped
+#[derive(Debug, Default, PartialEq)]
+pub struct ElemInst {
+    pub elements: Vec<Val>,
+}
+
+/// The runtime representation of a data segment, which is emptied once it is dropped
+#[derive(Debug, Default, PartialEq)]
+pub struct DataInst {
+    pub data: Vec<u8>,
+}
@@ -0,0 +1,411 @@
+use crate::module::types::{
+    DefType, FuncType, GlobalType, MemType, Mutability, TableType, ValType,
+};
+
+use super::{
+    function::{FuncInst, HostFunc},
+    global::GlobalInst,
+    heap::Heap,
+    instance::ModuleInst,
+    interrupt::InterruptHandle,
+    limiter::ResourceLimiter,
+    memory::{MemInst, SharedMemory},
+    segment::{DataInst, ElemInst},
+    table::TableInst,
+    tag::TagInst,
+    trap::Trap,
+    values::Val,
+};
+
+/// The address of a function instance in the store
+pub type FuncAddr = usize;
+/// The address of a table instance in the store
+pub type TableAddr = usize;
+/// The address of a memory instance in the store
+pub type MemAddr = usize;
+/// The address of a global instance in the store
+pub type GlobalAddr = usize;
+/// The address of an element segment instance in the store
+pub type ElemAddr = usize;
+/// The address of a data segment instance in the store
+pub type DataAddr = usize;
+/// The address of a tag instance in the store
+pub type TagAddr = usize;
+/// The address of a module instance in the store
+pub type InstanceAddr = usize;
+
+/// All of the global state that can be manipulated by Wasm programs
+#[derive(Default)]
+pub struct Store {
+    limiter: Option<Box<dyn ResourceLimiter>>,
+    interrupt: InterruptHandle,
+    pub(super) funcs: Vec<FuncInst>,
+    pub(super) tables: Vec<TableInst>,
+    pub(super) memories: Vec<MemInst>,
+    pub(super) globals: Vec<GlobalInst>,
+    pub(super) elems: Vec<ElemInst>,
+    pub(super) datas: Vec<DataInst>,
+    pub(super) tags: Vec<TagInst>,
+    pub(super) instances: Vec<ModuleInst>,
+    pub(super) heap: Heap,
+}
+
+impl Store {
+    /// Create an empty store without any resource limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the limiter that is consulted whenever a memory or table is allocated or grows
+    pub fn limiter(&mut self, limiter: impl ResourceLimiter + 'static) {
+        self.limiter = Some(Box::new(limiter));
+    }
+
+    /// Get a handle that can interrupt execution in this store, including from another thread
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Trap with `Trap::Interrupted` if an interruption was requested since the last check
+    ///
+    /// The interpreter calls this on every function entry and loop back-edge.
+    pub fn check_interrupt(&self) -> Result<(), Trap> {
+        if self.interrupt.take() {
+            Err(Trap::Interrupted)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Allocate a host function that can be provided as an import
+    pub fn alloc_func(
+        &mut self,
+        ty: FuncType,
+        func: impl Fn(&[Val]) -> Result<Vec<Val>, Trap> + 'static,
+    ) -> FuncAddr {
+        let func: HostFunc = Box::new(func);
+        self.funcs.push(FuncInst::Host { ty, func });
+        self.funcs.len() - 1
+    }
+
+    /// Allocate a table, consulting the limiter for its initial size
+    pub fn alloc_table(&mut self, tt: &TableType) -> Result<TableAddr, Trap> {
+        let table =
+            TableInst::new(tt, limiter(&mut self.limiter))?.ok_or(Trap::ResourceLimitExceeded)?;
+        self.tables.push(table);
+        Ok(self.tables.len() - 1)
+    }
+
+    /// Allocate a memory, consulting the limiter for its initial size
+    pub fn alloc_memory(&mut self, mt: &MemType) -> Result<MemAddr, Trap> {
+        let memory =
+            MemInst::new(mt, limiter(&mut self.limiter))?.ok_or(Trap::ResourceLimitExceeded)?;
+        self.memories.push(memory);
+        Ok(self.memories.len() - 1)
+    }
+
+    /// Add a shared memory, which may also be in use by stores on other threads
+    pub fn alloc_shared_memory(&mut self, shared: SharedMemory) -> MemAddr {
+        self.memories.push(MemInst::from_shared(shared));
+        self.memories.len() - 1
+    }
+
+    /// A handle to a memory that can be sent to other threads, if the memory is shared
+    pub fn shared_memory(&self, addr: MemAddr) -> Option<SharedMemory> {
+        self.memories[addr].shared().cloned()
+    }
+
+    /// Allocate a global holding the given value
+    pub fn alloc_global(&mut self, ty: GlobalType, value: Val) -> GlobalAddr {
+        self.globals.push(GlobalInst { ty, value });
+        self.globals.len() - 1
+    }
+
+    /// Allocate a tag for exceptions whose payload has the parameter types of `ty`, which must not have results
+    pub fn alloc_tag(&mut self, ty: FuncType) -> TagAddr {
+        self.tags.push(TagInst { ty });
+        self.tags.len() - 1
+    }
+
+    /// Free the GC objects that are no longer reachable, which otherwise happens as more objects are allocated
+    pub fn collect_garbage(&mut self) {
+        self.collect(&[]);
+    }
+
+    /// Collect garbage with `roots` as well as the globals, tables and element segments as roots
+    pub(super) fn collect(&mut self, roots: &[&[Val]]) {
+        let globals = self.globals.iter().map(|global| &global.value);
+        let tables = self.tables.iter().flat_map(|table| &table.elements);
+        let elems = self.elems.iter().flat_map(|elem| &elem.elements);
+        let roots = roots.iter().flat_map(|vals| vals.iter());
+        self.heap
+            .collect(roots.chain(globals).chain(tables).chain(elems));
+    }
+
+    /// Read an element of a table
+    pub fn table_get(&self, addr: TableAddr, index: u32) -> Result<Val, Trap> {
+        self.tables[addr]
+            .elements
+            .get(index as usize)
+            .cloned()
+            .ok_or(Trap::OutOfBoundsTableAccess)
+    }
+
+    /// Write an element of a table. The value must be a reference of the table's element type
+    pub fn table_set(&mut self, addr: TableAddr, index: u32, value: Val) -> Result<(), Trap> {
+        let table = &mut self.tables[addr];
+        if !value.matches(&ValType::RefType(table.et.clone())) {
+            return Err(Trap::TypeMismatch);
+        }
+        let element = table
+            .elements
+            .get_mut(index as usize)
+            .ok_or(Trap::OutOfBoundsTableAccess)?;
+        *element = value;
+        Ok(())
+    }
+
+    /// Read the value of a global
+    pub fn global_get(&self, addr: GlobalAddr) -> Val {
+        self.globals[addr].value.clone()
+    }
+
+    /// Write the value of a global. The global must be mutable and the value must match its type
+    pub fn global_set(&mut self, addr: GlobalAddr, value: Val) -> Result<(), Trap> {
+        let global = &mut self.globals[addr];
+        if global.ty.m == Mutability::Const || !value.matches(&global.ty.t) {
+            return Err(Trap::TypeMismatch);
+        }
+        global.value = value;
+        Ok(())
+    }
+
+    /// Invoke a function with the given arguments, returning all of its results
+    ///
+    /// A non-null reference argument of a concrete heap type must have that type in the function's module.
+    pub fn invoke(&mut self, addr: FuncAddr, args: &[Val]) -> Result<Vec<Val>, Trap> {
+        let func = &self.funcs[addr];
+        if !self.vals_match(args, self.func_types(func), &func.ty().rt1) {
+            return Err(Trap::TypeMismatch);
+        }
+        self.invoke_validated(addr, args)
+    }
+
+    /// Call a function whose arguments validation has already checked against its type
+    pub(super) fn invoke_validated(
+        &mut self,
+        addr: FuncAddr,
+        args: &[Val],
+    ) -> Result<Vec<Val>, Trap> {
+        self.check_interrupt()?;
+        match &self.funcs[addr] {
+            FuncInst::Host { ty, func } => {
+                // Host functions can return anything, so check their results before Wasm code relies on them
+                let results = func(args)?;
+                if !self.vals_match(&results, &[], &ty.rt2) {
+                    return Err(Trap::TypeMismatch);
+                }
+                Ok(results)
+            }
+            FuncInst::Wasm { .. } => self.call_wasm(addr, args.to_vec()),
+        }
+    }
+
+    /// The types that the concrete heap types in a function's type refer to
+    ///
+    /// Host functions don't belong to a module, so a non-null reference never matches a concrete heap type in theirs.
+    fn func_types(&self, func: &FuncInst) -> &[DefType] {
+        match func {
+            FuncInst::Wasm { instance, .. } => &self.instances[*instance].types,
+            FuncInst::Host { .. } => &[],
+        }
+    }
+
+    /// Whether values have the given types, whose type indices refer to `types`
+    fn vals_match(&self, vals: &[Val], types: &[DefType], ts: &[ValType]) -> bool {
+        vals.len() == ts.len()
+            && vals.iter().zip(ts).all(|(val, t)| match t {
+                ValType::RefType(rt) if !val.is_null() => self.ref_matches(val, types, rt),
+                t => val.matches(t),
+            })
+    }
+
+    /// Grow a memory by `delta` pages with the semantics of `memory.grow`, returning the previous size or -1
+    ///
+    /// Sizes are in pages and are only larger than `u32::MAX` for 64-bit memories.
+    pub fn memory_grow(&mut self, addr: MemAddr, delta: u64) -> Result<i64, Trap> {
+        let memory = &mut self.memories[addr];
+        let old = memory.grow(delta, limiter(&mut self.limiter))?;
+        Ok(old.map_or(-1, |old| old as i64))
+    }
+
+    /// Grow a table by `delta` elements with the semantics of `table.grow`, returning the previous size or -1
+    pub fn table_grow(&mut self, addr: TableAddr, delta: u32, init: Val) -> Result<i32, Trap> {
+        let table = &mut self.tables[addr];
+        let old = table.grow(delta, init, limiter(&mut self.limiter))?;
+        Ok(old.map_or(-1, |old| old as i32))
+    }
+}
+
+/// Borrow the store's limiter, if one is set, in the form the instances expect
+fn limiter(limiter: &mut Option<Box<dyn ResourceLimiter>>) -> Option<&mut dyn ResourceLimiter> {
+    limiter
+        .as_mut()
+        .map(|limiter| limiter.as_mut() as &mut dyn ResourceLimiter)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        module::types::{Limits, NumType, RefType},
+        runtime::values::ExternRef,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_interrupt() {
+        let store = Store::new();
+        let handle = store.interrupt_handle();
+        assert_eq!(store.check_interrupt(), Ok(()));
+        std::thread::spawn(move || handle.interrupt())
+            .join()
+            .unwrap();
+        assert_eq!(store.check_interrupt(), Err(Trap::Interrupted));
+        assert_eq!(store.check_interrupt(), Ok(()));
+    }
+
+    #[test]
+    fn test_invoke_host_func() {
+        let mut store = Store::new();
+        let ty = FuncType {
+            rt1: vec![ValType::NumType(NumType::I32)],
+            rt2: vec![ValType::NumType(NumType::I32)],
+        };
+        let addr = store.alloc_func(ty, |args| match args {
+            [Val::I32(n)] => Ok(vec![Val::I32(n + 1)]),
+            _ => unreachable!(),
+        });
+        assert_eq!(store.invoke(addr, &[Val::I32(1)]), Ok(vec!(Val::I32(2))));
+
+        store.interrupt_handle().interrupt();
+        assert_eq!(store.invoke(addr, &[Val::I32(1)]), Err(Trap::Interrupted));
+
+        let ty = FuncType {
+            rt1: vec![],
+            rt2: vec![
+                ValType::NumType(NumType::I32),
+                ValType::NumType(NumType::I32),
+            ],
+        };
+        let addr = store.alloc_func(ty, |_| Ok(vec![Val::I32(1)]));
+        assert_eq!(store.invoke(addr, &[]), Err(Trap::TypeMismatch));
+    }
+
+    #[test]
+    fn test_grow() {
+        let mut store = Store::new();
+        let memory = store
+            .alloc_memory(&MemType {
+                lim: Limits { min: 1, max: None },
+                shared: false,
+                memory64: false,
+            })
+            .unwrap();
+        let table = store
+            .alloc_table(&TableType {
+                lim: Limits {
+                    min: 0,
+                    max: Some(1),
+                },
+                et: RefType::FUNCREF,
+            })
+            .unwrap();
+        assert_eq!(store.memory_grow(memory, 1), Ok(1));
+        assert_eq!(store.table_grow(table, 1, Val::FuncRef(None)), Ok(0));
+        assert_eq!(store.table_grow(table, 1, Val::FuncRef(None)), Ok(-1));
+    }
+
+    #[test]
+    fn test_extern_refs() {
+        let mut store = Store::new();
+        let table = store
+            .alloc_table(&TableType {
+                lim: Limits { min: 1, max: None },
+                et: RefType::EXTERNREF,
+            })
+            .unwrap();
+        let global = store.alloc_global(
+            GlobalType {
+                m: Mutability::Var,
+                t: ValType::RefType(RefType::EXTERNREF),
+            },
+            Val::ExternRef(None),
+        );
+
+        let handle = Val::ExternRef(Some(ExternRef::new(42u64)));
+        assert_eq!(store.table_get(table, 0), Ok(Val::ExternRef(None)));
+        assert_eq!(store.table_set(table, 0, handle.clone()), Ok(()));
+        assert_eq!(store.table_get(table, 0), Ok(handle.clone()));
+        assert_eq!(
+            store.table_set(table, 1, handle.clone()),
+            Err(Trap::OutOfBoundsTableAccess)
+        );
+
+        assert_eq!(store.global_set(global, handle.clone()), Ok(()));
+        match store.global_get(global) {
+            Val::ExternRef(Some(object)) => {
+                assert_eq!(object.data().downcast_ref::<u64>(), Some(&42))
+            }
+            _ => panic!("expected an externref"),
+        }
+    }
+
+    #[test]
+    fn test_table_set_type_mismatch() {
+        let mut store = Store::new();
+        let table = store
+            .alloc_table(&TableType {
+                lim: Limits { min: 1, max: None },
+                et: RefType::FUNCREF,
+            })
+            .unwrap();
+        let handle = Val::ExternRef(Some(ExternRef::new(42u64)));
+        assert_eq!(store.table_set(table, 0, handle), Err(Trap::TypeMismatch));
+        assert_eq!(
+            store.table_set(table, 0, Val::I32(0)),
+            Err(Trap::TypeMismatch)
+        );
+        assert_eq!(store.table_get(table, 0), Ok(Val::FuncRef(None)));
+    }
+
+    #[test]
+    fn test_global_set_type_mismatch() {
+        let mut store = Store::new();
+        let constant = store.alloc_global(
+            GlobalType {
+                m: Mutability::Const,
+                t: ValType::NumType(NumType::I32),
+            },
+            Val::I32(1),
+        );
+        let variable = store.alloc_global(
+            GlobalType {
+                m: Mutability::Var,
+                t: ValType::NumType(NumType::I32),
+            },
+            Val::I32(1),
+        );
+        assert_eq!(
+            store.global_set(constant, Val::I32(2)),
+            Err(Trap::TypeMismatch)
+        );
+        assert_eq!(store.global_get(constant), Val::I32(1));
+        assert_eq!(
+            store.global_set(variable, Val::I64(2)),
+            Err(Trap::TypeMismatch)
+        );
+        assert_eq!(store.global_set(variable, Val::I32(2)), Ok(()));
+        assert_eq!(store.global_get(variable), Val::I32(2));
+    }
+}
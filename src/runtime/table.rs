@@ -0,0 +1,105 @@
+use crate::module::types::{RefType, TableType};
+
+use super::{limiter::ResourceLimiter, trap::Trap, values::Val};
+
+/// The runtime representation of a table
+#[derive(Debug, PartialEq)]
+pub struct TableInst {
+    pub et: RefType,
+    pub max: Option<u32>,
+    pub elements: Vec<Val>,
+}
+
+impl TableInst {
+    /// Allocate a table of the given type, or `None` if the limiter denies it
+    pub fn new(
+        tt: &TableType,
+        limiter: Option<&mut dyn ResourceLimiter>,
+    ) -> Result<Option<Self>, Trap> {
+        // Table limits are decoded from 32-bit bounds, so they always fit
+        let (min, max) = (tt.lim.min as u32, tt.lim.max.map(|max| max as u32));
+        if let Some(limiter) = limiter {
+            if !limiter.table_growing(0, min, max)? {
+                return Ok(None);
+            }
+        }
+        Ok(Some(Self {
+            et: tt.et.clone(),
+            max,
+            elements: vec![Val::null(&tt.et.ht); min as usize],
+        }))
+    }
+
+    /// The current number of elements in the table
+    pub fn size(&self) -> u32 {
+        self.elements.len() as u32
+    }
+
+    /// Grow the table by `delta` elements set to `init`, returning the previous size or `None` if the growth was denied
+    pub fn grow(
+        &mut self,
+        delta: u32,
+        init: Val,
+        limiter: Option<&mut dyn ResourceLimiter>,
+    ) -> Result<Option<u32>, Trap> {
+        let old = self.size();
+        let new = match old.checked_add(delta) {
+            Some(new) if self.max.is_none_or(|max| new <= max) => new,
+            _ => return Ok(None),
+        };
+        if let Some(limiter) = limiter {
+            if !limiter.table_growing(old, new, self.max)? {
+                return Ok(None);
+            }
+        }
+        self.elements.resize(new as usize, init);
+        Ok(Some(old))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        module::types::Limits,
+        runtime::{
+            limiter::StoreLimits,
+            values::{ExternRef, FuncHandle},
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_table_inst_grow() {
+        let tt = TableType {
+            lim: Limits {
+                min: 1,
+                max: Some(2),
+            },
+            et: RefType::FUNCREF,
+        };
+        let mut table = TableInst::new(&tt, None).unwrap().unwrap();
+        let init = Val::FuncRef(Some(FuncHandle(7)));
+        assert_eq!(table.grow(1, init.clone(), None), Ok(Some(1)));
+        assert_eq!(table.elements, vec!(Val::FuncRef(None), init));
+        assert_eq!(table.grow(1, Val::FuncRef(None), None), Ok(None));
+    }
+
+    #[test]
+    fn test_table_inst_limiter() {
+        let tt = TableType {
+            lim: Limits { min: 1, max: None },
+            et: RefType::EXTERNREF,
+        };
+        let mut limits = StoreLimits {
+            memory_size: None,
+            table_elements: Some(2),
+        };
+        let mut table = TableInst::new(&tt, Some(&mut limits)).unwrap().unwrap();
+        let init = Val::ExternRef(Some(ExternRef::new(())));
+        assert_eq!(table.grow(1, init.clone(), Some(&mut limits)), Ok(Some(1)));
+        assert_eq!(table.grow(1, init.clone(), Some(&mut limits)), Ok(None));
+        assert_eq!(table.elements, vec!(Val::ExternRef(None), init));
+        assert_eq!(table.size(), 2);
+    }
+}
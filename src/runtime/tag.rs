@@ -0,0 +1,9 @@
+use crate::module::types::FuncType;
+
+/// The runtime representation of an exception tag
+///
+/// Tags are compared by address rather than type, so two tags with the same type still catch different exceptions.
+#[derive(Debug, PartialEq)]
+pub struct TagInst {
+    pub ty: FuncType,
+}
@@ -0,0 +1,93 @@
+use std::{fmt, rc::Rc};
+
+use super::values::Exception;
+
+/// An error that aborts execution
+#[derive(Debug, PartialEq)]
+pub enum Trap {
+    /// A resource limiter refused to let a memory or table grow
+    ResourceLimitExceeded,
+    /// Execution was cancelled through an `InterruptHandle`
+    Interrupted,
+    /// A memory was accessed outside of its bounds
+    OutOfBoundsMemoryAccess,
+    /// A table was accessed outside of its bounds
+    OutOfBoundsTableAccess,
+    /// An `unreachable` instruction was executed
+    Unreachable,
+    /// An integer was divided by zero
+    IntegerDivideByZero,
+    /// The result of an integer operation or conversion does not fit its type
+    IntegerOverflow,
+    /// A NaN was converted to an integer
+    InvalidConversionToInteger,
+    /// An indirect call referred to an element outside of its table
+    UndefinedElement,
+    /// An indirect call referred to a null element
+    UninitializedElement,
+    /// An indirect call referred to a function of a different type than expected
+    IndirectCallTypeMismatch,
+    /// Calls were nested too deeply
+    CallStackExhausted,
+    /// An atomic instruction accessed an address that is not a multiple of its width
+    UnalignedAtomic,
+    /// `memory.atomic.wait` was executed on a memory that is not shared
+    ExpectedSharedMemory,
+    /// The host passed a value that does not match the type of the function, table or global it was given to, or
+    /// wrote to an immutable global
+    TypeMismatch,
+    /// `call_ref` or `return_call_ref` was given a null function reference
+    NullFunctionReference,
+    /// `ref.as_non_null` was given a null reference
+    NullReference,
+    /// `throw_ref` was given a null exception reference
+    NullExceptionReference,
+    /// A struct instruction was given a null reference
+    NullStructureReference,
+    /// An array instruction was given a null reference
+    NullArrayReference,
+    /// `i31.get_s` or `i31.get_u` was given a null reference
+    NullI31Reference,
+    /// An array was accessed outside of its bounds
+    OutOfBoundsArrayAccess,
+    /// `ref.cast` was given a reference that does not have the target type
+    CastFailure,
+    /// An exception was thrown and not caught, carrying its tag and payload to the host
+    Exception(Rc<Exception>),
+    /// The runtime does not support the operation yet
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ResourceLimitExceeded => write!(f, "resource limit exceeded"),
+            Self::Interrupted => write!(f, "interrupted"),
+            Self::OutOfBoundsMemoryAccess => write!(f, "out of bounds memory access"),
+            Self::OutOfBoundsTableAccess => write!(f, "out of bounds table access"),
+            Self::Unreachable => write!(f, "unreachable"),
+            Self::IntegerDivideByZero => write!(f, "integer divide by zero"),
+            Self::IntegerOverflow => write!(f, "integer overflow"),
+            Self::InvalidConversionToInteger => write!(f, "invalid conversion to integer"),
+            Self::UndefinedElement => write!(f, "undefined element"),
+            Self::UninitializedElement => write!(f, "uninitialized element"),
+            Self::IndirectCallTypeMismatch => write!(f, "indirect call type mismatch"),
+            Self::CallStackExhausted => write!(f, "call stack exhausted"),
+            Self::UnalignedAtomic => write!(f, "unaligned atomic"),
+            Self::ExpectedSharedMemory => write!(f, "expected shared memory"),
+            Self::TypeMismatch => write!(f, "type mismatch"),
+            Self::NullFunctionReference => write!(f, "null function reference"),
+            Self::NullReference => write!(f, "null reference"),
+            Self::NullExceptionReference => write!(f, "null exception reference"),
+            Self::NullStructureReference => write!(f, "null structure reference"),
+            Self::NullArrayReference => write!(f, "null array reference"),
+            Self::NullI31Reference => write!(f, "null i31 reference"),
+            Self::OutOfBoundsArrayAccess => write!(f, "out of bounds array access"),
+            Self::CastFailure => write!(f, "cast failure"),
+            Self::Exception(_) => write!(f, "uncaught exception"),
+            Self::Unsupported(operation) => write!(f, "unsupported: {}", operation),
+        }
+    }
+}
+
+impl std::error::Error for Trap {}
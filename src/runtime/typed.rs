@@ -0,0 +1,214 @@
+use std::rc::Rc;
+
+use crate::module::types::{FuncType, NumType, RefType, ValType, VecType};
+
+use super::{
+    store::{FuncAddr, Store},
+    trap::Trap,
+    values::{AnyRef, Exception, ExternRef, FuncHandle, Val},
+};
+
+/// A Rust type that corresponds to a Wasm value type
+pub trait WasmTy: Sized {
+    /// The Wasm value type
+    fn ty() -> ValType;
+    /// Convert to a Wasm value
+    fn into_val(self) -> Val;
+    /// Convert from a Wasm value, if it has the right type
+    fn from_val(val: Val) -> Option<Self>;
+}
+
+macro_rules! wasm_ty {
+    ($t:ty, $variant:ident, $val_type:expr) => {
+        impl WasmTy for $t {
+            fn ty() -> ValType {
+                $val_type
+            }
+
+            fn into_val(self) -> Val {
+                Val::$variant(self)
+            }
+
+            fn from_val(val: Val) -> Option<Self> {
+                match val {
+                    Val::$variant(z) => Some(z),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+wasm_ty!(i32, I32, ValType::NumType(NumType::I32));
+wasm_ty!(i64, I64, ValType::NumType(NumType::I64));
+wasm_ty!(f32, F32, ValType::NumType(NumType::F32));
+wasm_ty!(f64, F64, ValType::NumType(NumType::F64));
+wasm_ty!(u128, V128, ValType::VecType(VecType::V128));
+wasm_ty!(
+    Option<FuncHandle>,
+    FuncRef,
+    ValType::RefType(RefType::FUNCREF)
+);
+wasm_ty!(
+    Option<ExternRef>,
+    ExternRef,
+    ValType::RefType(RefType::EXTERNREF)
+);
+
+wasm_ty!(
+    Option<Rc<Exception>>,
+    ExnRef,
+    ValType::RefType(RefType::EXNREF)
+);
+wasm_ty!(Option<AnyRef>, AnyRef, ValType::RefType(RefType::ANYREF));
+
+/// A sequence of Rust types that corresponds to the parameters or results of a Wasm function
+///
+/// Functions without results use `()`, functions with a single result use the bare type, and functions with
+/// multiple results use a tuple.
+pub trait WasmTuple: Sized {
+    /// The Wasm value types, in order
+    fn types() -> Vec<ValType>;
+    /// Convert to Wasm values
+    fn into_vals(self) -> Vec<Val>;
+    /// Convert from Wasm values, if there are the right number of them and they have the right types
+    fn from_vals(vals: Vec<Val>) -> Option<Self>;
+}
+
+impl<T: WasmTy> WasmTuple for T {
+    fn types() -> Vec<ValType> {
+        vec![T::ty()]
+    }
+
+    fn into_vals(self) -> Vec<Val> {
+        vec![self.into_val()]
+    }
+
+    fn from_vals(vals: Vec<Val>) -> Option<Self> {
+        let [val] = <[Val; 1]>::try_from(vals).ok()?;
+        T::from_val(val)
+    }
+}
+
+macro_rules! wasm_tuple {
+    ($($t:ident)*) => {
+        #[allow(non_snake_case)]
+        impl<$($t: WasmTy),*> WasmTuple for ($($t,)*) {
+            fn types() -> Vec<ValType> {
+                vec![$($t::ty()),*]
+            }
+
+            fn into_vals(self) -> Vec<Val> {
+                let ($($t,)*) = self;
+                vec![$($t.into_val()),*]
+            }
+
+            fn from_vals(vals: Vec<Val>) -> Option<Self> {
+                let mut vals = vals.into_iter();
+                let tuple = ($($t::from_val(vals.next()?)?,)*);
+                vals.next().is_none().then_some(tuple)
+            }
+        }
+    };
+}
+
+wasm_tuple!();
+wasm_tuple!(A);
+wasm_tuple!(A B);
+wasm_tuple!(A B C);
+wasm_tuple!(A B C D);
+wasm_tuple!(A B C D E);
+wasm_tuple!(A B C D E F);
+wasm_tuple!(A B C D E F G);
+wasm_tuple!(A B C D E F G H);
+
+impl Store {
+    /// Call a function with Rust parameters and results, which must match the function's type
+    pub fn call<P: WasmTuple, R: WasmTuple>(
+        &mut self,
+        addr: FuncAddr,
+        params: P,
+    ) -> Result<R, Trap> {
+        let ty = self.funcs[addr].ty();
+        if ty.rt1 != P::types() || ty.rt2 != R::types() {
+            return Err(Trap::TypeMismatch);
+        }
+        let results = self.invoke(addr, &params.into_vals())?;
+        Ok(R::from_vals(results).expect("results match the function type"))
+    }
+
+    /// Allocate a host function whose type is given by its Rust parameters and results
+    pub fn alloc_typed_func<P: WasmTuple, R: WasmTuple>(
+        &mut self,
+        func: impl Fn(P) -> Result<R, Trap> + 'static,
+    ) -> FuncAddr {
+        let ty = FuncType {
+            rt1: P::types(),
+            rt2: R::types(),
+        };
+        self.alloc_func(ty, move |args| {
+            let params = P::from_vals(args.to_vec()).expect("arguments match the function type");
+            Ok(func(params)?.into_vals())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wasm_tuple() {
+        assert_eq!(<()>::types(), vec!());
+        assert_eq!(i32::types(), vec!(ValType::NumType(NumType::I32)));
+        assert_eq!(
+            <(i64, f32)>::types(),
+            vec!(
+                ValType::NumType(NumType::I64),
+                ValType::NumType(NumType::F32)
+            )
+        );
+        assert_eq!((1i32, 2.0f64).into_vals(), vec!(Val::I32(1), Val::F64(2.0)));
+        assert_eq!(
+            <(i32, f64)>::from_vals(vec!(Val::I32(1), Val::F64(2.0))),
+            Some((1, 2.0))
+        );
+        assert_eq!(<(i32, f64)>::from_vals(vec!(Val::I32(1))), None);
+        assert_eq!(
+            <(i32, f64)>::from_vals(vec!(Val::I32(1), Val::F64(2.0), Val::I32(3))),
+            None
+        );
+        assert_eq!(i32::from_vals(vec!(Val::I64(1))), None);
+        assert_eq!(
+            <Option<FuncHandle>>::from_vals(vec!(Val::FuncRef(None))),
+            Some(None)
+        );
+    }
+
+    #[test]
+    fn test_typed_host_func() {
+        let mut store = Store::new();
+        let divmod = store.alloc_typed_func(|(a, b): (i32, i32)| {
+            if b == 0 {
+                return Err(Trap::IntegerDivideByZero);
+            }
+            Ok((a / b, a % b))
+        });
+        assert_eq!(store.call::<_, (i32, i32)>(divmod, (7, 2)), Ok((3, 1)));
+        assert_eq!(
+            store.call::<_, (i32, i32)>(divmod, (7, 0)),
+            Err(Trap::IntegerDivideByZero)
+        );
+        assert_eq!(
+            store.call::<_, (i32, i64)>(divmod, (7, 2)),
+            Err(Trap::TypeMismatch)
+        );
+        assert_eq!(
+            store.invoke(divmod, &[Val::I32(9), Val::I32(4)]),
+            Ok(vec!(Val::I32(2), Val::I32(1)))
+        );
+
+        let unit = store.alloc_typed_func(|()| Ok(()));
+        assert_eq!(store.call::<_, ()>(unit, ()), Ok(()));
+    }
+}
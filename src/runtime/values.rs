@@ -0,0 +1,272 @@
+use std::{any::Any, fmt, rc::Rc};
+
+use crate::module::types::{HeapType, NumType, RefType, ValType, VecType};
+
+use super::{
+    heap::GcRef,
+    store::{FuncAddr, TagAddr},
+};
+
+/// A value that Wasm code can compute with
+#[derive(Clone, Debug, PartialEq)]
+pub enum Val {
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    V128(u128),
+    FuncRef(Option<FuncHandle>),
+    ExternRef(Option<ExternRef>),
+    ExnRef(Option<Rc<Exception>>),
+    AnyRef(Option<AnyRef>),
+}
+
+impl Val {
+    /// The null reference to the given heap type
+    ///
+    /// A concrete heap type is taken to be a function type, since only the module that defines it can tell whether it
+    /// is a struct or array type instead. `ModuleInst::null` resolves those.
+    pub fn null(ht: &HeapType) -> Self {
+        match ht {
+            HeapType::Func | HeapType::NoFunc | HeapType::Type(_) => Self::FuncRef(None),
+            HeapType::Extern | HeapType::NoExtern => Self::ExternRef(None),
+            HeapType::Exn | HeapType::NoExn => Self::ExnRef(None),
+            HeapType::Any
+            | HeapType::Eq
+            | HeapType::I31
+            | HeapType::Struct
+            | HeapType::Array
+            | HeapType::None => Self::AnyRef(None),
+        }
+    }
+
+    /// The default value of a type, which is zero for numbers and vectors and null for references
+    pub fn default(t: &ValType) -> Self {
+        match t {
+            ValType::NumType(NumType::I32) => Self::I32(0),
+            ValType::NumType(NumType::I64) => Self::I64(0),
+            ValType::NumType(NumType::F32) => Self::F32(0.0),
+            ValType::NumType(NumType::F64) => Self::F64(0.0),
+            ValType::VecType(VecType::V128) => Self::V128(0),
+            ValType::RefType(t) => Self::null(&t.ht),
+        }
+    }
+
+    /// Whether the value is a null reference, with the semantics of `ref.is_null`
+    pub fn is_null(&self) -> bool {
+        matches!(
+            self,
+            Self::FuncRef(None) | Self::ExternRef(None) | Self::ExnRef(None) | Self::AnyRef(None)
+        )
+    }
+
+    /// Whether the value belongs to the given type
+    ///
+    /// Type indices are only meaningful within the module that defines them, so a non-null function reference never
+    /// matches a concrete heap type here, and `Store` resolves those instead. Only the kind of object a GC reference
+    /// points to is checked against one. The bottom heap types only hold null.
+    pub fn matches(&self, t: &ValType) -> bool {
+        let ValType::RefType(rt) = t else {
+            return self.ty() == *t;
+        };
+        let kind = match (self, &rt.ht) {
+            (_, HeapType::NoFunc | HeapType::NoExtern | HeapType::NoExn | HeapType::None) => {
+                self.is_null() && Self::null(&rt.ht).ty() == self.ty()
+            }
+            (Self::FuncRef(_), HeapType::Func) | (Self::FuncRef(None), HeapType::Type(_)) => true,
+            (Self::ExternRef(_), HeapType::Extern) | (Self::ExnRef(_), HeapType::Exn) => true,
+            (
+                Self::AnyRef(None),
+                HeapType::Any
+                | HeapType::Eq
+                | HeapType::I31
+                | HeapType::Struct
+                | HeapType::Array
+                | HeapType::Type(_),
+            ) => true,
+            (Self::AnyRef(Some(reference)), ht) => reference.kind_matches(ht),
+            _ => false,
+        };
+        kind && (rt.nullable || !self.is_null())
+    }
+
+    /// The type of the value
+    pub fn ty(&self) -> ValType {
+        match self {
+            Self::I32(_) => ValType::NumType(NumType::I32),
+            Self::I64(_) => ValType::NumType(NumType::I64),
+            Self::F32(_) => ValType::NumType(NumType::F32),
+            Self::F64(_) => ValType::NumType(NumType::F64),
+            Self::V128(_) => ValType::VecType(VecType::V128),
+            Self::FuncRef(_) => ValType::RefType(RefType::FUNCREF),
+            Self::ExternRef(_) => ValType::RefType(RefType::EXTERNREF),
+            Self::ExnRef(_) => ValType::RefType(RefType::EXNREF),
+            Self::AnyRef(_) => ValType::RefType(RefType::ANYREF),
+        }
+    }
+}
+
+/// A non-null reference in the `any` hierarchy, which is either an unboxed 31-bit integer, an object on the heap or
+/// a host reference that was converted with `any.convert_extern`
+#[derive(Clone, Debug, PartialEq)]
+pub enum AnyRef {
+    I31(u32),
+    Struct(GcRef),
+    Array(GcRef),
+    Extern(ExternRef),
+}
+
+impl AnyRef {
+    /// Convert an external reference with the semantics of `any.convert_extern`, which undoes `extern.convert_any`
+    pub fn internalize(reference: ExternRef) -> Self {
+        match reference.data().downcast_ref::<Self>() {
+            Some(reference) => reference.clone(),
+            None => Self::Extern(reference),
+        }
+    }
+
+    /// Convert to an external reference with the semantics of `extern.convert_any`
+    pub fn externalize(self) -> ExternRef {
+        match self {
+            Self::Extern(reference) => reference,
+            reference => ExternRef::new(reference),
+        }
+    }
+
+    /// Whether the reference belongs to an abstract heap type, or has the kind of object a concrete one describes
+    fn kind_matches(&self, ht: &HeapType) -> bool {
+        match (self, ht) {
+            (_, HeapType::Any) => true,
+            (Self::Extern(_), _) => false,
+            (_, HeapType::Eq) => true,
+            (Self::I31(_), HeapType::I31) => true,
+            (Self::Struct(_), HeapType::Struct) | (Self::Array(_), HeapType::Array) => true,
+            (Self::Struct(_) | Self::Array(_), HeapType::Type(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A reference to a function in the store, as produced by `ref.func`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FuncHandle(pub FuncAddr);
+
+/// An opaque reference to a host object that Wasm code can pass around but not inspect
+///
+/// Cloning an `ExternRef` shares the underlying object, and two references are equal when they point to the same one.
+#[derive(Clone)]
+pub struct ExternRef(Rc<dyn Any>);
+
+impl ExternRef {
+    /// Wrap a host object in a reference
+    pub fn new(object: impl Any) -> Self {
+        Self(Rc::new(object))
+    }
+
+    /// Borrow the host object behind the reference
+    pub fn data(&self) -> &dyn Any {
+        self.0.as_ref()
+    }
+
+    /// Share the host object behind the reference, so that the collector can count the references to it
+    pub(super) fn rc(&self) -> Rc<dyn Any> {
+        self.0.clone()
+    }
+}
+
+impl From<Box<dyn Any>> for ExternRef {
+    fn from(object: Box<dyn Any>) -> Self {
+        Self(Rc::from(object))
+    }
+}
+
+impl PartialEq for ExternRef {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for ExternRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ExternRef({:p})", Rc::as_ptr(&self.0))
+    }
+}
+
+/// An exception thrown by `throw` or by a host function, which unwinds until a `try_table` catches it
+///
+/// An exception that nothing catches reaches the host as `Trap::Exception`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exception {
+    pub tag: TagAddr,
+    pub payload: Vec<Val>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_val_ty() {
+        assert_eq!(Val::I32(1).ty(), ValType::NumType(NumType::I32));
+        assert_eq!(Val::F64(1.0).ty(), ValType::NumType(NumType::F64));
+        assert_eq!(Val::V128(1).ty(), ValType::VecType(VecType::V128));
+        assert_eq!(Val::FuncRef(None).ty(), ValType::RefType(RefType::FUNCREF));
+        assert_eq!(
+            Val::ExternRef(None).ty(),
+            ValType::RefType(RefType::EXTERNREF)
+        );
+    }
+
+    #[test]
+    fn test_val_null() {
+        assert_eq!(Val::null(&HeapType::Func), Val::FuncRef(None));
+        assert_eq!(Val::null(&HeapType::Type(0)), Val::FuncRef(None));
+        assert!(Val::null(&HeapType::Extern).is_null());
+        assert!(Val::null(&HeapType::Exn).is_null());
+        assert!(!Val::FuncRef(Some(FuncHandle(0))).is_null());
+        assert!(!Val::ExternRef(Some(ExternRef::new(1))).is_null());
+        assert!(!Val::I32(0).is_null());
+    }
+
+    #[test]
+    fn test_val_matches() {
+        let func = ValType::RefType(RefType::non_null(HeapType::Func));
+        let typed = ValType::RefType(RefType::null(HeapType::Type(0)));
+        assert!(Val::FuncRef(Some(FuncHandle(0))).matches(&func));
+        assert!(!Val::FuncRef(None).matches(&func));
+        assert!(Val::FuncRef(None).matches(&typed));
+        assert!(!Val::FuncRef(Some(FuncHandle(0))).matches(&typed));
+        assert!(Val::FuncRef(None).matches(&ValType::RefType(RefType::FUNCREF)));
+        assert!(!Val::ExternRef(None).matches(&typed));
+        assert!(Val::I32(0).matches(&ValType::NumType(NumType::I32)));
+        assert!(!Val::I32(0).matches(&ValType::NumType(NumType::I64)));
+    }
+
+    #[test]
+    fn test_val_default() {
+        assert_eq!(Val::default(&ValType::NumType(NumType::I64)), Val::I64(0));
+        assert_eq!(Val::default(&ValType::VecType(VecType::V128)), Val::V128(0));
+        assert_eq!(
+            Val::default(&ValType::RefType(RefType::EXTERNREF)),
+            Val::ExternRef(None)
+        );
+    }
+
+    #[test]
+    fn test_extern_ref() {
+        let object = ExternRef::new(String::from("handle"));
+        let shared = object.clone();
+        assert_eq!(object, shared);
+        assert_ne!(object, ExternRef::new(String::from("handle")));
+        assert_eq!(
+            shared.data().downcast_ref::<String>().map(String::as_str),
+            Some("handle")
+        );
+
+        let boxed: Box<dyn Any> = Box::new(7u32);
+        assert_eq!(
+            ExternRef::from(boxed).data().downcast_ref::<u32>(),
+            Some(&7)
+        );
+    }
+}
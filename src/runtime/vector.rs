@@ -0,0 +1,937 @@
+use std::array;
+
+use crate::instructions::VectorInstruction;
+
+use super::{
+    memory::MemInst,
+    numeric::{fmax, fmin},
+    operands::{effective_address, pop_f32, pop_f64, pop_i32, pop_i64, pop_v128},
+    trap::Trap,
+    values::Val,
+};
+
+/// A type that can be packed into the lanes of a 128-bit vector
+trait Lane: Copy {
+    const BYTES: usize;
+
+    /// Read the lane from the first `BYTES` bytes, in little endian order
+    fn read(bytes: &[u8]) -> Self;
+
+    /// Write the lane to the first `BYTES` bytes, in little endian order
+    fn write(self, bytes: &mut [u8]);
+}
+
+macro_rules! lane {
+    ($($t:ty),*) => {
+        $(
+            impl Lane for $t {
+                const BYTES: usize = std::mem::size_of::<$t>();
+
+                fn read(bytes: &[u8]) -> Self {
+                    let mut le = [0; std::mem::size_of::<$t>()];
+                    le.copy_from_slice(&bytes[..Self::BYTES]);
+                    <$t>::from_le_bytes(le)
+                }
+
+                fn write(self, bytes: &mut [u8]) {
+                    bytes[..Self::BYTES].copy_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+lane!(i8, u8, i16, u16, i32, u32, i64, u64, f32, f64);
+
+/// Split a vector into its lanes
+fn lanes<T: Lane, const N: usize>(v: u128) -> [T; N] {
+    let bytes = v.to_le_bytes();
+    array::from_fn(|i| T::read(&bytes[i * T::BYTES..]))
+}
+
+/// Pack lanes into a vector
+fn vector<T: Lane, const N: usize>(lanes: [T; N]) -> u128 {
+    let mut bytes = [0; 16];
+    for (i, lane) in lanes.into_iter().enumerate() {
+        lane.write(&mut bytes[i * T::BYTES..]);
+    }
+    u128::from_le_bytes(bytes)
+}
+
+/// Apply `f` to every lane
+fn map<T: Lane, U: Lane, const N: usize>(a: u128, f: impl Fn(T) -> U) -> u128 {
+    vector::<U, N>(lanes::<T, N>(a).map(f))
+}
+
+/// Apply `f` to every pair of corresponding lanes
+fn zip<T: Lane, const N: usize>(a: u128, b: u128, f: impl Fn(T, T) -> T) -> u128 {
+    let (a, b) = (lanes::<T, N>(a), lanes::<T, N>(b));
+    vector::<T, N>(array::from_fn(|i| f(a[i], b[i])))
+}
+
+/// Compare every pair of corresponding lanes, setting all bits of a result lane when `f` holds
+fn compare<T: Lane, const N: usize>(a: u128, b: u128, f: impl Fn(T, T) -> bool) -> u128 {
+    let (a, b) = (lanes::<T, N>(a), lanes::<T, N>(b));
+    let mut bytes = [0; 16];
+    for i in (0..N).filter(|i| f(a[*i], b[*i])) {
+        bytes[i * T::BYTES..(i + 1) * T::BYTES].fill(0xFF);
+    }
+    u128::from_le_bytes(bytes)
+}
+
+/// Widen the low or high half of the `N` lanes of `a` into `M` lanes
+fn extend<T: Lane, U: Lane, const N: usize, const M: usize>(
+    a: u128,
+    high: bool,
+    f: impl Fn(T) -> U,
+) -> u128 {
+    let a = lanes::<T, N>(a);
+    let half = if high { M } else { 0 };
+    vector::<U, M>(array::from_fn(|i| f(a[half + i])))
+}
+
+/// Widen the low or high half of the lanes of `a` and `b` and combine them with `f`
+fn extmul<T: Lane, U: Lane, const N: usize, const M: usize>(
+    a: u128,
+    b: u128,
+    high: bool,
+    f: impl Fn(T, T) -> U,
+) -> u128 {
+    let (a, b) = (lanes::<T, N>(a), lanes::<T, N>(b));
+    let half = if high { M } else { 0 };
+    vector::<U, M>(array::from_fn(|i| f(a[half + i], b[half + i])))
+}
+
+/// Combine every pair of adjacent lanes of `a` into a single wider lane
+fn pairwise<T: Lane, U: Lane, const N: usize, const M: usize>(
+    a: u128,
+    f: impl Fn(T, T) -> U,
+) -> u128 {
+    let a = lanes::<T, N>(a);
+    vector::<U, M>(array::from_fn(|i| f(a[2 * i], a[2 * i + 1])))
+}
+
+/// Narrow the lanes of `a` followed by the lanes of `b` into a single vector
+fn narrow<T: Lane, U: Lane, const N: usize, const M: usize>(
+    a: u128,
+    b: u128,
+    f: impl Fn(T) -> U,
+) -> u128 {
+    let (a, b) = (lanes::<T, N>(a), lanes::<T, N>(b));
+    vector::<U, M>(array::from_fn(|i| f(if i < N { a[i] } else { b[i - N] })))
+}
+
+/// Whether every lane of `width` bytes is non-zero
+fn all_true(a: u128, width: usize) -> bool {
+    a.to_le_bytes()
+        .chunks(width)
+        .all(|lane| lane.iter().any(|byte| *byte != 0))
+}
+
+/// Gather the sign bit of every lane of `width` bytes
+fn bitmask(a: u128, width: usize) -> i32 {
+    a.to_le_bytes()
+        .chunks(width)
+        .enumerate()
+        .map(|(i, lane)| ((lane[width - 1] >> 7) as i32) << i)
+        .sum()
+}
+
+/// Load `len` bytes into the low bytes of a vector
+fn load(memory: &MemInst, ea: u64, len: usize) -> Result<u128, Trap> {
+    let mut bytes = [0; 16];
+    memory.read(ea, &mut bytes[..len])?;
+    Ok(u128::from_le_bytes(bytes))
+}
+
+/// Load `len` bytes and repeat them across the vector
+fn load_splat(memory: &MemInst, ea: u64, len: usize) -> Result<u128, Trap> {
+    let mut bytes = [0; 16];
+    memory.read(ea, &mut bytes[..len])?;
+    let (lane, rest) = bytes.split_at_mut(len);
+    for chunk in rest.chunks_mut(len) {
+        chunk.copy_from_slice(lane);
+    }
+    Ok(u128::from_le_bytes(bytes))
+}
+
+/// Replace lane `lane` of `width` bytes in `v` with bytes loaded from memory
+fn load_lane(memory: &MemInst, ea: u64, v: u128, lane: u8, width: usize) -> Result<u128, Trap> {
+    let mut bytes = v.to_le_bytes();
+    let start = lane as usize * width;
+    memory.read(ea, &mut bytes[start..start + width])?;
+    Ok(u128::from_le_bytes(bytes))
+}
+
+/// Store lane `lane` of `width` bytes in `v` to memory
+fn store_lane(memory: &mut MemInst, ea: u64, v: u128, lane: u8, width: usize) -> Result<(), Trap> {
+    let start = lane as usize * width;
+    memory.write(ea, &v.to_le_bytes()[start..start + width])
+}
+
+fn require(memory: Option<&mut MemInst>) -> &mut MemInst {
+    memory.expect("validation guarantees the module has a memory")
+}
+
+/// Execute a vector instruction against the operand stack
+///
+/// `memory` is the memory of the current module, which validation guarantees is present for instructions that
+/// access memory.
+pub fn execute(
+    instruction: &VectorInstruction,
+    stack: &mut Vec<Val>,
+    memory: Option<&mut MemInst>,
+) -> Result<(), Trap> {
+    use VectorInstruction::*;
+
+    let memory64 = memory.as_ref().is_some_and(|memory| memory.memory64);
+    let result = match instruction {
+        V128Load(m) => load(require(memory), effective_address(stack, m, memory64)?, 16)?,
+        V128Load8x8S(m) => extend::<i8, i16, 16, 8>(
+            load(require(memory), effective_address(stack, m, memory64)?, 8)?,
+            false,
+            |x| x as i16,
+        ),
+        V128Load8x8U(m) => extend::<u8, u16, 16, 8>(
+            load(require(memory), effective_address(stack, m, memory64)?, 8)?,
+            false,
+            |x| x as u16,
+        ),
+        V128Load16x4S(m) => extend::<i16, i32, 8, 4>(
+            load(require(memory), effective_address(stack, m, memory64)?, 8)?,
+            false,
+            |x| x as i32,
+        ),
+        V128Load16x4U(m) => extend::<u16, u32, 8, 4>(
+            load(require(memory), effective_address(stack, m, memory64)?, 8)?,
+            false,
+            |x| x as u32,
+        ),
+        V128Load32x2S(m) => extend::<i32, i64, 4, 2>(
+            load(require(memory), effective_address(stack, m, memory64)?, 8)?,
+            false,
+            |x| x as i64,
+        ),
+        V128Load32x2U(m) => extend::<u32, u64, 4, 2>(
+            load(require(memory), effective_address(stack, m, memory64)?, 8)?,
+            false,
+            |x| x as u64,
+        ),
+        V128Load8Splat(m) => {
+            load_splat(require(memory), effective_address(stack, m, memory64)?, 1)?
+        }
+        V128Load16Splat(m) => {
+            load_splat(require(memory), effective_address(stack, m, memory64)?, 2)?
+        }
+        V128Load32Splat(m) => {
+            load_splat(require(memory), effective_address(stack, m, memory64)?, 4)?
+        }
+        V128Load64Splat(m) => {
+            load_splat(require(memory), effective_address(stack, m, memory64)?, 8)?
+        }
+        V128Load32Zero(m) => load(require(memory), effective_address(stack, m, memory64)?, 4)?,
+        V128Load64Zero(m) => load(require(memory), effective_address(stack, m, memory64)?, 8)?,
+        V128Load8Lane(m, lane)
+        | V128Load16Lane(m, lane)
+        | V128Load32Lane(m, lane)
+        | V128Load64Lane(m, lane) => {
+            let width = match instruction {
+                V128Load8Lane(_, _) => 1,
+                V128Load16Lane(_, _) => 2,
+                V128Load32Lane(_, _) => 4,
+                _ => 8,
+            };
+            let v = pop_v128(stack);
+            let ea = effective_address(stack, m, memory64)?;
+            load_lane(require(memory), ea, v, *lane, width)?
+        }
+        V128Store(m) => {
+            let v = pop_v128(stack);
+            let ea = effective_address(stack, m, memory64)?;
+            return require(memory).write(ea, &v.to_le_bytes());
+        }
+        V128Store8Lane(m, lane)
+        | V128Store16Lane(m, lane)
+        | V128Store32Lane(m, lane)
+        | V128Store64Lane(m, lane) => {
+            let width = match instruction {
+                V128Store8Lane(_, _) => 1,
+                V128Store16Lane(_, _) => 2,
+                V128Store32Lane(_, _) => 4,
+                _ => 8,
+            };
+            let v = pop_v128(stack);
+            let ea = effective_address(stack, m, memory64)?;
+            return store_lane(require(memory), ea, v, *lane, width);
+        }
+        _ => {
+            let result = compute(instruction, stack);
+            stack.push(result);
+            return Ok(());
+        }
+    };
+    stack.push(Val::V128(result));
+    Ok(())
+}
+
+/// Compute the result of a vector instruction that does not access memory
+fn compute(instruction: &VectorInstruction, stack: &mut Vec<Val>) -> Val {
+    use VectorInstruction::*;
+
+    // Instructions that do not produce a vector
+    match instruction {
+        I8x16ExtractLaneS(lane) => {
+            return Val::I32(lanes::<i8, 16>(pop_v128(stack))[*lane as usize] as i32)
+        }
+        I8x16ExtractLaneU(lane) => {
+            return Val::I32(lanes::<u8, 16>(pop_v128(stack))[*lane as usize] as i32)
+        }
+        I16x8ExtractLaneS(lane) => {
+            return Val::I32(lanes::<i16, 8>(pop_v128(stack))[*lane as usize] as i32)
+        }
+        I16x8ExtractLaneU(lane) => {
+            return Val::I32(lanes::<u16, 8>(pop_v128(stack))[*lane as usize] as i32)
+        }
+        I32x4ExtractLane(lane) => {
+            return Val::I32(lanes::<i32, 4>(pop_v128(stack))[*lane as usize])
+        }
+        I64x2ExtractLane(lane) => {
+            return Val::I64(lanes::<i64, 2>(pop_v128(stack))[*lane as usize])
+        }
+        F32x4ExtractLane(lane) => {
+            return Val::F32(lanes::<f32, 4>(pop_v128(stack))[*lane as usize])
+        }
+        F64x2ExtractLane(lane) => {
+            return Val::F64(lanes::<f64, 2>(pop_v128(stack))[*lane as usize])
+        }
+        V128AnyTrue => return Val::I32((pop_v128(stack) != 0) as i32),
+        I8x16AllTrue => return Val::I32(all_true(pop_v128(stack), 1) as i32),
+        I16x8AllTrue => return Val::I32(all_true(pop_v128(stack), 2) as i32),
+        I32x4AllTrue => return Val::I32(all_true(pop_v128(stack), 4) as i32),
+        I64x2AllTrue => return Val::I32(all_true(pop_v128(stack), 8) as i32),
+        I8x16Bitmask => return Val::I32(bitmask(pop_v128(stack), 1)),
+        I16x8Bitmask => return Val::I32(bitmask(pop_v128(stack), 2)),
+        I32x4Bitmask => return Val::I32(bitmask(pop_v128(stack), 4)),
+        I64x2Bitmask => return Val::I32(bitmask(pop_v128(stack), 8)),
+        _ => (),
+    }
+
+    // Instructions with a non-vector operand on top of the stack
+    let v = match instruction {
+        V128Const(c) => *c,
+        I8x16Splat => vector([pop_i32(stack) as i8; 16]),
+        I16x8Splat => vector([pop_i32(stack) as i16; 8]),
+        I32x4Splat => vector([pop_i32(stack); 4]),
+        I64x2Splat => vector([pop_i64(stack); 2]),
+        F32x4Splat => vector([pop_f32(stack); 4]),
+        F64x2Splat => vector([pop_f64(stack); 2]),
+        I8x16ReplaceLane(lane) => {
+            let x = pop_i32(stack) as i8;
+            let mut lanes = lanes::<i8, 16>(pop_v128(stack));
+            lanes[*lane as usize] = x;
+            vector(lanes)
+        }
+        I16x8ReplaceLane(lane) => {
+            let x = pop_i32(stack) as i16;
+            let mut lanes = lanes::<i16, 8>(pop_v128(stack));
+            lanes[*lane as usize] = x;
+            vector(lanes)
+        }
+        I32x4ReplaceLane(lane) => {
+            let x = pop_i32(stack);
+            let mut lanes = lanes::<i32, 4>(pop_v128(stack));
+            lanes[*lane as usize] = x;
+            vector(lanes)
+        }
+        I64x2ReplaceLane(lane) => {
+            let x = pop_i64(stack);
+            let mut lanes = lanes::<i64, 2>(pop_v128(stack));
+            lanes[*lane as usize] = x;
+            vector(lanes)
+        }
+        F32x4ReplaceLane(lane) => {
+            let x = pop_f32(stack);
+            let mut lanes = lanes::<f32, 4>(pop_v128(stack));
+            lanes[*lane as usize] = x;
+            vector(lanes)
+        }
+        F64x2ReplaceLane(lane) => {
+            let x = pop_f64(stack);
+            let mut lanes = lanes::<f64, 2>(pop_v128(stack));
+            lanes[*lane as usize] = x;
+            vector(lanes)
+        }
+        I8x16Shl | I8x16ShrS | I8x16ShrU | I16x8Shl | I16x8ShrS | I16x8ShrU | I32x4Shl
+        | I32x4ShrS | I32x4ShrU | I64x2Shl | I64x2ShrS | I64x2ShrU => {
+            // The shift amount is taken modulo the lane width, which is what wrapping_shl and wrapping_shr do
+            let s = pop_i32(stack) as u32;
+            let a = pop_v128(stack);
+            match instruction {
+                I8x16Shl => map::<i8, i8, 16>(a, |x| x.wrapping_shl(s)),
+                I8x16ShrS => map::<i8, i8, 16>(a, |x| x.wrapping_shr(s)),
+                I8x16ShrU => map::<u8, u8, 16>(a, |x| x.wrapping_shr(s)),
+                I16x8Shl => map::<i16, i16, 8>(a, |x| x.wrapping_shl(s)),
+                I16x8ShrS => map::<i16, i16, 8>(a, |x| x.wrapping_shr(s)),
+                I16x8ShrU => map::<u16, u16, 8>(a, |x| x.wrapping_shr(s)),
+                I32x4Shl => map::<i32, i32, 4>(a, |x| x.wrapping_shl(s)),
+                I32x4ShrS => map::<i32, i32, 4>(a, |x| x.wrapping_shr(s)),
+                I32x4ShrU => map::<u32, u32, 4>(a, |x| x.wrapping_shr(s)),
+                I64x2Shl => map::<i64, i64, 2>(a, |x| x.wrapping_shl(s)),
+                I64x2ShrS => map::<i64, i64, 2>(a, |x| x.wrapping_shr(s)),
+                _ => map::<u64, u64, 2>(a, |x| x.wrapping_shr(s)),
+            }
+        }
+        V128Bitselect => {
+            let c = pop_v128(stack);
+            let b = pop_v128(stack);
+            let a = pop_v128(stack);
+            (a & c) | (b & !c)
+        }
+        _ => {
+            let a = pop_v128(stack);
+            match unary(instruction, a) {
+                Some(v) => v,
+                None => {
+                    let b = a;
+                    let a = pop_v128(stack);
+                    binary(instruction, a, b)
+                }
+            }
+        }
+    };
+    Val::V128(v)
+}
+
+/// Compute the result of a vector instruction with a single vector operand, or `None` if it takes two
+fn unary(instruction: &VectorInstruction, a: u128) -> Option<u128> {
+    use VectorInstruction::*;
+
+    let v = match instruction {
+        V128Not => !a,
+        I8x16Abs => map::<i8, i8, 16>(a, i8::wrapping_abs),
+        I8x16Neg => map::<i8, i8, 16>(a, i8::wrapping_neg),
+        I8x16Popcnt => map::<u8, u8, 16>(a, |x| x.count_ones() as u8),
+        I16x8Abs => map::<i16, i16, 8>(a, i16::wrapping_abs),
+        I16x8Neg => map::<i16, i16, 8>(a, i16::wrapping_neg),
+        I32x4Abs => map::<i32, i32, 4>(a, i32::wrapping_abs),
+        I32x4Neg => map::<i32, i32, 4>(a, i32::wrapping_neg),
+        I64x2Abs => map::<i64, i64, 2>(a, i64::wrapping_abs),
+        I64x2Neg => map::<i64, i64, 2>(a, i64::wrapping_neg),
+        F32x4Abs => map::<f32, f32, 4>(a, f32::abs),
+        F32x4Neg => map::<f32, f32, 4>(a, |x| -x),
+        F32x4Sqrt => map::<f32, f32, 4>(a, f32::sqrt),
+        F32x4Ceil => map::<f32, f32, 4>(a, f32::ceil),
+        F32x4Floor => map::<f32, f32, 4>(a, f32::floor),
+        F32x4Trunc => map::<f32, f32, 4>(a, f32::trunc),
+        F32x4Nearest => map::<f32, f32, 4>(a, f32::round_ties_even),
+        F64x2Abs => map::<f64, f64, 2>(a, f64::abs),
+        F64x2Neg => map::<f64, f64, 2>(a, |x| -x),
+        F64x2Sqrt => map::<f64, f64, 2>(a, f64::sqrt),
+        F64x2Ceil => map::<f64, f64, 2>(a, f64::ceil),
+        F64x2Floor => map::<f64, f64, 2>(a, f64::floor),
+        F64x2Trunc => map::<f64, f64, 2>(a, f64::trunc),
+        F64x2Nearest => map::<f64, f64, 2>(a, f64::round_ties_even),
+        I16x8ExtendLowI8x16S => extend::<i8, i16, 16, 8>(a, false, |x| x as i16),
+        I16x8ExtendHighI8x16S => extend::<i8, i16, 16, 8>(a, true, |x| x as i16),
+        I16x8ExtendLowI8x16U => extend::<u8, u16, 16, 8>(a, false, |x| x as u16),
+        I16x8ExtendHighI8x16U => extend::<u8, u16, 16, 8>(a, true, |x| x as u16),
+        I32x4ExtendLowI16x8S => extend::<i16, i32, 8, 4>(a, false, |x| x as i32),
+        I32x4ExtendHighI16x8S => extend::<i16, i32, 8, 4>(a, true, |x| x as i32),
+        I32x4ExtendLowI16x8U => extend::<u16, u32, 8, 4>(a, false, |x| x as u32),
+        I32x4ExtendHighI16x8U => extend::<u16, u32, 8, 4>(a, true, |x| x as u32),
+        I64x2ExtendLowI32x4S => extend::<i32, i64, 4, 2>(a, false, |x| x as i64),
+        I64x2ExtendHighI32x4S => extend::<i32, i64, 4, 2>(a, true, |x| x as i64),
+        I64x2ExtendLowI32x4U => extend::<u32, u64, 4, 2>(a, false, |x| x as u64),
+        I64x2ExtendHighI32x4U => extend::<u32, u64, 4, 2>(a, true, |x| x as u64),
+        I16x8ExtaddPairwiseI8x16S => pairwise::<i8, i16, 16, 8>(a, |x, y| x as i16 + y as i16),
+        I16x8ExtaddPairwiseI8x16U => pairwise::<u8, u16, 16, 8>(a, |x, y| x as u16 + y as u16),
+        I32x4ExtaddPairwiseI16x8S => pairwise::<i16, i32, 8, 4>(a, |x, y| x as i32 + y as i32),
+        I32x4ExtaddPairwiseI16x8U => pairwise::<u16, u32, 8, 4>(a, |x, y| x as u32 + y as u32),
+        // Casting a float to an integer in Rust saturates and maps NaN to 0, exactly like trunc_sat
+        I32x4TruncSatF32x4S => map::<f32, i32, 4>(a, |x| x as i32),
+        I32x4TruncSatF32x4U => map::<f32, u32, 4>(a, |x| x as u32),
+        I32x4TruncSatF64x2SZero => narrow::<f64, i32, 2, 4>(a, 0, |x| x as i32),
+        I32x4TruncSatF64x2UZero => narrow::<f64, u32, 2, 4>(a, 0, |x| x as u32),
+        F32x4ConvertI32x4S => map::<i32, f32, 4>(a, |x| x as f32),
+        F32x4ConvertI32x4U => map::<u32, f32, 4>(a, |x| x as f32),
+        F64x2ConvertLowI32x4S => extend::<i32, f64, 4, 2>(a, false, |x| x as f64),
+        F64x2ConvertLowI32x4U => extend::<u32, f64, 4, 2>(a, false, |x| x as f64),
+        F32x4DemoteF64x2Zero => narrow::<f64, f32, 2, 4>(a, 0, |x| x as f32),
+        F64x2PromoteLowF32x4 => extend::<f32, f64, 4, 2>(a, false, |x| x as f64),
+        _ => return None,
+    };
+    Some(v)
+}
+
+/// Compute the result of a vector instruction with two vector operands
+fn binary(instruction: &VectorInstruction, a: u128, b: u128) -> u128 {
+    use VectorInstruction::*;
+
+    match instruction {
+        I8x16Shuffle(indices) => {
+            let mut bytes = a.to_le_bytes().to_vec();
+            bytes.extend(b.to_le_bytes());
+            vector(indices.map(|i| bytes[i as usize]))
+        }
+        I8x16Swizzle => {
+            let a = lanes::<u8, 16>(a);
+            map::<u8, u8, 16>(b, |i| a.get(i as usize).copied().unwrap_or(0))
+        }
+        V128And => a & b,
+        V128Andnot => a & !b,
+        V128Or => a | b,
+        V128Xor => a ^ b,
+
+        I8x16Eq => compare::<i8, 16>(a, b, |x, y| x == y),
+        I8x16Ne => compare::<i8, 16>(a, b, |x, y| x != y),
+        I8x16LtS => compare::<i8, 16>(a, b, |x, y| x < y),
+        I8x16LtU => compare::<u8, 16>(a, b, |x, y| x < y),
+        I8x16GtS => compare::<i8, 16>(a, b, |x, y| x > y),
+        I8x16GtU => compare::<u8, 16>(a, b, |x, y| x > y),
+        I8x16LeS => compare::<i8, 16>(a, b, |x, y| x <= y),
+        I8x16LeU => compare::<u8, 16>(a, b, |x, y| x <= y),
+        I8x16GeS => compare::<i8, 16>(a, b, |x, y| x >= y),
+        I8x16GeU => compare::<u8, 16>(a, b, |x, y| x >= y),
+        I16x8Eq => compare::<i16, 8>(a, b, |x, y| x == y),
+        I16x8Ne => compare::<i16, 8>(a, b, |x, y| x != y),
+        I16x8LtS => compare::<i16, 8>(a, b, |x, y| x < y),
+        I16x8LtU => compare::<u16, 8>(a, b, |x, y| x < y),
+        I16x8GtS => compare::<i16, 8>(a, b, |x, y| x > y),
+        I16x8GtU => compare::<u16, 8>(a, b, |x, y| x > y),
+        I16x8LeS => compare::<i16, 8>(a, b, |x, y| x <= y),
+        I16x8LeU => compare::<u16, 8>(a, b, |x, y| x <= y),
+        I16x8GeS => compare::<i16, 8>(a, b, |x, y| x >= y),
+        I16x8GeU => compare::<u16, 8>(a, b, |x, y| x >= y),
+        I32x4Eq => compare::<i32, 4>(a, b, |x, y| x == y),
+        I32x4Ne => compare::<i32, 4>(a, b, |x, y| x != y),
+        I32x4LtS => compare::<i32, 4>(a, b, |x, y| x < y),
+        I32x4LtU => compare::<u32, 4>(a, b, |x, y| x < y),
+        I32x4GtS => compare::<i32, 4>(a, b, |x, y| x > y),
+        I32x4GtU => compare::<u32, 4>(a, b, |x, y| x > y),
+        I32x4LeS => compare::<i32, 4>(a, b, |x, y| x <= y),
+        I32x4LeU => compare::<u32, 4>(a, b, |x, y| x <= y),
+        I32x4GeS => compare::<i32, 4>(a, b, |x, y| x >= y),
+        I32x4GeU => compare::<u32, 4>(a, b, |x, y| x >= y),
+        I64x2Eq => compare::<i64, 2>(a, b, |x, y| x == y),
+        I64x2Ne => compare::<i64, 2>(a, b, |x, y| x != y),
+        I64x2LtS => compare::<i64, 2>(a, b, |x, y| x < y),
+        I64x2GtS => compare::<i64, 2>(a, b, |x, y| x > y),
+        I64x2LeS => compare::<i64, 2>(a, b, |x, y| x <= y),
+        I64x2GeS => compare::<i64, 2>(a, b, |x, y| x >= y),
+        F32x4Eq => compare::<f32, 4>(a, b, |x, y| x == y),
+        F32x4Ne => compare::<f32, 4>(a, b, |x, y| x != y),
+        F32x4Lt => compare::<f32, 4>(a, b, |x, y| x < y),
+        F32x4Gt => compare::<f32, 4>(a, b, |x, y| x > y),
+        F32x4Le => compare::<f32, 4>(a, b, |x, y| x <= y),
+        F32x4Ge => compare::<f32, 4>(a, b, |x, y| x >= y),
+        F64x2Eq => compare::<f64, 2>(a, b, |x, y| x == y),
+        F64x2Ne => compare::<f64, 2>(a, b, |x, y| x != y),
+        F64x2Lt => compare::<f64, 2>(a, b, |x, y| x < y),
+        F64x2Gt => compare::<f64, 2>(a, b, |x, y| x > y),
+        F64x2Le => compare::<f64, 2>(a, b, |x, y| x <= y),
+        F64x2Ge => compare::<f64, 2>(a, b, |x, y| x >= y),
+
+        I8x16NarrowI16x8S => narrow::<i16, i8, 8, 16>(a, b, |x| x.clamp(-128, 127) as i8),
+        I8x16NarrowI16x8U => narrow::<i16, u8, 8, 16>(a, b, |x| x.clamp(0, 255) as u8),
+        I16x8NarrowI32x4S => narrow::<i32, i16, 4, 8>(a, b, |x| x.clamp(-32768, 32767) as i16),
+        I16x8NarrowI32x4U => narrow::<i32, u16, 4, 8>(a, b, |x| x.clamp(0, 65535) as u16),
+
+        I8x16Add => zip::<i8, 16>(a, b, i8::wrapping_add),
+        I8x16AddSatS => zip::<i8, 16>(a, b, i8::saturating_add),
+        I8x16AddSatU => zip::<u8, 16>(a, b, u8::saturating_add),
+        I8x16Sub => zip::<i8, 16>(a, b, i8::wrapping_sub),
+        I8x16SubSatS => zip::<i8, 16>(a, b, i8::saturating_sub),
+        I8x16SubSatU => zip::<u8, 16>(a, b, u8::saturating_sub),
+        I8x16MinS => zip::<i8, 16>(a, b, i8::min),
+        I8x16MinU => zip::<u8, 16>(a, b, u8::min),
+        I8x16MaxS => zip::<i8, 16>(a, b, i8::max),
+        I8x16MaxU => zip::<u8, 16>(a, b, u8::max),
+        I8x16AvgrU => zip::<u8, 16>(a, b, |x, y| (x as u16 + y as u16).div_ceil(2) as u8),
+        I16x8Add => zip::<i16, 8>(a, b, i16::wrapping_add),
+        I16x8AddSatS => zip::<i16, 8>(a, b, i16::saturating_add),
+        I16x8AddSatU => zip::<u16, 8>(a, b, u16::saturating_add),
+        I16x8Sub => zip::<i16, 8>(a, b, i16::wrapping_sub),
+        I16x8SubSatS => zip::<i16, 8>(a, b, i16::saturating_sub),
+        I16x8SubSatU => zip::<u16, 8>(a, b, u16::saturating_sub),
+        I16x8Mul => zip::<i16, 8>(a, b, i16::wrapping_mul),
+        I16x8MinS => zip::<i16, 8>(a, b, i16::min),
+        I16x8MinU => zip::<u16, 8>(a, b, u16::min),
+        I16x8MaxS => zip::<i16, 8>(a, b, i16::max),
+        I16x8MaxU => zip::<u16, 8>(a, b, u16::max),
+        I16x8AvgrU => zip::<u16, 8>(a, b, |x, y| (x as u32 + y as u32).div_ceil(2) as u16),
+        I16x8Q15mulrSatS => zip::<i16, 8>(a, b, |x, y| {
+            ((x as i32 * y as i32 + 0x4000) >> 15).clamp(-32768, 32767) as i16
+        }),
+        I32x4Add => zip::<i32, 4>(a, b, i32::wrapping_add),
+        I32x4Sub => zip::<i32, 4>(a, b, i32::wrapping_sub),
+        I32x4Mul => zip::<i32, 4>(a, b, i32::wrapping_mul),
+        I32x4MinS => zip::<i32, 4>(a, b, i32::min),
+        I32x4MinU => zip::<u32, 4>(a, b, u32::min),
+        I32x4MaxS => zip::<i32, 4>(a, b, i32::max),
+        I32x4MaxU => zip::<u32, 4>(a, b, u32::max),
+        I32x4DotI16x8S => {
+            let (a, b) = (lanes::<i16, 8>(a), lanes::<i16, 8>(b));
+            let product = |i: usize| a[i] as i32 * b[i] as i32;
+            vector::<i32, 4>(array::from_fn(|i| {
+                product(2 * i).wrapping_add(product(2 * i + 1))
+            }))
+        }
+        I64x2Add => zip::<i64, 2>(a, b, i64::wrapping_add),
+        I64x2Sub => zip::<i64, 2>(a, b, i64::wrapping_sub),
+        I64x2Mul => zip::<i64, 2>(a, b, i64::wrapping_mul),
+
+        I16x8ExtmulLowI8x16S => extmul::<i8, i16, 16, 8>(a, b, false, |x, y| x as i16 * y as i16),
+        I16x8ExtmulHighI8x16S => extmul::<i8, i16, 16, 8>(a, b, true, |x, y| x as i16 * y as i16),
+        I16x8ExtmulLowI8x16U => extmul::<u8, u16, 16, 8>(a, b, false, |x, y| x as u16 * y as u16),
+        I16x8ExtmulHighI8x16U => extmul::<u8, u16, 16, 8>(a, b, true, |x, y| x as u16 * y as u16),
+        I32x4ExtmulLowI16x8S => extmul::<i16, i32, 8, 4>(a, b, false, |x, y| x as i32 * y as i32),
+        I32x4ExtmulHighI16x8S => extmul::<i16, i32, 8, 4>(a, b, true, |x, y| x as i32 * y as i32),
+        I32x4ExtmulLowI16x8U => extmul::<u16, u32, 8, 4>(a, b, false, |x, y| x as u32 * y as u32),
+        I32x4ExtmulHighI16x8U => extmul::<u16, u32, 8, 4>(a, b, true, |x, y| x as u32 * y as u32),
+        I64x2ExtmulLowI32x4S => extmul::<i32, i64, 4, 2>(a, b, false, |x, y| x as i64 * y as i64),
+        I64x2ExtmulHighI32x4S => extmul::<i32, i64, 4, 2>(a, b, true, |x, y| x as i64 * y as i64),
+        I64x2ExtmulLowI32x4U => extmul::<u32, u64, 4, 2>(a, b, false, |x, y| x as u64 * y as u64),
+        I64x2ExtmulHighI32x4U => extmul::<u32, u64, 4, 2>(a, b, true, |x, y| x as u64 * y as u64),
+
+        F32x4Add => zip::<f32, 4>(a, b, |x, y| x + y),
+        F32x4Sub => zip::<f32, 4>(a, b, |x, y| x - y),
+        F32x4Mul => zip::<f32, 4>(a, b, |x, y| x * y),
+        F32x4Div => zip::<f32, 4>(a, b, |x, y| x / y),
+        // Both operands convert to f64 exactly and the result is one of them, so converting back is exact too
+        F32x4Min => zip::<f32, 4>(a, b, |x, y| fmin(x as f64, y as f64) as f32),
+        F32x4Max => zip::<f32, 4>(a, b, |x, y| fmax(x as f64, y as f64) as f32),
+        F32x4Pmin => zip::<f32, 4>(a, b, |x, y| if y < x { y } else { x }),
+        F32x4Pmax => zip::<f32, 4>(a, b, |x, y| if x < y { y } else { x }),
+        F64x2Add => zip::<f64, 2>(a, b, |x, y| x + y),
+        F64x2Sub => zip::<f64, 2>(a, b, |x, y| x - y),
+        F64x2Mul => zip::<f64, 2>(a, b, |x, y| x * y),
+        F64x2Div => zip::<f64, 2>(a, b, |x, y| x / y),
+        F64x2Min => zip::<f64, 2>(a, b, fmin),
+        F64x2Max => zip::<f64, 2>(a, b, fmax),
+        F64x2Pmin => zip::<f64, 2>(a, b, |x, y| if y < x { y } else { x }),
+        F64x2Pmax => zip::<f64, 2>(a, b, |x, y| if x < y { y } else { x }),
+
+        _ => unreachable!("{:?} is not a binary vector instruction", instruction),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        instructions::MemArg,
+        module::types::{Limits, MemType},
+    };
+
+    use super::*;
+    use VectorInstruction::*;
+
+    /// Execute an instruction that does not access memory and return its single result
+    fn run(instruction: VectorInstruction, operands: Vec<Val>) -> Val {
+        let mut stack = operands;
+        assert_eq!(execute(&instruction, &mut stack, None), Ok(()));
+        assert_eq!(stack.len(), 1);
+        stack.pop().unwrap()
+    }
+
+    fn v128<T: Lane, const N: usize>(lanes: [T; N]) -> Val {
+        Val::V128(vector(lanes))
+    }
+
+    #[test]
+    fn test_lanes() {
+        let v = vector::<i16, 8>([1, -1, 2, -2, 3, -3, 4, -4]);
+        assert_eq!(lanes::<i16, 8>(v), [1, -1, 2, -2, 3, -3, 4, -4]);
+        assert_eq!(lanes::<u8, 16>(v)[..4], [1, 0, 0xFF, 0xFF]);
+        assert_eq!(run(I16x8ExtractLaneS(1), vec![Val::V128(v)]), Val::I32(-1));
+        assert_eq!(
+            run(I16x8ExtractLaneU(1), vec![Val::V128(v)]),
+            Val::I32(65535)
+        );
+        assert_eq!(
+            run(I32x4ReplaceLane(3), vec![Val::V128(0), Val::I32(7)]),
+            v128([0, 0, 0, 7])
+        );
+        assert_eq!(run(I8x16Splat, vec![Val::I32(0x1FF)]), v128([-1i8; 16]));
+    }
+
+    #[test]
+    fn test_integer_arithmetic() {
+        assert_eq!(
+            run(I8x16AddSatS, vec![v128([127i8; 16]), v128([1i8; 16])]),
+            v128([127i8; 16])
+        );
+        assert_eq!(
+            run(I8x16AddSatU, vec![v128([255u8; 16]), v128([1u8; 16])]),
+            v128([255u8; 16])
+        );
+        assert_eq!(
+            run(I16x8SubSatU, vec![v128([1u16; 8]), v128([2u16; 8])]),
+            v128([0u16; 8])
+        );
+        assert_eq!(
+            run(I8x16Add, vec![v128([127i8; 16]), v128([1i8; 16])]),
+            v128([-128i8; 16])
+        );
+        assert_eq!(
+            run(I8x16AvgrU, vec![v128([255u8; 16]), v128([254u8; 16])]),
+            v128([255u8; 16])
+        );
+        assert_eq!(
+            run(
+                I16x8Q15mulrSatS,
+                vec![v128([-32768i16; 8]), v128([-32768i16; 8])]
+            ),
+            v128([32767i16; 8])
+        );
+        assert_eq!(
+            run(I32x4Abs, vec![v128([i32::MIN, -1, 0, 1])]),
+            v128([i32::MIN, 1, 0, 1])
+        );
+        assert_eq!(run(I8x16Popcnt, vec![v128([0xFFu8; 16])]), v128([8u8; 16]));
+        assert_eq!(
+            run(
+                I32x4DotI16x8S,
+                vec![
+                    v128([1i16, 2, 3, 4, -32768, -32768, 0, 0]),
+                    v128([5i16, 6, 7, 8, -32768, -32768, 0, 0])
+                ]
+            ),
+            v128([17, 53, i32::MIN, 0])
+        );
+    }
+
+    #[test]
+    fn test_shifts() {
+        // The shift amount is taken modulo the lane width
+        assert_eq!(
+            run(I8x16Shl, vec![v128([1i8; 16]), Val::I32(9)]),
+            v128([2i8; 16])
+        );
+        assert_eq!(
+            run(I16x8ShrS, vec![v128([-4i16; 8]), Val::I32(1)]),
+            v128([-2i16; 8])
+        );
+        assert_eq!(
+            run(I32x4ShrU, vec![v128([-4i32; 4]), Val::I32(33)]),
+            v128([0x7FFFFFFEi32; 4])
+        );
+    }
+
+    #[test]
+    fn test_widening_and_narrowing() {
+        let bytes = v128([-1i8, 2, -3, 4, -5, 6, -7, 8, 9, 10, 11, 12, 13, 14, 15, 16]);
+        assert_eq!(
+            run(I16x8ExtendLowI8x16S, vec![bytes.clone()]),
+            v128([-1i16, 2, -3, 4, -5, 6, -7, 8])
+        );
+        assert_eq!(
+            run(I16x8ExtendLowI8x16U, vec![bytes.clone()]),
+            v128([255i16, 2, 253, 4, 251, 6, 249, 8])
+        );
+        assert_eq!(
+            run(I16x8ExtaddPairwiseI8x16S, vec![bytes.clone()]),
+            v128([1i16, 1, 1, 1, 19, 23, 27, 31])
+        );
+        assert_eq!(
+            run(I16x8ExtmulHighI8x16S, vec![bytes.clone(), bytes]),
+            v128([81i16, 100, 121, 144, 169, 196, 225, 256])
+        );
+        assert_eq!(
+            run(
+                I8x16NarrowI16x8S,
+                vec![v128([300i16, -300, 1, -1, 0, 0, 0, 0]), v128([0i16; 8])]
+            ),
+            v128([127i8, -128, 1, -1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+        );
+        assert_eq!(
+            run(
+                I8x16NarrowI16x8U,
+                vec![v128([0i16; 8]), v128([300i16, -300, 1, -1, 0, 0, 0, 0])]
+            ),
+            v128([0u8, 0, 0, 0, 0, 0, 0, 0, 255, 0, 1, 0, 0, 0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn test_float_arithmetic() {
+        let min = run(
+            F32x4Min,
+            vec![
+                v128([-0.0f32, 0.0, f32::NAN, 1.0]),
+                v128([0.0f32, -0.0, 1.0, 2.0]),
+            ],
+        );
+        let Val::V128(min) = min else { panic!() };
+        let min = lanes::<f32, 4>(min);
+        assert!(min[0] == 0.0 && min[0].is_sign_negative());
+        assert!(min[1] == 0.0 && min[1].is_sign_negative());
+        assert!(min[2].is_nan());
+        assert_eq!(min[3], 1.0);
+
+        // pmin and pmax return the first operand unless the second is strictly smaller or larger
+        assert_eq!(
+            run(F64x2Pmin, vec![v128([1.0f64, -0.0]), v128([f64::NAN, 0.0])]),
+            v128([1.0f64, -0.0])
+        );
+        assert_eq!(
+            run(F64x2Max, vec![v128([-0.0f64, 1.0]), v128([0.0f64, 2.0])]),
+            v128([0.0f64, 2.0])
+        );
+        assert_eq!(
+            run(F32x4Nearest, vec![v128([2.5f32, 3.5, -0.5, -1.5])]),
+            v128([2.0f32, 4.0, -0.0, -2.0])
+        );
+        assert_eq!(
+            run(F64x2Neg, vec![v128([0.0f64, -1.0])]),
+            v128([-0.0f64, 1.0])
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(
+            run(I32x4TruncSatF32x4S, vec![v128([f32::NAN, 3e9, -3e9, -1.5])]),
+            v128([0, i32::MAX, i32::MIN, -1])
+        );
+        assert_eq!(
+            run(I32x4TruncSatF32x4U, vec![v128([-1.0f32, 5e9, 1.5, 0.0])]),
+            v128([0, u32::MAX, 1, 0])
+        );
+        assert_eq!(
+            run(I32x4TruncSatF64x2UZero, vec![v128([-1.0f64, 4294967296.0])]),
+            v128([0, u32::MAX, 0, 0])
+        );
+        assert_eq!(
+            run(F64x2ConvertLowI32x4U, vec![v128([-1i32, 1, 5, 6])]),
+            v128([4294967295.0f64, 1.0])
+        );
+        assert_eq!(
+            run(F32x4DemoteF64x2Zero, vec![v128([1.5f64, 1e300])]),
+            v128([1.5f32, f32::INFINITY, 0.0, 0.0])
+        );
+        assert_eq!(
+            run(F64x2PromoteLowF32x4, vec![v128([1.5f32, -2.0, 3.0, 4.0])]),
+            v128([1.5f64, -2.0])
+        );
+    }
+
+    #[test]
+    fn test_comparisons_and_masks() {
+        let a = v128([1i32, -1, 3, 0]);
+        let b = v128([1i32, 2, -3, 0]);
+        assert_eq!(
+            run(I32x4LtS, vec![a.clone(), b.clone()]),
+            v128([0, -1, 0, 0])
+        );
+        assert_eq!(
+            run(I32x4LtU, vec![a.clone(), b.clone()]),
+            v128([0, 0, -1, 0])
+        );
+        assert_eq!(
+            run(F32x4Ne, vec![v128([f32::NAN; 4]), v128([f32::NAN; 4])]),
+            v128([-1i32; 4])
+        );
+        assert_eq!(run(I32x4Bitmask, vec![a.clone()]), Val::I32(0b0010));
+        assert_eq!(run(I32x4AllTrue, vec![a.clone()]), Val::I32(0));
+        assert_eq!(run(V128AnyTrue, vec![a]), Val::I32(1));
+        assert_eq!(
+            run(
+                V128Bitselect,
+                vec![Val::V128(0xFF), Val::V128(0xF0F), Val::V128(0xF0)]
+            ),
+            Val::V128(0xFFF)
+        );
+        assert_eq!(
+            run(V128Andnot, vec![Val::V128(0xFF), Val::V128(0x0F)]),
+            Val::V128(0xF0)
+        );
+    }
+
+    #[test]
+    fn test_shuffle_and_swizzle() {
+        let a = v128(std::array::from_fn::<u8, 16, _>(|i| i as u8));
+        let b = v128(std::array::from_fn::<u8, 16, _>(|i| 16 + i as u8));
+        let reversed = std::array::from_fn::<u8, 16, _>(|i| 31 - i as u8);
+        assert_eq!(
+            run(I8x16Shuffle(reversed), vec![a.clone(), b]),
+            v128(reversed)
+        );
+
+        // Out of range indices select zero
+        let indices = v128([0u8, 15, 16, 255, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1]);
+        assert_eq!(
+            run(I8x16Swizzle, vec![a, indices]),
+            v128([0u8, 15, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1])
+        );
+    }
+
+    #[test]
+    fn test_memory() {
+        let mt = MemType {
+            lim: Limits { min: 1, max: None },
+            shared: false,
+            memory64: false,
+        };
+        let mut memory = MemInst::new(&mt, None).unwrap().unwrap();
+        memory.write(0, &[0xFF, 1, 2, 3, 4, 5, 6, 7]).unwrap();
+        let memarg = |offset| MemArg {
+            align: 0,
+            offset,
+            memidx: 0,
+        };
+
+        let mut stack = vec![Val::I32(0)];
+        assert_eq!(
+            execute(&V128Load8x8S(memarg(0)), &mut stack, Some(&mut memory)),
+            Ok(())
+        );
+        assert_eq!(stack, vec![v128([-1i16, 1, 2, 3, 4, 5, 6, 7])]);
+
+        let mut stack = vec![Val::I32(1)];
+        assert_eq!(
+            execute(&V128Load16Splat(memarg(1)), &mut stack, Some(&mut memory)),
+            Ok(())
+        );
+        assert_eq!(stack, vec![v128([0x0302u16; 8])]);
+
+        let mut stack = vec![Val::I32(4), Val::V128(0)];
+        assert_eq!(
+            execute(&V128Load32Lane(memarg(0), 2), &mut stack, Some(&mut memory)),
+            Ok(())
+        );
+        assert_eq!(stack, vec![v128([0, 0, 0x07060504, 0])]);
+
+        let mut stack = vec![Val::I32(16), v128([1i64, 2])];
+        assert_eq!(
+            execute(
+                &V128Store64Lane(memarg(0), 1),
+                &mut stack,
+                Some(&mut memory)
+            ),
+            Ok(())
+        );
+        assert!(stack.is_empty());
+        let mut bytes = [0; 8];
+        memory.read(16, &mut bytes).unwrap();
+        assert_eq!(bytes, 2i64.to_le_bytes());
+
+        let mut stack = vec![Val::I32(16)];
+        assert_eq!(
+            execute(&V128Load64Zero(memarg(0)), &mut stack, Some(&mut memory)),
+            Ok(())
+        );
+        assert_eq!(stack, vec![v128([2i64, 0])]);
+
+        // The offset is added to the address without wrapping around
+        let mut stack = vec![Val::I32(-1)];
+        assert_eq!(
+            execute(&V128Load(memarg(1)), &mut stack, Some(&mut memory)),
+            Err(Trap::OutOfBoundsMemoryAccess)
+        );
+        let mut stack = vec![Val::I32(65535 - 15), Val::V128(0)];
+        assert_eq!(
+            execute(&V128Store(memarg(1)), &mut stack, Some(&mut memory)),
+            Err(Trap::OutOfBoundsMemoryAccess)
+        );
+    }
+}
@@ -0,0 +1,403 @@
+use std::ops::Range;
+
+use crate::module::{
+    indices::TypeIdx,
+    types::{
+        CompType, DefType, FieldType, FuncType, HeapType, Mutability, RefType, StorageType, ValType,
+    },
+};
+
+/// Whether a value of type `t1` can be used where a value of type `t2` is expected
+///
+/// Only reference types have proper subtypes, which follow the subtyping of their heap types and let a non-nullable
+/// reference match a nullable one. Both types are defined by the same module.
+pub fn matches(types: &[DefType], t1: &ValType, t2: &ValType) -> bool {
+    match (t1, t2) {
+        (ValType::RefType(rt1), ValType::RefType(rt2)) => matches_ref_type(types, rt1, rt2),
+        _ => t1 == t2,
+    }
+}
+
+pub fn matches_ref_type(types: &[DefType], rt1: &RefType, rt2: &RefType) -> bool {
+    (rt2.nullable || !rt1.nullable) && matches_heap_type(types, &rt1.ht, types, &rt2.ht)
+}
+
+/// Whether each of a sequence of types matches the corresponding type of another sequence of the same length
+pub fn matches_all(types: &[DefType], ts1: &[ValType], ts2: &[ValType]) -> bool {
+    ts1.len() == ts2.len() && ts1.iter().zip(ts2).all(|(t1, t2)| matches(types, t1, t2))
+}
+
+/// Whether heap type `ht1`, whose type indices refer to `types1`, is a subtype of `ht2`, whose indices refer to
+/// `types2`
+///
+/// The two sides can come from different modules, which is how the runtime checks casts of objects that were
+/// allocated by another instance.
+pub fn matches_heap_type(
+    types1: &[DefType],
+    ht1: &HeapType,
+    types2: &[DefType],
+    ht2: &HeapType,
+) -> bool {
+    match (ht1, ht2) {
+        (HeapType::Type(x), HeapType::Type(y)) => {
+            // A concrete type matches its declared supertype and, transitively, the supertypes of that
+            let mut x = *x;
+            loop {
+                if equivalent(types1, x, types2, *y) {
+                    return true;
+                }
+                // Supertypes are defined before their subtypes, which also keeps this loop from cycling
+                match types1.get(x as usize).and_then(|t| t.st.supertypes.first()) {
+                    Some(&supertype) if supertype < x => x = supertype,
+                    _ => return false,
+                }
+            }
+        }
+        (HeapType::Type(x), ht2) => {
+            let ht1 = match types1.get(*x as usize).map(|t| &t.st.ct) {
+                Some(CompType::Func(_)) => HeapType::Func,
+                Some(CompType::Struct(_)) => HeapType::Struct,
+                Some(CompType::Array(_)) => HeapType::Array,
+                None => return false,
+            };
+            matches_abstract(&ht1, ht2)
+        }
+        (ht1, HeapType::Type(_)) => *ht1 == bottom(types2, ht2),
+        (ht1, ht2) => matches_abstract(ht1, ht2),
+    }
+}
+
+/// Subtyping between abstract heap types, where `eq` sits between `any` and the types of objects it can compare
+fn matches_abstract(ht1: &HeapType, ht2: &HeapType) -> bool {
+    use HeapType::*;
+
+    ht1 == ht2
+        || matches!(
+            (ht1, ht2),
+            (Eq | I31 | Struct | Array | None, Any)
+                | (I31 | Struct | Array | None, Eq)
+                | (None, I31 | Struct | Array)
+                | (NoFunc, Func)
+                | (NoExtern, Extern)
+                | (NoExn, Exn)
+        )
+}
+
+/// The abstract heap type at the top of the hierarchy a heap type belongs to, which is what casts stay within
+pub fn top(types: &[DefType], ht: &HeapType) -> HeapType {
+    match ht {
+        HeapType::Func | HeapType::NoFunc => HeapType::Func,
+        HeapType::Extern | HeapType::NoExtern => HeapType::Extern,
+        HeapType::Exn | HeapType::NoExn => HeapType::Exn,
+        HeapType::Type(x) => match types.get(*x as usize).map(|t| &t.st.ct) {
+            Some(CompType::Struct(_) | CompType::Array(_)) => HeapType::Any,
+            _ => HeapType::Func,
+        },
+        _ => HeapType::Any,
+    }
+}
+
+/// The abstract heap type at the bottom of the hierarchy a heap type belongs to, which only null references have
+pub fn bottom(types: &[DefType], ht: &HeapType) -> HeapType {
+    match top(types, ht) {
+        HeapType::Func => HeapType::NoFunc,
+        HeapType::Extern => HeapType::NoExtern,
+        HeapType::Exn => HeapType::NoExn,
+        _ => HeapType::None,
+    }
+}
+
+/// Whether type `x` of `types1` is equivalent to type `y` of `types2`
+///
+/// Types are compared iso-recursively: they must be at the same position of recursion groups that are structurally
+/// equal, where a reference into the group is compared by its position and a reference to an earlier type by
+/// equivalence of the types it refers to.
+pub fn equivalent<'a>(
+    types1: &'a [DefType],
+    x: TypeIdx,
+    types2: &'a [DefType],
+    y: TypeIdx,
+) -> bool {
+    let same_module = std::ptr::eq(types1, types2);
+    if same_module && x == y {
+        return true;
+    }
+    let (Some(t1), Some(t2)) = (types1.get(x as usize), types2.get(y as usize)) else {
+        return false;
+    };
+    let (group1, group2) = (&t1.group, &t2.group);
+    if x - group1.start != y - group2.start
+        || group1.len() != group2.len()
+        || (same_module && group1 == group2)
+    {
+        return false;
+    }
+    let members = |types: &'a [DefType], group: &Range<TypeIdx>| {
+        types.get(group.start as usize..group.end as usize)
+    };
+    let (Some(members1), Some(members2)) = (members(types1, group1), members(types2, group2))
+    else {
+        return false;
+    };
+    let same = |a: TypeIdx, b: TypeIdx| match (group1.contains(&a), group2.contains(&b)) {
+        (true, true) => a - group1.start == b - group2.start,
+        // Recursion groups can only refer back to earlier types, which keeps this recursion finite
+        (false, false) if a < group1.start && b < group2.start => equivalent(types1, a, types2, b),
+        _ => false,
+    };
+    members1.iter().zip(members2).all(|(t1, t2)| {
+        let (st1, st2) = (&t1.st, &t2.st);
+        st1.is_final == st2.is_final
+            && st1.supertypes.len() == st2.supertypes.len()
+            && st1
+                .supertypes
+                .iter()
+                .zip(&st2.supertypes)
+                .all(|(a, b)| same(*a, *b))
+            && same_comp_type(&st1.ct, &st2.ct, &same)
+    })
+}
+
+/// Structural equality of composite types, comparing type indices with `same`
+fn same_comp_type(
+    ct1: &CompType,
+    ct2: &CompType,
+    same: &impl Fn(TypeIdx, TypeIdx) -> bool,
+) -> bool {
+    let same_types = |ts1: &[ValType], ts2: &[ValType]| {
+        ts1.len() == ts2.len()
+            && ts1
+                .iter()
+                .zip(ts2)
+                .all(|(t1, t2)| same_val_type(t1, t2, same))
+    };
+    let same_field = |ft1: &FieldType, ft2: &FieldType| {
+        ft1.m == ft2.m
+            && match (&ft1.st, &ft2.st) {
+                (StorageType::Val(t1), StorageType::Val(t2)) => same_val_type(t1, t2, same),
+                (st1, st2) => st1 == st2,
+            }
+    };
+    match (ct1, ct2) {
+        (CompType::Func(ft1), CompType::Func(ft2)) => {
+            same_types(&ft1.rt1, &ft2.rt1) && same_types(&ft1.rt2, &ft2.rt2)
+        }
+        (CompType::Struct(st1), CompType::Struct(st2)) => {
+            st1.fields.len() == st2.fields.len()
+                && st1
+                    .fields
+                    .iter()
+                    .zip(&st2.fields)
+                    .all(|(ft1, ft2)| same_field(ft1, ft2))
+        }
+        (CompType::Array(at1), CompType::Array(at2)) => same_field(&at1.ft, &at2.ft),
+        _ => false,
+    }
+}
+
+fn same_val_type(t1: &ValType, t2: &ValType, same: &impl Fn(TypeIdx, TypeIdx) -> bool) -> bool {
+    match (t1, t2) {
+        (ValType::RefType(rt1), ValType::RefType(rt2)) => {
+            rt1.nullable == rt2.nullable
+                && match (&rt1.ht, &rt2.ht) {
+                    (HeapType::Type(a), HeapType::Type(b)) => same(*a, *b),
+                    (ht1, ht2) => ht1 == ht2,
+                }
+        }
+        _ => t1 == t2,
+    }
+}
+
+/// Whether composite type `ct1` can be declared a subtype of `ct2`
+///
+/// Functions are contravariant in their parameters and covariant in their results. A struct can add fields after
+/// those of its supertype, and fields, including the elements of arrays, are covariant when immutable but must be
+/// equivalent when mutable.
+pub fn matches_comp_type(types: &[DefType], ct1: &CompType, ct2: &CompType) -> bool {
+    match (ct1, ct2) {
+        (
+            CompType::Func(FuncType { rt1: p1, rt2: r1 }),
+            CompType::Func(FuncType { rt1: p2, rt2: r2 }),
+        ) => matches_all(types, p2, p1) && matches_all(types, r1, r2),
+        (CompType::Struct(st1), CompType::Struct(st2)) => {
+            st1.fields.len() >= st2.fields.len()
+                && st1
+                    .fields
+                    .iter()
+                    .zip(&st2.fields)
+                    .all(|(ft1, ft2)| matches_field_type(types, ft1, ft2))
+        }
+        (CompType::Array(at1), CompType::Array(at2)) => matches_field_type(types, &at1.ft, &at2.ft),
+        _ => false,
+    }
+}
+
+fn matches_field_type(types: &[DefType], ft1: &FieldType, ft2: &FieldType) -> bool {
+    let matches_storage = |st1: &StorageType, st2: &StorageType| match (st1, st2) {
+        (StorageType::Val(t1), StorageType::Val(t2)) => matches(types, t1, t2),
+        (st1, st2) => st1 == st2,
+    };
+    ft1.m == ft2.m
+        && matches_storage(&ft1.st, &ft2.st)
+        && (ft1.m == Mutability::Const || matches_storage(&ft2.st, &ft1.st))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::module::types::{ArrayType, NumType, PackedType, RecType, StructType, SubType};
+
+    use super::*;
+
+    const I32: ValType = ValType::NumType(NumType::I32);
+
+    fn field(st: StorageType, m: Mutability) -> FieldType {
+        FieldType { st, m }
+    }
+
+    fn sub(supertypes: Vec<TypeIdx>, ct: CompType) -> SubType {
+        SubType {
+            is_final: false,
+            supertypes,
+            ct,
+        }
+    }
+
+    fn r#struct(fields: Vec<FieldType>) -> CompType {
+        CompType::Struct(StructType { fields })
+    }
+
+    fn reference(nullable: bool, typeidx: TypeIdx) -> StorageType {
+        StorageType::Val(ValType::RefType(RefType {
+            nullable,
+            ht: HeapType::Type(typeidx),
+        }))
+    }
+
+    #[test]
+    fn test_matches_abstract() {
+        let types = [];
+        let heap = |ht1, ht2| matches_heap_type(&types, &ht1, &types, &ht2);
+        assert!(heap(HeapType::I31, HeapType::Eq));
+        assert!(heap(HeapType::Struct, HeapType::Any));
+        assert!(heap(HeapType::None, HeapType::Array));
+        assert!(heap(HeapType::NoFunc, HeapType::Func));
+        assert!(!heap(HeapType::Any, HeapType::Eq));
+        assert!(!heap(HeapType::Func, HeapType::Any));
+        assert!(!heap(HeapType::NoExtern, HeapType::Any));
+        assert!(!heap(HeapType::I31, HeapType::Struct));
+
+        let nullable = ValType::RefType(RefType::EQREF);
+        let non_null = ValType::RefType(RefType::non_null(HeapType::I31));
+        assert!(matches(&types, &non_null, &nullable));
+        assert!(!matches(&types, &nullable, &non_null));
+        assert!(!matches(&types, &I32, &nullable));
+    }
+
+    #[test]
+    fn test_matches_concrete() {
+        // $point = (sub (struct (field i32))), $point3 = (sub $point (struct (field i32) (field i32))), a list
+        // that refers to itself and an array of i8
+        let types = DefType::flatten(vec![
+            RecType {
+                types: vec![sub(
+                    vec![],
+                    r#struct(vec![field(StorageType::Val(I32), Mutability::Const)]),
+                )],
+            },
+            RecType {
+                types: vec![sub(
+                    vec![0],
+                    r#struct(vec![
+                        field(StorageType::Val(I32), Mutability::Const),
+                        field(StorageType::Val(I32), Mutability::Var),
+                    ]),
+                )],
+            },
+            RecType {
+                types: vec![sub(
+                    vec![],
+                    r#struct(vec![field(reference(true, 2), Mutability::Var)]),
+                )],
+            },
+            RecType {
+                types: vec![sub(
+                    vec![],
+                    CompType::Array(ArrayType {
+                        ft: field(StorageType::Packed(PackedType::I8), Mutability::Var),
+                    }),
+                )],
+            },
+        ]);
+        let heap = |ht1, ht2| matches_heap_type(&types, &ht1, &types, &ht2);
+        assert!(heap(HeapType::Type(1), HeapType::Type(0)));
+        assert!(!heap(HeapType::Type(0), HeapType::Type(1)));
+        assert!(heap(HeapType::Type(1), HeapType::Struct));
+        assert!(heap(HeapType::Type(3), HeapType::Eq));
+        assert!(!heap(HeapType::Type(3), HeapType::Struct));
+        assert!(heap(HeapType::None, HeapType::Type(2)));
+        assert!(!heap(HeapType::NoFunc, HeapType::Type(2)));
+        assert!(!heap(HeapType::Struct, HeapType::Type(0)));
+        assert_eq!(top(&types, &HeapType::Type(3)), HeapType::Any);
+
+        // The same definitions in another module are equivalent, so casts work across instances
+        let other = types.clone();
+        assert!(matches_heap_type(
+            &types,
+            &HeapType::Type(1),
+            &other,
+            &HeapType::Type(0)
+        ));
+        assert!(equivalent(&types, 2, &other, 2));
+        assert!(!equivalent(&types, 2, &other, 3));
+
+        // A struct can add fields to its supertype, but mutable fields can't change their type
+        assert!(matches_comp_type(&types, &types[1].st.ct, &types[0].st.ct));
+        assert!(!matches_comp_type(&types, &types[0].st.ct, &types[1].st.ct));
+        let covariant = r#struct(vec![field(reference(false, 2), Mutability::Const)]);
+        let invariant = r#struct(vec![field(reference(false, 2), Mutability::Var)]);
+        assert!(matches_comp_type(
+            &types,
+            &covariant,
+            &r#struct(vec![field(reference(true, 2), Mutability::Const)])
+        ));
+        assert!(!matches_comp_type(&types, &invariant, &types[2].st.ct));
+    }
+
+    #[test]
+    fn test_equivalent_rec_groups() {
+        // Two groups of two mutually recursive structs, and a group with the same structs in the other order
+        let pair = |a, b| {
+            vec![
+                sub(
+                    vec![],
+                    r#struct(vec![field(reference(true, a), Mutability::Var)]),
+                ),
+                sub(
+                    vec![],
+                    r#struct(vec![field(reference(true, b), Mutability::Var)]),
+                ),
+            ]
+        };
+        let types = DefType::flatten(vec![
+            RecType { types: pair(1, 0) },
+            RecType { types: pair(3, 2) },
+            RecType {
+                types: vec![
+                    sub(
+                        vec![],
+                        r#struct(vec![field(StorageType::Val(I32), Mutability::Var)]),
+                    ),
+                    sub(
+                        vec![],
+                        r#struct(vec![field(reference(true, 5), Mutability::Var)]),
+                    ),
+                ],
+            },
+        ]);
+        assert!(equivalent(&types, 0, &types, 2));
+        assert!(equivalent(&types, 1, &types, 3));
+        assert!(!equivalent(&types, 0, &types, 3));
+        assert!(!equivalent(&types, 0, &types, 4));
+        assert!(!equivalent(&types, 0, &types, 1));
+    }
+}
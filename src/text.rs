@@ -0,0 +1,772 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Write},
+};
+
+use crate::{
+    instructions::Expression,
+    module::{
+        data::Data,
+        element::Element,
+        export::ExportDescriptor,
+        import::ImportDescriptor,
+        indices::{FuncIdx, TypeIdx},
+        names::NameMap,
+        types::{
+            CompType, DefType, FieldType, FuncType, GlobalType, HeapType, Limits, MemType,
+            Mutability, NumType, PackedType, RefType, StorageType, SubType, TableType, ValType,
+            VecType,
+        },
+        values::Name,
+    },
+    Module,
+};
+
+mod instructions;
+
+/// A module rendered in the text format, which `Module::wat` returns so that it can be configured before printing
+///
+/// Indices are printed as comments, such as `(func (;3;) ...)`, unless the name section gives the item a name.
+pub struct Wat<'a> {
+    module: &'a Module,
+    folded: bool,
+}
+
+impl Module {
+    /// Render the module in the text format
+    pub fn wat(&self) -> Wat<'_> {
+        Wat {
+            module: self,
+            folded: false,
+        }
+    }
+}
+
+impl Wat<'_> {
+    /// Print the instructions of function bodies as nested S-expressions instead of a flat sequence
+    pub fn folded(mut self, folded: bool) -> Self {
+        self.folded = folded;
+        self
+    }
+}
+
+impl fmt::Display for Wat<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Printer::new(self.module, self.folded).module())
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.wat().fmt(f)
+    }
+}
+
+/// Writes a module line by line, keeping track of the indentation and of the function being printed
+pub(crate) struct Printer<'a> {
+    module: &'a Module,
+    folded: bool,
+    out: String,
+    indent: usize,
+    /// The type of every function, imports first
+    funcs: Vec<TypeIdx>,
+    /// The identifier of every function that has a usable name
+    func_ids: Vec<Option<String>>,
+    /// The identifier of every local of the function being printed that has a usable name
+    local_ids: Vec<Option<String>>,
+    /// The number of blocks around the instruction being printed
+    depth: usize,
+}
+
+impl<'a> Printer<'a> {
+    pub(crate) fn new(module: &'a Module, folded: bool) -> Self {
+        let funcs: Vec<TypeIdx> = module
+            .imports
+            .iter()
+            .filter_map(|import| match import.descriptor {
+                ImportDescriptor::Func(typeidx) => Some(typeidx),
+                _ => None,
+            })
+            .chain(module.functions.iter().copied())
+            .collect();
+        let func_ids = ids(Some(&module.names.functions), funcs.len());
+        Self {
+            module,
+            folded,
+            out: String::new(),
+            indent: 0,
+            funcs,
+            func_ids,
+            local_ids: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.indent {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn module(mut self) -> String {
+        let module = self.module;
+        match module.names.module.as_ref().and_then(id) {
+            Some(id) => self.line(&format!("(module {}", id)),
+            None => self.line("(module"),
+        }
+        let empty = self.out.len();
+        self.indent += 1;
+
+        self.types(&module.types);
+
+        let (mut funcs, mut tables, mut mems, mut globals, mut tags) = (0, 0, 0, 0, 0);
+        for import in &module.imports {
+            let descriptor = match &import.descriptor {
+                ImportDescriptor::Func(typeidx) => {
+                    funcs += 1;
+                    format!(
+                        "(func {} {})",
+                        self.func_def(funcs - 1),
+                        self.type_use(*typeidx)
+                    )
+                }
+                ImportDescriptor::Table(tt) => {
+                    tables += 1;
+                    format!("(table (;{};) {})", tables - 1, tt)
+                }
+                ImportDescriptor::Mem(mt) => {
+                    mems += 1;
+                    format!("(memory (;{};) {})", mems - 1, mt)
+                }
+                ImportDescriptor::Global(gt) => {
+                    globals += 1;
+                    format!("(global (;{};) {})", globals - 1, gt)
+                }
+                ImportDescriptor::Tag(tt) => {
+                    tags += 1;
+                    format!("(tag (;{};) {})", tags - 1, self.type_use(tt.typeidx))
+                }
+            };
+            self.line(&format!(
+                "(import {} {} {})",
+                string(&import.module.0),
+                string(&import.name.0),
+                descriptor
+            ));
+        }
+
+        for (i, (typeidx, code)) in module.functions.iter().zip(&module.code).enumerate() {
+            let funcidx = funcs + i as FuncIdx;
+            let locals: Vec<&ValType> = code
+                .code
+                .locals
+                .iter()
+                .flat_map(|local| (0..local.count).map(move |_| &local.value_type))
+                .collect();
+            let ft = module
+                .types
+                .get(*typeidx as usize)
+                .and_then(DefType::func_type);
+            let params = ft.map_or(0, |ft| ft.rt1.len());
+            self.local_ids = ids(module.names.locals.get(funcidx), params + locals.len());
+
+            let mut header = format!("(func {} (type {})", self.func_def(funcidx), typeidx);
+            if let Some(ft) = ft {
+                header += &self.locals("param", &ft.rt1.iter().collect::<Vec<_>>(), 0);
+                header += &results(&ft.rt2);
+            }
+            self.line(&header);
+            self.indent += 1;
+            if !locals.is_empty() {
+                let locals = self.locals("local", &locals, params);
+                self.line(locals.trim_start());
+            }
+            self.instructions(&code.code.body.0);
+            self.indent -= 1;
+            self.line(")");
+        }
+
+        for table in &module.tables {
+            let init = match &table.init {
+                Some(init) => format!(" {}", self.const_expr(init)),
+                None => String::new(),
+            };
+            self.line(&format!("(table (;{};) {}{})", tables, table.tt, init));
+            tables += 1;
+        }
+        for memory in &module.memories {
+            self.line(&format!("(memory (;{};) {})", mems, memory.mt));
+            mems += 1;
+        }
+        for tag in &module.tags {
+            let type_use = self.type_use(tag.tt.typeidx);
+            self.line(&format!("(tag (;{};) {})", tags, type_use));
+            tags += 1;
+        }
+        for global in &module.globals {
+            let init = self.const_expr(&global.init);
+            self.line(&format!("(global (;{};) {} {})", globals, global.gt, init));
+            globals += 1;
+        }
+
+        for export in &module.exports {
+            let descriptor = match export.descriptor {
+                ExportDescriptor::Func(funcidx) => format!("func {}", self.func_use(funcidx)),
+                ExportDescriptor::Table(tableidx) => format!("table {}", tableidx),
+                ExportDescriptor::Mem(memidx) => format!("memory {}", memidx),
+                ExportDescriptor::Global(globalidx) => format!("global {}", globalidx),
+                ExportDescriptor::Tag(tagidx) => format!("tag {}", tagidx),
+            };
+            self.line(&format!(
+                "(export {} ({}))",
+                string(&export.name.0),
+                descriptor
+            ));
+        }
+        if let Some(start) = module.start {
+            let start = self.func_use(start);
+            self.line(&format!("(start {})", start));
+        }
+
+        for (i, element) in module.elements.iter().enumerate() {
+            let element = self.element(element);
+            self.line(&format!("(elem (;{};){})", i, element));
+        }
+        for (i, data) in module.data.iter().enumerate() {
+            let (memory, offset, bytes) = match data {
+                Data::Active(offset, bytes) => (String::new(), Some(offset), bytes),
+                Data::ActiveExplicit(memidx, offset, bytes) => {
+                    (format!(" (memory {})", memidx), Some(offset), bytes)
+                }
+                Data::Passive(bytes) => (String::new(), None, bytes),
+            };
+            let offset = match offset {
+                Some(offset) => format!(" {}", self.offset(offset)),
+                None => String::new(),
+            };
+            self.line(&format!(
+                "(data (;{};){}{} {})",
+                i,
+                memory,
+                offset,
+                string(bytes)
+            ));
+        }
+
+        self.indent -= 1;
+        if self.out.len() == empty {
+            // Close an empty module on the same line, as in `(module)`
+            self.out.pop();
+            self.out.push(')');
+            self.out.push('\n');
+        } else {
+            self.line(")");
+        }
+        self.out
+    }
+
+    /// Print the type section, grouping the types of a recursion group with `rec`
+    fn types(&mut self, types: &[DefType]) {
+        let mut i = 0;
+        while i < types.len() {
+            let group = types[i].group.clone();
+            if group.len() == 1 {
+                self.line(&format!("(type (;{};) {})", i, sub_type(&types[i].st)));
+            } else {
+                self.line("(rec");
+                self.indent += 1;
+                for typeidx in group.clone() {
+                    let st = &types[typeidx as usize].st;
+                    self.line(&format!("(type (;{};) {})", typeidx, sub_type(st)));
+                }
+                self.indent -= 1;
+                self.line(")");
+            }
+            i = (group.end as usize).max(i + 1);
+        }
+    }
+
+    /// The identifier or index comment that a function is defined with
+    fn func_def(&self, funcidx: FuncIdx) -> String {
+        match &self.func_ids[funcidx as usize] {
+            Some(id) => id.clone(),
+            None => format!("(;{};)", funcidx),
+        }
+    }
+
+    /// The identifier or index that a function is referred to with
+    fn func_use(&self, funcidx: FuncIdx) -> String {
+        match self.func_ids.get(funcidx as usize) {
+            Some(Some(id)) => id.clone(),
+            _ => funcidx.to_string(),
+        }
+    }
+
+    fn local_use(&self, localidx: u32) -> String {
+        match self.local_ids.get(localidx as usize) {
+            Some(Some(id)) => id.clone(),
+            _ => localidx.to_string(),
+        }
+    }
+
+    /// A reference to a type, followed by its parameters and results if it is a function type
+    fn type_use(&self, typeidx: TypeIdx) -> String {
+        let ft = self
+            .module
+            .types
+            .get(typeidx as usize)
+            .and_then(DefType::func_type);
+        match ft {
+            Some(ft) => format!("(type {}){}{}", typeidx, params(&ft.rt1), results(&ft.rt2)),
+            None => format!("(type {})", typeidx),
+        }
+    }
+
+    /// Declare parameters or locals, one by one if any of them has a name so that the names can be attached
+    fn locals(&self, keyword: &str, types: &[&ValType], first: usize) -> String {
+        if types.is_empty() {
+            return String::new();
+        }
+        let named =
+            (first..first + types.len()).any(|i| matches!(self.local_ids.get(i), Some(Some(_))));
+        if !named {
+            let types: Vec<String> = types.iter().map(ToString::to_string).collect();
+            return format!(" ({} {})", keyword, types.join(" "));
+        }
+        types
+            .iter()
+            .enumerate()
+            .map(|(i, t)| match self.local_ids.get(first + i) {
+                Some(Some(id)) => format!(" ({} {} {})", keyword, id, t),
+                _ => format!(" ({} {})", keyword, t),
+            })
+            .collect()
+    }
+
+    /// A constant expression, as a sequence of folded instructions
+    pub(crate) fn const_expr(&self, expr: &Expression) -> String {
+        let instructions: Vec<String> = expr
+            .0
+            .iter()
+            .map(|instruction| format!("({})", self.plain(instruction)))
+            .collect();
+        instructions.join(" ")
+    }
+
+    /// The offset of an active segment, which can drop the `offset` keyword if it is a single instruction
+    fn offset(&self, expr: &Expression) -> String {
+        if expr.0.len() == 1 {
+            self.const_expr(expr)
+        } else {
+            format!("(offset {})", self.const_expr(expr))
+        }
+    }
+
+    fn element(&self, element: &Element) -> String {
+        let funcs = |funcidxs: &[FuncIdx]| -> String {
+            funcidxs
+                .iter()
+                .map(|funcidx| format!(" {}", self.func_use(*funcidx)))
+                .collect()
+        };
+        let items = |t: &RefType, exprs: &[Expression]| -> String {
+            let items: String = exprs
+                .iter()
+                .map(|expr| format!(" (item {})", self.const_expr(expr)))
+                .collect();
+            format!(" {}{}", t, items)
+        };
+        match element {
+            Element::ActiveIndex(offset, funcidxs) => {
+                format!(" {} func{}", self.offset(offset), funcs(funcidxs))
+            }
+            Element::PassiveIndex(_, funcidxs) => format!(" func{}", funcs(funcidxs)),
+            Element::ActiveExplicitIndex(tableidx, offset, _, funcidxs) => format!(
+                " (table {}) {} func{}",
+                tableidx,
+                self.offset(offset),
+                funcs(funcidxs)
+            ),
+            Element::DeclarativeIndex(_, funcidxs) => format!(" declare func{}", funcs(funcidxs)),
+            Element::ActiveExpression(offset, exprs) => format!(
+                " {}{}",
+                self.offset(offset),
+                items(&RefType::FUNCREF, exprs)
+            ),
+            Element::PassiveExpression(t, exprs) => items(t, exprs),
+            Element::ActiveExplicitExpression(tableidx, offset, t, exprs) => format!(
+                " (table {}) {}{}",
+                tableidx,
+                self.offset(offset),
+                items(t, exprs)
+            ),
+            Element::DeclarativeExpression(t, exprs) => format!(" declare{}", items(t, exprs)),
+        }
+    }
+}
+
+/// Give each index of an index space the identifier of its name, unless it has no name or the name was already taken
+fn ids(names: Option<&NameMap>, count: usize) -> Vec<Option<String>> {
+    let mut taken = HashSet::new();
+    (0..count)
+        .map(|i| {
+            names
+                .and_then(|names| names.get(i as u32))
+                .and_then(id)
+                .filter(|id| taken.insert(id.clone()))
+        })
+        .collect()
+}
+
+/// The identifier for a name, replacing the characters that identifiers can't contain
+fn id(name: &Name) -> Option<String> {
+    if name.0.is_empty() {
+        return None;
+    }
+    let name: String = String::from_utf8_lossy(&name.0)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() && !"\"(),;[]{}".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    Some(format!("${}", name))
+}
+
+/// A string literal, escaping quotes, backslashes and any byte that isn't printable ASCII
+pub(crate) fn string(bytes: &[u8]) -> String {
+    let mut string = String::from("\"");
+    for byte in bytes {
+        match byte {
+            b'"' => string.push_str("\\\""),
+            b'\\' => string.push_str("\\\\"),
+            0x20..=0x7E => string.push(*byte as char),
+            _ => write!(string, "\\{:02x}", byte).unwrap(),
+        }
+    }
+    string.push('"');
+    string
+}
+
+/// A float literal that parses back to exactly the same value, including the payload of a NaN
+fn f32(z: f32) -> String {
+    let bits = z.to_bits();
+    if z.is_nan() {
+        nan(bits >> 31 != 0, u64::from(bits & 0x7F_FFFF), 1 << 22)
+    } else if z.is_infinite() {
+        infinity(z.is_sign_negative())
+    } else {
+        format!("{:?}", z)
+    }
+}
+
+fn f64(z: f64) -> String {
+    let bits = z.to_bits();
+    if z.is_nan() {
+        nan(bits >> 63 != 0, bits & 0xF_FFFF_FFFF_FFFF, 1 << 51)
+    } else if z.is_infinite() {
+        infinity(z.is_sign_negative())
+    } else {
+        format!("{:?}", z)
+    }
+}
+
+/// A NaN, which only has an explicit payload if it isn't the canonical NaN
+fn nan(negative: bool, payload: u64, canonical: u64) -> String {
+    let sign = if negative { "-" } else { "" };
+    if payload == canonical {
+        format!("{}nan", sign)
+    } else {
+        format!("{}nan:0x{:x}", sign, payload)
+    }
+}
+
+fn infinity(negative: bool) -> String {
+    if negative { "-inf" } else { "inf" }.to_string()
+}
+
+fn params(types: &[ValType]) -> String {
+    list("param", types)
+}
+
+fn results(types: &[ValType]) -> String {
+    list("result", types)
+}
+
+fn list(keyword: &str, types: &[ValType]) -> String {
+    if types.is_empty() {
+        return String::new();
+    }
+    let types: Vec<String> = types.iter().map(ToString::to_string).collect();
+    format!(" ({} {})", keyword, types.join(" "))
+}
+
+pub(crate) fn sub_type(st: &SubType) -> String {
+    if st.is_final && st.supertypes.is_empty() {
+        return comp_type(&st.ct);
+    }
+    let mut sub = String::from("(sub");
+    if st.is_final {
+        sub.push_str(" final");
+    }
+    for supertype in &st.supertypes {
+        write!(sub, " {}", supertype).unwrap();
+    }
+    format!("{} {})", sub, comp_type(&st.ct))
+}
+
+fn comp_type(ct: &CompType) -> String {
+    match ct {
+        CompType::Func(ft) => ft.to_string(),
+        CompType::Struct(st) => {
+            let fields: String = st
+                .fields
+                .iter()
+                .map(|ft| format!(" (field {})", field_type(ft)))
+                .collect();
+            format!("(struct{})", fields)
+        }
+        CompType::Array(at) => format!("(array {})", field_type(&at.ft)),
+    }
+}
+
+fn field_type(ft: &FieldType) -> String {
+    let st = match &ft.st {
+        StorageType::Val(t) => t.to_string(),
+        StorageType::Packed(PackedType::I8) => "i8".to_string(),
+        StorageType::Packed(PackedType::I16) => "i16".to_string(),
+    };
+    match ft.m {
+        Mutability::Const => st,
+        Mutability::Var => format!("(mut {})", st),
+    }
+}
+
+impl fmt::Display for NumType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::I32 => "i32",
+            Self::I64 => "i64",
+            Self::F32 => "f32",
+            Self::F64 => "f64",
+        })
+    }
+}
+
+impl fmt::Display for VecType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::V128 => f.write_str("v128"),
+        }
+    }
+}
+
+impl fmt::Display for HeapType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Func => f.write_str("func"),
+            Self::NoFunc => f.write_str("nofunc"),
+            Self::Extern => f.write_str("extern"),
+            Self::NoExtern => f.write_str("noextern"),
+            Self::Exn => f.write_str("exn"),
+            Self::NoExn => f.write_str("noexn"),
+            Self::Any => f.write_str("any"),
+            Self::Eq => f.write_str("eq"),
+            Self::I31 => f.write_str("i31"),
+            Self::Struct => f.write_str("struct"),
+            Self::Array => f.write_str("array"),
+            Self::None => f.write_str("none"),
+            Self::Type(typeidx) => write!(f, "{}", typeidx),
+        }
+    }
+}
+
+impl fmt::Display for RefType {
+    /// Nullable references to abstract heap types are printed with their shorthands, such as `funcref`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let shorthand = match self.ht {
+            _ if !self.nullable => None,
+            HeapType::Func => Some("funcref"),
+            HeapType::NoFunc => Some("nullfuncref"),
+            HeapType::Extern => Some("externref"),
+            HeapType::NoExtern => Some("nullexternref"),
+            HeapType::Exn => Some("exnref"),
+            HeapType::NoExn => Some("nullexnref"),
+            HeapType::Any => Some("anyref"),
+            HeapType::Eq => Some("eqref"),
+            HeapType::I31 => Some("i31ref"),
+            HeapType::Struct => Some("structref"),
+            HeapType::Array => Some("arrayref"),
+            HeapType::None => Some("nullref"),
+            HeapType::Type(_) => None,
+        };
+        match shorthand {
+            Some(shorthand) => f.write_str(shorthand),
+            None if self.nullable => write!(f, "(ref null {})", self.ht),
+            None => write!(f, "(ref {})", self.ht),
+        }
+    }
+}
+
+impl fmt::Display for ValType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NumType(t) => t.fmt(f),
+            Self::VecType(t) => t.fmt(f),
+            Self::RefType(t) => t.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for FuncType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(func{}{})", params(&self.rt1), results(&self.rt2))
+    }
+}
+
+impl fmt::Display for Limits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.min)?;
+        if let Some(max) = self.max {
+            write!(f, " {}", max)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TableType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.lim, self.et)
+    }
+}
+
+impl fmt::Display for MemType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.memory64 {
+            f.write_str("i64 ")?;
+        }
+        write!(f, "{}", self.lim)?;
+        if self.shared {
+            f.write_str(" shared")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for GlobalType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.m {
+            Mutability::Const => write!(f, "{}", self.t),
+            Mutability::Var => write!(f, "(mut {})", self.t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Decode;
+
+    use super::*;
+
+    // A module named "demo" with a function "clamp" whose locals are named "x" and "y", a nested block and if, a
+    // float constant, a load with an offset, and a data segment that needs escaping
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6D, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x06, 0x01, 0x60, 0x01, 0x7F, 0x01, 0x7F, // type
+        0x03, 0x02, 0x01, 0x00, // function
+        0x05, 0x03, 0x01, 0x00, 0x01, // memory
+        0x07, 0x09, 0x01, 0x05, 0x63, 0x6C, 0x61, 0x6D, 0x70, 0x00, 0x00, // export
+        0x0A, 0x25, 0x01, 0x23, 0x01, 0x01, 0x7F, 0x02, 0x7F, 0x20, 0x00, 0x41, 0x0A, 0x48, 0x04,
+        0x7F, 0x41, 0x00, 0x05, 0x20, 0x00, 0x28, 0x02, 0x04, 0x0B, 0x21, 0x01, 0x43, 0x00, 0x00,
+        0xC0, 0x3F, 0x1A, 0x20, 0x01, 0x0C, 0x00, 0x0B, 0x0B, // code
+        0x0B, 0x0B, 0x01, 0x00, 0x41, 0x08, 0x0B, 0x05, 0x68, 0x69, 0x0A, 0x22, 0x00, // data
+        0x00, 0x21, 0x04, 0x6E, 0x61, 0x6D, 0x65, 0x00, 0x05, 0x04, 0x64, 0x65, 0x6D, 0x6F, 0x01,
+        0x08, 0x01, 0x00, 0x05, 0x63, 0x6C, 0x61, 0x6D, 0x70, 0x02, 0x09, 0x01, 0x00, 0x02, 0x00,
+        0x01, 0x78, 0x01, 0x01, 0x79, // custom "name"
+    ];
+
+    #[test]
+    fn test_print() {
+        let (_, module) = Module::decode(MODULE).unwrap();
+        assert_eq!(
+            module.to_string(),
+            r#"(module $demo
+  (type (;0;) (func (param i32) (result i32)))
+  (func $clamp (type 0) (param $x i32) (result i32)
+    (local $y i32)
+    block (result i32)  ;; label = @1
+      local.get $x
+      i32.const 10
+      i32.lt_s
+      if (result i32)  ;; label = @2
+        i32.const 0
+      else
+        local.get $x
+        i32.load offset=4
+      end
+      local.set $y
+      f32.const 1.5
+      drop
+      local.get $y
+      br 0
+    end
+  )
+  (memory (;0;) 1)
+  (export "clamp" (func $clamp))
+  (data (;0;) (i32.const 8) "hi\0a\"\00")
+)
+"#
+        );
+
+        let (_, module) = Module::decode(&MODULE[..8]).unwrap();
+        assert_eq!(module.to_string(), "(module)\n");
+    }
+
+    #[test]
+    fn test_print_folded() {
+        let (_, module) = Module::decode(MODULE).unwrap();
+        assert_eq!(
+            module.wat().folded(true).to_string(),
+            r#"(module $demo
+  (type (;0;) (func (param i32) (result i32)))
+  (func $clamp (type 0) (param $x i32) (result i32)
+    (local $y i32)
+    (block (result i32)  ;; label = @1
+      (if (result i32) (i32.lt_s (local.get $x) (i32.const 10))  ;; label = @2
+        (then
+          (i32.const 0)
+        )
+        (else
+          (i32.load offset=4 (local.get $x))
+        )
+      )
+      (local.set $y)
+      (drop (f32.const 1.5))
+      (local.get $y)
+      (br 0)
+    )
+  )
+  (memory (;0;) 1)
+  (export "clamp" (func $clamp))
+  (data (;0;) (i32.const 8) "hi\0a\"\00")
+)
+"#
+        );
+    }
+
+    #[test]
+    fn test_floats() {
+        assert_eq!(f32(1.5), "1.5");
+        assert_eq!(f32(-0.0), "-0.0");
+        assert_eq!(f32(f32::INFINITY), "inf");
+        assert_eq!(f32(f32::from_bits(0xFFC0_0000)), "-nan");
+        assert_eq!(f32(f32::from_bits(0x7F80_0001)), "nan:0x1");
+        assert_eq!(f64(1e100), "1e100");
+        assert_eq!(f64(f64::NEG_INFINITY), "-inf");
+    }
+}
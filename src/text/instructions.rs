@@ -0,0 +1,470 @@
+use std::fmt::{Display, Write};
+
+use crate::{
+    instructions::{
+        BlockType, CatchClause, ControlInstruction, GcInstruction, Instruction, MemArg,
+        MemoryInstruction, NumericInstruction, ParametricInstruction, ReferenceInstruction,
+        TableInstruction, VariableInstruction, VectorInstruction,
+    },
+    module::indices::{MemIdx, TableIdx, TypeIdx},
+};
+
+use super::{f32, f64, results, Printer};
+
+impl Printer<'_> {
+    /// Print the instructions of a function body or block, one per line or folded
+    pub(super) fn instructions(&mut self, instructions: &[Instruction]) {
+        if self.folded {
+            self.folded_instructions(instructions);
+            return;
+        }
+        for instruction in instructions {
+            match instruction {
+                Instruction::Control(
+                    ControlInstruction::Block(_, body)
+                    | ControlInstruction::Loop(_, body)
+                    | ControlInstruction::TryTable(_, _, body),
+                ) => {
+                    let header = self.plain(instruction);
+                    self.block(&header, body);
+                    self.line("end");
+                }
+                Instruction::Control(ControlInstruction::If(_, then, otherwise)) => {
+                    let header = self.plain(instruction);
+                    self.block(&header, then);
+                    if !otherwise.is_empty() {
+                        self.depth += 1;
+                        self.line("else");
+                        self.indent += 1;
+                        self.instructions(otherwise);
+                        self.indent -= 1;
+                        self.depth -= 1;
+                    }
+                    self.line("end");
+                }
+                _ => {
+                    let text = self.plain(instruction);
+                    self.line(&text);
+                }
+            }
+        }
+    }
+
+    /// Print the header of a block with a comment that numbers its label, then its indented body
+    fn block(&mut self, header: &str, body: &[Instruction]) {
+        self.depth += 1;
+        self.line(&format!("{}  ;; label = @{}", header, self.depth));
+        self.indent += 1;
+        self.instructions(body);
+        self.indent -= 1;
+        self.depth -= 1;
+    }
+
+    /// Print instructions as S-expressions, nesting the instructions that produce the operands of another one
+    ///
+    /// Instructions that produce a single value are held back until it is clear whether the next instruction consumes
+    /// them. Since the operands of a folded instruction are written before it when it is unfolded, this never changes
+    /// the order of the instructions, only how they are grouped.
+    fn folded_instructions(&mut self, instructions: &[Instruction]) {
+        let mut pending: Vec<String> = Vec::new();
+        for instruction in instructions {
+            match instruction {
+                Instruction::Control(
+                    ControlInstruction::Block(_, body)
+                    | ControlInstruction::Loop(_, body)
+                    | ControlInstruction::TryTable(_, _, body),
+                ) => {
+                    self.flush(&mut pending);
+                    let header = format!("({}", self.plain(instruction));
+                    self.block(&header, body);
+                    self.line(")");
+                }
+                Instruction::Control(ControlInstruction::If(_, then, otherwise)) => {
+                    let condition = pending.pop().map(|c| format!(" {}", c)).unwrap_or_default();
+                    self.flush(&mut pending);
+                    self.depth += 1;
+                    self.line(&format!(
+                        "({}{}  ;; label = @{}",
+                        self.plain(instruction),
+                        condition,
+                        self.depth
+                    ));
+                    self.indent += 1;
+                    for (keyword, body) in [("then", then), ("else", otherwise)] {
+                        if keyword == "else" && body.is_empty() {
+                            continue;
+                        }
+                        self.line(&format!("({}", keyword));
+                        self.indent += 1;
+                        self.instructions(body);
+                        self.indent -= 1;
+                        self.line(")");
+                    }
+                    self.indent -= 1;
+                    self.depth -= 1;
+                    self.line(")");
+                }
+                _ => {
+                    let text = self.plain(instruction);
+                    match self.arity(instruction) {
+                        Some((pops, pushes)) => {
+                            let operands = pending.split_off(pending.len().saturating_sub(pops));
+                            let tree = operands
+                                .iter()
+                                .fold(format!("({}", text), |tree, operand| tree + " " + operand)
+                                + ")";
+                            if pushes == 1 {
+                                pending.push(tree);
+                            } else {
+                                self.flush(&mut pending);
+                                self.line(&tree);
+                            }
+                        }
+                        None => {
+                            self.flush(&mut pending);
+                            self.line(&format!("({})", text));
+                        }
+                    }
+                }
+            }
+        }
+        self.flush(&mut pending);
+    }
+
+    fn flush(&mut self, pending: &mut Vec<String>) {
+        for tree in pending.drain(..) {
+            self.line(&tree);
+        }
+    }
+
+    /// The number of operands an instruction pops and results it pushes, if that is simple to tell
+    ///
+    /// Instructions that return `None` are printed without nesting any operands.
+    fn arity(&self, instruction: &Instruction) -> Option<(usize, usize)> {
+        let call = |typeidx: Option<TypeIdx>, extra: usize| {
+            let ft = self.module.types.get(typeidx? as usize)?.func_type()?;
+            Some((ft.rt1.len() + extra, ft.rt2.len()))
+        };
+        match instruction {
+            Instruction::Control(instruction) => match instruction {
+                ControlInstruction::Unreachable | ControlInstruction::Nop => Some((0, 0)),
+                ControlInstruction::Call(funcidx) => {
+                    call(self.funcs.get(*funcidx as usize).copied(), 0)
+                }
+                ControlInstruction::CallIndirect(typeidx, _)
+                | ControlInstruction::CallRef(typeidx) => call(Some(*typeidx), 1),
+                _ => None,
+            },
+            Instruction::Reference(instruction) => match instruction {
+                ReferenceInstruction::Null(_) | ReferenceInstruction::Func(_) => Some((0, 1)),
+                ReferenceInstruction::IsNull | ReferenceInstruction::AsNonNull => Some((1, 1)),
+                ReferenceInstruction::Eq => Some((2, 1)),
+            },
+            Instruction::Parametric(ParametricInstruction::Drop) => Some((1, 0)),
+            Instruction::Parametric(_) => Some((3, 1)),
+            Instruction::Variable(instruction) => match instruction {
+                VariableInstruction::LocalGet(_) | VariableInstruction::GlobalGet(_) => {
+                    Some((0, 1))
+                }
+                VariableInstruction::LocalSet(_) | VariableInstruction::GlobalSet(_) => {
+                    Some((1, 0))
+                }
+                VariableInstruction::LocalTee(_) => Some((1, 1)),
+            },
+            Instruction::Table(instruction) => match instruction {
+                TableInstruction::TableGet(_) => Some((1, 1)),
+                TableInstruction::TableSet(_) => Some((2, 0)),
+                TableInstruction::TableGrow(_) => Some((2, 1)),
+                TableInstruction::TableSize(_) => Some((0, 1)),
+                TableInstruction::ElemDrop(_) => Some((0, 0)),
+                TableInstruction::TableInit(..)
+                | TableInstruction::TableCopy(..)
+                | TableInstruction::TableFill(_) => Some((3, 0)),
+            },
+            Instruction::Memory(instruction) => match instruction {
+                MemoryInstruction::MemorySize(_) => Some((0, 1)),
+                MemoryInstruction::MemoryGrow(_) => Some((1, 1)),
+                MemoryInstruction::DataDrop(_) => Some((0, 0)),
+                MemoryInstruction::MemoryInit(..)
+                | MemoryInstruction::MemoryCopy(..)
+                | MemoryInstruction::MemoryFill(_) => Some((3, 0)),
+                _ if instruction.name().contains(".load") => Some((1, 1)),
+                _ => Some((2, 0)),
+            },
+            Instruction::Numeric(
+                NumericInstruction::I32Const(_)
+                | NumericInstruction::I64Const(_)
+                | NumericInstruction::F32Const(_)
+                | NumericInstruction::F64Const(_),
+            ) => Some((0, 1)),
+            Instruction::Numeric(instruction) => {
+                let (_, op) = instruction.name().split_once('.')?;
+                let binary = [
+                    "eq", "ne", "lt", "gt", "le", "ge", "add", "sub", "mul", "div", "rem", "and",
+                    "or", "xor", "shl", "shr", "rotl", "rotr", "min", "max", "copysign",
+                ];
+                let op = op.split('_').next()?;
+                Some((if binary.contains(&op) { 2 } else { 1 }, 1))
+            }
+            Instruction::Saturating(_) => Some((1, 1)),
+            Instruction::Vector(VectorInstruction::V128Const(_)) => Some((0, 1)),
+            Instruction::Vector(_) | Instruction::Atomic(_) | Instruction::Gc(_) => None,
+        }
+    }
+
+    fn block_type(&self, bt: &BlockType) -> String {
+        match bt {
+            BlockType::Empty => String::new(),
+            BlockType::ValType(t) => results(std::slice::from_ref(t)),
+            BlockType::TypeIdx(typeidx) => format!(" {}", self.type_use(*typeidx)),
+        }
+    }
+
+    /// An instruction and its immediates on a single line, which is only the header of a block
+    pub(crate) fn plain(&self, instruction: &Instruction) -> String {
+        let immediates = match instruction {
+            Instruction::Control(instruction) => match instruction {
+                ControlInstruction::Block(bt, _)
+                | ControlInstruction::Loop(bt, _)
+                | ControlInstruction::If(bt, ..) => self.block_type(bt),
+                ControlInstruction::TryTable(bt, catches, _) => {
+                    self.block_type(bt) + &catch_clauses(catches)
+                }
+                ControlInstruction::Throw(tagidx) => immediate(tagidx),
+                ControlInstruction::Br(labelidx)
+                | ControlInstruction::BrIf(labelidx)
+                | ControlInstruction::BrOnNull(labelidx)
+                | ControlInstruction::BrOnNonNull(labelidx) => immediate(labelidx),
+                ControlInstruction::BrTable(labelidxs, default) => {
+                    labelidxs.iter().chain([default]).map(immediate).collect()
+                }
+                ControlInstruction::Call(funcidx) | ControlInstruction::ReturnCall(funcidx) => {
+                    immediate(self.func_use(*funcidx))
+                }
+                ControlInstruction::CallIndirect(typeidx, tableidx)
+                | ControlInstruction::ReturnCallIndirect(typeidx, tableidx) => {
+                    let table = if *tableidx != 0 {
+                        immediate(tableidx)
+                    } else {
+                        String::new()
+                    };
+                    format!("{} (type {})", table, typeidx)
+                }
+                ControlInstruction::CallRef(typeidx)
+                | ControlInstruction::ReturnCallRef(typeidx) => immediate(typeidx),
+                _ => String::new(),
+            },
+            Instruction::Reference(ReferenceInstruction::Null(ht)) => immediate(ht),
+            Instruction::Reference(ReferenceInstruction::Func(funcidx)) => {
+                immediate(self.func_use(*funcidx))
+            }
+            Instruction::Reference(_) => String::new(),
+            Instruction::Parametric(ParametricInstruction::SelectTyped(ts)) => results(ts),
+            Instruction::Parametric(_) => String::new(),
+            Instruction::Variable(instruction) => match instruction {
+                VariableInstruction::LocalGet(localidx)
+                | VariableInstruction::LocalSet(localidx)
+                | VariableInstruction::LocalTee(localidx) => immediate(self.local_use(*localidx)),
+                VariableInstruction::GlobalGet(globalidx)
+                | VariableInstruction::GlobalSet(globalidx) => immediate(globalidx),
+            },
+            Instruction::Table(instruction) => table(instruction),
+            Instruction::Memory(instruction) => memory(instruction),
+            Instruction::Numeric(instruction) => match instruction {
+                NumericInstruction::I32Const(n) => immediate(n),
+                NumericInstruction::I64Const(n) => immediate(n),
+                NumericInstruction::F32Const(z) => immediate(f32(*z)),
+                NumericInstruction::F64Const(z) => immediate(f64(*z)),
+                _ => String::new(),
+            },
+            Instruction::Saturating(_) => String::new(),
+            Instruction::Vector(instruction) => vector(instruction),
+            Instruction::Atomic(instruction) => match instruction.access() {
+                Some((_, _, width, m)) => memarg(m, width.trailing_zeros()),
+                None => String::new(),
+            },
+            Instruction::Gc(instruction) => gc(instruction),
+        };
+        format!("{}{}", instruction.name(), immediates)
+    }
+}
+
+/// An immediate, with the space that separates it from what comes before
+fn immediate(immediate: impl Display) -> String {
+    format!(" {}", immediate)
+}
+
+fn catch_clauses(catches: &[CatchClause]) -> String {
+    catches
+        .iter()
+        .map(|catch| match catch {
+            CatchClause::Catch(tagidx, labelidx) => format!(" (catch {} {})", tagidx, labelidx),
+            CatchClause::CatchRef(tagidx, labelidx) => {
+                format!(" (catch_ref {} {})", tagidx, labelidx)
+            }
+            CatchClause::CatchAll(labelidx) => format!(" (catch_all {})", labelidx),
+            CatchClause::CatchAllRef(labelidx) => format!(" (catch_all_ref {})", labelidx),
+        })
+        .collect()
+}
+
+fn table(instruction: &TableInstruction) -> String {
+    use TableInstruction::*;
+
+    let tableidx = |tableidx: &TableIdx| {
+        if *tableidx != 0 {
+            immediate(tableidx)
+        } else {
+            String::new()
+        }
+    };
+    match instruction {
+        TableGet(x) | TableSet(x) | TableGrow(x) | TableSize(x) | TableFill(x) => tableidx(x),
+        TableInit(elemidx, x) => tableidx(x) + &immediate(elemidx),
+        ElemDrop(elemidx) => immediate(elemidx),
+        TableCopy(dst, src) if *dst != 0 || *src != 0 => format!(" {} {}", dst, src),
+        TableCopy(..) => String::new(),
+    }
+}
+
+fn memory(instruction: &MemoryInstruction) -> String {
+    use MemoryInstruction::*;
+
+    let memidx = |memidx: &MemIdx| {
+        if *memidx != 0 {
+            immediate(memidx)
+        } else {
+            String::new()
+        }
+    };
+    match instruction {
+        MemorySize(m) | MemoryGrow(m) | MemoryFill(m) => memidx(m),
+        MemoryInit(dataidx, m) => memidx(m) + &immediate(dataidx),
+        DataDrop(dataidx) => immediate(dataidx),
+        MemoryCopy(dst, src) if *dst != 0 || *src != 0 => format!(" {} {}", dst, src),
+        MemoryCopy(..) => String::new(),
+        I32Load(m) | I64Load(m) | F32Load(m) | F64Load(m) | I32Load8S(m) | I32Load8U(m)
+        | I32Load16S(m) | I32Load16U(m) | I64Load8S(m) | I64Load8U(m) | I64Load16S(m)
+        | I64Load16U(m) | I64Load32S(m) | I64Load32U(m) | I32Store(m) | I64Store(m)
+        | F32Store(m) | F64Store(m) | I32Store8(m) | I32Store16(m) | I64Store8(m)
+        | I64Store16(m) | I64Store32(m) => memarg(m, natural_alignment(instruction.name())),
+    }
+}
+
+/// The immediates of a memory access, which leave out a memory index, offset or alignment that is the default
+fn memarg(m: &MemArg, natural: u32) -> String {
+    let mut text = String::new();
+    if m.memidx != 0 {
+        write!(text, " {}", m.memidx).unwrap();
+    }
+    if m.offset != 0 {
+        write!(text, " offset={}", m.offset).unwrap();
+    }
+    if m.align != natural {
+        // Alignments that don't fit in a u64 are invalid anyway, so they are printed as the exponent
+        match 1u64.checked_shl(m.align) {
+            Some(align) => write!(text, " align={}", align).unwrap(),
+            None => write!(text, " align=2**{}", m.align).unwrap(),
+        }
+    }
+    text
+}
+
+/// The alignment exponent that the text format assumes when `align=` is left out, which is the width of the access
+///
+/// The width is in the name of the instruction, such as 1 byte for `i64.load8_s` and 8 bytes for `v128.load16x4_s`,
+/// or else it is the width of the type, such as 4 bytes for `f32.store`.
+fn natural_alignment(name: &str) -> u32 {
+    let (t, op) = name.split_once('.').unwrap_or((name, ""));
+    let suffix = op.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    let bits: String = suffix.chars().take_while(char::is_ascii_digit).collect();
+    let bytes = match bits.parse::<u32>() {
+        Ok(_) if suffix[bits.len()..].starts_with('x') => 8,
+        Ok(bits) => bits / 8,
+        Err(_) => match t {
+            "i32" | "f32" => 4,
+            "i64" | "f64" => 8,
+            _ => 16,
+        },
+    };
+    bytes.trailing_zeros()
+}
+
+fn vector(instruction: &VectorInstruction) -> String {
+    use VectorInstruction::*;
+
+    let mut text = String::new();
+    if let Some(m) = instruction.memarg() {
+        text += &memarg(m, natural_alignment(instruction.name()));
+    }
+    match instruction {
+        V128Const(v) => {
+            text += " i32x4";
+            for lane in 0..4 {
+                write!(text, " 0x{:08x}", (v >> (32 * lane)) as u32).unwrap();
+            }
+        }
+        I8x16Shuffle(lanes) => {
+            for lane in lanes {
+                write!(text, " {}", lane).unwrap();
+            }
+        }
+        I8x16ExtractLaneS(lane)
+        | I8x16ExtractLaneU(lane)
+        | I8x16ReplaceLane(lane)
+        | I16x8ExtractLaneS(lane)
+        | I16x8ExtractLaneU(lane)
+        | I16x8ReplaceLane(lane)
+        | I32x4ExtractLane(lane)
+        | I32x4ReplaceLane(lane)
+        | I64x2ExtractLane(lane)
+        | I64x2ReplaceLane(lane)
+        | F32x4ExtractLane(lane)
+        | F32x4ReplaceLane(lane)
+        | F64x2ExtractLane(lane)
+        | F64x2ReplaceLane(lane)
+        | V128Load8Lane(_, lane)
+        | V128Load16Lane(_, lane)
+        | V128Load32Lane(_, lane)
+        | V128Load64Lane(_, lane)
+        | V128Store8Lane(_, lane)
+        | V128Store16Lane(_, lane)
+        | V128Store32Lane(_, lane)
+        | V128Store64Lane(_, lane) => write!(text, " {}", lane).unwrap(),
+        _ => (),
+    }
+    text
+}
+
+fn gc(instruction: &GcInstruction) -> String {
+    use GcInstruction::*;
+
+    match instruction {
+        StructNew(typeidx)
+        | StructNewDefault(typeidx)
+        | ArrayNew(typeidx)
+        | ArrayNewDefault(typeidx)
+        | ArrayGet(typeidx)
+        | ArrayGetS(typeidx)
+        | ArrayGetU(typeidx)
+        | ArraySet(typeidx)
+        | ArrayFill(typeidx) => format!(" {}", typeidx),
+        StructGet(x, y)
+        | StructGetS(x, y)
+        | StructGetU(x, y)
+        | StructSet(x, y)
+        | ArrayNewFixed(x, y)
+        | ArrayNewData(x, y)
+        | ArrayNewElem(x, y)
+        | ArrayCopy(x, y)
+        | ArrayInitData(x, y)
+        | ArrayInitElem(x, y) => format!(" {} {}", x, y),
+        RefTest(rt) | RefCast(rt) => format!(" {}", rt),
+        BrOnCast(labelidx, rt1, rt2) | BrOnCastFail(labelidx, rt1, rt2) => {
+            format!(" {} {} {}", labelidx, rt1, rt2)
+        }
+        ArrayLen | AnyConvertExtern | ExternConvertAny | RefI31 | I31GetS | I31GetU => {
+            String::new()
+        }
+    }
+}
@@ -0,0 +1,2267 @@
+use std::collections::HashSet;
+
+use crate::{
+    instructions::{
+        AtomicInstruction, AtomicOp, BlockType, CatchClause, ControlInstruction, GcInstruction,
+        Instruction, MemArg, MemoryInstruction, NumericInstruction, ParametricInstruction,
+        ReferenceInstruction, SaturatingTruncationInstruction, TableInstruction,
+        VariableInstruction, VectorInstruction,
+    },
+    module::{
+        element::Element,
+        export::ExportDescriptor,
+        import::ImportDescriptor,
+        indices::{
+            DataIdx, ElemIdx, FieldIdx, FuncIdx, LaneIdx, MemIdx, TableIdx, TagIdx, TypeIdx,
+        },
+        types::{
+            ArrayType, CompType, DefType, FieldType, FuncType, GlobalType, HeapType, MemType,
+            Mutability, NumType, RefType, StorageType, StructType, TableType, TagType, ValType,
+            VecType,
+        },
+    },
+    subtyping::{matches, matches_all, matches_comp_type, matches_ref_type, top},
+    Module,
+};
+
+const I32: ValType = ValType::NumType(NumType::I32);
+const I64: ValType = ValType::NumType(NumType::I64);
+const F32: ValType = ValType::NumType(NumType::F32);
+const F64: ValType = ValType::NumType(NumType::F64);
+const V128: ValType = ValType::VecType(VecType::V128);
+const EXNREF: ValType = ValType::RefType(RefType::EXNREF);
+
+/// Everything defined or imported by a module that the instructions of a function body can refer to
+pub struct Context<'a> {
+    pub types: &'a [DefType],
+    /// The type index of each function, which `ref.func` needs for the precise type of the reference it produces
+    pub funcs: Vec<TypeIdx>,
+    pub tables: Vec<&'a TableType>,
+    pub mems: Vec<&'a MemType>,
+    pub globals: Vec<&'a GlobalType>,
+    pub tags: Vec<&'a FuncType>,
+    /// The number of data segments given by the data count section, which instructions that name segments require
+    pub datas: Option<u32>,
+    /// The reference type of each element segment, which the table and array instructions that read segments need
+    pub elems: Vec<RefType>,
+    /// The functions that are declared outside of function bodies and so can be referenced with `ref.func`
+    pub refs: HashSet<FuncIdx>,
+}
+
+impl<'a> Context<'a> {
+    /// Build the context of a module, with imports preceding the module's own definitions in each index space
+    pub fn new(module: &'a Module) -> Result<Self, &'static str> {
+        let mut context = Self {
+            types: &module.types,
+            funcs: Vec::new(),
+            tables: Vec::new(),
+            mems: Vec::new(),
+            globals: Vec::new(),
+            tags: Vec::new(),
+            datas: module.data_count,
+            elems: Vec::new(),
+            refs: HashSet::new(),
+        };
+        for (x, ty) in context.types.iter().enumerate() {
+            context.def_type(x as TypeIdx, ty)?;
+        }
+        for import in &module.imports {
+            match &import.descriptor {
+                ImportDescriptor::Func(typeidx) => {
+                    context.func_type(*typeidx)?;
+                    context.funcs.push(*typeidx);
+                }
+                ImportDescriptor::Table(tt) => context.tables.push(tt),
+                ImportDescriptor::Mem(mt) => context.mems.push(mt),
+                ImportDescriptor::Global(gt) => context.globals.push(gt),
+                ImportDescriptor::Tag(tt) => context.tags.push(context.tag_type(tt)?),
+            }
+        }
+        for typeidx in &module.functions {
+            context.func_type(*typeidx)?;
+            context.funcs.push(*typeidx);
+        }
+        context
+            .tables
+            .extend(module.tables.iter().map(|table| &table.tt));
+        context
+            .mems
+            .extend(module.memories.iter().map(|memory| &memory.mt));
+        for tt in &context.tables {
+            context.ref_type(&tt.et)?;
+        }
+        for mt in &context.mems {
+            memory_type(mt)?;
+        }
+        context
+            .globals
+            .extend(module.globals.iter().map(|global| &global.gt));
+        for gt in &context.globals {
+            context.val_type(&gt.t)?;
+        }
+        for tag in &module.tags {
+            context.tags.push(context.tag_type(&tag.tt)?);
+        }
+
+        // Code can only use ref.func on functions that are referenced elsewhere in the module
+        for element in &module.elements {
+            let t = match element {
+                Element::PassiveExpression(t, _)
+                | Element::ActiveExplicitExpression(_, _, t, _)
+                | Element::DeclarativeExpression(t, _) => {
+                    context.ref_type(t)?;
+                    t.clone()
+                }
+                Element::ActiveExpression(_, _) => RefType::FUNCREF,
+                _ => RefType::non_null(HeapType::Func),
+            };
+            context.elems.push(t);
+            match element {
+                Element::ActiveIndex(_, funcs)
+                | Element::PassiveIndex(_, funcs)
+                | Element::ActiveExplicitIndex(_, _, _, funcs)
+                | Element::DeclarativeIndex(_, funcs) => context.refs.extend(funcs),
+                Element::ActiveExpression(_, exprs)
+                | Element::PassiveExpression(_, exprs)
+                | Element::ActiveExplicitExpression(_, _, _, exprs)
+                | Element::DeclarativeExpression(_, exprs) => context
+                    .refs
+                    .extend(exprs.iter().flat_map(|expr| expr.func_refs())),
+            }
+        }
+        context.refs.extend(
+            module
+                .globals
+                .iter()
+                .flat_map(|global| global.init.func_refs()),
+        );
+        context.refs.extend(
+            module
+                .tables
+                .iter()
+                .filter_map(|table| table.init.as_ref())
+                .flat_map(|init| init.func_refs()),
+        );
+        context.refs.extend(
+            module
+                .exports
+                .iter()
+                .filter_map(|export| match export.descriptor {
+                    ExportDescriptor::Func(funcidx) => Some(funcidx),
+                    _ => None,
+                }),
+        );
+
+        Ok(context)
+    }
+
+    /// Look up a defined type in the type section
+    fn def_type_at(&self, typeidx: TypeIdx) -> Result<&'a DefType, &'static str> {
+        self.types.get(typeidx as usize).ok_or("unknown type")
+    }
+
+    /// Look up a function type in the type section
+    pub fn func_type(&self, typeidx: TypeIdx) -> Result<&'a FuncType, &'static str> {
+        self.def_type_at(typeidx)?
+            .func_type()
+            .ok_or("non-function type")
+    }
+
+    fn struct_type(&self, typeidx: TypeIdx) -> Result<&'a StructType, &'static str> {
+        self.def_type_at(typeidx)?
+            .struct_type()
+            .ok_or("non-structure type")
+    }
+
+    fn array_type(&self, typeidx: TypeIdx) -> Result<&'a ArrayType, &'static str> {
+        self.def_type_at(typeidx)?
+            .array_type()
+            .ok_or("non-array type")
+    }
+
+    /// Check a type of the type section, which can refer to any type up to the end of its recursion group and
+    /// must match the one supertype it may declare
+    fn def_type(&self, x: TypeIdx, ty: &DefType) -> Result<(), &'static str> {
+        let known = |t: &ValType| match t {
+            ValType::RefType(RefType {
+                ht: HeapType::Type(typeidx),
+                ..
+            }) if *typeidx >= ty.group.end => Err("unknown type"),
+            _ => Ok(()),
+        };
+        let field = |ft: &FieldType| match &ft.st {
+            StorageType::Val(t) => known(t),
+            StorageType::Packed(_) => Ok(()),
+        };
+        match &ty.st.ct {
+            CompType::Func(ft) => ft.rt1.iter().chain(&ft.rt2).try_for_each(known)?,
+            CompType::Struct(st) => st.fields.iter().try_for_each(field)?,
+            CompType::Array(at) => field(&at.ft)?,
+        }
+        match ty.st.supertypes.as_slice() {
+            [] => Ok(()),
+            [supertype] => {
+                if *supertype >= x {
+                    return Err("unknown type");
+                }
+                let sup = &self.types[*supertype as usize].st;
+                if sup.is_final || !matches_comp_type(self.types, &ty.st.ct, &sup.ct) {
+                    return Err("sub type does not match super type");
+                }
+                Ok(())
+            }
+            _ => Err("multiple supertypes"),
+        }
+    }
+
+    /// Check that a value type only refers to types that exist
+    fn val_type(&self, t: &ValType) -> Result<(), &'static str> {
+        match t {
+            ValType::RefType(rt) => self.ref_type(rt),
+            _ => Ok(()),
+        }
+    }
+
+    fn ref_type(&self, rt: &RefType) -> Result<(), &'static str> {
+        self.heap_type(&rt.ht)
+    }
+
+    fn heap_type(&self, ht: &HeapType) -> Result<(), &'static str> {
+        match ht {
+            HeapType::Type(typeidx) => self.def_type_at(*typeidx).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Look up the function type of a tag, which must not have results because nothing returns to a throw
+    fn tag_type(&self, tt: &TagType) -> Result<&'a FuncType, &'static str> {
+        let ty = self.func_type(tt.typeidx)?;
+        if !ty.rt2.is_empty() {
+            return Err("non-empty tag result type");
+        }
+        Ok(ty)
+    }
+
+    /// The parameter and result types of a block
+    fn block_type(&self, bt: &BlockType) -> Result<(Vec<ValType>, Vec<ValType>), &'static str> {
+        match bt {
+            BlockType::Empty => Ok((vec![], vec![])),
+            BlockType::ValType(t) => {
+                self.val_type(t)?;
+                Ok((vec![], vec![t.clone()]))
+            }
+            BlockType::TypeIdx(typeidx) => {
+                let ty = self.func_type(*typeidx)?;
+                Ok((ty.rt1.clone(), ty.rt2.clone()))
+            }
+        }
+    }
+}
+
+/// Check that a function body is valid for the given function type and locals
+pub fn validate_func(
+    context: &Context,
+    ty: &FuncType,
+    locals: Vec<ValType>,
+    body: &[Instruction],
+) -> Result<(), &'static str> {
+    for t in &locals {
+        context.val_type(t)?;
+    }
+    // Parameters start out initialized, and so do declared locals unless they have no default value to start with
+    let inits = ty
+        .rt1
+        .iter()
+        .map(|_| true)
+        .chain(locals.iter().map(ValType::is_defaultable))
+        .collect();
+    let mut validator = Validator {
+        context,
+        locals: ty.rt1.iter().cloned().chain(locals).collect(),
+        inits,
+        set_locals: Vec::new(),
+        return_types: &ty.rt2,
+        vals: Vec::new(),
+        ctrls: Vec::new(),
+    };
+    validator.block(Kind::Block, &[], &ty.rt2, body)
+}
+
+/// The kind of structured instruction that opened a control frame
+#[derive(PartialEq)]
+enum Kind {
+    Block,
+    Loop,
+    If,
+}
+
+/// A control frame of the validation algorithm in the spec's appendix
+struct Frame {
+    kind: Kind,
+    start_types: Vec<ValType>,
+    end_types: Vec<ValType>,
+    height: usize,
+    /// The number of locals that had been set when the block was entered, which are all that remain set after it
+    init_height: usize,
+    unreachable: bool,
+}
+
+impl Frame {
+    /// The types a branch to this frame must provide
+    fn label_types(&self) -> &[ValType] {
+        if self.kind == Kind::Loop {
+            &self.start_types
+        } else {
+            &self.end_types
+        }
+    }
+}
+
+/// Type-checks a function body with an operand stack of types and a stack of control frames
+///
+/// An operand of `None` has an unknown type, which only occurs in unreachable code.
+///
+/// Locals without a default value must be set before they are read, which is tracked per block: `inits` says which
+/// locals are currently initialized, and `set_locals` records the locals that became initialized in the enclosing
+/// blocks so that they can be uninitialized again when those blocks end.
+struct Validator<'a> {
+    context: &'a Context<'a>,
+    locals: Vec<ValType>,
+    inits: Vec<bool>,
+    set_locals: Vec<u32>,
+    return_types: &'a [ValType],
+    vals: Vec<Option<ValType>>,
+    ctrls: Vec<Frame>,
+}
+
+impl<'a> Validator<'a> {
+    fn push(&mut self, t: ValType) {
+        self.vals.push(Some(t));
+    }
+
+    fn push_vals(&mut self, types: &[ValType]) {
+        self.vals.extend(types.iter().cloned().map(Some));
+    }
+
+    fn pop(&mut self) -> Result<Option<ValType>, &'static str> {
+        let frame = self.ctrls.last().expect("there is always a frame");
+        if self.vals.len() == frame.height {
+            if frame.unreachable {
+                return Ok(None);
+            }
+            return Err("type mismatch");
+        }
+        Ok(self.vals.pop().flatten())
+    }
+
+    fn pop_expect(&mut self, expected: &ValType) -> Result<Option<ValType>, &'static str> {
+        match self.pop()? {
+            Some(actual) if !matches(self.context.types, &actual, expected) => Err("type mismatch"),
+            Some(actual) => Ok(Some(actual)),
+            None => Ok(Some(expected.clone())),
+        }
+    }
+
+    /// Pop a reference operand, whose type is only unknown in unreachable code
+    fn pop_ref(&mut self) -> Result<Option<RefType>, &'static str> {
+        match self.pop()? {
+            Some(ValType::RefType(rt)) => Ok(Some(rt)),
+            Some(_) => Err("type mismatch"),
+            None => Ok(None),
+        }
+    }
+
+    fn pop_vals(&mut self, types: &[ValType]) -> Result<(), &'static str> {
+        for t in types.iter().rev() {
+            self.pop_expect(t)?;
+        }
+        Ok(())
+    }
+
+    /// Pop the operands and push the results of an instruction with a fixed type
+    fn op(&mut self, params: &[ValType], results: &[ValType]) -> Result<(), &'static str> {
+        self.pop_vals(params)?;
+        self.push_vals(results);
+        Ok(())
+    }
+
+    /// Mark the rest of the current block as unreachable, which makes the operand stack polymorphic
+    fn unreachable(&mut self) {
+        let frame = self.ctrls.last_mut().expect("there is always a frame");
+        self.vals.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    fn label(&self, labelidx: u32) -> Result<&Frame, &'static str> {
+        self.ctrls
+            .len()
+            .checked_sub(labelidx as usize + 1)
+            .map(|i| &self.ctrls[i])
+            .ok_or("unknown label")
+    }
+
+    /// Validate the instructions of a block whose parameters have already been popped
+    fn block(
+        &mut self,
+        kind: Kind,
+        params: &[ValType],
+        results: &[ValType],
+        body: &[Instruction],
+    ) -> Result<(), &'static str> {
+        self.ctrls.push(Frame {
+            kind,
+            start_types: params.to_vec(),
+            end_types: results.to_vec(),
+            height: self.vals.len(),
+            init_height: self.set_locals.len(),
+            unreachable: false,
+        });
+        self.push_vals(params);
+        for instruction in body {
+            self.instruction(instruction)?;
+        }
+        self.pop_vals(results)?;
+        let frame = self.ctrls.pop().expect("the frame pushed above");
+        if self.vals.len() != frame.height {
+            return Err("type mismatch");
+        }
+        for localidx in self.set_locals.drain(frame.init_height..) {
+            self.inits[localidx as usize] = false;
+        }
+        Ok(())
+    }
+
+    fn instruction(&mut self, instruction: &Instruction) -> Result<(), &'static str> {
+        match instruction {
+            Instruction::Control(instruction) => self.control(instruction),
+            Instruction::Reference(instruction) => self.reference(instruction),
+            Instruction::Parametric(instruction) => self.parametric(instruction),
+            Instruction::Variable(instruction) => self.variable(instruction),
+            Instruction::Table(instruction) => self.table(instruction),
+            Instruction::Memory(instruction) => self.memory(instruction),
+            Instruction::Numeric(instruction) => {
+                let (params, results) = numeric(instruction);
+                self.op(&params, &results)
+            }
+            Instruction::Saturating(instruction) => {
+                let (params, results) = saturating(instruction);
+                self.op(&params, &results)
+            }
+            Instruction::Vector(instruction) => self.vector(instruction),
+            Instruction::Atomic(instruction) => self.atomic(instruction),
+            Instruction::Gc(instruction) => self.gc(instruction),
+        }
+    }
+
+    fn control(&mut self, instruction: &ControlInstruction) -> Result<(), &'static str> {
+        match instruction {
+            ControlInstruction::Unreachable => self.unreachable(),
+            ControlInstruction::Nop => (),
+            ControlInstruction::Block(bt, body) | ControlInstruction::Loop(bt, body) => {
+                let (params, results) = self.context.block_type(bt)?;
+                self.pop_vals(&params)?;
+                let kind = match instruction {
+                    ControlInstruction::Loop(_, _) => Kind::Loop,
+                    _ => Kind::Block,
+                };
+                self.block(kind, &params, &results, body)?;
+                self.push_vals(&results);
+            }
+            ControlInstruction::If(bt, then, otherwise) => {
+                let (params, results) = self.context.block_type(bt)?;
+                self.pop_expect(&I32)?;
+                self.pop_vals(&params)?;
+                // Without an else branch the parameters are passed straight through when the condition is false
+                if otherwise.is_empty() && !matches_all(self.context.types, &params, &results) {
+                    return Err("type mismatch");
+                }
+                self.block(Kind::If, &params, &results, then)?;
+                self.block(Kind::If, &params, &results, otherwise)?;
+                self.push_vals(&results);
+            }
+            ControlInstruction::TryTable(bt, catches, body) => {
+                // Handlers branch from outside the try_table, so their labels don't include its own
+                for catch in catches {
+                    let (labelidx, types) = match catch {
+                        CatchClause::Catch(tagidx, labelidx) => {
+                            (labelidx, self.tag(*tagidx)?.rt1.clone())
+                        }
+                        CatchClause::CatchRef(tagidx, labelidx) => {
+                            let mut types = self.tag(*tagidx)?.rt1.clone();
+                            types.push(EXNREF);
+                            (labelidx, types)
+                        }
+                        CatchClause::CatchAll(labelidx) => (labelidx, vec![]),
+                        CatchClause::CatchAllRef(labelidx) => (labelidx, vec![EXNREF]),
+                    };
+                    let label_types = self.label(*labelidx)?.label_types();
+                    if !matches_all(self.context.types, &types, label_types) {
+                        return Err("type mismatch");
+                    }
+                }
+                let (params, results) = self.context.block_type(bt)?;
+                self.pop_vals(&params)?;
+                self.block(Kind::Block, &params, &results, body)?;
+                self.push_vals(&results);
+            }
+            ControlInstruction::Throw(tagidx) => {
+                let ty = self.tag(*tagidx)?;
+                self.pop_vals(&ty.rt1)?;
+                self.unreachable();
+            }
+            ControlInstruction::ThrowRef => {
+                self.pop_expect(&EXNREF)?;
+                self.unreachable();
+            }
+            ControlInstruction::Br(labelidx) => {
+                let types = self.label(*labelidx)?.label_types().to_vec();
+                self.pop_vals(&types)?;
+                self.unreachable();
+            }
+            ControlInstruction::BrIf(labelidx) => {
+                self.pop_expect(&I32)?;
+                let types = self.label(*labelidx)?.label_types().to_vec();
+                self.op(&types, &types)?;
+            }
+            ControlInstruction::BrTable(labels, default) => {
+                self.pop_expect(&I32)?;
+                let arity = self.label(*default)?.label_types().len();
+                for labelidx in labels.iter().chain([default]) {
+                    let types = self.label(*labelidx)?.label_types().to_vec();
+                    if types.len() != arity {
+                        return Err("type mismatch");
+                    }
+                    self.op(&types, &types)?;
+                }
+                let types = self.label(*default)?.label_types().to_vec();
+                self.pop_vals(&types)?;
+                self.unreachable();
+            }
+            ControlInstruction::BrOnNull(labelidx) => {
+                let rt = self.pop_ref()?;
+                let types = self.label(*labelidx)?.label_types().to_vec();
+                self.op(&types, &types)?;
+                // The reference is only left on the stack when it isn't null
+                self.vals
+                    .push(rt.map(|rt| ValType::RefType(RefType::non_null(rt.ht))));
+            }
+            ControlInstruction::BrOnNonNull(labelidx) => {
+                let rt = self.pop_ref()?;
+                let mut types = self.label(*labelidx)?.label_types().to_vec();
+                // The label receives the reference once it is known not to be null
+                let t = types.pop().ok_or("type mismatch")?;
+                let accepted = match rt {
+                    Some(rt) => matches(
+                        self.context.types,
+                        &ValType::RefType(RefType::non_null(rt.ht)),
+                        &t,
+                    ),
+                    None => matches!(t, ValType::RefType(_)),
+                };
+                if !accepted {
+                    return Err("type mismatch");
+                }
+                self.op(&types, &types)?;
+            }
+            ControlInstruction::Return => {
+                self.pop_vals(self.return_types)?;
+                self.unreachable();
+            }
+            ControlInstruction::Call(funcidx) => {
+                let ty = self.func(*funcidx)?;
+                self.op(&ty.rt1, &ty.rt2)?;
+            }
+            ControlInstruction::CallIndirect(typeidx, tableidx) => {
+                let ty = self.indirect(*typeidx, *tableidx)?;
+                self.op(&ty.rt1, &ty.rt2)?;
+            }
+            ControlInstruction::CallRef(typeidx) => {
+                let ty = self.call_ref(*typeidx)?;
+                self.op(&ty.rt1, &ty.rt2)?;
+            }
+            ControlInstruction::ReturnCall(funcidx) => {
+                let ty = self.func(*funcidx)?;
+                self.tail_call(ty)?;
+            }
+            ControlInstruction::ReturnCallIndirect(typeidx, tableidx) => {
+                let ty = self.indirect(*typeidx, *tableidx)?;
+                self.tail_call(ty)?;
+            }
+            ControlInstruction::ReturnCallRef(typeidx) => {
+                let ty = self.call_ref(*typeidx)?;
+                self.tail_call(ty)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn tag(&self, tagidx: TagIdx) -> Result<&'a FuncType, &'static str> {
+        self.context
+            .tags
+            .get(tagidx as usize)
+            .copied()
+            .ok_or("unknown tag")
+    }
+
+    fn func(&self, funcidx: FuncIdx) -> Result<&'a FuncType, &'static str> {
+        let typeidx = self.func_typeidx(funcidx)?;
+        self.context.func_type(typeidx)
+    }
+
+    fn func_typeidx(&self, funcidx: FuncIdx) -> Result<TypeIdx, &'static str> {
+        self.context
+            .funcs
+            .get(funcidx as usize)
+            .copied()
+            .ok_or("unknown function")
+    }
+
+    /// Check the table of an indirect call and pop the index into it, returning the expected function type
+    fn indirect(
+        &mut self,
+        typeidx: TypeIdx,
+        tableidx: TableIdx,
+    ) -> Result<&'a FuncType, &'static str> {
+        let table = self.table_type(tableidx)?;
+        if !matches_ref_type(self.context.types, &table.et, &RefType::FUNCREF) {
+            return Err("type mismatch");
+        }
+        let ty = self.context.func_type(typeidx)?;
+        self.pop_expect(&I32)?;
+        Ok(ty)
+    }
+
+    /// Pop the function reference of `call_ref` or `return_call_ref`, returning the function type it refers to
+    fn call_ref(&mut self, typeidx: TypeIdx) -> Result<&'a FuncType, &'static str> {
+        let ty = self.context.func_type(typeidx)?;
+        self.pop_expect(&ValType::RefType(RefType::null(HeapType::Type(typeidx))))?;
+        Ok(ty)
+    }
+
+    /// A tail call returns the callee's results from the caller, so they must be the caller's results
+    fn tail_call(&mut self, ty: &FuncType) -> Result<(), &'static str> {
+        if !matches_all(self.context.types, &ty.rt2, self.return_types) {
+            return Err("type mismatch");
+        }
+        self.pop_vals(&ty.rt1)?;
+        self.unreachable();
+        Ok(())
+    }
+
+    fn reference(&mut self, instruction: &ReferenceInstruction) -> Result<(), &'static str> {
+        match instruction {
+            ReferenceInstruction::Null(ht) => {
+                self.context.heap_type(ht)?;
+                self.push(ValType::RefType(RefType::null(ht.clone())));
+            }
+            ReferenceInstruction::IsNull => {
+                self.pop_ref()?;
+                self.push(I32);
+            }
+            ReferenceInstruction::Func(funcidx) => {
+                let typeidx = self.func_typeidx(*funcidx)?;
+                if !self.context.refs.contains(funcidx) {
+                    return Err("undeclared function reference");
+                }
+                self.push(ValType::RefType(RefType::non_null(HeapType::Type(typeidx))));
+            }
+            ReferenceInstruction::Eq => {
+                let eqref = ValType::RefType(RefType::EQREF);
+                self.op(&[eqref.clone(), eqref], &[I32])?;
+            }
+            ReferenceInstruction::AsNonNull => {
+                let rt = self.pop_ref()?;
+                self.vals
+                    .push(rt.map(|rt| ValType::RefType(RefType::non_null(rt.ht))));
+            }
+        }
+        Ok(())
+    }
+
+    fn gc(&mut self, instruction: &GcInstruction) -> Result<(), &'static str> {
+        use GcInstruction::*;
+
+        let types = self.context.types;
+        let reference =
+            |typeidx: TypeIdx| ValType::RefType(RefType::non_null(HeapType::Type(typeidx)));
+        let nullable = |typeidx: TypeIdx| ValType::RefType(RefType::null(HeapType::Type(typeidx)));
+        match instruction {
+            StructNew(x) => {
+                let fields: Vec<ValType> = self
+                    .context
+                    .struct_type(*x)?
+                    .fields
+                    .iter()
+                    .map(|ft| ft.st.unpacked())
+                    .collect();
+                self.op(&fields, &[reference(*x)])
+            }
+            StructNewDefault(x) => {
+                let st = self.context.struct_type(*x)?;
+                if !st.fields.iter().all(|ft| ft.st.unpacked().is_defaultable()) {
+                    return Err("field type is not defaultable");
+                }
+                self.op(&[], &[reference(*x)])
+            }
+            StructGet(x, y) | StructGetS(x, y) | StructGetU(x, y) => {
+                let ft = self.field(*x, *y)?;
+                let t = unpacked(ft, matches!(instruction, StructGet(_, _)))?;
+                self.op(&[nullable(*x)], &[t])
+            }
+            StructSet(x, y) => {
+                let ft = self.field(*x, *y)?;
+                if ft.m != Mutability::Var {
+                    return Err("field is immutable");
+                }
+                self.op(&[nullable(*x), ft.st.unpacked()], &[])
+            }
+            ArrayNew(x) => {
+                let t = self.context.array_type(*x)?.ft.st.unpacked();
+                self.op(&[t, I32], &[reference(*x)])
+            }
+            ArrayNewDefault(x) => {
+                if !self
+                    .context
+                    .array_type(*x)?
+                    .ft
+                    .st
+                    .unpacked()
+                    .is_defaultable()
+                {
+                    return Err("field type is not defaultable");
+                }
+                self.op(&[I32], &[reference(*x)])
+            }
+            ArrayNewFixed(x, n) => {
+                let t = self.context.array_type(*x)?.ft.st.unpacked();
+                // Pop one operand at a time rather than building a list of n types, which could be huge
+                for _ in 0..*n {
+                    self.pop_expect(&t)?;
+                }
+                self.push(reference(*x));
+                Ok(())
+            }
+            ArrayNewData(x, y) => {
+                numeric_or_vector(&self.context.array_type(*x)?.ft)?;
+                self.data(*y)?;
+                self.op(&[I32, I32], &[reference(*x)])
+            }
+            ArrayNewElem(x, y) => {
+                let ft = &self.context.array_type(*x)?.ft;
+                self.elem(*y, ft)?;
+                self.op(&[I32, I32], &[reference(*x)])
+            }
+            ArrayGet(x) | ArrayGetS(x) | ArrayGetU(x) => {
+                let ft = &self.context.array_type(*x)?.ft;
+                let t = unpacked(ft, matches!(instruction, ArrayGet(_)))?;
+                self.op(&[nullable(*x), I32], &[t])
+            }
+            ArraySet(x) => {
+                let t = self.mutable_array(*x)?.st.unpacked();
+                self.op(&[nullable(*x), I32, t], &[])
+            }
+            ArrayLen => self.op(&[ValType::RefType(RefType::ARRAYREF)], &[I32]),
+            ArrayFill(x) => {
+                let t = self.mutable_array(*x)?.st.unpacked();
+                self.op(&[nullable(*x), I32, t, I32], &[])
+            }
+            ArrayCopy(x, y) => {
+                let dst = self.mutable_array(*x)?;
+                let src = &self.context.array_type(*y)?.ft;
+                let storage_matches = match (&src.st, &dst.st) {
+                    (StorageType::Val(t1), StorageType::Val(t2)) => matches(types, t1, t2),
+                    (st1, st2) => st1 == st2,
+                };
+                if !storage_matches {
+                    return Err("array types do not match");
+                }
+                self.op(&[nullable(*x), I32, nullable(*y), I32, I32], &[])
+            }
+            ArrayInitData(x, y) => {
+                numeric_or_vector(self.mutable_array(*x)?)?;
+                self.data(*y)?;
+                self.op(&[nullable(*x), I32, I32, I32], &[])
+            }
+            ArrayInitElem(x, y) => {
+                let ft = self.mutable_array(*x)?;
+                self.elem(*y, ft)?;
+                self.op(&[nullable(*x), I32, I32, I32], &[])
+            }
+            RefTest(rt) => {
+                self.cast(rt)?;
+                self.push(I32);
+                Ok(())
+            }
+            RefCast(rt) => {
+                self.cast(rt)?;
+                self.push(ValType::RefType(rt.clone()));
+                Ok(())
+            }
+            BrOnCast(labelidx, rt1, rt2) | BrOnCastFail(labelidx, rt1, rt2) => {
+                self.context.ref_type(rt1)?;
+                self.context.ref_type(rt2)?;
+                if !matches_ref_type(types, rt2, rt1) {
+                    return Err("type mismatch");
+                }
+                // What remains of the first type after the cast fails, which is only non-null if a null would pass
+                let diff = RefType {
+                    nullable: rt1.nullable && !rt2.nullable,
+                    ht: rt1.ht.clone(),
+                };
+                let (taken, kept) = match instruction {
+                    BrOnCast(_, _, _) => (rt2.clone(), diff),
+                    _ => (diff, rt2.clone()),
+                };
+                let mut label_types = self.label(*labelidx)?.label_types().to_vec();
+                let t = label_types.pop().ok_or("type mismatch")?;
+                if !matches(types, &ValType::RefType(taken), &t) {
+                    return Err("type mismatch");
+                }
+                self.pop_expect(&ValType::RefType(rt1.clone()))?;
+                self.op(&label_types, &label_types)?;
+                self.push(ValType::RefType(kept));
+                Ok(())
+            }
+            AnyConvertExtern | ExternConvertAny => {
+                let (from, to) = match instruction {
+                    AnyConvertExtern => (HeapType::Extern, HeapType::Any),
+                    _ => (HeapType::Any, HeapType::Extern),
+                };
+                let rt = self.pop_expect(&ValType::RefType(RefType::null(from)))?;
+                // The conversion keeps the nullability of its operand
+                let nullable = matches!(rt, Some(ValType::RefType(RefType { nullable: true, .. })));
+                self.push(ValType::RefType(RefType { nullable, ht: to }));
+                Ok(())
+            }
+            RefI31 => self.op(
+                &[I32],
+                &[ValType::RefType(RefType::non_null(HeapType::I31))],
+            ),
+            I31GetS | I31GetU => self.op(&[ValType::RefType(RefType::I31REF)], &[I32]),
+        }
+    }
+
+    fn field(&self, typeidx: TypeIdx, fieldidx: FieldIdx) -> Result<&'a FieldType, &'static str> {
+        self.context
+            .struct_type(typeidx)?
+            .fields
+            .get(fieldidx as usize)
+            .ok_or("unknown field")
+    }
+
+    /// Look up the element type of an array that instructions write to
+    fn mutable_array(&self, typeidx: TypeIdx) -> Result<&'a FieldType, &'static str> {
+        let ft = &self.context.array_type(typeidx)?.ft;
+        if ft.m != Mutability::Var {
+            return Err("array is immutable");
+        }
+        Ok(ft)
+    }
+
+    /// Check that the references of an element segment can be stored in an array with the given element type
+    fn elem(&self, elemidx: ElemIdx, ft: &FieldType) -> Result<(), &'static str> {
+        let rt = self
+            .context
+            .elems
+            .get(elemidx as usize)
+            .ok_or("unknown elem segment")?;
+        if !matches(
+            self.context.types,
+            &ValType::RefType(rt.clone()),
+            &ft.st.unpacked(),
+        ) {
+            return Err("type mismatch");
+        }
+        Ok(())
+    }
+
+    /// Pop the operand of a cast, which must be in the same hierarchy as the type it is cast to
+    fn cast(&mut self, rt: &RefType) -> Result<(), &'static str> {
+        self.context.ref_type(rt)?;
+        let top = RefType::null(top(self.context.types, &rt.ht));
+        self.pop_expect(&ValType::RefType(top))?;
+        Ok(())
+    }
+
+    fn parametric(&mut self, instruction: &ParametricInstruction) -> Result<(), &'static str> {
+        match instruction {
+            ParametricInstruction::Drop => {
+                self.pop()?;
+            }
+            ParametricInstruction::Select => {
+                self.pop_expect(&I32)?;
+                let t1 = self.pop()?;
+                let t2 = self.pop()?;
+                // Without a type annotation select only works on numeric and vector operands
+                if matches!(t1, Some(ValType::RefType(_)))
+                    || matches!(t2, Some(ValType::RefType(_)))
+                {
+                    return Err("type mismatch");
+                }
+                let t = match (t1, t2) {
+                    (None, t) | (t, None) => t,
+                    (Some(t1), Some(t2)) if t1 == t2 => Some(t1),
+                    _ => return Err("type mismatch"),
+                };
+                self.vals.push(t);
+            }
+            ParametricInstruction::SelectTyped(types) => {
+                let [t] = types.as_slice() else {
+                    return Err("invalid result arity");
+                };
+                self.context.val_type(t)?;
+                self.pop_expect(&I32)?;
+                self.pop_expect(t)?;
+                self.pop_expect(t)?;
+                self.push(t.clone());
+            }
+        }
+        Ok(())
+    }
+
+    fn variable(&mut self, instruction: &VariableInstruction) -> Result<(), &'static str> {
+        match instruction {
+            VariableInstruction::LocalGet(localidx)
+            | VariableInstruction::LocalSet(localidx)
+            | VariableInstruction::LocalTee(localidx) => {
+                let t = self
+                    .locals
+                    .get(*localidx as usize)
+                    .ok_or("unknown local")?
+                    .clone();
+                match instruction {
+                    VariableInstruction::LocalGet(_) => {
+                        if !self.inits[*localidx as usize] {
+                            return Err("uninitialized local");
+                        }
+                        self.push(t)
+                    }
+                    VariableInstruction::LocalSet(_) => {
+                        self.pop_expect(&t)?;
+                        self.set_local(*localidx);
+                    }
+                    _ => {
+                        self.pop_expect(&t)?;
+                        self.set_local(*localidx);
+                        self.push(t);
+                    }
+                }
+                Ok(())
+            }
+            VariableInstruction::GlobalGet(globalidx)
+            | VariableInstruction::GlobalSet(globalidx) => {
+                let global = *self
+                    .context
+                    .globals
+                    .get(*globalidx as usize)
+                    .ok_or("unknown global")?;
+                match instruction {
+                    VariableInstruction::GlobalGet(_) => self.push(global.t.clone()),
+                    _ => {
+                        if global.m != Mutability::Var {
+                            return Err("global is immutable");
+                        }
+                        self.pop_expect(&global.t)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Mark a local as initialized until the end of the current block
+    fn set_local(&mut self, localidx: u32) {
+        if !self.inits[localidx as usize] {
+            self.inits[localidx as usize] = true;
+            self.set_locals.push(localidx);
+        }
+    }
+
+    fn table(&mut self, instruction: &TableInstruction) -> Result<(), &'static str> {
+        use TableInstruction::*;
+
+        match instruction {
+            TableGet(x) => {
+                let t = ValType::RefType(self.table_type(*x)?.et.clone());
+                self.op(&[I32], &[t])
+            }
+            TableSet(x) => {
+                let t = ValType::RefType(self.table_type(*x)?.et.clone());
+                self.op(&[I32, t], &[])
+            }
+            TableInit(y, x) => {
+                let et = &self.table_type(*x)?.et;
+                let rt = self
+                    .context
+                    .elems
+                    .get(*y as usize)
+                    .ok_or("unknown elem segment")?;
+                if !matches_ref_type(self.context.types, rt, et) {
+                    return Err("type mismatch");
+                }
+                self.op(&[I32, I32, I32], &[])
+            }
+            ElemDrop(y) => {
+                if *y as usize >= self.context.elems.len() {
+                    return Err("unknown elem segment");
+                }
+                Ok(())
+            }
+            TableCopy(x, y) => {
+                let (et_x, et_y) = (&self.table_type(*x)?.et, &self.table_type(*y)?.et);
+                if !matches_ref_type(self.context.types, et_y, et_x) {
+                    return Err("type mismatch");
+                }
+                self.op(&[I32, I32, I32], &[])
+            }
+            TableGrow(x) => {
+                let t = ValType::RefType(self.table_type(*x)?.et.clone());
+                self.op(&[t, I32], &[I32])
+            }
+            TableSize(x) => {
+                self.table_type(*x)?;
+                self.op(&[], &[I32])
+            }
+            TableFill(x) => {
+                let t = ValType::RefType(self.table_type(*x)?.et.clone());
+                self.op(&[I32, t, I32], &[])
+            }
+        }
+    }
+
+    fn table_type(&self, tableidx: TableIdx) -> Result<&'a TableType, &'static str> {
+        self.context
+            .tables
+            .get(tableidx as usize)
+            .copied()
+            .ok_or("unknown table")
+    }
+
+    fn memory(&mut self, instruction: &MemoryInstruction) -> Result<(), &'static str> {
+        use MemoryInstruction::*;
+
+        match instruction {
+            I32Load(m) => self.memarg(m, 2, &[I32], &[I32]),
+            I64Load(m) => self.memarg(m, 3, &[I32], &[I64]),
+            F32Load(m) => self.memarg(m, 2, &[I32], &[F32]),
+            F64Load(m) => self.memarg(m, 3, &[I32], &[F64]),
+            I32Load8S(m) | I32Load8U(m) => self.memarg(m, 0, &[I32], &[I32]),
+            I32Load16S(m) | I32Load16U(m) => self.memarg(m, 1, &[I32], &[I32]),
+            I64Load8S(m) | I64Load8U(m) => self.memarg(m, 0, &[I32], &[I64]),
+            I64Load16S(m) | I64Load16U(m) => self.memarg(m, 1, &[I32], &[I64]),
+            I64Load32S(m) | I64Load32U(m) => self.memarg(m, 2, &[I32], &[I64]),
+            I32Store(m) => self.memarg(m, 2, &[I32, I32], &[]),
+            I64Store(m) => self.memarg(m, 3, &[I32, I64], &[]),
+            F32Store(m) => self.memarg(m, 2, &[I32, F32], &[]),
+            F64Store(m) => self.memarg(m, 3, &[I32, F64], &[]),
+            I32Store8(m) => self.memarg(m, 0, &[I32, I32], &[]),
+            I32Store16(m) => self.memarg(m, 1, &[I32, I32], &[]),
+            I64Store8(m) => self.memarg(m, 0, &[I32, I64], &[]),
+            I64Store16(m) => self.memarg(m, 1, &[I32, I64], &[]),
+            I64Store32(m) => self.memarg(m, 2, &[I32, I64], &[]),
+            MemorySize(x) => {
+                let at = self.addr_type(*x)?;
+                self.op(&[], &[at])
+            }
+            MemoryGrow(x) => {
+                let at = [self.addr_type(*x)?];
+                self.op(&at, &at)
+            }
+            MemoryInit(dataidx, x) => {
+                let at = self.addr_type(*x)?;
+                self.data(*dataidx)?;
+                self.op(&[at, I32, I32], &[])
+            }
+            DataDrop(dataidx) => self.data(*dataidx),
+            MemoryCopy(x, y) => {
+                let (at_x, at_y) = (self.addr_type(*x)?, self.addr_type(*y)?);
+                // The length is only an i64 when both memories are 64-bit
+                let at_n = if at_x == I64 && at_y == I64 { I64 } else { I32 };
+                self.op(&[at_x, at_y, at_n], &[])
+            }
+            MemoryFill(x) => {
+                let at = self.addr_type(*x)?;
+                self.op(&[at.clone(), I32, at], &[])
+            }
+        }
+    }
+
+    fn vector(&mut self, instruction: &VectorInstruction) -> Result<(), &'static str> {
+        use VectorInstruction::*;
+
+        match instruction {
+            V128Load(m) => self.memarg(m, 4, &[I32], &[V128]),
+            V128Load8x8S(m) | V128Load8x8U(m) | V128Load16x4S(m) | V128Load16x4U(m)
+            | V128Load32x2S(m) | V128Load32x2U(m) | V128Load64Splat(m) | V128Load64Zero(m) => {
+                self.memarg(m, 3, &[I32], &[V128])
+            }
+            V128Load8Splat(m) => self.memarg(m, 0, &[I32], &[V128]),
+            V128Load16Splat(m) => self.memarg(m, 1, &[I32], &[V128]),
+            V128Load32Splat(m) | V128Load32Zero(m) => self.memarg(m, 2, &[I32], &[V128]),
+            V128Store(m) => self.memarg(m, 4, &[I32, V128], &[]),
+            V128Load8Lane(m, lane) => {
+                lane_index(*lane, 16)?;
+                self.memarg(m, 0, &[I32, V128], &[V128])
+            }
+            V128Load16Lane(m, lane) => {
+                lane_index(*lane, 8)?;
+                self.memarg(m, 1, &[I32, V128], &[V128])
+            }
+            V128Load32Lane(m, lane) => {
+                lane_index(*lane, 4)?;
+                self.memarg(m, 2, &[I32, V128], &[V128])
+            }
+            V128Load64Lane(m, lane) => {
+                lane_index(*lane, 2)?;
+                self.memarg(m, 3, &[I32, V128], &[V128])
+            }
+            V128Store8Lane(m, lane) => {
+                lane_index(*lane, 16)?;
+                self.memarg(m, 0, &[I32, V128], &[])
+            }
+            V128Store16Lane(m, lane) => {
+                lane_index(*lane, 8)?;
+                self.memarg(m, 1, &[I32, V128], &[])
+            }
+            V128Store32Lane(m, lane) => {
+                lane_index(*lane, 4)?;
+                self.memarg(m, 2, &[I32, V128], &[])
+            }
+            V128Store64Lane(m, lane) => {
+                lane_index(*lane, 2)?;
+                self.memarg(m, 3, &[I32, V128], &[])
+            }
+            V128Const(_) => self.op(&[], &[V128]),
+            I8x16Shuffle(lanes) => {
+                for lane in lanes {
+                    lane_index(*lane, 32)?;
+                }
+                self.op(&[V128, V128], &[V128])
+            }
+            I8x16Splat | I16x8Splat | I32x4Splat => self.op(&[I32], &[V128]),
+            I64x2Splat => self.op(&[I64], &[V128]),
+            F32x4Splat => self.op(&[F32], &[V128]),
+            F64x2Splat => self.op(&[F64], &[V128]),
+            I8x16ExtractLaneS(lane) | I8x16ExtractLaneU(lane) => {
+                lane_index(*lane, 16)?;
+                self.op(&[V128], &[I32])
+            }
+            I16x8ExtractLaneS(lane) | I16x8ExtractLaneU(lane) => {
+                lane_index(*lane, 8)?;
+                self.op(&[V128], &[I32])
+            }
+            I32x4ExtractLane(lane) => {
+                lane_index(*lane, 4)?;
+                self.op(&[V128], &[I32])
+            }
+            I64x2ExtractLane(lane) => {
+                lane_index(*lane, 2)?;
+                self.op(&[V128], &[I64])
+            }
+            F32x4ExtractLane(lane) => {
+                lane_index(*lane, 4)?;
+                self.op(&[V128], &[F32])
+            }
+            F64x2ExtractLane(lane) => {
+                lane_index(*lane, 2)?;
+                self.op(&[V128], &[F64])
+            }
+            I8x16ReplaceLane(lane) => {
+                lane_index(*lane, 16)?;
+                self.op(&[V128, I32], &[V128])
+            }
+            I16x8ReplaceLane(lane) => {
+                lane_index(*lane, 8)?;
+                self.op(&[V128, I32], &[V128])
+            }
+            I32x4ReplaceLane(lane) => {
+                lane_index(*lane, 4)?;
+                self.op(&[V128, I32], &[V128])
+            }
+            I64x2ReplaceLane(lane) => {
+                lane_index(*lane, 2)?;
+                self.op(&[V128, I64], &[V128])
+            }
+            F32x4ReplaceLane(lane) => {
+                lane_index(*lane, 4)?;
+                self.op(&[V128, F32], &[V128])
+            }
+            F64x2ReplaceLane(lane) => {
+                lane_index(*lane, 2)?;
+                self.op(&[V128, F64], &[V128])
+            }
+            V128AnyTrue | I8x16AllTrue | I16x8AllTrue | I32x4AllTrue | I64x2AllTrue
+            | I8x16Bitmask | I16x8Bitmask | I32x4Bitmask | I64x2Bitmask => self.op(&[V128], &[I32]),
+            I8x16Shl | I8x16ShrS | I8x16ShrU | I16x8Shl | I16x8ShrS | I16x8ShrU | I32x4Shl
+            | I32x4ShrS | I32x4ShrU | I64x2Shl | I64x2ShrS | I64x2ShrU => {
+                self.op(&[V128, I32], &[V128])
+            }
+            V128Bitselect => self.op(&[V128, V128, V128], &[V128]),
+            V128Not
+            | I8x16Abs
+            | I8x16Neg
+            | I8x16Popcnt
+            | I16x8Abs
+            | I16x8Neg
+            | I32x4Abs
+            | I32x4Neg
+            | I64x2Abs
+            | I64x2Neg
+            | F32x4Abs
+            | F32x4Neg
+            | F32x4Sqrt
+            | F32x4Ceil
+            | F32x4Floor
+            | F32x4Trunc
+            | F32x4Nearest
+            | F64x2Abs
+            | F64x2Neg
+            | F64x2Sqrt
+            | F64x2Ceil
+            | F64x2Floor
+            | F64x2Trunc
+            | F64x2Nearest
+            | I16x8ExtendLowI8x16S
+            | I16x8ExtendHighI8x16S
+            | I16x8ExtendLowI8x16U
+            | I16x8ExtendHighI8x16U
+            | I32x4ExtendLowI16x8S
+            | I32x4ExtendHighI16x8S
+            | I32x4ExtendLowI16x8U
+            | I32x4ExtendHighI16x8U
+            | I64x2ExtendLowI32x4S
+            | I64x2ExtendHighI32x4S
+            | I64x2ExtendLowI32x4U
+            | I64x2ExtendHighI32x4U
+            | I16x8ExtaddPairwiseI8x16S
+            | I16x8ExtaddPairwiseI8x16U
+            | I32x4ExtaddPairwiseI16x8S
+            | I32x4ExtaddPairwiseI16x8U
+            | I32x4TruncSatF32x4S
+            | I32x4TruncSatF32x4U
+            | I32x4TruncSatF64x2SZero
+            | I32x4TruncSatF64x2UZero
+            | F32x4ConvertI32x4S
+            | F32x4ConvertI32x4U
+            | F64x2ConvertLowI32x4S
+            | F64x2ConvertLowI32x4U
+            | F32x4DemoteF64x2Zero
+            | F64x2PromoteLowF32x4 => self.op(&[V128], &[V128]),
+            _ => self.op(&[V128, V128], &[V128]),
+        }
+    }
+
+    fn mem(&self, memidx: MemIdx) -> Result<&'a MemType, &'static str> {
+        self.context
+            .mems
+            .get(memidx as usize)
+            .copied()
+            .ok_or("unknown memory")
+    }
+
+    /// The type of addresses into a memory
+    fn addr_type(&self, memidx: MemIdx) -> Result<ValType, &'static str> {
+        Ok(ValType::NumType(self.mem(memidx)?.addr_type()))
+    }
+
+    /// The address type of the memory a memory argument names, whose offset must be in that memory's address space
+    fn address(&self, memarg: &MemArg) -> Result<ValType, &'static str> {
+        let mt = self.mem(memarg.memidx)?;
+        if !mt.memory64 && memarg.offset > u32::MAX as u64 {
+            return Err("offset out of range");
+        }
+        Ok(ValType::NumType(mt.addr_type()))
+    }
+
+    fn data(&self, dataidx: DataIdx) -> Result<(), &'static str> {
+        match self.context.datas {
+            None => Err("data count section required"),
+            Some(datas) if dataidx >= datas => Err("unknown data segment"),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Check an atomic instruction, whose alignment must be exactly its access width
+    fn atomic(&mut self, instruction: &AtomicInstruction) -> Result<(), &'static str> {
+        let Some((op, t, width, memarg)) = instruction.access() else {
+            return Ok(());
+        };
+        let at = self.address(memarg)?;
+        if 1 << memarg.align != width {
+            return Err("atomic alignment must be natural");
+        }
+        let t = ValType::NumType(t);
+        match op {
+            AtomicOp::Notify => self.op(&[at, I32], &[I32]),
+            AtomicOp::Wait => self.op(&[at, t, I64], &[I32]),
+            AtomicOp::Load => self.op(&[at], &[t]),
+            AtomicOp::Store => self.op(&[at, t], &[]),
+            AtomicOp::Rmw(_) => self.op(&[at, t.clone()], &[t]),
+            AtomicOp::Cmpxchg => self.op(&[at, t.clone(), t.clone()], &[t]),
+        }
+    }
+
+    /// Check that the memory exists and the alignment is at most `natural`, then apply the instruction's type
+    ///
+    /// The first parameter is the address, which takes the address type of the memory.
+    fn memarg(
+        &mut self,
+        memarg: &MemArg,
+        natural: u32,
+        params: &[ValType],
+        results: &[ValType],
+    ) -> Result<(), &'static str> {
+        let at = self.address(memarg)?;
+        if memarg.align > natural {
+            return Err("alignment must not be larger than natural");
+        }
+        self.op(&[&[at], &params[1..]].concat(), results)
+    }
+}
+
+/// The type a field is read as, where packed fields can only be read with an explicit sign extension
+fn unpacked(ft: &FieldType, plain: bool) -> Result<ValType, &'static str> {
+    match (&ft.st, plain) {
+        (StorageType::Val(t), true) => Ok(t.clone()),
+        (StorageType::Packed(_), false) => Ok(I32),
+        (StorageType::Val(_), false) => Err("field is unpacked"),
+        (StorageType::Packed(_), true) => Err("field is packed"),
+    }
+}
+
+/// Arrays can only be initialized from data segments if their elements are made of bytes
+fn numeric_or_vector(ft: &FieldType) -> Result<(), &'static str> {
+    match ft.st {
+        StorageType::Val(ValType::RefType(_)) => Err("array type is not numeric or vector"),
+        _ => Ok(()),
+    }
+}
+
+/// Check the limits of a memory type against the largest size of its address space
+fn memory_type(mt: &MemType) -> Result<(), &'static str> {
+    let (limit, error) = if mt.memory64 {
+        (1 << 48, "memory size must be at most 2^48 pages")
+    } else {
+        (1 << 16, "memory size must be at most 65536 pages (4GiB)")
+    };
+    if mt.lim.min > limit || mt.lim.max.is_some_and(|max| max > limit) {
+        return Err(error);
+    }
+    if mt.lim.max.is_some_and(|max| max < mt.lim.min) {
+        return Err("size minimum must not be greater than maximum");
+    }
+    if mt.shared && mt.lim.max.is_none() {
+        return Err("shared memory must have maximum");
+    }
+    Ok(())
+}
+
+fn lane_index(lane: LaneIdx, lanes: u8) -> Result<(), &'static str> {
+    if lane < lanes {
+        Ok(())
+    } else {
+        Err("invalid lane index")
+    }
+}
+
+/// The parameter and result types of a numeric instruction
+fn numeric(instruction: &NumericInstruction) -> (Vec<ValType>, Vec<ValType>) {
+    use NumericInstruction::*;
+
+    let (params, result): (&[ValType], ValType) = match instruction {
+        I32Const(_) => (&[], I32),
+        I64Const(_) => (&[], I64),
+        F32Const(_) => (&[], F32),
+        F64Const(_) => (&[], F64),
+        I32Eqz => (&[I32], I32),
+        I32Eq | I32Ne | I32LtS | I32NeU | I32GtS | I32GtU | I32LeS | I32LeU | I32GeS | I32GeU => {
+            (&[I32, I32], I32)
+        }
+        I64Eqz => (&[I64], I32),
+        I64Eq | I64Ne | I64LtS | I64NeU | I64GtS | I64GtU | I64LeS | I64LeU | I64GeS | I64GeU => {
+            (&[I64, I64], I32)
+        }
+        F32Eq | F32Ne | F32Lt | F32Gt | F32Le | F32Ge => (&[F32, F32], I32),
+        F64Eq | F64Ne | F64Lt | F64Gt | F64Le | F64Ge => (&[F64, F64], I32),
+        I32Clz | I32Ctz | I32Popcnt | I32Extend8S | I32Extend16S => (&[I32], I32),
+        I32Add | I32Sub | I32Mul | I32DivS | I32DivU | I32RemS | I32RemU | I32And | I32Or
+        | I32Xor | I32Shl | I32ShrS | I32ShrU | I32Rotl | I32Rotr => (&[I32, I32], I32),
+        I64Clz | I64Ctz | I64Popcnt | I64Extend8S | I64Extend16S | I64Extend32S => (&[I64], I64),
+        I64Add | I64Sub | I64Mul | I64DivS | I64DivU | I64RemS | I64RemU | I64And | I64Or
+        | I64Xor | I64Shl | I64ShrS | I64ShrU | I64Rotl | I64Rotr => (&[I64, I64], I64),
+        F32Abs | F32Neg | F32Ceil | F32Floor | F32Trunc | F32Nearest | F32Sqrt => (&[F32], F32),
+        F32Add | F32Sub | F32Mul | F32Div | F32Min | F32Max | F32CopySign => (&[F32, F32], F32),
+        F64Abs | F64Neg | F64Ceil | F64Floor | F64Trunc | F64Nearest | F64Sqrt => (&[F64], F64),
+        F64Add | F64Sub | F64Mul | F64Div | F64Min | F64Max | F64CopySign => (&[F64, F64], F64),
+        I32WrapI64 => (&[I64], I32),
+        I32TruncF32S | I32TruncF32U | I32ReinterpretF32 => (&[F32], I32),
+        I32TruncF64S | I32TruncF64U => (&[F64], I32),
+        I64ExtendI32S | I64ExtendI32U => (&[I32], I64),
+        I64TruncF32S | I64TruncF32U => (&[F32], I64),
+        I64TruncF64S | I64TruncF64U | I64ReinterpretF64 => (&[F64], I64),
+        F32ConvertI32S | F32ConvertI32U | F32ReinterpretI32 => (&[I32], F32),
+        F32ConvertI64S | F32ConvertI64u => (&[I64], F32),
+        F32DemoteF64 => (&[F64], F32),
+        F64ConvertI32S | F64ConvertI32U => (&[I32], F64),
+        F64ConvertI64S | F64ConvertI64u | F64ReinterpretI64 => (&[I64], F64),
+        F64PromoteF32 => (&[F32], F64),
+    };
+    (params.to_vec(), vec![result])
+}
+
+/// The parameter and result types of a saturating truncation
+fn saturating(instruction: &SaturatingTruncationInstruction) -> (Vec<ValType>, Vec<ValType>) {
+    use SaturatingTruncationInstruction::*;
+
+    let (param, result) = match instruction {
+        I32TruncSatF32S | I32TruncSatF32U => (F32, I32),
+        I32TruncSatF64S | I32TruncSatF64U => (F64, I32),
+        I64TruncSatF32S | I64TruncSatF32U => (F32, I64),
+        I64TruncSatF64S | I64TruncSatF64U => (F64, I64),
+    };
+    (vec![param], vec![result])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        instructions::Expression,
+        module::types::{
+            ArrayType, CompType, FieldType, Limits, Mutability, PackedType, RecType, StorageType,
+            StructType,
+        },
+        Decode,
+    };
+
+    use super::*;
+
+    /// Put each function type in a recursion group of its own, as the binary format does without `rec`
+    fn def_types(types: Vec<FuncType>) -> Vec<DefType> {
+        DefType::flatten(
+            types
+                .into_iter()
+                .map(|ty| RecType {
+                    types: vec![ty.into()],
+                })
+                .collect(),
+        )
+    }
+
+    fn validate(rt1: Vec<ValType>, rt2: Vec<ValType>, body: &[u8]) -> Result<(), &'static str> {
+        let types = vec![
+            FuncType {
+                rt1: vec![],
+                rt2: vec![I32, I64],
+            },
+            FuncType {
+                rt1: vec![I32],
+                rt2: vec![I32, I32],
+            },
+            FuncType {
+                rt1: vec![I32, I32],
+                rt2: vec![I32],
+            },
+        ];
+        let types = def_types(types);
+        let context = Context {
+            types: &types,
+            funcs: vec![],
+            tables: vec![],
+            mems: vec![],
+            globals: vec![],
+            tags: vec![],
+            datas: None,
+            elems: vec![],
+            refs: HashSet::new(),
+        };
+        let (_, body) = Expression::decode(body).unwrap();
+        validate_func(&context, &FuncType { rt1, rt2 }, vec![], &body.0)
+    }
+
+    #[test]
+    fn test_multi_value_blocks() {
+        assert_eq!(
+            validate(
+                vec!(),
+                vec!(),
+                &[0x02, 0x00, 0x41, 0x01, 0x42, 0x02, 0x0B, 0x1A, 0x1A, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(),
+                vec!(I32, I64),
+                &[0x02, 0x00, 0x41, 0x01, 0x42, 0x02, 0x0C, 0x00, 0x0B, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(),
+                vec!(I32, I32),
+                &[0x41, 0x03, 0x02, 0x01, 0x41, 0x04, 0x0B, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(),
+                vec!(I32, I64),
+                &[0x02, 0x00, 0x41, 0x01, 0x0B, 0x0B]
+            ),
+            Err("type mismatch")
+        );
+        assert_eq!(
+            validate(
+                vec!(),
+                vec!(I32, I32),
+                &[0x02, 0x01, 0x41, 0x04, 0x0B, 0x0B]
+            ),
+            Err("type mismatch")
+        );
+        assert_eq!(
+            validate(vec!(), vec!(), &[0x02, 0x07, 0x0B, 0x0B]),
+            Err("unknown type")
+        );
+    }
+
+    #[test]
+    fn test_multi_value_loops_and_ifs() {
+        // Branches to a loop provide its parameters rather than its results
+        assert_eq!(
+            validate(
+                vec!(I32),
+                vec!(I32),
+                &[0x20, 0x00, 0x20, 0x00, 0x03, 0x02, 0x0C, 0x00, 0x0B, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(I32),
+                vec!(I32),
+                &[0x20, 0x00, 0x20, 0x00, 0x03, 0x02, 0x6A, 0x0C, 0x00, 0x0B, 0x0B]
+            ),
+            Err("type mismatch")
+        );
+        assert_eq!(
+            validate(
+                vec!(I32),
+                vec!(I32, I32),
+                &[0x20, 0x00, 0x20, 0x00, 0x04, 0x01, 0x41, 0x01, 0x05, 0x41, 0x02, 0x0B, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(I32),
+                vec!(I32, I32),
+                &[0x20, 0x00, 0x20, 0x00, 0x04, 0x01, 0x41, 0x01, 0x0B, 0x0B]
+            ),
+            Err("type mismatch")
+        );
+    }
+
+    #[test]
+    fn test_unreachable_and_labels() {
+        assert_eq!(validate(vec!(), vec!(I32, I64), &[0x00, 0x0B]), Ok(()));
+        assert_eq!(
+            validate(vec!(), vec!(I32, I64), &[0x00, 0x42, 0x02, 0x0B]),
+            Ok(())
+        );
+        assert_eq!(
+            validate(vec!(), vec!(I32, I64), &[0x00, 0x41, 0x02, 0x0B]),
+            Err("type mismatch")
+        );
+        assert_eq!(
+            validate(vec!(), vec!(), &[0x0C, 0x01, 0x0B]),
+            Err("unknown label")
+        );
+    }
+
+    #[test]
+    fn test_exceptions() {
+        let types = vec![FuncType {
+            rt1: vec![I32],
+            rt2: vec![],
+        }];
+        let types = def_types(types);
+        let context = Context {
+            types: &types,
+            funcs: vec![],
+            tables: vec![],
+            mems: vec![],
+            globals: vec![],
+            tags: vec![types[0].func_type().unwrap()],
+            datas: None,
+            elems: vec![],
+            refs: HashSet::new(),
+        };
+        let validate = |rt2, body| {
+            let (_, body) = Expression::decode(body).unwrap();
+            validate_func(&context, &FuncType { rt1: vec![], rt2 }, vec![], &body.0)
+        };
+        // A handler's label is outside the try_table and must take the tag's payload
+        assert_eq!(
+            validate(
+                vec!(I32),
+                &[
+                    0x1F, 0x40, 0x01, 0x00, 0x00, 0x00, 0x41, 0x01, 0x08, 0x00, 0x0B, 0x41, 0x00,
+                    0x0B
+                ]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(I32),
+                &[0x1F, 0x40, 0x01, 0x00, 0x00, 0x01, 0x0B, 0x41, 0x00, 0x0B]
+            ),
+            Err("unknown label")
+        );
+        assert_eq!(
+            validate(vec!(), &[0x1F, 0x40, 0x01, 0x00, 0x00, 0x00, 0x0B, 0x0B]),
+            Err("type mismatch")
+        );
+        assert_eq!(
+            validate(
+                vec!(EXNREF),
+                &[0x1F, 0x40, 0x01, 0x03, 0x00, 0x0B, 0xD0, 0x69, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(vec!(), &[0x41, 0x01, 0x08, 0x01, 0x0B]),
+            Err("unknown tag")
+        );
+        assert_eq!(
+            validate(vec!(), &[0x42, 0x01, 0x08, 0x00, 0x0B]),
+            Err("type mismatch")
+        );
+        assert_eq!(validate(vec!(), &[0xD0, 0x69, 0x0A, 0x0B]), Ok(()));
+        assert_eq!(
+            validate(vec!(), &[0xD0, 0x70, 0x0A, 0x0B]),
+            Err("type mismatch")
+        );
+    }
+
+    #[test]
+    fn test_tail_calls() {
+        let types = vec![FuncType {
+            rt1: vec![I32],
+            rt2: vec![I64],
+        }];
+        let types = def_types(types);
+        let context = Context {
+            types: &types,
+            funcs: vec![0],
+            tables: vec![],
+            mems: vec![],
+            globals: vec![],
+            tags: vec![],
+            datas: None,
+            elems: vec![],
+            refs: HashSet::new(),
+        };
+        let validate = |rt2, body| {
+            let (_, body) = Expression::decode(body).unwrap();
+            validate_func(&context, &FuncType { rt1: vec![], rt2 }, vec![], &body.0)
+        };
+        // Anything can follow a tail call, since it never returns to the caller
+        assert_eq!(
+            validate(vec!(I64), &[0x41, 0x01, 0x12, 0x00, 0x1A, 0x0B]),
+            Ok(())
+        );
+        assert_eq!(
+            validate(vec!(I32), &[0x41, 0x01, 0x12, 0x00, 0x0B]),
+            Err("type mismatch")
+        );
+        assert_eq!(
+            validate(vec!(I64), &[0x42, 0x01, 0x12, 0x00, 0x0B]),
+            Err("type mismatch")
+        );
+        assert_eq!(
+            validate(vec!(I64), &[0x41, 0x01, 0x12, 0x01, 0x0B]),
+            Err("unknown function")
+        );
+        assert_eq!(
+            validate(vec!(I64), &[0x41, 0x01, 0x41, 0x00, 0x13, 0x00, 0x00, 0x0B]),
+            Err("unknown table")
+        );
+    }
+
+    #[test]
+    fn test_atomics() {
+        let mt = MemType {
+            lim: Limits {
+                min: 1,
+                max: Some(1),
+            },
+            shared: true,
+            memory64: false,
+        };
+        let context = Context {
+            types: &[],
+            funcs: vec![],
+            tables: vec![],
+            mems: vec![&mt],
+            globals: vec![],
+            tags: vec![],
+            datas: None,
+            elems: vec![],
+            refs: HashSet::new(),
+        };
+        let validate = |rt2, body| {
+            let (_, body) = Expression::decode(body).unwrap();
+            validate_func(&context, &FuncType { rt1: vec![], rt2 }, vec![], &body.0)
+        };
+        // i64.atomic.rmw32.cmpxchg_u with its natural alignment of 4 bytes
+        let cmpxchg = [
+            0x41, 0x00, 0x42, 0x01, 0x42, 0x02, 0xFE, 0x4E, 0x02, 0x00, 0x0B,
+        ];
+        assert_eq!(validate(vec!(I64), &cmpxchg), Ok(()));
+        assert_eq!(validate(vec!(I32), &cmpxchg), Err("type mismatch"));
+        // Atomic accesses can't be under-aligned like plain loads and stores
+        assert_eq!(
+            validate(
+                vec!(I64),
+                &[0x41, 0x00, 0x42, 0x01, 0x42, 0x02, 0xFE, 0x4E, 0x01, 0x00, 0x0B]
+            ),
+            Err("atomic alignment must be natural")
+        );
+        // A memory argument can name memory 1, but only if it exists
+        assert_eq!(
+            validate(vec!(I32), &[0x41, 0x00, 0xFE, 0x10, 0x42, 0x01, 0x00, 0x0B]),
+            Err("unknown memory")
+        );
+        // memory.atomic.wait64 and atomic.fence
+        assert_eq!(
+            validate(
+                vec!(I32),
+                &[
+                    0x41, 0x00, 0x42, 0x00, 0x42, 0x7F, 0xFE, 0x02, 0x03, 0x00, 0xFE, 0x03, 0x00,
+                    0x0B
+                ]
+            ),
+            Ok(())
+        );
+
+        let context = Context {
+            mems: vec![],
+            ..context
+        };
+        let (_, body) = Expression::decode(&cmpxchg).unwrap();
+        assert_eq!(
+            validate_func(
+                &context,
+                &FuncType {
+                    rt1: vec![],
+                    rt2: vec![I64]
+                },
+                vec![],
+                &body.0
+            ),
+            Err("unknown memory")
+        );
+    }
+
+    #[test]
+    fn test_memories() {
+        let mt = MemType {
+            lim: Limits { min: 1, max: None },
+            shared: false,
+            memory64: false,
+        };
+        let context = Context {
+            types: &[],
+            funcs: vec![],
+            tables: vec![],
+            mems: vec![&mt, &mt],
+            globals: vec![],
+            tags: vec![],
+            datas: Some(1),
+            elems: vec![],
+            refs: HashSet::new(),
+        };
+        let validate = |rt2, body| {
+            let (_, body) = Expression::decode(body).unwrap();
+            validate_func(&context, &FuncType { rt1: vec![], rt2 }, vec![], &body.0)
+        };
+        // i64.load16_s from memory 1, then memory.copy from memory 1 to memory 0
+        assert_eq!(
+            validate(vec!(I64), &[0x41, 0x00, 0x32, 0x41, 0x01, 0x00, 0x0B]),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(),
+                &[0x41, 0x00, 0x41, 0x00, 0x41, 0x00, 0xFC, 0x0A, 0x00, 0x01, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(vec!(I64), &[0x41, 0x00, 0x32, 0x42, 0x01, 0x00, 0x0B]),
+            Err("alignment must not be larger than natural")
+        );
+        assert_eq!(
+            validate(vec!(I32), &[0x3F, 0x02, 0x0B]),
+            Err("unknown memory")
+        );
+        assert_eq!(
+            validate(vec!(), &[0xFC, 0x09, 0x01, 0x0B]),
+            Err("unknown data segment")
+        );
+        assert_eq!(
+            validate(
+                vec!(),
+                &[0x42, 0x00, 0x43, 0x00, 0x00, 0x00, 0x00, 0x38, 0x02, 0x00, 0x0B]
+            ),
+            Err("type mismatch")
+        );
+
+        let context = Context {
+            datas: None,
+            elems: vec![],
+            ..context
+        };
+        let (_, body) = Expression::decode(&[0xFC, 0x09, 0x00, 0x0B]).unwrap();
+        assert_eq!(
+            validate_func(
+                &context,
+                &FuncType {
+                    rt1: vec![],
+                    rt2: vec![]
+                },
+                vec![],
+                &body.0
+            ),
+            Err("data count section required")
+        );
+    }
+
+    #[test]
+    fn test_memory64() {
+        let mem64 = MemType {
+            lim: Limits { min: 1, max: None },
+            shared: false,
+            memory64: true,
+        };
+        let mem32 = MemType {
+            memory64: false,
+            ..mem64.clone()
+        };
+        let context = Context {
+            types: &[],
+            funcs: vec![],
+            tables: vec![],
+            mems: vec![&mem64, &mem32],
+            globals: vec![],
+            tags: vec![],
+            datas: Some(1),
+            elems: vec![],
+            refs: HashSet::new(),
+        };
+        let validate = |rt2, body| {
+            let (_, body) = Expression::decode(body).unwrap();
+            validate_func(&context, &FuncType { rt1: vec![], rt2 }, vec![], &body.0)
+        };
+        // i32.load takes an i64 address in memory 0 and an i32 address in memory 1
+        assert_eq!(
+            validate(vec!(I32), &[0x42, 0x00, 0x28, 0x02, 0x00, 0x0B]),
+            Ok(())
+        );
+        assert_eq!(
+            validate(vec!(I32), &[0x41, 0x00, 0x28, 0x02, 0x00, 0x0B]),
+            Err("type mismatch")
+        );
+        assert_eq!(
+            validate(vec!(I32), &[0x41, 0x00, 0x28, 0x42, 0x01, 0x00, 0x0B]),
+            Ok(())
+        );
+        // Only 64-bit memories can have offsets beyond 4GiB
+        assert_eq!(
+            validate(
+                vec!(I32),
+                &[0x42, 0x00, 0x28, 0x02, 0x80, 0x80, 0x80, 0x80, 0x10, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(I32),
+                &[0x41, 0x00, 0x28, 0x42, 0x01, 0x80, 0x80, 0x80, 0x80, 0x10, 0x0B]
+            ),
+            Err("offset out of range")
+        );
+        // memory.size and memory.grow use the address type of their memory
+        assert_eq!(validate(vec!(I64), &[0x3F, 0x00, 0x0B]), Ok(()));
+        assert_eq!(validate(vec!(I32), &[0x41, 0x01, 0x40, 0x01, 0x0B]), Ok(()));
+        // memory.copy from memory 0 to memory 1 takes an i32 length, since memory 1 is 32-bit
+        assert_eq!(
+            validate(
+                vec!(),
+                &[0x41, 0x00, 0x42, 0x00, 0x41, 0x08, 0xFC, 0x0A, 0x01, 0x00, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(),
+                &[0x41, 0x00, 0x42, 0x00, 0x42, 0x08, 0xFC, 0x0A, 0x01, 0x00, 0x0B]
+            ),
+            Err("type mismatch")
+        );
+        // memory.fill and memory.init
+        assert_eq!(
+            validate(
+                vec!(),
+                &[0x42, 0x00, 0x41, 0x00, 0x42, 0x08, 0xFC, 0x0B, 0x00, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(),
+                &[0x42, 0x00, 0x41, 0x00, 0x41, 0x08, 0xFC, 0x08, 0x00, 0x00, 0x0B]
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            memory_type(&MemType {
+                lim: Limits {
+                    min: 1 << 16,
+                    max: Some(1 << 48)
+                },
+                ..mem64.clone()
+            }),
+            Ok(())
+        );
+        assert_eq!(
+            memory_type(&MemType {
+                lim: Limits {
+                    min: 0,
+                    max: Some((1 << 16) + 1)
+                },
+                ..mem32
+            }),
+            Err("memory size must be at most 65536 pages (4GiB)")
+        );
+        assert_eq!(
+            memory_type(&MemType {
+                lim: Limits {
+                    min: 2,
+                    max: Some(1)
+                },
+                ..mem64
+            }),
+            Err("size minimum must not be greater than maximum")
+        );
+    }
+
+    #[test]
+    fn test_typed_function_references() {
+        let types = vec![FuncType {
+            rt1: vec![I32],
+            rt2: vec![I32],
+        }];
+        let types = def_types(types);
+        let context = Context {
+            types: &types,
+            funcs: vec![0],
+            tables: vec![],
+            mems: vec![],
+            globals: vec![],
+            tags: vec![],
+            datas: None,
+            elems: vec![],
+            refs: HashSet::from([0]),
+        };
+        let t = |nullable| {
+            ValType::RefType(RefType {
+                nullable,
+                ht: HeapType::Type(0),
+            })
+        };
+        let validate = |rt2, locals, body| {
+            let (_, body) = Expression::decode(body).unwrap();
+            validate_func(&context, &FuncType { rt1: vec![], rt2 }, locals, &body.0)
+        };
+
+        // ref.func has the precise type of the function, which call_ref and return_call_ref accept
+        assert_eq!(
+            validate(
+                vec!(I32),
+                vec!(),
+                &[0x41, 0x01, 0xD2, 0x00, 0x14, 0x00, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(I32),
+                vec!(),
+                &[0x41, 0x01, 0xD0, 0x00, 0x15, 0x00, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(I32),
+                vec!(),
+                &[0x41, 0x01, 0xD0, 0x70, 0x14, 0x00, 0x0B]
+            ),
+            Err("type mismatch")
+        );
+        assert_eq!(
+            validate(vec!(t(false)), vec!(), &[0xD2, 0x00, 0x0B]),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(ValType::RefType(RefType::FUNCREF)),
+                vec!(),
+                &[0xD2, 0x00, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(vec!(t(false)), vec!(), &[0xD0, 0x00, 0x0B]),
+            Err("type mismatch")
+        );
+        assert_eq!(
+            validate(vec!(), vec!(), &[0xD0, 0x01, 0x1A, 0x0B]),
+            Err("unknown type")
+        );
+
+        // ref.as_non_null, br_on_null and br_on_non_null refine nullable references
+        assert_eq!(
+            validate(vec!(t(false)), vec!(), &[0xD0, 0x00, 0xD4, 0x0B]),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(t(false)),
+                vec!(),
+                &[0x02, 0x40, 0xD0, 0x00, 0xD5, 0x00, 0x0F, 0x0B, 0xD2, 0x00, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(t(false)),
+                vec!(),
+                &[0x02, 0x64, 0x00, 0xD0, 0x00, 0xD6, 0x00, 0x00, 0x0B, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(t(false)),
+                vec!(),
+                &[0x02, 0x40, 0xD0, 0x00, 0xD6, 0x00, 0x0B, 0xD2, 0x00, 0x0B]
+            ),
+            Err("type mismatch")
+        );
+        assert_eq!(
+            validate(vec!(), vec!(), &[0x41, 0x00, 0xD4, 0x1A, 0x0B]),
+            Err("type mismatch")
+        );
+
+        // Non-nullable locals must be set before they are read, and only stay set until the end of the block
+        assert_eq!(
+            validate(vec!(t(false)), vec!(t(false)), &[0x20, 0x00, 0x0B]),
+            Err("uninitialized local")
+        );
+        assert_eq!(
+            validate(
+                vec!(t(false)),
+                vec!(t(false)),
+                &[0xD2, 0x00, 0x21, 0x00, 0x20, 0x00, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(t(false)),
+                vec!(t(false)),
+                &[0x02, 0x40, 0xD2, 0x00, 0x21, 0x00, 0x0B, 0x20, 0x00, 0x0B]
+            ),
+            Err("uninitialized local")
+        );
+        assert_eq!(
+            validate(
+                vec!(t(false)),
+                vec!(t(false)),
+                &[0xD2, 0x00, 0x22, 0x00, 0x02, 0x40, 0x20, 0x00, 0x1A, 0x0B, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(vec!(t(true)), vec!(t(true)), &[0x20, 0x00, 0x0B]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_gc() {
+        let field = |st, m| FieldType { st, m };
+        let types = DefType::flatten(vec![
+            RecType {
+                types: vec![CompType::Struct(StructType {
+                    fields: vec![
+                        field(StorageType::Packed(PackedType::I8), Mutability::Const),
+                        field(StorageType::Val(I32), Mutability::Var),
+                    ],
+                })
+                .into()],
+            },
+            RecType {
+                types: vec![CompType::Array(ArrayType {
+                    ft: field(StorageType::Val(I32), Mutability::Const),
+                })
+                .into()],
+            },
+        ]);
+        let context = Context {
+            types: &types,
+            funcs: vec![],
+            tables: vec![],
+            mems: vec![],
+            globals: vec![],
+            tags: vec![],
+            datas: None,
+            elems: vec![],
+            refs: HashSet::new(),
+        };
+        let validate = |rt2, body| {
+            let (_, body) = Expression::decode(body).unwrap();
+            validate_func(&context, &FuncType { rt1: vec![], rt2 }, vec![], &body.0)
+        };
+
+        // Packed fields are read with an explicit sign extension
+        assert_eq!(
+            validate(
+                vec!(I32),
+                &[0x41, 0x01, 0x41, 0x02, 0xFB, 0x00, 0x00, 0xFB, 0x03, 0x00, 0x00, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(I32),
+                &[0x41, 0x01, 0x41, 0x02, 0xFB, 0x00, 0x00, 0xFB, 0x02, 0x00, 0x00, 0x0B]
+            ),
+            Err("field is packed")
+        );
+        assert_eq!(
+            validate(
+                vec!(),
+                &[0xD0, 0x00, 0x41, 0x01, 0xFB, 0x05, 0x00, 0x01, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(),
+                &[0xD0, 0x00, 0x41, 0x01, 0xFB, 0x05, 0x00, 0x00, 0x0B]
+            ),
+            Err("field is immutable")
+        );
+        assert_eq!(
+            validate(
+                vec!(),
+                &[0xD0, 0x01, 0x41, 0x00, 0x41, 0x01, 0xFB, 0x0E, 0x01, 0x0B]
+            ),
+            Err("array is immutable")
+        );
+        assert_eq!(
+            validate(vec!(I32), &[0xD0, 0x00, 0xFB, 0x0B, 0x00, 0x0B]),
+            Err("non-array type")
+        );
+
+        // Casts stay within the hierarchy of their operand
+        assert_eq!(
+            validate(vec!(I32), &[0xD0, 0x6E, 0xFB, 0x14, 0x6C, 0x0B]),
+            Ok(())
+        );
+        assert_eq!(
+            validate(
+                vec!(ValType::RefType(RefType::non_null(HeapType::Type(0)))),
+                &[0xD0, 0x6B, 0xFB, 0x16, 0x00, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(vec!(I32), &[0xD0, 0x70, 0xFB, 0x14, 0x6C, 0x0B]),
+            Err("type mismatch")
+        );
+    }
+
+    #[test]
+    fn test_tables() {
+        let funcref = TableType {
+            lim: Limits { min: 1, max: None },
+            et: RefType::FUNCREF,
+        };
+        let externref = TableType {
+            lim: Limits { min: 1, max: None },
+            et: RefType::EXTERNREF,
+        };
+        let context = Context {
+            types: &[],
+            funcs: vec![],
+            tables: vec![&funcref, &externref],
+            mems: vec![],
+            globals: vec![],
+            tags: vec![],
+            datas: None,
+            elems: vec![RefType::non_null(HeapType::Func)],
+            refs: HashSet::new(),
+        };
+        let validate = |rt2, body| {
+            let (_, body) = Expression::decode(body).unwrap();
+            validate_func(&context, &FuncType { rt1: vec![], rt2 }, vec![], &body.0)
+        };
+        // table.get from table 1, then table.grow of table 0 by a null funcref
+        assert_eq!(
+            validate(
+                vec!(ValType::RefType(RefType::EXTERNREF)),
+                &[0x41, 0x00, 0x25, 0x01, 0x0B]
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            validate(vec!(I32), &[0xD0, 0x70, 0x41, 0x01, 0xFC, 0x0F, 0x00, 0x0B]),
+            Ok(())
+        );
+        assert_eq!(
+            validate(vec!(), &[0x41, 0x00, 0xD0, 0x70, 0x26, 0x01, 0x0B]),
+            Err("type mismatch")
+        );
+        // The segment holds function references, which can't be stored in or copied to an externref table
+        assert_eq!(
+            validate(
+                vec!(),
+                &[0x41, 0x00, 0x41, 0x00, 0x41, 0x00, 0xFC, 0x0C, 0x00, 0x01, 0x0B]
+            ),
+            Err("type mismatch")
+        );
+        assert_eq!(
+            validate(
+                vec!(),
+                &[0x41, 0x00, 0x41, 0x00, 0x41, 0x00, 0xFC, 0x0E, 0x01, 0x00, 0x0B]
+            ),
+            Err("type mismatch")
+        );
+        assert_eq!(
+            validate(vec!(I32), &[0xFC, 0x10, 0x02, 0x0B]),
+            Err("unknown table")
+        );
+        assert_eq!(
+            validate(vec!(), &[0xFC, 0x0D, 0x01, 0x0B]),
+            Err("unknown elem segment")
+        );
+    }
+}
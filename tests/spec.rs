@@ -0,0 +1,401 @@
+//! Run the `.wast` scripts in tests/spec, which follow the format of the WebAssembly spec test suite
+//!
+//! Only the directives the scripts use are supported: modules, invocations and the return, trap, exception, invalid
+//! and malformed assertions, all against the most recently defined module.
+//!
+//! The `simd_*` scripts are the SIMD proposal's tests from the copy of the WebAssembly testsuite that wabt bundles,
+//! as published in the wabt-sys 0.8.0 crate, copied verbatim. That snapshot predates the standardized proposal, so its
+//! renamed instructions are rewritten to their standard names before parsing, and the directives the final spec
+//! contradicts are listed in `OUTDATED`. Scripts the proposal added later, such as those for the dot product and
+//! rounding instructions, aren't in the snapshot.
+
+use std::fs;
+
+use easy::{Extern, ExternRef, Module, ModuleInst, New, Store, Trap, Val};
+use wast::{
+    core::{AbstractHeapType, HeapType, NanPattern, V128Pattern, WastArgCore, WastRetCore},
+    parser::{self, ParseBuffer},
+    token::{F32, F64},
+    QuoteWat, Wast, WastArg, WastDirective, WastExecute, WastRet,
+};
+
+/// The SIMD instructions that were renamed before the proposal was standardized, by their old and standard names
+const RENAMED: &[(&str, &str)] = &[
+    ("v8x16.shuffle", "i8x16.shuffle"),
+    ("v8x16.swizzle", "i8x16.swizzle"),
+    ("v8x16.load_splat", "v128.load8_splat"),
+    ("v16x8.load_splat", "v128.load16_splat"),
+    ("v32x4.load_splat", "v128.load32_splat"),
+    ("v64x2.load_splat", "v128.load64_splat"),
+    ("i16x8.load8x8_s", "v128.load8x8_s"),
+    ("i16x8.load8x8_u", "v128.load8x8_u"),
+    ("i32x4.load16x4_s", "v128.load16x4_s"),
+    ("i32x4.load16x4_u", "v128.load16x4_u"),
+    ("i64x2.load32x2_s", "v128.load32x2_s"),
+    ("i64x2.load32x2_u", "v128.load32x2_u"),
+    ("i8x16.any_true", "v128.any_true"),
+    ("i16x8.any_true", "v128.any_true"),
+    ("i32x4.any_true", "v128.any_true"),
+    ("i8x16.add_saturate_s", "i8x16.add_sat_s"),
+    ("i8x16.add_saturate_u", "i8x16.add_sat_u"),
+    ("i8x16.sub_saturate_s", "i8x16.sub_sat_s"),
+    ("i8x16.sub_saturate_u", "i8x16.sub_sat_u"),
+    ("i16x8.add_saturate_s", "i16x8.add_sat_s"),
+    ("i16x8.add_saturate_u", "i16x8.add_sat_u"),
+    ("i16x8.sub_saturate_s", "i16x8.sub_sat_s"),
+    ("i16x8.sub_saturate_u", "i16x8.sub_sat_u"),
+    ("i16x8.widen_low_i8x16_s", "i16x8.extend_low_i8x16_s"),
+    ("i16x8.widen_low_i8x16_u", "i16x8.extend_low_i8x16_u"),
+    ("i16x8.widen_high_i8x16_s", "i16x8.extend_high_i8x16_s"),
+    ("i16x8.widen_high_i8x16_u", "i16x8.extend_high_i8x16_u"),
+    ("i32x4.widen_low_i16x8_s", "i32x4.extend_low_i16x8_s"),
+    ("i32x4.widen_low_i16x8_u", "i32x4.extend_low_i16x8_u"),
+    ("i32x4.widen_high_i16x8_s", "i32x4.extend_high_i16x8_s"),
+    ("i32x4.widen_high_i16x8_u", "i32x4.extend_high_i16x8_u"),
+];
+
+/// The directives that the standardized proposals contradict, by script, the lines they start on and the reason
+///
+/// The scripts are a snapshot from before SIMD was standardized, and are kept verbatim, so these directives are left
+/// out when the scripts run instead.
+const OUTDATED: &[(&str, &[usize], &str)] = &[
+    (
+        "simd_address",
+        &[137, 145],
+        "memory64 made offsets beyond 4GiB valid syntax, so validation rejects them instead",
+    ),
+    (
+        "simd_const",
+        &[1566, 1581, 1583, 1598, 1600, 1615, 1617, 1630, 1634, 1649, 1651, 1664],
+        "these binary modules encode v128.const with its opcode from before the instructions were renumbered",
+    ),
+    (
+        "simd_f64x2",
+        &[115],
+        "the sign of a NaN that f64x2.min produces from a NaN operand is nondeterministic",
+    ),
+    (
+        "simd_f64x2_arith",
+        &[5299],
+        "the sign of a NaN that f64x2.sub produces from a NaN operand is nondeterministic",
+    ),
+    (
+        "simd_i16x8_arith2",
+        &[339],
+        "i64x2.abs was added to the proposal after this snapshot",
+    ),
+    (
+        "simd_lane",
+        &[556, 560],
+        "i8x16.swizzle and i8x16.shuffle became the standard names",
+    ),
+    (
+        "simd_lane",
+        &[888, 892, 893, 895, 896, 897, 900],
+        "lane indices are unsigned, so a sign in front of one is malformed",
+    ),
+];
+
+/// The state of a script: the store every module is instantiated in and the module invocations refer to
+struct Runner {
+    store: Store,
+    instance: Option<ModuleInst>,
+}
+
+impl Runner {
+    /// Decode, validate and instantiate a module, which becomes the target of the directives that follow
+    fn module(&mut self, wat: &mut QuoteWat) -> Result<(), String> {
+        let module = decode(wat)?;
+        module.validate()?;
+        let instance = self
+            .store
+            .instantiate(&module, &[])
+            .map_err(|error| error.to_string())?;
+        self.instance = Some(instance);
+        Ok(())
+    }
+
+    /// Invoke an export of the current module, returning its results or the trap it hit
+    fn execute(&mut self, exec: WastExecute) -> Result<Vec<Val>, Trap> {
+        let WastExecute::Invoke(invoke) = exec else {
+            panic!("unsupported execution {:?}", exec);
+        };
+        let instance = self.instance.as_ref().expect("a module to invoke");
+        let Some(Extern::Func(addr)) = instance.export(invoke.name) else {
+            panic!("no function exported as {}", invoke.name);
+        };
+        let args: Vec<Val> = invoke.args.iter().map(arg).collect();
+        self.store.invoke(addr, &args)
+    }
+}
+
+/// Encode a module to the binary format and decode it again
+fn decode(wat: &mut QuoteWat) -> Result<Module, String> {
+    let bytes = wat.encode().map_err(|error| error.to_string())?;
+    Module::new(bytes.as_slice()).ok_or_else(|| "malformed module".to_string())
+}
+
+fn arg(arg: &WastArg) -> Val {
+    let WastArg::Core(arg) = arg else {
+        panic!("unsupported argument {:?}", arg);
+    };
+    match arg {
+        WastArgCore::I32(n) => Val::I32(*n),
+        WastArgCore::I64(n) => Val::I64(*n),
+        WastArgCore::F32(z) => Val::F32(f32::from_bits(z.bits)),
+        WastArgCore::F64(z) => Val::F64(f64::from_bits(z.bits)),
+        WastArgCore::V128(v) => Val::V128(u128::from_le_bytes(v.to_le_bytes())),
+        WastArgCore::RefNull(HeapType::Abstract {
+            ty: AbstractHeapType::Extern,
+            ..
+        }) => Val::ExternRef(None),
+        WastArgCore::RefNull(_) => Val::FuncRef(None),
+        WastArgCore::RefExtern(n) => Val::ExternRef(Some(ExternRef::new(*n))),
+        arg => panic!("unsupported argument {:?}", arg),
+    }
+}
+
+/// Whether a result matches the expected one, where NaN patterns accept any NaN of the right kind
+fn matches(ret: &WastRet, val: &Val) -> bool {
+    let WastRet::Core(ret) = ret else {
+        panic!("unsupported result {:?}", ret);
+    };
+    matches_core(ret, val)
+}
+
+fn matches_core(ret: &WastRetCore, val: &Val) -> bool {
+    match (ret, val) {
+        (WastRetCore::I32(expected), Val::I32(n)) => expected == n,
+        (WastRetCore::I64(expected), Val::I64(n)) => expected == n,
+        (WastRetCore::F32(expected), Val::F32(z)) => matches_f32(expected, z.to_bits()),
+        (WastRetCore::F64(expected), Val::F64(z)) => matches_f64(expected, z.to_bits()),
+        (WastRetCore::V128(expected), Val::V128(v)) => matches_v128(expected, *v),
+        (WastRetCore::RefNull(_), val) => val.is_null(),
+        (WastRetCore::RefExtern(None), Val::ExternRef(Some(_))) => true,
+        (WastRetCore::RefExtern(Some(expected)), Val::ExternRef(Some(reference))) => {
+            reference.data().downcast_ref::<u32>() == Some(expected)
+        }
+        (WastRetCore::RefFunc(_), Val::FuncRef(Some(_))) => true,
+        (WastRetCore::Either(rets), val) => rets.iter().any(|ret| matches_core(ret, val)),
+        _ => false,
+    }
+}
+
+fn matches_f32(expected: &NanPattern<F32>, bits: u32) -> bool {
+    match expected {
+        NanPattern::CanonicalNan => bits & 0x7FFF_FFFF == 0x7FC0_0000,
+        NanPattern::ArithmeticNan => f32::from_bits(bits).is_nan() && bits & 0x0040_0000 != 0,
+        NanPattern::Value(z) => z.bits == bits,
+    }
+}
+
+fn matches_f64(expected: &NanPattern<F64>, bits: u64) -> bool {
+    match expected {
+        NanPattern::CanonicalNan => bits & 0x7FFF_FFFF_FFFF_FFFF == 0x7FF8_0000_0000_0000,
+        NanPattern::ArithmeticNan => {
+            f64::from_bits(bits).is_nan() && bits & 0x0008_0000_0000_0000 != 0
+        }
+        NanPattern::Value(z) => z.bits == bits,
+    }
+}
+
+fn matches_v128(expected: &V128Pattern, v: u128) -> bool {
+    let bytes = v.to_le_bytes();
+    match expected {
+        V128Pattern::I8x16(lanes) => lanes.iter().zip(bytes).all(|(x, y)| *x as u8 == y),
+        V128Pattern::I16x8(lanes) => lanes
+            .iter()
+            .zip(bytes.chunks(2))
+            .all(|(x, y)| x.to_le_bytes() == y),
+        V128Pattern::I32x4(lanes) => lanes
+            .iter()
+            .zip(bytes.chunks(4))
+            .all(|(x, y)| x.to_le_bytes() == y),
+        V128Pattern::I64x2(lanes) => lanes
+            .iter()
+            .zip(bytes.chunks(8))
+            .all(|(x, y)| x.to_le_bytes() == y),
+        V128Pattern::F32x4(lanes) => lanes
+            .iter()
+            .zip(bytes.chunks(4))
+            .all(|(x, y)| matches_f32(x, u32::from_le_bytes(y.try_into().unwrap()))),
+        V128Pattern::F64x2(lanes) => lanes
+            .iter()
+            .zip(bytes.chunks(8))
+            .all(|(x, y)| matches_f64(x, u64::from_le_bytes(y.try_into().unwrap()))),
+    }
+}
+
+/// Rewrite every renamed SIMD instruction in a script to its standard name
+fn modernize(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut word = String::new();
+    for c in source.chars().chain(['\n']) {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+            word.push(c);
+            continue;
+        }
+        match RENAMED.iter().find(|(old, _)| *old == word) {
+            Some((_, new)) => out.push_str(new),
+            None => out.push_str(&word),
+        }
+        word.clear();
+        out.push(c);
+    }
+    out.pop();
+    out
+}
+
+/// Blank out the directive that starts on the given line, keeping the lines of the rest of the script where they are
+fn skip(source: &mut String, line: usize) {
+    let mut bytes = std::mem::take(source).into_bytes();
+    let start = bytes
+        .split(|b| *b == b'\n')
+        .take(line - 1)
+        .map(|line| line.len() + 1)
+        .sum::<usize>();
+    let start = start + bytes[start..].iter().position(|b| *b == b'(').unwrap();
+    let (mut depth, mut end, mut quoted, mut escaped) = (0, start, false, false);
+    for (i, b) in bytes.iter().enumerate().skip(start) {
+        match (quoted, *b) {
+            (true, _) if escaped => escaped = false,
+            (true, b'\\') => escaped = true,
+            (_, b'"') => quoted = !quoted,
+            (false, b'(') => depth += 1,
+            (false, b')') => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            end = i + 1;
+            break;
+        }
+    }
+    for b in &mut bytes[start..end] {
+        if *b != b'\n' {
+            *b = b' ';
+        }
+    }
+    *source = String::from_utf8(bytes).unwrap();
+}
+
+/// Run every directive of tests/spec/<name>.wast, panicking with its line number on the first one that fails
+fn run(name: &str) {
+    let path = format!("{}/tests/spec/{}.wast", env!("CARGO_MANIFEST_DIR"), name);
+    let mut source = modernize(&fs::read_to_string(&path).unwrap());
+    for (_, lines, _) in OUTDATED.iter().filter(|(script, ..)| *script == name) {
+        for line in *lines {
+            skip(&mut source, *line);
+        }
+    }
+    let buffer = ParseBuffer::new(&source).unwrap();
+    let wast = parser::parse::<Wast>(&buffer).unwrap();
+    let mut runner = Runner {
+        store: Store::new(),
+        instance: None,
+    };
+    for directive in wast.directives {
+        let (line, _) = directive.span().linecol_in(&source);
+        let at = format!("{}.wast:{}", name, line + 1);
+        match directive {
+            WastDirective::Module(mut wat) => {
+                if let Err(error) = runner.module(&mut wat) {
+                    panic!("{}: module failed: {}", at, error);
+                }
+            }
+            WastDirective::Invoke(invoke) => {
+                if let Err(trap) = runner.execute(WastExecute::Invoke(invoke)) {
+                    panic!("{}: unexpected trap: {}", at, trap);
+                }
+            }
+            WastDirective::AssertReturn { exec, results, .. } => {
+                let actual = match runner.execute(exec) {
+                    Ok(actual) => actual,
+                    Err(trap) => panic!("{}: unexpected trap: {}", at, trap),
+                };
+                let ok = actual.len() == results.len()
+                    && results
+                        .iter()
+                        .zip(&actual)
+                        .all(|(ret, val)| matches(ret, val));
+                assert!(ok, "{}: expected {:?}, got {:?}", at, results, actual);
+            }
+            WastDirective::AssertTrap { exec, message, .. } => match runner.execute(exec) {
+                Ok(actual) => panic!("{}: expected trap {}, got {:?}", at, message, actual),
+                Err(trap) => assert!(
+                    message.starts_with(&trap.to_string()),
+                    "{}: expected trap {}, got {}",
+                    at,
+                    message,
+                    trap
+                ),
+            },
+            WastDirective::AssertException { exec, .. } => match runner.execute(exec) {
+                Err(Trap::Exception(_)) => {}
+                Ok(actual) => panic!("{}: expected exception, got {:?}", at, actual),
+                Err(trap) => panic!("{}: expected exception, got trap {}", at, trap),
+            },
+            WastDirective::AssertInvalid { mut module, .. } => {
+                let module =
+                    decode(&mut module).unwrap_or_else(|error| panic!("{}: {}", at, error));
+                assert!(
+                    module.validate().is_err(),
+                    "{}: expected invalid module",
+                    at
+                );
+            }
+            WastDirective::AssertMalformed { mut module, .. } => {
+                assert!(
+                    decode(&mut module).is_err(),
+                    "{}: expected malformed module",
+                    at
+                );
+            }
+            directive => panic!("{}: unsupported directive {:?}", at, directive),
+        }
+    }
+}
+
+macro_rules! spec {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[test]
+            fn $name() {
+                run(stringify!($name));
+            }
+        )*
+    };
+}
+
+spec!(
+    simd_address,
+    simd_align,
+    simd_bit_shift,
+    simd_bitwise,
+    simd_boolean,
+    simd_const,
+    simd_conversions,
+    simd_f32x4,
+    simd_f32x4_arith,
+    simd_f32x4_cmp,
+    simd_f64x2,
+    simd_f64x2_arith,
+    simd_f64x2_cmp,
+    simd_i16x8_arith,
+    simd_i16x8_arith2,
+    simd_i16x8_cmp,
+    simd_i16x8_sat_arith,
+    simd_i32x4_arith,
+    simd_i32x4_arith2,
+    simd_i32x4_cmp,
+    simd_i64x2_arith,
+    simd_i8x16_arith,
+    simd_i8x16_arith2,
+    simd_i8x16_cmp,
+    simd_i8x16_sat_arith,
+    simd_lane,
+    simd_load,
+    simd_load_extend,
+    simd_load_splat,
+    simd_splat,
+    simd_store,
+);
@@ -0,0 +1,116 @@
+# Generates the variants, decode and encode match arms of AtomicInstruction from the table of 0xFE-prefixed opcodes
+
+# (opcode, instruction, immediates)
+instructions = [
+    (0x00, 'memory.atomic.notify', 'memarg'),
+    (0x01, 'memory.atomic.wait32', 'memarg'),
+    (0x02, 'memory.atomic.wait64', 'memarg'),
+    (0x03, 'atomic.fence', 'reserved'),
+    (0x10, 'i32.atomic.load', 'memarg'),
+    (0x11, 'i64.atomic.load', 'memarg'),
+    (0x12, 'i32.atomic.load8_u', 'memarg'),
+    (0x13, 'i32.atomic.load16_u', 'memarg'),
+    (0x14, 'i64.atomic.load8_u', 'memarg'),
+    (0x15, 'i64.atomic.load16_u', 'memarg'),
+    (0x16, 'i64.atomic.load32_u', 'memarg'),
+    (0x17, 'i32.atomic.store', 'memarg'),
+    (0x18, 'i64.atomic.store', 'memarg'),
+    (0x19, 'i32.atomic.store8', 'memarg'),
+    (0x1A, 'i32.atomic.store16', 'memarg'),
+    (0x1B, 'i64.atomic.store8', 'memarg'),
+    (0x1C, 'i64.atomic.store16', 'memarg'),
+    (0x1D, 'i64.atomic.store32', 'memarg'),
+]
+
+# Each read-modify-write operation has seven opcodes in a row, one for each combination of type and width
+for i, op in enumerate(['add', 'sub', 'and', 'or', 'xor', 'xchg', 'cmpxchg']):
+    for j, prefix in enumerate(['i32.atomic.rmw', 'i64.atomic.rmw', 'i32.atomic.rmw8', 'i32.atomic.rmw16',
+                                'i64.atomic.rmw8', 'i64.atomic.rmw16', 'i64.atomic.rmw32']):
+        suffix = '' if prefix.endswith('rmw') else '_u'
+        instructions.append((0x1E + i * 7 + j, f'{prefix}.{op}{suffix}', 'memarg'))
+
+immediate_types = {
+    'memarg': 'MemArg',
+}
+
+
+def variant(name):
+    return ''.join(part[0].upper() + part[1:] for part in name.replace('.', '_').split('_'))
+
+
+def variants():
+    lines = []
+    for _, name, immediates in instructions:
+        types = [immediate_types[i] for i in immediates.split() if i in immediate_types]
+        fields = f"({', '.join(types)})" if types else ''
+        lines.append(f"    {variant(name)}{fields},")
+    return '\n'.join(lines)
+
+
+def arms():
+    lines = []
+    for opcode, name, immediates in instructions:
+        if immediates == 'reserved':
+            lines.append(f"            0x{opcode:02X} => map(tag([0x00]), |_| Self::{variant(name)})(input),")
+        else:
+            lines.append(f"            0x{opcode:02X} => map(MemArg::decode, Self::{variant(name)})(input),")
+    return '\n'.join(lines)
+
+
+def access(name):
+    t = 'I64' if name.startswith('i64') or name.endswith('64') else 'I32'
+    if name.startswith('memory.atomic.notify'):
+        return 'AtomicOp::Notify', t, 4
+    if name.startswith('memory.atomic.wait'):
+        return 'AtomicOp::Wait', t, int(name[-2:]) // 8
+    tail = name.split('.')[-1]
+    if tail.startswith(('load', 'store')):
+        bits = ''.join(c for c in tail if c.isdigit())
+        width = int(bits) // 8 if bits else int(t[1:]) // 8
+        return ('AtomicOp::Load' if tail.startswith('load') else 'AtomicOp::Store'), t, width
+    rmw = name.split('.')[2]
+    bits = rmw[len('rmw'):]
+    width = int(bits) // 8 if bits else int(t[1:]) // 8
+    op = tail.removesuffix('_u')
+    if op == 'cmpxchg':
+        return 'AtomicOp::Cmpxchg', t, width
+    return f'AtomicOp::Rmw(RmwOp::{op.capitalize()})', t, width
+
+
+def accesses():
+    lines = []
+    for _, name, immediates in instructions:
+        if immediates == 'reserved':
+            lines.append(f"            Self::{variant(name)} => return None,")
+        else:
+            op, t, width = access(name)
+            lines.append(f"            Self::{variant(name)}(memarg) => ({op}, NumType::{t}, {width}, memarg),")
+    return '\n'.join(lines)
+
+
+def names():
+    lines = []
+    for _, name, immediates in instructions:
+        fields = '(..)' if immediates != 'reserved' else ''
+        lines.append(f"            Self::{variant(name)}{fields} => \"{name}\",")
+    return '\n'.join(lines)
+
+
+def opcodes():
+    lines = []
+    for opcode, name, immediates in instructions:
+        fields = '(..)' if immediates != 'reserved' else ''
+        lines.append(f"            Self::{variant(name)}{fields} => 0x{opcode:02X},")
+    return '\n'.join(lines)
+
+
+if __name__ == '__main__':
+    print(variants())
+    print()
+    print(arms())
+    print()
+    print(accesses())
+    print()
+    print(names())
+    print()
+    print(opcodes())
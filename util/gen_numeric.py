@@ -6,6 +6,11 @@ def single(i, t):
     return f"map(tag([0x{hex(base + i)[2:].upper()}]), |_| Self::{t})"
 
 
+def opcodes():
+    base = 69
+    return '\n'.join(f"            Self::{t} => 0x{base + i:02X}," for i, t in enumerate(types))
+
+
 types = [
     'I32Eqz',
     'I32Eq',
@@ -138,4 +143,6 @@ types = [
 ]
 
 if __name__ == '__main__':
-    print(main())
\ No newline at end of file
+    print(main())
+    print()
+    print(opcodes())
\ No newline at end of file
@@ -0,0 +1,325 @@
+# Generates the variants, decode and encode match arms of VectorInstruction from the table of 0xFD-prefixed opcodes
+
+# (opcode, instruction, immediates)
+instructions = [
+    (0, 'v128.load', 'memarg'),
+    (1, 'v128.load8x8_s', 'memarg'),
+    (2, 'v128.load8x8_u', 'memarg'),
+    (3, 'v128.load16x4_s', 'memarg'),
+    (4, 'v128.load16x4_u', 'memarg'),
+    (5, 'v128.load32x2_s', 'memarg'),
+    (6, 'v128.load32x2_u', 'memarg'),
+    (7, 'v128.load8_splat', 'memarg'),
+    (8, 'v128.load16_splat', 'memarg'),
+    (9, 'v128.load32_splat', 'memarg'),
+    (10, 'v128.load64_splat', 'memarg'),
+    (11, 'v128.store', 'memarg'),
+    (12, 'v128.const', 'i128'),
+    (13, 'i8x16.shuffle', 'lanes'),
+    (14, 'i8x16.swizzle', ''),
+    (15, 'i8x16.splat', ''),
+    (16, 'i16x8.splat', ''),
+    (17, 'i32x4.splat', ''),
+    (18, 'i64x2.splat', ''),
+    (19, 'f32x4.splat', ''),
+    (20, 'f64x2.splat', ''),
+    (21, 'i8x16.extract_lane_s', 'lane'),
+    (22, 'i8x16.extract_lane_u', 'lane'),
+    (23, 'i8x16.replace_lane', 'lane'),
+    (24, 'i16x8.extract_lane_s', 'lane'),
+    (25, 'i16x8.extract_lane_u', 'lane'),
+    (26, 'i16x8.replace_lane', 'lane'),
+    (27, 'i32x4.extract_lane', 'lane'),
+    (28, 'i32x4.replace_lane', 'lane'),
+    (29, 'i64x2.extract_lane', 'lane'),
+    (30, 'i64x2.replace_lane', 'lane'),
+    (31, 'f32x4.extract_lane', 'lane'),
+    (32, 'f32x4.replace_lane', 'lane'),
+    (33, 'f64x2.extract_lane', 'lane'),
+    (34, 'f64x2.replace_lane', 'lane'),
+    (35, 'i8x16.eq', ''),
+    (36, 'i8x16.ne', ''),
+    (37, 'i8x16.lt_s', ''),
+    (38, 'i8x16.lt_u', ''),
+    (39, 'i8x16.gt_s', ''),
+    (40, 'i8x16.gt_u', ''),
+    (41, 'i8x16.le_s', ''),
+    (42, 'i8x16.le_u', ''),
+    (43, 'i8x16.ge_s', ''),
+    (44, 'i8x16.ge_u', ''),
+    (45, 'i16x8.eq', ''),
+    (46, 'i16x8.ne', ''),
+    (47, 'i16x8.lt_s', ''),
+    (48, 'i16x8.lt_u', ''),
+    (49, 'i16x8.gt_s', ''),
+    (50, 'i16x8.gt_u', ''),
+    (51, 'i16x8.le_s', ''),
+    (52, 'i16x8.le_u', ''),
+    (53, 'i16x8.ge_s', ''),
+    (54, 'i16x8.ge_u', ''),
+    (55, 'i32x4.eq', ''),
+    (56, 'i32x4.ne', ''),
+    (57, 'i32x4.lt_s', ''),
+    (58, 'i32x4.lt_u', ''),
+    (59, 'i32x4.gt_s', ''),
+    (60, 'i32x4.gt_u', ''),
+    (61, 'i32x4.le_s', ''),
+    (62, 'i32x4.le_u', ''),
+    (63, 'i32x4.ge_s', ''),
+    (64, 'i32x4.ge_u', ''),
+    (65, 'f32x4.eq', ''),
+    (66, 'f32x4.ne', ''),
+    (67, 'f32x4.lt', ''),
+    (68, 'f32x4.gt', ''),
+    (69, 'f32x4.le', ''),
+    (70, 'f32x4.ge', ''),
+    (71, 'f64x2.eq', ''),
+    (72, 'f64x2.ne', ''),
+    (73, 'f64x2.lt', ''),
+    (74, 'f64x2.gt', ''),
+    (75, 'f64x2.le', ''),
+    (76, 'f64x2.ge', ''),
+    (77, 'v128.not', ''),
+    (78, 'v128.and', ''),
+    (79, 'v128.andnot', ''),
+    (80, 'v128.or', ''),
+    (81, 'v128.xor', ''),
+    (82, 'v128.bitselect', ''),
+    (83, 'v128.any_true', ''),
+    (84, 'v128.load8_lane', 'memarg lane'),
+    (85, 'v128.load16_lane', 'memarg lane'),
+    (86, 'v128.load32_lane', 'memarg lane'),
+    (87, 'v128.load64_lane', 'memarg lane'),
+    (88, 'v128.store8_lane', 'memarg lane'),
+    (89, 'v128.store16_lane', 'memarg lane'),
+    (90, 'v128.store32_lane', 'memarg lane'),
+    (91, 'v128.store64_lane', 'memarg lane'),
+    (92, 'v128.load32_zero', 'memarg'),
+    (93, 'v128.load64_zero', 'memarg'),
+    (94, 'f32x4.demote_f64x2_zero', ''),
+    (95, 'f64x2.promote_low_f32x4', ''),
+    (96, 'i8x16.abs', ''),
+    (97, 'i8x16.neg', ''),
+    (98, 'i8x16.popcnt', ''),
+    (99, 'i8x16.all_true', ''),
+    (100, 'i8x16.bitmask', ''),
+    (101, 'i8x16.narrow_i16x8_s', ''),
+    (102, 'i8x16.narrow_i16x8_u', ''),
+    (103, 'f32x4.ceil', ''),
+    (104, 'f32x4.floor', ''),
+    (105, 'f32x4.trunc', ''),
+    (106, 'f32x4.nearest', ''),
+    (107, 'i8x16.shl', ''),
+    (108, 'i8x16.shr_s', ''),
+    (109, 'i8x16.shr_u', ''),
+    (110, 'i8x16.add', ''),
+    (111, 'i8x16.add_sat_s', ''),
+    (112, 'i8x16.add_sat_u', ''),
+    (113, 'i8x16.sub', ''),
+    (114, 'i8x16.sub_sat_s', ''),
+    (115, 'i8x16.sub_sat_u', ''),
+    (116, 'f64x2.ceil', ''),
+    (117, 'f64x2.floor', ''),
+    (118, 'i8x16.min_s', ''),
+    (119, 'i8x16.min_u', ''),
+    (120, 'i8x16.max_s', ''),
+    (121, 'i8x16.max_u', ''),
+    (122, 'f64x2.trunc', ''),
+    (123, 'i8x16.avgr_u', ''),
+    (124, 'i16x8.extadd_pairwise_i8x16_s', ''),
+    (125, 'i16x8.extadd_pairwise_i8x16_u', ''),
+    (126, 'i32x4.extadd_pairwise_i16x8_s', ''),
+    (127, 'i32x4.extadd_pairwise_i16x8_u', ''),
+    (128, 'i16x8.abs', ''),
+    (129, 'i16x8.neg', ''),
+    (130, 'i16x8.q15mulr_sat_s', ''),
+    (131, 'i16x8.all_true', ''),
+    (132, 'i16x8.bitmask', ''),
+    (133, 'i16x8.narrow_i32x4_s', ''),
+    (134, 'i16x8.narrow_i32x4_u', ''),
+    (135, 'i16x8.extend_low_i8x16_s', ''),
+    (136, 'i16x8.extend_high_i8x16_s', ''),
+    (137, 'i16x8.extend_low_i8x16_u', ''),
+    (138, 'i16x8.extend_high_i8x16_u', ''),
+    (139, 'i16x8.shl', ''),
+    (140, 'i16x8.shr_s', ''),
+    (141, 'i16x8.shr_u', ''),
+    (142, 'i16x8.add', ''),
+    (143, 'i16x8.add_sat_s', ''),
+    (144, 'i16x8.add_sat_u', ''),
+    (145, 'i16x8.sub', ''),
+    (146, 'i16x8.sub_sat_s', ''),
+    (147, 'i16x8.sub_sat_u', ''),
+    (148, 'f64x2.nearest', ''),
+    (149, 'i16x8.mul', ''),
+    (150, 'i16x8.min_s', ''),
+    (151, 'i16x8.min_u', ''),
+    (152, 'i16x8.max_s', ''),
+    (153, 'i16x8.max_u', ''),
+    (155, 'i16x8.avgr_u', ''),
+    (156, 'i16x8.extmul_low_i8x16_s', ''),
+    (157, 'i16x8.extmul_high_i8x16_s', ''),
+    (158, 'i16x8.extmul_low_i8x16_u', ''),
+    (159, 'i16x8.extmul_high_i8x16_u', ''),
+    (160, 'i32x4.abs', ''),
+    (161, 'i32x4.neg', ''),
+    (163, 'i32x4.all_true', ''),
+    (164, 'i32x4.bitmask', ''),
+    (167, 'i32x4.extend_low_i16x8_s', ''),
+    (168, 'i32x4.extend_high_i16x8_s', ''),
+    (169, 'i32x4.extend_low_i16x8_u', ''),
+    (170, 'i32x4.extend_high_i16x8_u', ''),
+    (171, 'i32x4.shl', ''),
+    (172, 'i32x4.shr_s', ''),
+    (173, 'i32x4.shr_u', ''),
+    (174, 'i32x4.add', ''),
+    (177, 'i32x4.sub', ''),
+    (181, 'i32x4.mul', ''),
+    (182, 'i32x4.min_s', ''),
+    (183, 'i32x4.min_u', ''),
+    (184, 'i32x4.max_s', ''),
+    (185, 'i32x4.max_u', ''),
+    (186, 'i32x4.dot_i16x8_s', ''),
+    (188, 'i32x4.extmul_low_i16x8_s', ''),
+    (189, 'i32x4.extmul_high_i16x8_s', ''),
+    (190, 'i32x4.extmul_low_i16x8_u', ''),
+    (191, 'i32x4.extmul_high_i16x8_u', ''),
+    (192, 'i64x2.abs', ''),
+    (193, 'i64x2.neg', ''),
+    (195, 'i64x2.all_true', ''),
+    (196, 'i64x2.bitmask', ''),
+    (199, 'i64x2.extend_low_i32x4_s', ''),
+    (200, 'i64x2.extend_high_i32x4_s', ''),
+    (201, 'i64x2.extend_low_i32x4_u', ''),
+    (202, 'i64x2.extend_high_i32x4_u', ''),
+    (203, 'i64x2.shl', ''),
+    (204, 'i64x2.shr_s', ''),
+    (205, 'i64x2.shr_u', ''),
+    (206, 'i64x2.add', ''),
+    (209, 'i64x2.sub', ''),
+    (213, 'i64x2.mul', ''),
+    (214, 'i64x2.eq', ''),
+    (215, 'i64x2.ne', ''),
+    (216, 'i64x2.lt_s', ''),
+    (217, 'i64x2.gt_s', ''),
+    (218, 'i64x2.le_s', ''),
+    (219, 'i64x2.ge_s', ''),
+    (220, 'i64x2.extmul_low_i32x4_s', ''),
+    (221, 'i64x2.extmul_high_i32x4_s', ''),
+    (222, 'i64x2.extmul_low_i32x4_u', ''),
+    (223, 'i64x2.extmul_high_i32x4_u', ''),
+    (224, 'f32x4.abs', ''),
+    (225, 'f32x4.neg', ''),
+    (227, 'f32x4.sqrt', ''),
+    (228, 'f32x4.add', ''),
+    (229, 'f32x4.sub', ''),
+    (230, 'f32x4.mul', ''),
+    (231, 'f32x4.div', ''),
+    (232, 'f32x4.min', ''),
+    (233, 'f32x4.max', ''),
+    (234, 'f32x4.pmin', ''),
+    (235, 'f32x4.pmax', ''),
+    (236, 'f64x2.abs', ''),
+    (237, 'f64x2.neg', ''),
+    (239, 'f64x2.sqrt', ''),
+    (240, 'f64x2.add', ''),
+    (241, 'f64x2.sub', ''),
+    (242, 'f64x2.mul', ''),
+    (243, 'f64x2.div', ''),
+    (244, 'f64x2.min', ''),
+    (245, 'f64x2.max', ''),
+    (246, 'f64x2.pmin', ''),
+    (247, 'f64x2.pmax', ''),
+    (248, 'i32x4.trunc_sat_f32x4_s', ''),
+    (249, 'i32x4.trunc_sat_f32x4_u', ''),
+    (250, 'f32x4.convert_i32x4_s', ''),
+    (251, 'f32x4.convert_i32x4_u', ''),
+    (252, 'i32x4.trunc_sat_f64x2_s_zero', ''),
+    (253, 'i32x4.trunc_sat_f64x2_u_zero', ''),
+    (254, 'f64x2.convert_low_i32x4_s', ''),
+    (255, 'f64x2.convert_low_i32x4_u', ''),
+]
+
+immediate_types = {
+    'memarg': 'MemArg',
+    'lane': 'LaneIdx',
+    'lanes': '[LaneIdx; 16]',
+    'i128': 'u128',
+}
+
+immediate_decoders = {
+    'memarg': 'MemArg::decode',
+    'lane': 'LaneIdx::decode',
+    'lanes': 'lanes',
+    'i128': 'u128::decode',
+}
+
+
+def variant(name):
+    return ''.join(part[0].upper() + part[1:] for part in name.replace('.', '_').split('_'))
+
+
+def variants():
+    lines = []
+    for _, name, immediates in instructions:
+        types = [immediate_types[i] for i in immediates.split()]
+        fields = f"({', '.join(types)})" if types else ''
+        lines.append(f"    {variant(name)}{fields},")
+    return '\n'.join(lines)
+
+
+def arms():
+    lines = []
+    for opcode, name, immediates in instructions:
+        decoders = [immediate_decoders[i] for i in immediates.split()]
+        if not decoders:
+            lines.append(f"            {opcode} => Ok((input, Self::{variant(name)})),")
+        elif len(decoders) == 1:
+            lines.append(f"            {opcode} => map({decoders[0]}, Self::{variant(name)})(input),")
+        else:
+            lines.append(
+                f"            {opcode} => map(pair({', '.join(decoders)}), |pair| {{ Self::{variant(name)}(pair.0, pair.1) }})(input),"
+            )
+    return '\n'.join(lines)
+
+
+def names():
+    lines = []
+    for _, name, immediates in instructions:
+        fields = '(..)' if immediates else ''
+        lines.append(f"            Self::{variant(name)}{fields} => \"{name}\",")
+    return '\n'.join(lines)
+
+
+def opcodes():
+    lines = []
+    for opcode, name, immediates in instructions:
+        fields = '(..)' if immediates else ''
+        lines.append(f"            Self::{variant(name)}{fields} => {opcode},")
+    return '\n'.join(lines)
+
+
+def immediates():
+    """The encode match arms that write the memarg and lane immediates, one arm for each kind"""
+    arms = {
+        'memarg': ('Self::{}(memarg)', 'memarg.encode(out),'),
+        'lane': ('Self::{}(lane)', 'lane.encode(out),'),
+        'memarg lane': ('Self::{}(memarg, lane)', '{\n                memarg.encode(out);\n                lane.encode(out);\n            }'),
+    }
+    lines = []
+    for kind, (binding, body) in arms.items():
+        patterns = [binding.format(variant(name)) for _, name, i in instructions if i == kind]
+        lines.append('            ' + '\n            | '.join(patterns) + ' => ' + body)
+    return '\n'.join(lines)
+
+
+if __name__ == '__main__':
+    print(variants())
+    print()
+    print(arms())
+    print()
+    print(names())
+    print()
+    print(opcodes())
+    print()
+    print(immediates())